@@ -437,6 +437,10 @@ impl Chain {
             chain_genesis.height,
             chain_config.save_trie_changes,
         );
+        chain_store.index_account_activity = chain_config.save_account_activity;
+        if !chain_config.save_account_activity {
+            chain_store.clear_account_activity()?;
+        }
         let mut store_update = chain_store.store_update();
         let (block_head, header_head) = match store_update.head() {
             Ok(block_head) => {
@@ -2174,6 +2178,13 @@ impl Chain {
         block: &Block,
         shard_id: ShardId,
     ) -> Result<(), Error> {
+        // The flat storage heads and memtrie roots of the blocks sandbox may be reverted to have
+        // to stay available.
+        #[cfg(feature = "sandbox")]
+        if self.chain_store.sandbox_pinned_height()?.is_some() {
+            return Ok(());
+        }
+
         let epoch_id = block.header().epoch_id();
         let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, epoch_id)?;
 
@@ -4479,6 +4490,35 @@ impl Chain {
     pub fn patch_state_in_progress(&self) -> bool {
         !self.pending_state_patch.is_empty()
     }
+
+    /// Resets the head of the chain to the given block, which has to be on the canonical chain.
+    /// Blocks after it stay in the store, but are not part of the canonical chain anymore. The
+    /// pending state patch is dropped.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_revert_head(&mut self, block_hash: &CryptoHash) -> Result<(), Error> {
+        if self.blocks_in_processing_len() > 0 {
+            return Err(Error::Other("Cannot revert while blocks are being processed".to_string()));
+        }
+        let header = self.get_block_header(block_hash)?;
+        if self.chain_store.get_block_hash_by_height(header.height())? != *block_hash {
+            return Err(Error::Other(format!(
+                "Block {} is not on the canonical chain",
+                block_hash
+            )));
+        }
+        let final_header = if header.last_final_block() == &CryptoHash::default() {
+            self.genesis.header().clone()
+        } else {
+            self.get_block_header(header.last_final_block())?
+        };
+
+        let mut chain_store_update = self.chain_store.store_update();
+        chain_store_update.save_head(&Tip::from_header(&header))?;
+        chain_store_update.save_final_head(&Tip::from_header(&final_header))?;
+        chain_store_update.commit()?;
+        self.pending_state_patch.clear();
+        Ok(())
+    }
 }
 
 pub fn do_apply_chunks(
@@ -14,6 +14,7 @@ use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, inde
 use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
 use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId};
 
+use crate::store::account_activity_keys;
 use crate::types::RuntimeAdapter;
 use crate::{metrics, Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate};
 
@@ -150,6 +151,8 @@ impl ChainStore {
         if gc_stop_height > head.height {
             return Err(Error::GCError("gc_stop_height cannot be larger than head.height".into()));
         }
+        #[cfg(feature = "sandbox")]
+        let gc_stop_height = self.sandbox_gc_stop_height(gc_stop_height)?;
         let prev_epoch_id = *self.get_block_header(&head.prev_block_hash)?.epoch_id();
         let epoch_change = prev_epoch_id != head.epoch_id;
         let mut fork_tail = self.fork_tail()?;
@@ -247,6 +250,8 @@ impl ChainStore {
         if gc_stop_height > head.height {
             return Err(Error::GCError("gc_stop_height cannot be larger than head.height".into()));
         }
+        #[cfg(feature = "sandbox")]
+        let gc_stop_height = self.sandbox_gc_stop_height(gc_stop_height)?;
 
         let mut chain_store_update = self.store_update();
         chain_store_update.clear_redundant_chunk_data(gc_stop_height, gc_height_limit)?;
@@ -255,6 +260,16 @@ impl ChainStore {
         chain_store_update.commit()
     }
 
+    /// Keeps the data of the blocks needed to revert to the sandbox snapshots, see
+    /// `sandbox_pinned_height()`.
+    #[cfg(feature = "sandbox")]
+    fn sandbox_gc_stop_height(&self, gc_stop_height: BlockHeight) -> Result<BlockHeight, Error> {
+        Ok(match self.sandbox_pinned_height()? {
+            Some(pinned_height) => gc_stop_height.min(pinned_height),
+            None => gc_stop_height,
+        })
+    }
+
     fn clear_forks_data(
         &mut self,
         tries: ShardTries,
@@ -634,6 +649,7 @@ impl<'a> ChainStoreUpdate<'a> {
         self.gc_col(DBCol::NextBlockHashes, block_hash.as_bytes());
         self.gc_col(DBCol::ChallengedBlocks, block_hash.as_bytes());
         self.gc_col(DBCol::BlocksToCatchup, block_hash.as_bytes());
+        self.gc_account_activity(&block_hash, height)?;
         let storage_key = KeyForStateChanges::for_block(&block_hash);
         let stored_state_changes: Vec<Box<[u8]>> = self
             .store()
@@ -735,6 +751,7 @@ impl<'a> ChainStoreUpdate<'a> {
         self.gc_col(DBCol::NextBlockHashes, block_hash.as_bytes());
         self.gc_col(DBCol::ChallengedBlocks, block_hash.as_bytes());
         self.gc_col(DBCol::BlocksToCatchup, block_hash.as_bytes());
+        self.gc_account_activity(&block_hash, head_height)?;
         let storage_key = KeyForStateChanges::for_block(&block_hash);
         let stored_state_changes: Vec<Box<[u8]>> = self
             .store()
@@ -849,6 +866,24 @@ impl<'a> ChainStoreUpdate<'a> {
         self.merge(store_update);
     }
 
+    /// Deletes the entries of the block from `DBCol::AccountActivity`.  Their keys are derived
+    /// from the state changes and execution outcomes of the block, which therefore must not be
+    /// garbage collected yet.  Archival nodes keep serving the entries from the cold database.
+    /// If the index is disabled, it has been deleted when the chain was created.
+    fn gc_account_activity(
+        &mut self,
+        block_hash: &CryptoHash,
+        height: BlockHeight,
+    ) -> Result<(), Error> {
+        if !self.chain_store().index_account_activity {
+            return Ok(());
+        }
+        for key in account_activity_keys(self.store(), height, block_hash)? {
+            self.gc_col(DBCol::AccountActivity, &key);
+        }
+        Ok(())
+    }
+
     fn gc_outcomes(&mut self, block: &Block) -> Result<(), Error> {
         let block_hash = block.hash();
         let store_update = self.store().store_update();
@@ -991,12 +1026,16 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::StateSyncNewChunks => {
                 store_update.delete(col, key);
             }
+            DBCol::AccountActivity => {
+                store_update.delete(col, key);
+            }
             DBCol::DbVersion
             | DBCol::BlockMisc
             | DBCol::_GCCount
             | DBCol::BlockHeight  // block sync needs it + genesis should be accessible
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
+            | DBCol::PeerScores
             | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
@@ -1026,6 +1065,8 @@ impl<'a> ChainStoreUpdate<'a> {
             // Note that StateSyncHashes should not ever have too many keys in them
             // because we remove unneeded keys as we add new ones.
             | DBCol::StateSyncHashes
+            | DBCol::TransactionPool
+            | DBCol::StateHistory
             => unreachable!(),
         }
         self.merge(store_update);
@@ -1,6 +1,6 @@
 use near_o11y::metrics::{
     exponential_buckets, linear_buckets, processing_time_buckets, try_create_histogram_vec,
-    try_create_int_gauge_vec, HistogramVec, IntGaugeVec,
+    try_create_int_counter, try_create_int_gauge_vec, HistogramVec, IntCounter, IntGaugeVec,
 };
 
 use std::sync::LazyLock;
@@ -107,3 +107,11 @@ pub(crate) static STATE_SYNC_APPLY_PART_DELAY: LazyLock<HistogramVec> = LazyLock
     )
     .unwrap()
 });
+
+pub(crate) static STATE_HISTORY_QUERIES_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_state_history_queries_total",
+        "Number of queries answered from the state history index instead of the trie",
+    )
+    .unwrap()
+});
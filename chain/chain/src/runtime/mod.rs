@@ -1,3 +1,4 @@
+use crate::near_chain_primitives::error::QueryError;
 use crate::types::{
     ApplyChunkBlockContext, ApplyChunkResult, ApplyChunkShardContext,
     PrepareTransactionsBlockContext, PrepareTransactionsChunkContext, PrepareTransactionsLimit,
@@ -25,7 +26,7 @@ use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::trie_key::TrieKey;
+use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::{
     AccountId, Balance, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, MerkleHash,
     ShardId, StateChangeCause, StateRoot, StateRootNode,
@@ -33,9 +34,12 @@ use near_primitives::types::{
 use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives::views::{
     AccessKeyInfoView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewStateResult,
+    QueryResponseKind, StateItem, ViewStateResult,
 };
 use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
+use near_store::archive::state_history::{
+    get_historical_value, get_state_history_range, iter_historical_values,
+};
 use near_store::flat::FlatStorageManager;
 use near_store::metadata::DbKind;
 use near_store::{
@@ -75,6 +79,9 @@ pub struct NightshadeRuntime {
     epoch_manager: Arc<EpochManagerHandle>,
     migration_data: Arc<MigrationData>,
     gc_num_epochs_to_keep: u64,
+    /// Whether queries may be answered from the state history index, see
+    /// `query_state_history`.
+    state_history_queries: bool,
 }
 
 impl NightshadeRuntime {
@@ -89,6 +96,7 @@ impl NightshadeRuntime {
         gc_num_epochs_to_keep: u64,
         trie_config: TrieConfig,
         state_snapshot_config: StateSnapshotConfig,
+        state_history_queries: bool,
     ) -> Arc<Self> {
         let runtime_config_store = match runtime_config_store {
             Some(store) => store,
@@ -128,6 +136,7 @@ impl NightshadeRuntime {
             epoch_manager,
             migration_data,
             gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_GC_NUM_EPOCHS_TO_KEEP),
+            state_history_queries,
         })
     }
 
@@ -386,6 +395,7 @@ impl NightshadeRuntime {
             bandwidth_requests: apply_result.bandwidth_requests,
             bandwidth_scheduler_state_hash: apply_result.bandwidth_scheduler_state_hash,
             contract_updates: apply_result.contract_updates,
+            wasm_gas_profiles: apply_result.wasm_gas_profiles,
         };
 
         Ok(result)
@@ -480,6 +490,111 @@ impl NightshadeRuntime {
 
         Ok(state_part)
     }
+
+    /// Answers the query from the state history index of archival nodes if
+    /// the index is enabled and covers the block.  Returns `None` if the query
+    /// needs to be answered from the trie.
+    fn query_state_history(
+        &self,
+        block_height: BlockHeight,
+        block_hash: &CryptoHash,
+        request: &QueryRequest,
+    ) -> Result<Option<QueryResponseKind>, QueryError> {
+        if !self.state_history_queries {
+            return Ok(None);
+        }
+        let internal_error = |err: std::io::Error| QueryError::InternalError {
+            error_message: err.to_string(),
+            block_height,
+            block_hash: *block_hash,
+        };
+        if !matches!(
+            request,
+            QueryRequest::ViewAccount { .. }
+                | QueryRequest::ViewAccessKey { .. }
+                | QueryRequest::ViewState { include_proof: false, .. }
+        ) {
+            return Ok(None);
+        }
+        let Some(range) = get_state_history_range(&self.store).map_err(internal_error)? else {
+            return Ok(None);
+        };
+        if !range.contains(block_height) {
+            return Ok(None);
+        }
+        // The index only has the state of the canonical chain.
+        let canonical_block_hash = self
+            .store
+            .get_ser::<CryptoHash>(DBCol::BlockHeight, &block_height.to_le_bytes())
+            .map_err(internal_error)?;
+        if canonical_block_hash.as_ref() != Some(block_hash) {
+            return Ok(None);
+        }
+
+        let get = |trie_key: TrieKey| {
+            get_historical_value(&self.store, &range, &trie_key.to_vec(), block_height)
+                .map_err(internal_error)
+        };
+        let get_account = |account_id: &AccountId| {
+            let value =
+                get(TrieKey::Account { account_id: account_id.clone() })?.ok_or_else(|| {
+                    QueryError::UnknownAccount {
+                        requested_account_id: account_id.clone(),
+                        block_height,
+                        block_hash: *block_hash,
+                    }
+                })?;
+            Account::try_from_slice(&value).map_err(internal_error)
+        };
+        let kind = match request {
+            QueryRequest::ViewAccount { account_id } => {
+                QueryResponseKind::ViewAccount(get_account(account_id)?.into())
+            }
+            QueryRequest::ViewAccessKey { account_id, public_key } => {
+                let trie_key = TrieKey::AccessKey {
+                    account_id: account_id.clone(),
+                    public_key: public_key.clone(),
+                };
+                let value = get(trie_key)?.ok_or_else(|| QueryError::UnknownAccessKey {
+                    public_key: public_key.clone(),
+                    block_height,
+                    block_hash: *block_hash,
+                })?;
+                let access_key = AccessKey::try_from_slice(&value).map_err(internal_error)?;
+                QueryResponseKind::AccessKey(access_key.into())
+            }
+            QueryRequest::ViewState { account_id, prefix, .. } => {
+                get_account(account_id)?;
+                let raw_prefix =
+                    trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix.as_ref());
+                let acc_sep_len = raw_prefix.len() - prefix.len();
+                // Unlike the trie viewer, the size limit is checked against the
+                // returned data as the size of the contract code isn't indexed.
+                let state_size_limit = self.trie_viewer.state_size_limit();
+                let mut state_size = 0;
+                let mut values = vec![];
+                for item in iter_historical_values(&self.store, &range, &raw_prefix, block_height) {
+                    let (key, value) = item.map_err(internal_error)?;
+                    state_size += (key.len() - acc_sep_len + value.len()) as u64;
+                    if state_size_limit.is_some_and(|limit| state_size > limit) {
+                        return Err(QueryError::TooLargeContractState {
+                            requested_account_id: account_id.clone(),
+                            block_height,
+                            block_hash: *block_hash,
+                        });
+                    }
+                    values.push(StateItem {
+                        key: key[acc_sep_len..].to_vec().into(),
+                        value: value.into(),
+                    });
+                }
+                QueryResponseKind::ViewState(ViewStateResult { values, proof: vec![] })
+            }
+            _ => return Ok(None),
+        };
+        metrics::STATE_HISTORY_QUERIES_TOTAL.inc();
+        Ok(Some(kind))
+    }
 }
 
 fn format_total_gas_burnt(gas: Gas) -> String {
@@ -917,6 +1032,9 @@ impl RuntimeAdapter for NightshadeRuntime {
         epoch_id: &EpochId,
         request: &QueryRequest,
     ) -> Result<QueryResponse, crate::near_chain_primitives::error::QueryError> {
+        if let Some(kind) = self.query_state_history(block_height, block_hash, request)? {
+            return Ok(QueryResponse { kind, block_height, block_hash: *block_hash });
+        }
         match request {
             QueryRequest::ViewAccount { account_id } => {
                 let account =
@@ -945,8 +1063,9 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
-            QueryRequest::CallFunction { account_id, method_name, args } => {
+            QueryRequest::CallFunction { account_id, method_name, args, profile_wasm_gas } => {
                 let mut logs = vec![];
+                let mut wasm_gas_profile = profile_wasm_gas.then(String::new);
                 let (epoch_height, current_protocol_version) = {
                     let epoch_manager = self.epoch_manager.read();
                     let epoch_info = epoch_manager.get_epoch_info(epoch_id).map_err(|err| {
@@ -973,6 +1092,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                         method_name,
                         args.as_ref(),
                         &mut logs,
+                        wasm_gas_profile.as_mut(),
                         self.epoch_manager.as_ref(),
                         current_protocol_version,
                     )
@@ -987,6 +1107,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     kind: QueryResponseKind::CallResult(CallResult {
                         result: call_function_result,
                         logs,
+                        wasm_gas_profile,
                     }),
                     block_height,
                     block_hash: *block_hash,
@@ -1416,6 +1537,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         method_name: &str,
         args: &[u8],
         logs: &mut Vec<String>,
+        wasm_gas_profile: Option<&mut String>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
@@ -1438,6 +1560,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             method_name,
             args,
             logs,
+            wasm_gas_profile,
             epoch_info_provider,
         )
     }
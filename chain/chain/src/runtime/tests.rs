@@ -31,7 +31,7 @@ use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
 use near_chain_configs::{
     default_produce_chunk_add_transactions_time_limit, Genesis, MutableConfigValue,
-    DEFAULT_GC_NUM_EPOCHS_TO_KEEP, NEAR_BASE,
+    TransactionPoolOrdering, DEFAULT_GC_NUM_EPOCHS_TO_KEEP, NEAR_BASE,
 };
 use near_crypto::{InMemorySigner, Signer};
 use near_o11y::testonly::init_test_logger;
@@ -147,6 +147,7 @@ impl TestEnv {
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
             },
+            false,
         );
         let state_roots = get_genesis_state_roots(&store).unwrap().unwrap();
         let genesis_hash = hash(&[0]);
@@ -1580,7 +1581,7 @@ fn generate_transaction_pool(signers: &Vec<Signer>, block_hash: CryptoHash) -> T
     }
     transactions.shuffle(&mut rng);
 
-    let mut pool = TransactionPool::new(TEST_SEED, None, "");
+    let mut pool = TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::RoundRobin, "");
     for transaction in transactions {
         assert_eq!(pool.insert_transaction(transaction), InsertTransactionResult::Success);
     }
@@ -1863,6 +1864,77 @@ fn test_precompile_contracts_updates_cache() {
     }
 }
 
+/// Tests that queries are answered from the state history index only if it is
+/// enabled.
+#[test]
+fn test_query_state_history() {
+    let (storage, ..) = near_store::test_utils::create_test_node_storage_with_cold(
+        near_store::metadata::DB_VERSION,
+        DbKind::Hot,
+    );
+    let hot_store = storage.get_hot_store();
+    let split_store = storage.get_split_store().unwrap();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let tempdir = tempfile::tempdir().unwrap();
+    initialize_genesis_state(hot_store.clone(), &genesis, Some(tempdir.path()));
+    let state_roots = get_genesis_state_roots(&hot_store).unwrap().unwrap();
+    let shard_uid = ShardUId::single_shard();
+    let height = genesis.config.genesis_height;
+    let block_hash = CryptoHash::hash_bytes(b"genesis");
+    let mut store_update = hot_store.store_update();
+    store_update.set_ser(DBCol::BlockHeight, &height.to_le_bytes(), &block_hash).unwrap();
+    store_update.commit().unwrap();
+    near_store::archive::state_history::init_state_history(
+        storage.cold_db().unwrap(),
+        &hot_store,
+        &[(shard_uid, state_roots[0])],
+        height,
+    )
+    .unwrap();
+
+    let contract_cache = FilesystemContractRuntimeCache::new(tempdir.path(), None::<&str>)
+        .expect("filesystem contract cache");
+    let query = |state_history_queries| {
+        let epoch_manager =
+            EpochManager::new_arc_handle(split_store.clone(), &genesis.config, None);
+        let runtime = NightshadeRuntime::new(
+            split_store.clone(),
+            contract_cache.handle(),
+            &genesis.config,
+            epoch_manager,
+            None,
+            None,
+            Some(RuntimeConfigStore::test()),
+            DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+            Default::default(),
+            StateSnapshotConfig {
+                state_snapshot_type: StateSnapshotType::ForReshardingOnly,
+                home_dir: tempdir.path().to_path_buf(),
+                hot_store_path: PathBuf::from("data"),
+                state_snapshot_subdir: PathBuf::from("state_snapshot"),
+            },
+            state_history_queries,
+        );
+        // The account can only be found in the index as the trie is empty.
+        runtime.query(
+            shard_uid,
+            &Trie::EMPTY_ROOT,
+            height,
+            0,
+            &CryptoHash::default(),
+            &block_hash,
+            &EpochId::default(),
+            &QueryRequest::ViewAccount { account_id: "test0".parse().unwrap() },
+        )
+    };
+    assert_matches!(query(false), Err(QueryError::UnknownAccount { .. }));
+    let response = query(true).unwrap();
+    let QueryResponseKind::ViewAccount(account) = response.kind else {
+        panic!("unexpected response {:?}", response.kind);
+    };
+    assert_eq!(account.amount, TESTING_INIT_BALANCE - TESTING_INIT_STAKE);
+}
+
 fn stake(
     nonce: Nonce,
     signer: &Signer,
@@ -0,0 +1,523 @@
+//! Index of the activity of accounts: the transactions and receipts which
+//! changed the state of each account or were executed by it.
+//!
+//! The index is optional and is maintained only when `save_account_activity`
+//! is enabled in the client config.  It is built from the state changes and
+//! execution outcomes of each processed block and written in the same store
+//! update as the block, so it may also be backfilled for blocks whose data is
+//! still in the database.  Entries are garbage collected together with their
+//! blocks, and archival nodes copy them to the cold database before that.  The
+//! index is deleted from the hot database if it gets disabled.
+//!
+//! Entries are stored per account, height and block hash so that blocks on
+//! different forks don't overwrite each other.  Entries of blocks which are
+//! not on the canonical chain are skipped when the index is read.
+
+use std::collections::BTreeMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_chain_primitives::Error;
+use near_crypto::PublicKey;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::ExecutionOutcomeWithProof;
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::{
+    AccountId, BlockHeight, RawStateChangesWithTrieKey, StateChangeCause,
+};
+use near_primitives::utils::get_outcome_id_block_hash;
+use near_primitives::views::{AccountActivityCursor, AccountActivityView};
+use near_store::archive::account_activity::{
+    account_activity_key, account_activity_key_prefix, ACCOUNT_ID_SEPARATOR,
+};
+use near_store::{DBCol, KeyForStateChanges, Store, StoreUpdate};
+
+use super::{ChainStore, ChainStoreUpdate};
+use crate::ChainStoreAccess;
+
+/// Parses the height and block hash out of a key of `DBCol::AccountActivity`
+/// of the given account.
+fn parse_account_activity_key(
+    account_id: &AccountId,
+    key: &[u8],
+) -> Result<(BlockHeight, CryptoHash), Error> {
+    let suffix = key.get(account_id.len() + 1..).filter(|suffix| suffix.len() == 8 + 32);
+    let Some(suffix) = suffix else {
+        return Err(Error::Other(format!("Invalid account activity key: {:?}", key)));
+    };
+    let height = BlockHeight::from_be_bytes(suffix[..8].try_into().unwrap());
+    let block_hash = CryptoHash(suffix[8..].try_into().unwrap());
+    Ok((height, block_hash))
+}
+
+/// Activity of accounts in a single block.
+#[derive(Default)]
+struct BlockAccountActivity(BTreeMap<AccountId, Vec<AccountActivity>>);
+
+impl BlockAccountActivity {
+    fn push(&mut self, account_id: AccountId, activity: AccountActivity) {
+        let entries = self.0.entry(account_id).or_default();
+        if !entries.contains(&activity) {
+            entries.push(activity);
+        }
+    }
+
+    fn add_state_changes(&mut self, changes: &RawStateChangesWithTrieKey) {
+        let Some(account_id) = changes.trie_key.get_account_id() else {
+            return;
+        };
+        let public_key = match &changes.trie_key {
+            TrieKey::AccessKey { public_key, .. } => Some(public_key.clone()),
+            _ => None,
+        };
+        for change in &changes.changes {
+            // Same as in `WrappedTrieChanges::state_changes_into`, these are never saved.
+            if matches!(
+                change.cause,
+                StateChangeCause::ReshardingV2 | StateChangeCause::NotWritableToDisk
+            ) {
+                continue;
+            }
+            let activity =
+                AccountActivity { cause: change.cause.clone(), public_key: public_key.clone() };
+            self.push(account_id.clone(), activity);
+        }
+    }
+
+    /// Receipts executed by an account don't necessarily change its state,
+    /// e.g. failed function calls, so the execution outcomes are indexed as
+    /// well.  Transactions always change the state of their signer, so the
+    /// state changes of the block must be added first.
+    fn add_outcome(&mut self, outcome_id: &CryptoHash, executor_id: &AccountId) {
+        let is_transaction = self.0.get(executor_id).is_some_and(|entries| {
+            entries.iter().any(|entry| {
+                matches!(
+                    entry.cause,
+                    StateChangeCause::TransactionProcessing { tx_hash } if tx_hash == *outcome_id
+                )
+            })
+        });
+        if !is_transaction {
+            let cause = StateChangeCause::ReceiptProcessing { receipt_hash: *outcome_id };
+            self.push(executor_id.clone(), AccountActivity { cause, public_key: None });
+        }
+    }
+
+    /// Collects the activity from the state changes and execution outcomes of
+    /// the block saved in the store.
+    fn from_store(store: &Store, block_hash: &CryptoHash) -> Result<Self, Error> {
+        let mut activity = Self::default();
+        let storage_key = KeyForStateChanges::for_block(block_hash);
+        for changes in storage_key.find_iter(store) {
+            activity.add_state_changes(&changes?);
+        }
+        for item in store.iter_prefix_ser::<Vec<CryptoHash>>(DBCol::OutcomeIds, block_hash.as_ref())
+        {
+            let (_, outcome_ids) = item?;
+            for outcome_id in outcome_ids {
+                let Some(outcome) = store.get_ser::<ExecutionOutcomeWithProof>(
+                    DBCol::TransactionResultForBlock,
+                    &get_outcome_id_block_hash(&outcome_id, block_hash),
+                )?
+                else {
+                    continue;
+                };
+                activity.add_outcome(&outcome_id, &outcome.outcome.executor_id);
+            }
+        }
+        Ok(activity)
+    }
+
+    fn write(
+        &self,
+        store_update: &mut StoreUpdate,
+        height: BlockHeight,
+        block_hash: &CryptoHash,
+    ) -> Result<(), Error> {
+        for (account_id, entries) in &self.0 {
+            store_update.set_ser(
+                DBCol::AccountActivity,
+                &account_activity_key(account_id, height, block_hash),
+                entries,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the keys of `DBCol::AccountActivity` of the given block, which has
+/// to be garbage collected before its state changes and execution outcomes.
+pub(crate) fn account_activity_keys(
+    store: &Store,
+    height: BlockHeight,
+    block_hash: &CryptoHash,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let activity = BlockAccountActivity::from_store(store, block_hash)?;
+    Ok(activity
+        .0
+        .keys()
+        .map(|account_id| account_activity_key(account_id, height, block_hash))
+        .collect())
+}
+
+impl ChainStoreUpdate<'_> {
+    /// Records the activity of accounts in the blocks of this update, from
+    /// their state changes and execution outcomes which are yet to be written.
+    pub(super) fn write_account_activity(
+        &self,
+        store_update: &mut StoreUpdate,
+    ) -> Result<(), Error> {
+        let mut activity: BTreeMap<CryptoHash, BlockAccountActivity> = BTreeMap::new();
+        for trie_changes in &self.trie_changes {
+            let block_activity = activity.entry(*trie_changes.block_hash()).or_default();
+            for changes in trie_changes.state_changes() {
+                block_activity.add_state_changes(changes);
+            }
+        }
+        // Outcome ids are in the order of execution within each shard.
+        let mut outcome_ids: Vec<_> = self.chain_store_cache_update.outcome_ids.iter().collect();
+        outcome_ids.sort_by_key(|((block_hash, shard_id), _)| (*block_hash, *shard_id));
+        for ((block_hash, _), ids) in outcome_ids {
+            let block_activity = activity.entry(*block_hash).or_default();
+            for outcome_id in ids {
+                let Some(outcome) =
+                    self.chain_store_cache_update.outcomes.get(&(*outcome_id, *block_hash))
+                else {
+                    continue;
+                };
+                block_activity.add_outcome(outcome_id, &outcome.outcome.executor_id);
+            }
+        }
+        for (block_hash, block_activity) in &activity {
+            let height = self.get_block_header(block_hash)?.height();
+            block_activity.write(store_update, height, block_hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChainStore {
+    /// Records the activity of accounts in the given block in
+    /// `DBCol::AccountActivity` of `target_store`, which is the cold store
+    /// when backfilling blocks already copied to it on split storage.  The
+    /// state changes and execution outcomes of the block must already be
+    /// saved.  Indexing a block again overwrites its previous entries.
+    pub fn save_account_activity(
+        &self,
+        block_hash: &CryptoHash,
+        target_store: &Store,
+    ) -> Result<(), Error> {
+        let _span =
+            tracing::debug_span!(target: "chain", "save_account_activity", ?block_hash).entered();
+        let header = self.get_block_header(block_hash)?;
+        let activity = BlockAccountActivity::from_store(&self.store, block_hash)?;
+        let mut store_update = target_store.store_update();
+        activity.write(&mut store_update, header.height(), block_hash)?;
+        store_update.commit()?;
+        tracing::debug!(target: "chain", accounts = activity.0.len(), "Saved account activity");
+        Ok(())
+    }
+
+    /// Deletes the whole index once it has been disabled, as its entries
+    /// wouldn't be garbage collected anymore.  Entries which have already been
+    /// copied to the cold database are kept there.
+    pub(crate) fn clear_account_activity(&self) -> Result<(), Error> {
+        if self.store.iter(DBCol::AccountActivity).next().transpose()?.is_none() {
+            return Ok(());
+        }
+        tracing::info!(target: "chain", "Deleting the account activity index as save_account_activity is disabled");
+        let mut store_update = self.store.store_update();
+        store_update.delete_all(DBCol::AccountActivity);
+        store_update.commit()?;
+        Ok(())
+    }
+
+    /// Returns the activity of the account on the canonical chain between
+    /// `from_height` and `to_height` inclusive, optionally only the activity
+    /// which changed the given access key.
+    ///
+    /// At most `limit` entries are returned.  If there are more, the cursor at
+    /// which the next query should continue is returned as well.
+    pub fn get_account_activity(
+        &self,
+        account_id: &AccountId,
+        public_key: Option<&PublicKey>,
+        from_height: BlockHeight,
+        to_height: BlockHeight,
+        cursor: Option<AccountActivityCursor>,
+        limit: usize,
+    ) -> Result<(Vec<AccountActivityView>, Option<AccountActivityCursor>), Error> {
+        let start_height = match cursor {
+            Some(cursor) => cursor.block_height.max(from_height),
+            None => from_height,
+        };
+        let lower_bound = account_activity_key_prefix(account_id, start_height);
+        let upper_bound = match to_height.checked_add(1) {
+            Some(height) => account_activity_key_prefix(account_id, height),
+            None => {
+                let mut key = account_id.as_bytes().to_vec();
+                key.push(ACCOUNT_ID_SEPARATOR + 1);
+                key
+            }
+        };
+
+        let mut result = vec![];
+        for item in
+            self.store.iter_range(DBCol::AccountActivity, Some(&lower_bound), Some(&upper_bound))
+        {
+            let (key, value) = item?;
+            let (height, block_hash) = parse_account_activity_key(account_id, &key)?;
+            match self.get_block_hash_by_height(height) {
+                Ok(canonical_hash) if canonical_hash == block_hash => {}
+                Ok(_) | Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            }
+            let entries = Vec::<AccountActivity>::try_from_slice(&value)?;
+            for (index, entry) in entries.into_iter().enumerate() {
+                let index = index as u32;
+                if let Some(cursor) = cursor {
+                    if height == cursor.block_height && index < cursor.index {
+                        continue;
+                    }
+                }
+                if public_key.is_some() && entry.public_key.as_ref() != public_key {
+                    continue;
+                }
+                if result.len() >= limit {
+                    let next_cursor = AccountActivityCursor { block_height: height, index };
+                    return Ok((result, Some(next_cursor)));
+                }
+                result.push(AccountActivityView {
+                    block_height: height,
+                    block_hash,
+                    cause: entry.cause.into(),
+                    public_key: entry.public_key,
+                });
+            }
+        }
+        Ok((result, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::get_chain;
+    use near_async::time::Clock;
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::block::Block;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::test_utils::{create_test_signer, TestBlockBuilder};
+    use near_primitives::transaction::{ExecutionOutcome, ExecutionOutcomeWithId};
+    use near_primitives::types::RawStateChange;
+    use near_store::{Trie, TrieChanges, WrappedTrieChanges};
+    use std::sync::Arc;
+
+    fn state_changes(trie_key: TrieKey, cause: StateChangeCause) -> RawStateChangesWithTrieKey {
+        RawStateChangesWithTrieKey {
+            trie_key,
+            changes: vec![RawStateChange { cause, data: Some(vec![1]) }],
+        }
+    }
+
+    fn outcome(id: CryptoHash, executor_id: &AccountId) -> ExecutionOutcomeWithId {
+        let outcome = ExecutionOutcome { executor_id: executor_id.clone(), ..Default::default() };
+        ExecutionOutcomeWithId { id, outcome }
+    }
+
+    /// Saves the block with the given state changes and outcomes the same way the chain does
+    /// when it applies the block, optionally making it the canonical block at its height.
+    fn save_block(
+        chain: &mut crate::Chain,
+        block: &Block,
+        changes: Vec<RawStateChangesWithTrieKey>,
+        outcomes: Vec<ExecutionOutcomeWithId>,
+        canonical: bool,
+    ) {
+        let tries = chain.runtime_adapter.get_tries();
+        let mut store_update = chain.mut_chain_store().store_update();
+        store_update.save_block_header(block.header().clone()).unwrap();
+        if canonical {
+            let height_to_hashes = &mut store_update.chain_store_cache_update.height_to_hashes;
+            height_to_hashes.insert(block.header().height(), Some(*block.hash()));
+        }
+        store_update.save_trie_changes(WrappedTrieChanges::new(
+            tries,
+            ShardUId::single_shard(),
+            TrieChanges::empty(Trie::EMPTY_ROOT),
+            changes,
+            *block.hash(),
+            block.header().height(),
+        ));
+        let proofs = vec![vec![]; outcomes.len()];
+        store_update.save_outcomes_with_proofs(block.hash(), 0.into(), outcomes, proofs);
+        store_update.commit().unwrap();
+    }
+
+    /// Views don't implement `PartialEq`, so they are compared through their debug output.
+    fn summary(activity: &[AccountActivityView]) -> Vec<String> {
+        activity.iter().map(|entry| format!("{:?}", entry)).collect()
+    }
+
+    fn view(
+        block: &Block,
+        cause: StateChangeCause,
+        public_key: Option<PublicKey>,
+    ) -> AccountActivityView {
+        AccountActivityView {
+            block_height: block.header().height(),
+            block_hash: *block.hash(),
+            cause: cause.into(),
+            public_key,
+        }
+    }
+
+    #[test]
+    fn test_account_activity() {
+        let mut chain = get_chain(Clock::real());
+        chain.mut_chain_store().index_account_activity = true;
+        let signer = Arc::new(create_test_signer("test1"));
+        let genesis = chain.get_block_by_height(0).unwrap();
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let bob: AccountId = "bob.near".parse().unwrap();
+        let public_key = SecretKey::from_seed(KeyType::ED25519, "alice").public_key();
+
+        // Block 1: alice sends a transaction which changes her access key, and bob executes the
+        // receipt without changing his state.
+        let block1 = TestBlockBuilder::new(Clock::real(), &genesis, signer.clone()).build();
+        let tx_hash = CryptoHash::hash_bytes(b"tx");
+        let receipt_hash = CryptoHash::hash_bytes(b"receipt");
+        save_block(
+            &mut chain,
+            &block1,
+            vec![
+                state_changes(
+                    TrieKey::Account { account_id: alice.clone() },
+                    StateChangeCause::TransactionProcessing { tx_hash },
+                ),
+                state_changes(
+                    TrieKey::AccessKey {
+                        account_id: alice.clone(),
+                        public_key: public_key.clone(),
+                    },
+                    StateChangeCause::TransactionProcessing { tx_hash },
+                ),
+            ],
+            vec![outcome(tx_hash, &alice), outcome(receipt_hash, &bob)],
+            true,
+        );
+        // Block 2 on a fork which didn't become canonical.
+        let fork_block =
+            TestBlockBuilder::new(Clock::real(), &block1, signer.clone()).height(2).build();
+        save_block(
+            &mut chain,
+            &fork_block,
+            vec![state_changes(
+                TrieKey::Account { account_id: alice.clone() },
+                StateChangeCause::ValidatorAccountsUpdate,
+            )],
+            vec![],
+            false,
+        );
+        // Block 3 refunds alice.
+        let block3 =
+            TestBlockBuilder::new(Clock::real(), &block1, signer.clone()).height(3).build();
+        let refund_hash = CryptoHash::hash_bytes(b"refund");
+        save_block(
+            &mut chain,
+            &block3,
+            vec![state_changes(
+                TrieKey::Account { account_id: alice.clone() },
+                StateChangeCause::ReceiptProcessing { receipt_hash: refund_hash },
+            )],
+            vec![outcome(refund_hash, &alice)],
+            true,
+        );
+
+        let chain_store = chain.chain_store();
+        let (activity, cursor) =
+            chain_store.get_account_activity(&alice, None, 0, 10, None, 100).unwrap();
+        assert_eq!(cursor, None);
+        let tx_cause = StateChangeCause::TransactionProcessing { tx_hash };
+        let refund_cause = StateChangeCause::ReceiptProcessing { receipt_hash: refund_hash };
+        let expected = [
+            view(&block1, tx_cause.clone(), None),
+            view(&block1, tx_cause, Some(public_key.clone())),
+            view(&block3, refund_cause, None),
+        ];
+        assert_eq!(summary(&activity), summary(&expected));
+
+        // Receipts are indexed for their executor even if they didn't change its state.
+        let (bob_activity, _) =
+            chain_store.get_account_activity(&bob, None, 0, 10, None, 100).unwrap();
+        let receipt_cause = StateChangeCause::ReceiptProcessing { receipt_hash };
+        assert_eq!(summary(&bob_activity), summary(&[view(&block1, receipt_cause, None)]));
+
+        // Only the activity which changed the access key.
+        let (key_activity, _) =
+            chain_store.get_account_activity(&alice, Some(&public_key), 0, 10, None, 100).unwrap();
+        assert_eq!(summary(&key_activity), summary(&expected[1..2]));
+
+        // Pagination continues right after the last returned entry.
+        let mut paginated = vec![];
+        let mut cursor = None;
+        loop {
+            let (page, next_cursor) =
+                chain_store.get_account_activity(&alice, None, 0, 10, cursor, 1).unwrap();
+            assert!(page.len() <= 1);
+            paginated.extend(page);
+            cursor = next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(summary(&paginated), summary(&expected));
+
+        // Height filtering.
+        let (filtered, _) =
+            chain_store.get_account_activity(&alice, None, 2, 3, None, 100).unwrap();
+        assert_eq!(summary(&filtered), summary(&expected[2..]));
+        let (filtered, _) =
+            chain_store.get_account_activity(&alice, None, 0, 2, None, 100).unwrap();
+        assert_eq!(summary(&filtered), summary(&expected[..2]));
+
+        // Keys for garbage collection cover all entries of the block, including the fork.
+        let store = chain_store.store();
+        for block in [&block1, &fork_block, &block3] {
+            let height = block.header().height();
+            let mut keys = account_activity_keys(store, height, block.hash()).unwrap();
+            keys.sort();
+            let mut stored: Vec<Vec<u8>> = store
+                .iter(DBCol::AccountActivity)
+                .map(|item| item.unwrap().0.to_vec())
+                .filter(|key| key.ends_with(block.hash().as_ref()))
+                .collect();
+            stored.sort();
+            assert!(!keys.is_empty());
+            assert_eq!(keys, stored);
+        }
+
+        // Backfilling a block gives the same entries as indexing it with the block.
+        let before: Vec<_> = store.iter(DBCol::AccountActivity).map(Result::unwrap).collect();
+        chain_store.save_account_activity(block1.hash(), store).unwrap();
+        let after: Vec<_> = store.iter(DBCol::AccountActivity).map(Result::unwrap).collect();
+        assert_eq!(before, after);
+
+        // Disabling the index deletes it.
+        chain_store.clear_account_activity().unwrap();
+        assert!(store.iter(DBCol::AccountActivity).next().is_none());
+    }
+
+    #[test]
+    fn test_account_activity_key() {
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let block_hash = CryptoHash::hash_bytes(b"block");
+        let key = account_activity_key(&account_id, 42, &block_hash);
+        assert_eq!(parse_account_activity_key(&account_id, &key).unwrap(), (42, block_hash));
+
+        // Entries of an account are not interleaved with entries of accounts
+        // whose ids start with the id of that account.
+        let other_account_id: AccountId = "alice.near.app".parse().unwrap();
+        let other_key = account_activity_key(&other_account_id, 0, &block_hash);
+        assert!(other_key > account_activity_key(&account_id, u64::MAX, &block_hash));
+    }
+}
@@ -54,8 +54,10 @@ use crate::types::{Block, BlockHeader, LatestKnown};
 use near_store::db::{StoreStatistics, STATE_SYNC_DUMP_KEY};
 use std::sync::Arc;
 
+mod account_activity;
 mod latest_witnesses;
 mod merkle_proof;
+pub(crate) use account_activity::account_activity_keys;
 pub use latest_witnesses::LatestWitnessesInfo;
 pub use merkle_proof::MerkleProofAccess;
 
@@ -457,6 +459,8 @@ pub struct ChainStore {
     /// - archive is true, cold_store is configured and migration to split_storage is finished - node
     /// working in split storage mode needs trie changes in order to do garbage collection on hot.
     save_trie_changes: bool,
+    /// Whether to maintain the index in `DBCol::AccountActivity`, see `account_activity`.
+    pub(crate) index_account_activity: bool,
 }
 
 fn option_to_not_found<T, F>(res: io::Result<Option<T>>, field_name: F) -> Result<T, Error>
@@ -498,6 +502,7 @@ impl ChainStore {
             block_ordinal_to_hash: CellLruCache::new(CACHE_SIZE),
             processed_block_heights: CellLruCache::new(CACHE_SIZE),
             save_trie_changes,
+            index_account_activity: false,
         }
     }
 
@@ -864,6 +869,32 @@ impl ChainStore {
         store_update.commit().map_err(|err| err.into())
     }
 
+    /// Height of the lowest block whose data is kept for sandbox snapshots, if any snapshot is
+    /// taken. Garbage collection doesn't go past it and the flat storage and memtries are not
+    /// updated while it is set.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_pinned_height(&self) -> Result<Option<BlockHeight>, Error> {
+        self.store
+            .get_ser(DBCol::BlockMisc, near_store::SANDBOX_PINNED_HEIGHT_KEY)
+            .map_err(|e| e.into())
+    }
+
+    /// Sets or clears the height returned by `sandbox_pinned_height()`. It is stored, so that
+    /// the garbage collection running on its own `ChainStore` sees it.
+    #[cfg(feature = "sandbox")]
+    pub fn save_sandbox_pinned_height(&self, height: Option<BlockHeight>) -> Result<(), Error> {
+        let mut store_update = self.store.store_update();
+        match height {
+            Some(height) => store_update.set_ser(
+                DBCol::BlockMisc,
+                near_store::SANDBOX_PINNED_HEIGHT_KEY,
+                &height,
+            )?,
+            None => store_update.delete(DBCol::BlockMisc, near_store::SANDBOX_PINNED_HEIGHT_KEY),
+        }
+        store_update.commit().map_err(|err| err.into())
+    }
+
     /// Retrieve the kinds of state changes occurred in a given block.
     ///
     /// We store different types of data, so we prefer to only expose minimal information about the
@@ -2463,6 +2494,11 @@ impl<'a> ChainStoreUpdate<'a> {
             }
         }
 
+        if self.chain_store.index_account_activity {
+            let _span = tracing::trace_span!(target: "store", "write_account_activity").entered();
+            self.write_account_activity(&mut store_update)?;
+        }
+
         for (block_hash, refcount) in self.chain_store_cache_update.block_refcounts.iter() {
             store_update.set_ser(DBCol::BlockRefCount, block_hash.as_ref(), refcount)?;
         }
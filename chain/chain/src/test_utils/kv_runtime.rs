@@ -38,10 +38,12 @@ use near_primitives::transaction::{
     Action, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus,
     SignedTransaction, TransferAction,
 };
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
     AccountId, ApprovalStake, Balance, BlockHeight, EpochHeight, EpochId, Nonce, NumShards,
-    ShardId, ShardIndex, StateRoot, StateRootNode, ValidatorInfoIdentifier,
+    RawStateChange, RawStateChangesWithTrieKey, ShardId, ShardIndex, StateChangeCause, StateRoot,
+    StateRootNode, ValidatorInfoIdentifier,
 };
 use near_primitives::version::{ProtocolFeature, ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
@@ -1163,23 +1165,43 @@ impl RuntimeAdapter for KeyValueRuntime {
                         transaction.transaction.nonce(),
                     ));
                 }
+            } else if let Action::Delegate(_) = transaction.transaction.actions()[0] {
+                // Transactions of the RPC relayer are accepted but not executed.
+                continue;
             } else {
                 unreachable!();
             }
         }
 
         let mut outgoing_receipts = vec![];
+        // Changes of the balances, reported as changes of the accounts.
+        let mut account_changes: BTreeMap<AccountId, Vec<RawStateChange>> = BTreeMap::new();
+        let mut record_account_change = |account_id: &AccountId, balance, cause| {
+            let account = Account::new(balance, 0, 0, CryptoHash::default(), 0, PROTOCOL_VERSION);
+            let data = Some(borsh::to_vec(&account).unwrap());
+            account_changes
+                .entry(account_id.clone())
+                .or_default()
+                .push(RawStateChange { cause, data });
+        };
 
         for (hash, from, to, amount, nonce) in balance_transfers {
             let mut good_to_go = false;
+            let is_receipt = account_id_to_shard_id(&from, self.num_shards) != shard_id;
+            let cause = if is_receipt {
+                StateChangeCause::ReceiptProcessing { receipt_hash: hash }
+            } else {
+                StateChangeCause::TransactionProcessing { tx_hash: hash }
+            };
 
-            if account_id_to_shard_id(&from, self.num_shards) != shard_id {
+            if is_receipt {
                 // This is a receipt, was already debited
                 good_to_go = true;
             } else if let Some(balance) = state.amounts.get(&from) {
                 if *balance >= amount {
                     let new_balance = balance - amount;
                     state.amounts.insert(from.clone(), new_balance);
+                    record_account_change(&from, new_balance, cause.clone());
                     good_to_go = true;
                 }
             }
@@ -1187,7 +1209,9 @@ impl RuntimeAdapter for KeyValueRuntime {
             if good_to_go {
                 let new_receipt_hashes = if account_id_to_shard_id(&to, self.num_shards) == shard_id
                 {
-                    state.amounts.insert(to.clone(), state.amounts.get(&to).unwrap_or(&0) + amount);
+                    let new_balance = state.amounts.get(&to).unwrap_or(&0) + amount;
+                    state.amounts.insert(to.clone(), new_balance);
+                    record_account_change(&to, new_balance, cause);
                     vec![]
                 } else {
                     assert_ne!(nonce, 0);
@@ -1236,7 +1260,13 @@ impl RuntimeAdapter for KeyValueRuntime {
                 self.get_tries(),
                 ShardUId::new(0, shard_id),
                 TrieChanges::empty(state_root),
-                Default::default(),
+                account_changes
+                    .into_iter()
+                    .map(|(account_id, changes)| RawStateChangesWithTrieKey {
+                        trie_key: TrieKey::Account { account_id },
+                        changes,
+                    })
+                    .collect(),
                 block.block_hash,
                 block.height,
             ),
@@ -1254,6 +1284,7 @@ impl RuntimeAdapter for KeyValueRuntime {
             bandwidth_requests: BandwidthRequests::default_for_protocol_version(PROTOCOL_VERSION),
             bandwidth_scheduler_state_hash: CryptoHash::default(),
             contract_updates: Default::default(),
+            wasm_gas_profiles: vec![],
         })
     }
 
@@ -1322,6 +1353,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                 kind: QueryResponseKind::CallResult(CallResult {
                     result: Default::default(),
                     logs: Default::default(),
+                    wasm_gas_profile: None,
                 }),
                 block_height,
                 block_hash: *block_hash,
@@ -42,6 +42,7 @@ use near_store::flat::FlatStorageManager;
 use near_store::{PartialStorage, ShardTries, Store, Trie, WrappedTrieChanges};
 use near_vm_runner::ContractCode;
 use near_vm_runner::ContractRuntimeCache;
+use node_runtime::FunctionCallWasmGasProfile;
 use num_rational::Rational32;
 use tracing::instrument;
 
@@ -113,6 +114,9 @@ pub struct ApplyChunkResult {
     pub bandwidth_scheduler_state_hash: CryptoHash,
     /// Contracts accessed and deployed while applying the chunk.
     pub contract_updates: ContractUpdates,
+    /// Wasm gas profiles of the function calls applied in the chunk, only recorded when the
+    /// runtime config has `wasm_gas_profiling` enabled.
+    pub wasm_gas_profiles: Vec<FunctionCallWasmGasProfile>,
 }
 
 impl ApplyChunkResult {
@@ -220,6 +224,8 @@ pub struct ChainConfig {
     pub background_migration_threads: usize,
     /// The resharding configuration.
     pub resharding_config: MutableConfigValue<ReshardingConfig>,
+    /// Whether to index the activity of accounts in `DBCol::AccountActivity`.
+    pub save_account_activity: bool,
 }
 
 impl ChainConfig {
@@ -231,6 +237,7 @@ impl ChainConfig {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
         }
     }
 }
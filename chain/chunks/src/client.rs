@@ -3,11 +3,13 @@ use std::collections::HashMap;
 use actix::Message;
 use itertools::Itertools;
 
+use near_chain_configs::TransactionPoolOrdering;
 use near_pool::types::TransactionGroupIterator;
 use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::{
     epoch_info::RngSeed,
+    hash::CryptoHash,
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
     transaction::SignedTransaction,
     types::{AccountId, ShardId},
@@ -44,11 +46,18 @@ pub struct ShardedTransactionPool {
     /// If set, new transactions that bring the size of the pool over this limit will be rejected.
     /// The size is tracked and enforced separately for each shard.
     pool_size_limit: Option<u64>,
+
+    /// Order in which transactions are taken from the pools.
+    ordering: TransactionPoolOrdering,
 }
 
 impl ShardedTransactionPool {
-    pub fn new(rng_seed: RngSeed, pool_size_limit: Option<u64>) -> Self {
-        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit }
+    pub fn new(
+        rng_seed: RngSeed,
+        pool_size_limit: Option<u64>,
+        ordering: TransactionPoolOrdering,
+    ) -> Self {
+        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit, ordering }
     }
 
     pub fn get_pool_iterator(&mut self, shard_uid: ShardUId) -> Option<PoolIteratorWrapper<'_>> {
@@ -87,11 +96,30 @@ impl ShardedTransactionPool {
             TransactionPool::new(
                 Self::random_seed(&self.rng_seed, shard_uid.shard_id()),
                 self.pool_size_limit,
+                self.ordering,
                 &shard_uid.to_string(),
             )
         })
     }
 
+    /// Returns all transactions in the pools of all shards.
+    pub fn transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.tx_pools.values().flat_map(|pool| pool.transactions())
+    }
+
+    /// Returns whether the transaction with the given hash is in the pool of any shard.
+    pub fn contains(&self, tx_hash: &CryptoHash) -> bool {
+        self.tx_pools.values().any(|pool| pool.contains(tx_hash))
+    }
+
+    /// Removes all transactions from the pools of all shards.
+    pub fn clear(&mut self) {
+        for pool in self.tx_pools.values_mut() {
+            let transactions: Vec<_> = pool.transactions().cloned().collect();
+            pool.remove_transactions(&transactions);
+        }
+    }
+
     pub fn debug_status(&self) -> String {
         self.tx_pools
             .iter()
@@ -112,7 +140,7 @@ impl ShardedTransactionPool {
         for tx in transactions {
             reintroduced_count += match pool.insert_transaction(tx.clone()) {
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate => 1,
-                InsertTransactionResult::NoSpaceLeft => 0,
+                InsertTransactionResult::NoSpaceLeft | InsertTransactionResult::Underpriced => 0,
             }
         }
         reintroduced_count
@@ -155,6 +183,7 @@ impl ShardedTransactionPool {
 #[cfg(test)]
 mod tests {
     use crate::client::ShardedTransactionPool;
+    use near_chain_configs::TransactionPoolOrdering;
     use near_crypto::{InMemorySigner, KeyType};
     use near_o11y::testonly::init_test_logger;
     use near_pool::types::TransactionGroupIterator;
@@ -196,7 +225,8 @@ mod tests {
         let old_shard_layout = ShardLayout::get_simple_nightshade_layout();
         let new_shard_layout = ShardLayout::get_simple_nightshade_layout_v2();
 
-        let mut pool = ShardedTransactionPool::new(TEST_SEED, None);
+        let mut pool =
+            ShardedTransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::RoundRobin);
 
         let mut shard_id_to_accounts: HashMap<ShardId, _> = HashMap::new();
         shard_id_to_accounts.insert(ShardId::new(0), vec!["aaa", "abcd", "a-a-a-a-a"]);
@@ -195,6 +195,44 @@ pub enum DebugStatus {
     RequestedStateParts,
 }
 
+/// Request for the status of the data scrubber.
+#[derive(Debug)]
+pub struct DebugScrubberStatus;
+
+impl actix::Message for DebugScrubberStatus {
+    type Result = ScrubberStatusView;
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ScrubberCheckView {
+    pub check: String,
+    /// Where the current pass of the check is, e.g. the shard and root of the
+    /// trie being walked or the height of the block.
+    pub position: Option<String>,
+    pub entries_checked: u64,
+    pub passes_completed: u64,
+    pub corruptions_found: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ScrubberFindingView {
+    pub time: Utc,
+    pub check: String,
+    /// The corrupted entry, e.g. the shard and hash of a trie node.
+    pub entry: String,
+    pub error: String,
+    /// Outcome of the repair, if one was attempted.
+    pub repair: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ScrubberStatusView {
+    pub enabled: bool,
+    pub checks: Vec<ScrubberCheckView>,
+    /// Most recent corruptions found, oldest first.
+    pub findings: Vec<ScrubberFindingView>,
+}
+
 impl actix::Message for DebugStatus {
     type Result = Result<DebugStatusResponse, StatusError>;
 }
@@ -1,5 +1,6 @@
 use actix::Message;
 use near_chain_configs::{ClientConfig, ProtocolConfigView};
+use near_crypto::PublicKey;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
@@ -10,10 +11,11 @@ use near_primitives::types::{
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, GasPriceView,
-    LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView, QueryRequest,
-    QueryResponse, ReceiptView, SplitStorageInfoView, StateChangesKindsView,
-    StateChangesRequestView, StateChangesView, StateSyncStatusView, SyncStatusView, TxStatusView,
+    AccountActivityCursor, AccountActivityView, BlockView, ChunkView, EpochValidatorInfo,
+    ExecutionOutcomeWithIdView, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
+    MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView, SplitStorageInfoView,
+    StateChangesKindsView, StateChangesRequestView, StateChangesView, StateSyncStatusView,
+    SyncStatusView, TxStatusView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use near_time::Duration;
@@ -619,6 +621,16 @@ impl Message for TxStatus {
     type Result = Result<TxStatusView, TxStatusError>;
 }
 
+/// Statuses of several transactions, looked up in a single request, e.g. for
+/// all the transactions followed by a WebSocket connection when a block is
+/// accepted. The statuses are returned in the order of the requests.
+#[derive(Debug)]
+pub struct TxStatuses(pub Vec<TxStatus>);
+
+impl Message for TxStatuses {
+    type Result = Vec<Result<TxStatusView, TxStatusError>>;
+}
+
 #[derive(Debug)]
 pub struct GetValidatorInfo {
     pub epoch_reference: EpochReference,
@@ -732,6 +744,62 @@ impl Message for GetStateChangesWithCauseInBlockForTrackedShards {
     type Result = Result<HashMap<ShardId, StateChangesView>, GetStateChangesError>;
 }
 
+/// Queries the index of transactions and receipts which touched an account.
+#[derive(Debug)]
+pub struct GetAccountActivity {
+    pub account_id: AccountId,
+    /// If set, only the activity which changed this access key of the account is returned.
+    pub public_key: Option<PublicKey>,
+    pub from_height: Option<BlockHeight>,
+    pub to_height: Option<BlockHeight>,
+    pub cursor: Option<AccountActivityCursor>,
+    pub limit: usize,
+}
+
+#[derive(Debug)]
+pub struct AccountActivityResponse {
+    pub activity: Vec<AccountActivityView>,
+    /// Cursor at which the next query should continue, if there is more activity.
+    pub next_cursor: Option<AccountActivityCursor>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetAccountActivityError {
+    #[error("Account activity index is not enabled on this node")]
+    IndexDisabled,
+    #[error("Account activity below height {tail_height} has been garbage collected")]
+    GarbageCollected { tail_height: BlockHeight },
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for GetAccountActivityError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => {
+                Self::IOError { error_message: error.to_string() }
+            }
+            _ => Self::Unreachable { error_message: error.to_string() },
+        }
+    }
+}
+
+impl From<std::io::Error> for GetAccountActivityError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IOError { error_message: error.to_string() }
+    }
+}
+
+impl Message for GetAccountActivity {
+    type Result = Result<AccountActivityResponse, GetAccountActivityError>;
+}
+
 #[derive(Debug)]
 pub struct GetExecutionOutcome {
     pub id: TransactionOrReceiptId,
@@ -1009,6 +1077,13 @@ pub enum SandboxMessage {
     SandboxPatchStateStatus,
     SandboxFastForward(near_primitives::types::BlockHeightDelta),
     SandboxFastForwardStatus,
+    SandboxSnapshot,
+    SandboxRevert(u64),
+    SandboxImpersonateAccounts(Vec<AccountId>),
+    /// Timestamp of the next block in nanoseconds.
+    SandboxSetNextBlockTimestamp(u64),
+    /// Time to add to the timestamps of the next blocks in nanoseconds.
+    SandboxTimeWarp(u64),
 }
 
 #[cfg(feature = "sandbox")]
@@ -1017,6 +1092,12 @@ pub enum SandboxResponse {
     SandboxPatchStateFinished(bool),
     SandboxFastForwardFinished(bool),
     SandboxFastForwardFailed(String),
+    SandboxSnapshotTaken(u64),
+    SandboxSnapshotFailed(String),
+    SandboxRevertFinished,
+    SandboxRevertFailed(String),
+    SandboxTimeUpdated,
+    SandboxTimeUpdateFailed(String),
     SandboxNoResponse,
 }
 #[cfg(feature = "sandbox")]
@@ -11,12 +11,15 @@ use crate::stateless_validation::partial_witness::partial_witness_actor::Partial
 use crate::sync::block::BlockSync;
 use crate::sync::header::HeaderSync;
 use crate::sync::state::{StateSync, StateSyncResult};
+use crate::transaction_pool_snapshot::load_saved_transactions;
 use crate::{metrics, SyncStatus};
 use itertools::Itertools;
 use near_async::futures::{AsyncComputationSpawner, FutureSpawner};
 use near_async::messaging::IntoSender;
 use near_async::messaging::{CanSend, Sender};
 use near_async::time::{Clock, Duration, Instant};
+#[cfg(feature = "sandbox")]
+use near_async::time::{FakeClock, Utc};
 use near_chain::chain::{
     ApplyChunksDoneMessage, BlockCatchUpRequest, BlockMissingChunks, BlocksCatchUpState,
     VerifyBlockHashAndSignatureResult,
@@ -132,6 +135,19 @@ pub struct Client {
     /// Fast Forward accrued delta height used to calculate fast forwarded timestamps for each block.
     #[cfg(feature = "sandbox")]
     pub(crate) accrued_fastforward_delta: near_primitives::types::BlockHeightDelta,
+    /// Snapshots taken by sandbox_snapshot requests, ordered by their ids.
+    #[cfg(feature = "sandbox")]
+    sandbox_snapshots: Vec<SandboxSnapshot>,
+    /// Id of the next sandbox snapshot.
+    #[cfg(feature = "sandbox")]
+    next_sandbox_snapshot_id: u64,
+    /// Time added to the timestamps of produced blocks by sandbox_time_warp and
+    /// sandbox_set_next_block_timestamp requests.
+    #[cfg(feature = "sandbox")]
+    sandbox_time_offset: Duration,
+    /// Timestamp of the next produced block set by a sandbox_set_next_block_timestamp request.
+    #[cfg(feature = "sandbox")]
+    sandbox_next_block_timestamp: Option<Utc>,
 
     pub clock: Clock,
     pub config: ClientConfig,
@@ -200,6 +216,20 @@ pub struct Client {
     chunk_distribution_network: Option<ChunkDistributionNetwork>,
     /// Upgrade schedule which determines when the client starts voting for new protocol versions.
     upgrade_schedule: ProtocolUpgradeVotingSchedule,
+    /// Transactions loaded from the saved transaction pool which are yet to be submitted again.
+    transactions_to_restore: Vec<SignedTransaction>,
+    /// Hashes of the transactions in the saved transaction pool, so that only the changes of the
+    /// pool are written when it is saved again. `None` until the pool is saved for the first time.
+    saved_transactions: Option<HashSet<CryptoHash>>,
+}
+
+/// Point of the chain that sandbox can be reverted to.
+#[cfg(feature = "sandbox")]
+struct SandboxSnapshot {
+    id: u64,
+    head: CryptoHash,
+    /// Transactions which were in the pool when the snapshot was taken.
+    transactions: Vec<SignedTransaction>,
 }
 
 impl AsRef<Client> for Client {
@@ -267,6 +297,7 @@ impl Client {
             save_trie_changes: config.save_trie_changes,
             background_migration_threads: config.client_background_migration_threads,
             resharding_config: config.resharding_config.clone(),
+            save_account_activity: config.save_account_activity,
         };
         let chain = Chain::new(
             clock.clone(),
@@ -282,8 +313,19 @@ impl Client {
             resharding_sender.clone(),
         )?;
         chain.init_flat_storage()?;
-        let sharded_tx_pool =
-            ShardedTransactionPool::new(rng_seed, config.transaction_pool_size_limit);
+        // Sandbox snapshots are lost on restart, so the data kept for them isn't needed anymore.
+        #[cfg(feature = "sandbox")]
+        chain.chain_store().save_sandbox_pinned_height(None)?;
+        let sharded_tx_pool = ShardedTransactionPool::new(
+            rng_seed,
+            config.transaction_pool_size_limit,
+            config.transaction_pool_ordering,
+        );
+        let transactions_to_restore = if config.save_transaction_pool {
+            load_saved_transactions(&chain.chain_store.store())
+        } else {
+            vec![]
+        };
         let sync_status = SyncStatus::AwaitingPeers;
         let epoch_sync = EpochSync::new(
             clock.clone(),
@@ -362,6 +404,14 @@ impl Client {
             produce_invalid_tx_in_chunks: false,
             #[cfg(feature = "sandbox")]
             accrued_fastforward_delta: 0,
+            #[cfg(feature = "sandbox")]
+            sandbox_snapshots: vec![],
+            #[cfg(feature = "sandbox")]
+            next_sandbox_snapshot_id: 0,
+            #[cfg(feature = "sandbox")]
+            sandbox_time_offset: Duration::ZERO,
+            #[cfg(feature = "sandbox")]
+            sandbox_next_block_timestamp: None,
             clock: clock.clone(),
             config,
             sync_status,
@@ -403,6 +453,8 @@ impl Client {
             partial_witness_adapter,
             chunk_distribution_network,
             upgrade_schedule,
+            transactions_to_restore,
+            saved_transactions: None,
         })
     }
 
@@ -705,11 +757,6 @@ impl Client {
             prev_next_bp_hash
         };
 
-        #[cfg(feature = "sandbox")]
-        let sandbox_delta_time = Some(self.sandbox_delta_time());
-        #[cfg(not(feature = "sandbox"))]
-        let sandbox_delta_time = None;
-
         // Get block extra from previous block.
         let block_merkle_tree = self.chain.chain_store().get_block_merkle_tree(&prev_hash)?;
         let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
@@ -793,6 +840,11 @@ impl Client {
         let next_epoch_protocol_version =
             self.epoch_manager.get_epoch_protocol_version(&next_epoch_id)?;
 
+        #[cfg(feature = "sandbox")]
+        let (clock, sandbox_delta_time) = self.sandbox_block_clock();
+        #[cfg(not(feature = "sandbox"))]
+        let (clock, sandbox_delta_time) = (self.clock.clone(), None);
+
         let block = Block::produce(
             this_epoch_protocol_version,
             next_epoch_protocol_version,
@@ -816,7 +868,7 @@ impl Client {
             &*validator_signer,
             next_bp_hash,
             block_merkle_root,
-            self.clock.clone(),
+            clock,
             sandbox_delta_time,
         );
 
@@ -1568,6 +1620,7 @@ impl Client {
     }
 
     /// Gets the advanced timestamp delta in nanoseconds for sandbox once it has been fast-forwarded
+    /// or its time has been warped
     #[cfg(feature = "sandbox")]
     pub fn sandbox_delta_time(&self) -> Duration {
         let avg_block_prod_time = (self.config.min_block_production_delay.whole_nanoseconds()
@@ -1583,7 +1636,114 @@ impl Client {
                 )
             });
 
-        Duration::nanoseconds(ns)
+        Duration::nanoseconds(ns) + self.sandbox_time_offset
+    }
+
+    /// Returns the clock and the time delta to produce the next block with. If the timestamp of
+    /// the next block was set, the block gets exactly that timestamp and the timestamps of the
+    /// following blocks continue from it.
+    #[cfg(feature = "sandbox")]
+    fn sandbox_block_clock(&mut self) -> (Clock, Option<Duration>) {
+        match self.sandbox_next_block_timestamp.take() {
+            Some(timestamp) => {
+                self.sandbox_time_offset +=
+                    timestamp - (self.clock.now_utc() + self.sandbox_delta_time());
+                (FakeClock::new(timestamp).clock(), Some(Duration::ZERO))
+            }
+            None => (self.clock.clone(), Some(self.sandbox_delta_time())),
+        }
+    }
+
+    /// Moves the timestamps of the blocks produced from now on forward by the given delta.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_time_warp(&mut self, delta: Duration) -> Result<(), Error> {
+        if delta.is_negative() {
+            return Err(Error::Other("Cannot warp time backwards".to_string()));
+        }
+        self.sandbox_time_offset += delta;
+        Ok(())
+    }
+
+    /// Sets the timestamp of the next produced block, which has to be after the timestamp of the
+    /// head of the chain.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_set_next_block_timestamp(&mut self, timestamp: Utc) -> Result<(), Error> {
+        let head = self.chain.head()?;
+        let head_timestamp = self.chain.get_block_header(&head.last_block_hash)?.timestamp();
+        if timestamp <= head_timestamp {
+            return Err(Error::Other(format!(
+                "Timestamp {} is not after the timestamp of the head {}",
+                timestamp, head_timestamp
+            )));
+        }
+        self.sandbox_next_block_timestamp = Some(timestamp);
+        Ok(())
+    }
+
+    /// Takes a snapshot of the current head and transaction pool and returns its id, which
+    /// `sandbox_revert` takes to revert to it.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_snapshot(&mut self) -> Result<u64, Error> {
+        let head = self.chain.head()?;
+        let id = self.next_sandbox_snapshot_id;
+        self.next_sandbox_snapshot_id += 1;
+        self.sandbox_snapshots.push(SandboxSnapshot {
+            id,
+            head: head.last_block_hash,
+            transactions: self.sharded_tx_pool.transactions().cloned().collect(),
+        });
+        // Producing blocks on top of the snapshotted head needs the blocks since the final one.
+        if self.chain.chain_store().sandbox_pinned_height()?.is_none() {
+            let final_head = self.chain.final_head()?;
+            self.chain.chain_store().save_sandbox_pinned_height(Some(final_head.height))?;
+        }
+        debug!(target: "client", id, height = head.height, "Sandbox snapshot taken");
+        Ok(id)
+    }
+
+    /// Reverts the chain, its state and the transaction pool to the given snapshot. The snapshot
+    /// and all the snapshots taken after it are dropped.
+    ///
+    /// Blocks produced after the revert continue from the snapshotted block at heights above the
+    /// latest known one, as the heights of the dropped blocks have already been used.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_revert(&mut self, id: u64) -> Result<(), Error> {
+        let Some(index) = self.sandbox_snapshots.iter().position(|snapshot| snapshot.id == id)
+        else {
+            return Err(Error::Other(format!("Unknown sandbox snapshot {}", id)));
+        };
+        let snapshot = self.sandbox_snapshots.remove(index);
+        self.sandbox_snapshots.truncate(index);
+        self.chain.sandbox_revert_head(&snapshot.head)?;
+
+        let latest_known = self.chain.mut_chain_store().get_latest_known()?;
+        let new_latest_known =
+            LatestKnown { height: latest_known.height + 1, seen: latest_known.seen };
+        self.chain.mut_chain_store().save_latest_known(new_latest_known.clone())?;
+        self.sandbox_update_tip(new_latest_known.height)?;
+        self.shards_manager_adapter.send(ShardsManagerRequestFromClient::UpdateChainHeads {
+            head: self.chain.head()?,
+            header_head: self.chain.header_head()?,
+        });
+
+        self.sharded_tx_pool.clear();
+        let signer = self.validator_signer.get();
+        for transaction in &snapshot.transactions {
+            if let Err(err) = self.process_tx_internal(transaction, true, false, &signer) {
+                debug!(
+                    target: "client",
+                    tx_hash = ?transaction.get_hash(),
+                    ?err,
+                    "Failed to restore transaction"
+                );
+            }
+        }
+
+        if self.sandbox_snapshots.is_empty() {
+            self.chain.chain_store().save_sandbox_pinned_height(None)?;
+        }
+        debug!(target: "client", id, head = ?snapshot.head, "Sandbox reverted to snapshot");
+        Ok(())
     }
 
     pub fn send_block_approval(
@@ -2322,7 +2482,7 @@ impl Client {
     }
 
     /// Process transaction and either add it to the mempool or return to redirect to another validator.
-    fn process_tx_internal(
+    pub(crate) fn process_tx_internal(
         &mut self,
         tx: &SignedTransaction,
         is_forwarded: bool,
@@ -2419,6 +2579,10 @@ impl Client {
                             trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Duplicate transaction, not forwarding it.");
                             return Ok(ProcessTxResponse::ValidTx);
                         }
+                        InsertTransactionResult::Underpriced => {
+                            debug!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Transaction with the same nonce and at least the same priority is in the pool, dropping the transaction.");
+                            return Ok(ProcessTxResponse::Underpriced);
+                        }
                         InsertTransactionResult::NoSpaceLeft => {
                             if is_forwarded {
                                 trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Transaction pool is full, dropping the transaction.");
@@ -87,6 +87,11 @@ const STATUS_WAIT_TIME_MULTIPLIER: i32 = 10;
 /// the current `head`
 const HEAD_STALL_MULTIPLIER: u32 = 4;
 
+/// Number of accepted block notifications buffered for each subscriber.
+/// Subscribers that fall further behind miss the oldest notifications rather
+/// than slowing down block processing.
+const ACCEPTED_BLOCKS_CHANNEL_CAPACITY: usize = 128;
+
 pub type ClientActor = ActixWrapper<ClientActorInner>;
 
 /// Returns random seed sampled from the current thread
@@ -252,6 +257,8 @@ pub struct ClientActorInner {
 
     // Last time when log_summary method was called.
     log_summary_timer_next_attempt: near_async::time::Utc,
+    /// Next time the transaction pool should be saved to the database.
+    transaction_pool_save_next_attempt: near_async::time::Utc,
 
     doomslug_timer_next_attempt: near_async::time::Utc,
     sync_timer_next_attempt: near_async::time::Utc,
@@ -267,6 +274,9 @@ pub struct ClientActorInner {
 
     /// Manages updating the config.
     config_updater: Option<ConfigUpdater>,
+
+    /// Notifies subscribers (e.g. JSON RPC WebSocket sessions) about accepted blocks.
+    accepted_blocks_sender: broadcast::Sender<CryptoHash>,
 }
 
 impl messaging::Actor for ClientActorInner {
@@ -357,6 +367,8 @@ impl ClientActorInner {
         let info_helper = InfoHelper::new(clock.clone(), telemetry_sender, &client.config);
 
         let now = clock.now_utc();
+        let transaction_pool_save_next_attempt = now + client.config.transaction_pool_save_period;
+        let (accepted_blocks_sender, _) = broadcast::channel(ACCEPTED_BLOCKS_CHANNEL_CAPACITY);
         Ok(ClientActorInner {
             clock,
             adv,
@@ -380,6 +392,7 @@ impl ClientActorInner {
             info_helper,
             block_production_next_attempt: now,
             log_summary_timer_next_attempt: now,
+            transaction_pool_save_next_attempt,
             doomslug_timer_next_attempt: now,
             sync_timer_next_attempt: now,
             sync_started: false,
@@ -388,6 +401,7 @@ impl ClientActorInner {
             shutdown_signal,
             config_updater,
             sync_jobs_sender,
+            accepted_blocks_sender,
         })
     }
 }
@@ -684,6 +698,64 @@ impl Handler<near_client_primitives::types::SandboxMessage> for ClientActorInner
                     self.fastforward_delta == 0,
                 )
             }
+            near_client_primitives::types::SandboxMessage::SandboxSnapshot => {
+                match self.client.sandbox_snapshot() {
+                    Ok(id) => {
+                        near_client_primitives::types::SandboxResponse::SandboxSnapshotTaken(id)
+                    }
+                    Err(err) => {
+                        near_client_primitives::types::SandboxResponse::SandboxSnapshotFailed(
+                            err.to_string(),
+                        )
+                    }
+                }
+            }
+            near_client_primitives::types::SandboxMessage::SandboxRevert(id) => {
+                if self.fastforward_delta > 0 {
+                    return near_client_primitives::types::SandboxResponse::SandboxRevertFailed(
+                        "Cannot revert while a fast_forward request is going on.".to_string(),
+                    );
+                }
+                match self.client.sandbox_revert(id) {
+                    Ok(()) => near_client_primitives::types::SandboxResponse::SandboxRevertFinished,
+                    Err(err) => {
+                        near_client_primitives::types::SandboxResponse::SandboxRevertFailed(
+                            err.to_string(),
+                        )
+                    }
+                }
+            }
+            near_client_primitives::types::SandboxMessage::SandboxImpersonateAccounts(
+                account_ids,
+            ) => {
+                near_primitives::sandbox::impersonation::set_impersonated_accounts(account_ids);
+                near_client_primitives::types::SandboxResponse::SandboxNoResponse
+            }
+            near_client_primitives::types::SandboxMessage::SandboxSetNextBlockTimestamp(
+                timestamp,
+            ) => {
+                let result = Utc::from_unix_timestamp_nanos(timestamp as i128)
+                    .map_err(|err| Error::Other(err.to_string()))
+                    .and_then(|timestamp| self.client.sandbox_set_next_block_timestamp(timestamp));
+                match result {
+                    Ok(()) => near_client_primitives::types::SandboxResponse::SandboxTimeUpdated,
+                    Err(err) => {
+                        near_client_primitives::types::SandboxResponse::SandboxTimeUpdateFailed(
+                            err.to_string(),
+                        )
+                    }
+                }
+            }
+            near_client_primitives::types::SandboxMessage::SandboxTimeWarp(delta) => {
+                match self.client.sandbox_time_warp(Duration::nanoseconds(delta as i64)) {
+                    Ok(()) => near_client_primitives::types::SandboxResponse::SandboxTimeUpdated,
+                    Err(err) => {
+                        near_client_primitives::types::SandboxResponse::SandboxTimeUpdateFailed(
+                            err.to_string(),
+                        )
+                    }
+                }
+            }
         }
     }
 }
@@ -831,6 +903,36 @@ fn make_known_producer(
     }
 }
 
+/// Subscribes to hashes of blocks accepted by the client.
+///
+/// Every block accepted by the client, whether it became the new head or not,
+/// is reported once to every subscriber.  The channel is bounded by
+/// `ACCEPTED_BLOCKS_CHANNEL_CAPACITY`; a subscriber that falls behind gets
+/// `RecvError::Lagged` instead of slowing down the client.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "AcceptedBlocksReceiver")]
+pub struct SubscribeAcceptedBlocks;
+
+#[derive(actix::MessageResponse, Debug)]
+pub struct AcceptedBlocksReceiver(pub broadcast::Receiver<CryptoHash>);
+
+impl Handler<SubscribeAcceptedBlocks> for ClientActorInner {
+    fn handle(&mut self, _msg: SubscribeAcceptedBlocks) -> AcceptedBlocksReceiver {
+        AcceptedBlocksReceiver(self.accepted_blocks_sender.subscribe())
+    }
+}
+
+/// Saves the transaction pool to the database, e.g. before the node shuts down.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub struct SaveTransactionPool;
+
+impl Handler<SaveTransactionPool> for ClientActorInner {
+    fn handle(&mut self, _msg: SaveTransactionPool) {
+        self.save_transaction_pool();
+    }
+}
+
 impl Handler<GetNetworkInfo> for ClientActorInner {
     fn handle(&mut self, _msg: GetNetworkInfo) -> Result<NetworkInfoResponse, String> {
         Ok(NetworkInfoResponse {
@@ -1261,10 +1363,28 @@ impl ClientActorInner {
             "log_summary",
         );
         delay = core::cmp::min(delay, self.log_summary_timer_next_attempt - now);
+
+        if self.client.config.save_transaction_pool {
+            self.client.restore_transaction_pool();
+            self.transaction_pool_save_next_attempt = self.run_timer(
+                self.client.config.transaction_pool_save_period,
+                self.transaction_pool_save_next_attempt,
+                ctx,
+                |act, _ctx| act.save_transaction_pool(),
+                "save_transaction_pool",
+            );
+            delay = core::cmp::min(delay, self.transaction_pool_save_next_attempt - now);
+        }
         timer.observe_duration();
         delay
     }
 
+    fn save_transaction_pool(&mut self) {
+        if let Err(err) = self.client.save_transaction_pool() {
+            tracing::error!(target: "client", ?err, "Failed to save transaction pool");
+        }
+    }
+
     /// "Unfinished" blocks means that blocks that client has started the processing and haven't
     /// finished because it was waiting for applying chunks to be done. This function checks
     /// if there are any "unfinished" blocks that are ready to be processed again and finish processing
@@ -1444,6 +1564,8 @@ impl ClientActorInner {
             self.send_chunks_metrics(&block);
             self.send_block_metrics(&block);
             self.check_send_announce_account(*block.header().last_final_block(), signer);
+            // Sending fails only if there are no subscribers, which is fine.
+            let _ = self.accepted_blocks_sender.send(accepted_block);
         }
     }
 
@@ -708,3 +708,47 @@ pub(crate) static DECODE_PARTIAL_WITNESS_ACCESSED_CONTRACTS_STATE_COUNT: LazyLoc
         )
         .unwrap()
     });
+
+pub(crate) static TRANSACTION_POOL_SAVED_TRANSACTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_transaction_pool_saved_transactions",
+        "Number of transactions saved to the database in the last transaction pool snapshot",
+    )
+    .unwrap()
+});
+
+pub(crate) static SCRUBBER_ENTRIES_CHECKED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_scrubber_entries_checked_total",
+        "Number of database entries checked by the data scrubber, by check",
+        &["check"],
+    )
+    .unwrap()
+});
+
+pub(crate) static SCRUBBER_PASSES_COMPLETED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_scrubber_passes_completed_total",
+        "Number of full passes completed by the data scrubber, by check",
+        &["check"],
+    )
+    .unwrap()
+});
+
+pub(crate) static SCRUBBER_CORRUPTIONS_FOUND: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_scrubber_corruptions_found_total",
+        "Number of corrupted database entries found by the data scrubber, by check",
+        &["check"],
+    )
+    .unwrap()
+});
+
+pub(crate) static SCRUBBER_REPAIRS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_scrubber_repairs_total",
+        "Number of repairs attempted by the data scrubber, by check and result",
+        &["check", "result"],
+    )
+    .unwrap()
+});
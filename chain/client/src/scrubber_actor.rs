@@ -0,0 +1,886 @@
+//! Data scrubber, which checks the integrity of the database of a running node
+//! in the background.
+//!
+//! The scrubber runs three checks, each making a bounded amount of progress at
+//! every step so that the load on the database stays low:
+//!
+//! * `state` walks the tries of the tracked shards from the state roots at the
+//!   final head and checks that every node and value is present, has positive
+//!   reference count and is stored under its hash.
+//! * `flat_state` compares values in `FlatState` with values looked up in the
+//!   trie at the flat storage head.
+//! * `chain` checks that the blocks and their headers are stored under their
+//!   hashes and are internally consistent, and that the new chunks match the
+//!   `ChunkExtra` of the previous block.
+//!
+//! Corruptions are reported as metrics, logged and listed on the
+//! `/debug/api/scrubber_status` debug page.  Optionally `FlatState` values are
+//! repaired from the trie and trie nodes are restored from the state parts in
+//! the external storage configured for state sync.  Since nodes of the trie are
+//! addressed by their hash, a node can be restored from the state parts of any
+//! epoch containing it.  Only the state part whose range of keys covers the
+//! path of the node is downloaded, in the background so that the actor isn't
+//! blocked.  Restoring nodes from peers isn't supported because the network
+//! protocol has no way to request individual trie nodes.
+//!
+//! `FlatState` values are repaired through `FlatStorage`, which is locked while
+//! the value is written, so the flat head can't move under the repair.  Only
+//! trie nodes whose reference count is intact are restored: the reference
+//! count of a missing node is lost and can't be recovered from the state parts,
+//! and guessing it would either leak the node or let it be garbage collected
+//! while still referenced.  Restored nodes are written with a merge replacing
+//! only their data, so updates of the reference count by block processing or
+//! garbage collection racing with the repair aren't lost.
+
+use crate::metrics;
+use crate::sync::external::{
+    create_bucket_readonly, external_storage_location, ExternalConnection, StateFileType,
+};
+use borsh::BorshDeserialize;
+use near_async::futures::{
+    DelayedActionRunner, DelayedActionRunnerExt, FutureSpawner, FutureSpawnerExt,
+};
+use near_async::messaging::{Actor, Handler};
+use near_async::time::Clock;
+use near_chain::validate::validate_chunk_with_chunk_extra;
+use near_chain::{Chain, ChainStore, ChainStoreAccess};
+use near_chain_configs::{
+    ExternalStorageConfig, ExternalStorageLocation, ScrubberConfig, StateSyncConfig, SyncConfig,
+};
+use near_client_primitives::debug::{
+    DebugScrubberStatus, ScrubberCheckView, ScrubberFindingView, ScrubberStatusView,
+};
+use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::block::{Block, BlockHeader};
+use near_primitives::challenge::PartialState;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::state::{FlatStateValue, ValueRef};
+use near_primitives::state_sync::ShardStateSyncResponseHeader;
+use near_primitives::types::{BlockHeight, EpochId, StateRoot};
+use near_store::adapter::StoreAdapter;
+use near_store::flat::{FlatStorageManager, FlatStorageStatus};
+use near_store::{
+    DBCol, KeyLookupMode, NibbleSlice, RawTrieNode, RawTrieNodeWithSize, Store, Trie, TrieDBStorage,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{mpsc, Arc};
+
+/// Number of most recent findings listed on the debug page.
+const MAX_FINDINGS: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+enum Check {
+    State,
+    FlatState,
+    Chain,
+}
+
+/// Progress of a check, shown on the debug page.
+#[derive(Default)]
+struct CheckProgress {
+    entries_checked: u64,
+    passes_completed: u64,
+    corruptions_found: u64,
+}
+
+/// Entry of the trie still to be checked by a walk, together with the path
+/// to it in nibbles.  The path of a value is the key it's stored under.
+enum StateEntry {
+    Node(CryptoHash, Vec<u8>),
+    Value(ValueRef, Vec<u8>),
+}
+
+/// Walk over the trie of a shard from the state root at a block.
+struct StateWalk {
+    shard_uid: ShardUId,
+    block_hash: CryptoHash,
+    state_root: StateRoot,
+    stack: Vec<StateEntry>,
+}
+
+/// Scan over the `FlatState` values of a shard.
+struct FlatStateScan {
+    shard_uid: ShardUId,
+    /// Key to continue the scan from.
+    next_key: Option<Vec<u8>>,
+}
+
+/// Trie node or value with corrupted data but a positive reference count,
+/// waiting to be restored.
+struct StateRepair {
+    shard_uid: ShardUId,
+    hash: CryptoHash,
+    /// Path to the node in nibbles, which determines the state part to fetch
+    /// it from.
+    path: Vec<u8>,
+}
+
+/// Data of a trie node or value fetched from the external storage, or the
+/// reason why it couldn't be fetched.
+struct FetchedRepair {
+    repair: StateRepair,
+    result: anyhow::Result<Vec<u8>>,
+}
+
+/// An actor which checks the integrity of the database in the background.
+/// The actor runs periodically, as determined by `step_period`, and checks at
+/// most `max_entries_per_step` entries at each step.
+pub struct ScrubberActor {
+    clock: Clock,
+    store: Store,
+    chain_store: ChainStore,
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    flat_storage_manager: FlatStorageManager,
+    config: ScrubberConfig,
+    chain_id: String,
+    external: Option<ExternalConnection>,
+    /// Runs the requests to the external storage.
+    future_spawner: Arc<dyn FutureSpawner>,
+    /// Check to run at the next step; the checks take turns.
+    next_check: usize,
+    progress: HashMap<Check, CheckProgress>,
+    state_walk: Option<StateWalk>,
+    /// Index of the shard to walk next in the shard layout of the final head.
+    next_state_shard: usize,
+    flat_state_scan: Option<FlatStateScan>,
+    next_flat_state_shard: usize,
+    next_chain_height: Option<BlockHeight>,
+    state_repairs: Vec<StateRepair>,
+    /// Trie nodes and values being fetched from the external storage.
+    repairs_in_flight: HashSet<(ShardUId, CryptoHash)>,
+    fetched_sender: mpsc::Sender<FetchedRepair>,
+    fetched_receiver: mpsc::Receiver<FetchedRepair>,
+    findings: VecDeque<ScrubberFindingView>,
+}
+
+impl ScrubberActor {
+    pub fn new(
+        clock: Clock,
+        store: Store,
+        genesis_height: BlockHeight,
+        epoch_manager: Arc<dyn EpochManagerAdapter>,
+        flat_storage_manager: FlatStorageManager,
+        config: ScrubberConfig,
+        chain_id: String,
+        state_sync_config: &StateSyncConfig,
+        future_spawner: Arc<dyn FutureSpawner>,
+    ) -> Self {
+        let external = match &state_sync_config.sync {
+            SyncConfig::ExternalStorage(ExternalStorageConfig { location, .. })
+                if config.repair_state_from_external_storage =>
+            {
+                create_external_connection(location)
+            }
+            _ => None,
+        };
+        let (fetched_sender, fetched_receiver) = mpsc::channel();
+        ScrubberActor {
+            clock,
+            store: store.clone(),
+            chain_store: ChainStore::new(store, genesis_height, true),
+            epoch_manager,
+            flat_storage_manager,
+            config,
+            chain_id,
+            external,
+            future_spawner,
+            next_check: 0,
+            progress: HashMap::new(),
+            state_walk: None,
+            next_state_shard: 0,
+            flat_state_scan: None,
+            next_flat_state_shard: 0,
+            next_chain_height: None,
+            state_repairs: vec![],
+            repairs_in_flight: HashSet::new(),
+            fetched_sender,
+            fetched_receiver,
+            findings: VecDeque::new(),
+        }
+    }
+
+    fn step(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
+        let checks = [Check::State, Check::FlatState, Check::Chain];
+        let check = checks[self.next_check % checks.len()];
+        self.next_check = self.next_check.wrapping_add(1);
+        self.run_check(check);
+
+        ctx.run_later("data scrubber", self.config.step_period, move |act, ctx| {
+            act.step(ctx);
+        });
+    }
+
+    /// Makes a step of the check followed by the repairs of the trie nodes it
+    /// found to be corrupted.  Returns the number of entries checked.
+    fn run_check(&mut self, check: Check) -> u64 {
+        while let Ok(fetched) = self.fetched_receiver.try_recv() {
+            self.restore_state_value(fetched);
+        }
+        let budget = self.config.max_entries_per_step;
+        let result = match check {
+            Check::State => self.check_state(budget),
+            Check::FlatState => self.check_flat_state(budget),
+            Check::Chain => self.check_chain(budget),
+        };
+        let entries_checked = match result {
+            Ok(entries_checked) => {
+                self.progress.entry(check).or_default().entries_checked += entries_checked;
+                let check: &str = check.into();
+                metrics::SCRUBBER_ENTRIES_CHECKED
+                    .with_label_values(&[check])
+                    .inc_by(entries_checked);
+                entries_checked
+            }
+            Err(err) => {
+                tracing::warn!(target: "scrubber", ?check, ?err, "Error in data scrubber step");
+                0
+            }
+        };
+        if !self.state_repairs.is_empty() {
+            self.repair_state();
+        }
+        entries_checked
+    }
+
+    /// Runs each check until it completes a full pass, or stops making
+    /// progress, without waiting between the steps, and waits for the repairs
+    /// of the trie nodes to finish.  Returns the status of the scrubber
+    /// afterwards.
+    ///
+    /// Meant for tests, which drive the scrubber without starting the actor.
+    pub fn run_full_pass(&mut self) -> ScrubberStatusView {
+        for check in [Check::State, Check::FlatState, Check::Chain] {
+            let passes_completed = |act: &Self| {
+                act.progress.get(&check).map_or(0, |progress| progress.passes_completed)
+            };
+            let passes_before = passes_completed(self);
+            loop {
+                let entries_checked = self.run_check(check);
+                if passes_completed(self) > passes_before || entries_checked == 0 {
+                    break;
+                }
+            }
+        }
+        while !self.repairs_in_flight.is_empty() {
+            let Ok(fetched) = self.fetched_receiver.recv() else {
+                break;
+            };
+            self.restore_state_value(fetched);
+        }
+        self.status()
+    }
+
+    /// Records a corrupted entry found by a check.
+    fn report(&mut self, check: Check, entry: String, error: String) {
+        tracing::error!(target: "scrubber", ?check, entry, error, "Data scrubber found corruption");
+        self.progress.entry(check).or_default().corruptions_found += 1;
+        let check: &str = check.into();
+        metrics::SCRUBBER_CORRUPTIONS_FOUND.with_label_values(&[check]).inc();
+        if self.findings.len() == MAX_FINDINGS {
+            self.findings.pop_front();
+        }
+        self.findings.push_back(ScrubberFindingView {
+            time: self.clock.now_utc(),
+            check: check.to_string(),
+            entry,
+            error,
+            repair: None,
+        });
+    }
+
+    /// Records the outcome of the repair of the most recent finding for the
+    /// entry.
+    fn report_repair(&mut self, check: Check, entry: &str, result: Result<(), String>) {
+        let check: &str = check.into();
+        let label = if result.is_ok() { "ok" } else { "error" };
+        metrics::SCRUBBER_REPAIRS.with_label_values(&[check, label]).inc();
+        let repair = match result {
+            Ok(()) => "repaired".to_string(),
+            Err(err) => {
+                tracing::error!(
+                    target: "scrubber",
+                    check,
+                    entry,
+                    err,
+                    "Data scrubber repair failed"
+                );
+                format!("failed: {err}")
+            }
+        };
+        let finding = self.findings.iter_mut().rev().find(|finding| finding.entry == entry);
+        if let Some(finding) = finding {
+            finding.repair = Some(repair);
+        }
+    }
+
+    fn pass_completed(&mut self, check: Check) {
+        self.progress.entry(check).or_default().passes_completed += 1;
+        let check: &str = check.into();
+        metrics::SCRUBBER_PASSES_COMPLETED.with_label_values(&[check]).inc();
+    }
+
+    /// Returns the shards of the final head together with the hash of the
+    /// final block.
+    fn final_head_shards(&self) -> anyhow::Result<(CryptoHash, Vec<ShardUId>)> {
+        let final_head = self.chain_store.final_head()?;
+        let shard_layout = self.epoch_manager.get_shard_layout(&final_head.epoch_id)?;
+        Ok((final_head.last_block_hash, shard_layout.shard_uids().collect()))
+    }
+
+    /// Continues the walk over the trie of a tracked shard, starting a new one
+    /// for the next shard if there is none in progress.
+    fn check_state(&mut self, budget: u64) -> anyhow::Result<u64> {
+        if self.state_walk.is_none() {
+            let (block_hash, shard_uids) = self.final_head_shards()?;
+            // Skip the shards that aren't tracked, i.e. have no chunk extra.
+            for _ in 0..shard_uids.len() {
+                let shard_uid = shard_uids[self.next_state_shard % shard_uids.len()];
+                self.next_state_shard = self.next_state_shard.wrapping_add(1);
+                let Ok(chunk_extra) = self.chain_store.get_chunk_extra(&block_hash, &shard_uid)
+                else {
+                    continue;
+                };
+                let state_root = *chunk_extra.state_root();
+                let stack = if state_root == Trie::EMPTY_ROOT {
+                    vec![]
+                } else {
+                    vec![StateEntry::Node(state_root, vec![])]
+                };
+                self.state_walk = Some(StateWalk { shard_uid, block_hash, state_root, stack });
+                break;
+            }
+        }
+        let Some(mut walk) = self.state_walk.take() else {
+            return Ok(0);
+        };
+
+        let trie_store = self.store.trie_store();
+        let mut entries_checked = 0;
+        let mut corruptions = vec![];
+        while entries_checked < budget {
+            let Some(entry) = walk.stack.pop() else {
+                break;
+            };
+            entries_checked += 1;
+            let (entry_hash, length, path) = match entry {
+                StateEntry::Node(hash, path) => (hash, None, path),
+                StateEntry::Value(value_ref, path) => {
+                    (value_ref.hash, Some(value_ref.length), path)
+                }
+            };
+            // Errors come with whether the reference count of the cell is
+            // intact, so that its data can be restored.
+            let error = match trie_store.get_with_refcount(walk.shard_uid, &entry_hash) {
+                Err(err) => Some((format!("unreadable: {err}"), false)),
+                Ok(None) => Some(("missing".to_string(), false)),
+                Ok(Some((_, refcount))) if refcount <= 0 => {
+                    Some((format!("non-positive reference count {refcount}"), false))
+                }
+                Ok(Some((data, _))) => {
+                    let data_hash = hash(&data);
+                    if data_hash != entry_hash {
+                        Some((format!("hash mismatch, actual {data_hash}"), true))
+                    } else if length.is_some_and(|length| length as usize != data.len()) {
+                        Some((format!("length mismatch, actual {}", data.len()), true))
+                    } else if length.is_none() {
+                        match RawTrieNodeWithSize::try_from_slice(&data) {
+                            Ok(node) => {
+                                push_children(&mut walk.stack, &path, node.node);
+                                None
+                            }
+                            Err(err) => Some((format!("undecodable node: {err}"), true)),
+                        }
+                    } else {
+                        None
+                    }
+                }
+            };
+            if let Some((error, repairable)) = error {
+                corruptions.push((entry_hash, path, error, repairable));
+            }
+        }
+
+        if !corruptions.is_empty() {
+            // A node may be missing only because the block whose state is
+            // walked has been garbage collected in the meantime.  Garbage
+            // collection removes the chunk extra of the block before any of
+            // the nodes of its state, so if the chunk extra is still there the
+            // node is corrupted indeed.
+            if self.chain_store.get_chunk_extra(&walk.block_hash, &walk.shard_uid).is_err() {
+                tracing::debug!(
+                    target: "scrubber",
+                    shard_uid = %walk.shard_uid,
+                    block_hash = %walk.block_hash,
+                    "Abandoning walk over garbage collected state"
+                );
+                return Ok(entries_checked);
+            }
+            for (hash, path, error, repairable) in corruptions {
+                let entry = format!("{} {}", walk.shard_uid, hash);
+                self.report(Check::State, entry.clone(), error);
+                if self.external.is_none() {
+                    continue;
+                }
+                if repairable {
+                    let shard_uid = walk.shard_uid;
+                    self.state_repairs.push(StateRepair { shard_uid, hash, path });
+                } else {
+                    let error = "reference count is lost, the node can't be restored".to_string();
+                    self.report_repair(Check::State, &entry, Err(error));
+                }
+            }
+        }
+        if walk.stack.is_empty() {
+            tracing::debug!(
+                target: "scrubber",
+                shard_uid = %walk.shard_uid,
+                state_root = %walk.state_root,
+                "Finished walk over state"
+            );
+            self.pass_completed(Check::State);
+        } else {
+            self.state_walk = Some(walk);
+        }
+        Ok(entries_checked)
+    }
+
+    /// Continues the scan over the `FlatState` values of a shard, starting a
+    /// new one for the next shard if there is none in progress.
+    fn check_flat_state(&mut self, budget: u64) -> anyhow::Result<u64> {
+        let flat_store = self.store.flat_store();
+        if self.flat_state_scan.is_none() {
+            let (_, shard_uids) = self.final_head_shards()?;
+            for _ in 0..shard_uids.len() {
+                let shard_uid = shard_uids[self.next_flat_state_shard % shard_uids.len()];
+                self.next_flat_state_shard = self.next_flat_state_shard.wrapping_add(1);
+                if let FlatStorageStatus::Ready(_) =
+                    flat_store.get_flat_storage_status(shard_uid)?
+                {
+                    self.flat_state_scan = Some(FlatStateScan { shard_uid, next_key: None });
+                    break;
+                }
+            }
+        }
+        let Some(mut scan) = self.flat_state_scan.take() else {
+            return Ok(0);
+        };
+        let shard_uid = scan.shard_uid;
+        let FlatStorageStatus::Ready(status) = flat_store.get_flat_storage_status(shard_uid)?
+        else {
+            return Ok(0);
+        };
+        let flat_head = status.flat_head.hash;
+        let state_root = *self.chain_store.get_chunk_extra(&flat_head, &shard_uid)?.state_root();
+        let storage = Arc::new(TrieDBStorage::new(self.store.trie_store(), shard_uid));
+        let trie = Trie::new(storage, state_root, None);
+
+        let batch = flat_store
+            .iter_range(shard_uid, scan.next_key.as_deref(), None)
+            .take(budget as usize)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut mismatches = vec![];
+        for (key, value) in &batch {
+            let error = match trie.get_optimized_ref(key, KeyLookupMode::Trie) {
+                Ok(Some(trie_value)) => {
+                    if trie_value.into_value_ref() == value.to_value_ref() {
+                        continue;
+                    }
+                    ("value differs from the trie".to_string(), true)
+                }
+                Ok(None) => ("value missing from the trie".to_string(), true),
+                // The trie itself is corrupted, so the value can't be repaired.
+                Err(err) => (format!("trie lookup failed: {err}"), false),
+            };
+            mismatches.push((key.clone(), error));
+        }
+
+        // The flat storage head may have moved while the batch was checked, in
+        // which case the values can't be compared with the trie at the old
+        // head.  The batch is checked again at the next step.
+        let status = flat_store.get_flat_storage_status(shard_uid)?;
+        if !matches!(status, FlatStorageStatus::Ready(status) if status.flat_head.hash == flat_head)
+        {
+            self.flat_state_scan = Some(scan);
+            return Ok(0);
+        }
+
+        for (key, (error, repairable)) in mismatches {
+            let entry = format!("{} {}", shard_uid, hex::encode(&key));
+            self.report(Check::FlatState, entry.clone(), error);
+            if self.config.repair_flat_state && repairable {
+                let result = self.repair_flat_state(&trie, shard_uid, &flat_head, key);
+                self.report_repair(Check::FlatState, &entry, result);
+            }
+        }
+        let entries_checked = batch.len() as u64;
+        if entries_checked < budget {
+            self.pass_completed(Check::FlatState);
+        } else {
+            // The smallest key greater than the last checked one.
+            let mut next_key = batch.last().map(|(key, _)| key.clone()).unwrap_or_default();
+            next_key.push(0);
+            scan.next_key = Some(next_key);
+            self.flat_state_scan = Some(scan);
+        }
+        Ok(entries_checked)
+    }
+
+    /// Overwrites the `FlatState` value with the value from the trie at the
+    /// flat head.  Fails if the flat head has moved since.
+    fn repair_flat_state(
+        &self,
+        trie: &Trie,
+        shard_uid: ShardUId,
+        flat_head: &CryptoHash,
+        key: Vec<u8>,
+    ) -> Result<(), String> {
+        let Some(flat_storage) = self.flat_storage_manager.get_flat_storage_for_shard(shard_uid)
+        else {
+            return Err("flat storage is not loaded".to_string());
+        };
+        let value = trie.get(&key).map_err(|err| err.to_string())?;
+        flat_storage
+            .repair_value(flat_head, key, value.as_deref().map(FlatStateValue::on_disk))
+            .map_err(|err| format!("{err:?}"))
+    }
+
+    /// Continues checking the blocks of the canonical chain, from the tail of
+    /// the chain to the final head.
+    fn check_chain(&mut self, budget: u64) -> anyhow::Result<u64> {
+        let tail = self.chain_store.tail()?;
+        let final_head = self.chain_store.final_head()?;
+        let mut height = self.next_chain_height.unwrap_or(tail).max(tail);
+        let mut entries_checked = 0;
+        while entries_checked < budget && height <= final_head.height {
+            entries_checked += 1;
+            if let Ok(block_hash) = self.chain_store.get_block_hash_by_height(height) {
+                if let Err(error) = self.check_block(&block_hash) {
+                    self.report(Check::Chain, format!("#{height} {block_hash}"), error);
+                }
+            }
+            height += 1;
+        }
+        if height > final_head.height {
+            self.pass_completed(Check::Chain);
+            self.next_chain_height = None;
+        } else {
+            self.next_chain_height = Some(height);
+        }
+        Ok(entries_checked)
+    }
+
+    /// Checks the block and its header, and the new chunks of the block against
+    /// the chunk extras of the previous block.
+    fn check_block(&self, block_hash: &CryptoHash) -> Result<(), String> {
+        let header = self
+            .store
+            .get_ser::<BlockHeader>(DBCol::BlockHeader, block_hash.as_ref())
+            .map_err(|err| format!("unreadable header: {err}"))?
+            .ok_or_else(|| "missing header".to_string())?;
+        if header.hash() != block_hash {
+            return Err(format!("header hash mismatch, actual {}", header.hash()));
+        }
+        // Blocks and chunk extras of the blocks before the tail may be garbage
+        // collected already.
+        let Some(block) = self
+            .store
+            .get_ser::<Block>(DBCol::Block, block_hash.as_ref())
+            .map_err(|err| format!("unreadable block: {err}"))?
+        else {
+            return Ok(());
+        };
+        if block.hash() != block_hash {
+            return Err(format!("block hash mismatch, actual {}", block.hash()));
+        }
+        block.check_validity().map_err(|err| format!("invalid block: {err:?}"))?;
+        let Ok(prev_block) = self.chain_store.get_block(header.prev_hash()) else {
+            return Ok(());
+        };
+
+        let epoch_manager = self.epoch_manager.as_ref();
+        let prev_chunk_headers = Chain::get_prev_chunk_headers(epoch_manager, &prev_block)
+            .map_err(|err| format!("failed to get previous chunks: {err}"))?;
+        for (chunk_header, prev_chunk_header) in
+            block.chunks().iter_deprecated().zip(prev_chunk_headers.iter())
+        {
+            if !chunk_header.is_new_chunk(header.height()) {
+                continue;
+            }
+            let shard_id = chunk_header.shard_id();
+            let Ok(shard_uid) = epoch_manager.shard_id_to_uid(shard_id, header.epoch_id()) else {
+                continue;
+            };
+            let Ok(prev_chunk_extra) =
+                self.chain_store.get_chunk_extra(header.prev_hash(), &shard_uid)
+            else {
+                continue;
+            };
+            match validate_chunk_with_chunk_extra(
+                &self.chain_store,
+                epoch_manager,
+                header.prev_hash(),
+                &prev_chunk_extra,
+                prev_chunk_header.height_included(),
+                chunk_header,
+            ) {
+                Ok(()) | Err(near_chain::Error::DBNotFoundErr(_)) => {}
+                Err(err) => {
+                    return Err(format!(
+                        "chunk of shard {shard_id} doesn't match chunk extra: {err}"
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts fetching the corrupted trie nodes and values from the state
+    /// parts of the current epoch in the external storage.  They are restored
+    /// at the next step once fetched.
+    fn repair_state(&mut self) {
+        let repairs = std::mem::take(&mut self.state_repairs);
+        let Some(external) = self.external.clone() else {
+            return;
+        };
+        let dump = match self.state_dump_location() {
+            Ok(dump) => Arc::new(dump),
+            Err(err) => {
+                tracing::warn!(target: "scrubber", ?err, "Failed to locate state parts");
+                return;
+            }
+        };
+        for repair in repairs {
+            // The walk may find the same corruption again before the previous
+            // fetch completes.
+            if !self.repairs_in_flight.insert((repair.shard_uid, repair.hash)) {
+                continue;
+            }
+            let external = external.clone();
+            let store = self.store.clone();
+            let dump = dump.clone();
+            let sender = self.fetched_sender.clone();
+            self.future_spawner.spawn("scrubber fetch state part", async move {
+                let result = fetch_from_state_part(&external, &store, &dump, &repair).await;
+                // The receiver is dropped only together with the actor.
+                let _ = sender.send(FetchedRepair { repair, result });
+            });
+        }
+    }
+
+    /// Returns where the state of the epoch of the final head is dumped.
+    fn state_dump_location(&self) -> anyhow::Result<StateDumpLocation> {
+        let epoch_id = self.chain_store.final_head()?.epoch_id;
+        let epoch_height = self.epoch_manager.get_epoch_info(&epoch_id)?.epoch_height();
+        Ok(StateDumpLocation { chain_id: self.chain_id.clone(), epoch_id, epoch_height })
+    }
+
+    /// Replaces the data of the fetched trie node or value with a merge which
+    /// keeps its reference count, so that concurrent updates of the count by
+    /// block processing or garbage collection aren't lost.
+    fn restore_state_value(&mut self, fetched: FetchedRepair) {
+        let FetchedRepair { repair, result } = fetched;
+        self.repairs_in_flight.remove(&(repair.shard_uid, repair.hash));
+        let entry = format!("{} {}", repair.shard_uid, repair.hash);
+        let result = result
+            .map_err(|err| format!("{err:#}"))
+            .and_then(|value| self.write_state_value(repair.shard_uid, &repair.hash, &value));
+        self.report_repair(Check::State, &entry, result);
+    }
+
+    fn write_state_value(
+        &self,
+        shard_uid: ShardUId,
+        hash: &CryptoHash,
+        value: &[u8],
+    ) -> Result<(), String> {
+        let trie_store = self.store.trie_store();
+        // The merge does nothing if the node has been removed meanwhile, this
+        // only reports it.
+        let refcount = match trie_store.get_with_refcount(shard_uid, hash) {
+            Ok(Some((_, refcount))) => refcount,
+            Ok(None) => 0,
+            Err(err) => return Err(err.to_string()),
+        };
+        if refcount <= 0 {
+            return Err(format!("reference count changed to {refcount}, not restoring"));
+        }
+        let mut store_update = trie_store.store_update();
+        store_update.replace_value(shard_uid, hash, value);
+        store_update.commit().map_err(|err| err.to_string())
+    }
+
+    fn status(&self) -> ScrubberStatusView {
+        let position = |check: Check| match check {
+            Check::State => self
+                .state_walk
+                .as_ref()
+                .map(|walk| format!("shard {} state root {}", walk.shard_uid, walk.state_root)),
+            Check::FlatState => self.flat_state_scan.as_ref().map(|scan| {
+                let key = scan.next_key.as_deref().map_or("start".to_string(), hex::encode);
+                format!("shard {} key {}", scan.shard_uid, key)
+            }),
+            Check::Chain => self.next_chain_height.map(|height| format!("height {height}")),
+        };
+        let checks = [Check::State, Check::FlatState, Check::Chain]
+            .into_iter()
+            .map(|check| {
+                let progress = self.progress.get(&check);
+                ScrubberCheckView {
+                    check: <&str>::from(check).to_string(),
+                    position: position(check),
+                    entries_checked: progress.map_or(0, |progress| progress.entries_checked),
+                    passes_completed: progress.map_or(0, |progress| progress.passes_completed),
+                    corruptions_found: progress.map_or(0, |progress| progress.corruptions_found),
+                }
+            })
+            .collect();
+        ScrubberStatusView {
+            enabled: self.config.enabled,
+            checks,
+            findings: self.findings.iter().cloned().collect(),
+        }
+    }
+}
+
+fn push_children(stack: &mut Vec<StateEntry>, path: &[u8], node: RawTrieNode) {
+    let child_path = |nibbles: &[u8]| [path, nibbles].concat();
+    let key_path =
+        |key: &[u8]| child_path(&NibbleSlice::from_encoded(key).0.iter().collect::<Vec<_>>());
+    match node {
+        RawTrieNode::Leaf(key, value_ref) => {
+            stack.push(StateEntry::Value(value_ref, key_path(&key)))
+        }
+        RawTrieNode::BranchNoValue(children) => {
+            stack.extend(
+                children.iter().map(|(index, hash)| StateEntry::Node(*hash, child_path(&[index]))),
+            );
+        }
+        RawTrieNode::BranchWithValue(value_ref, children) => {
+            stack.push(StateEntry::Value(value_ref, path.to_vec()));
+            stack.extend(
+                children.iter().map(|(index, hash)| StateEntry::Node(*hash, child_path(&[index]))),
+            );
+        }
+        RawTrieNode::Extension(key, child) => stack.push(StateEntry::Node(child, key_path(&key))),
+    }
+}
+
+/// Location of the state of an epoch in the external storage.
+struct StateDumpLocation {
+    chain_id: String,
+    epoch_id: EpochId,
+    epoch_height: u64,
+}
+
+impl StateDumpLocation {
+    fn file(&self, repair: &StateRepair, file_type: &StateFileType) -> String {
+        let shard_id = repair.shard_uid.shard_id();
+        external_storage_location(
+            &self.chain_id,
+            &self.epoch_id,
+            self.epoch_height,
+            shard_id,
+            file_type,
+        )
+    }
+}
+
+/// Fetches the trie node or value from the state part whose range of keys
+/// covers its path.  The state root and the number of parts are taken from
+/// the state header, which also holds the root node itself.
+async fn fetch_from_state_part(
+    external: &ExternalConnection,
+    store: &Store,
+    dump: &StateDumpLocation,
+    repair: &StateRepair,
+) -> anyhow::Result<Vec<u8>> {
+    let shard_id = repair.shard_uid.shard_id();
+    let file_type = StateFileType::StateHeader;
+    let bytes = external.get_file(shard_id, &dump.file(repair, &file_type), &file_type).await?;
+    let header = ShardStateSyncResponseHeader::try_from_slice(&bytes)?;
+    let state_root_node = header.state_root_node();
+    if hash(&state_root_node.data) == repair.hash {
+        return Ok(state_root_node.data.to_vec());
+    }
+
+    let num_parts = header.num_state_parts();
+    let part_id = find_covering_part(store, repair, header.chunk_prev_state_root(), num_parts)?;
+    let file_type = StateFileType::StatePart { part_id, num_parts };
+    let bytes = external.get_file(shard_id, &dump.file(repair, &file_type), &file_type).await?;
+    let PartialState::TrieValues(values) = PartialState::try_from_slice(&bytes)?;
+    values
+        .into_iter()
+        .find(|value| hash(value) == repair.hash)
+        .map(|value| value.to_vec())
+        .ok_or_else(|| {
+            anyhow::anyhow!("not found in state part {part_id} of {num_parts} of the current epoch")
+        })
+}
+
+/// Finds the state part of the state with the given root whose range of keys
+/// covers the path of the node, by binary search over the part boundaries.
+/// The boundaries are looked up in the local trie, so this fails if another
+/// corrupted node lies on the way to them.
+fn find_covering_part(
+    store: &Store,
+    repair: &StateRepair,
+    state_root: StateRoot,
+    num_parts: u64,
+) -> anyhow::Result<u64> {
+    let storage = Arc::new(TrieDBStorage::new(store.trie_store(), repair.shard_uid));
+    let trie = Trie::new(storage, state_root, None);
+    let (mut low, mut high) = (0, num_parts);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if trie.find_state_part_boundary(middle, num_parts)? <= repair.path {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low)
+}
+
+fn create_external_connection(location: &ExternalStorageLocation) -> Option<ExternalConnection> {
+    let connection = match location {
+        ExternalStorageLocation::S3 { bucket, region, .. } => {
+            let timeout = std::time::Duration::from_secs(60);
+            match create_bucket_readonly(bucket, region, timeout) {
+                Ok(bucket) => ExternalConnection::s3(bucket),
+                Err(err) => {
+                    tracing::error!(target: "scrubber", ?err, "Failed to create an S3 bucket");
+                    return None;
+                }
+            }
+        }
+        ExternalStorageLocation::Filesystem { root_dir } => {
+            ExternalConnection::filesystem(root_dir.clone())
+        }
+        ExternalStorageLocation::GCS { bucket, .. } => ExternalConnection::gcs(bucket.clone()),
+        ExternalStorageLocation::Http { base_url } => match ExternalConnection::http(base_url) {
+            Ok(connection) => connection,
+            Err(err) => {
+                tracing::error!(target: "scrubber", ?err, "Invalid state sync base URL");
+                return None;
+            }
+        },
+    };
+    Some(connection)
+}
+
+impl Actor for ScrubberActor {
+    fn start_actor(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
+        if self.config.enabled {
+            tracing::info!(target: "scrubber", config = ?self.config, "Starting the data scrubber");
+            self.step(ctx);
+        }
+    }
+}
+
+impl Handler<DebugScrubberStatus> for ScrubberActor {
+    fn handle(&mut self, _msg: DebugScrubberStatus) -> ScrubberStatusView {
+        self.status()
+    }
+}
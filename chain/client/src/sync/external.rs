@@ -1,5 +1,5 @@
 use crate::metrics;
-use futures::TryStreamExt;
+use futures::{StreamExt, TryStreamExt};
 use near_primitives::types::{EpochId, ShardId};
 use std::io::{Read, Write};
 use std::path::PathBuf;
@@ -41,29 +41,382 @@ impl StateFileType {
     }
 }
 
-/// Connection to the external storage.
-#[derive(Clone)]
-pub enum ExternalConnection {
-    S3 {
-        bucket: Arc<s3::Bucket>,
-    },
-    Filesystem {
-        root_dir: PathBuf,
-    },
-    GCS {
-        // Used for uploading and listing state parts.
-        // Requires valid credentials to be specified through env variable.
-        gcs_client: Arc<cloud_storage::Client>,
-        // Used for anonymously downloading state parts.
-        reqwest_client: Arc<reqwest::Client>,
-        bucket: String,
-    },
+/// Object store holding state headers and parts, addressed by paths such as those returned by
+/// `external_storage_location()`.
+///
+/// Nodes that dump state need all three operations, while nodes that sync state only download
+/// objects, so read-only stores return errors from `put_object()` and `list_objects()`.
+#[async_trait::async_trait]
+pub trait ExternalStorage: Send + Sync {
+    /// Downloads the object at the given location.
+    async fn get_object(&self, location: &str) -> Result<Vec<u8>, anyhow::Error>;
+
+    /// Uploads the object to the given location, overwriting the existing one.
+    async fn put_object(&self, location: &str, data: &[u8]) -> Result<(), anyhow::Error>;
+
+    /// Returns file names of the objects in the given directory.
+    async fn list_objects(&self, directory_path: &str) -> Result<Vec<String>, anyhow::Error>;
+}
+
+pub struct S3Storage {
+    pub bucket: s3::Bucket,
+}
+
+#[async_trait::async_trait]
+impl ExternalStorage for S3Storage {
+    async fn get_object(&self, location: &str) -> Result<Vec<u8>, anyhow::Error> {
+        let response = self.bucket.get_object(location).await?;
+        if response.status_code() == 200 {
+            Ok(response.bytes().to_vec())
+        } else {
+            Err(anyhow::anyhow!("Bad response status code: {}", response.status_code()))
+        }
+    }
+
+    async fn put_object(&self, location: &str, data: &[u8]) -> Result<(), anyhow::Error> {
+        self.bucket.put_object(&location, data).await?;
+        Ok(())
+    }
+
+    async fn list_objects(&self, directory_path: &str) -> Result<Vec<String>, anyhow::Error> {
+        let prefix = format!("{}/", directory_path);
+        let list_results = self.bucket.list(prefix, Some("/".to_string())).await?;
+        let mut file_names = vec![];
+        for res in list_results {
+            for obj in res.contents {
+                file_names.push(extract_file_name_from_full_path(obj.key))
+            }
+        }
+        Ok(file_names)
+    }
+}
+
+pub struct FilesystemStorage {
+    pub root_dir: PathBuf,
+}
+
+#[async_trait::async_trait]
+impl ExternalStorage for FilesystemStorage {
+    async fn get_object(&self, location: &str) -> Result<Vec<u8>, anyhow::Error> {
+        let path = self.root_dir.join(location);
+        tracing::debug!(target: "sync", ?path, "Reading a file");
+        Ok(std::fs::read(&path)?)
+    }
+
+    async fn put_object(&self, location: &str, data: &[u8]) -> Result<(), anyhow::Error> {
+        let path = self.root_dir.join(location);
+        if let Some(parent_dir) = path.parent() {
+            std::fs::create_dir_all(parent_dir)?;
+        }
+        let mut file =
+            std::fs::OpenOptions::new().write(true).create(true).truncate(true).open(&path)?;
+        file.write_all(data)?;
+        Ok(())
+    }
+
+    async fn list_objects(&self, directory_path: &str) -> Result<Vec<String>, anyhow::Error> {
+        let path = self.root_dir.join(directory_path);
+        std::fs::create_dir_all(&path)?;
+        let mut file_names = vec![];
+        let files = std::fs::read_dir(&path)?;
+        for file in files {
+            let file_name = extract_file_name_from_path_buf(file?.path());
+            file_names.push(file_name);
+        }
+        Ok(file_names)
+    }
+}
+
+pub struct GcsStorage {
+    /// Used for uploading and listing state parts.
+    /// Requires valid credentials to be specified through env variable.
+    pub gcs_client: cloud_storage::Client,
+    /// Used for anonymously downloading state parts.
+    pub reqwest_client: reqwest::Client,
+    pub bucket: String,
 }
 
 const GCS_ENCODE_SET: &percent_encoding::AsciiSet =
     &percent_encoding::NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_');
 
+#[async_trait::async_trait]
+impl ExternalStorage for GcsStorage {
+    async fn get_object(&self, location: &str) -> Result<Vec<u8>, anyhow::Error> {
+        // Download should be handled anonymously, therefore we are not using cloud-storage crate.
+        let url = format!(
+            "https://storage.googleapis.com/storage/v1/b/{}/o/{}?alt=media",
+            percent_encoding::percent_encode(self.bucket.as_bytes(), GCS_ENCODE_SET),
+            percent_encoding::percent_encode(location.as_bytes(), GCS_ENCODE_SET),
+        );
+        let response = self.reqwest_client.get(&url).send().await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn put_object(&self, location: &str, data: &[u8]) -> Result<(), anyhow::Error> {
+        self.gcs_client
+            .object()
+            .create(&self.bucket, data.to_vec(), location, "application/octet-stream")
+            .await?;
+        Ok(())
+    }
+
+    /// Requires credentials. Thus, this function shouldn't be used for sync node that is
+    /// expected to operate anonymously. Only dump nodes should use this function.
+    async fn list_objects(&self, directory_path: &str) -> Result<Vec<String>, anyhow::Error> {
+        let prefix = format!("{}/", directory_path);
+        Ok(self
+            .gcs_client
+            .object()
+            .list(
+                &self.bucket,
+                cloud_storage::ListRequest { prefix: Some(prefix), ..Default::default() },
+            )
+            .await?
+            .try_collect::<Vec<cloud_storage::object::ObjectList>>()
+            .await?
+            .into_iter()
+            .map(|object_list| {
+                object_list
+                    .items
+                    .into_iter()
+                    .map(|obj| extract_file_name_from_full_path(obj.name))
+                    .collect::<Vec<String>>()
+            })
+            .flatten()
+            .collect())
+    }
+}
+
+/// Objects larger than this are downloaded from HTTP servers supporting range requests in
+/// multiple ranges of this size.
+const HTTP_RANGE_SIZE: u64 = 4 * 1024 * 1024;
+/// How many ranges of a single object are downloaded concurrently.
+const HTTP_MAX_PARALLEL_RANGES: usize = 4;
+/// How many times a failed HTTP request is retried before giving up.
+const HTTP_MAX_RETRIES: u32 = 3;
+/// Delay before the first retry of an HTTP request, doubled after every attempt.
+const HTTP_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Read-only storage served by a plain HTTP(S) server, e.g. a mirror of a state dump bucket.
+///
+/// Objects are downloaded from `<base_url>/<location>`. Failed requests are retried unless the
+/// server reports that the object doesn't exist. Downloaded state parts are validated against
+/// the state root by the caller, so a corrupted mirror can't do more than fail the download.
+pub struct HttpStorage {
+    client: reqwest::Client,
+    base_url: reqwest::Url,
+    range_size: u64,
+    max_parallel_ranges: usize,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+/// Failure of a single HTTP request.
+struct HttpRequestError {
+    error: anyhow::Error,
+    /// Whether repeating the request may succeed, e.g. after a timeout or a 503 response.
+    retryable: bool,
+}
+
+impl From<reqwest::Error> for HttpRequestError {
+    fn from(error: reqwest::Error) -> Self {
+        // Errors sending requests or reading responses are typically transient.
+        Self { retryable: !error.is_builder(), error: error.into() }
+    }
+}
+
+fn check_http_status(response: reqwest::Response) -> Result<reqwest::Response, HttpRequestError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(HttpRequestError {
+        error: anyhow::anyhow!("Bad response status code: {}", status),
+        retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+    })
+}
+
+impl HttpStorage {
+    pub fn new(base_url: &str) -> Result<Self, anyhow::Error> {
+        // Without the trailing slash the last path segment would be replaced by `Url::join()`.
+        let base_url = if base_url.ends_with('/') {
+            reqwest::Url::parse(base_url)?
+        } else {
+            reqwest::Url::parse(&format!("{}/", base_url))?
+        };
+        Ok(Self {
+            client: reqwest::Client::default(),
+            base_url,
+            range_size: HTTP_RANGE_SIZE,
+            max_parallel_ranges: HTTP_MAX_PARALLEL_RANGES,
+            max_retries: HTTP_MAX_RETRIES,
+            retry_backoff: HTTP_RETRY_BACKOFF,
+        })
+    }
+
+    async fn with_retries<T, Fut>(
+        &self,
+        url: &reqwest::Url,
+        request: impl Fn() -> Fut,
+    ) -> Result<T, anyhow::Error>
+    where
+        Fut: std::future::Future<Output = Result<T, HttpRequestError>>,
+    {
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(HttpRequestError { error, retryable }) => {
+                    if !retryable || attempt >= self.max_retries {
+                        return Err(error.context(format!("Request to {} failed", url)));
+                    }
+                    tracing::debug!(target: "sync", %url, attempt, ?error, "Retrying HTTP request");
+                    attempt += 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+
+    /// Returns the size of the object if the server supports range requests for it.
+    async fn get_size_for_ranges(&self, url: &reqwest::Url) -> Result<Option<u64>, anyhow::Error> {
+        self.with_retries(url, || async {
+            let response = check_http_status(self.client.head(url.clone()).send().await?)?;
+            let headers = response.headers();
+            let accepts_ranges = headers
+                .get(reqwest::header::ACCEPT_RANGES)
+                .is_some_and(|value| value.as_bytes() == b"bytes");
+            let size = headers
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+            Ok(size.filter(|_| accepts_ranges))
+        })
+        .await
+    }
+
+    async fn get_whole(&self, url: &reqwest::Url) -> Result<Vec<u8>, anyhow::Error> {
+        self.with_retries(url, || async {
+            let response = check_http_status(self.client.get(url.clone()).send().await?)?;
+            Ok(response.bytes().await?.to_vec())
+        })
+        .await
+    }
+
+    async fn get_range(
+        &self,
+        url: &reqwest::Url,
+        range: std::ops::Range<u64>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        self.with_retries(url, || async {
+            let response = self
+                .client
+                .get(url.clone())
+                .header(reqwest::header::RANGE, format!("bytes={}-{}", range.start, range.end - 1))
+                .send()
+                .await?;
+            let response = check_http_status(response)?;
+            if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                return Err(HttpRequestError {
+                    error: anyhow::anyhow!("Range request returned {}", response.status()),
+                    retryable: false,
+                });
+            }
+            let bytes = response.bytes().await?;
+            if bytes.len() as u64 != range.end - range.start {
+                return Err(HttpRequestError {
+                    error: anyhow::anyhow!(
+                        "Got {} bytes for range {:?}",
+                        bytes.len(),
+                        range.clone()
+                    ),
+                    retryable: true,
+                });
+            }
+            Ok(bytes.to_vec())
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl ExternalStorage for HttpStorage {
+    async fn get_object(&self, location: &str) -> Result<Vec<u8>, anyhow::Error> {
+        let url = self.base_url.join(location)?;
+        // Servers that don't support HEAD requests are still fine for downloading whole objects.
+        let size = self.get_size_for_ranges(&url).await.unwrap_or_else(|err| {
+            tracing::debug!(target: "sync", %url, ?err, "Failed to get the object size");
+            None
+        });
+        let size = match size {
+            Some(size) if size > self.range_size => size,
+            _ => return self.get_whole(&url).await,
+        };
+        let ranges = (0..size)
+            .step_by(self.range_size as usize)
+            .map(|start| start..(start + self.range_size).min(size));
+        let chunks: Vec<Vec<u8>> = futures::stream::iter(ranges)
+            .map(|range| self.get_range(&url, range))
+            .buffered(self.max_parallel_ranges)
+            .try_collect()
+            .await?;
+        Ok(chunks.concat())
+    }
+
+    async fn put_object(&self, _location: &str, _data: &[u8]) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!("HTTP external storage is read-only"))
+    }
+
+    async fn list_objects(&self, _directory_path: &str) -> Result<Vec<String>, anyhow::Error> {
+        Err(anyhow::anyhow!("HTTP external storage doesn't support listing objects"))
+    }
+}
+
+fn extract_file_name_from_full_path(full_path: String) -> String {
+    return extract_file_name_from_path_buf(PathBuf::from(full_path));
+}
+
+fn extract_file_name_from_path_buf(path_buf: PathBuf) -> String {
+    return path_buf.file_name().unwrap().to_str().unwrap().to_string();
+}
+
+/// Connection to the external storage.
+///
+/// Wraps an `ExternalStorage` and records metrics of the requests made to it.
+#[derive(Clone)]
+pub struct ExternalConnection {
+    storage: Arc<dyn ExternalStorage>,
+}
+
 impl ExternalConnection {
+    pub fn new(storage: Arc<dyn ExternalStorage>) -> Self {
+        Self { storage }
+    }
+
+    pub fn s3(bucket: s3::Bucket) -> Self {
+        Self::new(Arc::new(S3Storage { bucket }))
+    }
+
+    pub fn filesystem(root_dir: PathBuf) -> Self {
+        Self::new(Arc::new(FilesystemStorage { root_dir }))
+    }
+
+    /// Uploading and listing objects requires credentials specified through the
+    /// `SERVICE_ACCOUNT` env variable.
+    pub fn gcs(bucket: String) -> Self {
+        Self::new(Arc::new(GcsStorage {
+            gcs_client: cloud_storage::Client::default(),
+            reqwest_client: reqwest::Client::default(),
+            bucket,
+        }))
+    }
+
+    pub fn http(base_url: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self::new(Arc::new(HttpStorage::new(base_url)?)))
+    }
+
     pub async fn get_file(
         &self,
         shard_id: ShardId,
@@ -73,45 +426,17 @@ impl ExternalConnection {
         let _timer = metrics::STATE_SYNC_EXTERNAL_PARTS_REQUEST_DELAY
             .with_label_values(&[&shard_id.to_string(), &file_type.to_string()])
             .start_timer();
-        match self {
-            ExternalConnection::S3 { bucket } => {
-                let response = bucket.get_object(location).await?;
-                tracing::debug!(target: "sync", %shard_id, location, response_code = response.status_code(), num_bytes = response.bytes().len(), "S3 request finished");
-                if response.status_code() == 200 {
-                    Ok(response.bytes().to_vec())
-                } else {
-                    Err(anyhow::anyhow!("Bad response status code: {}", response.status_code()))
-                }
+        match self.storage.get_object(location).await {
+            Err(err) => {
+                tracing::debug!(target: "sync", %shard_id, location, ?err, "External storage request failed");
+                Err(err)
             }
-            ExternalConnection::Filesystem { root_dir } => {
-                let path = root_dir.join(location);
-                tracing::debug!(target: "sync", %shard_id, ?path, "Reading a file");
-                let data = std::fs::read(&path)?;
-                Ok(data)
-            }
-            ExternalConnection::GCS { reqwest_client, bucket, .. } => {
-                // Download should be handled anonymously, therefore we are not using cloud-storage crate.
-                let url = format!(
-                    "https://storage.googleapis.com/storage/v1/b/{}/o/{}?alt=media",
-                    percent_encoding::percent_encode(bucket.as_bytes(), GCS_ENCODE_SET),
-                    percent_encoding::percent_encode(location.as_bytes(), GCS_ENCODE_SET),
-                );
-                let response = reqwest_client.get(&url).send().await?.error_for_status();
-
-                match response {
-                    Err(e) => {
-                        tracing::debug!(target: "sync", %shard_id, location, error = ?e, "GCS state_part request failed");
-                        Err(e.into())
-                    }
-                    Ok(r) => {
-                        let bytes = r.bytes().await?.to_vec();
-                        tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "GCS state_part request finished");
-                        metrics::STATE_SYNC_EXTERNAL_PARTS_SIZE_DOWNLOADED
-                            .with_label_values(&[&shard_id.to_string(), &file_type.to_string()])
-                            .inc_by(bytes.len() as u64);
-                        Ok(bytes)
-                    }
-                }
+            Ok(bytes) => {
+                tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "External storage request finished");
+                metrics::STATE_SYNC_EXTERNAL_PARTS_SIZE_DOWNLOADED
+                    .with_label_values(&[&shard_id.to_string(), &file_type.to_string()])
+                    .inc_by(bytes.len() as u64);
+                Ok(bytes)
             }
         }
     }
@@ -126,65 +451,18 @@ impl ExternalConnection {
         location: &str,
     ) -> Result<(), anyhow::Error> {
         let instant = Instant::now();
-        let res = self.put_file_impl(&file_type, data, shard_id, location).await;
+        let res = self.storage.put_object(location, data).await;
         let is_ok = if res.is_ok() { "ok" } else { "error" };
         let elapsed = instant.elapsed();
         metrics::STATE_SYNC_DUMP_PUT_OBJECT_ELAPSED
             .with_label_values(&[&shard_id.to_string(), is_ok, &file_type.to_string()])
             .observe(elapsed.as_secs_f64());
-        res
-    }
-
-    /// Actual implementation.
-    async fn put_file_impl(
-        &self,
-        file_type: &StateFileType,
-        data: &[u8],
-        shard_id: ShardId,
-        location: &str,
-    ) -> Result<(), anyhow::Error> {
-        match self {
-            ExternalConnection::S3 { bucket } => {
-                bucket.put_object(&location, data).await?;
-                tracing::debug!(target: "state_sync_dump", ?shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to S3");
-                Ok(())
-            }
-            ExternalConnection::Filesystem { root_dir } => {
-                let path = root_dir.join(location);
-                if let Some(parent_dir) = path.parent() {
-                    std::fs::create_dir_all(parent_dir)?;
-                }
-                let mut file = std::fs::OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .open(&path)?;
-                file.write_all(data)?;
-                tracing::debug!(target: "state_sync_dump", ?shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to a file");
-                Ok(())
-            }
-            ExternalConnection::GCS { gcs_client, bucket, .. } => {
-                gcs_client
-                    .object()
-                    .create(bucket, data.to_vec(), location, "application/octet-stream")
-                    .await?;
-                tracing::debug!(target: "state_sync_dump", ?shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to GCS");
-                Ok(())
-            }
+        if res.is_ok() {
+            tracing::debug!(target: "state_sync_dump", ?shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to external storage");
         }
+        res
     }
 
-    fn extract_file_name_from_full_path(full_path: String) -> String {
-        return Self::extract_file_name_from_path_buf(PathBuf::from(full_path));
-    }
-
-    fn extract_file_name_from_path_buf(path_buf: PathBuf) -> String {
-        return path_buf.file_name().unwrap().to_str().unwrap().to_string();
-    }
-
-    /// When using GCS external connection, this function requires credentials.
-    /// Thus, this function shouldn't be used for sync node that is expected to operate anonymously.
-    /// Only dump nodes should use this function.
     pub async fn list_objects(
         &self,
         shard_id: ShardId,
@@ -193,55 +471,8 @@ impl ExternalConnection {
         let _timer = metrics::STATE_SYNC_DUMP_LIST_OBJECT_ELAPSED
             .with_label_values(&[&shard_id.to_string()])
             .start_timer();
-        match self {
-            ExternalConnection::S3 { bucket } => {
-                let prefix = format!("{}/", directory_path);
-                let list_results = bucket.list(prefix.clone(), Some("/".to_string())).await?;
-                tracing::debug!(target: "state_sync_dump", ?shard_id, ?directory_path, "List state parts in s3");
-                let mut file_names = vec![];
-                for res in list_results {
-                    for obj in res.contents {
-                        file_names.push(Self::extract_file_name_from_full_path(obj.key))
-                    }
-                }
-                Ok(file_names)
-            }
-            ExternalConnection::Filesystem { root_dir } => {
-                let path = root_dir.join(directory_path);
-                tracing::debug!(target: "state_sync_dump", ?shard_id, ?path, "List state parts in local directory");
-                std::fs::create_dir_all(&path)?;
-                let mut file_names = vec![];
-                let files = std::fs::read_dir(&path)?;
-                for file in files {
-                    let file_name = Self::extract_file_name_from_path_buf(file?.path());
-                    file_names.push(file_name);
-                }
-                Ok(file_names)
-            }
-            ExternalConnection::GCS { gcs_client, bucket, .. } => {
-                let prefix = format!("{}/", directory_path);
-                tracing::debug!(target: "state_sync_dump", ?shard_id, ?directory_path, "List state parts in GCS");
-                Ok(gcs_client
-                    .object()
-                    .list(
-                        bucket,
-                        cloud_storage::ListRequest { prefix: Some(prefix), ..Default::default() },
-                    )
-                    .await?
-                    .try_collect::<Vec<cloud_storage::object::ObjectList>>()
-                    .await?
-                    .into_iter()
-                    .map(|object_list| {
-                        object_list
-                            .items
-                            .into_iter()
-                            .map(|obj| Self::extract_file_name_from_full_path(obj.name))
-                            .collect::<Vec<String>>()
-                    })
-                    .flatten()
-                    .collect())
-            }
-        }
+        tracing::debug!(target: "state_sync_dump", ?shard_id, ?directory_path, "List state parts in external storage");
+        self.storage.list_objects(directory_path).await
     }
 
     /// Check if the state sync header exists in the external storage.
@@ -410,11 +641,17 @@ fn create_bucket(
 mod test {
     use crate::sync::external::{
         get_num_parts_from_filename, get_part_id_from_filename, is_part_filename,
-        ExternalConnection, StateFileType,
+        ExternalConnection, ExternalStorage, HttpStorage, StateFileType,
     };
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server, StatusCode};
     use near_o11y::testonly::init_test_logger;
     use near_primitives::types::ShardId;
     use rand::distributions::{Alphanumeric, DistString};
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
 
     fn random_string(rand_len: usize) -> String {
         Alphanumeric.sample_string(&mut rand::thread_rng(), rand_len)
@@ -446,11 +683,7 @@ mod test {
         tracing::debug!("Filename: {:?}", filename);
 
         // Define bucket.
-        let connection = ExternalConnection::GCS {
-            gcs_client: std::sync::Arc::new(cloud_storage::Client::default()),
-            reqwest_client: std::sync::Arc::new(reqwest::Client::default()),
-            bucket: "state-parts".to_string(),
-        };
+        let connection = ExternalConnection::gcs("state-parts".to_string());
 
         // Generate random data.
         let data = random_string(1000);
@@ -498,4 +731,108 @@ mod test {
         });
         assert!(download_data.is_err(), "{:?}", download_data);
     }
+
+    /// Serves `data` at `/mirror/dir/object` with support for range requests. The first
+    /// `num_failures` requests are answered with 503 Service Unavailable.
+    fn start_http_server(
+        rt: &tokio::runtime::Runtime,
+        data: Vec<u8>,
+        num_failures: usize,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let data = Arc::new(data);
+        let failures_left = Arc::new(AtomicUsize::new(num_failures));
+        let num_requests = Arc::new(AtomicUsize::new(0));
+        let counter = num_requests.clone();
+        rt.spawn(async move {
+            let make_svc = make_service_fn(move |_conn| {
+                let data = data.clone();
+                let failures_left = failures_left.clone();
+                let counter = counter.clone();
+                let handle_request = move |request: Request<Body>| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let response = if failures_left
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                        .is_ok()
+                    {
+                        Response::builder()
+                            .status(StatusCode::SERVICE_UNAVAILABLE)
+                            .body(Body::empty())
+                    } else if request.uri().path() != "/mirror/dir/object" {
+                        Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty())
+                    } else if let Some(range) = request.headers().get(hyper::header::RANGE) {
+                        let range = range.to_str().unwrap().strip_prefix("bytes=").unwrap();
+                        let (start, end) = range.split_once('-').unwrap();
+                        let range =
+                            start.parse::<usize>().unwrap()..end.parse::<usize>().unwrap() + 1;
+                        Response::builder()
+                            .status(StatusCode::PARTIAL_CONTENT)
+                            .body(Body::from(data[range].to_vec()))
+                    } else {
+                        Response::builder()
+                            .header(hyper::header::ACCEPT_RANGES, "bytes")
+                            .header(hyper::header::CONTENT_LENGTH, data.len())
+                            .body(Body::from(data.to_vec()))
+                    };
+                    async move { Ok::<_, Infallible>(response.unwrap()) }
+                };
+                async move { Ok::<_, Infallible>(service_fn(handle_request)) }
+            });
+            let server = Server::from_tcp(listener).unwrap().serve(make_svc);
+            if let Err(e) = server.await {
+                eprintln!("server error: {}", e);
+            }
+        });
+        (format!("http://127.0.0.1:{}/mirror", port), num_requests)
+    }
+
+    fn test_http_storage(base_url: &str) -> HttpStorage {
+        HttpStorage {
+            range_size: 100,
+            retry_backoff: Duration::from_millis(1),
+            ..HttpStorage::new(base_url).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_http_download_in_ranges() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let data: Vec<u8> = random_string(1050).into();
+        let (base_url, num_requests) = start_http_server(&rt, data.clone(), 2);
+        let storage = test_http_storage(&base_url);
+
+        let downloaded = rt.block_on(storage.get_object("dir/object")).unwrap();
+        assert_eq!(downloaded, data);
+        // Two failed requests, one HEAD request and 11 ranges.
+        assert_eq!(num_requests.load(Ordering::SeqCst), 14);
+    }
+
+    #[test]
+    fn test_http_download_errors() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (base_url, num_requests) = start_http_server(&rt, vec![1, 2, 3], 100);
+        let storage = test_http_storage(&base_url);
+
+        // Gives up after the configured number of retries.
+        let res = rt.block_on(storage.get_object("dir/object"));
+        assert!(res.is_err(), "{:?}", res);
+        assert_eq!(num_requests.load(Ordering::SeqCst), 2 * (storage.max_retries as usize + 1));
+
+        // Missing objects aren't retried.
+        let (base_url, num_requests) = start_http_server(&rt, vec![1, 2, 3], 0);
+        let storage = test_http_storage(&base_url);
+        let res = rt.block_on(storage.get_object("dir/missing"));
+        assert!(res.is_err(), "{:?}", res);
+        assert_eq!(num_requests.load(Ordering::SeqCst), 2);
+
+        // Small objects are downloaded in a single request.
+        let res = rt.block_on(storage.get_object("dir/object"));
+        assert_eq!(res.unwrap(), vec![1, 2, 3]);
+        assert_eq!(num_requests.load(Ordering::SeqCst), 4);
+
+        assert!(rt.block_on(storage.put_object("dir/object", &[1])).is_err());
+    }
 }
@@ -126,16 +126,20 @@ impl StateSync {
                         if let Err(err) = bucket {
                             panic!("Failed to create an S3 bucket: {}", err);
                         }
-                        ExternalConnection::S3 { bucket: Arc::new(bucket.unwrap()) }
+                        ExternalConnection::s3(bucket.unwrap())
                     }
                     ExternalStorageLocation::Filesystem { root_dir } => {
-                        ExternalConnection::Filesystem { root_dir: root_dir.clone() }
+                        ExternalConnection::filesystem(root_dir.clone())
+                    }
+                    ExternalStorageLocation::GCS { bucket, .. } => {
+                        ExternalConnection::gcs(bucket.clone())
+                    }
+                    ExternalStorageLocation::Http { base_url } => {
+                        match ExternalConnection::http(base_url) {
+                            Ok(connection) => connection,
+                            Err(err) => panic!("Invalid state sync base URL {}: {}", base_url, err),
+                        }
                     }
-                    ExternalStorageLocation::GCS { bucket, .. } => ExternalConnection::GCS {
-                        gcs_client: Arc::new(cloud_storage::Client::default()),
-                        reqwest_client: Arc::new(reqwest::Client::default()),
-                        bucket: bucket.clone(),
-                    },
                 };
                 let num_concurrent_requests = if catchup {
                     *num_concurrent_requests_during_catchup
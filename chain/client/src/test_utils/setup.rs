@@ -269,6 +269,7 @@ pub fn setup_only_view(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -1122,6 +1123,7 @@ pub fn setup_synchronous_shards_manager(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
         }, // irrelevant
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -802,6 +802,7 @@ impl TestEnv {
             | ProcessTxResponse::RequestRouted
             | ProcessTxResponse::ValidTx => (),
             ProcessTxResponse::InvalidTx(e) => return Err(e),
+            ProcessTxResponse::Underpriced => panic!("transaction was replaced in the pool"),
             ProcessTxResponse::DoesNotTrackShard => panic!("test setup is buggy"),
         }
         let max_iters = 100;
@@ -87,6 +87,7 @@ where
                 account_id: account_id.clone(),
                 method_name: method.to_string(),
                 args: args.to_vec().into(),
+                profile_wasm_gas: false,
             },
         );
         if let QueryResponseKind::CallResult(call_result) = response.kind {
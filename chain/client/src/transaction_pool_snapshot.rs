@@ -0,0 +1,147 @@
+//! Saving the transaction pool to the database so that pending transactions survive restarts of
+//! the node.
+//!
+//! The pool is saved into `DBCol::TransactionPool` periodically and on graceful shutdown,
+//! replacing the previous snapshot.  The first save after startup rewrites the whole column, the
+//! following ones only add the new transactions and delete the ones which have left the pool.
+//! On startup the saved transactions are loaded and, once the
+//! node is in sync with the chain, submitted again the same way as transactions forwarded by
+//! other nodes.  This drops the transactions which have been included into the chain, have
+//! expired or are no longer valid against the current state.  Nodes without a validator key don't
+//! keep transactions in the pool, so they forward the restored transactions to the validators.
+
+use std::collections::HashSet;
+
+use near_chain::ChainStoreAccess;
+use near_chain_primitives::Error;
+use near_network::client::ProcessTxResponse;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::SignedTransaction;
+use near_store::{DBCol, Store};
+
+use crate::metrics;
+use crate::Client;
+
+/// Loads the transactions saved by `Client::save_transaction_pool`.
+pub(crate) fn load_saved_transactions(store: &Store) -> Vec<SignedTransaction> {
+    let mut transactions = vec![];
+    for item in store.iter_ser::<SignedTransaction>(DBCol::TransactionPool) {
+        match item {
+            Ok((_, transaction)) => transactions.push(transaction),
+            Err(err) => {
+                tracing::warn!(target: "client", ?err, "Failed to read a saved transaction");
+            }
+        }
+    }
+    tracing::info!(target: "client", count = transactions.len(), "Loaded saved transaction pool");
+    transactions
+}
+
+impl Client {
+    /// Loads the saved transactions to be submitted again by `restore_transaction_pool`, which is
+    /// otherwise done only on startup.
+    pub fn load_saved_transaction_pool(&mut self) {
+        self.transactions_to_restore = load_saved_transactions(&self.chain.chain_store().store());
+    }
+
+    /// Saves all transactions in the pool, as well as the loaded ones which haven't been
+    /// restored yet, replacing the previously saved transactions.
+    pub fn save_transaction_pool(&mut self) -> Result<(), Error> {
+        if !self.config.save_transaction_pool {
+            return Ok(());
+        }
+        let _span = tracing::debug_span!(target: "client", "save_transaction_pool").entered();
+        let mut store_update = self.chain.chain_store().store().store_update();
+        let previous = self.saved_transactions.take();
+        if previous.is_none() {
+            store_update.delete_all(DBCol::TransactionPool);
+        }
+        let mut saved = HashSet::new();
+        let mut added = 0;
+        for transaction in
+            self.sharded_tx_pool.transactions().chain(self.transactions_to_restore.iter())
+        {
+            let tx_hash = transaction.get_hash();
+            if !saved.insert(tx_hash) {
+                continue;
+            }
+            if previous.as_ref().map_or(true, |previous| !previous.contains(&tx_hash)) {
+                store_update.set_ser(DBCol::TransactionPool, tx_hash.as_ref(), transaction)?;
+                added += 1;
+            }
+        }
+        let mut removed = 0;
+        for tx_hash in previous.iter().flatten().filter(|tx_hash| !saved.contains(tx_hash)) {
+            store_update.delete(DBCol::TransactionPool, tx_hash.as_ref());
+            removed += 1;
+        }
+        store_update.commit()?;
+        let count = saved.len();
+        self.saved_transactions = Some(saved);
+        metrics::TRANSACTION_POOL_SAVED_TRANSACTIONS.set(count as i64);
+        tracing::debug!(target: "client", count, added, removed, "Saved transaction pool");
+        Ok(())
+    }
+
+    /// Submits the transactions loaded at startup again once the node is in sync with the chain,
+    /// as validating them requires the current state.
+    pub fn restore_transaction_pool(&mut self) {
+        if self.transactions_to_restore.is_empty() || self.sync_status.is_syncing() {
+            return;
+        }
+        let transactions = std::mem::take(&mut self.transactions_to_restore);
+        let signer = self.validator_signer.get();
+        let total = transactions.len();
+        let mut restored = 0;
+        for transaction in transactions {
+            let tx_hash = transaction.get_hash();
+            match self.chain.chain_store().get_transaction(&tx_hash) {
+                Ok(None) => {}
+                Ok(Some(_)) => {
+                    tracing::trace!(
+                        target: "client",
+                        ?tx_hash,
+                        "Saved transaction is already included"
+                    );
+                    continue;
+                }
+                Err(err) => {
+                    tracing::debug!(
+                        target: "client",
+                        ?tx_hash,
+                        ?err,
+                        "Failed to check saved transaction"
+                    );
+                    continue;
+                }
+            }
+            // On validators restored transactions are treated as forwarded so that they are not
+            // forwarded again: they have already been forwarded before the restart.  A forwarded
+            // transaction isn't kept by a node without a validator key, so it forwards them again.
+            let is_forwarded = signer.is_some();
+            match self.process_tx_internal(&transaction, is_forwarded, false, &signer) {
+                // A validator keeps the transaction in the pool even if it doesn't produce the
+                // next chunks of the shard, in which case the response is `NoResponse`.
+                Ok(_) if self.sharded_tx_pool.contains(&tx_hash) => restored += 1,
+                Ok(ProcessTxResponse::RequestRouted) => restored += 1,
+                Ok(response) => {
+                    tracing::trace!(
+                        target: "client",
+                        ?tx_hash,
+                        ?response,
+                        "Dropping saved transaction"
+                    );
+                }
+                Err(err) => {
+                    tracing::debug!(target: "client", ?tx_hash, ?err, "Dropping saved transaction");
+                }
+            }
+        }
+        tracing::info!(
+            target: "client",
+            restored,
+            dropped = total - restored,
+            "Restored transaction pool"
+        );
+    }
+}
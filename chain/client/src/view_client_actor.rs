@@ -18,14 +18,15 @@ use near_chain::{
 use near_chain_configs::{ClientConfig, MutableValidatorSigner, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
-    Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
+    AccountActivityResponse, Error, GetAccountActivity, GetAccountActivityError, GetBlock,
+    GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
     GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
     GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
     GetMaintenanceWindowsError, GetNextLightClientBlockError, GetProtocolConfig,
     GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
     GetSplitStorageInfoError, GetStateChangesError, GetStateChangesWithCauseInBlock,
     GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
-    TxStatus, TxStatusError,
+    TxStatus, TxStatusError, TxStatuses,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
@@ -853,6 +854,27 @@ impl Handler<TxStatus> for ViewClientActorInner {
     }
 }
 
+impl Handler<TxStatuses> for ViewClientActorInner {
+    #[perf]
+    fn handle(&mut self, msg: TxStatuses) -> Vec<Result<TxStatusView, TxStatusError>> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer =
+            metrics::VIEW_CLIENT_MESSAGE_TIME.with_label_values(&["TxStatuses"]).start_timer();
+        let validator_signer = self.validator.get();
+        msg.0
+            .into_iter()
+            .map(|msg| {
+                self.get_tx_status(
+                    msg.tx_hash,
+                    msg.signer_account_id,
+                    msg.fetch_receipt,
+                    &validator_signer,
+                )
+            })
+            .collect()
+    }
+}
+
 impl Handler<GetValidatorInfo> for ViewClientActorInner {
     #[perf]
     fn handle(
@@ -1016,6 +1038,52 @@ impl Handler<GetStateChangesWithCauseInBlockForTrackedShards> for ViewClientActo
     }
 }
 
+impl Handler<GetAccountActivity> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetAccountActivity,
+    ) -> Result<AccountActivityResponse, GetAccountActivityError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetAccountActivity"])
+            .start_timer();
+        if !self.config.save_account_activity {
+            return Err(GetAccountActivityError::IndexDisabled);
+        }
+        let to_height = match msg.to_height {
+            Some(height) => height,
+            None => self.chain.head()?.height,
+        };
+        // The index is garbage collected together with the blocks, so the activity below the
+        // tail is gone rather than empty.  On split storage the blocks up to the cold head,
+        // which is never below the tail, are copied to the cold database together with their
+        // entries before they are garbage collected, so the whole history is available.
+        let store = self.chain.chain_store().store();
+        let tail_height = match store.get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY)? {
+            Some(_) => self.chain.genesis().height(),
+            None => self.chain.tail()?,
+        };
+        let from_height = msg.from_height.unwrap_or(tail_height);
+        let start_height = match msg.cursor {
+            Some(cursor) => cursor.block_height.max(from_height),
+            None => from_height,
+        };
+        if start_height < tail_height {
+            return Err(GetAccountActivityError::GarbageCollected { tail_height });
+        }
+        let (activity, next_cursor) = self.chain.chain_store().get_account_activity(
+            &msg.account_id,
+            msg.public_key.as_ref(),
+            from_height,
+            to_height,
+            msg.cursor,
+            msg.limit,
+        )?;
+        Ok(AccountActivityResponse { activity, next_cursor })
+    }
+}
+
 /// Returns the next light client block, given the hash of the last block known to the light client.
 /// There are three cases:
 ///  1. The last block known to the light client is in the same epoch as the tip:
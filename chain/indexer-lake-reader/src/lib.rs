@@ -0,0 +1,184 @@
+#![doc = include_str!("../README.md")]
+
+use near_indexer_primitives::types::BlockHeight;
+use near_indexer_primitives::{views, IndexerShard, StreamerMessage};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+pub use near_indexer_primitives;
+pub use storage::{block_key, shard_key, LakeLocation, LakeStorage, PROGRESS_KEY};
+
+mod storage;
+
+const LAKE_READER: &str = "lake_reader";
+
+/// Number of block heights listed in the storage at once.
+const LIST_LIMIT: usize = 100;
+
+/// Configuration of the stream of blocks read from a `LakeLocation`.
+#[derive(Debug, Clone)]
+pub struct LakeReaderConfig {
+    /// Where the blocks are read from.
+    pub location: LakeLocation,
+    /// Height of the first block to stream. Heights without a block are skipped.
+    pub start_block_height: BlockHeight,
+    /// How long to wait for new blocks once all the stored ones have been streamed.
+    pub poll_interval: Duration,
+}
+
+/// Starts streaming the blocks stored in the configured location, in the order of their heights
+/// and without an end: once all the stored blocks are streamed, waits for new ones to be written.
+///
+/// The returned task finishes with an error if the storage can't be read, or with `Ok` once the
+/// receiver is dropped.
+pub fn streamer(
+    config: LakeReaderConfig,
+) -> (JoinHandle<anyhow::Result<()>>, mpsc::Receiver<StreamerMessage>) {
+    let (sender, receiver) = mpsc::channel(100);
+    (tokio::spawn(start(config, sender)), receiver)
+}
+
+async fn start(
+    config: LakeReaderConfig,
+    sender: mpsc::Sender<StreamerMessage>,
+) -> anyhow::Result<()> {
+    let storage = LakeStorage::new(&config.location)?;
+    let mut next_height = config.start_block_height;
+    loop {
+        let heights = storage.list_heights(next_height, LIST_LIMIT).await?;
+        let mut streamed = 0;
+        for height in heights {
+            let Some(streamer_message) = read_streamer_message(&storage, height).await? else {
+                tracing::debug!(target: LAKE_READER, height, "Block is still being written");
+                break;
+            };
+            if sender.send(streamer_message).await.is_err() {
+                tracing::info!(target: LAKE_READER, "Listener is gone, stopping");
+                return Ok(());
+            }
+            next_height = height + 1;
+            streamed += 1;
+        }
+        if streamed < LIST_LIMIT {
+            tokio::time::sleep(config.poll_interval).await;
+        }
+    }
+}
+
+/// Reads the block stored at the given height.
+///
+/// Returns `None` if the block is not stored or is incomplete. The sink writes `block.json` after
+/// the files of all the shards, so the block is complete once `block.json` is there.
+pub async fn read_streamer_message(
+    storage: &LakeStorage,
+    height: BlockHeight,
+) -> anyhow::Result<Option<StreamerMessage>> {
+    let Some(block) = storage.get(&block_key(height)).await? else {
+        return Ok(None);
+    };
+    let block: views::BlockView = serde_json::from_slice(&block)?;
+    let mut shards = Vec::with_capacity(block.chunks.len());
+    for chunk in &block.chunks {
+        let key = shard_key(height, chunk.shard_id);
+        let shard = storage
+            .get(&key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("{} is missing while the block is stored", key))?;
+        let shard: IndexerShard = serde_json::from_slice(&shard)?;
+        shards.push(shard);
+    }
+    Ok(Some(StreamerMessage { block, shards }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        block_key, read_streamer_message, shard_key, streamer, LakeLocation, LakeReaderConfig,
+        LakeStorage,
+    };
+    use near_indexer_primitives::near_primitives::block::{genesis_chunks, Block};
+    use near_indexer_primitives::near_primitives::hash::CryptoHash;
+    use near_indexer_primitives::near_primitives::types::{BlockHeight, ShardId, StateRoot};
+    use near_indexer_primitives::near_primitives::version::PROTOCOL_VERSION;
+    use near_indexer_primitives::{views, IndexerShard, StreamerMessage};
+    use std::time::Duration;
+
+    fn make_streamer_message(height: BlockHeight) -> StreamerMessage {
+        let shard_ids: Vec<_> = (0..2).map(ShardId::new).collect();
+        let chunks = genesis_chunks(
+            vec![StateRoot::new()],
+            vec![Some(Default::default()); shard_ids.len()],
+            &shard_ids,
+            1000,
+            height,
+            PROTOCOL_VERSION,
+        );
+        let block = Block::genesis(
+            PROTOCOL_VERSION,
+            chunks.into_iter().map(|chunk| chunk.take_header()).collect(),
+            near_time::Utc::UNIX_EPOCH,
+            height,
+            100,
+            1_000_000,
+            CryptoHash::default(),
+        );
+        let shards = shard_ids
+            .into_iter()
+            .map(|shard_id| IndexerShard {
+                shard_id,
+                chunk: None,
+                receipt_execution_outcomes: vec![],
+                state_changes: vec![],
+            })
+            .collect();
+        StreamerMessage {
+            block: views::BlockView::from_author_block("test.near".parse().unwrap(), block),
+            shards,
+        }
+    }
+
+    async fn write(storage: &LakeStorage, streamer_message: &StreamerMessage, with_block: bool) {
+        let height = streamer_message.block.header.height;
+        for shard in &streamer_message.shards {
+            let data = serde_json::to_vec(shard).unwrap();
+            storage.put(&shard_key(height, shard.shard_id), &data).await.unwrap();
+        }
+        if with_block {
+            let data = serde_json::to_vec(&streamer_message.block).unwrap();
+            storage.put(&block_key(height), &data).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamer() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = LakeLocation::Filesystem { root_dir: dir.path().to_path_buf() };
+        let storage = LakeStorage::new(&location).unwrap();
+        for height in [1, 2, 4] {
+            write(&storage, &make_streamer_message(height), true).await;
+        }
+        // Only the shards of this block are written so far.
+        write(&storage, &make_streamer_message(5), false).await;
+        assert!(read_streamer_message(&storage, 5).await.unwrap().is_none());
+
+        let config = LakeReaderConfig {
+            location,
+            start_block_height: 2,
+            poll_interval: Duration::from_millis(10),
+        };
+        let (handle, mut stream) = streamer(config);
+        for height in [2, 4] {
+            let streamer_message = stream.recv().await.unwrap();
+            assert_eq!(streamer_message.block.header.height, height);
+            assert_eq!(streamer_message.shards.len(), 2);
+        }
+
+        write(&storage, &make_streamer_message(5), true).await;
+        assert_eq!(stream.recv().await.unwrap().block.header.height, 5);
+
+        drop(stream);
+        write(&storage, &make_streamer_message(6), true).await;
+        handle.await.unwrap().unwrap();
+    }
+}
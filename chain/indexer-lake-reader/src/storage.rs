@@ -0,0 +1,348 @@
+use near_indexer_primitives::types::{BlockHeight, ShardId};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Requests to S3 that take longer than this are failed, so that a stuck request doesn't stall
+/// the sink or the reader forever.
+const S3_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Key of the object holding the height of the last block written by the sink.
+///
+/// It is stored next to the blocks, but isn't a number, so it is never taken for a block.
+pub const PROGRESS_KEY: &str = "last_block_height";
+
+/// Number of consecutive heights without a block after which the filesystem storage stops
+/// checking the heights one by one and lists the directory to find the next block.
+const MAX_PROBED_GAP: u64 = 1000;
+
+/// Where the blocks are stored.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub enum LakeLocation {
+    /// Directory on the local filesystem.
+    Filesystem { root_dir: PathBuf },
+    /// S3 bucket, or a bucket of an S3-compatible service such as MinIO.
+    S3 {
+        bucket: String,
+        region: String,
+        /// Endpoint of an S3-compatible service, e.g. `http://localhost:9000`. The buckets of
+        /// custom endpoints are addressed path-style.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        endpoint: Option<String>,
+        /// JSON file with `access_key` and `secret_key`. If not given, the credentials are
+        /// looked up in the environment and the AWS profile.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        credentials_file: Option<PathBuf>,
+    },
+}
+
+/// Key of the `BlockView` of the block at the given height.
+///
+/// Heights are zero-padded to 12 digits, so that the keys are ordered by height.
+pub fn block_key(height: BlockHeight) -> String {
+    format!("{:012}/block.json", height)
+}
+
+/// Key of the `IndexerShard` of the given shard of the block at the given height.
+pub fn shard_key(height: BlockHeight, shard_id: ShardId) -> String {
+    format!("{:012}/shard_{}.json", height, shard_id)
+}
+
+fn parse_height(name: &str) -> Option<BlockHeight> {
+    if name.len() == 12 && name.bytes().all(|b| b.is_ascii_digit()) {
+        name.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct S3CredentialsConfig {
+    access_key: String,
+    secret_key: String,
+}
+
+enum Backend {
+    Filesystem(PathBuf),
+    S3(Box<s3::Bucket>),
+}
+
+/// Objects stored in a `LakeLocation`, addressed by keys such as those returned by
+/// `block_key()` and `shard_key()`.
+pub struct LakeStorage {
+    backend: Backend,
+}
+
+impl LakeStorage {
+    pub fn new(location: &LakeLocation) -> anyhow::Result<Self> {
+        let backend = match location {
+            LakeLocation::Filesystem { root_dir } => Backend::Filesystem(root_dir.clone()),
+            LakeLocation::S3 { bucket, region, endpoint, credentials_file } => {
+                let credentials = match credentials_file {
+                    Some(credentials_file) => {
+                        let config: S3CredentialsConfig =
+                            serde_json::from_slice(&std::fs::read(credentials_file)?)?;
+                        s3::creds::Credentials::new(
+                            Some(&config.access_key),
+                            Some(&config.secret_key),
+                            None,
+                            None,
+                            None,
+                        )
+                    }
+                    None => s3::creds::Credentials::default(),
+                }?;
+                let region = match endpoint {
+                    Some(endpoint) => {
+                        s3::Region::Custom { region: region.clone(), endpoint: endpoint.clone() }
+                    }
+                    None => region.parse::<s3::Region>()?,
+                };
+                let mut bucket = s3::Bucket::new(bucket, region, credentials)?;
+                if endpoint.is_some() {
+                    bucket.set_path_style();
+                }
+                bucket.set_request_timeout(Some(S3_REQUEST_TIMEOUT));
+                Backend::S3(Box::new(bucket))
+            }
+        };
+        Ok(Self { backend })
+    }
+
+    /// Downloads the object with the given key, returns `None` if there is no such object.
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match &self.backend {
+            Backend::Filesystem(root_dir) => match tokio::fs::read(root_dir.join(key)).await {
+                Ok(data) => Ok(Some(data)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+            Backend::S3(bucket) => match bucket.get_object(key).await {
+                Ok(response) if response.status_code() == 200 => {
+                    Ok(Some(response.bytes().to_vec()))
+                }
+                Ok(response) if response.status_code() == 404 => Ok(None),
+                Ok(response) => {
+                    anyhow::bail!("bad response status code {} for {}", response.status_code(), key)
+                }
+                Err(s3::error::S3Error::Http(404, _)) => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+        }
+    }
+
+    /// Uploads the object with the given key, overwriting the existing one.
+    ///
+    /// Files are written next to their final path first and then renamed, so that readers never
+    /// see a partially written file.
+    pub async fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        match &self.backend {
+            Backend::Filesystem(root_dir) => {
+                let path = root_dir.join(key);
+                if let Some(parent_dir) = path.parent() {
+                    tokio::fs::create_dir_all(parent_dir).await?;
+                }
+                let tmp_path = root_dir.join(format!("{}.tmp", key));
+                tokio::fs::write(&tmp_path, data).await?;
+                tokio::fs::rename(&tmp_path, &path).await?;
+            }
+            Backend::S3(bucket) => {
+                bucket.put_object(key, data).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Height of the last block written by the sink, if any.
+    pub async fn last_block_height(&self) -> anyhow::Result<Option<BlockHeight>> {
+        match self.get(PROGRESS_KEY).await? {
+            Some(value) => Ok(Some(String::from_utf8(value)?.trim().parse()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the heights of the stored blocks which are at least `start_height`, in increasing
+    /// order and at most `limit` of them.
+    ///
+    /// A height is listed as soon as any of the files of its block is stored, see
+    /// `read_streamer_message()` for how incomplete blocks are handled. If the sink's progress is
+    /// stored, the filesystem storage lists only the heights up to the last written block.
+    pub async fn list_heights(
+        &self,
+        start_height: BlockHeight,
+        limit: usize,
+    ) -> anyhow::Result<Vec<BlockHeight>> {
+        let mut heights = vec![];
+        match &self.backend {
+            Backend::Filesystem(root_dir) => {
+                let Some(last_height) = self.last_block_height().await? else {
+                    return list_dir_heights(root_dir, start_height, limit).await;
+                };
+                // The directories of the heights are checked one by one, so that polling for new
+                // blocks doesn't read the whole directory.
+                let mut height = start_height;
+                let mut gap = 0;
+                while height <= last_height && heights.len() < limit {
+                    if tokio::fs::try_exists(root_dir.join(format!("{:012}", height))).await? {
+                        heights.push(height);
+                        gap = 0;
+                    } else if gap == MAX_PROBED_GAP {
+                        // E.g. the start height is far below the first stored block.
+                        match list_dir_heights(root_dir, height, 1).await?.first() {
+                            Some(next_height) => height = *next_height,
+                            None => break,
+                        }
+                        gap = 0;
+                        continue;
+                    } else {
+                        gap += 1;
+                    }
+                    height += 1;
+                }
+            }
+            Backend::S3(bucket) => {
+                // Any key of the blocks at `start_height` and above is after the bare height.
+                let (result, _) = bucket
+                    .list_page(
+                        String::new(),
+                        Some("/".to_string()),
+                        None,
+                        Some(format!("{:012}", start_height)),
+                        Some(limit),
+                    )
+                    .await?;
+                heights.extend(
+                    result
+                        .common_prefixes
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|prefix| parse_height(prefix.prefix.trim_end_matches('/'))),
+                );
+            }
+        }
+        Ok(heights)
+    }
+}
+
+/// Lists the heights of the blocks stored in the directory by reading all its entries.
+async fn list_dir_heights(
+    root_dir: &Path,
+    start_height: BlockHeight,
+    limit: usize,
+) -> anyhow::Result<Vec<BlockHeight>> {
+    let mut heights = vec![];
+    let mut entries = match tokio::fs::read_dir(root_dir).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(heights),
+        Err(err) => return Err(err.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let height = entry.file_name().to_str().and_then(parse_height);
+        if let Some(height) = height.filter(|height| *height >= start_height) {
+            heights.push(height);
+        }
+    }
+    heights.sort_unstable();
+    heights.truncate(limit);
+    Ok(heights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        block_key, parse_height, shard_key, Backend, LakeLocation, LakeStorage, PROGRESS_KEY,
+    };
+    use near_indexer_primitives::types::ShardId;
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(block_key(42), "000000000042/block.json");
+        assert_eq!(shard_key(42, ShardId::new(3)), "000000000042/shard_3.json");
+        assert_eq!(parse_height("000000000042"), Some(42));
+        assert_eq!(parse_height("42"), None);
+        assert_eq!(parse_height(PROGRESS_KEY), None);
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage =
+            LakeStorage::new(&LakeLocation::Filesystem { root_dir: dir.path().to_path_buf() })
+                .unwrap();
+        assert_eq!(storage.list_heights(0, 10).await.unwrap(), Vec::<u64>::new());
+        assert_eq!(storage.get(&block_key(1)).await.unwrap(), None);
+
+        for height in [10, 3, 7] {
+            storage.put(&block_key(height), b"block").await.unwrap();
+        }
+        // Without the progress of the sink, the directory is listed.
+        assert_eq!(storage.list_heights(0, 10).await.unwrap(), vec![3, 7, 10]);
+        storage.put(PROGRESS_KEY, b"10").await.unwrap();
+        assert_eq!(storage.last_block_height().await.unwrap(), Some(10));
+        assert_eq!(storage.get(&block_key(3)).await.unwrap().as_deref(), Some(&b"block"[..]));
+        assert_eq!(storage.list_heights(0, 10).await.unwrap(), vec![3, 7, 10]);
+        assert_eq!(storage.list_heights(4, 10).await.unwrap(), vec![7, 10]);
+        assert_eq!(storage.list_heights(0, 2).await.unwrap(), vec![3, 7]);
+        assert_eq!(storage.list_heights(11, 10).await.unwrap(), Vec::<u64>::new());
+
+        // Blocks after the last written one are still being written.
+        storage.put(&shard_key(11, ShardId::new(0)), b"shard").await.unwrap();
+        assert_eq!(storage.list_heights(8, 10).await.unwrap(), vec![10]);
+
+        // Long gaps between the blocks are skipped by listing the directory.
+        let height = 10 + 3 * super::MAX_PROBED_GAP;
+        storage.put(&block_key(height), b"block").await.unwrap();
+        storage.put(PROGRESS_KEY, height.to_string().as_bytes()).await.unwrap();
+        assert_eq!(storage.list_heights(8, 10).await.unwrap(), vec![10, 11, height]);
+    }
+
+    #[test]
+    fn test_s3_path_style() {
+        let dir = tempfile::tempdir().unwrap();
+        let credentials_file = dir.path().join("credentials.json");
+        std::fs::write(&credentials_file, r#"{"access_key": "key", "secret_key": "secret"}"#)
+            .unwrap();
+        let location = |endpoint: Option<&str>| LakeLocation::S3 {
+            bucket: "near-lake".to_string(),
+            region: "eu-central-1".to_string(),
+            endpoint: endpoint.map(str::to_string),
+            credentials_file: Some(credentials_file.clone()),
+        };
+        let is_path_style =
+            |location: LakeLocation| match LakeStorage::new(&location).unwrap().backend {
+                Backend::S3(bucket) => bucket.is_path_style(),
+                Backend::Filesystem(_) => unreachable!(),
+            };
+        assert!(!is_path_style(location(None)));
+        assert!(is_path_style(location(Some("http://localhost:9000"))));
+    }
+
+    /// Runs against an S3-compatible service at `localhost:9000`, e.g. started with
+    /// `minio server <dir>`, which has an empty `near-lake-test` bucket. The credentials are taken
+    /// from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+    #[tokio::test]
+    #[ignore]
+    async fn test_s3_storage() {
+        let storage = LakeStorage::new(&LakeLocation::S3 {
+            bucket: "near-lake-test".to_string(),
+            region: "us-east-1".to_string(),
+            endpoint: Some("http://localhost:9000".to_string()),
+            credentials_file: None,
+        })
+        .unwrap();
+        assert_eq!(storage.get(&block_key(1)).await.unwrap(), None);
+
+        for height in [10, 3, 7] {
+            storage.put(&shard_key(height, ShardId::new(0)), b"shard").await.unwrap();
+            storage.put(&block_key(height), b"block").await.unwrap();
+        }
+        storage.put(PROGRESS_KEY, b"10").await.unwrap();
+        assert_eq!(storage.get(&block_key(3)).await.unwrap().as_deref(), Some(&b"block"[..]));
+        assert_eq!(storage.get(PROGRESS_KEY).await.unwrap().as_deref(), Some(&b"10"[..]));
+        assert_eq!(storage.list_heights(0, 10).await.unwrap(), vec![3, 7, 10]);
+        assert_eq!(storage.list_heights(4, 10).await.unwrap(), vec![7, 10]);
+        // The listing starts after the bare height, which is before any key of its block.
+        assert_eq!(storage.list_heights(7, 10).await.unwrap(), vec![7, 10]);
+        assert_eq!(storage.list_heights(0, 2).await.unwrap(), vec![3, 7]);
+        assert_eq!(storage.list_heights(11, 10).await.unwrap(), Vec::<u64>::new());
+    }
+}
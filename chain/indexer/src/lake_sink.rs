@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use near_indexer_lake_reader::{block_key, shard_key, LakeLocation, LakeStorage, PROGRESS_KEY};
+use near_primitives::types::BlockHeight;
+use tokio::sync::mpsc;
+
+use crate::{StreamerMessage, SyncModeEnum, INDEXER};
+
+/// Delay before retrying to write a block that failed to be written.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Writes `StreamerMessage`s to a local directory or an S3 bucket in the NEAR Lake layout: the
+/// `BlockView` to `<height>/block.json` and every `IndexerShard` to `<height>/shard_<id>.json`.
+/// The files can be streamed back with `near-indexer-lake-reader`.
+pub struct LakeSink {
+    storage: LakeStorage,
+}
+
+impl LakeSink {
+    pub fn new(location: &LakeLocation) -> anyhow::Result<Self> {
+        Ok(Self { storage: LakeStorage::new(location)? })
+    }
+
+    /// Height of the last block written by the sink, if any.
+    pub async fn last_block_height(&self) -> anyhow::Result<Option<BlockHeight>> {
+        self.storage.last_block_height().await
+    }
+
+    /// Returns the sync mode to start the Indexer with. `FromInterruption` resumes from the
+    /// block after the last one written by the sink, as the Indexer records a block as synced
+    /// once it is streamed, which may be before the sink has written it.
+    pub async fn sync_mode(&self, sync_mode: SyncModeEnum) -> anyhow::Result<SyncModeEnum> {
+        Ok(match sync_mode {
+            SyncModeEnum::FromInterruption => match self.last_block_height().await? {
+                Some(height) => SyncModeEnum::BlockHeight(height + 1),
+                None => SyncModeEnum::FromInterruption,
+            },
+            sync_mode => sync_mode,
+        })
+    }
+
+    /// Writes the block along with all its shards and records it as the last block written.
+    ///
+    /// `block.json` is written after the shards, so that readers never see an incomplete block.
+    pub async fn write(&self, streamer_message: &StreamerMessage) -> anyhow::Result<()> {
+        let height = streamer_message.block.header.height;
+        for shard in &streamer_message.shards {
+            let data = serde_json::to_vec(shard)?;
+            self.storage.put(&shard_key(height, shard.shard_id), &data).await?;
+        }
+        let data = serde_json::to_vec(&streamer_message.block)?;
+        self.storage.put(&block_key(height), &data).await?;
+        self.storage.put(PROGRESS_KEY, height.to_string().as_bytes()).await
+    }
+
+    /// Writes all the blocks of the stream until it ends. Blocks that fail to be written are
+    /// retried, so that the written blocks have no gaps.
+    pub async fn run(self, mut stream: mpsc::Receiver<StreamerMessage>) {
+        while let Some(streamer_message) = stream.recv().await {
+            let height = streamer_message.block.header.height;
+            while let Err(err) = self.write(&streamer_message).await {
+                tracing::warn!(target: INDEXER, height, ?err, "Failed to write block, retrying");
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+            tracing::debug!(target: INDEXER, height, "Block written by the Lake sink");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LakeSink;
+    use crate::{IndexerShard, StreamerMessage, SyncModeEnum};
+    use near_indexer_lake_reader::{LakeLocation, LakeReaderConfig};
+    use near_primitives::block::{genesis_chunks, Block};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::{BlockHeight, ShardId, StateRoot};
+    use near_primitives::version::PROTOCOL_VERSION;
+    use near_primitives::views;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    fn make_streamer_message(height: BlockHeight) -> StreamerMessage {
+        let shard_ids: Vec<_> = (0..2).map(ShardId::new).collect();
+        let chunks = genesis_chunks(
+            vec![StateRoot::new()],
+            vec![Some(Default::default()); shard_ids.len()],
+            &shard_ids,
+            1000,
+            height,
+            PROTOCOL_VERSION,
+        );
+        let block = Block::genesis(
+            PROTOCOL_VERSION,
+            chunks.into_iter().map(|chunk| chunk.take_header()).collect(),
+            near_time::Utc::UNIX_EPOCH,
+            height,
+            100,
+            1_000_000,
+            CryptoHash::default(),
+        );
+        let shards = shard_ids
+            .into_iter()
+            .map(|shard_id| IndexerShard {
+                shard_id,
+                chunk: None,
+                receipt_execution_outcomes: vec![],
+                state_changes: vec![views::StateChangeWithCauseView {
+                    cause: views::StateChangeCauseView::InitialState,
+                    value: views::StateChangeValueView::AccountDeletion {
+                        account_id: format!("shard{}.near", shard_id).parse().unwrap(),
+                    },
+                }],
+            })
+            .collect();
+        StreamerMessage {
+            block: views::BlockView::from_author_block("test.near".parse().unwrap(), block),
+            shards,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sink_reader_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = LakeLocation::Filesystem { root_dir: dir.path().to_path_buf() };
+        let streamer_messages: Vec<_> = [3, 4, 6].into_iter().map(make_streamer_message).collect();
+        let (sender, receiver) = mpsc::channel(streamer_messages.len());
+        for streamer_message in &streamer_messages {
+            sender.send(streamer_message.clone()).await.unwrap();
+        }
+        drop(sender);
+        LakeSink::new(&location).unwrap().run(receiver).await;
+
+        let config = LakeReaderConfig {
+            location,
+            start_block_height: 0,
+            poll_interval: Duration::from_millis(10),
+        };
+        let (handle, mut stream) = near_indexer_lake_reader::streamer(config);
+        for expected in &streamer_messages {
+            let streamer_message = stream.recv().await.unwrap();
+            assert_eq!(
+                serde_json::to_value(&streamer_message).unwrap(),
+                serde_json::to_value(expected).unwrap()
+            );
+        }
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_sync_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = LakeLocation::Filesystem { root_dir: dir.path().to_path_buf() };
+        let sink = LakeSink::new(&location).unwrap();
+        assert_eq!(sink.last_block_height().await.unwrap(), None);
+        // Nothing is written yet, so the Indexer resumes from where it was interrupted itself.
+        assert!(matches!(
+            sink.sync_mode(SyncModeEnum::FromInterruption).await.unwrap(),
+            SyncModeEnum::FromInterruption
+        ));
+
+        sink.write(&make_streamer_message(5)).await.unwrap();
+        sink.write(&make_streamer_message(7)).await.unwrap();
+        drop(sink);
+
+        // A restarted sink resumes from the block after the last one written.
+        let sink = LakeSink::new(&location).unwrap();
+        assert_eq!(sink.last_block_height().await.unwrap(), Some(7));
+        assert!(matches!(
+            sink.sync_mode(SyncModeEnum::FromInterruption).await.unwrap(),
+            SyncModeEnum::BlockHeight(8)
+        ));
+        assert!(matches!(
+            sink.sync_mode(SyncModeEnum::BlockHeight(3)).await.unwrap(),
+            SyncModeEnum::BlockHeight(3)
+        ));
+        assert!(matches!(
+            sink.sync_mode(SyncModeEnum::LatestSynced).await.unwrap(),
+            SyncModeEnum::LatestSynced
+        ));
+    }
+}
@@ -15,9 +15,14 @@ pub use near_indexer_primitives::{
     StreamerMessage,
 };
 
+pub use lake_sink::LakeSink;
 use near_epoch_manager::shard_tracker::ShardTracker;
-pub use streamer::build_streamer_message;
+pub use near_indexer_lake_reader::LakeLocation;
+pub use streamer::{
+    build_filtered_streamer_message, build_streamer_message, IndexerActionKind, IndexerFilter,
+};
 
+mod lake_sink;
 mod streamer;
 
 pub const INDEXER: &str = "indexer";
@@ -85,6 +90,8 @@ pub struct IndexerConfig {
     pub await_for_node_synced: AwaitForNodeSyncedEnum,
     /// Tells whether to validate the genesis file before starting
     pub validate_genesis: bool,
+    /// Selects the data to fetch and stream, `IndexerFilter::default()` streams everything
+    pub filter: IndexerFilter,
 }
 
 /// This is the core component, which handles `nearcore` and internal `streamer`.
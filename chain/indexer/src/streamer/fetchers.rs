@@ -12,6 +12,8 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::{types, views};
 
 use super::errors::FailedToFetchData;
+use super::filter::IndexerFilter;
+use super::metrics;
 use super::INDEXER;
 use near_epoch_manager::shard_tracker::ShardTracker;
 
@@ -89,9 +91,12 @@ pub(crate) async fn fetch_state_changes(
 
 /// Fetch all ExecutionOutcomeWithId for current block
 /// Returns a HashMap where the key is shard id IndexerExecutionOutcomeWithOptionalReceipt
+/// Outcomes of the shards not wanted by the `filter` are left out, and the receipts the `filter`
+/// rules out are not fetched.
 pub(crate) async fn fetch_outcomes(
     client: &Addr<near_client::ViewClientActor>,
     block_hash: CryptoHash,
+    filter: &IndexerFilter,
 ) -> Result<
     HashMap<near_primitives::types::ShardId, Vec<IndexerExecutionOutcomeWithOptionalReceipt>>,
     FailedToFetchData,
@@ -106,10 +111,18 @@ pub(crate) async fn fetch_outcomes(
         near_primitives::types::ShardId,
         Vec<IndexerExecutionOutcomeWithOptionalReceipt>,
     > = HashMap::new();
-    for (shard_id, shard_outcomes) in outcomes {
+    for (shard_id, shard_outcomes) in retain_wanted_shards(outcomes, filter) {
         tracing::debug!(target: INDEXER, "Fetching outcomes with receipts for shard: {}", shard_id);
         let mut outcomes_with_receipts: Vec<IndexerExecutionOutcomeWithOptionalReceipt> = vec![];
         for outcome in shard_outcomes {
+            if !filter.may_match_receipt_executed_by(&outcome.outcome.executor_id) {
+                metrics::FILTERED_OUT.with_label_values(&["receipt_fetch"]).inc();
+                outcomes_with_receipts.push(IndexerExecutionOutcomeWithOptionalReceipt {
+                    execution_outcome: outcome,
+                    receipt: None,
+                });
+                continue;
+            }
             let receipt = match fetch_receipt_by_id(&client, outcome.id).await {
                 Ok(res) => res,
                 Err(e) => {
@@ -157,22 +170,21 @@ async fn fetch_single_chunk(
         .map_err(|err| FailedToFetchData::String(err.to_string()))
 }
 
-/// Fetches all chunks belonging to given block.
+/// Fetches all chunks belonging to given block, except for the shards not wanted by the `filter`.
 /// Includes transactions and receipts in custom struct (to provide more info).
 pub(crate) async fn fetch_block_chunks(
     client: &Addr<near_client::ViewClientActor>,
     block: &views::BlockView,
     shard_tracker: &ShardTracker,
+    filter: &IndexerFilter,
 ) -> Result<Vec<views::ChunkView>, FailedToFetchData> {
     tracing::debug!(target: INDEXER, "Fetching chunks for block #{}", block.header.height);
-    let mut futures: futures::stream::FuturesUnordered<_> = block
-        .chunks
-        .iter()
-        .filter(|chunk| {
-            shard_tracker.care_about_shard(None, &block.header.prev_hash, chunk.shard_id, false)
-                && chunk.height_included == block.header.height
+    let mut futures: futures::stream::FuturesUnordered<_> =
+        chunks_to_fetch(&block.chunks, block.header.height, filter, |shard_id| {
+            shard_tracker.care_about_shard(None, &block.header.prev_hash, shard_id, false)
         })
-        .map(|chunk| fetch_single_chunk(&client, chunk.chunk_hash))
+        .into_iter()
+        .map(|chunk_hash| fetch_single_chunk(&client, chunk_hash))
         .collect();
     let mut chunks = Vec::<views::ChunkView>::with_capacity(futures.len());
     while let Some(chunk) = futures.next().await {
@@ -181,6 +193,37 @@ pub(crate) async fn fetch_block_chunks(
     Ok(chunks)
 }
 
+/// Hashes of the chunks of the tracked shards which are new at the given height and wanted by the
+/// `filter`.
+fn chunks_to_fetch(
+    chunks: &[views::ChunkHeaderView],
+    height: types::BlockHeight,
+    filter: &IndexerFilter,
+    tracks_shard: impl Fn(types::ShardId) -> bool,
+) -> Vec<CryptoHash> {
+    chunks
+        .iter()
+        .filter(|chunk| tracks_shard(chunk.shard_id) && chunk.height_included == height)
+        .filter(|chunk| {
+            let wanted = filter.wants_shard(chunk.shard_id);
+            if !wanted {
+                metrics::FILTERED_OUT.with_label_values(&["chunk"]).inc();
+            }
+            wanted
+        })
+        .map(|chunk| chunk.chunk_hash)
+        .collect()
+}
+
+/// Drops the outcomes of the shards not wanted by the `filter`.
+fn retain_wanted_shards<T>(
+    mut outcomes: HashMap<types::ShardId, T>,
+    filter: &IndexerFilter,
+) -> HashMap<types::ShardId, T> {
+    outcomes.retain(|shard_id, _| filter.wants_shard(*shard_id));
+    outcomes
+}
+
 pub(crate) async fn fetch_protocol_config(
     client: &Addr<near_client::ViewClientActor>,
     block_hash: near_primitives::hash::CryptoHash,
@@ -196,3 +239,77 @@ pub(crate) async fn fetch_protocol_config(
         .await?
         .map_err(|err| FailedToFetchData::String(err.to_string()))?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{chunks_to_fetch, retain_wanted_shards};
+    use crate::streamer::filter::IndexerFilter;
+    use near_crypto::{KeyType, Signature};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::{BlockHeight, ShardId};
+    use near_primitives::views::ChunkHeaderView;
+    use std::collections::HashMap;
+
+    fn chunk(shard_id: u64, height_included: BlockHeight) -> ChunkHeaderView {
+        ChunkHeaderView {
+            chunk_hash: CryptoHash::hash_borsh((shard_id, height_included)),
+            prev_block_hash: CryptoHash::default(),
+            outcome_root: CryptoHash::default(),
+            prev_state_root: CryptoHash::default(),
+            encoded_merkle_root: CryptoHash::default(),
+            encoded_length: 0,
+            height_created: height_included,
+            height_included,
+            shard_id: ShardId::new(shard_id),
+            gas_used: 0,
+            gas_limit: 0,
+            rent_paid: 0,
+            validator_reward: 0,
+            balance_burnt: 0,
+            outgoing_receipts_root: CryptoHash::default(),
+            tx_root: CryptoHash::default(),
+            validator_proposals: vec![],
+            congestion_info: None,
+            bandwidth_requests: None,
+            signature: Signature::empty(KeyType::ED25519),
+        }
+    }
+
+    #[test]
+    fn test_chunks_to_fetch() {
+        // The chunk of shard 2 is missing in the block at height 10.
+        let chunks = [chunk(0, 10), chunk(1, 10), chunk(2, 9), chunk(3, 10)];
+        let hashes = |shard_ids: &[u64]| -> Vec<CryptoHash> {
+            shard_ids.iter().map(|&shard_id| chunks[shard_id as usize].chunk_hash).collect()
+        };
+
+        let filter = IndexerFilter::default();
+        assert_eq!(chunks_to_fetch(&chunks, 10, &filter, |_| true), hashes(&[0, 1, 3]));
+        let tracked = |shard_id: ShardId| shard_id != ShardId::new(1);
+        assert_eq!(chunks_to_fetch(&chunks, 10, &filter, tracked), hashes(&[0, 3]));
+
+        let filter = IndexerFilter {
+            shard_ids: vec![ShardId::new(1), ShardId::new(2), ShardId::new(3)],
+            ..Default::default()
+        };
+        assert_eq!(chunks_to_fetch(&chunks, 10, &filter, |_| true), hashes(&[1, 3]));
+        assert_eq!(chunks_to_fetch(&chunks, 10, &filter, tracked), hashes(&[3]));
+    }
+
+    #[test]
+    fn test_retain_wanted_shards() {
+        let outcomes: HashMap<ShardId, Vec<u32>> =
+            (0..4).map(|shard_id| (ShardId::new(shard_id), vec![shard_id as u32])).collect();
+
+        let filter = IndexerFilter::default();
+        assert_eq!(retain_wanted_shards(outcomes.clone(), &filter), outcomes);
+
+        let filter = IndexerFilter {
+            shard_ids: vec![ShardId::new(1), ShardId::new(3), ShardId::new(5)],
+            ..Default::default()
+        };
+        let mut shard_ids: Vec<_> = retain_wanted_shards(outcomes, &filter).into_keys().collect();
+        shard_ids.sort();
+        assert_eq!(shard_ids, vec![ShardId::new(1), ShardId::new(3)]);
+    }
+}
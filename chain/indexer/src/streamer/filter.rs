@@ -0,0 +1,339 @@
+//! Server-side filtering of the data put into `StreamerMessage`s, so that the streamer doesn't
+//! fetch data nobody is going to look at.
+use near_primitives::types::{AccountId, ShardId};
+use near_primitives::views;
+
+/// Kind of an action, used to select transactions and receipts by the actions they contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexerActionKind {
+    CreateAccount,
+    DeployContract,
+    FunctionCall,
+    Transfer,
+    Stake,
+    AddKey,
+    DeleteKey,
+    DeleteAccount,
+    Delegate,
+}
+
+impl IndexerActionKind {
+    fn matches(self, action: &views::ActionView) -> bool {
+        match self {
+            Self::CreateAccount => matches!(action, views::ActionView::CreateAccount),
+            Self::DeployContract => matches!(action, views::ActionView::DeployContract { .. }),
+            Self::FunctionCall => matches!(action, views::ActionView::FunctionCall { .. }),
+            Self::Transfer => matches!(action, views::ActionView::Transfer { .. }),
+            Self::Stake => matches!(action, views::ActionView::Stake { .. }),
+            Self::AddKey => matches!(action, views::ActionView::AddKey { .. }),
+            Self::DeleteKey => matches!(action, views::ActionView::DeleteKey { .. }),
+            Self::DeleteAccount => matches!(action, views::ActionView::DeleteAccount { .. }),
+            Self::Delegate => matches!(action, views::ActionView::Delegate { .. }),
+        }
+    }
+}
+
+/// Selects the data streamed by the Indexer.
+///
+/// Empty lists don't restrict anything, so the default filter streams everything.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct IndexerFilter {
+    /// Patterns of the receivers of the transactions and receipts to stream, e.g. `app.near` or
+    /// `*.app.near`. `*` matches any sequence of characters.
+    pub receiver_ids: Vec<String>,
+    /// Patterns of the signers of the transactions and receipts to stream. Data receipts are
+    /// matched by their predecessor.
+    ///
+    /// If both `receiver_ids` and `signer_ids` are given, matching either of them is enough.
+    pub signer_ids: Vec<String>,
+    /// Shards to stream. Chunks and receipts of the other shards are not fetched at all and their
+    /// `IndexerShard`s are left empty. Execution outcomes and state changes are fetched for the
+    /// whole block and those of the other shards are dropped.
+    pub shard_ids: Vec<ShardId>,
+    /// Kinds of actions at least one of which a transaction or an action receipt must contain to
+    /// be streamed. Data receipts are only streamed if this is empty.
+    pub action_kinds: Vec<IndexerActionKind>,
+    /// Whether to fetch and stream state changes. The state changes are restricted to the
+    /// accounts matching `receiver_ids` or `signer_ids`.
+    pub state_changes: bool,
+    /// Whether to fetch and stream the execution outcomes of receipts. The outcomes of
+    /// transactions are always streamed along with the transactions.
+    pub execution_outcomes: bool,
+}
+
+impl Default for IndexerFilter {
+    fn default() -> Self {
+        Self {
+            receiver_ids: vec![],
+            signer_ids: vec![],
+            shard_ids: vec![],
+            action_kinds: vec![],
+            state_changes: true,
+            execution_outcomes: true,
+        }
+    }
+}
+
+impl IndexerFilter {
+    pub(crate) fn wants_shard(&self, shard_id: ShardId) -> bool {
+        self.shard_ids.is_empty() || self.shard_ids.contains(&shard_id)
+    }
+
+    pub(crate) fn matches_transaction(&self, transaction: &views::SignedTransactionView) -> bool {
+        self.matches_accounts(&transaction.receiver_id, &transaction.signer_id)
+            && self.matches_actions(&transaction.actions)
+    }
+
+    pub(crate) fn matches_receipt(&self, receipt: &views::ReceiptView) -> bool {
+        match &receipt.receipt {
+            views::ReceiptEnumView::Action { signer_id, actions, .. } => {
+                self.matches_accounts(&receipt.receiver_id, signer_id)
+                    && self.matches_actions(actions)
+            }
+            views::ReceiptEnumView::Data { .. } => {
+                self.matches_accounts(&receipt.receiver_id, &receipt.predecessor_id)
+                    && self.action_kinds.is_empty()
+            }
+        }
+    }
+
+    /// Whether a receipt executed by the given account may be streamed, to decide if the receipt
+    /// needs to be fetched at all. Only `receiver_ids` can be checked without the receipt.
+    pub(crate) fn may_match_receipt_executed_by(&self, executor_id: &AccountId) -> bool {
+        self.execution_outcomes
+            && (!self.signer_ids.is_empty()
+                || self.receiver_ids.is_empty()
+                || matches_any(&self.receiver_ids, executor_id))
+    }
+
+    pub(crate) fn matches_state_change(&self, change: &views::StateChangeWithCauseView) -> bool {
+        if self.receiver_ids.is_empty() && self.signer_ids.is_empty() {
+            return true;
+        }
+        let account_id = match &change.value {
+            views::StateChangeValueView::AccountUpdate { account_id, .. }
+            | views::StateChangeValueView::AccountDeletion { account_id }
+            | views::StateChangeValueView::AccessKeyUpdate { account_id, .. }
+            | views::StateChangeValueView::AccessKeyDeletion { account_id, .. }
+            | views::StateChangeValueView::DataUpdate { account_id, .. }
+            | views::StateChangeValueView::DataDeletion { account_id, .. }
+            | views::StateChangeValueView::ContractCodeUpdate { account_id, .. }
+            | views::StateChangeValueView::ContractCodeDeletion { account_id } => account_id,
+        };
+        matches_any(&self.receiver_ids, account_id) || matches_any(&self.signer_ids, account_id)
+    }
+
+    fn matches_accounts(&self, receiver_id: &AccountId, signer_id: &AccountId) -> bool {
+        (self.receiver_ids.is_empty() && self.signer_ids.is_empty())
+            || matches_any(&self.receiver_ids, receiver_id)
+            || matches_any(&self.signer_ids, signer_id)
+    }
+
+    fn matches_actions(&self, actions: &[views::ActionView]) -> bool {
+        self.action_kinds.is_empty()
+            || actions
+                .iter()
+                .any(|action| self.action_kinds.iter().any(|kind| kind.matches(action)))
+    }
+}
+
+fn matches_any(patterns: &[String], account_id: &AccountId) -> bool {
+    patterns.iter().any(|pattern| matches_pattern(pattern, account_id.as_str()))
+}
+
+/// Matches `text` against a pattern in which `*` stands for any sequence of characters.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // `split` always yields at least one part.
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` in the pattern.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_pattern, IndexerActionKind, IndexerFilter};
+    use near_crypto::{KeyType, PublicKey, Signature};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::views;
+
+    fn filter(receiver_ids: &[&str], signer_ids: &[&str]) -> IndexerFilter {
+        IndexerFilter {
+            receiver_ids: receiver_ids.iter().map(|pattern| pattern.to_string()).collect(),
+            signer_ids: signer_ids.iter().map(|pattern| pattern.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn transaction(
+        signer_id: &str,
+        receiver_id: &str,
+        actions: Vec<views::ActionView>,
+    ) -> views::SignedTransactionView {
+        views::SignedTransactionView {
+            signer_id: signer_id.parse().unwrap(),
+            public_key: PublicKey::empty(KeyType::ED25519),
+            nonce: 0,
+            receiver_id: receiver_id.parse().unwrap(),
+            actions,
+            priority_fee: 0,
+            signature: Signature::empty(KeyType::ED25519),
+            hash: CryptoHash::default(),
+        }
+    }
+
+    fn action_receipt(
+        signer_id: &str,
+        receiver_id: &str,
+        actions: Vec<views::ActionView>,
+    ) -> views::ReceiptView {
+        views::ReceiptView {
+            predecessor_id: signer_id.parse().unwrap(),
+            receiver_id: receiver_id.parse().unwrap(),
+            receipt_id: CryptoHash::default(),
+            receipt: views::ReceiptEnumView::Action {
+                signer_id: signer_id.parse().unwrap(),
+                signer_public_key: PublicKey::empty(KeyType::ED25519),
+                gas_price: 0,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions,
+                is_promise_yield: false,
+            },
+            priority: 0,
+        }
+    }
+
+    fn data_receipt(predecessor_id: &str, receiver_id: &str) -> views::ReceiptView {
+        views::ReceiptView {
+            predecessor_id: predecessor_id.parse().unwrap(),
+            receiver_id: receiver_id.parse().unwrap(),
+            receipt_id: CryptoHash::default(),
+            receipt: views::ReceiptEnumView::Data {
+                data_id: CryptoHash::default(),
+                data: None,
+                is_promise_resume: false,
+            },
+            priority: 0,
+        }
+    }
+
+    fn account_deletion(account_id: &str) -> views::StateChangeWithCauseView {
+        views::StateChangeWithCauseView {
+            cause: views::StateChangeCauseView::InitialState,
+            value: views::StateChangeValueView::AccountDeletion {
+                account_id: account_id.parse().unwrap(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_matches_transaction() {
+        let transfer = || vec![views::ActionView::Transfer { deposit: 1 }];
+        let tx = transaction("alice.near", "my.app.near", transfer());
+        assert!(IndexerFilter::default().matches_transaction(&tx));
+        assert!(filter(&["*.app.near"], &[]).matches_transaction(&tx));
+        assert!(filter(&[], &["alice.near"]).matches_transaction(&tx));
+        // Matching either the receiver or the signer is enough.
+        assert!(filter(&["other.near"], &["alice.near"]).matches_transaction(&tx));
+        assert!(!filter(&["other.near"], &["bob.near"]).matches_transaction(&tx));
+
+        let mut filter = filter(&["*.app.near"], &[]);
+        filter.action_kinds = vec![IndexerActionKind::FunctionCall, IndexerActionKind::Transfer];
+        assert!(filter.matches_transaction(&tx));
+        let tx = transaction("alice.near", "my.app.near", vec![views::ActionView::CreateAccount]);
+        assert!(!filter.matches_transaction(&tx));
+    }
+
+    #[test]
+    fn test_matches_receipt() {
+        let receipt =
+            action_receipt("alice.near", "app.near", vec![views::ActionView::CreateAccount]);
+        assert!(IndexerFilter::default().matches_receipt(&receipt));
+        assert!(filter(&["app.near"], &[]).matches_receipt(&receipt));
+        assert!(filter(&[], &["alice.near"]).matches_receipt(&receipt));
+        assert!(!filter(&["other.near"], &["bob.near"]).matches_receipt(&receipt));
+
+        let mut create_account = filter(&["app.near"], &[]);
+        create_account.action_kinds = vec![IndexerActionKind::CreateAccount];
+        assert!(create_account.matches_receipt(&receipt));
+        let mut transfer = filter(&["app.near"], &[]);
+        transfer.action_kinds = vec![IndexerActionKind::Transfer];
+        assert!(!transfer.matches_receipt(&receipt));
+
+        // Data receipts are matched by their predecessor and have no actions.
+        let receipt = data_receipt("alice.near", "app.near");
+        assert!(IndexerFilter::default().matches_receipt(&receipt));
+        assert!(filter(&["app.near"], &[]).matches_receipt(&receipt));
+        assert!(filter(&[], &["alice.near"]).matches_receipt(&receipt));
+        assert!(!filter(&[], &["app.near"]).matches_receipt(&receipt));
+        assert!(!create_account.matches_receipt(&receipt));
+        let action_kinds_only = IndexerFilter {
+            action_kinds: vec![IndexerActionKind::FunctionCall],
+            ..Default::default()
+        };
+        assert!(!action_kinds_only.matches_receipt(&receipt));
+    }
+
+    #[test]
+    fn test_may_match_receipt_executed_by() {
+        let app = "app.near".parse().unwrap();
+        let other = "other.near".parse().unwrap();
+        assert!(IndexerFilter::default().may_match_receipt_executed_by(&app));
+        assert!(filter(&["app.near"], &[]).may_match_receipt_executed_by(&app));
+        assert!(!filter(&["app.near"], &[]).may_match_receipt_executed_by(&other));
+        // The signer is only known from the receipt, so it has to be fetched.
+        assert!(filter(&["app.near"], &["alice.near"]).may_match_receipt_executed_by(&other));
+        assert!(filter(&[], &["alice.near"]).may_match_receipt_executed_by(&other));
+
+        let mut filter = filter(&["app.near"], &["alice.near"]);
+        filter.execution_outcomes = false;
+        assert!(!filter.may_match_receipt_executed_by(&app));
+        assert!(!filter.may_match_receipt_executed_by(&other));
+        let filter = IndexerFilter { execution_outcomes: false, ..Default::default() };
+        assert!(!filter.may_match_receipt_executed_by(&app));
+    }
+
+    #[test]
+    fn test_matches_state_change() {
+        let change = account_deletion("my.app.near");
+        assert!(IndexerFilter::default().matches_state_change(&change));
+        assert!(filter(&["*.app.near"], &[]).matches_state_change(&change));
+        assert!(filter(&[], &["my.app.near"]).matches_state_change(&change));
+        assert!(!filter(&["app.near"], &["alice.near"]).matches_state_change(&change));
+        // Action kinds don't restrict the state changes.
+        let filter = IndexerFilter {
+            action_kinds: vec![IndexerActionKind::Stake],
+            ..filter(&["*.app.near"], &[])
+        };
+        assert!(filter.matches_state_change(&change));
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("app.near", "app.near"));
+        assert!(!matches_pattern("app.near", "my.app.near"));
+        assert!(matches_pattern("*.app.near", "my.app.near"));
+        assert!(!matches_pattern("*.app.near", "app.near"));
+        assert!(matches_pattern("*", "app.near"));
+        assert!(matches_pattern("token.*.near", "token.v2.near"));
+        assert!(!matches_pattern("token.*.near", "token.near"));
+        assert!(matches_pattern("a*b*c", "abc"));
+        assert!(!matches_pattern("a*b*c", "acb"));
+    }
+}
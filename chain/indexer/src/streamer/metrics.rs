@@ -1,6 +1,6 @@
 use near_o11y::metrics::{
-    try_create_histogram, try_create_int_counter, try_create_int_gauge, Histogram, IntCounter,
-    IntGauge,
+    try_create_histogram, try_create_int_counter, try_create_int_counter_vec, try_create_int_gauge,
+    Histogram, IntCounter, IntCounterVec, IntGauge,
 };
 use std::sync::LazyLock;
 
@@ -52,3 +52,14 @@ pub(crate) static LOCAL_RECEIPT_LOOKUP_IN_HISTORY_BLOCKS_BACK: LazyLock<IntGauge
         )
         .unwrap()
     });
+
+pub(crate) static FILTERED_OUT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_indexer_filtered_out_total",
+        "Number of items left out of streamer messages by the IndexerFilter, by kind. Chunks, \
+         receipt fetches and state changes fetches are not fetched from the node at all, other \
+         items are fetched and dropped",
+        &["kind"],
+    )
+    .unwrap()
+});
@@ -28,8 +28,11 @@ use crate::INDEXER;
 use crate::{AwaitForNodeSyncedEnum, IndexerConfig};
 use near_epoch_manager::shard_tracker::ShardTracker;
 
+pub use self::filter::{IndexerActionKind, IndexerFilter};
+
 mod errors;
 mod fetchers;
+mod filter;
 mod metrics;
 mod utils;
 
@@ -76,9 +79,20 @@ pub async fn build_streamer_message(
     client: &Addr<near_client::ViewClientActor>,
     block: views::BlockView,
     shard_tracker: &ShardTracker,
+) -> Result<StreamerMessage, FailedToFetchData> {
+    build_filtered_streamer_message(client, block, shard_tracker, &IndexerFilter::default()).await
+}
+
+/// Same as [`build_streamer_message`], but only fetches and returns the parts of the block
+/// selected by the `filter`.
+pub async fn build_filtered_streamer_message(
+    client: &Addr<near_client::ViewClientActor>,
+    block: views::BlockView,
+    shard_tracker: &ShardTracker,
+    filter: &IndexerFilter,
 ) -> Result<StreamerMessage, FailedToFetchData> {
     let _timer = metrics::BUILD_STREAMER_MESSAGE_TIME.start_timer();
-    let chunks = fetch_block_chunks(&client, &block, shard_tracker).await?;
+    let chunks = fetch_block_chunks(&client, &block, shard_tracker, filter).await?;
 
     let protocol_config_view = fetch_protocol_config(&client, block.header.hash).await?;
     let shard_ids = protocol_config_view.shard_layout.shard_ids();
@@ -86,19 +100,30 @@ pub async fn build_streamer_message(
     let runtime_config_store = near_parameters::RuntimeConfigStore::new(None);
     let runtime_config = runtime_config_store.get_config(protocol_config_view.protocol_version);
 
-    let mut shards_outcomes = fetch_outcomes(&client, block.header.hash).await?;
-    let mut state_changes = fetch_state_changes(
-        &client,
-        block.header.hash,
-        near_primitives::types::EpochId(block.header.epoch_id),
-    )
-    .await?;
+    let mut shards_outcomes = fetch_outcomes(&client, block.header.hash, filter).await?;
+    let mut state_changes = if filter.state_changes {
+        fetch_state_changes(
+            &client,
+            block.header.hash,
+            near_primitives::types::EpochId(block.header.epoch_id),
+        )
+        .await?
+    } else {
+        metrics::FILTERED_OUT.with_label_values(&["state_changes_fetch"]).inc();
+        HashMap::new()
+    };
     let mut indexer_shards = shard_ids
-        .map(|shard_id| IndexerShard {
-            shard_id,
-            chunk: None,
-            receipt_execution_outcomes: vec![],
-            state_changes: state_changes.remove(&shard_id).unwrap_or_default(),
+        .map(|shard_id| {
+            let mut shard_state_changes = state_changes.remove(&shard_id).unwrap_or_default();
+            retain_counted(&mut shard_state_changes, "state_change", |change| {
+                filter.wants_shard(shard_id) && filter.matches_state_change(change)
+            });
+            IndexerShard {
+                shard_id,
+                chunk: None,
+                receipt_execution_outcomes: vec![],
+                state_changes: shard_state_changes,
+            }
         })
         .collect::<Vec<_>>();
 
@@ -113,7 +138,7 @@ pub async fn build_streamer_message(
         // Take execution outcomes for receipts from the vec and keep only the ones for transactions
         let mut receipt_outcomes = outcomes.split_off(transactions.len());
 
-        let indexer_transactions = transactions
+        let mut indexer_transactions = transactions
             .into_iter()
             .zip(outcomes.into_iter())
             .map(|(transaction, outcome)| {
@@ -160,6 +185,8 @@ pub async fn build_streamer_message(
 
         let mut chunk_receipts = chunk_local_receipts;
 
+        retain_wanted_receipt_outcomes(&mut receipt_outcomes, filter);
+
         let mut receipt_execution_outcomes: Vec<IndexerExecutionOutcomeWithReceipt> = vec![];
         for outcome in receipt_outcomes {
             let IndexerExecutionOutcomeWithOptionalReceipt { execution_outcome, receipt } = outcome;
@@ -207,6 +234,10 @@ pub async fn build_streamer_message(
                     .await?
                 }
             };
+            if !filter.matches_receipt(&receipt) {
+                metrics::FILTERED_OUT.with_label_values(&["receipt_execution_outcome"]).inc();
+                continue;
+            }
             receipt_execution_outcomes
                 .push(IndexerExecutionOutcomeWithReceipt { execution_outcome, receipt });
         }
@@ -235,6 +266,11 @@ pub async fn build_streamer_message(
         }
 
         chunk_receipts.extend(chunk_non_local_receipts);
+        retain_counted(&mut chunk_receipts, "receipt", |receipt| filter.matches_receipt(receipt));
+        // Filtered only now, as the local receipts must be built from all the transactions
+        retain_counted(&mut indexer_transactions, "transaction", |tx| {
+            filter.matches_transaction(&tx.transaction)
+        });
         // Find the shard index for the chunk by shard_id
         let shard_index = protocol_config_view
             .shard_layout
@@ -254,11 +290,15 @@ pub async fn build_streamer_message(
     // Ideally we expect `shards_outcomes` to be empty by this time, but if something went wrong with
     // chunks and we end up with non-empty `shards_outcomes` we want to be sure we put them into IndexerShard
     // That might happen before the fix https://github.com/near/nearcore/pull/4228
-    for (shard_id, outcomes) in shards_outcomes {
+    for (shard_id, mut outcomes) in shards_outcomes {
         let shard_index = protocol_config_view
             .shard_layout
             .get_shard_index(shard_id)
             .map_err(|e| FailedToFetchData::String(e.to_string()))?;
+        retain_counted(&mut outcomes, "receipt_execution_outcome", |outcome| {
+            filter.may_match_receipt_executed_by(&outcome.execution_outcome.outcome.executor_id)
+                && outcome.receipt.as_ref().map_or(true, |receipt| filter.matches_receipt(receipt))
+        });
         indexer_shards[shard_index].receipt_execution_outcomes.extend(outcomes.into_iter().map(
             |outcome| IndexerExecutionOutcomeWithReceipt {
                 execution_outcome: outcome.execution_outcome,
@@ -270,6 +310,35 @@ pub async fn build_streamer_message(
     Ok(StreamerMessage { block, shards: indexer_shards })
 }
 
+/// Keeps the items the filter wants, counting the rest in the `FILTERED_OUT` metric.
+fn retain_counted<T>(items: &mut Vec<T>, kind: &str, mut wanted: impl FnMut(&T) -> bool) {
+    let len = items.len();
+    items.retain(|item| wanted(item));
+    let filtered_out = len - items.len();
+    if filtered_out > 0 {
+        metrics::FILTERED_OUT.with_label_values(&[kind]).inc_by(filtered_out as u64);
+    }
+}
+
+/// Drops the outcomes of the receipts the `filter` rules out. Their receipts were not fetched, so
+/// they must not be looked for, and the delayed local receipts they would have taken from the
+/// cache are forgotten.
+fn retain_wanted_receipt_outcomes(
+    outcomes: &mut Vec<IndexerExecutionOutcomeWithOptionalReceipt>,
+    filter: &IndexerFilter,
+) {
+    retain_counted(outcomes, "receipt_execution_outcome", |outcome| {
+        let wanted =
+            filter.may_match_receipt_executed_by(&outcome.execution_outcome.outcome.executor_id);
+        if !wanted && outcome.receipt.is_none() {
+            if let Ok(mut cache) = DELAYED_LOCAL_RECEIPTS_CACHE.write() {
+                cache.remove(&outcome.execution_outcome.id);
+            }
+        }
+        wanted
+    });
+}
+
 // Receipt might be missing only in case of delayed local receipt
 // that appeared in some of the previous blocks
 // we will be iterating over previous blocks until we found the receipt
@@ -335,10 +404,12 @@ async fn find_local_receipt_by_id_in_block(
     receipt_id: near_primitives::hash::CryptoHash,
     shard_tracker: &ShardTracker,
 ) -> Result<Option<views::ReceiptView>, FailedToFetchData> {
-    let chunks = fetch_block_chunks(&client, &block, shard_tracker).await?;
+    // The local receipt may be in any of the shards, whatever the filter is.
+    let filter = IndexerFilter::default();
+    let chunks = fetch_block_chunks(&client, &block, shard_tracker, &filter).await?;
 
     let protocol_config_view = fetch_protocol_config(&client, block.header.hash).await?;
-    let mut shards_outcomes = fetch_outcomes(&client, block.header.hash).await?;
+    let mut shards_outcomes = fetch_outcomes(&client, block.header.hash, &filter).await?;
 
     for chunk in chunks {
         let views::ChunkView { header, transactions, .. } = chunk;
@@ -448,7 +519,13 @@ pub(crate) async fn start(
         for block_height in start_syncing_block_height..=latest_block_height {
             metrics::CURRENT_BLOCK_HEIGHT.set(block_height as i64);
             if let Ok(block) = fetch_block_by_height(&view_client, block_height).await {
-                let response = build_streamer_message(&view_client, block, &shard_tracker).await;
+                let response = build_filtered_streamer_message(
+                    &view_client,
+                    block,
+                    &shard_tracker,
+                    &indexer_config.filter,
+                )
+                .await;
 
                 match response {
                     Ok(streamer_message) => {
@@ -477,3 +554,83 @@ pub(crate) async fn start(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{retain_wanted_receipt_outcomes, DELAYED_LOCAL_RECEIPTS_CACHE};
+    use crate::streamer::filter::IndexerFilter;
+    use near_indexer_primitives::IndexerExecutionOutcomeWithOptionalReceipt;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::views;
+
+    fn receipt(receipt_id: CryptoHash, receiver_id: &str) -> views::ReceiptView {
+        views::ReceiptView {
+            predecessor_id: "alice.near".parse().unwrap(),
+            receiver_id: receiver_id.parse().unwrap(),
+            receipt_id,
+            receipt: views::ReceiptEnumView::Data {
+                data_id: CryptoHash::default(),
+                data: None,
+                is_promise_resume: false,
+            },
+            priority: 0,
+        }
+    }
+
+    fn outcome(
+        receipt_id: CryptoHash,
+        executor_id: &str,
+        receipt: Option<views::ReceiptView>,
+    ) -> IndexerExecutionOutcomeWithOptionalReceipt {
+        IndexerExecutionOutcomeWithOptionalReceipt {
+            execution_outcome: views::ExecutionOutcomeWithIdView {
+                proof: vec![],
+                block_hash: CryptoHash::default(),
+                id: receipt_id,
+                outcome: views::ExecutionOutcomeView {
+                    logs: vec![],
+                    receipt_ids: vec![],
+                    gas_burnt: 0,
+                    tokens_burnt: 0,
+                    executor_id: executor_id.parse().unwrap(),
+                    status: views::ExecutionStatusView::Unknown,
+                    metadata: views::ExecutionMetadataView::default(),
+                },
+            },
+            receipt,
+        }
+    }
+
+    #[test]
+    fn test_retain_wanted_receipt_outcomes() {
+        // The ids are unique to this test, as the cache is shared by the whole process.
+        let wanted_id = CryptoHash::hash_bytes(b"test_retain_wanted_receipt_outcomes wanted");
+        let unwanted_id = CryptoHash::hash_bytes(b"test_retain_wanted_receipt_outcomes unwanted");
+        let fetched_id = CryptoHash::hash_bytes(b"test_retain_wanted_receipt_outcomes fetched");
+        {
+            let mut cache = DELAYED_LOCAL_RECEIPTS_CACHE.write().unwrap();
+            cache.insert(wanted_id, receipt(wanted_id, "app.near"));
+            cache.insert(unwanted_id, receipt(unwanted_id, "other.near"));
+        }
+        let mut outcomes = vec![
+            outcome(wanted_id, "app.near", None),
+            outcome(unwanted_id, "other.near", None),
+            outcome(fetched_id, "other.near", Some(receipt(fetched_id, "other.near"))),
+        ];
+
+        // Nothing is dropped by the default filter.
+        retain_wanted_receipt_outcomes(&mut outcomes, &IndexerFilter::default());
+        assert_eq!(outcomes.len(), 3);
+
+        let filter =
+            IndexerFilter { receiver_ids: vec!["app.near".to_string()], ..Default::default() };
+        retain_wanted_receipt_outcomes(&mut outcomes, &filter);
+        let ids: Vec<_> = outcomes.iter().map(|outcome| outcome.execution_outcome.id).collect();
+        assert_eq!(ids, vec![wanted_id]);
+        let mut cache = DELAYED_LOCAL_RECEIPTS_CACHE.write().unwrap();
+        // The delayed local receipt of the dropped outcome is forgotten, the other one is left
+        // for the outcome to take.
+        assert!(!cache.contains_key(&unwanted_id));
+        assert!(cache.remove(&wanted_id).is_some());
+    }
+}
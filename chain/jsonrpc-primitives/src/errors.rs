@@ -91,6 +91,21 @@ impl RpcError {
         }
     }
 
+    /// Create an invalid request error.
+    ///
+    /// Used when the payload is valid JSON but not a valid request, e.g. an
+    /// empty or oversized batch.
+    pub fn invalid_request(e: String) -> Self {
+        RpcError {
+            code: -32_600,
+            message: "Invalid request".to_owned(),
+            data: Some(Value::String(e.clone())),
+            error_struct: Some(RpcErrorKind::RequestValidationError(
+                RpcRequestValidationErrorKind::ParseError { error_message: e },
+            )),
+        }
+    }
+
     pub fn serialization_error(e: String) -> Self {
         RpcError::new_internal_error(Some(Value::String(e.clone())), e)
     }
@@ -130,6 +145,19 @@ impl RpcError {
         }
     }
 
+    /// Create an error returned to clients whose requests have been throttled.
+    pub fn rate_limit_exceeded(e: String, info: Value) -> Self {
+        RpcError {
+            code: -32_005,
+            message: "Rate limit exceeded".to_owned(),
+            data: Some(Value::String(e)),
+            error_struct: Some(RpcErrorKind::HandlerError(serde_json::json!({
+                "name": "RATE_LIMIT_EXCEEDED",
+                "info": info,
+            }))),
+        }
+    }
+
     /// Create a method not found error.
     pub fn method_not_found(method: String) -> Self {
         RpcError {
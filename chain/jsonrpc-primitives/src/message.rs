@@ -142,6 +142,16 @@ pub struct Notification {
     pub params: Value,
 }
 
+impl Notification {
+    /// Converts the notification into a request with a `null` id.
+    ///
+    /// This allows notifications to be handled by the same code path as
+    /// requests.  The caller is responsible for discarding the response.
+    pub fn into_request(self) -> Request {
+        Request { jsonrpc: self.jsonrpc, method: self.method, params: self.params, id: Value::Null }
+    }
+}
+
 /// One message of the JSON RPC protocol.
 ///
 /// One message, directly mapped from the structures of the protocol. See the
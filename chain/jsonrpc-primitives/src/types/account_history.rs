@@ -0,0 +1,65 @@
+use near_crypto::PublicKey;
+use near_primitives::types::{AccountId, BlockHeight};
+use near_primitives::views::{AccountActivityCursor, AccountActivityView};
+
+/// Number of entries returned when the request doesn't specify a limit.
+pub const DEFAULT_ACCOUNT_HISTORY_LIMIT: usize = 100;
+/// Maximum number of entries returned by a single request.
+pub const MAX_ACCOUNT_HISTORY_LIMIT: usize = 1000;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcAccountHistoryRequest {
+    pub account_id: AccountId,
+    /// Lowest height of blocks to return the activity for.  Defaults to the tail of the chain,
+    /// i.e. the lowest height which hasn't been garbage collected.
+    #[serde(default)]
+    pub from_block_height: Option<BlockHeight>,
+    /// Highest height of blocks to return the activity for.  Defaults to the head of the chain.
+    #[serde(default)]
+    pub to_block_height: Option<BlockHeight>,
+    /// `next_cursor` returned by the previous request.
+    #[serde(default)]
+    pub cursor: Option<AccountActivityCursor>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcAccessKeyHistoryRequest {
+    pub public_key: PublicKey,
+    #[serde(flatten)]
+    pub account_history_request: RpcAccountHistoryRequest,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcAccountHistoryResponse {
+    pub activity: Vec<AccountActivityView>,
+    /// Cursor to pass to the next request to get more activity, if there is any.
+    pub next_cursor: Option<AccountActivityCursor>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcAccountHistoryError {
+    #[error("Account activity index is not enabled on this node")]
+    IndexDisabled,
+    #[error("Account activity below height {tail_height} has been garbage collected")]
+    GarbageCollected { tail_height: BlockHeight },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcAccountHistoryError> for crate::errors::RpcError {
+    fn from(error: RpcAccountHistoryError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcAccountHistoryError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
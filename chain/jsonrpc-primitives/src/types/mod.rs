@@ -1,3 +1,4 @@
+pub mod account_history;
 pub mod blocks;
 pub mod changes;
 pub mod chunks;
@@ -11,8 +12,10 @@ pub mod maintenance;
 pub mod network_info;
 pub mod query;
 pub mod receipts;
+pub mod relayer;
 pub mod sandbox;
 pub mod split_storage;
 pub mod status;
+pub mod subscriptions;
 pub mod transactions;
 pub mod validator;
@@ -0,0 +1,64 @@
+use near_primitives::types::{AccountId, BlockHeight, Gas, Nonce};
+use serde_json::Value;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcRelayDelegateActionRequest {
+    pub signed_delegate_action: near_primitives::action::delegate::SignedDelegateAction,
+    #[serde(default)]
+    pub wait_until: near_primitives::views::TxExecutionStatus,
+}
+
+#[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcRelayError {
+    #[error("The relayer is not enabled on this node")]
+    RelayerDisabled,
+    #[error("The delegate action has an invalid signature")]
+    InvalidSignature,
+    #[error("Public key {public_key} is not an access key of {sender_id}")]
+    UnknownAccessKey { sender_id: AccountId, public_key: near_crypto::PublicKey },
+    #[error("Nonce {nonce} must be larger than the nonce {access_key_nonce} of the access key")]
+    InvalidNonce { nonce: Nonce, access_key_nonce: Nonce },
+    #[error(
+        "The delegate action expired at height {max_block_height}, the head is at {block_height}"
+    )]
+    Expired { max_block_height: BlockHeight, block_height: BlockHeight },
+    #[error("Relaying delegate actions to {receiver_id} is not allowed")]
+    ReceiverNotAllowed { receiver_id: AccountId },
+    #[error("Relaying calls to method {method_name} is not allowed")]
+    MethodNotAllowed { method_name: String },
+    #[error("Relaying {action} actions is not allowed, only function calls are relayed")]
+    ActionNotAllowed { action: String },
+    #[error("Gas budget of {sender_id} exceeded: {used} of {budget} gas is already used")]
+    GasBudgetExceeded { sender_id: AccountId, budget: Gas, used: Gas },
+    #[error("Relayed transaction failed: {error}")]
+    TransactionError { error: crate::types::transactions::RpcTransactionError },
+    #[error("The node reached its limits. Try again later. More details: {debug_info}")]
+    InternalError { debug_info: String },
+}
+
+impl From<crate::types::transactions::RpcTransactionError> for RpcRelayError {
+    fn from(error: crate::types::transactions::RpcTransactionError) -> Self {
+        Self::TransactionError { error }
+    }
+}
+
+impl From<RpcRelayError> for crate::errors::RpcError {
+    fn from(error: RpcRelayError) -> Self {
+        // Errors of the relayed transaction are reported the same way as by `send_tx`.
+        if let RpcRelayError::TransactionError { error } = error {
+            return error.into();
+        }
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcRelayError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
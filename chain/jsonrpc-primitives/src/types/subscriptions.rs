@@ -0,0 +1,79 @@
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, ShardId};
+use serde_json::Value;
+
+/// Identifier of a subscription.  Unique within a single WebSocket connection.
+pub type SubscriptionId = u64;
+
+/// Name of the notification method used to deliver subscription events.
+pub const SUBSCRIPTION_NOTIFICATION_METHOD: &str = "subscription";
+
+/// Name of the notification method sent when the connection fell behind the
+/// node and some blocks were skipped.
+pub const SUBSCRIPTION_LAGGED_NOTIFICATION_METHOD: &str = "subscription_lagged";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcSubscribeChunksRequest {
+    pub shard_id: ShardId,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcSubscribeTxStatusRequest {
+    pub tx_hash: CryptoHash,
+    pub sender_account_id: AccountId,
+    /// The subscription fires once, as soon as the transaction reaches this
+    /// execution status, and is then removed.
+    #[serde(default)]
+    pub wait_until: near_primitives::views::TxExecutionStatus,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcSubscribeAccountChangesRequest {
+    pub account_ids: Vec<AccountId>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcUnsubscribeRequest {
+    pub subscription: SubscriptionId,
+}
+
+/// Parameters of the `subscription` notification.
+///
+/// `result` is a `BlockView`, `ChunkView`, `RpcTransactionResponse` or
+/// `RpcStateChangesInBlockResponse` depending on the kind of the subscription.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcSubscriptionNotification {
+    pub subscription: SubscriptionId,
+    pub result: Value,
+}
+
+/// Parameters of the `subscription_lagged` notification.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcSubscriptionLaggedNotification {
+    pub skipped_blocks: u64,
+}
+
+#[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSubscriptionError {
+    #[error("Too many subscriptions, at most {limit} are allowed per connection")]
+    TooManySubscriptions { limit: usize },
+    #[error("Subscription {subscription} does not exist")]
+    UnknownSubscription { subscription: SubscriptionId },
+}
+
+impl From<RpcSubscriptionError> for crate::errors::RpcError {
+    fn from(error: RpcSubscriptionError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSubscriptionError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
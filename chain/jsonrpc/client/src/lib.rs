@@ -5,6 +5,10 @@ use near_jsonrpc_primitives::message::{from_slice, Message};
 use near_jsonrpc_primitives::types::changes::{
     RpcStateChangesInBlockByTypeRequest, RpcStateChangesInBlockByTypeResponse,
 };
+use near_jsonrpc_primitives::types::light_client::{
+    RpcLightClientExecutionProofRequest, RpcLightClientExecutionProofResponse,
+    RpcLightClientNextBlockRequest, RpcLightClientNextBlockResponse,
+};
 use near_jsonrpc_primitives::types::transactions::{
     RpcTransactionResponse, RpcTransactionStatusRequest,
 };
@@ -242,6 +246,21 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validators_ordered", request)
     }
 
+    pub fn next_light_client_block(
+        &self,
+        request: RpcLightClientNextBlockRequest,
+    ) -> RpcRequest<RpcLightClientNextBlockResponse> {
+        call_method(&self.client, &self.server_addr, "next_light_client_block", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_light_client_proof(
+        &self,
+        request: RpcLightClientExecutionProofRequest,
+    ) -> RpcRequest<RpcLightClientExecutionProofResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_light_client_proof", request)
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_receipt(
         &self,
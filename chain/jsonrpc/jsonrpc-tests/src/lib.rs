@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use actix::Addr;
@@ -9,7 +10,9 @@ use near_async::{
 use near_chain_configs::GenesisConfig;
 use near_client::test_utils::setup_no_network_with_validity_period;
 use near_client::ViewClientActor;
-use near_jsonrpc::{start_http, RpcConfig};
+use near_jsonrpc::{
+    start_http, RpcConfig, RpcRateLimitsConfig, RpcRelayerConfig, RpcWebSocketConfig,
+};
 use near_jsonrpc_primitives::{
     message::{from_slice, Message},
     types::entity_debug::DummyEntityDebugHandler,
@@ -38,6 +41,47 @@ pub fn start_all_with_validity_period(
     node_type: NodeType,
     transaction_validity_period: NumBlocks,
     enable_doomslug: bool,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(
+        clock,
+        node_type,
+        transaction_validity_period,
+        enable_doomslug,
+        Path::new("."),
+        |_| {},
+    )
+}
+
+/// Starts the server with the given limits on the rate of requests.
+pub fn start_all_with_rate_limits(
+    clock: Clock,
+    node_type: NodeType,
+    rate_limits: RpcRateLimitsConfig,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(clock, node_type, 100, false, Path::new("."), |config| {
+        config.limits_config.rate_limits = rate_limits;
+    })
+}
+
+/// Starts the server with the built-in relayer, whose key file is resolved against `home_dir`.
+pub fn start_all_with_relayer(
+    clock: Clock,
+    node_type: NodeType,
+    home_dir: &Path,
+    relayer_config: RpcRelayerConfig,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
+    start_all_with_config(clock, node_type, 100, false, home_dir, |config| {
+        config.relayer_config = relayer_config;
+    })
+}
+
+fn start_all_with_config(
+    clock: Clock,
+    node_type: NodeType,
+    transaction_validity_period: NumBlocks,
+    enable_doomslug: bool,
+    home_dir: &Path,
+    configure: impl FnOnce(&mut RpcConfig),
 ) -> (Addr<ViewClientActor>, tcp::ListenerAddr) {
     let actor_handles = setup_no_network_with_validity_period(
         clock,
@@ -53,12 +97,19 @@ pub fn start_all_with_validity_period(
     );
 
     let addr = tcp::ListenerAddr::reserve_for_test();
+    let mut config = RpcConfig {
+        websocket_config: RpcWebSocketConfig { enabled: true, ..Default::default() },
+        ..RpcConfig::new(addr)
+    };
+    configure(&mut config);
     start_http(
-        RpcConfig::new(addr),
+        config,
+        home_dir,
         TEST_GENESIS_CONFIG.clone(),
         actor_handles.client_actor.clone().with_auto_span_context().into_multi_sender(),
         actor_handles.view_client_actor.clone().with_auto_span_context().into_multi_sender(),
         noop().into_multi_sender(),
+        noop().into_multi_sender(),
         #[cfg(feature = "test_features")]
         noop().into_multi_sender(),
         Arc::new(DummyEntityDebugHandler {}),
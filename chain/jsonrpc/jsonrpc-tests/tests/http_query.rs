@@ -1,8 +1,11 @@
 use actix::System;
+use awc::http::StatusCode;
 use futures::{future, FutureExt};
+use serde_json::json;
 
 use near_actix_test_utils::run_actix;
 use near_jsonrpc::client::new_http_client;
+use near_jsonrpc::{RpcRateLimitsConfig, RpcTokenBucketConfig};
 use near_o11y::testonly::init_test_logger;
 use near_time::Clock;
 
@@ -28,3 +31,60 @@ fn test_status() {
         }));
     });
 }
+
+/// Requests over the rate limits are rejected with HTTP 429 and a JSON RPC error.
+#[test]
+fn test_rate_limit_exceeded() {
+    init_test_logger();
+
+    run_actix(async {
+        let rate_limits = RpcRateLimitsConfig {
+            per_method: [(
+                "status".to_string(),
+                RpcTokenBucketConfig { maximum_size: 1, refill_rate: 0.0 },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let (_view_client_addr, addr) = test_utils::start_all_with_rate_limits(
+            Clock::real(),
+            test_utils::NodeType::NonValidator,
+            rate_limits,
+        );
+
+        actix::spawn(async move {
+            let client = awc::Client::new();
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "dontcare",
+                "method": "status",
+                "params": [],
+            });
+            let send = || {
+                client
+                    .post(format!("http://{}", addr))
+                    .insert_header(("Content-Type", "application/json"))
+                    .send_json(&request)
+            };
+
+            let response = send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let mut response = send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            let body: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(body["id"], json!("dontcare"));
+            assert_eq!(body["error"]["code"], json!(-32_005));
+            assert_eq!(body["error"]["name"], json!("HANDLER_ERROR"));
+            assert_eq!(
+                body["error"]["cause"],
+                json!({
+                    "name": "RATE_LIMIT_EXCEEDED",
+                    "info": { "limit": "method", "method": "status" },
+                })
+            );
+            System::current().stop();
+        });
+    });
+}
@@ -328,6 +328,7 @@ fn test_query_call_function() {
                     account_id: "test".parse().unwrap(),
                     method_name: "method".to_string(),
                     args: vec![].into(),
+                    profile_wasm_gas: false,
                 },
             })
             .await
@@ -346,6 +347,29 @@ fn test_query_call_function() {
     });
 }
 
+/// Wasm gas profiles are only returned if the node operator enabled them.
+#[test]
+fn test_query_call_function_wasm_gas_profiling_disabled() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let err = client
+            .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::CallFunction {
+                    account_id: "test".parse().unwrap(),
+                    method_name: "method".to_string(),
+                    args: vec![].into(),
+                    profile_wasm_gas: true,
+                },
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.data.unwrap(),
+            serde_json::json!("Wasm gas profiling is not enabled on this node")
+        );
+    });
+}
+
 /// query contract code
 #[test]
 fn test_query_contract_code() {
@@ -660,3 +684,78 @@ fn test_get_chunk_with_object_in_params() {
         assert_eq!(chunk.header.chunk_hash, same_chunk.header.chunk_hash);
     });
 }
+
+#[test]
+fn test_batch_request() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let json = serde_json::json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "block", "params": {"block_id": 0}},
+            {"jsonrpc": "2.0", "method": "status"},
+            {"jsonrpc": "2.0", "id": 2, "method": "no_such_method", "params": []},
+            {"jsonrpc": "2.0", "id": 3, "method": "gas_price", "params": [null]},
+        ]);
+
+        let response = &mut client
+            .client
+            .post(&client.server_addr)
+            .insert_header(("Content-Type", "application/json"))
+            .send_json(&json)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response: serde_json::Value = response.json().await.unwrap();
+        let responses = response.as_array().unwrap();
+        // The notification is not answered and the order of requests is kept.
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], json!(1));
+        assert_eq!(responses[0]["result"]["header"]["height"], json!(0));
+        assert_eq!(responses[1]["id"], json!(2));
+        assert_eq!(responses[1]["error"]["code"], json!(-32_601));
+        assert_eq!(responses[2]["id"], json!(3));
+        assert!(responses[2]["result"]["gas_price"].is_string());
+    });
+}
+
+#[test]
+fn test_batch_request_limits() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let request = json!({"jsonrpc": "2.0", "id": "dontcare", "method": "status"});
+        let batch_max_size = near_jsonrpc::RpcLimitsConfig::default().batch_max_size;
+        for batch in [json!([]), json!(vec![request; batch_max_size + 1])] {
+            let response = &mut client
+                .client
+                .post(&client.server_addr)
+                .insert_header(("Content-Type", "application/json"))
+                .send_json(&batch)
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let response: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(response["error"]["code"], json!(-32_600));
+        }
+    });
+}
+
+#[test]
+fn test_notifications_only() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let json = serde_json::json!([
+            {"jsonrpc": "2.0", "method": "status"},
+            {"jsonrpc": "2.0", "method": "health"},
+        ]);
+
+        let response = client
+            .client
+            .post(&client.server_addr)
+            .insert_header(("Content-Type", "application/json"))
+            .send_json(&json)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    });
+}
@@ -0,0 +1,319 @@
+use actix::System;
+use awc::ws;
+use futures::{SinkExt, Stream, StreamExt};
+use serde_json::{json, Value};
+
+use near_actix_test_utils::run_actix;
+use near_crypto::InMemorySigner;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::serialize::to_base64;
+use near_primitives::transaction::SignedTransaction;
+use near_time::Clock;
+
+use near_jsonrpc_tests as test_utils;
+
+/// Reads the next JSON message sent by the server, skipping control frames.
+async fn next_message(
+    connection: &mut (impl Stream<Item = Result<ws::Frame, ws::ProtocolError>> + Unpin),
+) -> Value {
+    loop {
+        match connection.next().await.unwrap().unwrap() {
+            ws::Frame::Text(bytes) => return serde_json::from_slice(&bytes).unwrap(),
+            ws::Frame::Ping(_) | ws::Frame::Pong(_) => continue,
+            frame => panic!("Unexpected frame {:?}", frame),
+        }
+    }
+}
+
+/// Reads messages until the response to the request with the given id arrives.
+async fn next_response(
+    connection: &mut (impl Stream<Item = Result<ws::Frame, ws::ProtocolError>> + Unpin),
+    id: &str,
+) -> Value {
+    loop {
+        let message = next_message(connection).await;
+        if message["id"] == json!(id) {
+            return message;
+        }
+    }
+}
+
+/// Subscribes to new blocks over WebSocket and checks that notifications
+/// arrive until the subscription is cancelled.
+#[test]
+fn test_subscribe_blocks() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all(Clock::real(), test_utils::NodeType::Validator);
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request =
+                json!({"jsonrpc": "2.0", "id": "subscribe", "method": "subscribe_blocks"});
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "subscribe").await;
+            let subscription = response["result"].clone();
+            assert!(subscription.is_u64(), "{:?}", response);
+
+            let mut last_height = 0;
+            for _ in 0..2 {
+                let notification = next_message(&mut connection).await;
+                assert_eq!(notification["method"], json!("subscription"));
+                assert_eq!(notification["params"]["subscription"], subscription);
+                let height = notification["params"]["result"]["header"]["height"].as_u64().unwrap();
+                assert!(height > last_height);
+                last_height = height;
+            }
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "unsubscribe",
+                "method": "unsubscribe_blocks",
+                "params": {"subscription": subscription},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "unsubscribe").await;
+            assert_eq!(response["result"], json!(true));
+
+            System::current().stop();
+        });
+    });
+}
+
+/// Subscribes to the chunks of a shard and checks that every notification is
+/// a chunk of the shard which is new in its block.
+#[test]
+fn test_subscribe_chunks() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all(Clock::real(), test_utils::NodeType::Validator);
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "subscribe",
+                "method": "subscribe_chunks",
+                "params": {"shard_id": 0},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "subscribe").await;
+            let subscription = response["result"].clone();
+            assert!(subscription.is_u64(), "{:?}", response);
+
+            let mut last_height = 0;
+            for _ in 0..2 {
+                let notification = next_message(&mut connection).await;
+                assert_eq!(notification["method"], json!("subscription"));
+                assert_eq!(notification["params"]["subscription"], subscription);
+                let header = &notification["params"]["result"]["header"];
+                assert_eq!(header["shard_id"], json!(0));
+                let height = header["height_included"].as_u64().unwrap();
+                assert!(height > last_height);
+                last_height = height;
+            }
+
+            System::current().stop();
+        });
+    });
+}
+
+/// Follows a transfer with the `tx_status` and `account_changes`
+/// subscriptions: the former fires once, when the transaction is executed,
+/// the latter when the block changes the balance of the receiver.
+#[test]
+fn test_subscribe_tx_status_and_account_changes() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all(Clock::real(), test_utils::NodeType::Validator);
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "block",
+                "method": "block",
+                "params": {"finality": "final"},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "block").await;
+            let block_hash =
+                response["result"]["header"]["hash"].as_str().unwrap().parse().unwrap();
+            let signer = InMemorySigner::test_signer(&"test1".parse().unwrap());
+            let tx = SignedTransaction::send_money(
+                1,
+                "test1".parse().unwrap(),
+                "test2".parse().unwrap(),
+                &signer,
+                100,
+                block_hash,
+            );
+            let tx_hash = tx.get_hash().to_string();
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "subscribe_account_changes",
+                "method": "subscribe_account_changes",
+                "params": {"account_ids": ["test2"]},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "subscribe_account_changes").await;
+            let account_subscription = response["result"].clone();
+            assert!(account_subscription.is_u64(), "{:?}", response);
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "subscribe_tx_status",
+                "method": "subscribe_tx_status",
+                "params": {
+                    "tx_hash": tx_hash,
+                    "sender_account_id": "test1",
+                    "wait_until": "EXECUTED",
+                },
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "subscribe_tx_status").await;
+            let tx_subscription = response["result"].clone();
+            assert!(tx_subscription.is_u64(), "{:?}", response);
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "broadcast",
+                "method": "broadcast_tx_async",
+                "params": [to_base64(&borsh::to_vec(&tx).unwrap())],
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+
+            // Notifications may arrive before the response to the broadcast.
+            let mut tx_status = None;
+            let mut account_changes = None;
+            while tx_status.is_none() || account_changes.is_none() {
+                let message = next_message(&mut connection).await;
+                let params = &message["params"];
+                if message["id"] == json!("broadcast") {
+                    assert_eq!(message["result"], json!(tx_hash));
+                } else if params["subscription"] == tx_subscription {
+                    assert!(tx_status.is_none(), "{:?}", message);
+                    tx_status = Some(params["result"].clone());
+                } else if params["subscription"] == account_subscription {
+                    account_changes = Some(params["result"].clone());
+                }
+            }
+
+            let tx_status = tx_status.unwrap();
+            assert_eq!(tx_status["transaction"]["hash"], json!(tx_hash));
+            assert_eq!(tx_status["status"], json!({"SuccessValue": ""}));
+            let account_changes = account_changes.unwrap();
+            let change = &account_changes["changes"][0];
+            assert_eq!(change["cause"]["tx_hash"], json!(tx_hash));
+            assert_eq!(change["change"]["account_id"], json!("test2"));
+
+            // The subscription to the transaction status is removed once it
+            // has fired.
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "unsubscribe",
+                "method": "unsubscribe_tx_status",
+                "params": {"subscription": tx_subscription},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "unsubscribe").await;
+            assert_eq!(response["error"]["cause"]["name"], json!("UNKNOWN_SUBSCRIPTION"));
+
+            System::current().stop();
+        });
+    });
+}
+
+/// Regular JSON RPC methods are served over WebSocket as well, while
+/// subscription methods report errors for invalid subscriptions.
+#[test]
+fn test_websocket_requests() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all(Clock::real(), test_utils::NodeType::NonValidator);
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request = json!({"jsonrpc": "2.0", "id": "status", "method": "status"});
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "status").await;
+            assert_eq!(response["result"]["chain_id"], json!("unittest"));
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "unsubscribe",
+                "method": "unsubscribe_chunks",
+                "params": {"subscription": 42},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "unsubscribe").await;
+            assert_eq!(response["error"]["cause"]["name"], json!("UNKNOWN_SUBSCRIPTION"));
+
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": "subscribe",
+                "method": "subscribe_chunks",
+                "params": {"shard": 0},
+            });
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = next_response(&mut connection, "subscribe").await;
+            assert_eq!(response["error"]["code"], json!(-32_700));
+
+            System::current().stop();
+        });
+    });
+}
+
+/// Subscription methods in a batch are handled the same way as outside of it,
+/// with responses in the order of the requests.
+#[test]
+fn test_websocket_batch_with_subscriptions() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all(Clock::real(), test_utils::NodeType::NonValidator);
+
+        actix::spawn(async move {
+            let (_, mut connection) =
+                awc::Client::new().ws(format!("ws://{}/ws", addr)).connect().await.unwrap();
+
+            let request = json!([
+                {"jsonrpc": "2.0", "id": "status", "method": "status"},
+                {"jsonrpc": "2.0", "id": "subscribe", "method": "subscribe_blocks"},
+                {
+                    "jsonrpc": "2.0",
+                    "id": "unsubscribe",
+                    "method": "unsubscribe_chunks",
+                    "params": {"subscription": 42},
+                },
+            ]);
+            connection.send(ws::Message::Text(request.to_string().into())).await.unwrap();
+            let response = loop {
+                let message = next_message(&mut connection).await;
+                if message.is_array() {
+                    break message;
+                }
+            };
+            let ids: Vec<_> = response.as_array().unwrap().iter().map(|r| &r["id"]).collect();
+            assert_eq!(ids, vec!["status", "subscribe", "unsubscribe"]);
+            assert_eq!(response[0]["result"]["chain_id"], json!("unittest"));
+            assert!(response[1]["result"].is_u64(), "{:?}", response);
+            assert_eq!(response[2]["error"]["cause"]["name"], json!("UNKNOWN_SUBSCRIPTION"));
+
+            System::current().stop();
+        });
+    });
+}
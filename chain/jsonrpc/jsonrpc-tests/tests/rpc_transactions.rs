@@ -7,15 +7,22 @@ use futures::{future, FutureExt, TryFutureExt};
 use near_actix_test_utils::run_actix;
 use near_crypto::InMemorySigner;
 use near_jsonrpc::client::new_client;
-use near_jsonrpc_primitives::types::transactions::{RpcTransactionStatusRequest, TransactionInfo};
+use near_jsonrpc::RpcRelayerConfig;
+use near_jsonrpc_primitives::types::transactions::{
+    RpcTransactionResponse, RpcTransactionStatusRequest, TransactionInfo,
+};
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
+use near_primitives::action::delegate::{DelegateAction, SignedDelegateAction};
+use near_primitives::action::{Action, FunctionCallAction};
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::serialize::to_base64;
+use near_primitives::signable_message::{SignableMessage, SignableMessageType};
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::BlockReference;
+use near_primitives::types::{BlockHeight, BlockReference, Gas};
 use near_primitives::views::{FinalExecutionStatus, TxExecutionStatus};
 use near_time::Clock;
+use serde_json::json;
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
 
@@ -246,3 +253,96 @@ fn test_check_invalid_tx() {
         }
     });
 }
+
+fn signed_delegate_action(
+    receiver_id: &str,
+    gas: Gas,
+    max_block_height: BlockHeight,
+) -> SignedDelegateAction {
+    let signer = InMemorySigner::test_signer(&"test2".parse().unwrap());
+    let action = Action::FunctionCall(Box::new(FunctionCallAction {
+        method_name: "main".to_string(),
+        args: vec![],
+        gas,
+        deposit: 0,
+    }));
+    let delegate_action = DelegateAction {
+        sender_id: "test2".parse().unwrap(),
+        receiver_id: receiver_id.parse().unwrap(),
+        actions: vec![action.try_into().unwrap()],
+        nonce: 1,
+        max_block_height,
+        public_key: signer.public_key(),
+    };
+    let signature =
+        SignableMessage::new(&delegate_action, SignableMessageType::DelegateAction).sign(&signer);
+    SignedDelegateAction { delegate_action, signature }
+}
+
+/// Test relaying delegate actions with the relayer key read from the home directory.
+#[test]
+fn test_relay_delegate_action() {
+    init_test_logger();
+
+    run_actix(async {
+        let home_dir = tempfile::tempdir().unwrap();
+        let relayer_signer = InMemorySigner::test_signer(&"test1".parse().unwrap());
+        relayer_signer.write_to_file(&home_dir.path().join("relayer_key.json")).unwrap();
+        let gas = 100_000_000_000_000;
+        let relayer_config = RpcRelayerConfig {
+            enabled: true,
+            allowed_receivers: vec!["test1".parse().unwrap()],
+            gas_budget_per_user: gas,
+            ..Default::default()
+        };
+        let (_, addr) = test_utils::start_all_with_relayer(
+            Clock::real(),
+            test_utils::NodeType::Validator,
+            home_dir.path(),
+            relayer_config,
+        );
+        let client = new_client(&format!("http://{}", addr));
+
+        actix::spawn(async move {
+            let relay = |params| {
+                test_utils::call_method::<RpcTransactionResponse>(
+                    &client.client,
+                    &client.server_addr,
+                    "relay_delegate_action",
+                    params,
+                )
+            };
+
+            let response = relay(json!({
+                "signed_delegate_action": signed_delegate_action("test1", gas, 1000),
+                "wait_until": TxExecutionStatus::None,
+            }))
+            .await
+            .unwrap();
+            assert_eq!(response.final_execution_status, TxExecutionStatus::None);
+
+            let sda = signed_delegate_action("test1", gas, 1000);
+            let err = relay(json!([to_base64(&borsh::to_vec(&sda).unwrap())])).await.unwrap_err();
+            assert_eq!(
+                err.data.unwrap(),
+                json!(format!("Gas budget of test2 exceeded: {gas} of {gas} gas is already used"))
+            );
+
+            let sda = signed_delegate_action("test3", gas, 1000);
+            let err = relay(json!([to_base64(&borsh::to_vec(&sda).unwrap())])).await.unwrap_err();
+            assert_eq!(
+                err.data.unwrap(),
+                json!("Relaying delegate actions to test3 is not allowed")
+            );
+
+            // Expired delegate actions are rejected before anything is charged.
+            let sda = signed_delegate_action("test1", gas, 0);
+            let err = relay(json!([to_base64(&borsh::to_vec(&sda).unwrap())])).await.unwrap_err();
+            let message = err.data.unwrap().as_str().unwrap().to_owned();
+            assert!(message.starts_with("The delegate action expired at height 0"), "{message}");
+
+            drop(home_dir);
+            System::current().stop();
+        });
+    });
+}
@@ -0,0 +1,49 @@
+use near_async::messaging::AsyncSendError;
+use serde_json::Value;
+
+use near_client_primitives::types::GetAccountActivityError;
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::account_history::{
+    RpcAccessKeyHistoryRequest, RpcAccountHistoryError, RpcAccountHistoryRequest,
+};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcAccountHistoryRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcAccessKeyHistoryRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcAccountHistoryError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetAccountActivityError> for RpcAccountHistoryError {
+    fn rpc_from(error: GetAccountActivityError) -> Self {
+        match error {
+            GetAccountActivityError::IndexDisabled => Self::IndexDisabled,
+            GetAccountActivityError::GarbageCollected { tail_height } => {
+                Self::GarbageCollected { tail_height }
+            }
+            GetAccountActivityError::IOError { error_message } => {
+                Self::InternalError { error_message }
+            }
+            GetAccountActivityError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcAccountHistoryError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
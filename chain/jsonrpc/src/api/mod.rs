@@ -4,6 +4,7 @@ use serde_json::Value;
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::errors::{RpcError, ServerError};
 
+mod account_history;
 mod blocks;
 mod changes;
 mod chunks;
@@ -16,9 +17,11 @@ mod maintenance;
 mod network_info;
 mod query;
 mod receipts;
+mod relayer;
 mod sandbox;
 mod split_storage;
 mod status;
+mod subscriptions;
 mod transactions;
 mod validator;
 
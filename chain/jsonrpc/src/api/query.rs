@@ -78,6 +78,7 @@ fn parse_path_data(path: String, data: String) -> Result<RpcQueryRequest, RpcPar
                 account_id,
                 method_name: method_name.to_string(),
                 args: parse_data()?.into(),
+                profile_wasm_gas: false,
             },
             None => return Err(RpcParseError("Method name is missing".to_string())),
         },
@@ -0,0 +1,44 @@
+use near_async::messaging::AsyncSendError;
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::query::RpcQueryError;
+use near_jsonrpc_primitives::types::relayer::{RpcRelayDelegateActionRequest, RpcRelayError};
+use near_primitives::action::delegate::SignedDelegateAction;
+use near_primitives::borsh::BorshDeserialize;
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcRelayDelegateActionRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        // A one-element array holds a borsh serialized and base64 encoded delegate action, like
+        // the legacy parameters of `broadcast_tx_*` methods.
+        Params::new(value)
+            .try_singleton(|value| {
+                Ok(RpcRelayDelegateActionRequest {
+                    signed_delegate_action: decode_signed_delegate_action(value)?,
+                    wait_until: Default::default(),
+                })
+            })
+            .unwrap_or_parse()
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcRelayError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { debug_info: error.to_string() }
+    }
+}
+
+impl RpcFrom<RpcQueryError> for RpcRelayError {
+    fn rpc_from(error: RpcQueryError) -> Self {
+        Self::InternalError { debug_info: error.to_string() }
+    }
+}
+
+fn decode_signed_delegate_action(value: String) -> Result<SignedDelegateAction, RpcParseError> {
+    let bytes = near_primitives::serialize::from_base64(&value)
+        .map_err(|err| RpcParseError(format!("Failed to decode delegate action: {}", err)))?;
+    SignedDelegateAction::try_from_slice(&bytes)
+        .map_err(|err| RpcParseError(format!("Failed to decode delegate action: {}", err)))
+}
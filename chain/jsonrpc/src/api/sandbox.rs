@@ -3,8 +3,11 @@ use serde_json::Value;
 
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::sandbox::{
-    RpcSandboxFastForwardError, RpcSandboxFastForwardRequest, RpcSandboxPatchStateError,
-    RpcSandboxPatchStateRequest,
+    RpcSandboxFastForwardError, RpcSandboxFastForwardRequest, RpcSandboxImpersonateAccountError,
+    RpcSandboxImpersonateAccountRequest, RpcSandboxPatchStateError, RpcSandboxPatchStateRequest,
+    RpcSandboxRevertError, RpcSandboxRevertRequest, RpcSandboxSetNextBlockTimestampError,
+    RpcSandboxSetNextBlockTimestampRequest, RpcSandboxSnapshotError, RpcSandboxSnapshotRequest,
+    RpcSandboxTimeWarpError, RpcSandboxTimeWarpRequest,
 };
 
 use super::{Params, RpcFrom, RpcRequest};
@@ -21,6 +24,36 @@ impl RpcRequest for RpcSandboxFastForwardRequest {
     }
 }
 
+impl RpcRequest for RpcSandboxSnapshotRequest {
+    fn parse(_: Value) -> Result<Self, RpcParseError> {
+        Ok(Self {})
+    }
+}
+
+impl RpcRequest for RpcSandboxRevertRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcSandboxImpersonateAccountRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcSandboxSetNextBlockTimestampRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcSandboxTimeWarpRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
 impl RpcFrom<AsyncSendError> for RpcSandboxPatchStateError {
     fn rpc_from(error: AsyncSendError) -> Self {
         Self::InternalError { error_message: error.to_string() }
@@ -32,3 +65,33 @@ impl RpcFrom<AsyncSendError> for RpcSandboxFastForwardError {
         Self::InternalError { error_message: error.to_string() }
     }
 }
+
+impl RpcFrom<AsyncSendError> for RpcSandboxSnapshotError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcSandboxRevertError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcSandboxImpersonateAccountError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcSandboxSetNextBlockTimestampError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcSandboxTimeWarpError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
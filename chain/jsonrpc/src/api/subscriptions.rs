@@ -0,0 +1,33 @@
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::subscriptions::{
+    RpcSubscribeAccountChangesRequest, RpcSubscribeChunksRequest, RpcSubscribeTxStatusRequest,
+    RpcUnsubscribeRequest,
+};
+
+use super::{Params, RpcRequest};
+
+impl RpcRequest for RpcSubscribeChunksRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcSubscribeTxStatusRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcSubscribeAccountChangesRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcUnsubscribeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
@@ -11,20 +11,24 @@ use near_async::messaging::{
 };
 use near_chain_configs::GenesisConfig;
 use near_client::{
-    DebugStatus, GetBlock, GetBlockProof, GetChunk, GetClientConfig, GetExecutionOutcome,
-    GetGasPrice, GetMaintenanceWindows, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig,
-    GetReceipt, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
-    ProcessTxRequest, ProcessTxResponse, Query, Status, TxStatus,
+    DebugStatus, GetAccountActivity, GetBlock, GetBlockProof, GetChunk, GetClientConfig,
+    GetExecutionOutcome, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
+    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
+    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, ProcessTxRequest,
+    ProcessTxResponse, Query, Status, SubscribeAcceptedBlocks, TxStatus, TxStatuses,
 };
+use near_client_primitives::debug::DebugScrubberStatus;
 use near_client_primitives::types::GetSplitStorageInfo;
 pub use near_jsonrpc_client as client;
 pub use near_jsonrpc_primitives as primitives;
-use near_jsonrpc_primitives::errors::{RpcError, RpcErrorKind};
+use near_jsonrpc_primitives::errors::{RpcError, RpcErrorKind, RpcParseError};
 use near_jsonrpc_primitives::message::{Message, Request};
 use near_jsonrpc_primitives::types::blocks::RpcBlockRequest;
 use near_jsonrpc_primitives::types::config::{RpcProtocolConfigError, RpcProtocolConfigResponse};
 use near_jsonrpc_primitives::types::entity_debug::{EntityDebugHandler, EntityQueryWithParams};
 use near_jsonrpc_primitives::types::query::RpcQueryRequest;
+#[cfg(feature = "sandbox")]
+use near_jsonrpc_primitives::types::sandbox;
 use near_jsonrpc_primitives::types::split_storage::{
     RpcSplitStorageInfoRequest, RpcSplitStorageInfoResponse,
 };
@@ -38,8 +42,10 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockHeight, BlockId, BlockReference};
 use near_primitives::views::{QueryRequest, TxExecutionStatus};
+use rate_limits::RateLimiter;
+use relayer::Relayer;
 use serde_json::{json, Value};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::{sleep, timeout};
@@ -47,6 +53,13 @@ use tracing::{error, info};
 
 mod api;
 mod metrics;
+mod rate_limits;
+mod relayer;
+mod websocket;
+
+pub use rate_limits::{RpcRateLimitsConfig, RpcTokenBucketConfig};
+pub use relayer::RpcRelayerConfig;
+pub use websocket::RpcWebSocketConfig;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
@@ -63,15 +76,30 @@ impl Default for RpcPollingConfig {
     }
 }
 
+fn default_batch_max_size() -> usize {
+    100
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct RpcLimitsConfig {
-    /// Maximum byte size of the json payload.
+    /// Maximum byte size of the json payload.  For batch requests the limit
+    /// applies to the whole batch rather than to its individual entries.
     pub json_payload_max_size: usize,
+    /// Maximum number of requests and notifications in a single batch.
+    #[serde(default = "default_batch_max_size")]
+    pub batch_max_size: usize,
+    /// Limits on the rate of requests per client IP address and per method.
+    #[serde(default)]
+    pub rate_limits: RpcRateLimitsConfig,
 }
 
 impl Default for RpcLimitsConfig {
     fn default() -> Self {
-        Self { json_payload_max_size: 10 * 1024 * 1024 }
+        Self {
+            json_payload_max_size: 10 * 1024 * 1024,
+            batch_max_size: default_batch_max_size(),
+            rate_limits: Default::default(),
+        }
     }
 }
 
@@ -92,10 +120,19 @@ pub struct RpcConfig {
     // We disable it by default, as some of those endpoints might be quite CPU heavy.
     #[serde(default = "default_enable_debug_rpc")]
     pub enable_debug_rpc: bool,
+    // If true, `call_function` queries may request a profile of the Wasm gas with
+    // `profile_wasm_gas`. Profiled calls run on Wasmtime and are slower, so this is disabled by
+    // default.
+    #[serde(default)]
+    pub enable_wasm_gas_profiling: bool,
     // For node developers only: if specified, the HTML files used to serve the debug pages will
     // be read from this directory, instead of the contents compiled into the binary. This allows
     // for quick iterative development.
     pub experimental_debug_pages_src_path: Option<String>,
+    #[serde(default)]
+    pub websocket_config: RpcWebSocketConfig,
+    #[serde(default)]
+    pub relayer_config: RpcRelayerConfig,
 }
 
 impl Default for RpcConfig {
@@ -107,7 +144,10 @@ impl Default for RpcConfig {
             polling_config: Default::default(),
             limits_config: Default::default(),
             enable_debug_rpc: false,
+            enable_wasm_gas_profiling: false,
             experimental_debug_pages_src_path: None,
+            websocket_config: Default::default(),
+            relayer_config: Default::default(),
         }
     }
 }
@@ -151,6 +191,7 @@ impl near_jsonrpc_primitives::types::transactions::RpcTransactionError {
         match resp {
             ProcessTxResponse::InvalidTx(context) => Self::InvalidTransaction { context },
             ProcessTxResponse::NoResponse => Self::TimeoutError,
+            ProcessTxResponse::Underpriced => Self::Underpriced,
             ProcessTxResponse::DoesNotTrackShard | ProcessTxResponse::RequestRouted => {
                 Self::DoesNotTrackShard
             }
@@ -159,6 +200,78 @@ impl near_jsonrpc_primitives::types::transactions::RpcTransactionError {
     }
 }
 
+/// Names of the methods served by the handler, as reported in metrics and
+/// rate limited, including the names of `query` calls by request type (see
+/// `query_metrics_name`) and the subscriptions of WebSocket connections.
+/// Calls of other methods, including the adversarial and sandbox ones, are
+/// reported as `UNSUPPORTED_METHOD` when they are throttled.
+const RPC_METHODS: &[&str] = &[
+    "block",
+    "broadcast_tx_async",
+    "broadcast_tx_commit",
+    "chunk",
+    "client_config",
+    "gas_price",
+    "health",
+    "light_client_proof",
+    "network_info",
+    "next_light_client_block",
+    "query",
+    "query_call_function",
+    "query_view_access_key",
+    "query_view_access_key_list",
+    "query_view_account",
+    "query_view_code",
+    "query_view_state",
+    "query_view_state_with_proof",
+    "relay_delegate_action",
+    "send_tx",
+    "status",
+    "tx",
+    "validators",
+    "EXPERIMENTAL_access_key_history",
+    "EXPERIMENTAL_account_history",
+    "EXPERIMENTAL_changes",
+    "EXPERIMENTAL_changes_in_block",
+    "EXPERIMENTAL_congestion_level",
+    "EXPERIMENTAL_genesis_config",
+    "EXPERIMENTAL_light_client_block_proof",
+    "EXPERIMENTAL_light_client_proof",
+    "EXPERIMENTAL_maintenance_windows",
+    "EXPERIMENTAL_protocol_config",
+    "EXPERIMENTAL_receipt",
+    "EXPERIMENTAL_split_storage_info",
+    "EXPERIMENTAL_tx_status",
+    "EXPERIMENTAL_validators_ordered",
+    "subscribe_account_changes",
+    "subscribe_blocks",
+    "subscribe_chunks",
+    "subscribe_tx_status",
+    "unsubscribe_account_changes",
+    "unsubscribe_blocks",
+    "unsubscribe_chunks",
+    "unsubscribe_tx_status",
+];
+
+/// Returns name under which calls of the `query` method with the given
+/// request are reported in metrics and rate limited.
+fn query_metrics_name(request: &QueryRequest) -> &'static str {
+    match request {
+        QueryRequest::ViewAccount { .. } => "query_view_account",
+        QueryRequest::ViewCode { .. } => "query_view_code",
+        QueryRequest::ViewState { include_proof, .. } => {
+            if *include_proof {
+                "query_view_state_with_proof"
+            } else {
+                "query_view_state"
+            }
+        }
+        QueryRequest::ViewAccessKey { .. } => "query_view_access_key",
+        QueryRequest::ViewAccessKeyList { .. } => "query_view_access_key_list",
+        QueryRequest::CallFunction { .. } => "query_call_function",
+    }
+}
+
 /// This function processes response from query method to introduce
 /// backward compatible response in case of specific errors
 #[allow(clippy::result_large_err)]
@@ -228,6 +341,7 @@ pub struct ClientSenderForRpc(
     AsyncSender<GetNetworkInfo, ActixResult<GetNetworkInfo>>,
     AsyncSender<ProcessTxRequest, ActixResult<ProcessTxRequest>>,
     AsyncSender<Status, ActixResult<Status>>,
+    AsyncSender<SubscribeAcceptedBlocks, ActixResult<SubscribeAcceptedBlocks>>,
     Sender<ProcessTxRequest>,
     #[cfg(feature = "test_features")] Sender<near_client::NetworkAdversarialMessage>,
     #[cfg(feature = "test_features")]
@@ -244,6 +358,7 @@ pub struct ClientSenderForRpc(
 
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
 pub struct ViewClientSenderForRpc(
+    AsyncSender<GetAccountActivity, ActixResult<GetAccountActivity>>,
     AsyncSender<GetBlock, ActixResult<GetBlock>>,
     AsyncSender<GetBlockProof, ActixResult<GetBlockProof>>,
     AsyncSender<GetChunk, ActixResult<GetChunk>>,
@@ -260,6 +375,7 @@ pub struct ViewClientSenderForRpc(
     AsyncSender<GetValidatorOrdered, ActixResult<GetValidatorOrdered>>,
     AsyncSender<Query, ActixResult<Query>>,
     AsyncSender<TxStatus, ActixResult<TxStatus>>,
+    AsyncSender<TxStatuses, ActixResult<TxStatuses>>,
     #[cfg(feature = "test_features")] Sender<near_client::NetworkAdversarialMessage>,
 );
 
@@ -275,27 +391,110 @@ pub struct GCSenderForRpc(
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
 pub struct PeerManagerSenderForRpc(AsyncSender<GetDebugStatus, ActixResult<GetDebugStatus>>);
 
+#[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
+pub struct ScrubberSenderForRpc(AsyncSender<DebugScrubberStatus, ActixResult<DebugScrubberStatus>>);
+
 struct JsonRpcHandler {
     client_sender: ClientSenderForRpc,
     view_client_sender: ViewClientSenderForRpc,
     peer_manager_sender: PeerManagerSenderForRpc,
+    scrubber_sender: ScrubberSenderForRpc,
     #[cfg(feature = "test_features")]
     gc_sender: GCSenderForRpc,
     polling_config: RpcPollingConfig,
     genesis_config: GenesisConfig,
     enable_debug_rpc: bool,
+    enable_wasm_gas_profiling: bool,
     debug_pages_src_path: Option<PathBuf>,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    json_payload_max_size: usize,
+    batch_max_size: usize,
+    websocket_config: RpcWebSocketConfig,
+    rate_limiter: Arc<RateLimiter>,
+    relayer: Option<Arc<Relayer>>,
 }
 
 impl JsonRpcHandler {
-    async fn process(&self, message: Message) -> Message {
+    /// Processes a single message or a batch of messages.
+    ///
+    /// Returns `None` if there is nothing to reply with, i.e. if the message
+    /// was a notification or a batch consisting solely of notifications.
+    async fn process(&self, message: Message) -> Option<Message> {
+        match message {
+            Message::Batch(messages) => self.process_batch(messages).await,
+            message => self.process_single(message).await,
+        }
+    }
+
+    /// Processes entries of a batch concurrently.
+    ///
+    /// Responses are returned in the same order as the requests in the batch
+    /// with notifications omitted.
+    async fn process_batch(&self, messages: Vec<Message>) -> Option<Message> {
+        if let Err(error) = self.check_batch(&messages) {
+            return Some(error);
+        }
+        let responses: Vec<Message> = futures::future::join_all(
+            messages.into_iter().map(|message| self.process_batch_entry(message)),
+        )
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if responses.is_empty() {
+            None
+        } else {
+            Some(Message::Batch(responses))
+        }
+    }
+
+    /// Checks that the batch is neither empty nor too large, returning the
+    /// error to reply with otherwise.
+    fn check_batch(&self, messages: &[Message]) -> Result<(), Message> {
+        if messages.is_empty() {
+            return Err(Message::error(RpcError::invalid_request(
+                "Batch must contain at least one request".to_owned(),
+            )));
+        }
+        if messages.len() > self.batch_max_size {
+            return Err(Message::error(RpcError::invalid_request(format!(
+                "Batch contains {} requests but at most {} are allowed",
+                messages.len(),
+                self.batch_max_size
+            ))));
+        }
+        metrics::RPC_BATCH_SIZE.observe(messages.len() as f64);
+        Ok(())
+    }
+
+    async fn process_batch_entry(&self, message: Message) -> Option<Message> {
+        match message {
+            Message::Batch(_) => Some(Message::error(RpcError::invalid_request(
+                "Nested batches are not allowed".to_owned(),
+            ))),
+            message => self.process_single(message).await,
+        }
+    }
+
+    async fn process_single(&self, message: Message) -> Option<Message> {
         let id = message.id();
         match message {
-            Message::Request(request) => Message::response(id, self.process_request(request).await),
-            _ => Message::error(RpcError::parse_error(
+            Message::Request(request) => {
+                Some(Message::response(id, self.process_request(request).await))
+            }
+            Message::Notification(notification) => {
+                // Notifications are executed but, as per the specification, the
+                // server must not reply to them.
+                let _ = self.process_request(notification.into_request()).await;
+                None
+            }
+            Message::UnmatchedSub(_) => Some(Message::error(RpcError::invalid_request(
+                "JSON RPC Request format was expected".to_owned(),
+            ))),
+            _ => Some(Message::error(RpcError::parse_error(
                 "JSON RPC Request format was expected".to_owned(),
-            )),
+            ))),
         }
     }
 
@@ -343,20 +542,17 @@ impl JsonRpcHandler {
                     Ok(params) => params,
                     Err(err) => return (method_name, Err(RpcError::from(err))),
                 };
-                let metrics_name = match params.request {
-                    QueryRequest::ViewAccount { .. } => "query_view_account",
-                    QueryRequest::ViewCode { .. } => "query_view_code",
-                    QueryRequest::ViewState { include_proof, .. } => {
-                        if include_proof {
-                            "query_view_state_with_proof"
-                        } else {
-                            "query_view_state"
-                        }
-                    }
-                    QueryRequest::ViewAccessKey { .. } => "query_view_access_key",
-                    QueryRequest::ViewAccessKeyList { .. } => "query_view_access_key_list",
-                    QueryRequest::CallFunction { .. } => "query_call_function",
-                };
+                if !self.enable_wasm_gas_profiling
+                    && matches!(
+                        params.request,
+                        QueryRequest::CallFunction { profile_wasm_gas: true, .. }
+                    )
+                {
+                    let err =
+                        RpcParseError("Wasm gas profiling is not enabled on this node".to_string());
+                    return (method_name, Err(RpcError::from(err)));
+                }
+                let metrics_name = query_metrics_name(&params.request);
                 (metrics_name.to_string(), process_query_response(self.query(params).await))
             }
             _ => {
@@ -395,6 +591,9 @@ impl JsonRpcHandler {
                 process_method_call(request, |params| self.next_light_client_block(params)).await
             }
             "network_info" => process_method_call(request, |_params: ()| self.network_info()).await,
+            "relay_delegate_action" => {
+                process_method_call(request, |params| self.relay_delegate_action(params)).await
+            }
             "send_tx" => process_method_call(request, |params| self.send_tx(params)).await,
             "status" => process_method_call(request, |_params: ()| self.status()).await,
             "tx" => {
@@ -404,6 +603,12 @@ impl JsonRpcHandler {
             "client_config" => {
                 process_method_call(request, |_params: ()| self.client_config()).await
             }
+            "EXPERIMENTAL_access_key_history" => {
+                process_method_call(request, |params| self.access_key_history(params)).await
+            }
+            "EXPERIMENTAL_account_history" => {
+                process_method_call(request, |params| self.account_history(params)).await
+            }
             "EXPERIMENTAL_changes" => {
                 process_method_call(request, |params| self.changes_in_block_by_type(params)).await
             }
@@ -454,6 +659,28 @@ impl JsonRpcHandler {
             "sandbox_fast_forward" => {
                 process_method_call(request, |params| self.sandbox_fast_forward(params)).await
             }
+            #[cfg(feature = "sandbox")]
+            "sandbox_snapshot" => {
+                process_method_call(request, |params| self.sandbox_snapshot(params)).await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_revert" => {
+                process_method_call(request, |params| self.sandbox_revert(params)).await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_impersonate_account" => {
+                process_method_call(request, |params| self.sandbox_impersonate_account(params))
+                    .await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_set_next_block_timestamp" => {
+                process_method_call(request, |params| self.sandbox_set_next_block_timestamp(params))
+                    .await
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_time_warp" => {
+                process_method_call(request, |params| self.sandbox_time_warp(params)).await
+            }
             _ => return Err(request),
         })
     }
@@ -527,6 +754,15 @@ impl JsonRpcHandler {
         self.peer_manager_sender.send_async(msg).await.map_err(RpcFrom::rpc_from)
     }
 
+    async fn scrubber_send<M, T, E>(&self, msg: M) -> Result<T, E>
+    where
+        ScrubberSenderForRpc: CanSend<MessageWithCallback<M, T>>,
+        T: Send + 'static,
+        E: RpcFrom<AsyncSendError> + Send + 'static,
+    {
+        self.scrubber_sender.send_async(msg).await.map_err(RpcFrom::rpc_from)
+    }
+
     async fn send_tx_async(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcSendTransactionRequest,
@@ -717,6 +953,108 @@ impl JsonRpcHandler {
         }
     }
 
+    /// Wraps a delegate action signed by a user in a transaction signed by
+    /// the relayer account and sends it like `send_tx` does.
+    ///
+    /// The access key of the delegate action is checked at the head before
+    /// anything is charged, so that budgets are tracked only for existing
+    /// accounts and the relayer doesn't pay for actions which would fail.
+    async fn relay_delegate_action(
+        &self,
+        request_data: near_jsonrpc_primitives::types::relayer::RpcRelayDelegateActionRequest,
+    ) -> Result<RpcTransactionResponse, near_jsonrpc_primitives::types::relayer::RpcRelayError>
+    {
+        use near_jsonrpc_primitives::types::relayer::RpcRelayError;
+        use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+
+        let Some(relayer) = &self.relayer else {
+            return Err(RpcRelayError::RelayerDisabled);
+        };
+        let signed_delegate_action = request_data.signed_delegate_action;
+        let sender_id = signed_delegate_action.delegate_action.sender_id.clone();
+        let public_key = signed_delegate_action.delegate_action.public_key.clone();
+        let sender_access_key = self
+            .query(RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::ViewAccessKey {
+                    account_id: sender_id.clone(),
+                    public_key: public_key.clone(),
+                },
+            })
+            .await;
+        match sender_access_key {
+            Ok(near_jsonrpc_primitives::types::query::RpcQueryResponse {
+                kind: near_jsonrpc_primitives::types::query::QueryResponseKind::AccessKey(view),
+                block_height,
+                ..
+            }) => crate::relayer::check_access_key(
+                &signed_delegate_action.delegate_action,
+                &view,
+                block_height,
+            )?,
+            Ok(response) => {
+                return Err(RpcRelayError::InternalError {
+                    debug_info: format!("Unexpected access key query response: {:?}", response),
+                });
+            }
+            Err(
+                near_jsonrpc_primitives::types::query::RpcQueryError::UnknownAccount { .. }
+                | near_jsonrpc_primitives::types::query::RpcQueryError::UnknownAccessKey { .. },
+            ) => {
+                return Err(RpcRelayError::UnknownAccessKey { sender_id, public_key });
+            }
+            Err(err) => return Err(RpcFrom::rpc_from(err)),
+        }
+        let gas = relayer.check_and_charge(&signed_delegate_action)?;
+
+        let access_key = self
+            .query(RpcQueryRequest {
+                block_reference: BlockReference::Finality(near_primitives::types::Finality::Final),
+                request: QueryRequest::ViewAccessKey {
+                    account_id: relayer.account_id(),
+                    public_key: relayer.public_key(),
+                },
+            })
+            .await;
+        let (access_key_nonce, block_hash) = match access_key {
+            Ok(near_jsonrpc_primitives::types::query::RpcQueryResponse {
+                kind: near_jsonrpc_primitives::types::query::QueryResponseKind::AccessKey(view),
+                block_hash,
+                ..
+            }) => (view.nonce, block_hash),
+            Ok(response) => {
+                relayer.refund(&sender_id, gas);
+                return Err(RpcRelayError::InternalError {
+                    debug_info: format!("Unexpected access key query response: {:?}", response),
+                });
+            }
+            Err(err) => {
+                relayer.refund(&sender_id, gas);
+                return Err(RpcFrom::rpc_from(err));
+            }
+        };
+
+        let signed_transaction =
+            relayer.sign_transaction(signed_delegate_action, access_key_nonce, block_hash);
+        let result = self
+            .send_tx(RpcSendTransactionRequest {
+                signed_transaction,
+                wait_until: request_data.wait_until,
+            })
+            .await;
+        if let Err(
+            RpcTransactionError::InvalidTransaction { .. }
+            | RpcTransactionError::DoesNotTrackShard
+            | RpcTransactionError::Underpriced
+            | RpcTransactionError::InternalError { .. },
+        ) = &result
+        {
+            // The transaction hasn't been accepted so the user hasn't spent any gas.
+            relayer.refund(&sender_id, gas);
+        }
+        Ok(result?)
+    }
+
     async fn send_tx_commit(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcSendTransactionRequest,
@@ -830,6 +1168,11 @@ impl JsonRpcHandler {
                             .map_err(|e| e.into_rpc_status_error())?;
                         near_jsonrpc_primitives::types::status::DebugStatusResponse::SplitStoreStatus(split_storage_info.result)
                     }
+                    "/debug/api/scrubber_status" => {
+                        near_jsonrpc_primitives::types::status::DebugStatusResponse::ScrubberStatus(
+                            self.scrubber_send(DebugScrubberStatus).await?,
+                        )
+                    }
                     _ => return Ok(None),
                 };
             Ok(Some(near_jsonrpc_primitives::types::status::RpcDebugStatusResponse {
@@ -1020,6 +1363,58 @@ impl JsonRpcHandler {
         })
     }
 
+    async fn account_history(
+        &self,
+        request: near_jsonrpc_primitives::types::account_history::RpcAccountHistoryRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse,
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryError,
+    > {
+        self.account_activity(request, None).await
+    }
+
+    async fn access_key_history(
+        &self,
+        request: near_jsonrpc_primitives::types::account_history::RpcAccessKeyHistoryRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse,
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryError,
+    > {
+        self.account_activity(request.account_history_request, Some(request.public_key)).await
+    }
+
+    async fn account_activity(
+        &self,
+        request: near_jsonrpc_primitives::types::account_history::RpcAccountHistoryRequest,
+        public_key: Option<near_crypto::PublicKey>,
+    ) -> Result<
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse,
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryError,
+    > {
+        use near_jsonrpc_primitives::types::account_history::{
+            DEFAULT_ACCOUNT_HISTORY_LIMIT, MAX_ACCOUNT_HISTORY_LIMIT,
+        };
+
+        let limit = request
+            .limit
+            .unwrap_or(DEFAULT_ACCOUNT_HISTORY_LIMIT)
+            .clamp(1, MAX_ACCOUNT_HISTORY_LIMIT);
+        let response = self
+            .view_client_send(GetAccountActivity {
+                account_id: request.account_id,
+                public_key,
+                from_height: request.from_block_height,
+                to_height: request.to_block_height,
+                cursor: request.cursor,
+                limit,
+            })
+            .await?;
+        Ok(near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse {
+            activity: response.activity,
+            next_cursor: response.next_cursor,
+        })
+    }
+
     async fn next_light_client_block(
         &self,
         request: near_jsonrpc_primitives::types::light_client::RpcLightClientNextBlockRequest,
@@ -1277,6 +1672,147 @@ impl JsonRpcHandler {
 
         Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxFastForwardResponse {})
     }
+
+    async fn sandbox_snapshot(
+        &self,
+        _snapshot_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotError,
+    > {
+        use near_client_primitives::types::SandboxResponse;
+
+        match self
+            .client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxSnapshot)
+            .await
+            .map_err(RpcFrom::rpc_from)?
+        {
+            SandboxResponse::SandboxSnapshotTaken(snapshot_id) => {
+                Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotResponse {
+                    snapshot_id,
+                })
+            }
+            SandboxResponse::SandboxSnapshotFailed(err) => Err(
+                near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotError::InternalError {
+                    error_message: format!("sandbox failed to take a snapshot due to: {}", err),
+                },
+            ),
+            response => Err(
+                near_jsonrpc_primitives::types::sandbox::RpcSandboxSnapshotError::InternalError {
+                    error_message: format!("unexpected sandbox response: {:?}", response),
+                },
+            ),
+        }
+    }
+
+    async fn sandbox_revert(
+        &self,
+        revert_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertError,
+    > {
+        use near_client_primitives::types::SandboxResponse;
+
+        match self
+            .client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxRevert(
+                revert_request.snapshot_id,
+            ))
+            .await
+            .map_err(RpcFrom::rpc_from)?
+        {
+            SandboxResponse::SandboxRevertFinished => {
+                Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertResponse {})
+            }
+            SandboxResponse::SandboxRevertFailed(err) => {
+                Err(near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertError::InternalError {
+                    error_message: format!("sandbox failed to revert due to: {}", err),
+                })
+            }
+            response => {
+                Err(near_jsonrpc_primitives::types::sandbox::RpcSandboxRevertError::InternalError {
+                    error_message: format!("unexpected sandbox response: {:?}", response),
+                })
+            }
+        }
+    }
+
+    async fn sandbox_impersonate_account(
+        &self,
+        impersonate_request: sandbox::RpcSandboxImpersonateAccountRequest,
+    ) -> Result<
+        sandbox::RpcSandboxImpersonateAccountResponse,
+        sandbox::RpcSandboxImpersonateAccountError,
+    > {
+        self.client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxImpersonateAccounts(
+                impersonate_request.account_ids,
+            ))
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        Ok(sandbox::RpcSandboxImpersonateAccountResponse {})
+    }
+
+    async fn sandbox_set_next_block_timestamp(
+        &self,
+        timestamp_request: sandbox::RpcSandboxSetNextBlockTimestampRequest,
+    ) -> Result<
+        sandbox::RpcSandboxSetNextBlockTimestampResponse,
+        sandbox::RpcSandboxSetNextBlockTimestampError,
+    > {
+        use near_client_primitives::types::SandboxResponse;
+
+        match self
+            .client_sender
+            .send_async(
+                near_client_primitives::types::SandboxMessage::SandboxSetNextBlockTimestamp(
+                    timestamp_request.timestamp_nanosec,
+                ),
+            )
+            .await
+            .map_err(RpcFrom::rpc_from)?
+        {
+            SandboxResponse::SandboxTimeUpdated => {
+                Ok(sandbox::RpcSandboxSetNextBlockTimestampResponse {})
+            }
+            SandboxResponse::SandboxTimeUpdateFailed(err) => {
+                Err(sandbox::RpcSandboxSetNextBlockTimestampError::InternalError {
+                    error_message: format!("sandbox failed to update time due to: {}", err),
+                })
+            }
+            response => Err(sandbox::RpcSandboxSetNextBlockTimestampError::InternalError {
+                error_message: format!("unexpected sandbox response: {:?}", response),
+            }),
+        }
+    }
+
+    async fn sandbox_time_warp(
+        &self,
+        time_warp_request: sandbox::RpcSandboxTimeWarpRequest,
+    ) -> Result<sandbox::RpcSandboxTimeWarpResponse, sandbox::RpcSandboxTimeWarpError> {
+        use near_client_primitives::types::SandboxResponse;
+
+        match self
+            .client_sender
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxTimeWarp(
+                time_warp_request.delta_nanosec,
+            ))
+            .await
+            .map_err(RpcFrom::rpc_from)?
+        {
+            SandboxResponse::SandboxTimeUpdated => Ok(sandbox::RpcSandboxTimeWarpResponse {}),
+            SandboxResponse::SandboxTimeUpdateFailed(err) => {
+                Err(sandbox::RpcSandboxTimeWarpError::InternalError {
+                    error_message: format!("sandbox failed to update time due to: {}", err),
+                })
+            }
+            response => Err(sandbox::RpcSandboxTimeWarpError::InternalError {
+                error_message: format!("unexpected sandbox response: {:?}", response),
+            }),
+        }
+    }
 }
 
 #[cfg(feature = "test_features")]
@@ -1385,13 +1921,26 @@ async fn handle_unknown_block(
 }
 
 async fn rpc_handler(
+    http_request: HttpRequest,
     request: web::Json<Message>,
     handler: web::Data<JsonRpcHandler>,
 ) -> HttpResponse {
-    let message = handler.process(request.0.clone()).await;
+    let client = handler.rate_limiter.client(&http_request);
+    if let Err(err) = handler.rate_limiter.check(client, &request.0) {
+        let mut response = match &err.error_struct {
+            Some(RpcErrorKind::RequestValidationError(_)) => HttpResponse::BadRequest(),
+            _ => HttpResponse::TooManyRequests(),
+        };
+        return response.json(Message::response(request.0.id(), Err(err)));
+    }
+
+    let Some(message) = handler.process(request.0.clone()).await else {
+        // Nothing to reply with since the request consisted of notifications only.
+        return HttpResponse::NoContent().finish();
+    };
 
-    let mut response = if let Message::Response(response) = &message {
-        match &response.result {
+    let mut response = match &message {
+        Message::Response(response) => match &response.result {
             Ok(_) => HttpResponse::Ok(),
             Err(err) => match &err.error_struct {
                 Some(RpcErrorKind::RequestValidationError(_)) => HttpResponse::BadRequest(),
@@ -1405,9 +1954,12 @@ async fn rpc_handler(
                 Some(RpcErrorKind::InternalError(_)) => HttpResponse::InternalServerError(),
                 None => HttpResponse::Ok(),
             },
-        }
-    } else {
-        HttpResponse::InternalServerError()
+        },
+        // A batch may mix successful and failed responses, each carrying its
+        // own error, so the HTTP status only reflects that the batch itself
+        // has been processed.
+        Message::Batch(_) => HttpResponse::Ok(),
+        _ => HttpResponse::InternalServerError(),
     };
 
     response.json(message)
@@ -1562,6 +2114,7 @@ async fn display_debug_html(
         "validator" => Some(debug_page_string!("validator.html", handler)),
         "validator.css" => Some(debug_page_string!("validator.css", handler)),
         "split_store" => Some(debug_page_string!("split_store.html", handler)),
+        "scrubber" => Some(debug_page_string!("scrubber.html", handler)),
         "congestion_control" => Some(debug_page_string!("congestion_control.html", handler)),
         "congestion_control.css" => Some(debug_page_string!("congestion_control.css", handler)),
         "congestion_control.js" => Some(debug_page_string!("congestion_control.js", handler)),
@@ -1587,12 +2140,16 @@ async fn display_debug_html(
 /// as a tuple containing a name of the server (e.g. `"JSON RPC"`) which can be
 /// used in diagnostic messages and a [`actix_web::dev::Server`] object which
 /// can be used to control the server (most notably stop it).
+///
+/// Relative paths in `config` are resolved against `home_dir`.
 pub fn start_http(
     config: RpcConfig,
+    home_dir: &Path,
     genesis_config: GenesisConfig,
     client_sender: ClientSenderForRpc,
     view_client_sender: ViewClientSenderForRpc,
     peer_manager_sender: PeerManagerSenderForRpc,
+    scrubber_sender: ScrubberSenderForRpc,
     #[cfg(feature = "test_features")] gc_sender: GCSenderForRpc,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
 ) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
@@ -1603,12 +2160,27 @@ pub fn start_http(
         polling_config,
         limits_config,
         enable_debug_rpc,
+        enable_wasm_gas_profiling,
         experimental_debug_pages_src_path: debug_pages_src_path,
+        websocket_config,
+        relayer_config,
     } = config;
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr.to_string());
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
     info!(target:"network", "Starting http server at {}", addr);
     let mut servers = Vec::new();
+    // Shared by all the workers so that the limits apply to the server as a whole.
+    let rate_limiter = Arc::new(RateLimiter::new(
+        limits_config.rate_limits.clone(),
+        near_async::time::Clock::real(),
+    ));
+    let relayer = relayer_config.enabled.then(|| {
+        let key_file = home_dir.join(&relayer_config.key_file);
+        match Relayer::new(relayer_config, &key_file, near_async::time::Clock::real()) {
+            Ok(relayer) => Arc::new(relayer),
+            Err(err) => panic!("Failed to load relayer key from {}: {}", key_file.display(), err),
+        }
+    });
     let listener = HttpServer::new(move || {
         App::new()
             .wrap(get_cors(&cors_allowed_origins))
@@ -1616,17 +2188,25 @@ pub fn start_http(
                 client_sender: client_sender.clone(),
                 view_client_sender: view_client_sender.clone(),
                 peer_manager_sender: peer_manager_sender.clone(),
+                scrubber_sender: scrubber_sender.clone(),
                 polling_config,
                 genesis_config: genesis_config.clone(),
                 enable_debug_rpc,
+                enable_wasm_gas_profiling,
                 debug_pages_src_path: debug_pages_src_path.clone().map(Into::into),
                 entity_debug_handler: entity_debug_handler.clone(),
+                json_payload_max_size: limits_config.json_payload_max_size,
+                batch_max_size: limits_config.batch_max_size,
+                websocket_config: websocket_config.clone(),
+                rate_limiter: rate_limiter.clone(),
+                relayer: relayer.clone(),
                 #[cfg(feature = "test_features")]
                 gc_sender: gc_sender.clone(),
             }))
             .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
             .wrap(middleware::Logger::default())
             .service(web::resource("/").route(web::post().to(rpc_handler)))
+            .service(web::resource("/ws").route(web::get().to(websocket::websocket_handler)))
             .service(
                 web::resource("/status")
                     .route(web::get().to(status_handler))
@@ -1,4 +1,4 @@
-use near_o11y::metrics::{exponential_buckets, HistogramVec, IntCounter, IntCounterVec};
+use near_o11y::metrics::{exponential_buckets, Histogram, HistogramVec, IntCounter, IntCounterVec};
 use std::sync::LazyLock;
 
 pub static RPC_PROCESSING_TIME: LazyLock<HistogramVec> = LazyLock::new(|| {
@@ -55,3 +55,34 @@ pub static RPC_UNREACHABLE_ERROR_COUNT: LazyLock<IntCounterVec> = LazyLock::new(
     )
     .unwrap()
 });
+pub static RPC_BATCH_SIZE: LazyLock<Histogram> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_histogram_with_buckets(
+        "near_rpc_batch_size",
+        "Number of requests in JSON RPC batches",
+        exponential_buckets(1.0, 2.0, 10).unwrap(),
+    )
+    .unwrap()
+});
+pub static RPC_WEBSOCKET_SUBSCRIPTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_int_gauge_vec(
+        "near_rpc_websocket_subscriptions",
+        "Number of active WebSocket subscriptions, by kind",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub static RPC_WEBSOCKET_LAGGED_BLOCKS_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_int_counter(
+        "near_rpc_websocket_lagged_blocks_total",
+        "Total count of accepted blocks skipped by WebSocket connections which fell behind",
+    )
+    .unwrap()
+});
+pub static RPC_THROTTLED_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_int_counter_vec(
+        "near_rpc_throttled_requests_total",
+        "Total count of JSON RPC calls rejected by rate limits, by method and exceeded limit",
+        &["method", "limit"],
+    )
+    .unwrap()
+});
@@ -0,0 +1,548 @@
+//! Rate limiting of JSON RPC requests.
+//!
+//! Every call costs a number of tokens which depends on the method being
+//! called.  The tokens are taken from the bucket of the client's IP address
+//! and from the bucket of the method, both refilled at a configured rate (see
+//! [`TokenBucket`]).  Requests which don't fit into one of the buckets are
+//! rejected with HTTP 429 and are not processed at all.  Requests costing more
+//! than a bucket can ever hold, such as large batches, are rejected as invalid
+//! instead, as retrying them would never succeed.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use actix_web::HttpRequest;
+use lru::LruCache;
+use near_async::time::Clock;
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::message::Message;
+use near_jsonrpc_primitives::types::query::RpcQueryRequest;
+use near_network::TokenBucket;
+
+use crate::{metrics, query_metrics_name, RpcRequest, RPC_METHODS};
+
+/// Header carrying an API key which exempts the client from rate limits.
+const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcTokenBucketConfig {
+    /// Maximum number of tokens the bucket can hold, i.e. the allowed burst.
+    pub maximum_size: u32,
+    /// Number of tokens added to the bucket every second.
+    pub refill_rate: f32,
+}
+
+fn default_method_costs() -> HashMap<String, u32> {
+    [
+        ("query_call_function", 10),
+        ("query_view_state", 5),
+        ("query_view_state_with_proof", 10),
+        ("EXPERIMENTAL_changes", 10),
+        ("EXPERIMENTAL_changes_in_block", 5),
+        ("EXPERIMENTAL_account_history", 5),
+        ("EXPERIMENTAL_access_key_history", 5),
+    ]
+    .into_iter()
+    .map(|(method, cost)| (method.to_string(), cost))
+    .collect()
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RpcRateLimitsConfig {
+    /// Bucket of every client IP address.  Per-IP limits are disabled if unset.
+    pub per_ip: Option<RpcTokenBucketConfig>,
+    /// Buckets shared by all clients calling the given method.
+    pub per_method: HashMap<String, RpcTokenBucketConfig>,
+    /// Number of tokens a call costs, by method.  Methods are named the same
+    /// way as in metrics, e.g. `query_call_function` is the `query` method
+    /// with the `call_function` request type.
+    pub method_costs: HashMap<String, u32>,
+    /// Cost of methods not listed in `method_costs`.
+    pub default_method_cost: u32,
+    /// Maximum number of IP addresses whose buckets are kept.  Buckets of the
+    /// least recently seen addresses are dropped first.
+    pub max_tracked_ips: usize,
+    /// Whether to take the client address from the `Forwarded` and
+    /// `X-Forwarded-For` headers.  Only enable it behind a trusted proxy.
+    pub use_forwarded_for: bool,
+    /// Clients which are not subject to any limits.
+    pub allowed_ips: Vec<IpAddr>,
+    /// API keys, passed in the `X-Api-Key` header, which exempt clients from
+    /// all limits.
+    pub allowed_api_keys: Vec<String>,
+}
+
+impl Default for RpcRateLimitsConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: None,
+            per_method: HashMap::new(),
+            method_costs: default_method_costs(),
+            default_method_cost: 1,
+            max_tracked_ips: 100_000,
+            use_forwarded_for: false,
+            allowed_ips: vec![],
+            allowed_api_keys: vec![],
+        }
+    }
+}
+
+impl RpcRateLimitsConfig {
+    fn is_enabled(&self) -> bool {
+        self.per_ip.is_some() || !self.per_method.is_empty()
+    }
+
+    fn method_cost(&self, method: &str) -> u32 {
+        self.method_costs.get(method).copied().unwrap_or(self.default_method_cost)
+    }
+}
+
+/// Identity of the client a request came from, as far as rate limits are
+/// concerned.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RpcClient {
+    /// The client is allowlisted and not limited.
+    Exempt,
+    Ip(IpAddr),
+    /// The address couldn't be determined; only per-method limits apply.
+    Unknown,
+}
+
+#[derive(Debug)]
+enum RateLimitExceeded {
+    Ip,
+    Method(String),
+}
+
+impl From<RateLimitExceeded> for RpcError {
+    fn from(limit: RateLimitExceeded) -> Self {
+        match limit {
+            RateLimitExceeded::Ip => RpcError::rate_limit_exceeded(
+                "Too many requests from this IP address".to_string(),
+                serde_json::json!({ "limit": "ip" }),
+            ),
+            RateLimitExceeded::Method(method) => RpcError::rate_limit_exceeded(
+                format!("Too many requests to {method}"),
+                serde_json::json!({ "limit": "method", "method": method }),
+            ),
+        }
+    }
+}
+
+pub(crate) struct RateLimiter {
+    config: RpcRateLimitsConfig,
+    clock: Clock,
+    ip_buckets: Mutex<LruCache<IpAddr, TokenBucket>>,
+    method_buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(mut config: RpcRateLimitsConfig, clock: Clock) -> Self {
+        let now = clock.now();
+        if let Some(per_ip) = &config.per_ip {
+            if let Err(err) = TokenBucket::new(0, 0, per_ip.refill_rate, now) {
+                tracing::warn!(target: "jsonrpc", %err, "ignoring per IP rate limit");
+                config.per_ip = None;
+            }
+        }
+        let mut method_buckets = HashMap::new();
+        for (method, bucket) in &config.per_method {
+            match TokenBucket::new(
+                bucket.maximum_size,
+                bucket.maximum_size,
+                bucket.refill_rate,
+                now,
+            ) {
+                Ok(bucket) => {
+                    method_buckets.insert(method.clone(), bucket);
+                }
+                Err(err) => {
+                    tracing::warn!(target: "jsonrpc", %err, method, "ignoring rate limit of method")
+                }
+            }
+        }
+        let max_tracked_ips =
+            NonZeroUsize::new(config.max_tracked_ips).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            config,
+            clock,
+            ip_buckets: Mutex::new(LruCache::new(max_tracked_ips)),
+            method_buckets: Mutex::new(method_buckets),
+        }
+    }
+
+    /// Identifies the client which sent the request.
+    pub fn client(&self, request: &HttpRequest) -> RpcClient {
+        if !self.config.is_enabled() {
+            return RpcClient::Exempt;
+        }
+        let api_key = request.headers().get(API_KEY_HEADER).and_then(|key| key.to_str().ok());
+        if let Some(api_key) = api_key {
+            if self.config.allowed_api_keys.iter().any(|allowed| allowed == api_key) {
+                return RpcClient::Exempt;
+            }
+        }
+        let ip = if self.config.use_forwarded_for {
+            request.connection_info().realip_remote_addr().and_then(|addr| {
+                addr.parse::<IpAddr>()
+                    .ok()
+                    .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+            })
+        } else {
+            request.peer_addr().map(|addr| addr.ip())
+        };
+        match ip {
+            Some(ip) if self.config.allowed_ips.contains(&ip) => RpcClient::Exempt,
+            Some(ip) => RpcClient::Ip(ip),
+            None => RpcClient::Unknown,
+        }
+    }
+
+    /// Takes tokens for all calls in the message (a single request or
+    /// notification, or a batch of them).
+    ///
+    /// Returns an error if the client or one of the methods has exceeded its
+    /// limit, in which case the message must not be processed.  Tokens are
+    /// taken only if all the buckets have enough of them, so a rejected
+    /// message doesn't count against any limit.
+    pub fn check(&self, client: RpcClient, message: &Message) -> Result<(), RpcError> {
+        if matches!(client, RpcClient::Exempt) {
+            return Ok(());
+        }
+        let mut methods = Vec::new();
+        collect_methods(message, &mut methods);
+        let mut method_costs = HashMap::<String, u32>::new();
+        for method in methods {
+            let cost = self.config.method_cost(&method);
+            let total = method_costs.entry(method).or_default();
+            *total = total.saturating_add(cost);
+        }
+        self.take_tokens(client, &method_costs)
+    }
+
+    /// Takes tokens for a message which couldn't be parsed, which costs as
+    /// much as a call to an unknown method.
+    pub fn check_unparsed(&self, client: RpcClient) -> Result<(), RpcError> {
+        if matches!(client, RpcClient::Exempt) {
+            return Ok(());
+        }
+        let method = "UNSUPPORTED_METHOD".to_owned();
+        let cost = self.config.method_cost(&method);
+        self.take_tokens(client, &HashMap::from([(method, cost)]))
+    }
+
+    fn take_tokens(
+        &self,
+        client: RpcClient,
+        method_costs: &HashMap<String, u32>,
+    ) -> Result<(), RpcError> {
+        let total_cost =
+            method_costs.values().fold(0u32, |total, cost| total.saturating_add(*cost));
+        if let (RpcClient::Ip(_), Some(per_ip)) = (client, &self.config.per_ip) {
+            if total_cost > per_ip.maximum_size {
+                return Err(RpcError::invalid_request(format!(
+                    "The request costs {total_cost} tokens, but at most {} are allowed per IP",
+                    per_ip.maximum_size
+                )));
+            }
+        }
+        for (method, cost) in method_costs {
+            if let Some(per_method) = self.config.per_method.get(method) {
+                if *cost > per_method.maximum_size {
+                    return Err(RpcError::invalid_request(format!(
+                        "The calls to {method} cost {cost} tokens, but at most {} are allowed",
+                        per_method.maximum_size
+                    )));
+                }
+            }
+        }
+        let now = self.clock.now();
+
+        // Both locks are held until the tokens are taken, so that the buckets
+        // can't change between checking and taking the tokens.
+        let mut ip_buckets = self.ip_buckets.lock().unwrap();
+        let mut ip_bucket = match (client, &self.config.per_ip) {
+            (RpcClient::Ip(ip), Some(per_ip)) => Some(ip_buckets.get_or_insert_mut(ip, || {
+                TokenBucket::new(per_ip.maximum_size, per_ip.maximum_size, per_ip.refill_rate, now)
+                    .expect("refill rate has been validated")
+            })),
+            _ => None,
+        };
+        let mut method_buckets = self.method_buckets.lock().unwrap();
+
+        if let Some(bucket) = &mut ip_bucket {
+            if !bucket.can_acquire(total_cost, now) {
+                return Err(self.throttled(method_costs, RateLimitExceeded::Ip));
+            }
+        }
+        for (method, cost) in method_costs {
+            if let Some(bucket) = method_buckets.get_mut(method) {
+                if !bucket.can_acquire(*cost, now) {
+                    let limit = RateLimitExceeded::Method(method.clone());
+                    return Err(self.throttled(method_costs, limit));
+                }
+            }
+        }
+
+        if let Some(bucket) = ip_bucket {
+            bucket.acquire(total_cost, now);
+        }
+        for (method, cost) in method_costs {
+            if let Some(bucket) = method_buckets.get_mut(method) {
+                bucket.acquire(*cost, now);
+            }
+        }
+        Ok(())
+    }
+
+    fn throttled(&self, method_costs: &HashMap<String, u32>, limit: RateLimitExceeded) -> RpcError {
+        let limit_name = match limit {
+            RateLimitExceeded::Ip => "ip",
+            RateLimitExceeded::Method(_) => "method",
+        };
+        for method in method_costs.keys() {
+            let method = self.metrics_name(method);
+            metrics::RPC_THROTTLED_REQUESTS_TOTAL.with_label_values(&[method, limit_name]).inc();
+        }
+        limit.into()
+    }
+
+    /// Returns the name under which calls of the method are reported in
+    /// metrics.  Unknown methods are reported as `UNSUPPORTED_METHOD`, the
+    /// same as by the handler, so that clients can't create arbitrary labels.
+    fn metrics_name<'a>(&self, method: &'a str) -> &'a str {
+        if RPC_METHODS.contains(&method)
+            || self.config.method_costs.contains_key(method)
+            || self.config.per_method.contains_key(method)
+        {
+            method
+        } else {
+            "UNSUPPORTED_METHOD"
+        }
+    }
+}
+
+/// Collects names of the methods called by the message, the same names as
+/// used in metrics.
+fn collect_methods(message: &Message, methods: &mut Vec<String>) {
+    let (method, params) = match message {
+        Message::Request(request) => (&request.method, &request.params),
+        Message::Notification(notification) => (&notification.method, &notification.params),
+        Message::Batch(messages) => {
+            for message in messages {
+                collect_methods(message, methods);
+            }
+            return;
+        }
+        Message::Response(_) | Message::UnmatchedSub(_) => return,
+    };
+    let method = if method == "query" {
+        match RpcQueryRequest::parse(params.clone()) {
+            Ok(request) => query_metrics_name(&request.request).to_string(),
+            Err(_) => method.clone(),
+        }
+    } else {
+        method.clone()
+    };
+    methods.push(method);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimiter, RpcClient, RpcRateLimitsConfig, RpcTokenBucketConfig};
+    use near_async::time::{Duration, FakeClock};
+    use near_jsonrpc_primitives::message::Message;
+    use serde_json::json;
+
+    fn call_function() -> Message {
+        Message::request(
+            "query".to_string(),
+            json!({
+                "request_type": "call_function",
+                "finality": "final",
+                "account_id": "test.near",
+                "method_name": "get",
+                "args_base64": "",
+            }),
+        )
+    }
+
+    fn status() -> Message {
+        Message::request("status".to_string(), json!(null))
+    }
+
+    #[test]
+    fn test_per_ip_limit() {
+        let clock = FakeClock::default();
+        let config = RpcRateLimitsConfig {
+            per_ip: Some(RpcTokenBucketConfig { maximum_size: 20, refill_rate: 10.0 }),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let client = RpcClient::Ip([1, 2, 3, 4].into());
+        let other_client = RpcClient::Ip([4, 3, 2, 1].into());
+
+        // `call_function` costs 10 tokens by default.
+        assert!(limiter.check(client, &call_function()).is_ok());
+        assert!(limiter.check(client, &call_function()).is_ok());
+        assert!(limiter.check(client, &status()).is_err());
+        assert!(limiter.check(other_client, &status()).is_ok());
+        assert!(limiter.check(RpcClient::Exempt, &call_function()).is_ok());
+
+        clock.advance(Duration::milliseconds(100));
+        assert!(limiter.check(client, &status()).is_ok());
+        assert!(limiter.check(client, &call_function()).is_err());
+    }
+
+    #[test]
+    fn test_per_method_limit() {
+        let clock = FakeClock::default();
+        let config = RpcRateLimitsConfig {
+            per_method: [(
+                "query_call_function".to_string(),
+                RpcTokenBucketConfig { maximum_size: 25, refill_rate: 0.0 },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let client = RpcClient::Ip([1, 2, 3, 4].into());
+        let other_client = RpcClient::Unknown;
+
+        assert!(limiter.check(client, &call_function()).is_ok());
+        assert!(limiter.check(other_client, &call_function()).is_ok());
+        assert!(limiter.check(client, &call_function()).is_err());
+        assert!(limiter.check(client, &status()).is_ok());
+    }
+
+    #[test]
+    fn test_batch_is_charged_for_every_call() {
+        let clock = FakeClock::default();
+        let config = RpcRateLimitsConfig {
+            per_ip: Some(RpcTokenBucketConfig { maximum_size: 25, refill_rate: 0.0 }),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let client = RpcClient::Ip([1, 2, 3, 4].into());
+
+        let batch = Message::Batch(vec![call_function(), call_function(), status()]);
+        assert!(limiter.check(client, &batch).is_ok());
+        let batch = Message::Batch(vec![status(), status(), status(), status(), status()]);
+        assert!(limiter.check(client, &batch).is_err());
+    }
+
+    #[test]
+    fn test_rejected_message_takes_no_tokens() {
+        let clock = FakeClock::default();
+        let config = RpcRateLimitsConfig {
+            per_ip: Some(RpcTokenBucketConfig { maximum_size: 25, refill_rate: 0.0 }),
+            per_method: [
+                ("status".to_string(), RpcTokenBucketConfig { maximum_size: 2, refill_rate: 0.0 }),
+                (
+                    "query_call_function".to_string(),
+                    RpcTokenBucketConfig { maximum_size: 10, refill_rate: 0.0 },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let client = RpcClient::Ip([1, 2, 3, 4].into());
+
+        // The batch exceeds the limit of `query_call_function`, so neither the
+        // IP address nor `status` are charged for it.
+        let batch = Message::Batch(vec![status(), call_function(), call_function()]);
+        assert!(limiter.check(client, &batch).is_err());
+        assert!(limiter.check(client, &call_function()).is_ok());
+        assert!(limiter.check(client, &status()).is_ok());
+        assert!(limiter.check(client, &status()).is_ok());
+        assert!(limiter.check(client, &status()).is_err());
+
+        // Nor are the methods charged for a message over the limit of the IP
+        // address.
+        let config = RpcRateLimitsConfig {
+            per_ip: Some(RpcTokenBucketConfig { maximum_size: 1, refill_rate: 0.0 }),
+            per_method: [(
+                "status".to_string(),
+                RpcTokenBucketConfig { maximum_size: 2, refill_rate: 0.0 },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let batch = Message::Batch(vec![status(), status()]);
+        assert!(limiter.check(client, &batch).is_err());
+        let other_client = RpcClient::Ip([4, 3, 2, 1].into());
+        assert!(limiter.check(other_client, &status()).is_ok());
+        assert!(limiter.check(RpcClient::Unknown, &status()).is_ok());
+        assert!(limiter.check(RpcClient::Unknown, &status()).is_err());
+    }
+
+    #[test]
+    fn test_unparsed_message_is_charged() {
+        let clock = FakeClock::default();
+        let config = RpcRateLimitsConfig {
+            per_ip: Some(RpcTokenBucketConfig { maximum_size: 2, refill_rate: 0.0 }),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let client = RpcClient::Ip([1, 2, 3, 4].into());
+
+        assert!(limiter.check_unparsed(client).is_ok());
+        assert!(limiter.check(client, &status()).is_ok());
+        assert!(limiter.check_unparsed(client).is_err());
+        assert!(limiter.check_unparsed(RpcClient::Exempt).is_ok());
+    }
+
+    #[test]
+    fn test_request_over_bucket_size_is_invalid() {
+        let clock = FakeClock::default();
+        let config = RpcRateLimitsConfig {
+            per_ip: Some(RpcTokenBucketConfig { maximum_size: 25, refill_rate: 0.0 }),
+            per_method: [(
+                "status".to_string(),
+                RpcTokenBucketConfig { maximum_size: 2, refill_rate: 0.0 },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, clock.clock());
+        let client = RpcClient::Ip([1, 2, 3, 4].into());
+
+        // Such requests would never fit into the buckets, so they are invalid
+        // rather than throttled, and take no tokens.
+        let batch = Message::Batch(vec![call_function(), call_function(), call_function()]);
+        assert_eq!(limiter.check(client, &batch).unwrap_err().code, -32_600);
+        let batch = Message::Batch(vec![status(), status(), status()]);
+        assert_eq!(limiter.check(RpcClient::Unknown, &batch).unwrap_err().code, -32_600);
+        assert!(limiter.check(RpcClient::Exempt, &batch).is_ok());
+        assert!(limiter.check(client, &call_function()).is_ok());
+        assert!(limiter.check(client, &call_function()).is_ok());
+        assert!(limiter.check(client, &status()).is_ok());
+    }
+
+    #[test]
+    fn test_metrics_name() {
+        let config = RpcRateLimitsConfig {
+            per_method: [(
+                "custom".to_string(),
+                RpcTokenBucketConfig { maximum_size: 2, refill_rate: 0.0 },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config, FakeClock::default().clock());
+        assert_eq!(limiter.metrics_name("status"), "status");
+        assert_eq!(limiter.metrics_name("query_view_account"), "query_view_account");
+        assert_eq!(limiter.metrics_name("custom"), "custom");
+        assert_eq!(limiter.metrics_name("no_such_method"), "UNSUPPORTED_METHOD");
+    }
+}
@@ -0,0 +1,398 @@
+//! Built-in relayer of meta transactions (NEP-366).
+//!
+//! Users sign `DelegateAction`s with their own keys and send them to the
+//! `relay_delegate_action` method.  The relayer wraps each of them in a
+//! transaction signed by the relayer account, which pays for the gas.  Only
+//! calls to the configured contracts and methods are relayed and the gas each
+//! user may spend is limited by a budget replenished periodically.
+//!
+//! Before a delegate action is relayed, its public key must be an access key
+//! of its sender, its nonce must be above the nonce of that key and it must
+//! not have expired, so that the relayer pays only for actions which can be
+//! executed and budgets are tracked only for existing accounts.
+//!
+//! Budgets are tracked in memory only, so they are reset when the node
+//! restarts.  Budgets of users who haven't relayed anything for a whole
+//! period are dropped.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use near_async::time::{Clock, Duration, Instant};
+use near_crypto::{InMemorySigner, PublicKey, Signer};
+use near_jsonrpc_primitives::types::relayer::RpcRelayError;
+use near_primitives::action::delegate::{DelegateAction, SignedDelegateAction};
+use near_primitives::action::Action;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{SignedTransaction, Transaction, TransactionV0};
+use near_primitives::types::{AccountId, BlockHeight, Gas, Nonce};
+use near_primitives::views::AccessKeyView;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RpcRelayerConfig {
+    /// Whether the `relay_delegate_action` method is enabled.
+    pub enabled: bool,
+    /// Key file of the relayer account in the same format as
+    /// `validator_key.json`.  Relative paths are resolved against the home
+    /// directory of the node.
+    pub key_file: PathBuf,
+    /// Accounts which relayed delegate actions may be sent to.
+    pub allowed_receivers: Vec<AccountId>,
+    /// Methods which relayed delegate actions may call.  If empty, any method
+    /// of the allowed receivers may be called.
+    pub allowed_methods: Vec<String>,
+    /// Maximum amount of gas attached to function calls relayed for a single
+    /// user within `budget_period`.  Budgets are not persisted, so a restart
+    /// of the node replenishes them.
+    pub gas_budget_per_user: Gas,
+    /// How often users' gas budgets are replenished.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub budget_period: Duration,
+}
+
+impl Default for RpcRelayerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_file: "relayer_key.json".into(),
+            allowed_receivers: vec![],
+            allowed_methods: vec![],
+            gas_budget_per_user: 3_000_000_000_000_000,
+            budget_period: Duration::days(1),
+        }
+    }
+}
+
+/// Gas used by a single user in the current budget period.
+struct GasBudget {
+    period_start: Instant,
+    used: Gas,
+}
+
+/// Gas budgets of all users who relayed delegate actions recently.
+struct GasBudgets {
+    users: HashMap<AccountId, GasBudget>,
+    /// When budgets of expired periods were last dropped.
+    pruned_at: Instant,
+}
+
+impl GasBudgets {
+    fn new(now: Instant) -> Self {
+        Self { users: HashMap::new(), pruned_at: now }
+    }
+
+    /// Drops the budgets whose period has expired, once per period, so that
+    /// only users who relayed something recently are tracked.
+    fn prune(&mut self, now: Instant, budget_period: Duration) {
+        if now - self.pruned_at < budget_period {
+            return;
+        }
+        self.users.retain(|_, budget| now - budget.period_start < budget_period);
+        self.pruned_at = now;
+    }
+}
+
+/// Checks that the delegate action can be executed by its sender, given the
+/// access key of its public key as of the block at `block_height`.
+pub(crate) fn check_access_key(
+    delegate_action: &DelegateAction,
+    access_key: &AccessKeyView,
+    block_height: BlockHeight,
+) -> Result<(), RpcRelayError> {
+    if delegate_action.nonce <= access_key.nonce {
+        return Err(RpcRelayError::InvalidNonce {
+            nonce: delegate_action.nonce,
+            access_key_nonce: access_key.nonce,
+        });
+    }
+    if delegate_action.max_block_height < block_height {
+        return Err(RpcRelayError::Expired {
+            max_block_height: delegate_action.max_block_height,
+            block_height,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) struct Relayer {
+    config: RpcRelayerConfig,
+    signer: Signer,
+    clock: Clock,
+    budgets: Mutex<GasBudgets>,
+    /// Nonce of the last transaction signed by the relayer.  Used so that
+    /// requests relayed concurrently get distinct nonces even before the
+    /// previous transactions are included in the chain.
+    last_nonce: Mutex<Nonce>,
+}
+
+impl Relayer {
+    /// Creates a relayer signing with the key read from `key_file`, which is
+    /// `config.key_file` resolved against the home directory.
+    pub fn new(config: RpcRelayerConfig, key_file: &Path, clock: Clock) -> std::io::Result<Self> {
+        let signer = InMemorySigner::from_file(key_file)?;
+        tracing::info!(target: "jsonrpc", account_id = %signer.get_account_id(), "Relayer enabled");
+        let budgets = Mutex::new(GasBudgets::new(clock.now()));
+        Ok(Self { config, signer, clock, budgets, last_nonce: Mutex::new(0) })
+    }
+
+    pub fn account_id(&self) -> AccountId {
+        self.signer.get_account_id()
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.signer.public_key()
+    }
+
+    /// Checks that the delegate action may be relayed and charges the gas it
+    /// attaches to the budget of its sender.  Returns the charged gas.
+    pub fn check_and_charge(
+        &self,
+        signed_delegate_action: &SignedDelegateAction,
+    ) -> Result<Gas, RpcRelayError> {
+        if !signed_delegate_action.verify() {
+            return Err(RpcRelayError::InvalidSignature);
+        }
+        let delegate_action = &signed_delegate_action.delegate_action;
+        if !self.config.allowed_receivers.contains(&delegate_action.receiver_id) {
+            return Err(RpcRelayError::ReceiverNotAllowed {
+                receiver_id: delegate_action.receiver_id.clone(),
+            });
+        }
+        let mut gas: Gas = 0;
+        for action in delegate_action.get_actions() {
+            let Action::FunctionCall(function_call) = &action else {
+                let action = action.as_ref().to_owned();
+                return Err(RpcRelayError::ActionNotAllowed { action });
+            };
+            if !self.config.allowed_methods.is_empty()
+                && !self.config.allowed_methods.contains(&function_call.method_name)
+            {
+                return Err(RpcRelayError::MethodNotAllowed {
+                    method_name: function_call.method_name.clone(),
+                });
+            }
+            gas = gas.saturating_add(function_call.gas);
+        }
+
+        let now = self.clock.now();
+        let mut budgets = self.budgets.lock().unwrap();
+        budgets.prune(now, self.config.budget_period);
+        let budget = budgets
+            .users
+            .entry(delegate_action.sender_id.clone())
+            .or_insert(GasBudget { period_start: now, used: 0 });
+        if now - budget.period_start >= self.config.budget_period {
+            *budget = GasBudget { period_start: now, used: 0 };
+        }
+        let used = budget.used.saturating_add(gas);
+        if used > self.config.gas_budget_per_user {
+            return Err(RpcRelayError::GasBudgetExceeded {
+                sender_id: delegate_action.sender_id.clone(),
+                budget: self.config.gas_budget_per_user,
+                used: budget.used,
+            });
+        }
+        budget.used = used;
+        Ok(gas)
+    }
+
+    /// Returns gas charged by `check_and_charge` to the sender's budget if
+    /// the relayed transaction has been rejected.
+    pub fn refund(&self, sender_id: &AccountId, gas: Gas) {
+        if let Some(budget) = self.budgets.lock().unwrap().users.get_mut(sender_id) {
+            budget.used = budget.used.saturating_sub(gas);
+        }
+    }
+
+    /// Wraps the delegate action in a transaction signed by the relayer.
+    ///
+    /// `access_key_nonce` is the nonce of the relayer's access key as seen in
+    /// the block with `block_hash`.
+    pub fn sign_transaction(
+        &self,
+        signed_delegate_action: SignedDelegateAction,
+        access_key_nonce: Nonce,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
+        let nonce = {
+            let mut last_nonce = self.last_nonce.lock().unwrap();
+            *last_nonce = (*last_nonce).max(access_key_nonce) + 1;
+            *last_nonce
+        };
+        let transaction = Transaction::V0(TransactionV0 {
+            signer_id: self.signer.get_account_id(),
+            public_key: self.signer.public_key(),
+            nonce,
+            receiver_id: signed_delegate_action.delegate_action.sender_id.clone(),
+            block_hash,
+            actions: vec![Action::Delegate(Box::new(signed_delegate_action))],
+        });
+        let signature = self.signer.sign(transaction.get_hash_and_size().0.as_ref());
+        SignedTransaction::new(signature, transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_async::time::FakeClock;
+    use near_crypto::KeyType;
+    use near_primitives::action::delegate::DelegateAction;
+    use near_primitives::action::{FunctionCallAction, TransferAction};
+    use near_primitives::signable_message::{SignableMessage, SignableMessageType};
+
+    fn relayer(clock: Clock) -> Relayer {
+        let config = RpcRelayerConfig {
+            enabled: true,
+            allowed_receivers: vec!["game.near".parse().unwrap()],
+            allowed_methods: vec!["play".to_owned()],
+            gas_budget_per_user: 100,
+            budget_period: Duration::hours(1),
+            ..Default::default()
+        };
+        Relayer {
+            config,
+            signer: InMemorySigner::from_seed(
+                "relayer.near".parse().unwrap(),
+                KeyType::ED25519,
+                "relayer",
+            ),
+            budgets: Mutex::new(GasBudgets::new(clock.now())),
+            clock,
+            last_nonce: Mutex::new(0),
+        }
+    }
+
+    fn delegate_action(receiver_id: &str, actions: Vec<Action>) -> SignedDelegateAction {
+        delegate_action_from("alice.near", receiver_id, actions)
+    }
+
+    fn delegate_action_from(
+        sender_id: &str,
+        receiver_id: &str,
+        actions: Vec<Action>,
+    ) -> SignedDelegateAction {
+        let sender_id: AccountId = sender_id.parse().unwrap();
+        let signer =
+            InMemorySigner::from_seed(sender_id.clone(), KeyType::ED25519, sender_id.as_str());
+        let delegate_action = DelegateAction {
+            sender_id,
+            receiver_id: receiver_id.parse().unwrap(),
+            actions: actions.into_iter().map(|action| action.try_into().unwrap()).collect(),
+            nonce: 1,
+            max_block_height: 1000,
+            public_key: signer.public_key(),
+        };
+        let signature = SignableMessage::new(&delegate_action, SignableMessageType::DelegateAction)
+            .sign(&signer);
+        SignedDelegateAction { delegate_action, signature }
+    }
+
+    fn function_call(method_name: &str, gas: Gas) -> Action {
+        Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: method_name.to_owned(),
+            args: vec![],
+            gas,
+            deposit: 0,
+        }))
+    }
+
+    #[test]
+    fn test_policy() {
+        let relayer = relayer(FakeClock::default().clock());
+        let action = delegate_action("game.near", vec![function_call("play", 10)]);
+        assert_eq!(relayer.check_and_charge(&action).unwrap(), 10);
+
+        let mut invalid_signature = action.clone();
+        invalid_signature.delegate_action.nonce += 1;
+        assert!(matches!(
+            relayer.check_and_charge(&invalid_signature),
+            Err(RpcRelayError::InvalidSignature)
+        ));
+
+        let action = delegate_action("bank.near", vec![function_call("play", 10)]);
+        assert!(matches!(
+            relayer.check_and_charge(&action),
+            Err(RpcRelayError::ReceiverNotAllowed { .. })
+        ));
+
+        let action = delegate_action("game.near", vec![function_call("cheat", 10)]);
+        assert!(matches!(
+            relayer.check_and_charge(&action),
+            Err(RpcRelayError::MethodNotAllowed { .. })
+        ));
+
+        let transfer = Action::Transfer(TransferAction { deposit: 1 });
+        let action = delegate_action("game.near", vec![transfer]);
+        assert!(matches!(
+            relayer.check_and_charge(&action),
+            Err(RpcRelayError::ActionNotAllowed { action }) if action == "Transfer"
+        ));
+    }
+
+    #[test]
+    fn test_gas_budget() {
+        let clock = FakeClock::default();
+        let relayer = relayer(clock.clock());
+        let action = delegate_action("game.near", vec![function_call("play", 40)]);
+        assert!(relayer.check_and_charge(&action).is_ok());
+        assert!(relayer.check_and_charge(&action).is_ok());
+        assert!(matches!(
+            relayer.check_and_charge(&action),
+            Err(RpcRelayError::GasBudgetExceeded { used: 80, budget: 100, .. })
+        ));
+
+        relayer.refund(&action.delegate_action.sender_id, 40);
+        assert!(relayer.check_and_charge(&action).is_ok());
+
+        clock.advance(Duration::hours(1));
+        assert!(relayer.check_and_charge(&action).is_ok());
+        assert!(relayer.check_and_charge(&action).is_ok());
+
+        // Budgets are dropped once their period has expired.
+        let bob = delegate_action_from("bob.near", "game.near", vec![function_call("play", 10)]);
+        clock.advance(Duration::minutes(30));
+        assert!(relayer.check_and_charge(&bob).is_ok());
+        assert_eq!(relayer.budgets.lock().unwrap().users.len(), 2);
+        clock.advance(Duration::hours(1));
+        assert!(relayer.check_and_charge(&bob).is_ok());
+        assert_eq!(relayer.budgets.lock().unwrap().users.len(), 1);
+    }
+
+    #[test]
+    fn test_check_access_key() {
+        let action = delegate_action("game.near", vec![function_call("play", 10)]);
+        let delegate_action = &action.delegate_action;
+        let access_key = |nonce| AccessKeyView {
+            nonce,
+            permission: near_primitives::views::AccessKeyPermissionView::FullAccess,
+        };
+        assert!(check_access_key(delegate_action, &access_key(0), 1000).is_ok());
+        assert!(matches!(
+            check_access_key(delegate_action, &access_key(1), 10),
+            Err(RpcRelayError::InvalidNonce { nonce: 1, access_key_nonce: 1 })
+        ));
+        assert!(matches!(
+            check_access_key(delegate_action, &access_key(0), 1001),
+            Err(RpcRelayError::Expired { max_block_height: 1000, block_height: 1001 })
+        ));
+    }
+
+    #[test]
+    fn test_sign_transaction() {
+        let relayer = relayer(FakeClock::default().clock());
+        let action = delegate_action("game.near", vec![function_call("play", 10)]);
+        let tx = relayer.sign_transaction(action.clone(), 5, CryptoHash::default());
+        assert_eq!(tx.transaction.nonce(), 6);
+        assert_eq!(tx.transaction.signer_id().as_str(), "relayer.near");
+        assert_eq!(tx.transaction.receiver_id().as_str(), "alice.near");
+        assert!(tx.signature.verify(tx.get_hash().as_ref(), &relayer.public_key()));
+
+        // Transactions relayed before the previous ones are included get
+        // consecutive nonces.
+        let tx = relayer.sign_transaction(action, 5, CryptoHash::default());
+        assert_eq!(tx.transaction.nonce(), 7);
+    }
+}
@@ -0,0 +1,439 @@
+//! WebSocket endpoint of the JSON RPC server.
+//!
+//! Besides regular JSON RPC requests, which are handled the same way as over
+//! HTTP, a WebSocket connection accepts `subscribe_*` and `unsubscribe_*`
+//! methods.  Events for active subscriptions are pushed to the client as
+//! `subscription` notifications whenever the client actor accepts a block.
+//!
+//! Every connection has its own task which reads hashes of accepted blocks
+//! from a bounded broadcast channel.  A connection which cannot keep up with
+//! the node skips blocks (and is told so with a `subscription_lagged`
+//! notification) rather than slowing down block processing.
+
+use std::collections::HashMap;
+
+use actix_web::{web, Error as HttpError, HttpRequest, HttpResponse};
+use near_async::messaging::SendAsync;
+use near_client::{AcceptedBlocksReceiver, SubscribeAcceptedBlocks, TxStatus, TxStatuses};
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::message::{self, Message, Request};
+use near_jsonrpc_primitives::types::blocks::RpcBlockRequest;
+use near_jsonrpc_primitives::types::changes::RpcStateChangesInBlockByTypeRequest;
+use near_jsonrpc_primitives::types::chunks::{ChunkReference, RpcChunkRequest};
+use near_jsonrpc_primitives::types::subscriptions::{
+    RpcSubscribeAccountChangesRequest, RpcSubscribeChunksRequest, RpcSubscribeTxStatusRequest,
+    RpcSubscriptionError, RpcSubscriptionLaggedNotification, RpcSubscriptionNotification,
+    RpcUnsubscribeRequest, SubscriptionId, SUBSCRIPTION_LAGGED_NOTIFICATION_METHOD,
+    SUBSCRIPTION_NOTIFICATION_METHOD,
+};
+use near_jsonrpc_primitives::types::transactions::RpcTransactionResponse;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockId, BlockReference, ShardId};
+use near_primitives::views::{BlockView, StateChangesRequestView};
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::rate_limits::RpcClient;
+use crate::{
+    metrics, serialize_response, tx_execution_status_meets_expectations, JsonRpcHandler, RpcRequest,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RpcWebSocketConfig {
+    /// Whether the `/ws` endpoint is served.
+    pub enabled: bool,
+    /// Maximum number of active subscriptions in a single connection.
+    pub max_subscriptions_per_connection: usize,
+}
+
+impl Default for RpcWebSocketConfig {
+    fn default() -> Self {
+        Self { enabled: false, max_subscriptions_per_connection: 32 }
+    }
+}
+
+enum Subscription {
+    Blocks,
+    Chunks(ShardId),
+    TxStatus(RpcSubscribeTxStatusRequest),
+    AccountChanges(Vec<AccountId>),
+}
+
+impl Subscription {
+    /// Kind of the subscription as used in `subscribe_<kind>` and
+    /// `unsubscribe_<kind>` method names.
+    fn kind(&self) -> &'static str {
+        match self {
+            Subscription::Blocks => "blocks",
+            Subscription::Chunks(_) => "chunks",
+            Subscription::TxStatus(_) => "tx_status",
+            Subscription::AccountChanges(_) => "account_changes",
+        }
+    }
+}
+
+pub(crate) async fn websocket_handler(
+    request: HttpRequest,
+    body: web::Payload,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.websocket_config.enabled {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let AcceptedBlocksReceiver(accepted_blocks) =
+        match handler.client_sender.send_async(SubscribeAcceptedBlocks).await {
+            Ok(receiver) => receiver,
+            Err(_) => return Ok(HttpResponse::ServiceUnavailable().finish()),
+        };
+    let client = handler.rate_limiter.client(&request);
+    let (response, session, stream) = actix_ws::handle(&request, body)?;
+    let stream = stream.max_frame_size(handler.json_payload_max_size);
+    let session = WebSocketSession {
+        handler,
+        session,
+        client,
+        subscriptions: HashMap::new(),
+        next_subscription_id: 0,
+    };
+    actix_web::rt::spawn(session.run(stream, accepted_blocks));
+    Ok(response)
+}
+
+/// Error returned when the WebSocket connection has been closed.
+struct Closed;
+
+struct WebSocketSession {
+    handler: web::Data<JsonRpcHandler>,
+    session: actix_ws::Session,
+    /// Client the connection came from, whose rate limits every message
+    /// received over the connection is subject to.
+    client: RpcClient,
+    subscriptions: HashMap<SubscriptionId, Subscription>,
+    next_subscription_id: SubscriptionId,
+}
+
+impl WebSocketSession {
+    async fn run(
+        mut self,
+        mut stream: actix_ws::MessageStream,
+        mut accepted_blocks: broadcast::Receiver<CryptoHash>,
+    ) {
+        loop {
+            let result = tokio::select! {
+                message = stream.recv() => match message {
+                    Some(Ok(actix_ws::Message::Text(text))) => self.on_text(&text).await,
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        self.session.pong(&bytes).await.map_err(|_| Closed)
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => Err(Closed),
+                    Some(Ok(_)) => Ok(()),
+                },
+                block_hash = accepted_blocks.recv() => match block_hash {
+                    Ok(block_hash) => self.on_accepted_block(block_hash).await,
+                    Err(RecvError::Lagged(skipped_blocks)) => {
+                        metrics::RPC_WEBSOCKET_LAGGED_BLOCKS_TOTAL.inc_by(skipped_blocks);
+                        self.send_notification(
+                            SUBSCRIPTION_LAGGED_NOTIFICATION_METHOD,
+                            RpcSubscriptionLaggedNotification { skipped_blocks },
+                        )
+                        .await
+                    }
+                    Err(RecvError::Closed) => Err(Closed),
+                },
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        for subscription in self.subscriptions.values() {
+            metrics::RPC_WEBSOCKET_SUBSCRIPTIONS.with_label_values(&[subscription.kind()]).dec();
+        }
+        let _ = self.session.close(None).await;
+    }
+
+    async fn on_text(&mut self, text: &str) -> Result<(), Closed> {
+        let message = match message::from_str(text) {
+            Ok(message) => message,
+            Err(broken) => {
+                let response = match self.handler.rate_limiter.check_unparsed(self.client) {
+                    Ok(()) => broken.reply(),
+                    Err(err) => Message::error(err),
+                };
+                return self.send(response).await;
+            }
+        };
+        if let Err(err) = self.handler.rate_limiter.check(self.client, &message) {
+            return self.send(Message::response(message.id(), Err(err))).await;
+        }
+        let response = match message {
+            Message::Batch(messages) => self.process_batch(messages).await,
+            message => match self.process_subscription(message) {
+                Ok(response) => Some(response),
+                Err(message) => self.handler.process(message).await,
+            },
+        };
+        match response {
+            Some(response) => self.send(response).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Processes entries of a batch the same way as `JsonRpcHandler` does,
+    /// except that subscription requests are handled by the session.
+    async fn process_batch(&mut self, messages: Vec<Message>) -> Option<Message> {
+        if let Err(error) = self.handler.check_batch(&messages) {
+            return Some(error);
+        }
+        let mut responses = Vec::new();
+        let mut other_messages = Vec::new();
+        for (index, message) in messages.into_iter().enumerate() {
+            match self.process_subscription(message) {
+                Ok(response) => responses.push((index, response)),
+                Err(message) => other_messages.push((index, message)),
+            }
+        }
+        let handler = &self.handler;
+        let other_responses = other_messages.into_iter().map(|(index, message)| async move {
+            handler.process_batch_entry(message).await.map(|response| (index, response))
+        });
+        let other_responses = futures::future::join_all(other_responses).await;
+        responses.extend(other_responses.into_iter().flatten());
+        if responses.is_empty() {
+            return None;
+        }
+        responses.sort_by_key(|(index, _)| *index);
+        Some(Message::Batch(responses.into_iter().map(|(_, response)| response).collect()))
+    }
+
+    /// Handles the message if it is a `subscribe_*` or `unsubscribe_*`
+    /// request and returns the response, or returns the message back
+    /// otherwise.
+    fn process_subscription(&mut self, message: Message) -> Result<Message, Message> {
+        match message {
+            Message::Request(request)
+                if request.method.starts_with("subscribe_")
+                    || request.method.starts_with("unsubscribe_") =>
+            {
+                let id = request.id.clone();
+                let result = if request.method.starts_with("subscribe_") {
+                    self.subscribe(request)
+                } else {
+                    self.unsubscribe(request)
+                };
+                Ok(Message::response(id, result))
+            }
+            message => Err(message),
+        }
+    }
+
+    fn subscribe(&mut self, request: Request) -> Result<Value, RpcError> {
+        let subscription = match request.method.as_str() {
+            "subscribe_blocks" => Subscription::Blocks,
+            "subscribe_chunks" => {
+                let RpcSubscribeChunksRequest { shard_id } = RpcRequest::parse(request.params)?;
+                Subscription::Chunks(shard_id)
+            }
+            "subscribe_tx_status" => Subscription::TxStatus(RpcRequest::parse(request.params)?),
+            "subscribe_account_changes" => {
+                let RpcSubscribeAccountChangesRequest { account_ids } =
+                    RpcRequest::parse(request.params)?;
+                Subscription::AccountChanges(account_ids)
+            }
+            _ => return Err(RpcError::method_not_found(request.method)),
+        };
+        let limit = self.handler.websocket_config.max_subscriptions_per_connection;
+        if self.subscriptions.len() >= limit {
+            return Err(RpcSubscriptionError::TooManySubscriptions { limit }.into());
+        }
+        let subscription_id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        metrics::RPC_WEBSOCKET_SUBSCRIPTIONS.with_label_values(&[subscription.kind()]).inc();
+        self.subscriptions.insert(subscription_id, subscription);
+        serialize_response(subscription_id)
+    }
+
+    fn unsubscribe(&mut self, request: Request) -> Result<Value, RpcError> {
+        let kind = request.method.strip_prefix("unsubscribe_").unwrap_or_default();
+        if !["blocks", "chunks", "tx_status", "account_changes"].contains(&kind) {
+            return Err(RpcError::method_not_found(request.method));
+        }
+        let RpcUnsubscribeRequest { subscription } = RpcRequest::parse(request.params)?;
+        match self.subscriptions.get(&subscription) {
+            Some(existing) if existing.kind() == kind => {
+                self.subscriptions.remove(&subscription);
+                metrics::RPC_WEBSOCKET_SUBSCRIPTIONS.with_label_values(&[kind]).dec();
+                serialize_response(true)
+            }
+            _ => Err(RpcSubscriptionError::UnknownSubscription { subscription }.into()),
+        }
+    }
+
+    /// Sends events for all subscriptions which are affected by the block.
+    async fn on_accepted_block(&mut self, block_hash: CryptoHash) -> Result<(), Closed> {
+        if self.subscriptions.is_empty() {
+            return Ok(());
+        }
+        let block_reference = BlockReference::BlockId(BlockId::Hash(block_hash));
+        let block = match self.handler.block(RpcBlockRequest { block_reference }).await {
+            Ok(response) => response.block_view,
+            Err(err) => {
+                tracing::debug!(
+                    target: "jsonrpc", ?block_hash, ?err, "Failed to fetch accepted block"
+                );
+                return Ok(());
+            }
+        };
+
+        let mut tx_status_events = self.tx_status_events().await;
+        let mut events = Vec::new();
+        let mut finished = Vec::new();
+        for (&subscription_id, subscription) in &self.subscriptions {
+            let result = match subscription {
+                Subscription::Blocks => serialize_response(&block).ok(),
+                Subscription::Chunks(shard_id) => self.chunk_event(&block, *shard_id).await,
+                Subscription::TxStatus(_) => {
+                    let event = tx_status_events.remove(&subscription_id);
+                    if event.is_some() {
+                        finished.push(subscription_id);
+                    }
+                    event
+                }
+                Subscription::AccountChanges(account_ids) => {
+                    self.account_changes_event(&block, account_ids.clone()).await
+                }
+            };
+            if let Some(result) = result {
+                events.push(RpcSubscriptionNotification { subscription: subscription_id, result });
+            }
+        }
+        // Subscriptions to transaction status fire only once.
+        for subscription_id in finished {
+            if let Some(subscription) = self.subscriptions.remove(&subscription_id) {
+                metrics::RPC_WEBSOCKET_SUBSCRIPTIONS
+                    .with_label_values(&[subscription.kind()])
+                    .dec();
+            }
+        }
+
+        events.sort_by_key(|event| event.subscription);
+        for event in events {
+            self.send_notification(SUBSCRIPTION_NOTIFICATION_METHOD, event).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the chunk of the given shard if it is new in the block.
+    async fn chunk_event(&self, block: &BlockView, shard_id: ShardId) -> Option<Value> {
+        let header = block.chunks.iter().find(|header| {
+            header.shard_id == shard_id && header.height_included == block.header.height
+        })?;
+        let chunk_reference = ChunkReference::ChunkHash { chunk_id: header.chunk_hash };
+        match self.handler.chunk(RpcChunkRequest { chunk_reference }).await {
+            Ok(response) => serialize_response(response.chunk_view).ok(),
+            Err(err) => {
+                tracing::debug!(
+                    target: "jsonrpc",
+                    chunk_hash = ?header.chunk_hash,
+                    ?err,
+                    "Failed to fetch chunk"
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns the outcomes of the transactions which have reached the status
+    /// requested by their subscriptions.  The statuses of all the followed
+    /// transactions are looked up with a single view client request.
+    async fn tx_status_events(&self) -> HashMap<SubscriptionId, Value> {
+        let requests: Vec<(SubscriptionId, &RpcSubscribeTxStatusRequest)> = self
+            .subscriptions
+            .iter()
+            .filter_map(|(&subscription_id, subscription)| match subscription {
+                Subscription::TxStatus(request) => Some((subscription_id, request)),
+                _ => None,
+            })
+            .collect();
+        if requests.is_empty() {
+            return HashMap::new();
+        }
+        let tx_statuses = TxStatuses(
+            requests
+                .iter()
+                .map(|(_, request)| TxStatus {
+                    tx_hash: request.tx_hash,
+                    signer_account_id: request.sender_account_id.clone(),
+                    fetch_receipt: false,
+                })
+                .collect(),
+        );
+        let statuses = match self.handler.view_client_sender.send_async(tx_statuses).await {
+            Ok(statuses) => statuses,
+            Err(err) => {
+                tracing::debug!(target: "jsonrpc", ?err, "Failed to fetch transaction statuses");
+                return HashMap::new();
+            }
+        };
+        requests
+            .into_iter()
+            .zip(statuses)
+            .filter_map(|((subscription_id, request), status)| match status {
+                Ok(status)
+                    if tx_execution_status_meets_expectations(
+                        &request.wait_until,
+                        &status.status,
+                    ) =>
+                {
+                    let result = serialize_response(RpcTransactionResponse::from(status)).ok()?;
+                    Some((subscription_id, result))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns changes of the given accounts in the block, if there are any.
+    async fn account_changes_event(
+        &self,
+        block: &BlockView,
+        account_ids: Vec<AccountId>,
+    ) -> Option<Value> {
+        let request = RpcStateChangesInBlockByTypeRequest {
+            block_reference: BlockReference::BlockId(BlockId::Hash(block.header.hash)),
+            state_changes_request: StateChangesRequestView::AccountChanges { account_ids },
+        };
+        match self.handler.changes_in_block_by_type(request).await {
+            Ok(response) if !response.changes.is_empty() => serialize_response(response).ok(),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::debug!(
+                    target: "jsonrpc",
+                    block_hash = ?block.header.hash,
+                    ?err,
+                    "Failed to fetch account changes"
+                );
+                None
+            }
+        }
+    }
+
+    async fn send_notification(
+        &mut self,
+        method: &str,
+        params: impl serde::Serialize,
+    ) -> Result<(), Closed> {
+        match serde_json::to_value(params) {
+            Ok(params) => self.send(Message::notification(method.to_owned(), params)).await,
+            Err(err) => {
+                tracing::warn!(target: "jsonrpc", ?err, "Failed to serialize notification");
+                Ok(())
+            }
+        }
+    }
+
+    async fn send(&mut self, message: Message) -> Result<(), Closed> {
+        let text: String = message.into();
+        self.session.text(text).await.map_err(|_| Closed)
+    }
+}
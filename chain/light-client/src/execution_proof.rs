@@ -0,0 +1,86 @@
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::{compute_root_from_path, MerklePath};
+use near_primitives::views::{ExecutionOutcomeWithIdView, LightClientBlockLiteView};
+
+use crate::Error;
+
+/// Response of `EXPERIMENTAL_light_client_proof`. It is the same as
+/// `RpcLightClientExecutionProofResponse` of `near-jsonrpc-primitives`, which isn't used so that
+/// this crate doesn't depend on the client types of the node.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ExecutionProof {
+    pub outcome_proof: ExecutionOutcomeWithIdView,
+    pub outcome_root_proof: MerklePath,
+    pub block_header_lite: LightClientBlockLiteView,
+    pub block_proof: MerklePath,
+}
+
+/// Verifies a response of `EXPERIMENTAL_light_client_proof`: the outcome must be part of the
+/// outcome root of its block, and the block must be part of the chain whose block merkle root is
+/// `block_merkle_root`, i.e. the one of the block passed as `light_client_head`.
+pub fn verify_execution_proof(
+    proof: &ExecutionProof,
+    block_merkle_root: &CryptoHash,
+) -> Result<(), Error> {
+    let outcome_hash = CryptoHash::hash_borsh(proof.outcome_proof.to_hashes());
+    let shard_outcome_root = compute_root_from_path(&proof.outcome_proof.proof, outcome_hash);
+    let block_outcome_root = compute_root_from_path(
+        &proof.outcome_root_proof,
+        CryptoHash::hash_borsh(shard_outcome_root),
+    );
+    if block_outcome_root != proof.block_header_lite.inner_lite.outcome_root {
+        return Err(Error::InvalidOutcomeRoot {
+            computed: block_outcome_root,
+            expected: proof.block_header_lite.inner_lite.outcome_root,
+        });
+    }
+
+    let block_hash = proof.block_header_lite.hash();
+    if block_hash != proof.outcome_proof.block_hash {
+        return Err(Error::InvalidBlockHash {
+            computed: block_hash,
+            expected: proof.outcome_proof.block_hash,
+        });
+    }
+
+    let computed_block_merkle_root = compute_root_from_path(&proof.block_proof, block_hash);
+    if computed_block_merkle_root != *block_merkle_root {
+        return Err(Error::InvalidBlockProof {
+            computed: computed_block_merkle_root,
+            expected: *block_merkle_root,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_execution_proof, ExecutionProof};
+    use crate::Error;
+    use near_primitives::hash::CryptoHash;
+    use std::str::FromStr;
+
+    fn load_proof(data: &[u8]) -> ExecutionProof {
+        let response: serde_json::Value = serde_json::from_slice(data).unwrap();
+        serde_json::from_value(response["result"].clone()).unwrap()
+    }
+
+    #[test]
+    fn test_verify_execution_proof() {
+        let block_merkle_root =
+            CryptoHash::from_str("BWwZdhAhjAgKxZ5ycqn1CvXads5DjPMfj4kRdc1rWit8").unwrap();
+        let proof = load_proof(include_bytes!("../res/proof_example.json"));
+        verify_execution_proof(&proof, &block_merkle_root).unwrap();
+        assert!(matches!(
+            verify_execution_proof(&proof, &CryptoHash::default()),
+            Err(Error::InvalidBlockProof { .. })
+        ));
+
+        // Proof with a wrong outcome (as user specified wrong shard).
+        let proof = load_proof(include_bytes!("../res/invalid_proof.json"));
+        assert!(matches!(
+            verify_execution_proof(&proof, &block_merkle_root),
+            Err(Error::InvalidOutcomeRoot { .. })
+        ));
+    }
+}
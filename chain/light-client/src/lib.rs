@@ -0,0 +1,325 @@
+#![doc = include_str!("../README.md")]
+
+use near_primitives::block_header::{Approval, ApprovalInner};
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::combine_hash;
+use near_primitives::types::{AccountId, Balance, BlockHeight};
+use near_primitives::views::validator_stake_view::ValidatorStakeView;
+use near_primitives::views::{LightClientBlockLiteView, LightClientBlockView};
+
+pub use execution_proof::{verify_execution_proof, ExecutionProof};
+pub use state_proof::{verify_state_roots, verify_view_state, StateProof};
+
+mod execution_proof;
+mod state_proof;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("block at height {height} is not above the head at height {head_height}")]
+    BlockNotAboveHead { height: BlockHeight, head_height: BlockHeight },
+    #[error("epoch {0} of the block is neither the epoch of the head nor the next one")]
+    UnknownEpoch(CryptoHash),
+    #[error("block doesn't have the block producers of its next epoch")]
+    MissingNextBlockProducers,
+    #[error("approval of {0} has an invalid signature")]
+    InvalidApprovalSignature(AccountId),
+    #[error(
+        "approved stake {approved_stake} is not more than 2/3 of the total stake {total_stake}"
+    )]
+    NotEnoughApprovals { approved_stake: Balance, total_stake: Balance },
+    #[error("next block producers don't match the next_bp_hash of the block")]
+    InvalidNextBlockProducers,
+    #[error(
+        "computed outcome root {computed} doesn't match the outcome root {expected} of the block"
+    )]
+    InvalidOutcomeRoot { computed: CryptoHash, expected: CryptoHash },
+    #[error("header hash {computed} doesn't match the block hash {expected} of the outcome")]
+    InvalidBlockHash { computed: CryptoHash, expected: CryptoHash },
+    #[error("computed block merkle root {computed} doesn't match the trusted one {expected}")]
+    InvalidBlockProof { computed: CryptoHash, expected: CryptoHash },
+    #[error("state proof has a node which can't be decoded: {0}")]
+    InvalidStateProofNode(String),
+    #[error("state proof doesn't prove the value of key {0:?}")]
+    InvalidStateValue(Vec<u8>),
+    #[error("computed state root {computed} doesn't match the state root {expected} of the block")]
+    InvalidStateRoots { computed: CryptoHash, expected: CryptoHash },
+}
+
+/// Light client which follows the chain from a trusted head, as described in
+/// https://nomicon.io/ChainSpec/LightClient.
+///
+/// It keeps the block producers of the epoch of the head and of the next epoch, which are needed
+/// to verify the approvals of the blocks that can follow the head.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct LightClient {
+    head: LightClientBlockLiteView,
+    block_producers: Vec<ValidatorStakeView>,
+    next_block_producers: Vec<ValidatorStakeView>,
+}
+
+impl LightClient {
+    /// Creates the light client from a trusted block and the ordered list of the block producers
+    /// of its epoch, e.g. as returned by `EXPERIMENTAL_validators_ordered`. Neither of them is
+    /// verified, the block must have `next_bps` though.
+    pub fn new(
+        head: LightClientBlockView,
+        block_producers: Vec<ValidatorStakeView>,
+    ) -> Result<Self, Error> {
+        let next_block_producers = head.next_bps.ok_or(Error::MissingNextBlockProducers)?;
+        let head = LightClientBlockLiteView {
+            prev_block_hash: head.prev_block_hash,
+            inner_rest_hash: head.inner_rest_hash,
+            inner_lite: head.inner_lite,
+        };
+        Ok(Self { head, block_producers, next_block_producers })
+    }
+
+    pub fn head(&self) -> &LightClientBlockLiteView {
+        &self.head
+    }
+
+    pub fn head_hash(&self) -> CryptoHash {
+        self.head.hash()
+    }
+
+    /// Verifies that the block is approved by more than 2/3 of the stake of the block producers of
+    /// its epoch and makes it the new head. The block must be above the head and belong either to
+    /// the epoch of the head or to the next one.
+    pub fn validate_and_update_head(&mut self, block: &LightClientBlockView) -> Result<(), Error> {
+        let head_height = self.head.inner_lite.height;
+        if block.inner_lite.height <= head_height {
+            return Err(Error::BlockNotAboveHead { height: block.inner_lite.height, head_height });
+        }
+        let epoch_id = block.inner_lite.epoch_id;
+        let block_producers = if epoch_id == self.head.inner_lite.epoch_id {
+            &self.block_producers
+        } else if epoch_id == self.head.inner_lite.next_epoch_id {
+            if block.next_bps.is_none() {
+                return Err(Error::MissingNextBlockProducers);
+            }
+            &self.next_block_producers
+        } else {
+            return Err(Error::UnknownEpoch(epoch_id));
+        };
+
+        let lite = LightClientBlockLiteView {
+            prev_block_hash: block.prev_block_hash,
+            inner_rest_hash: block.inner_rest_hash,
+            inner_lite: block.inner_lite.clone(),
+        };
+        // Block producers approve the block after the next one with an endorsement of the next
+        // block, so it's the hash of the next block that is signed.
+        let next_block_hash = combine_hash(&block.next_block_inner_hash, &lite.hash());
+        let approval_data = Approval::get_data_for_sig(
+            &ApprovalInner::Endorsement(next_block_hash),
+            block.inner_lite.height + 2,
+        );
+        verify_approvals(block_producers, &block.approvals_after_next, &approval_data)?;
+
+        if let Some(next_bps) = &block.next_bps {
+            if next_bp_hash(next_bps) != block.inner_lite.next_bp_hash {
+                return Err(Error::InvalidNextBlockProducers);
+            }
+        }
+
+        if epoch_id != self.head.inner_lite.epoch_id {
+            self.block_producers = std::mem::take(&mut self.next_block_producers);
+        }
+        if let Some(next_bps) = &block.next_bps {
+            self.next_block_producers = next_bps.clone();
+        }
+        self.head = lite;
+        Ok(())
+    }
+
+    /// Verifies a response of `EXPERIMENTAL_light_client_proof` requested with the head of this
+    /// light client as `light_client_head`.
+    pub fn verify_execution_proof(&self, proof: &ExecutionProof) -> Result<(), Error> {
+        verify_execution_proof(proof, &self.head.inner_lite.block_merkle_root)
+    }
+}
+
+/// Checks the signatures of the approvals, which are ordered the same way as the block producers,
+/// and that the approved stake is more than 2/3 of the total stake.
+fn verify_approvals(
+    block_producers: &[ValidatorStakeView],
+    approvals: &[Option<Box<near_crypto::Signature>>],
+    approval_data: &[u8],
+) -> Result<(), Error> {
+    let mut total_stake: Balance = 0;
+    let mut approved_stake: Balance = 0;
+    for (i, block_producer) in block_producers.iter().enumerate() {
+        let ValidatorStakeView::V1(block_producer) = block_producer;
+        total_stake += block_producer.stake;
+        let Some(Some(signature)) = approvals.get(i) else {
+            continue;
+        };
+        if !signature.verify(approval_data, &block_producer.public_key) {
+            return Err(Error::InvalidApprovalSignature(block_producer.account_id.clone()));
+        }
+        approved_stake += block_producer.stake;
+    }
+    if approved_stake <= total_stake * 2 / 3 {
+        return Err(Error::NotEnoughApprovals { approved_stake, total_stake });
+    }
+    Ok(())
+}
+
+/// Hash of the block producers which is stored as `next_bp_hash` in the block headers.
+fn next_bp_hash(block_producers: &[ValidatorStakeView]) -> CryptoHash {
+    CryptoHash::hash_borsh_iter(
+        block_producers.iter().cloned().map(ValidatorStakeView::into_validator_stake),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_bp_hash, Error, LightClient};
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::block_header::{Approval, ApprovalInner};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::merkle::combine_hash;
+    use near_primitives::types::BlockHeight;
+    use near_primitives::views::validator_stake_view::ValidatorStakeView;
+    use near_primitives::views::{
+        BlockHeaderInnerLiteView, LightClientBlockLiteView, LightClientBlockView,
+        ValidatorStakeViewV1,
+    };
+
+    struct Epoch {
+        id: CryptoHash,
+        keys: Vec<SecretKey>,
+        block_producers: Vec<ValidatorStakeView>,
+    }
+
+    impl Epoch {
+        fn new(name: &str, stakes: &[u128]) -> Self {
+            let keys: Vec<_> = (0..stakes.len())
+                .map(|i| SecretKey::from_seed(KeyType::ED25519, &format!("{name}{i}")))
+                .collect();
+            let block_producers = keys
+                .iter()
+                .zip(stakes)
+                .enumerate()
+                .map(|(i, (key, stake))| {
+                    ValidatorStakeView::V1(ValidatorStakeViewV1 {
+                        account_id: format!("{name}{i}.near").parse().unwrap(),
+                        public_key: key.public_key(),
+                        stake: *stake,
+                    })
+                })
+                .collect();
+            Self { id: CryptoHash::hash_bytes(name.as_bytes()), keys, block_producers }
+        }
+    }
+
+    /// Creates a block of the `epoch` approved by the block producers with the given indices.
+    fn make_block(
+        height: BlockHeight,
+        epoch: &Epoch,
+        next_epoch: &Epoch,
+        with_next_bps: bool,
+        approvers: &[usize],
+    ) -> LightClientBlockView {
+        let inner_lite = BlockHeaderInnerLiteView {
+            height,
+            epoch_id: epoch.id,
+            next_epoch_id: next_epoch.id,
+            prev_state_root: CryptoHash::default(),
+            outcome_root: CryptoHash::default(),
+            timestamp: height,
+            timestamp_nanosec: height,
+            next_bp_hash: next_bp_hash(&next_epoch.block_producers),
+            block_merkle_root: CryptoHash::default(),
+        };
+        let lite = LightClientBlockLiteView {
+            prev_block_hash: CryptoHash::hash_borsh(height - 1),
+            inner_rest_hash: CryptoHash::hash_borsh(height),
+            inner_lite: inner_lite.clone(),
+        };
+        let next_block_inner_hash = CryptoHash::hash_borsh(height + 1);
+        let next_block_hash = combine_hash(&next_block_inner_hash, &lite.hash());
+        let data =
+            Approval::get_data_for_sig(&ApprovalInner::Endorsement(next_block_hash), height + 2);
+        let approvals_after_next = (0..epoch.keys.len())
+            .map(|i| approvers.contains(&i).then(|| Box::new(epoch.keys[i].sign(&data))))
+            .collect();
+        LightClientBlockView {
+            prev_block_hash: lite.prev_block_hash,
+            next_block_inner_hash,
+            inner_lite,
+            inner_rest_hash: lite.inner_rest_hash,
+            next_bps: with_next_bps.then(|| next_epoch.block_producers.clone()),
+            approvals_after_next,
+        }
+    }
+
+    #[test]
+    fn test_validate_and_update_head() {
+        let epoch1 = Epoch::new("one", &[100, 100, 100, 100]);
+        let epoch2 = Epoch::new("two", &[300, 100, 100]);
+        let epoch3 = Epoch::new("three", &[100]);
+        let head = make_block(10, &epoch1, &epoch2, true, &[0, 1, 2]);
+        let mut client = LightClient::new(head, epoch1.block_producers.clone()).unwrap();
+
+        let block = make_block(15, &epoch1, &epoch2, true, &[0, 1, 3]);
+        client.validate_and_update_head(&block).unwrap();
+        assert_eq!(client.head().inner_lite.height, 15);
+
+        let block = make_block(15, &epoch1, &epoch2, true, &[0, 1, 2, 3]);
+        assert_eq!(
+            client.validate_and_update_head(&block),
+            Err(Error::BlockNotAboveHead { height: 15, head_height: 15 })
+        );
+
+        // The first block of the next epoch must carry the block producers of the epoch after.
+        let block = make_block(20, &epoch2, &epoch3, false, &[0, 1]);
+        assert_eq!(client.validate_and_update_head(&block), Err(Error::MissingNextBlockProducers));
+        let block = make_block(20, &epoch2, &epoch3, true, &[0, 1]);
+        client.validate_and_update_head(&block).unwrap();
+        assert_eq!(client.head_hash(), block_hash(&block));
+
+        // The epoch of the previous head is now too old.
+        let block = make_block(21, &epoch1, &epoch2, true, &[0, 1, 2, 3]);
+        assert_eq!(client.validate_and_update_head(&block), Err(Error::UnknownEpoch(epoch1.id)));
+        let block = make_block(21, &epoch2, &epoch3, true, &[0, 2]);
+        client.validate_and_update_head(&block).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_approvals() {
+        let epoch1 = Epoch::new("one", &[100, 100, 100]);
+        let epoch2 = Epoch::new("two", &[100]);
+        let head = make_block(10, &epoch1, &epoch2, true, &[0, 1, 2]);
+        let mut client = LightClient::new(head, epoch1.block_producers.clone()).unwrap();
+
+        // Exactly 2/3 of the stake is not enough.
+        let block = make_block(11, &epoch1, &epoch2, true, &[0, 2]);
+        assert_eq!(
+            client.validate_and_update_head(&block),
+            Err(Error::NotEnoughApprovals { approved_stake: 200, total_stake: 300 })
+        );
+
+        let mut block = make_block(11, &epoch1, &epoch2, true, &[0, 1, 2]);
+        block.approvals_after_next.swap(0, 1);
+        assert_eq!(
+            client.validate_and_update_head(&block),
+            Err(Error::InvalidApprovalSignature("one0.near".parse().unwrap()))
+        );
+
+        let mut block = make_block(11, &epoch1, &epoch2, true, &[0, 1, 2]);
+        block.next_bps = Some(epoch1.block_producers.clone());
+        assert_eq!(client.validate_and_update_head(&block), Err(Error::InvalidNextBlockProducers));
+
+        assert_eq!(client.head().inner_lite.height, 10);
+    }
+
+    fn block_hash(block: &LightClientBlockView) -> CryptoHash {
+        LightClientBlockLiteView {
+            prev_block_hash: block.prev_block_hash,
+            inner_rest_hash: block.inner_rest_hash,
+            inner_lite: block.inner_lite.clone(),
+        }
+        .hash()
+    }
+}
@@ -0,0 +1,311 @@
+use borsh::BorshDeserialize;
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::merklize;
+use near_primitives::state::ValueRef;
+use near_primitives::trie_key::trie_key_parsers;
+use near_primitives::types::{AccountId, StateRoot};
+use near_primitives::views::{LightClientBlockLiteView, ViewStateResult};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::Error;
+
+/// Trie node as it is stored, followed by the memory usage of its subtree. Mirrors
+/// `RawTrieNodeWithSize` of `near-store`, which this crate doesn't depend on.
+#[derive(BorshDeserialize)]
+enum RawTrieNode {
+    Leaf(Vec<u8>, ValueRef),
+    BranchNoValue(Children),
+    BranchWithValue(ValueRef, Children),
+    Extension(Vec<u8>, CryptoHash),
+}
+
+/// Children of a branch node, serialized as a bitmap of the present children followed by their
+/// hashes.
+struct Children([Option<CryptoHash>; 16]);
+
+impl BorshDeserialize for Children {
+    fn deserialize_reader<R: std::io::Read>(rd: &mut R) -> std::io::Result<Self> {
+        let mut bitmap = u16::deserialize_reader(rd)?;
+        let mut children = [None; 16];
+        while bitmap != 0 {
+            let idx = bitmap.trailing_zeros() as usize;
+            bitmap &= bitmap - 1;
+            children[idx] = Some(CryptoHash::deserialize_reader(rd)?);
+        }
+        Ok(Self(children))
+    }
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|byte| [byte >> 4, byte & 0xf]).collect()
+}
+
+/// Decodes the hex-prefix encoded key of a leaf or an extension node into nibbles.
+fn decode_node_key(encoded: &[u8]) -> Option<Vec<u8>> {
+    let first = *encoded.first()?;
+    let offset = if first & 0x10 == 0x10 { 1 } else { 2 };
+    Some(to_nibbles(encoded).split_off(offset))
+}
+
+/// Trie nodes returned by `view_state` with `include_proof`, which prove the values of the keys
+/// they lead to from the state root.
+pub struct StateProof {
+    nodes: HashMap<CryptoHash, RawTrieNode>,
+}
+
+impl StateProof {
+    pub fn new(proof: &[Arc<[u8]>]) -> Result<Self, Error> {
+        let nodes = proof
+            .iter()
+            .map(|bytes| {
+                let (node, _memory_usage) = <(RawTrieNode, u64)>::try_from_slice(bytes)
+                    .map_err(|err| Error::InvalidStateProofNode(err.to_string()))?;
+                Ok((CryptoHash::hash_bytes(bytes), node))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { nodes })
+    }
+
+    /// Checks that the contract data of the account under `key` is `expected` in the state with the
+    /// given root, where `None` means that there is no such key.
+    ///
+    /// Returns false if the proof doesn't have all the nodes on the path to the key.
+    pub fn verify(
+        &self,
+        state_root: &StateRoot,
+        account_id: &AccountId,
+        key: &[u8],
+        expected: Option<&[u8]>,
+    ) -> bool {
+        let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, key);
+        let nibbles = to_nibbles(&query);
+        let mut key = &nibbles[..];
+        let expected = expected.map(ValueRef::new);
+
+        let mut node_hash = state_root;
+        while let Some(node) = self.nodes.get(node_hash) {
+            let (value, children) = match node {
+                RawTrieNode::Leaf(node_key, value) => {
+                    return match decode_node_key(node_key) {
+                        Some(node_key) if node_key == key => expected == Some(*value),
+                        Some(_) => expected.is_none(),
+                        None => false,
+                    };
+                }
+                RawTrieNode::Extension(node_key, child_hash) => {
+                    let Some(node_key) = decode_node_key(node_key) else {
+                        return false;
+                    };
+                    let Some(rest) = key.strip_prefix(node_key.as_slice()) else {
+                        return expected.is_none();
+                    };
+                    key = rest;
+                    node_hash = child_hash;
+                    continue;
+                }
+                RawTrieNode::BranchNoValue(children) => (None, children),
+                RawTrieNode::BranchWithValue(value, children) => (Some(*value), children),
+            };
+            let Some((&nibble, rest)) = key.split_first() else {
+                return expected == value;
+            };
+            let Some(child_hash) = &children.0[usize::from(nibble)] else {
+                return expected.is_none();
+            };
+            key = rest;
+            node_hash = child_hash;
+        }
+        false
+    }
+}
+
+/// Checks that all the values of a `view_state` result requested with `include_proof` are in the
+/// state of the account with the given root.
+///
+/// The proof doesn't show that no values are missing from the result.
+pub fn verify_view_state(
+    state_root: &StateRoot,
+    account_id: &AccountId,
+    result: &ViewStateResult,
+) -> Result<(), Error> {
+    let proof = StateProof::new(&result.proof)?;
+    for item in &result.values {
+        if !proof.verify(state_root, account_id, &item.key, Some(&item.value[..])) {
+            return Err(Error::InvalidStateValue(item.key.to_vec()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the state roots are the `prev_state_root`s of the chunks of the block, in the order
+/// of the shards. The state root of a shard is the root of its state after the previous block,
+/// which is what `view_state` returns for the previous block unless the chunk is missing.
+pub fn verify_state_roots(
+    block: &LightClientBlockLiteView,
+    state_roots: &[StateRoot],
+) -> Result<(), Error> {
+    let (computed, _) = merklize(state_roots);
+    if computed != block.inner_lite.prev_state_root {
+        return Err(Error::InvalidStateRoots {
+            computed,
+            expected: block.inner_lite.prev_state_root,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_view_state, StateProof};
+    use crate::Error;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::state::ValueRef;
+    use near_primitives::trie_key::trie_key_parsers;
+    use near_primitives::types::AccountId;
+    use near_primitives::views::{StateItem, ViewStateResult};
+    use std::sync::Arc;
+
+    /// Hex-prefix encoding of the key of a leaf or an extension node.
+    fn encode_key(key_nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+        let flag = if is_leaf { 0x20 } else { 0x00 };
+        let mut encoded = if key_nibbles.len() % 2 == 1 {
+            vec![flag | 0x10 | key_nibbles[0]]
+        } else {
+            vec![flag]
+        };
+        let rest = &key_nibbles[key_nibbles.len() % 2..];
+        encoded.extend(rest.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+        encoded
+    }
+
+    fn leaf(key_nibbles: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut node = vec![0];
+        node.extend(borsh::to_vec(&encode_key(key_nibbles, true)).unwrap());
+        node.extend(borsh::to_vec(&ValueRef::new(value)).unwrap());
+        node.extend(42u64.to_le_bytes());
+        node
+    }
+
+    fn extension(key_nibbles: &[u8], child: &[u8]) -> Vec<u8> {
+        let mut node = vec![3];
+        node.extend(borsh::to_vec(&encode_key(key_nibbles, false)).unwrap());
+        node.extend(CryptoHash::hash_bytes(child).as_bytes());
+        node.extend(42u64.to_le_bytes());
+        node
+    }
+
+    fn branch(children: &[(u8, &[u8])]) -> Vec<u8> {
+        branch_node(vec![1], children)
+    }
+
+    fn branch_with_value(value: &[u8], children: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut node = vec![2];
+        node.extend(borsh::to_vec(&ValueRef::new(value)).unwrap());
+        branch_node(node, children)
+    }
+
+    fn branch_node(mut node: Vec<u8>, children: &[(u8, &[u8])]) -> Vec<u8> {
+        let bitmap = children.iter().fold(0u16, |bitmap, &(nibble, _)| bitmap | 1 << nibble);
+        node.extend(bitmap.to_le_bytes());
+        for (_, child) in children {
+            node.extend(CryptoHash::hash_bytes(child).as_bytes());
+        }
+        node.extend(42u64.to_le_bytes());
+        node
+    }
+
+    #[test]
+    fn test_state_proof() {
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let bob: AccountId = "bob.near".parse().unwrap();
+        let nibbles = |account_id: &AccountId, key: &[u8]| -> Vec<u8> {
+            trie_key_parsers::get_raw_prefix_for_contract_data(account_id, key)
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0xf])
+                .collect()
+        };
+        // Both keys start with the contract data column, and diverge on the second nibble of the
+        // account id: 'a' = 0x61 and 'b' = 0x62.
+        let alice_key = nibbles(&alice, b"key");
+        let bob_key = nibbles(&bob, b"key");
+        assert_eq!(alice_key[..3], bob_key[..3]);
+        assert_ne!(alice_key[3], bob_key[3]);
+
+        let alice_leaf = leaf(&alice_key[4..], b"alice value");
+        let bob_leaf = leaf(&bob_key[4..], b"bob value");
+        let branch =
+            branch(&[(alice_key[3], alice_leaf.as_slice()), (bob_key[3], bob_leaf.as_slice())]);
+        // The root is an extension node with the common prefix of three nibbles.
+        let root_node = extension(&alice_key[..3], &branch);
+        let state_root = CryptoHash::hash_bytes(&root_node);
+
+        let nodes: Vec<Arc<[u8]>> =
+            vec![root_node.into(), branch.into(), alice_leaf.into(), bob_leaf.into()];
+        let proof = StateProof::new(&nodes).unwrap();
+        assert!(proof.verify(&state_root, &alice, b"key", Some(&b"alice value"[..])));
+        assert!(proof.verify(&state_root, &bob, b"key", Some(&b"bob value"[..])));
+        assert!(!proof.verify(&state_root, &alice, b"key", Some(&b"bob value"[..])));
+        assert!(!proof.verify(&state_root, &alice, b"key", None));
+        assert!(proof.verify(&state_root, &alice, b"other key", None));
+        assert!(!proof.verify(&state_root, &alice, b"other key", Some(&b"alice value"[..])));
+        assert!(!proof.verify(&CryptoHash::default(), &alice, b"key", Some(&b"alice value"[..])));
+
+        let mut result = ViewStateResult {
+            values: vec![StateItem {
+                key: b"key".to_vec().into(),
+                value: b"alice value".to_vec().into(),
+            }],
+            proof: nodes[..3].to_vec(),
+        };
+        verify_view_state(&state_root, &alice, &result).unwrap();
+        result.values[0].value = b"bogus".to_vec().into();
+        assert_eq!(
+            verify_view_state(&state_root, &alice, &result),
+            Err(Error::InvalidStateValue(b"key".to_vec()))
+        );
+
+        let invalid_node: Arc<[u8]> = vec![7, 1, 2].into();
+        assert!(matches!(StateProof::new(&[invalid_node]), Err(Error::InvalidStateProofNode(_))));
+    }
+    /// A key ending at a branch node has the value of the branch, and keys whose nodes are missing
+    /// from the proof can't be verified either way.
+    #[test]
+    fn test_state_proof_branch_with_value() {
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let nibbles = |key: &[u8]| -> Vec<u8> {
+            trie_key_parsers::get_raw_prefix_for_contract_data(&alice, key)
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0xf])
+                .collect()
+        };
+        // The key "k1" continues the key "k" with the nibbles of '1' = 0x31.
+        let short_key = nibbles(b"k");
+        let long_key = nibbles(b"k1");
+        assert_eq!(long_key[..short_key.len()], short_key[..]);
+        let (branch_nibble, leaf_key) = long_key[short_key.len()..].split_first().unwrap();
+
+        let leaf = leaf(leaf_key, b"long value");
+        let branch = branch_with_value(b"short value", &[(*branch_nibble, leaf.as_slice())]);
+        let root_node = extension(&short_key, &branch);
+        let state_root = CryptoHash::hash_bytes(&root_node);
+
+        let nodes: Vec<Arc<[u8]>> = vec![root_node.into(), branch.into(), leaf.into()];
+        let proof = StateProof::new(&nodes).unwrap();
+        assert!(proof.verify(&state_root, &alice, b"k", Some(&b"short value"[..])));
+        assert!(!proof.verify(&state_root, &alice, b"k", Some(&b"long value"[..])));
+        assert!(!proof.verify(&state_root, &alice, b"k", None));
+        assert!(proof.verify(&state_root, &alice, b"k1", Some(&b"long value"[..])));
+        // '2' = 0x32 leads to the leaf of "k1", 'x' = 0x78 to a missing child of the branch.
+        assert!(proof.verify(&state_root, &alice, b"k2", None));
+        assert!(proof.verify(&state_root, &alice, b"kx", None));
+        assert!(!proof.verify(&state_root, &alice, b"kx", Some(&b"short value"[..])));
+        // A key sharing no prefix with the extension node is absent as well.
+        assert!(proof.verify(&state_root, &"bob.near".parse().unwrap(), b"k", None));
+
+        let proof = StateProof::new(&nodes[..2]).unwrap();
+        assert!(proof.verify(&state_root, &alice, b"k", Some(&b"short value"[..])));
+        assert!(!proof.verify(&state_root, &alice, b"k1", Some(&b"long value"[..])));
+        assert!(!proof.verify(&state_root, &alice, b"k1", None));
+    }
+}
@@ -107,6 +107,9 @@ pub enum ProcessTxResponse {
     ValidTx,
     /// Invalid transaction inserted into mempool as response to Transaction.
     InvalidTx(InvalidTxError),
+    /// A transaction with the same signer, access key and nonce paying at least the same priority
+    /// fee is already in the mempool, so the transaction is dropped.
+    Underpriced,
     /// The request is routed to other shards
     RequestRouted,
     /// The node being queried does not track the shard needed and therefore cannot provide userful
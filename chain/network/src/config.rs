@@ -103,6 +103,82 @@ pub struct Tier1 {
     pub enable_outbound: bool,
 }
 
+/// Whether peer connections should be encrypted and authenticated.
+///
+/// Encryption is negotiated in the Handshake, so that nodes with different settings
+/// (and nodes which don't support encryption at all) can still connect to each other,
+/// unless `Required` is set. See `encrypted_transport` in network_protocol/network.proto.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptedTransport {
+    /// Connections are never encrypted.
+    #[default]
+    Disabled,
+    /// Connections are encrypted iff the peer supports it.
+    Preferred,
+    /// Connections with peers which don't support encryption are rejected.
+    /// Note that with `Preferred` an active attacker can make the connection plaintext
+    /// by stripping the encryption flag from the Handshake, so only `Required`
+    /// protects against such downgrades.
+    Required,
+}
+
+/// Zstd compression of large messages: blocks, state responses, partial state witnesses
+/// and snapshot hosts.
+///
+/// Compression is negotiated in the Handshake and used only if both peers enable it.
+/// See `CompressedPeerMessage` in network_protocol/network.proto.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct MessageCompressionConfig {
+    /// Whether to compress messages sent to the peers which support compression.
+    /// Compressed messages are accepted only from peers for which it is enabled.
+    pub enabled: bool,
+    /// Messages smaller than this (before compression) are sent uncompressed.
+    pub threshold_bytes: usize,
+    /// Zstd compression level.
+    pub level: i32,
+}
+
+impl Default for MessageCompressionConfig {
+    fn default() -> Self {
+        Self { enabled: false, threshold_bytes: 64 * 1024, level: 3 }
+    }
+}
+
+/// Configuration of the reputation scores of peers, see `peer_manager::peer_score`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PeerScoreConfig {
+    /// Whether the scores decide which peers get banned, which peers we connect to or
+    /// disconnect from, and which peers get sync requests. If disabled, the scores are not
+    /// tracked and every protocol violation bans the peer.
+    pub enabled: bool,
+    /// Time after which a score decays to half of its value.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub half_life: time::Duration,
+    /// Peers whose score drops below this value get banned.
+    pub ban_threshold: f64,
+    /// Peers whose score is below this value are not sent sync requests,
+    /// unless there are no other peers to choose from.
+    pub deprioritize_threshold: f64,
+    /// Responses to block requests slower than this lower the score.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub slow_response_threshold: time::Duration,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            half_life: time::Duration::hours(1),
+            ban_threshold: -50.,
+            deprioritize_threshold: -10.,
+            slow_response_threshold: time::Duration::seconds(2),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SocketOptions {
     pub recv_buffer_size: Option<u32>,
@@ -195,6 +271,15 @@ pub struct NetworkConfig {
 
     /// Configuration of rate limits for incoming messages.
     pub received_messages_rate_limits: messages_limits::Config,
+    /// Configuration of rate limits for incoming compressed messages, checked before they are
+    /// decompressed in addition to `received_messages_rate_limits`.
+    pub received_compressed_messages_rate_limits: messages_limits::Config,
+    /// Whether to encrypt the peer connections.
+    pub encrypted_transport: EncryptedTransport,
+    /// Compression of large messages.
+    pub message_compression: MessageCompressionConfig,
+    /// Reputation scores of peers.
+    pub peer_score: PeerScoreConfig,
 
     #[cfg(test)]
     pub(crate) event_sink:
@@ -244,6 +329,9 @@ impl NetworkConfig {
         if let Some(rate_limits) = overrides.received_messages_rate_limits {
             self.received_messages_rate_limits.apply_overrides(rate_limits);
         }
+        if let Some(rate_limits) = overrides.received_compressed_messages_rate_limits {
+            self.received_compressed_messages_rate_limits.apply_overrides(rate_limits);
+        }
     }
 
     pub fn new(
@@ -380,6 +468,10 @@ impl NetworkConfig {
             },
             // Use a preset to configure rate limits and override entries with user defined values later.
             received_messages_rate_limits: messages_limits::Config::standard_preset(),
+            received_compressed_messages_rate_limits: messages_limits::Config::compressed_preset(),
+            encrypted_transport: cfg.experimental.encrypted_transport,
+            message_compression: cfg.experimental.message_compression.clone(),
+            peer_score: cfg.experimental.peer_score.clone(),
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -458,6 +550,10 @@ impl NetworkConfig {
             }),
             skip_tombstones: None,
             received_messages_rate_limits: messages_limits::Config::default(),
+            received_compressed_messages_rate_limits: messages_limits::Config::default(),
+            encrypted_transport: EncryptedTransport::Disabled,
+            message_compression: MessageCompressionConfig::default(),
+            peer_score: PeerScoreConfig::default(),
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -511,9 +607,24 @@ impl NetworkConfig {
             .validate()
             .context("routing_table_update_rate_limit")?;
 
+        if !self.peer_score.half_life.is_positive() {
+            anyhow::bail!("peer_score.half_life({}) must be positive", self.peer_score.half_life);
+        }
+
+        if !(self.peer_score.ban_threshold < self.peer_score.deprioritize_threshold) {
+            anyhow::bail!(
+                "peer_score.ban_threshold({}) must be lower than peer_score.deprioritize_threshold({})",
+                self.peer_score.ban_threshold,
+                self.peer_score.deprioritize_threshold
+            );
+        }
+
         if let Err(err) = self.received_messages_rate_limits.validate() {
             anyhow::bail!("One or more invalid rate limits: {err:?}");
         }
+        if let Err(err) = self.received_compressed_messages_rate_limits.validate() {
+            anyhow::bail!("One or more invalid rate limits of compressed messages: {err:?}");
+        }
 
         Ok(VerifiedConfig { node_id: self.node_id(), inner: self })
     }
@@ -1,3 +1,4 @@
+use crate::config::{EncryptedTransport, MessageCompressionConfig, PeerScoreConfig};
 use crate::network_protocol::PeerAddr;
 use crate::rate_limits::messages_limits;
 use crate::stun;
@@ -269,6 +270,18 @@ pub struct ExperimentalConfig {
     /// Fields set here will override the NetworkConfig fields.
     #[serde(default)]
     pub network_config_overrides: NetworkConfigOverrides,
+
+    /// See `near_network::config::EncryptedTransport`.
+    #[serde(default)]
+    pub encrypted_transport: EncryptedTransport,
+
+    /// See `near_network::config::MessageCompressionConfig`.
+    #[serde(default)]
+    pub message_compression: MessageCompressionConfig,
+
+    /// See `near_network::config::PeerScoreConfig`.
+    #[serde(default)]
+    pub peer_score: PeerScoreConfig,
 }
 
 /// Overrides values from NetworkConfig.
@@ -287,6 +300,7 @@ pub struct NetworkConfigOverrides {
     pub routing_table_update_rate_limit_burst: Option<u64>,
     pub routing_table_update_rate_limit_qps: Option<f64>,
     pub received_messages_rate_limits: Option<messages_limits::OverrideConfig>,
+    pub received_compressed_messages_rate_limits: Option<messages_limits::OverrideConfig>,
 }
 
 impl Default for ExperimentalConfig {
@@ -300,6 +314,9 @@ impl Default for ExperimentalConfig {
             tier1_connect_interval: default_tier1_connect_interval(),
             tier1_new_connections_per_attempt: default_tier1_new_connections_per_attempt(),
             network_config_overrides: Default::default(),
+            encrypted_transport: Default::default(),
+            message_compression: Default::default(),
+            peer_score: Default::default(),
         }
     }
 }
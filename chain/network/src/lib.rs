@@ -2,6 +2,7 @@
 
 pub use crate::peer_manager::peer_manager_actor::{Event, PeerManagerActor};
 pub use crate::rate_limits::messages_limits::OverrideConfig as MessagesLimitsOverrideConfig;
+pub use crate::rate_limits::token_bucket::{TokenBucket, TokenBucketError};
 
 mod accounts_data;
 mod announce_accounts;
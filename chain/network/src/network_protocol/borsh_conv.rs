@@ -14,6 +14,11 @@ impl From<&net::Handshake> for mem::Handshake {
             sender_chain_info: x.sender_chain_info.clone(),
             partial_edge_info: x.partial_edge_info.clone(),
             owned_account: None,
+            // Encrypted connections are supported only with proto encoding.
+            encrypted_transport: false,
+            // Compressed messages are supported only with proto encoding.
+            message_compression: false,
+            encrypted_transport_signature: None,
         }
     }
 }
@@ -0,0 +1,186 @@
+//! Zstd compression of large messages.
+//!
+//! Compression is negotiated in the Handshake (see `message_compression` in network.proto)
+//! and is supported only with proto encoding. A compressed message declares the type and
+//! the size of the message it contains, so that the receiver can apply the rate limits and
+//! the message size limits before decompressing it.
+use super::{proto, Encoding, ParsePeerMessageError, PeerMessage, RoutedMessageBody};
+use crate::config::MessageCompressionConfig;
+use crate::network_protocol::proto::peer_message::Message_type as ProtoMT;
+use crate::rate_limits::messages_limits::RateLimitedPeerMessageKey;
+use bytesize::MIB;
+use near_primitives::stateless_validation::partial_witness::MAX_COMPRESSED_STATE_WITNESS_SIZE;
+use protobuf::Message as _;
+
+/// Maximal ratio of the declared size of a compressed message to the size of its compressed
+/// data. Real messages compress a few times at most, so a message declaring a much larger size
+/// is most likely a decompression bomb and is rejected without decompressing it.
+pub(crate) const MAX_COMPRESSION_RATIO: usize = 100;
+
+/// Maximal size of a compressed message of the given type after decompression. It is much
+/// lower than the maximal network message size, which bounds the memory a peer can make the
+/// node allocate for every compressed message it sends.
+pub(crate) fn max_decompressed_size(message_type: RateLimitedPeerMessageKey) -> usize {
+    use RateLimitedPeerMessageKey::*;
+    match message_type {
+        Block => 16 * MIB as usize,
+        // State headers and parts.
+        VersionedStateResponse => 64 * MIB as usize,
+        // A part is at most the whole encoded witness, with some room for the rest of the message.
+        PartialEncodedStateWitness | PartialEncodedStateWitnessForward => {
+            (MAX_COMPRESSED_STATE_WITNESS_SIZE.as_u64() + MIB) as usize
+        }
+        SyncSnapshotHosts => 4 * MIB as usize,
+        // Other message types are never sent compressed.
+        _ => 0,
+    }
+}
+
+/// Returns the type of the message if it may be sent compressed.
+pub(crate) fn compressible_type(msg: &PeerMessage) -> Option<RateLimitedPeerMessageKey> {
+    use RateLimitedPeerMessageKey::*;
+    match msg {
+        PeerMessage::Block(_) => Some(Block),
+        PeerMessage::VersionedStateResponse(_) => Some(VersionedStateResponse),
+        PeerMessage::SyncSnapshotHosts(_) => Some(SyncSnapshotHosts),
+        PeerMessage::Routed(msg) => match msg.body {
+            RoutedMessageBody::PartialEncodedStateWitness(_) => Some(PartialEncodedStateWitness),
+            RoutedMessageBody::PartialEncodedStateWitnessForward(_) => {
+                Some(PartialEncodedStateWitnessForward)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Compresses `msg`, given its proto encoding `bytes`.
+/// Returns `None` if the message shouldn't be sent compressed: because of its type,
+/// because it is smaller than the threshold, because compression doesn't make it smaller or
+/// because the receiver would reject it as too large or compressed too well.
+pub(crate) fn compress_message(
+    msg: &PeerMessage,
+    bytes: &[u8],
+    cfg: &MessageCompressionConfig,
+) -> Option<Vec<u8>> {
+    let message_type = compressible_type(msg)?;
+    if bytes.len() < cfg.threshold_bytes || bytes.len() > max_decompressed_size(message_type) {
+        return None;
+    }
+    let data = match zstd::bulk::compress(bytes, cfg.level) {
+        Ok(data) => data,
+        Err(err) => {
+            tracing::warn!(target: "network", ?err, "failed to compress {message_type} message");
+            return None;
+        }
+    };
+    if data.len() >= bytes.len() || bytes.len() > data.len() * MAX_COMPRESSION_RATIO {
+        return None;
+    }
+    let msg = proto::PeerMessage {
+        message_type: Some(ProtoMT::Compressed(proto::CompressedPeerMessage {
+            message_type: message_type.to_string(),
+            uncompressed_size: bytes.len() as u64,
+            data,
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    Some(msg.write_to_bytes().unwrap())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseCompressedPeerMessageError {
+    #[error("unknown message type {0:?}")]
+    UnknownMessageType(String),
+    #[error("uncompressed_size: {0}")]
+    UncompressedSize(std::num::TryFromIntError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum DecompressError {
+    #[error("message too large: got {got_bytes} bytes, want <= {want_max_bytes} bytes")]
+    TooLarge { got_bytes: usize, want_max_bytes: usize },
+    #[error(
+        "compression ratio too high: {uncompressed_bytes} bytes from {compressed_bytes} bytes"
+    )]
+    RatioTooHigh { uncompressed_bytes: usize, compressed_bytes: usize },
+    #[error("zstd")]
+    Zstd(#[source] std::io::Error),
+    #[error("decompressed {got_bytes} bytes, want {want_bytes} bytes")]
+    SizeMismatch { got_bytes: usize, want_bytes: usize },
+    #[error("ParsePeerMessage")]
+    Parse(#[source] ParsePeerMessageError),
+    #[error("declared type {declared}, but the message is {got:?}")]
+    TypeMismatch { declared: RateLimitedPeerMessageKey, got: Option<RateLimitedPeerMessageKey> },
+}
+
+/// A received compressed message, which hasn't been decompressed yet.
+#[derive(Debug)]
+pub(crate) struct CompressedPeerMessage {
+    /// Declared type of the message. It is verified only after decompression.
+    pub message_type: RateLimitedPeerMessageKey,
+    /// Declared size of the decompressed message.
+    pub uncompressed_size: usize,
+    data: Vec<u8>,
+}
+
+impl TryFrom<&proto::CompressedPeerMessage> for CompressedPeerMessage {
+    type Error = ParseCompressedPeerMessageError;
+    fn try_from(x: &proto::CompressedPeerMessage) -> Result<Self, Self::Error> {
+        Ok(Self {
+            message_type: x.message_type.parse().map_err(|_| {
+                ParseCompressedPeerMessageError::UnknownMessageType(x.message_type.clone())
+            })?,
+            uncompressed_size: x
+                .uncompressed_size
+                .try_into()
+                .map_err(ParseCompressedPeerMessageError::UncompressedSize)?,
+            data: x.data.clone(),
+        })
+    }
+}
+
+impl CompressedPeerMessage {
+    /// Size of the compressed message.
+    pub fn compressed_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Decompresses the message. At most `max_size` bytes, or less if the maximal size for the
+    /// type of the message is lower, are ever allocated for the decompressed message, whatever
+    /// the compressed data is. The declared size has to be at most `MAX_COMPRESSION_RATIO`
+    /// times the size of the compressed data.
+    pub fn decompress(self, max_size: usize) -> Result<PeerMessage, DecompressError> {
+        let max_size = max_size.min(max_decompressed_size(self.message_type));
+        if self.uncompressed_size > max_size {
+            return Err(DecompressError::TooLarge {
+                got_bytes: self.uncompressed_size,
+                want_max_bytes: max_size,
+            });
+        }
+        if self.uncompressed_size > self.data.len().saturating_mul(MAX_COMPRESSION_RATIO) {
+            return Err(DecompressError::RatioTooHigh {
+                uncompressed_bytes: self.uncompressed_size,
+                compressed_bytes: self.data.len(),
+            });
+        }
+        // `decompress` fails rather than allocate more than `uncompressed_size` bytes.
+        let data = zstd::bulk::decompress(&self.data, self.uncompressed_size)
+            .map_err(DecompressError::Zstd)?;
+        if data.len() != self.uncompressed_size {
+            return Err(DecompressError::SizeMismatch {
+                got_bytes: data.len(),
+                want_bytes: self.uncompressed_size,
+            });
+        }
+        // `deserialize` rejects nested compressed messages.
+        let msg =
+            PeerMessage::deserialize(Encoding::Proto, &data).map_err(DecompressError::Parse)?;
+        let got = compressible_type(&msg);
+        if got != Some(self.message_type) {
+            return Err(DecompressError::TypeMismatch { declared: self.message_type, got });
+        }
+        Ok(msg)
+    }
+}
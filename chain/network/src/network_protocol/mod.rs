@@ -2,10 +2,12 @@
 #[path = "borsh.rs"]
 mod borsh_;
 mod borsh_conv;
+mod compression;
 mod edge;
 mod peer;
 mod proto_conv;
 mod state_sync;
+pub(crate) use compression::{compress_message, CompressedPeerMessage};
 pub use edge::*;
 use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
 use near_primitives::stateless_validation::contract_distribution::ChunkContractAccesses;
@@ -365,6 +367,44 @@ pub struct Handshake {
     pub(crate) partial_edge_info: PartialEdgeInfo,
     /// Account owned by the sender.
     pub(crate) owned_account: Option<SignedOwnedAccount>,
+    /// Whether the sender wants to encrypt the connection.
+    /// See `encrypted_transport` in network_protocol/network.proto.
+    pub(crate) encrypted_transport: bool,
+    /// Whether the sender accepts compressed messages.
+    /// See `message_compression` in network_protocol/network.proto.
+    pub(crate) message_compression: bool,
+    /// Signature of `encrypted_transport` by the sender's node key.
+    /// See `encrypted_transport_signature` in network_protocol/network.proto.
+    pub(crate) encrypted_transport_signature: Option<Signature>,
+}
+
+impl Handshake {
+    /// Hash of `encrypted_transport` bound to the connection, so that the flag can't be
+    /// stripped or replayed from another connection.
+    fn encrypted_transport_hash(&self) -> CryptoHash {
+        CryptoHash::hash_borsh((
+            "encrypted_transport",
+            &self.sender_peer_id,
+            &self.target_peer_id,
+            self.partial_edge_info.nonce,
+            self.encrypted_transport,
+        ))
+    }
+
+    /// Signs `encrypted_transport` with the node key of the sender.
+    pub(crate) fn sign_encrypted_transport(mut self, node_key: &near_crypto::SecretKey) -> Self {
+        let hash = self.encrypted_transport_hash();
+        self.encrypted_transport_signature = Some(node_key.sign(hash.as_ref()));
+        self
+    }
+
+    /// Whether `encrypted_transport` is signed by the sender.
+    pub(crate) fn verify_encrypted_transport(&self) -> bool {
+        let Some(signature) = &self.encrypted_transport_signature else {
+            return false;
+        };
+        signature.verify(self.encrypted_transport_hash().as_ref(), self.sender_peer_id.public_key())
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, strum::IntoStaticStr)]
@@ -472,6 +512,18 @@ pub enum ParsePeerMessageError {
     ProtoDecode(#[source] protobuf::Error),
     #[error("ProtoConv")]
     ProtoConv(#[source] proto_conv::ParsePeerMessageError),
+    #[error("Compressed")]
+    Compressed(#[source] compression::ParseCompressedPeerMessageError),
+    #[error("UnexpectedCompressed")]
+    UnexpectedCompressed,
+}
+
+/// A frame received from a peer.
+pub(crate) enum ReceivedFrame {
+    Message(PeerMessage),
+    /// A compressed message. It is up to the caller to check it against
+    /// the limits before decompressing it.
+    Compressed(CompressedPeerMessage),
 }
 
 impl PeerMessage {
@@ -493,8 +545,19 @@ impl PeerMessage {
         enc: Encoding,
         data: &[u8],
     ) -> Result<PeerMessage, ParsePeerMessageError> {
+        match Self::deserialize_frame(enc, data)? {
+            ReceivedFrame::Message(msg) => Ok(msg),
+            ReceivedFrame::Compressed(_) => Err(ParsePeerMessageError::UnexpectedCompressed),
+        }
+    }
+
+    /// Deserializes a frame in the given encoding, which may contain a compressed message.
+    pub(crate) fn deserialize_frame(
+        enc: Encoding,
+        data: &[u8],
+    ) -> Result<ReceivedFrame, ParsePeerMessageError> {
         let span = tracing::trace_span!(target: "network", "deserialize").entered();
-        Ok(match enc {
+        Ok(ReceivedFrame::Message(match enc {
             Encoding::Borsh => (&borsh_::PeerMessage::try_from_slice(data)
                 .map_err(ParsePeerMessageError::BorshDecode)?)
                 .try_into()
@@ -502,12 +565,19 @@ impl PeerMessage {
             Encoding::Proto => {
                 let proto_msg: proto::PeerMessage = proto::PeerMessage::parse_from_bytes(data)
                     .map_err(ParsePeerMessageError::ProtoDecode)?;
+                if let Some(proto::peer_message::Message_type::Compressed(msg)) =
+                    &proto_msg.message_type
+                {
+                    return Ok(ReceivedFrame::Compressed(
+                        msg.try_into().map_err(ParsePeerMessageError::Compressed)?,
+                    ));
+                }
                 if let Ok(extracted_span_context) = extract_span_context(&proto_msg.trace_context) {
                     span.clone().or_current().add_link(extracted_span_context);
                 }
                 (&proto_msg).try_into().map_err(|err| ParsePeerMessageError::ProtoConv(err))?
             }
-        })
+        }))
     }
 
     pub(crate) fn msg_variant(&self) -> &'static str {
@@ -912,6 +982,21 @@ impl StateResponseInfo {
         }
     }
 
+    /// Whether the response contains neither the state header nor a state part,
+    /// i.e. the peer wasn't able to serve the request.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::V1(info) => {
+                info.state_response.header.is_none() && info.state_response.part.is_none()
+            }
+            Self::V2(info) => match &info.state_response {
+                ShardStateSyncResponse::V1(r) => r.header.is_none() && r.part.is_none(),
+                ShardStateSyncResponse::V2(r) => r.header.is_none() && r.part.is_none(),
+                ShardStateSyncResponse::V3(r) => r.header.is_none() && r.part.is_none(),
+            },
+        }
+    }
+
     pub fn take_state_response(self) -> ShardStateSyncResponse {
         match self {
             Self::V1(info) => ShardStateSyncResponse::V1(info.state_response),
@@ -77,6 +77,8 @@ pub enum ParseHandshakeError {
     PartialEdgeInfo(ParseRequiredError<ParsePartialEdgeInfoError>),
     #[error("owned_account {0}")]
     OwnedAccount(ParseSignedOwnedAccountError),
+    #[error("encrypted_transport_signature {0}")]
+    EncryptedTransportSignature(ParseSignatureError),
 }
 
 impl From<&Handshake> for proto::Handshake {
@@ -90,6 +92,13 @@ impl From<&Handshake> for proto::Handshake {
             sender_chain_info: MF::some((&x.sender_chain_info).into()),
             partial_edge_info: MF::some((&x.partial_edge_info).into()),
             owned_account: x.owned_account.as_ref().map(Into::into).into(),
+            encrypted_transport: x.encrypted_transport,
+            message_compression: x.message_compression,
+            encrypted_transport_signature: x
+                .encrypted_transport_signature
+                .as_ref()
+                .map(Into::into)
+                .into(),
             ..Self::default()
         }
     }
@@ -120,6 +129,10 @@ impl TryFrom<&proto::Handshake> for Handshake {
                 .map_err(Self::Error::PartialEdgeInfo)?,
             owned_account: try_from_optional(&p.owned_account)
                 .map_err(Self::Error::OwnedAccount)?,
+            encrypted_transport: p.encrypted_transport,
+            message_compression: p.message_compression,
+            encrypted_transport_signature: try_from_optional(&p.encrypted_transport_signature)
+                .map_err(Self::Error::EncryptedTransportSignature)?,
         })
     }
 }
@@ -324,7 +324,7 @@ impl Chain {
 }
 
 pub fn make_handshake<R: Rng>(rng: &mut R, chain: &Chain) -> Handshake {
-    let a = make_signer(rng);
+    let a = make_secret_key(rng);
     let b = make_signer(rng);
     let a_id = PeerId::new(a.public_key());
     let b_id = PeerId::new(b.public_key());
@@ -337,7 +337,11 @@ pub fn make_handshake<R: Rng>(rng: &mut R, chain: &Chain) -> Handshake {
         sender_chain_info: chain.get_peer_chain_info(),
         partial_edge_info: make_partial_edge(rng),
         owned_account: None,
+        encrypted_transport: false,
+        message_compression: false,
+        encrypted_transport_signature: None,
     }
+    .sign_encrypted_transport(&a)
 }
 
 pub fn make_routed_message<R: Rng>(rng: &mut R, body: RoutedMessageBody) -> RoutedMessageV2 {
@@ -1,10 +1,16 @@
 use super::*;
+use crate::config::MessageCompressionConfig;
+use crate::network_protocol::compression::{
+    max_decompressed_size, DecompressError, MAX_COMPRESSION_RATIO,
+};
 use crate::network_protocol::testonly as data;
 use crate::network_protocol::{Encoding, PeersResponse};
+use crate::rate_limits::messages_limits::RateLimitedPeerMessageKey;
 use crate::testonly::make_rng;
 use crate::types::{Disconnect, HandshakeFailureReason, PeerMessage};
 use crate::types::{PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg};
 use anyhow::{bail, Context as _};
+use assert_matches::assert_matches;
 use itertools::Itertools as _;
 use near_async::time;
 use rand::Rng as _;
@@ -170,3 +176,116 @@ fn serialize_deserialize() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn make_compressed_frame(message_type: &str, uncompressed_size: usize, data: Vec<u8>) -> Vec<u8> {
+    use protobuf::Message as _;
+    let msg = proto::PeerMessage {
+        message_type: Some(proto::peer_message::Message_type::Compressed(
+            proto::CompressedPeerMessage {
+                message_type: message_type.to_string(),
+                uncompressed_size: uncompressed_size as u64,
+                data,
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    msg.write_to_bytes().unwrap()
+}
+
+fn parse_compressed_frame(bytes: &[u8]) -> CompressedPeerMessage {
+    match PeerMessage::deserialize_frame(Encoding::Proto, bytes).unwrap() {
+        ReceivedFrame::Compressed(msg) => msg,
+        ReceivedFrame::Message(msg) => panic!("got {msg}, want a compressed message"),
+    }
+}
+
+#[test]
+fn compress_decompress() {
+    let mut rng = make_rng(4823749823);
+    let mut clock = time::FakeClock::default();
+    let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let cfg = MessageCompressionConfig { enabled: true, threshold_bytes: 0, level: 3 };
+
+    let msg = PeerMessage::Block(chain.blocks[5].clone());
+    let bytes = msg.serialize(Encoding::Proto);
+    let compressed = compress_message(&msg, &bytes, &cfg).unwrap();
+    assert!(compressed.len() < bytes.len());
+    // Compressed messages are never accepted where a plain message is expected.
+    assert_matches!(
+        PeerMessage::deserialize(Encoding::Proto, &compressed),
+        Err(ParsePeerMessageError::UnexpectedCompressed)
+    );
+    let got = parse_compressed_frame(&compressed);
+    assert_eq!(RateLimitedPeerMessageKey::Block, got.message_type);
+    assert_eq!(bytes.len(), got.uncompressed_size);
+    assert_eq!(msg, got.decompress(bytes.len()).unwrap());
+
+    // The declared size is checked against the limit before decompression.
+    let got = parse_compressed_frame(&compressed);
+    assert_matches!(got.decompress(bytes.len() - 1), Err(DecompressError::TooLarge { .. }));
+
+    // Messages below the threshold are not compressed.
+    let cfg = MessageCompressionConfig { threshold_bytes: bytes.len() + 1, ..cfg };
+    assert_eq!(None, compress_message(&msg, &bytes, &cfg));
+
+    // Only large message types are compressed.
+    let cfg = MessageCompressionConfig { threshold_bytes: 0, ..cfg };
+    let msg = PeerMessage::BlockRequest(*chain.blocks[5].hash());
+    assert_eq!(None, compress_message(&msg, &msg.serialize(Encoding::Proto), &cfg));
+}
+
+#[test]
+fn decompress_invalid() {
+    let mut rng = make_rng(2340982394);
+    let mut clock = time::FakeClock::default();
+    let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let max_size = 1000;
+
+    // Data which decompresses to more than declared is rejected without decompressing it all.
+    let bomb = zstd::bulk::compress(&vec![0; 1 << 24], 3).unwrap();
+    let got = parse_compressed_frame(&make_compressed_frame("Block", max_size, bomb));
+    assert_matches!(got.decompress(max_size), Err(DecompressError::Zstd(_)));
+
+    // Data which is declared to compress too well is rejected before decompression.
+    let data = vec![0; 5];
+    let declared_size = data.len() * MAX_COMPRESSION_RATIO + 1;
+    assert!(declared_size <= max_size);
+    let got = parse_compressed_frame(&make_compressed_frame("Block", declared_size, data));
+    assert_matches!(got.decompress(max_size), Err(DecompressError::RatioTooHigh { .. }));
+
+    // Every type has its own maximal size, lower than the maximal network message size.
+    let max_type_size = max_decompressed_size(RateLimitedPeerMessageKey::SyncSnapshotHosts);
+    let data = vec![0; max_type_size / MAX_COMPRESSION_RATIO + 1];
+    let got = parse_compressed_frame(&make_compressed_frame(
+        "SyncSnapshotHosts",
+        max_type_size + 1,
+        data,
+    ));
+    assert_matches!(
+        got.decompress(usize::MAX),
+        Err(DecompressError::TooLarge { want_max_bytes, .. }) if want_max_bytes == max_type_size
+    );
+
+    // The declared type has to match the type of the message.
+    let bytes = PeerMessage::Block(chain.blocks[5].clone()).serialize(Encoding::Proto);
+    let data = zstd::bulk::compress(&bytes, 3).unwrap();
+    let got =
+        parse_compressed_frame(&make_compressed_frame("SyncSnapshotHosts", bytes.len(), data));
+    assert_matches!(got.decompress(bytes.len()), Err(DecompressError::TypeMismatch { .. }));
+
+    // Compressed messages cannot be nested.
+    let inner = make_compressed_frame("Block", 1, vec![0]);
+    let data = zstd::bulk::compress(&inner, 3).unwrap();
+    let got = parse_compressed_frame(&make_compressed_frame("Block", inner.len(), data));
+    assert_matches!(
+        got.decompress(max_size),
+        Err(DecompressError::Parse(ParsePeerMessageError::UnexpectedCompressed))
+    );
+
+    // Unknown message types are rejected.
+    assert_matches!(
+        PeerMessage::deserialize_frame(Encoding::Proto, &make_compressed_frame("Foo", 1, vec![])),
+        Err(ParsePeerMessageError::Compressed(_))
+    );
+}
@@ -1,4 +1,5 @@
 pub(crate) mod peer_actor;
+mod noise;
 mod stream;
 mod tracker;
 mod transfer_stats;
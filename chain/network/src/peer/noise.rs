@@ -0,0 +1,234 @@
+//! `Noise_XX_25519_ChaChaPoly_SHA256` handshake and the resulting transport
+//! encryption, as specified in <https://noiseprotocol.org/noise.html>.
+//!
+//! The protocol itself is implemented by the `snow` crate; this module adapts
+//! it to the peer connections (see `stream::FramedStream::start_session`),
+//! which use the XX pattern:
+//! ```text
+//!   -> e
+//!   <- e, ee, s, es
+//!   -> s, se
+//! ```
+//! The static keys are generated per connection and are bound to the node keys
+//! by signatures carried in the handshake payloads.
+//!
+//! The send and recv loops of a connection run independently, so the
+//! transport keys are split into a `CipherState` per direction, each keeping
+//! its own nonce counter.
+use std::sync::Arc;
+
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_SHA256";
+pub(crate) const TAG_LEN: usize = 16;
+/// Maximal length of a single Noise message, including the authentication tag.
+pub(crate) const MAX_MESSAGE_LEN: usize = 65535;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum Error {
+    #[error("{0}")]
+    Snow(#[from] snow::Error),
+    #[error("nonce exhausted")]
+    NonceExhausted,
+    #[error("message too long: got {got_bytes}B, want <={want_max_bytes}B")]
+    MessageTooLong { got_bytes: usize, want_max_bytes: usize },
+}
+
+/// Transport keys of one direction of a session, with a counter nonce.
+pub(crate) struct CipherState {
+    transport: Arc<snow::StatelessTransportState>,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn next_nonce(&mut self) -> Result<u64, Error> {
+        // Nonce 2^64-1 is reserved by the spec.
+        if self.nonce == u64::MAX {
+            return Err(Error::NonceExhausted);
+        }
+        self.nonce += 1;
+        Ok(self.nonce - 1)
+    }
+
+    /// Encrypts a transport message.
+    /// `plaintext` should be at most `MAX_MESSAGE_LEN - TAG_LEN` bytes long.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        if plaintext.len() + TAG_LEN > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong {
+                got_bytes: plaintext.len() + TAG_LEN,
+                want_max_bytes: MAX_MESSAGE_LEN,
+            });
+        }
+        let nonce = self.next_nonce()?;
+        let mut buf = vec![0; plaintext.len() + TAG_LEN];
+        let len = self.transport.write_message(nonce, plaintext, &mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Decrypts and authenticates a transport message.
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.next_nonce()?;
+        let mut buf = vec![0; ciphertext.len()];
+        let len = self.transport.read_message(nonce, ciphertext, &mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
+
+/// Keys of an established session.
+pub(crate) struct Transport {
+    /// Encrypts the messages sent to the peer.
+    pub send: CipherState,
+    /// Decrypts the messages received from the peer.
+    pub recv: CipherState,
+}
+
+/// State of a `Noise_XX` handshake.
+/// Messages have to be written and read alternately, starting with
+/// `write_message` on the initiator side and `read_message` on the responder
+/// side.
+pub(crate) struct HandshakeState {
+    state: snow::HandshakeState,
+    local_static: Vec<u8>,
+}
+
+impl HandshakeState {
+    /// `prologue` has to be the same on both sides, otherwise the handshake
+    /// fails.
+    pub fn new(initiator: bool, prologue: &[u8]) -> Result<Self, Error> {
+        let builder = snow::Builder::new(NOISE_PARAMS.parse()?);
+        let keypair = builder.generate_keypair()?;
+        let builder = builder.local_private_key(&keypair.private).prologue(prologue);
+        let state =
+            if initiator { builder.build_initiator()? } else { builder.build_responder()? };
+        Ok(Self { state, local_static: keypair.public })
+    }
+
+    /// Public static key of this side of the handshake.
+    pub fn local_static(&self) -> &[u8] {
+        &self.local_static
+    }
+
+    /// Public static key of the peer, known once it has been received.
+    pub fn remote_static(&self) -> Option<&[u8]> {
+        self.state.get_remote_static()
+    }
+
+    /// Writes the next handshake message carrying `payload`.
+    pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut message = vec![0; MAX_MESSAGE_LEN];
+        let len = self.state.write_message(payload, &mut message)?;
+        message.truncate(len);
+        Ok(message)
+    }
+
+    /// Reads the next handshake message and returns its payload.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong {
+                got_bytes: message.len(),
+                want_max_bytes: MAX_MESSAGE_LEN,
+            });
+        }
+        let mut payload = vec![0; message.len()];
+        let len = self.state.read_message(message, &mut payload)?;
+        payload.truncate(len);
+        Ok(payload)
+    }
+
+    /// Derives the transport keys once all the handshake messages have been
+    /// exchanged.
+    pub fn into_transport(self) -> Result<Transport, Error> {
+        // The stateless transport encrypts with the initiator to responder key
+        // on the initiator side and with the other one on the responder side,
+        // so the same state serves both directions.
+        let transport = Arc::new(self.state.into_stateless_transport_mode()?);
+        Ok(Transport {
+            send: CipherState { transport: transport.clone(), nonce: 0 },
+            recv: CipherState { transport, nonce: 0 },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(
+        initiator_prologue: &[u8],
+        responder_prologue: &[u8],
+    ) -> Result<(Transport, Transport), Error> {
+        let mut initiator = HandshakeState::new(true, initiator_prologue)?;
+        let mut responder = HandshakeState::new(false, responder_prologue)?;
+        let msg = initiator.write_message(b"")?;
+        assert_eq!(responder.read_message(&msg)?, b"");
+        let msg = responder.write_message(b"responder payload")?;
+        assert_eq!(initiator.read_message(&msg)?, b"responder payload");
+        assert_eq!(initiator.remote_static(), Some(responder.local_static()));
+        let msg = initiator.write_message(b"initiator payload")?;
+        assert_eq!(responder.read_message(&msg)?, b"initiator payload");
+        assert_eq!(responder.remote_static(), Some(initiator.local_static()));
+        Ok((initiator.into_transport()?, responder.into_transport()?))
+    }
+
+    #[test]
+    fn test_handshake_and_transport() {
+        let (mut initiator, mut responder) = handshake(b"prologue", b"prologue").unwrap();
+        for i in 1..10 {
+            let msg = vec![i; 100 * i as usize];
+            let ciphertext = initiator.send.encrypt(&msg).unwrap();
+            assert_eq!(ciphertext.len(), msg.len() + TAG_LEN);
+            assert_ne!(ciphertext[..msg.len()], msg[..]);
+            assert_eq!(responder.recv.decrypt(&ciphertext).unwrap(), msg);
+            let ciphertext = responder.send.encrypt(&msg).unwrap();
+            assert_eq!(initiator.recv.decrypt(&ciphertext).unwrap(), msg);
+        }
+        // A message encrypted for the peer can't be decrypted by the sender.
+        let ciphertext = initiator.send.encrypt(b"message").unwrap();
+        assert!(initiator.recv.decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_tampered_message() {
+        let (mut initiator, mut responder) = handshake(b"prologue", b"prologue").unwrap();
+        let mut ciphertext = initiator.send.encrypt(b"message").unwrap();
+        ciphertext[0] ^= 1;
+        assert!(matches!(
+            responder.recv.decrypt(&ciphertext),
+            Err(Error::Snow(snow::Error::Decrypt))
+        ));
+
+        // Replayed messages are rejected as well.
+        let (mut initiator, mut responder) = handshake(b"prologue", b"prologue").unwrap();
+        let ciphertext = initiator.send.encrypt(b"message").unwrap();
+        assert_eq!(responder.recv.decrypt(&ciphertext).unwrap(), b"message");
+        assert!(matches!(
+            responder.recv.decrypt(&ciphertext),
+            Err(Error::Snow(snow::Error::Decrypt))
+        ));
+    }
+
+    #[test]
+    fn test_prologue_mismatch() {
+        assert!(matches!(handshake(b"prologue", b"other"), Err(Error::Snow(snow::Error::Decrypt))));
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let mut initiator = HandshakeState::new(true, b"").unwrap();
+        assert!(matches!(initiator.read_message(&[0; 32]), Err(Error::Snow(_))));
+        let mut responder = HandshakeState::new(false, b"").unwrap();
+        assert!(matches!(responder.write_message(b""), Err(Error::Snow(_))));
+        assert!(matches!(responder.into_transport(), Err(Error::Snow(_))));
+    }
+
+    #[test]
+    fn test_message_too_long() {
+        let (mut initiator, _) = handshake(b"", b"").unwrap();
+        let msg = vec![0; MAX_MESSAGE_LEN - TAG_LEN];
+        initiator.send.encrypt(&msg).unwrap();
+        assert!(matches!(
+            initiator.send.encrypt(&[msg.as_slice(), &[0]].concat()),
+            Err(Error::MessageTooLong { .. })
+        ));
+    }
+}
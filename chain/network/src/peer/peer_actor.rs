@@ -6,14 +6,14 @@ use crate::client::{
 };
 use crate::concurrency::atomic_cell::AtomicCell;
 use crate::concurrency::demux;
-use crate::config::PEERS_RESPONSE_MAX_PEERS;
+use crate::config::{EncryptedTransport, PEERS_RESPONSE_MAX_PEERS};
 #[cfg(feature = "distance_vector_routing")]
 use crate::network_protocol::DistanceVector;
 use crate::network_protocol::{
-    Edge, EdgeState, Encoding, OwnedAccount, ParsePeerMessageError, PartialEdgeInfo,
-    PeerChainInfoV2, PeerIdOrHash, PeerInfo, PeersRequest, PeersResponse, RawRoutedMessage,
-    RoutedMessageBody, RoutingTableUpdate, SnapshotHostInfoVerificationError, SyncAccountsData,
-    SyncSnapshotHosts,
+    compress_message, CompressedPeerMessage, Edge, EdgeState, Encoding, OwnedAccount,
+    ParsePeerMessageError, PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, PeersRequest,
+    PeersResponse, RawRoutedMessage, ReceivedFrame, RoutedMessageBody, RoutingTableUpdate,
+    SnapshotHostInfoVerificationError, SyncAccountsData, SyncSnapshotHosts,
 };
 use crate::peer::stream;
 use crate::peer::tracker::Tracker;
@@ -22,6 +22,7 @@ use crate::peer_manager::network_state::{NetworkState, PRUNE_EDGES_AFTER};
 #[cfg(test)]
 use crate::peer_manager::peer_manager_actor::Event;
 use crate::peer_manager::peer_manager_actor::MAX_TIER2_PEERS;
+use crate::peer_manager::peer_score;
 use crate::private_actix::{RegisterPeerError, SendMessage};
 use crate::rate_limits::messages_limits;
 use crate::routing::edge::verify_nonce;
@@ -46,7 +47,7 @@ use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::types::EpochId;
 use near_primitives::utils::DisplayOption;
 use near_primitives::version::{
-    ProtocolVersion, PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION,
+    ProtocolFeature, ProtocolVersion, PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION,
 };
 use parking_lot::Mutex;
 use rand::seq::IteratorRandom;
@@ -179,6 +180,8 @@ pub(crate) struct PeerActor {
     /// Whether the PeerActor should skip protobuf support detection and use
     /// a given encoding right away.
     force_encoding: Option<Encoding>,
+    /// Whether both peers accept compressed messages, as negotiated in the Handshake.
+    message_compression: bool,
 
     /// Peer status.
     peer_status: PeerStatus,
@@ -191,6 +194,9 @@ pub(crate) struct PeerActor {
 
     /// Per-message rate limits for incoming messages.
     received_messages_rate_limits: messages_limits::RateLimits,
+    /// Per-message rate limits for incoming compressed messages, on top of
+    /// `received_messages_rate_limits`.
+    received_compressed_messages_rate_limits: messages_limits::RateLimits,
 }
 
 impl Debug for PeerActor {
@@ -330,6 +336,10 @@ impl PeerActor {
             &network_state.config.received_messages_rate_limits,
             clock.now(),
         );
+        let received_compressed_messages_rate_limits = messages_limits::RateLimits::from_config(
+            &network_state.config.received_compressed_messages_rate_limits,
+            clock.now(),
+        );
         // recv is the HandshakeSignal returned by this spawn_inner() call.
         let (send, recv): (HandshakeSignalSender, HandshakeSignal) =
             tokio::sync::oneshot::channel();
@@ -360,6 +370,7 @@ impl PeerActor {
                     ),
                     protocol_buffers_supported: false,
                     force_encoding,
+                    message_compression: false,
                     peer_info: match &stream_type {
                         tcp::StreamType::Inbound => None,
                         tcp::StreamType::Outbound { peer_id, .. } => Some(PeerInfo {
@@ -371,6 +382,7 @@ impl PeerActor {
                     .into(),
                     network_state,
                     received_messages_rate_limits,
+                    received_compressed_messages_rate_limits,
                 }
             }),
             recv,
@@ -394,15 +406,72 @@ impl PeerActor {
         }
     }
 
-    fn parse_message(&mut self, msg: &[u8]) -> Result<PeerMessage, ParsePeerMessageError> {
+    fn parse_message(&mut self, msg: &[u8]) -> Result<ReceivedFrame, ParsePeerMessageError> {
         if let Some(e) = self.encoding() {
-            return PeerMessage::deserialize(e, msg);
+            return PeerMessage::deserialize_frame(e, msg);
         }
         if let Ok(msg) = PeerMessage::deserialize(Encoding::Proto, msg) {
             self.protocol_buffers_supported = true;
-            return Ok(msg);
+            return Ok(ReceivedFrame::Message(msg));
+        }
+        return PeerMessage::deserialize(Encoding::Borsh, msg).map(ReceivedFrame::Message);
+    }
+
+    /// Decompresses a received compressed message.
+    /// The declared type and size of the message are checked against the rate limits, including
+    /// the rate limits of compressed messages, the maximal size of a message of that type and the maximal compression ratio before
+    /// decompression, so that a peer cannot make the node decompress more than it would
+    /// accept for that type, nor much more than the peer has sent.
+    /// Returns `None` if the message should be dropped.
+    fn decompress_message(
+        &mut self,
+        msg: CompressedPeerMessage,
+        now: time::Instant,
+    ) -> Option<PeerMessage> {
+        let msg_type = msg.message_type.to_string();
+        if !self.message_compression {
+            tracing::debug!(target: "network", "Received compressed {} from {}, but compression has not been negotiated", msg_type, self.peer_info);
+            metrics::PEER_MESSAGE_DECOMPRESSION_FAILED_BY_TYPE_TOTAL
+                .with_label_values(&[msg_type.as_str()])
+                .inc();
+            return None;
+        }
+        // All the compressible messages have a token cost of 1.
+        if !self.received_messages_rate_limits.is_allowed_key(msg.message_type, 1, now)
+            || !self.received_compressed_messages_rate_limits.is_allowed_key(
+                msg.message_type,
+                1,
+                now,
+            )
+        {
+            metrics::PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL
+                .with_label_values(&[msg_type.as_str()])
+                .inc();
+            tracing::debug!(target: "network", "Peer {} is being rate limited for message {}", self.peer_info, msg_type);
+            return None;
+        }
+        let labels = ["received", msg_type.as_str()];
+        let compressed_size = msg.compressed_size();
+        let uncompressed_size = msg.uncompressed_size;
+        match msg.decompress(stream::NETWORK_MESSAGE_MAX_SIZE_BYTES) {
+            Ok(msg) => {
+                metrics::PEER_MESSAGE_COMPRESSED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
+                metrics::PEER_MESSAGE_COMPRESSED_BY_TYPE_BYTES
+                    .with_label_values(&labels)
+                    .inc_by(compressed_size as u64);
+                metrics::PEER_MESSAGE_COMPRESSED_BY_TYPE_UNCOMPRESSED_BYTES
+                    .with_label_values(&labels)
+                    .inc_by(uncompressed_size as u64);
+                Some(msg)
+            }
+            Err(err) => {
+                tracing::debug!(target: "network", ?err, "Received invalid compressed {} from {}", msg_type, self.peer_info);
+                metrics::PEER_MESSAGE_DECOMPRESSION_FAILED_BY_TYPE_TOTAL
+                    .with_label_values(&[msg_type.as_str()])
+                    .inc();
+                None
+            }
         }
-        return PeerMessage::deserialize(Encoding::Borsh, msg);
     }
 
     fn send_message_or_log(&self, msg: &PeerMessage) {
@@ -435,7 +504,7 @@ impl PeerActor {
             // peers to update its height at the peer. In the future we will introduce a new
             // peer message type for that and then we can enable this check again.
             //PeerMessage::Block(b) if self.tracker.lock().has_received(b.hash()) => return,
-            PeerMessage::BlockRequest(h) => self.tracker.lock().push_request(*h),
+            PeerMessage::BlockRequest(h) => self.tracker.lock().push_request(*h, self.clock.now()),
             PeerMessage::SyncAccountsData(d) => metrics::SYNC_ACCOUNTS_DATA
                 .with_label_values(&[
                     "sent",
@@ -452,7 +521,21 @@ impl PeerActor {
             _ => (),
         };
 
-        let bytes = msg.serialize(enc);
+        let mut bytes = msg.serialize(enc);
+        if self.message_compression && enc == Encoding::Proto {
+            let cfg = &self.network_state.config.message_compression;
+            if let Some(compressed) = compress_message(msg, &bytes, cfg) {
+                let labels = ["sent", msg.msg_variant()];
+                metrics::PEER_MESSAGE_COMPRESSED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
+                metrics::PEER_MESSAGE_COMPRESSED_BY_TYPE_BYTES
+                    .with_label_values(&labels)
+                    .inc_by(compressed.len() as u64);
+                metrics::PEER_MESSAGE_COMPRESSED_BY_TYPE_UNCOMPRESSED_BYTES
+                    .with_label_values(&labels)
+                    .inc_by(bytes.len() as u64);
+                bytes = compressed;
+            }
+        }
         self.tracker.lock().increment_sent(&self.clock, bytes.len() as u64);
         let bytes_len = bytes.len();
         tracing::trace!(target: "network", msg_len = bytes_len);
@@ -494,18 +577,67 @@ impl PeerActor {
                 }
                 .sign(&signer)
             }),
-        };
+            encrypted_transport: self.wants_encrypted_transport(spec.protocol_version),
+            message_compression: self.network_state.config.message_compression.enabled,
+            encrypted_transport_signature: None,
+        }
+        .sign_encrypted_transport(&self.network_state.config.node_key);
+        let encrypted_transport = handshake.encrypted_transport;
         let msg = match spec.tier {
             tcp::Tier::T1 => PeerMessage::Tier1Handshake(handshake),
             tcp::Tier::T2 => PeerMessage::Tier2Handshake(handshake),
             tcp::Tier::T3 => PeerMessage::Tier3Handshake(handshake),
         };
+        // Encryption can be negotiated only with proto encoding. Also if the Handshake was
+        // sent in both encodings, the peer would receive the borsh one after the plaintext
+        // part of the stream has ended.
+        if encrypted_transport && self.encoding().is_none() {
+            self.send_message_with_encoding(&msg, Encoding::Proto);
+            return;
+        }
         self.send_message_or_log(&msg);
     }
 
+    /// Whether this node wants to encrypt the connection using the given protocol version.
+    /// The connection is encrypted iff both peers want it.
+    fn wants_encrypted_transport(&self, protocol_version: ProtocolVersion) -> bool {
+        self.network_state.config.encrypted_transport != EncryptedTransport::Disabled
+            && ProtocolFeature::EncryptedPeerConnections.enabled(protocol_version)
+    }
+
+    fn session_spec(&self, tier: tcp::Tier, handshake: &Handshake) -> stream::SessionSpec {
+        let initiator = self.peer_type == PeerType::Outbound;
+        let (initiator_id, responder_id) = if initiator {
+            (self.my_node_id(), &handshake.sender_peer_id)
+        } else {
+            (&handshake.sender_peer_id, self.my_node_id())
+        };
+        let tier: &str = tier.as_ref();
+        stream::SessionSpec {
+            initiator,
+            node_key: self.network_state.config.node_key.clone(),
+            peer_id: handshake.sender_peer_id.clone(),
+            prologue: borsh::to_vec(&(
+                tier,
+                initiator_id,
+                responder_id,
+                handshake.partial_edge_info.nonce,
+            ))
+            .unwrap(),
+            timeout: self.network_state.config.handshake_timeout,
+        }
+    }
+
     fn stop(&mut self, ctx: &mut actix::Context<PeerActor>, reason: ClosingReason) {
         // Only the first call to stop sets the closing_reason.
         if self.closing_reason.is_none() {
+            if let (ClosingReason::Ban(ban_reason), Some(peer_id)) = (&reason, self.other_peer_id())
+            {
+                if !self.network_state.peer_scores.should_ban(&self.clock, peer_id, *ban_reason) {
+                    tracing::debug!(target: "network", ?peer_id, ?ban_reason, "Peer penalized instead of banned");
+                    return;
+                }
+            }
             self.closing_reason = Some(reason);
         }
         ctx.stop();
@@ -634,6 +766,49 @@ impl PeerActor {
             }
         }
 
+        // An on-path attacker could strip `encrypted_transport` from the plaintext Handshake
+        // (or drop the proto Handshake and forward the borsh one, which doesn't carry the
+        // flag) to silently downgrade the connection. Peers supporting encryption sign the
+        // flag, so if we want encryption we require a signed proto Handshake from them.
+        // Downgrading by lowering `protocol_version` remains possible until
+        // PEER_MIN_ALLOWED_PROTOCOL_VERSION supports EncryptedPeerConnections.
+        if self.wants_encrypted_transport(handshake.protocol_version)
+            && (self.encoding() != Some(Encoding::Proto) || !handshake.verify_encrypted_transport())
+        {
+            tracing::debug!(
+                target: "network",
+                peer_id = ?handshake.sender_peer_id,
+                "Handshake without a valid encrypted_transport signature"
+            );
+            self.stop(ctx, ClosingReason::HandshakeFailed);
+            return;
+        }
+        let encrypted = self.wants_encrypted_transport(handshake.protocol_version)
+            && handshake.encrypted_transport;
+        if !encrypted
+            && self.network_state.config.encrypted_transport == EncryptedTransport::Required
+        {
+            tracing::debug!(
+                target: "network",
+                peer_id = ?handshake.sender_peer_id,
+                "Encrypted connections are required, but the peer doesn't support them"
+            );
+            self.stop(ctx, ClosingReason::HandshakeFailed);
+            return;
+        }
+        let mut session_spec = encrypted.then(|| self.session_spec(tier, &handshake));
+        if encrypted {
+            // The Handshake is the last plaintext frame sent by the peer.
+            self.framed.expect_session();
+            // The outbound side has already sent its Handshake, so it starts the session
+            // right away. The inbound side starts it after responding with its Handshake.
+            if self.peer_type == PeerType::Outbound {
+                self.framed.start_session(session_spec.take().unwrap());
+            }
+        }
+        self.message_compression =
+            self.network_state.config.message_compression.enabled && handshake.message_compression;
+
         // Merge partial edges.
         let nonce = handshake.partial_edge_info.nonce;
         let partial_edge_info = match cs {
@@ -676,6 +851,7 @@ impl PeerActor {
             archival: handshake.sender_chain_info.archival,
             last_block: Default::default(),
             peer_type: self.peer_type,
+            encrypted,
             stats: self.stats.clone(),
             _peer_connections_metric: metrics::PEER_CONNECTIONS.new_point(&metrics::Connection {
                 tier: tier,
@@ -743,6 +919,9 @@ impl PeerActor {
                                 protocol_version: handshake.protocol_version,
                                 partial_edge_info: partial_edge_info,
                             });
+                            if let Some(spec) = session_spec {
+                                act.framed.start_session(spec);
+                            }
                         }
                         // TIER1 is strictly reserved for BFT consensensus messages,
                         // so all kinds of periodical syncs happen only on TIER2 connections.
@@ -1015,6 +1194,19 @@ impl PeerActor {
                 });
                 let mut tracker = self.tracker.lock();
                 tracker.push_received(hash);
+                if let Some(latency) = tracker.pop_request_latency(&hash, self.clock.now()) {
+                    let peer_scores = &self.network_state.peer_scores;
+                    peer_scores.record(
+                        &self.clock,
+                        &conn.peer_info.id,
+                        peer_score::Event::UsefulResponse,
+                    );
+                    peer_scores.record(
+                        &self.clock,
+                        &conn.peer_info.id,
+                        peer_score::Event::ResponseLatency(latency),
+                    );
+                }
                 tracker.has_request(&hash)
             }
             _ => false,
@@ -1076,12 +1268,21 @@ impl PeerActor {
                     None
                 }
                 PeerMessage::BlockHeaders(headers) => {
-                    if let Ok(Err(ban_reason)) = network_state
+                    let event = if headers.is_empty() {
+                        peer_score::Event::UselessResponse
+                    } else {
+                        peer_score::Event::UsefulResponse
+                    };
+                    match network_state
                         .client
-                        .send_async(BlockHeadersResponse(headers, peer_id))
+                        .send_async(BlockHeadersResponse(headers, peer_id.clone()))
                         .await
                     {
-                        return Err(ban_reason);
+                        Ok(Err(ban_reason)) => return Err(ban_reason),
+                        Ok(Ok(())) => {
+                            network_state.peer_scores.record(&clock, &peer_id, event);
+                        }
+                        Err(_) => {}
                     }
                     None
                 }
@@ -1104,6 +1305,12 @@ impl PeerActor {
                     .flatten()
                     .map(|response| PeerMessage::VersionedStateResponse(*response.0)),
                 PeerMessage::VersionedStateResponse(info) => {
+                    let event = if info.is_empty() {
+                        peer_score::Event::UselessResponse
+                    } else {
+                        peer_score::Event::UsefulResponse
+                    };
+                    network_state.peer_scores.record(&clock, &peer_id, event);
                     //TODO: Route to state sync actor.
                     network_state
                         .client
@@ -1428,6 +1635,7 @@ impl PeerActor {
                 }
 
                 self.network_state.add_route_back(&self.clock, &conn, msg.as_ref());
+                let peer_scores = &self.network_state.peer_scores;
                 if for_me {
                     // Handle Ping and Pong message if they are for us without sending to client.
                     // i.e. Return false in case of Ping and Pong
@@ -1456,8 +1664,19 @@ impl PeerActor {
                     }
                 } else {
                     if msg.decrease_ttl() {
-                        self.network_state.send_message_to_peer(&self.clock, conn.tier, msg);
+                        if self.network_state.send_message_to_peer(&self.clock, conn.tier, msg) {
+                            peer_scores.record(
+                                &self.clock,
+                                &conn.peer_info.id,
+                                peer_score::Event::RoutingSuccess,
+                            );
+                        }
                     } else {
+                        peer_scores.record(
+                            &self.clock,
+                            &conn.peer_info.id,
+                            peer_score::Event::RoutingFailure,
+                        );
                         #[cfg(test)]
                         self.network_state.config.event_sink.send(Event::RoutedMessageDropped);
                         tracing::debug!(target: "network", ?msg, from = ?conn.peer_info.id, "Message dropped because TTL reached 0.");
@@ -1645,8 +1864,16 @@ impl actix::Handler<stream::Error> for PeerActor {
             }
             // It is expected in a sense that the peer might be just slow.
             stream::Error::Send(stream::SendError::QueueOverflow { .. }) => true,
+            // The peer has sent invalid or tampered data, or failed to authenticate.
+            stream::Error::Recv(stream::RecvError::Decryption(_))
+            | stream::Error::Session(stream::SessionError::Noise(_))
+            | stream::Error::Session(stream::SessionError::Timeout)
+            | stream::Error::Session(stream::SessionError::InvalidSignature) => true,
+            // Nonce exhaustion would require sending 2^64 frames.
+            stream::Error::Send(stream::SendError::Encryption(_)) => false,
             stream::Error::Recv(stream::RecvError::IO(err))
-            | stream::Error::Send(stream::SendError::IO(err)) => match err.kind() {
+            | stream::Error::Send(stream::SendError::IO(err))
+            | stream::Error::Session(stream::SessionError::IO(err)) => match err.kind() {
                 // Connection has been closed.
                 io::ErrorKind::UnexpectedEof
                 | io::ErrorKind::ConnectionReset
@@ -1693,8 +1920,14 @@ impl actix::Handler<stream::Frame> for PeerActor {
             self.tracker.lock().increment_received(&self.clock, msg.len() as u64);
         }
 
-        let mut peer_msg = match self.parse_message(&msg) {
-            Ok(msg) => msg,
+        let now = self.clock.now();
+        // Compressed messages are checked against the rate limits before decompression.
+        let (mut peer_msg, rate_limits_checked) = match self.parse_message(&msg) {
+            Ok(ReceivedFrame::Message(msg)) => (msg, false),
+            Ok(ReceivedFrame::Compressed(msg)) => match self.decompress_message(msg, now) {
+                Some(msg) => (msg, true),
+                None => return,
+            },
             Err(err) => {
                 tracing::debug!(target: "network", "Received invalid data {} from {}: {}", near_fmt::AbbrBytes(&msg), self.peer_info, err);
                 return;
@@ -1703,14 +1936,15 @@ impl actix::Handler<stream::Frame> for PeerActor {
 
         tracing::trace!(target: "network", "Received message: {}", peer_msg);
 
-        let now = self.clock.now();
         {
             let labels = [peer_msg.msg_variant()];
             metrics::PEER_MESSAGE_RECEIVED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
             metrics::PEER_MESSAGE_RECEIVED_BY_TYPE_BYTES
                 .with_label_values(&labels)
                 .inc_by(msg.len() as u64);
-            if !self.received_messages_rate_limits.is_allowed(&peer_msg, now) {
+            if !rate_limits_checked
+                && !self.received_messages_rate_limits.is_allowed(&peer_msg, now)
+            {
                 metrics::PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
                 tracing::debug!(target: "network", "Peer {} is being rate limited for message {}", self.peer_info, peer_msg.msg_variant());
                 return;
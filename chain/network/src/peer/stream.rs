@@ -1,24 +1,36 @@
+use crate::peer::noise;
 use crate::peer_manager::connection;
 use crate::stats::metrics;
 use crate::tcp;
 use actix::fut::future::wrap_future;
 use actix::AsyncContext as _;
+use borsh::BorshDeserialize as _;
 use bytesize::{GIB, MIB};
+use near_async::time;
+use near_crypto::{SecretKey, Signature};
+use near_primitives::network::PeerId;
 use std::io;
 use std::net::SocketAddr;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::io::AsyncReadExt as _;
 use tokio::io::AsyncWriteExt as _;
 
 /// Maximum size of network message in encoded format.
 /// We encode length as `u32`, and therefore maximum size can't be larger than `u32::MAX`.
-const NETWORK_MESSAGE_MAX_SIZE_BYTES: usize = 512 * MIB as usize;
+pub(crate) const NETWORK_MESSAGE_MAX_SIZE_BYTES: usize = 512 * MIB as usize;
 /// Maximum capacity of write buffer in bytes.
 const MAX_WRITE_BUFFER_CAPACITY_BYTES: usize = GIB as usize;
+/// Maximum size of a chunk of an encrypted frame, so that every chunk fits into
+/// a single Noise message.
+const ENCRYPTED_CHUNK_SIZE_BYTES: usize = noise::MAX_MESSAGE_LEN - noise::TAG_LEN;
+/// Prefix of the data signed by the node key to bind the Noise static key to it.
+const STATIC_KEY_SIGNATURE_PREFIX: &[u8] = b"near-network-noise-static-key:";
 
 type ReadHalf = tokio::io::ReadHalf<tokio::net::TcpStream>;
 type WriteHalf = tokio::io::WriteHalf<tokio::net::TcpStream>;
+type Reader = tokio::io::BufReader<ReadHalf>;
+type Writer = tokio::io::BufWriter<WriteHalf>;
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum SendError {
@@ -26,6 +38,8 @@ pub(crate) enum SendError {
     IO(#[source] io::Error),
     #[error("queue is full, got {got_bytes}B, max capacity is {want_max_bytes}")]
     QueueOverflow { got_bytes: usize, want_max_bytes: usize },
+    #[error("encryption: {0}")]
+    Encryption(#[source] noise::Error),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -34,6 +48,40 @@ pub(crate) enum RecvError {
     IO(#[source] io::Error),
     #[error("message too large: got {got_bytes}B, want <={want_max_bytes}B")]
     MessageTooLarge { got_bytes: usize, want_max_bytes: usize },
+    #[error("decryption: {0}")]
+    Decryption(#[source] noise::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum SessionError {
+    #[error("IO error: {0}")]
+    IO(#[source] io::Error),
+    #[error("noise: {0}")]
+    Noise(#[from] noise::Error),
+    #[error("timeout")]
+    Timeout,
+    #[error("invalid signature of the peer's static key")]
+    InvalidSignature,
+}
+
+/// Parameters of an encrypted session, see `FramedStream::start_session`.
+pub(crate) struct SessionSpec {
+    /// Whether this side starts the Noise handshake.
+    /// It should be the outbound side of the connection.
+    pub initiator: bool,
+    /// Key of this node, used to sign the Noise static key of this side.
+    pub node_key: SecretKey,
+    /// Id of the peer. The peer's Noise static key has to be signed by it.
+    pub peer_id: PeerId,
+    /// Binds the session to the connection, has to be the same on both sides.
+    pub prologue: Vec<u8>,
+    /// Maximum time for the Noise handshake to complete.
+    pub timeout: time::Duration,
+}
+
+enum SendItem {
+    Frame(Frame),
+    StartSession(SessionSpec),
 }
 
 #[derive(actix::Message, PartialEq, Eq, Clone, Debug)]
@@ -54,13 +102,17 @@ pub(crate) enum Error {
     Send(#[source] SendError),
     #[error("recv: {0}")]
     Recv(#[source] RecvError),
+    #[error("session: {0}")]
+    Session(#[source] SessionError),
 }
 
 pub(crate) struct FramedStream<Actor: actix::Actor> {
-    queue_send: tokio::sync::mpsc::UnboundedSender<Frame>,
+    queue_send: tokio::sync::mpsc::UnboundedSender<SendItem>,
     stats: Arc<connection::Stats>,
     send_buf_size_metric: Arc<metrics::IntGaugeGuard>,
     addr: actix::Addr<Actor>,
+    /// Set once the recv loop should hand the stream over for the Noise handshake.
+    session_expected: Arc<AtomicBool>,
 }
 
 impl<Actor> FramedStream<Actor>
@@ -76,6 +128,11 @@ where
     ) -> Self {
         let (tcp_recv, tcp_send) = tokio::io::split(stream.stream);
         let (queue_send, queue_recv) = tokio::sync::mpsc::unbounded_channel();
+        // The recv loop passes the read half of the stream to the send loop, which
+        // performs the Noise handshake and passes it back together with the recv keys.
+        let (reader_send, reader_recv) = tokio::sync::oneshot::channel();
+        let (session_send, session_recv) = tokio::sync::oneshot::channel();
+        let session_expected = Arc::new(AtomicBool::new(false));
         let send_buf_size_metric = Arc::new(metrics::MetricGuard::new(
             &*metrics::PEER_DATA_WRITE_BUFFER_SIZE,
             vec![stream.peer_addr.to_string()],
@@ -85,23 +142,35 @@ where
             let stats = stats.clone();
             let m = send_buf_size_metric.clone();
             async move {
-                if let Err(err) = Self::run_send_loop(tcp_send, queue_recv, stats, m).await {
-                    addr.do_send(Error::Send(SendError::IO(err)));
+                if let Err(err) =
+                    Self::run_send_loop(tcp_send, queue_recv, stats, m, reader_recv, session_send)
+                        .await
+                {
+                    addr.do_send(err);
                 }
             }
         }));
         ctx.spawn(wrap_future({
             let addr = ctx.address();
             let stats = stats.clone();
+            let session_expected = session_expected.clone();
             async move {
-                if let Err(err) =
-                    Self::run_recv_loop(stream.peer_addr, tcp_recv, addr.clone(), stats).await
+                if let Err(err) = Self::run_recv_loop(
+                    stream.peer_addr,
+                    tcp_recv,
+                    addr.clone(),
+                    stats,
+                    session_expected,
+                    reader_send,
+                    session_recv,
+                )
+                .await
                 {
                     addr.do_send(Error::Recv(err));
                 }
             }
         }));
-        Self { queue_send, stats, send_buf_size_metric, addr: ctx.address() }
+        Self { queue_send, stats, send_buf_size_metric, addr: ctx.address(), session_expected }
     }
 
     /// Pushes `msg` to the send queue.
@@ -125,7 +194,26 @@ where
                 want_max_bytes: MAX_WRITE_BUFFER_CAPACITY_BYTES,
             }));
         }
-        let _ = self.queue_send.send(frame);
+        let _ = self.queue_send.send(SendItem::Frame(frame));
+    }
+
+    /// Marks the frame currently being handled by Actor as the last plaintext frame
+    /// sent by the peer: it is followed by the Noise handshake and encrypted frames.
+    /// It has to be called from the Frame handler, so that the recv loop doesn't
+    /// read past the frame in plaintext.
+    pub fn expect_session(&self) {
+        self.session_expected.store(true, Ordering::Release);
+    }
+
+    /// Performs the Noise handshake right after the frames already queued are sent,
+    /// and encrypts all the frames queued afterwards.
+    /// The peer is expected to start the session at the matching point of the stream,
+    /// and `expect_session` should be called when handling the last plaintext frame
+    /// received from the peer.
+    /// Failures are reported to Actor as stream::Error::Session.
+    /// It can be called at most once.
+    pub fn start_session(&self, spec: SessionSpec) {
+        let _ = self.queue_send.send(SendItem::StartSession(spec));
     }
 
     /// Event loop receiving and processing messages.
@@ -141,9 +229,15 @@ where
         read: ReadHalf,
         addr: actix::Addr<Actor>,
         stats: Arc<connection::Stats>,
+        session_expected: Arc<AtomicBool>,
+        reader_send: tokio::sync::oneshot::Sender<Reader>,
+        session_recv: tokio::sync::oneshot::Receiver<(Reader, noise::CipherState)>,
     ) -> Result<(), RecvError> {
         const READ_BUFFER_CAPACITY: usize = 8 * 1024;
         let mut read = tokio::io::BufReader::with_capacity(READ_BUFFER_CAPACITY, read);
+        let mut reader_send = Some(reader_send);
+        let mut session_recv = Some(session_recv);
+        let mut cipher: Option<noise::CipherState> = None;
 
         let msg_size_metric =
             metrics::MetricGuard::new(&metrics::PEER_MSG_SIZE_BYTES, vec![peer_addr.to_string()]);
@@ -152,7 +246,15 @@ where
             vec![peer_addr.to_string()],
         );
         loop {
-            let n = read.read_u32_le().await.map_err(RecvError::IO)? as usize;
+            let n = match &mut cipher {
+                None => read.read_u32_le().await.map_err(RecvError::IO)? as usize,
+                Some(cipher) => {
+                    let mut header = [0; 4 + noise::TAG_LEN];
+                    read.read_exact(&mut header).await.map_err(RecvError::IO)?;
+                    let header = cipher.decrypt(&header).map_err(RecvError::Decryption)?;
+                    u32::from_le_bytes(header.try_into().unwrap()) as usize
+                }
+            };
             if n > NETWORK_MESSAGE_MAX_SIZE_BYTES {
                 return Err(RecvError::MessageTooLarge {
                     got_bytes: n,
@@ -161,9 +263,15 @@ where
             }
             msg_size_metric.observe(n as f64);
             buf_size_metric.set(n as i64);
-            let mut buf = vec![0; n];
             let t = metrics::PEER_MSG_READ_LATENCY.start_timer();
-            read.read_exact(&mut buf[..]).await.map_err(RecvError::IO)?;
+            let buf = match &mut cipher {
+                None => {
+                    let mut buf = vec![0; n];
+                    read.read_exact(&mut buf[..]).await.map_err(RecvError::IO)?;
+                    buf
+                }
+                Some(cipher) => read_encrypted_frame(&mut read, cipher, n).await?,
+            };
             t.observe_duration();
             buf_size_metric.set(0);
             stats.received_messages.fetch_add(1, Ordering::Relaxed);
@@ -173,32 +281,77 @@ where
                 // so we should just close the stream.
                 return Ok(());
             }
+            // Actor has handled the frame, so if it was the last plaintext frame,
+            // session_expected is already set.
+            if cipher.is_none() && session_expected.load(Ordering::Acquire) {
+                // If any of the channels is closed, the send loop has already stopped
+                // and reported the reason to Actor.
+                let Some(Ok(())) = reader_send.take().map(|send| send.send(read)) else {
+                    return Ok(());
+                };
+                let Ok((new_read, new_cipher)) = session_recv.take().unwrap().await else {
+                    return Ok(());
+                };
+                read = new_read;
+                cipher = Some(new_cipher);
+            }
         }
     }
     async fn run_send_loop(
         tcp_send: WriteHalf,
-        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<Frame>,
+        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<SendItem>,
         stats: Arc<connection::Stats>,
         buf_size_metric: Arc<metrics::IntGaugeGuard>,
-    ) -> io::Result<()> {
+        reader_recv: tokio::sync::oneshot::Receiver<Reader>,
+        session_send: tokio::sync::oneshot::Sender<(Reader, noise::CipherState)>,
+    ) -> Result<(), Error> {
         const WRITE_BUFFER_CAPACITY: usize = 8 * 1024;
         let mut writer = tokio::io::BufWriter::with_capacity(WRITE_BUFFER_CAPACITY, tcp_send);
-        while let Some(Frame(mut msg)) = queue_recv.recv().await {
+        let mut session_channels = Some((reader_recv, session_send));
+        let mut cipher: Option<noise::CipherState> = None;
+        while let Some(mut item) = queue_recv.recv().await {
             // Try writing a batch of messages and flush once at the end.
             loop {
-                // TODO(gprusak): sending a too large message should probably be treated as a bug,
-                // since dropping messages may lead to hard-to-debug high-level issues.
-                if msg.len() > NETWORK_MESSAGE_MAX_SIZE_BYTES {
-                    metrics::MessageDropped::InputTooLong.inc_unknown_msg();
-                } else {
-                    writer.write_u32_le(msg.len() as u32).await?;
-                    writer.write_all(&msg[..]).await?;
+                match item {
+                    SendItem::Frame(Frame(msg)) => {
+                        // TODO(gprusak): sending a too large message should probably be treated as
+                        // a bug, since dropping messages may lead to hard-to-debug high-level
+                        // issues.
+                        if msg.len() > NETWORK_MESSAGE_MAX_SIZE_BYTES {
+                            metrics::MessageDropped::InputTooLong.inc_unknown_msg();
+                        } else {
+                            write_frame(&mut writer, cipher.as_mut(), &msg)
+                                .await
+                                .map_err(Error::Send)?;
+                        }
+                        stats.messages_to_send.fetch_sub(1, Ordering::Release);
+                        stats.bytes_to_send.fetch_sub(msg.len() as u64, Ordering::Release);
+                        buf_size_metric.sub(msg.len() as i64);
+                    }
+                    SendItem::StartSession(spec) => {
+                        let (reader_recv, session_send) =
+                            session_channels.take().expect("start_session called twice");
+                        writer.flush().await.map_err(|err| Error::Send(SendError::IO(err)))?;
+                        // Wait for the recv loop to reach the end of the plaintext frames.
+                        // If it has stopped, the reason has been already reported to Actor.
+                        let Ok(mut reader) = reader_recv.await else {
+                            return Ok(());
+                        };
+                        let transport = tokio::time::timeout(
+                            spec.timeout.unsigned_abs(),
+                            run_session_handshake(&mut writer, &mut reader, &spec),
+                        )
+                        .await
+                        .map_err(|_| Error::Session(SessionError::Timeout))?
+                        .map_err(Error::Session)?;
+                        if session_send.send((reader, transport.recv)).is_err() {
+                            return Ok(());
+                        }
+                        cipher = Some(transport.send);
+                    }
                 }
-                stats.messages_to_send.fetch_sub(1, Ordering::Release);
-                stats.bytes_to_send.fetch_sub(msg.len() as u64, Ordering::Release);
-                buf_size_metric.sub(msg.len() as i64);
-                msg = match queue_recv.try_recv() {
-                    Ok(Frame(it)) => it,
+                item = match queue_recv.try_recv() {
+                    Ok(it) => it,
                     Err(_) => break,
                 };
             }
@@ -208,8 +361,106 @@ where
             // and added to the queue at a rate similar to flush latency. To fix that
             // we would need to put writer.flush() and queue_recv.recv() into a tokio::select
             // and make sure that both are cancellation-safe.
-            writer.flush().await?;
+            writer.flush().await.map_err(|err| Error::Send(SendError::IO(err)))?;
         }
         Ok(())
     }
 }
+
+async fn write_frame(
+    writer: &mut Writer,
+    cipher: Option<&mut noise::CipherState>,
+    msg: &[u8],
+) -> Result<(), SendError> {
+    let Some(cipher) = cipher else {
+        writer.write_u32_le(msg.len() as u32).await.map_err(SendError::IO)?;
+        return writer.write_all(msg).await.map_err(SendError::IO);
+    };
+    // The length of the frame is encrypted as well, so that it cannot be tampered with.
+    let header =
+        cipher.encrypt(&(msg.len() as u32).to_le_bytes()).map_err(SendError::Encryption)?;
+    writer.write_all(&header).await.map_err(SendError::IO)?;
+    for chunk in msg.chunks(ENCRYPTED_CHUNK_SIZE_BYTES) {
+        let chunk = cipher.encrypt(chunk).map_err(SendError::Encryption)?;
+        writer.write_all(&chunk).await.map_err(SendError::IO)?;
+    }
+    Ok(())
+}
+
+async fn read_encrypted_frame(
+    read: &mut Reader,
+    cipher: &mut noise::CipherState,
+    n: usize,
+) -> Result<Vec<u8>, RecvError> {
+    let mut buf = Vec::with_capacity(n);
+    let mut chunk = vec![0; noise::MAX_MESSAGE_LEN];
+    while buf.len() < n {
+        let len = (n - buf.len()).min(ENCRYPTED_CHUNK_SIZE_BYTES) + noise::TAG_LEN;
+        read.read_exact(&mut chunk[..len]).await.map_err(RecvError::IO)?;
+        buf.extend(cipher.decrypt(&chunk[..len]).map_err(RecvError::Decryption)?);
+    }
+    Ok(buf)
+}
+
+fn static_key_signature_data(static_key: &[u8]) -> Vec<u8> {
+    [STATIC_KEY_SIGNATURE_PREFIX, static_key].concat()
+}
+
+fn verify_static_key(
+    peer_id: &PeerId,
+    static_key: Option<&[u8]>,
+    payload: &[u8],
+) -> Result<(), SessionError> {
+    let signature =
+        Signature::try_from_slice(payload).map_err(|_| SessionError::InvalidSignature)?;
+    let static_key = static_key.ok_or(SessionError::InvalidSignature)?;
+    if !signature.verify(&static_key_signature_data(static_key), peer_id.public_key()) {
+        return Err(SessionError::InvalidSignature);
+    }
+    Ok(())
+}
+
+async fn write_handshake_message(writer: &mut Writer, msg: &[u8]) -> Result<(), SessionError> {
+    writer.write_u32_le(msg.len() as u32).await.map_err(SessionError::IO)?;
+    writer.write_all(msg).await.map_err(SessionError::IO)?;
+    writer.flush().await.map_err(SessionError::IO)
+}
+
+async fn read_handshake_message(reader: &mut Reader) -> Result<Vec<u8>, SessionError> {
+    let n = reader.read_u32_le().await.map_err(SessionError::IO)? as usize;
+    if n > noise::MAX_MESSAGE_LEN {
+        return Err(noise::Error::MessageTooLong {
+            got_bytes: n,
+            want_max_bytes: noise::MAX_MESSAGE_LEN,
+        }
+        .into());
+    }
+    let mut msg = vec![0; n];
+    reader.read_exact(&mut msg).await.map_err(SessionError::IO)?;
+    Ok(msg)
+}
+
+/// Performs the Noise_XX handshake. Each side sends the signature of its Noise
+/// static key made with its node key, which authenticates the session.
+async fn run_session_handshake(
+    writer: &mut Writer,
+    reader: &mut Reader,
+    spec: &SessionSpec,
+) -> Result<noise::Transport, SessionError> {
+    let mut state = noise::HandshakeState::new(spec.initiator, &spec.prologue)?;
+    let signature =
+        borsh::to_vec(&spec.node_key.sign(&static_key_signature_data(state.local_static())))
+            .unwrap();
+    if spec.initiator {
+        write_handshake_message(writer, &state.write_message(&[])?).await?;
+        let payload = state.read_message(&read_handshake_message(reader).await?)?;
+        verify_static_key(&spec.peer_id, state.remote_static(), &payload)?;
+        write_handshake_message(writer, &state.write_message(&signature)?).await?;
+    } else {
+        state.read_message(&read_handshake_message(reader).await?)?;
+        write_handshake_message(writer, &state.write_message(&signature)?).await?;
+        let payload = state.read_message(&read_handshake_message(reader).await?)?;
+        verify_static_key(&spec.peer_id, state.remote_static(), &payload)?;
+    }
+    Ok(state.into_transport()?)
+}
@@ -1,3 +1,4 @@
+use crate::config::MessageCompressionConfig;
 use crate::network_protocol::testonly as data;
 use crate::network_protocol::{
     Encoding, Handshake, HandshakeFailureReason, PartialEdgeInfo, PeerMessage, PeersRequest,
@@ -19,23 +20,31 @@ use std::sync::Arc;
 async fn test_peer_communication(
     outbound_encoding: Option<Encoding>,
     inbound_encoding: Option<Encoding>,
+    message_compression: bool,
 ) -> anyhow::Result<()> {
-    tracing::info!("test_peer_communication({outbound_encoding:?},{inbound_encoding:?})");
+    tracing::info!(
+        "test_peer_communication({outbound_encoding:?},{inbound_encoding:?},{message_compression})"
+    );
 
     let mut rng = make_rng(89028037453);
     let mut clock = time::FakeClock::default();
 
     let chain = Arc::new(data::Chain::make(&mut clock, &mut rng, 12));
-    let inbound_cfg = PeerConfig {
+    // Compress all the messages which can be compressed.
+    let compression_cfg =
+        MessageCompressionConfig { enabled: message_compression, threshold_bytes: 0, level: 3 };
+    let mut inbound_cfg = PeerConfig {
         chain: chain.clone(),
         network: chain.make_config(&mut rng),
         force_encoding: inbound_encoding,
     };
-    let outbound_cfg = PeerConfig {
+    inbound_cfg.network.message_compression = compression_cfg.clone();
+    let mut outbound_cfg = PeerConfig {
         chain: chain.clone(),
         network: chain.make_config(&mut rng),
         force_encoding: outbound_encoding,
     };
+    outbound_cfg.network.message_compression = compression_cfg;
     let (outbound_stream, inbound_stream) =
         tcp::Stream::loopback(inbound_cfg.id(), tcp::Tier::T2).await;
     let mut inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
@@ -171,9 +180,11 @@ async fn peer_communication() -> anyhow::Result<()> {
                     continue;
                 }
             }
-            test_peer_communication(*outbound, *inbound)
-                .await
-                .with_context(|| format!("(outbound={outbound:?},inbound={inbound:?})"))?;
+            for compression in [false, true] {
+                test_peer_communication(*outbound, *inbound, compression).await.with_context(
+                    || format!("(outbound={outbound:?},inbound={inbound:?},{compression})"),
+                )?;
+            }
         }
     }
     Ok(())
@@ -211,6 +222,9 @@ async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: O
         partial_edge_info: outbound_cfg
             .partial_edge_info(&inbound.cfg.id(), Edge::create_fresh_nonce(&clock.clock())),
         owned_account: None,
+        encrypted_transport: false,
+        message_compression: false,
+        encrypted_transport_signature: None,
     };
     // We will also introduce chain_id mismatch, but ProtocolVersionMismatch is expected to take priority.
     handshake.sender_chain_info.genesis_id.chain_id = "unknown_chain".to_string();
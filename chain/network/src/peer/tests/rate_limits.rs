@@ -86,6 +86,42 @@ async fn slow_test_message_rate_limits_over_time() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+// Verifies that the rate limits of compressed messages don't apply to the same messages received
+// uncompressed.
+async fn test_uncompressed_block_burst_is_not_rate_limited() -> anyhow::Result<()> {
+    init_test_logger();
+    tracing::info!("test_uncompressed_block_burst_is_not_rate_limited");
+
+    let mut clock = FakeClock::default();
+    let mut rng = make_rng(89028037453);
+    let (outbound, inbound) = setup_test_peers_with(&mut clock, &mut rng, |mut network_config| {
+        network_config.received_messages_rate_limits = messages_limits::Config::standard_preset();
+        network_config.received_compressed_messages_rate_limits =
+            messages_limits::Config::compressed_preset();
+        network_config
+    })
+    .await;
+
+    // Above the burst of blocks allowed compressed.
+    const MESSAGES: u32 = 250;
+    let maximum_size = messages_limits::Config::compressed_preset().rate_limits
+        [&messages_limits::RateLimitedPeerMessageKey::Block]
+        .maximum_size;
+    assert!(MESSAGES > maximum_size);
+    let mut events = inbound.events.from_now();
+    let message = PeerMessage::Block(outbound.cfg.chain.blocks[5].clone());
+    for _ in 0..MESSAGES {
+        outbound.send(message.clone()).await;
+    }
+
+    let messages_received =
+        wait_for_similar_messages(&[message], &mut events, Duration::from_secs(3)).await;
+    assert_eq!(messages_received[0], MESSAGES);
+
+    Ok(())
+}
+
 /// Waits up to `duration` and then checks how many events equal (in type only) to each one of `samples`
 /// have been received.
 ///
@@ -117,9 +153,7 @@ async fn wait_for_similar_messages(
 /// Rate limits configuration:
 /// - `BlockRequest`, `PartialEncodedChunkRequest`: bucket_start = 5, bucket_max = 10, refill_rate = 2.5/s
 /// - `Transaction`: bucket_start = bucket_max = 50, refill_rate = 5/s
-async fn setup_test_peers(clock: &mut FakeClock, mut rng: &mut Rng) -> (PeerHandle, PeerHandle) {
-    let chain = Arc::new(data::Chain::make(clock, &mut rng, 12));
-
+async fn setup_test_peers(clock: &mut FakeClock, rng: &mut Rng) -> (PeerHandle, PeerHandle) {
     // Customize the network configuration to set some arbitrary rate limits.
     let add_rate_limits = |mut network_config: NetworkConfig| {
         let rate_limits = &mut network_config.received_messages_rate_limits.rate_limits;
@@ -133,15 +167,25 @@ async fn setup_test_peers(clock: &mut FakeClock, mut rng: &mut Rng) -> (PeerHand
         rate_limits.insert(Transaction, messages_limits::SingleMessageConfig::new(50, 5.0, None));
         network_config
     };
+    setup_test_peers_with(clock, rng, add_rate_limits).await
+}
+
+/// Setup two connected peers, with the network configuration customized by `configure`.
+async fn setup_test_peers_with(
+    clock: &mut FakeClock,
+    mut rng: &mut Rng,
+    configure: impl Fn(NetworkConfig) -> NetworkConfig,
+) -> (PeerHandle, PeerHandle) {
+    let chain = Arc::new(data::Chain::make(clock, &mut rng, 12));
 
     let inbound_cfg = PeerConfig {
         chain: chain.clone(),
-        network: add_rate_limits(chain.make_config(&mut rng)),
+        network: configure(chain.make_config(&mut rng)),
         force_encoding: Some(Encoding::Proto),
     };
     let outbound_cfg = PeerConfig {
         chain: chain.clone(),
-        network: add_rate_limits(chain.make_config(&mut rng)),
+        network: configure(chain.make_config(&mut rng)),
         force_encoding: Some(Encoding::Proto),
     };
     let (outbound_stream, inbound_stream) =
@@ -1,6 +1,8 @@
 use crate::peer::transfer_stats::TransferStats;
+use lru::LruCache;
 use near_async::time;
 use near_primitives::hash::CryptoHash;
+use std::num::NonZeroUsize;
 
 /// Maximum number of requests and responses to track.
 const MAX_TRACK_SIZE: usize = 30;
@@ -47,6 +49,8 @@ pub(crate) struct Tracker {
     pub(crate) received_bytes: TransferStats,
     /// Sent requests.
     requested: CircularUniqueQueue,
+    /// Times at which the requests were sent, to measure the latency of the responses.
+    request_sent_at: LruCache<CryptoHash, time::Instant>,
     /// Received elements.
     received: CircularUniqueQueue,
 }
@@ -57,6 +61,7 @@ impl Default for Tracker {
             sent_bytes: TransferStats::default(),
             received_bytes: TransferStats::default(),
             requested: CircularUniqueQueue::new(MAX_TRACK_SIZE),
+            request_sent_at: LruCache::new(NonZeroUsize::new(MAX_TRACK_SIZE).unwrap()),
             received: CircularUniqueQueue::new(MAX_TRACK_SIZE),
         }
     }
@@ -86,8 +91,19 @@ impl Tracker {
         self.requested.contains(hash)
     }
 
-    pub(crate) fn push_request(&mut self, hash: CryptoHash) {
+    pub(crate) fn push_request(&mut self, hash: CryptoHash, now: time::Instant) {
         self.requested.push(hash);
+        self.request_sent_at.put(hash, now);
+    }
+
+    /// Returns the time elapsed since the request for `hash` was sent,
+    /// and stops tracking it, so that the latency is measured only for the first response.
+    pub(crate) fn pop_request_latency(
+        &mut self,
+        hash: &CryptoHash,
+        now: time::Instant,
+    ) -> Option<time::Duration> {
+        self.request_sent_at.pop(hash).map(|sent_at| now - sent_at)
     }
 }
 
@@ -165,4 +181,20 @@ mod tests {
         }
         assert!(q.contains(&hash(&[5])));
     }
+
+    #[test]
+    fn test_request_latency() {
+        let clock = time::FakeClock::default();
+        let mut tracker = Tracker::default();
+        tracker.push_request(hash(&[1]), clock.now());
+        clock.advance(time::Duration::milliseconds(300));
+        assert!(tracker.has_request(&hash(&[1])));
+        assert_eq!(
+            tracker.pop_request_latency(&hash(&[1]), clock.now()),
+            Some(time::Duration::milliseconds(300))
+        );
+        // Only the first response is measured.
+        assert_eq!(tracker.pop_request_latency(&hash(&[1]), clock.now()), None);
+        assert_eq!(tracker.pop_request_latency(&hash(&[2]), clock.now()), None);
+    }
 }
@@ -143,6 +143,8 @@ pub(crate) struct Connection {
 
     /// Who started connection. Inbound (other) or Outbound (us).
     pub peer_type: PeerType,
+    /// Whether the traffic of the connection is encrypted.
+    pub encrypted: bool,
     /// Time where the connection was established.
     pub established_time: time::Instant,
 
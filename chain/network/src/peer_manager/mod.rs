@@ -2,6 +2,7 @@ pub(crate) mod connection;
 pub(crate) mod connection_store;
 pub(crate) mod network_state;
 pub(crate) mod peer_manager_actor;
+pub(crate) mod peer_score;
 pub(crate) mod peer_store;
 
 #[cfg(test)]
@@ -15,6 +15,7 @@ use crate::peer::peer_actor::ClosingReason;
 use crate::peer::peer_actor::PeerActor;
 use crate::peer_manager::connection;
 use crate::peer_manager::connection_store;
+use crate::peer_manager::peer_score;
 use crate::peer_manager::peer_store;
 use crate::private_actix::RegisterPeerError;
 use crate::routing::route_back_cache::RouteBackCache;
@@ -70,6 +71,10 @@ pub const PRUNE_EDGES_AFTER: time::Duration = time::Duration::minutes(30);
 /// How long to wait between reconnection attempts to the same peer
 pub(crate) const RECONNECT_ATTEMPT_INTERVAL: time::Duration = time::Duration::seconds(10);
 
+/// If peer scoring is enabled, we connect to the highest scored peer among this many
+/// randomly picked candidates.
+const CONNECT_CANDIDATES: usize = 3;
+
 impl WhitelistNode {
     pub fn from_peer_info(pi: &PeerInfo) -> anyhow::Result<Self> {
         Ok(Self {
@@ -132,6 +137,8 @@ pub(crate) struct NetworkState {
     pub snapshot_hosts: Arc<SnapshotHostsCache>,
     /// Connection store that provides read/write access to stored connections.
     pub connection_store: connection_store::ConnectionStore,
+    /// Reputation scores of peers.
+    pub peer_scores: peer_score::PeerScores,
     /// List of peers to which we should re-establish a connection
     pub pending_reconnect: Mutex<Vec<PeerInfo>>,
     /// A graph of the whole NEAR network.
@@ -214,6 +221,7 @@ impl NetworkState {
             peer_store,
             snapshot_hosts: Arc::new(SnapshotHostsCache::new(config.snapshot_hosts.clone())),
             connection_store: connection_store::ConnectionStore::new(store.clone()).unwrap(),
+            peer_scores: peer_score::PeerScores::new(config.peer_score.clone(), store.clone()),
             pending_reconnect: Mutex::new(Vec::<PeerInfo>::new()),
             accounts_data: Arc::new(AccountDataCache::new()),
             account_announcements: Arc::new(AnnounceAccountCache::new(store)),
@@ -251,6 +259,8 @@ impl NetworkState {
 
     /// Stops peer instance if it is still connected,
     /// and then mark peer as banned in the peer store.
+    /// If peer scoring is enabled, the peer is only penalized, unless its score drops
+    /// below the ban threshold.
     pub fn disconnect_and_ban(
         &self,
         clock: &time::Clock,
@@ -259,8 +269,9 @@ impl NetworkState {
     ) {
         let tier2 = self.tier2.load();
         if let Some(peer) = tier2.ready.get(peer_id) {
+            // PeerActor applies the penalty before deciding whether to ban the peer.
             peer.stop(Some(ban_reason));
-        } else {
+        } else if self.peer_scores.should_ban(clock, peer_id, ban_reason) {
             if let Err(err) = self.peer_store.peer_ban(clock, peer_id, ban_reason) {
                 tracing::debug!(target: "network", ?err, "Failed to save peer data");
             }
@@ -278,6 +289,57 @@ impl NetworkState {
             .any(|wn| wn.account_id.is_none() || wn.account_id == peer_info.account_id)
     }
 
+    /// If peer scoring is enabled, returns the connected peer with the lowest score,
+    /// which can be disconnected to make room for an inbound connection from `peer_info`.
+    /// A peer qualifies only if `peer_info` has a sufficiently higher score
+    /// and it is not whitelisted.
+    pub(crate) fn peer_to_evict(
+        &self,
+        clock: &time::Clock,
+        peer_info: &PeerInfo,
+    ) -> Option<Arc<connection::Connection>> {
+        if !self.peer_scores.enabled() || self.config.inbound_disabled {
+            return None;
+        }
+        let score = self.peer_scores.score(clock, &peer_info.id);
+        let (lowest, lowest_score) = (self.tier2.load().ready.values())
+            .filter(|p| !self.is_peer_whitelisted(&p.peer_info))
+            .map(|p| (p.clone(), self.peer_scores.score(clock, &p.peer_info.id)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        if lowest_score + peer_score::EVICTION_SCORE_MARGIN <= score {
+            Some(lowest)
+        } else {
+            None
+        }
+    }
+
+    /// Picks an unconnected peer to establish an outbound connection to.
+    /// If peer scoring is enabled, picks the highest scored of a few random candidates.
+    pub(crate) fn peer_to_connect(
+        &self,
+        clock: &time::Clock,
+        prefer_previously_connected_peer: bool,
+    ) -> Option<PeerInfo> {
+        let tier2 = self.tier2.load();
+        let node_id = self.config.node_id();
+        let candidates = self.peer_store.unconnected_peers(
+            |peer_state| {
+                // Ignore connecting to ourself
+                node_id == peer_state.peer_info.id
+                || self.config.node_addr.as_ref().map(|a| **a) == peer_state.peer_info.addr
+                // Or to peers we are currently trying to connect to
+                || tier2.outbound_handshakes.contains(&peer_state.peer_info.id)
+            },
+            prefer_previously_connected_peer,
+            if self.peer_scores.enabled() { CONNECT_CANDIDATES } else { 1 },
+        );
+        candidates.into_iter().max_by(|a, b| {
+            let a = self.peer_scores.score(clock, &a.id);
+            let b = self.peer_scores.score(clock, &b.id);
+            a.total_cmp(&b)
+        })
+    }
+
     /// predicate checking whether we should allow an inbound connection from peer_info.
     fn is_inbound_allowed(&self, peer_info: &PeerInfo) -> bool {
         // Check if we have spare inbound connections capacity.
@@ -342,14 +404,24 @@ impl NetworkState {
                 tcp::Tier::T2 => {
                     if conn.peer_type == PeerType::Inbound {
                         if !this.is_inbound_allowed(&peer_info) {
-                            // TODO(1896): Gracefully drop inbound connection for other peer.
-                            let tier2 = this.tier2.load();
-                            tracing::debug!(target: "network",
-                                tier2 = tier2.ready.len(), outgoing_peers = tier2.outbound_handshakes.len(),
-                                max_num_peers = this.config.max_num_peers,
-                                "Dropping handshake (network at max capacity)."
-                            );
-                            return Err(RegisterPeerError::ConnectionLimitExceeded);
+                            // Evict a connected peer if the new one has a much higher score.
+                            if let Some(evicted) = this.peer_to_evict(&clock, &peer_info) {
+                                tracing::debug!(target: "network",
+                                    evicted = ?evicted.peer_info.id, new = ?peer_info.id,
+                                    "Evicting the lowest scored peer (network at max capacity)."
+                                );
+                                this.tier2.remove(&evicted);
+                                evicted.stop(None);
+                            } else {
+                                // TODO(1896): Gracefully drop inbound connection for other peer.
+                                let tier2 = this.tier2.load();
+                                tracing::debug!(target: "network",
+                                    tier2 = tier2.ready.len(), outgoing_peers = tier2.outbound_handshakes.len(),
+                                    max_num_peers = this.config.max_num_peers,
+                                    "Dropping handshake (network at max capacity)."
+                                );
+                                return Err(RegisterPeerError::ConnectionLimitExceeded);
+                            }
                         }
                     }
                     // First verify and broadcast the edge of the connection, so that in case
@@ -41,7 +41,7 @@ use rand::seq::{IteratorRandom, SliceRandom};
 use rand::thread_rng;
 use rand::Rng;
 use std::cmp::min;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tracing::Instrument as _;
@@ -85,6 +85,8 @@ const PREFER_PREVIOUSLY_CONNECTED_PEER: f64 = 0.6;
 
 /// How often to update the connections in storage.
 pub(crate) const UPDATE_CONNECTION_STORE_INTERVAL: time::Duration = time::Duration::minutes(1);
+/// How often to save the peer scores in storage.
+const SAVE_PEER_SCORES_INTERVAL: time::Duration = time::Duration::minutes(1);
 /// How often to poll the NetworkState for closed connections we'd like to re-establish.
 pub(crate) const POLL_CONNECTION_STORE_INTERVAL: time::Duration = time::Duration::minutes(1);
 
@@ -94,8 +96,6 @@ const TIER3_IDLE_TIMEOUT: time::Duration = time::Duration::seconds(15);
 /// Actor that manages peers connections.
 pub struct PeerManagerActor {
     pub(crate) clock: time::Clock,
-    /// Peer information for this node.
-    my_peer_id: PeerId,
     /// Flag that track whether we started attempts to establish outbound connections.
     started_connect_attempts: bool,
 
@@ -187,6 +187,17 @@ impl actix::Actor for PeerManagerActor {
             }
         }));
 
+        // Periodically save the peer scores.
+        let clock = self.clock.clone();
+        let state = self.state.clone();
+        ctx.spawn(wrap_future(async move {
+            let mut interval = time::Interval::new(clock.now(), SAVE_PEER_SCORES_INTERVAL);
+            loop {
+                interval.tick(&clock).await;
+                state.peer_scores.save(&clock);
+            }
+        }));
+
         // Periodically prints bandwidth stats for each peer.
         self.report_bandwidth_stats_trigger(ctx, REPORT_BANDWIDTH_STATS_TRIGGER_INTERVAL);
 
@@ -197,6 +208,7 @@ impl actix::Actor for PeerManagerActor {
     /// Try to gracefully disconnect from connected peers.
     fn stopping(&mut self, _ctx: &mut Self::Context) -> actix::Running {
         tracing::warn!("PeerManager: stopping");
+        self.state.peer_scores.save(&self.clock);
         self.state.tier2.broadcast_message(Arc::new(PeerMessage::Disconnect(Disconnect {
             remove_from_connection_store: false,
         })));
@@ -236,7 +248,6 @@ impl PeerManagerActor {
             }
             v
         };
-        let my_peer_id = config.node_id();
         let arbiter = actix::Arbiter::new().handle();
         let clock = clock;
         let state = Arc::new(NetworkState::new(
@@ -348,7 +359,6 @@ impl PeerManagerActor {
             }
         });
         Ok(Self::start_in_arbiter(&arbiter, move |_ctx| Self {
-            my_peer_id: my_peer_id.clone(),
             started_connect_attempts: false,
             state,
             clock,
@@ -416,7 +426,9 @@ impl PeerManagerActor {
             && !self.state.config.outbound_disabled
     }
 
-    /// Returns peers close to the highest height
+    /// Returns peers close to the highest height.
+    /// If peer scoring is enabled, peers with low scores are left out,
+    /// unless there are no other peers close to the highest height.
     fn highest_height_peers(&self) -> Vec<HighestHeightPeerInfo> {
         let infos: Vec<HighestHeightPeerInfo> = self
             .state
@@ -433,13 +445,17 @@ impl PeerManagerActor {
             None => return vec![],
         };
         // Find all peers whose height is within `highest_peer_horizon` from max height peer(s).
-        infos
+        let (infos, deprioritized): (Vec<_>, Vec<_>) = infos
             .into_iter()
             .filter(|i| {
                 i.highest_block_height.saturating_add(self.state.config.highest_peer_horizon)
                     >= max_height
             })
-            .collect()
+            .partition(|i| !self.state.peer_scores.is_deprioritized(&self.clock, &i.peer_info.id));
+        if infos.is_empty() {
+            return deprioritized;
+        }
+        infos
     }
 
     // Get peers that are potentially unreliable and we should avoid routing messages through them.
@@ -550,7 +566,18 @@ impl PeerManagerActor {
 
         // Build valid candidate list to choose the peer to be removed. All peers outside the safe set.
         let candidates = tier2.ready.values().filter(|p| !safe_set.contains(&p.peer_info.id));
-        if let Some(p) = candidates.choose(&mut rand::thread_rng()) {
+        // If peer scoring is enabled, remove the lowest scored peer. Otherwise a random one.
+        let peer_scores = &self.state.peer_scores;
+        let to_stop = if peer_scores.enabled() {
+            candidates.min_by(|a, b| {
+                let a = peer_scores.score(&self.clock, &a.peer_info.id);
+                let b = peer_scores.score(&self.clock, &b.peer_info.id);
+                a.total_cmp(&b)
+            })
+        } else {
+            candidates.choose(&mut rand::thread_rng())
+        };
+        if let Some(p) = to_stop {
             tracing::debug!(target: "network", id = ?p.peer_info.id,
                 tier2_len = tier2.ready.len(),
                 ideal_connections_hi = self.state.config.ideal_connections_hi,
@@ -607,20 +634,12 @@ impl PeerManagerActor {
         self.state.peer_store.update(&self.clock);
 
         if self.is_outbound_bootstrap_needed() {
-            let tier2 = self.state.tier2.load();
             // With some odds - try picking one of the 'NotConnected' peers -- these are the ones that we were able to connect to in the past.
             let prefer_previously_connected_peer =
                 thread_rng().gen_bool(PREFER_PREVIOUSLY_CONNECTED_PEER);
-            if let Some(peer_info) = self.state.peer_store.unconnected_peer(
-                |peer_state| {
-                    // Ignore connecting to ourself
-                    self.my_peer_id == peer_state.peer_info.id
-                    || self.state.config.node_addr.as_ref().map(|a|**a) == peer_state.peer_info.addr
-                    // Or to peers we are currently trying to connect to
-                    || tier2.outbound_handshakes.contains(&peer_state.peer_info.id)
-                },
-                prefer_previously_connected_peer,
-            ) {
+            if let Some(peer_info) =
+                self.state.peer_to_connect(&self.clock, prefer_previously_connected_peer)
+            {
                 // Start monitor_peers_attempts from start after we discover the first healthy peer
                 if !self.started_connect_attempts {
                     self.started_connect_attempts = true;
@@ -1326,6 +1345,10 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
     fn handle(&mut self, msg: GetDebugStatus, _ctx: &mut actix::Context<Self>) -> Self::Result {
         match msg {
             GetDebugStatus::PeerStore => {
+                let peer_scores: HashMap<_, _> = (self.state.peer_scores.load(&self.clock))
+                    .into_iter()
+                    .map(|s| (s.peer_id.clone(), s))
+                    .collect();
                 let mut peer_states_view = self
                     .state
                     .peer_store
@@ -1346,6 +1369,7 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
                                 (attempt_time.unix_timestamp(), foo)
                             },
                         ),
+                        score: peer_scores.get(peer_id).map(Into::into),
                     })
                     .collect::<Vec<_>>();
 
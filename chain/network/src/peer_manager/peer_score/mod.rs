@@ -0,0 +1,369 @@
+//! Reputation scores of peers.
+//!
+//! Every peer accumulates a score from the events observed on its connection:
+//!     - responses to our requests (blocks, headers, state parts), which are rewarded if they
+//!       contain the requested data and penalized otherwise,
+//!     - the latency of the responses to our block requests,
+//!     - routed messages sent by the peer, which are rewarded if we were able to forward them
+//!       and penalized if their TTL has run out. Messages addressed to us are not rewarded,
+//!       since the peer may have sent them just for the reward, and the reward of forwarded
+//!       messages is capped per time window,
+//!     - protocol violations (see `ReasonForBan`), which are penalized depending on how likely
+//!       they are to be caused by an honest peer. Violations an honest peer never commits
+//!       cost more than the whole range of the scores.
+//!
+//! Scores are kept within [MIN_SCORE, MAX_SCORE] and decay exponentially towards 0,
+//! so that old events are eventually forgotten. They are saved to the DB periodically.
+//!
+//! Routing events are observed for every routed message, so they are counted in sharded
+//! buffers and applied to the scores in batches, whenever the scores are read.
+//!
+//! If `PeerScoreConfig::enabled` is set, a protocol violation bans the peer only if its score
+//! drops below `PeerScoreConfig::ban_threshold`, and the scores are used to pick the peers
+//! to connect to, to disconnect from and to send sync requests to. Otherwise no events are
+//! recorded and every protocol violation bans the peer.
+use crate::config::PeerScoreConfig;
+use crate::store;
+use crate::types::{PeerScore, ReasonForBan};
+use near_async::time;
+use near_primitives::network::PeerId;
+use near_primitives::views::PeerScoreView;
+use parking_lot::{Mutex, MutexGuard};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+
+#[cfg(test)]
+mod tests;
+
+/// Upper bound of the scores.
+pub(crate) const MAX_SCORE: f64 = 100.;
+/// Lower bound of the scores.
+pub(crate) const MIN_SCORE: f64 = -100.;
+
+const USEFUL_RESPONSE_REWARD: f64 = 1.;
+const USELESS_RESPONSE_PENALTY: f64 = 2.;
+const SLOW_RESPONSE_PENALTY: f64 = 1.;
+const ROUTING_SUCCESS_REWARD: f64 = 0.1;
+const ROUTING_FAILURE_PENALTY: f64 = 0.5;
+/// Maximal reward for forwarded messages of a peer within `ROUTING_REWARD_WINDOW`,
+/// so that a peer can't make up for violations by sending many cheap messages.
+const MAX_ROUTING_REWARD: f64 = 1.;
+const ROUTING_REWARD_WINDOW: time::Duration = time::Duration::minutes(10);
+
+/// Weight of the latest sample in the moving average of the response latency.
+const LATENCY_AVERAGE_WEIGHT: f64 = 0.2;
+
+/// An inbound peer may replace a connected peer once the connection limit is reached,
+/// only if its score is higher by at least this much.
+pub(crate) const EVICTION_SCORE_MARGIN: f64 = 10.;
+
+/// Maximal number of stored scores. If exceeded, the scores closest to 0 are dropped.
+const MAX_STORED_SCORES: usize = 1000;
+
+/// Number of buffers the routing events are counted in before they are applied to the scores.
+const ROUTING_SHARDS: usize = 16;
+
+/// An event observed on a connection, which affects the score of the peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Event {
+    /// The peer responded to our request with the requested data.
+    UsefulResponse,
+    /// The peer responded to our request without the requested data.
+    UselessResponse,
+    /// The peer responded to our block request after the given time.
+    ResponseLatency(time::Duration),
+    /// We have forwarded a routed message sent by the peer.
+    RoutingSuccess,
+    /// The peer sent us a routed message with exhausted TTL.
+    RoutingFailure,
+    /// The peer violated the protocol.
+    ProtocolViolation(ReasonForBan),
+}
+
+/// Penalty for a protocol violation.
+fn violation_penalty(reason: ReasonForBan) -> f64 {
+    match reason {
+        ReasonForBan::None => 0.,
+        // An honest peer may send us a header late or not have the headers we asked for.
+        ReasonForBan::ProvidedNotEnoughHeaders => 20.,
+        // The remaining violations are never committed by an honest peer: blocks are
+        // validated before they are relayed, approvals and state witnesses are sent by
+        // their authors.
+        ReasonForBan::BadBlock
+        | ReasonForBan::BadBlockHeader
+        | ReasonForBan::BadBlockApproval
+        | ReasonForBan::BadChunkStateWitness
+        | ReasonForBan::HeightFraud
+        | ReasonForBan::BadHandshake
+        | ReasonForBan::Abusive
+        | ReasonForBan::InvalidSignature
+        | ReasonForBan::InvalidPeerId
+        | ReasonForBan::InvalidHash
+        | ReasonForBan::InvalidEdge
+        | ReasonForBan::InvalidDistanceVector
+        | ReasonForBan::Blacklisted => MAX_SCORE - MIN_SCORE,
+    }
+}
+
+/// Returns `score` decayed over `elapsed` time.
+fn decay(score: f64, elapsed: time::Duration, half_life: time::Duration) -> f64 {
+    if !elapsed.is_positive() {
+        return score;
+    }
+    score * 0.5f64.powf(elapsed / half_life)
+}
+
+fn new_score(peer_id: PeerId, now: time::Utc) -> PeerScore {
+    PeerScore {
+        peer_id,
+        score: 0.,
+        updated_at: now,
+        useful_responses: 0,
+        useless_responses: 0,
+        routing_successes: 0,
+        routing_failures: 0,
+        violations: 0,
+        avg_latency: None,
+    }
+}
+
+/// Routing events of a peer which haven't been applied to its score yet.
+#[derive(Default)]
+struct PendingRouting {
+    successes: u64,
+    failures: u64,
+}
+
+/// Reward for forwarded messages a peer got in the current window.
+struct RoutingReward {
+    window_start: time::Utc,
+    reward: f64,
+}
+
+struct Inner {
+    config: PeerScoreConfig,
+    store: store::Store,
+    scores: HashMap<PeerId, PeerScore>,
+    routing_rewards: HashMap<PeerId, RoutingReward>,
+}
+
+impl Inner {
+    /// Returns the reward for `successes` forwarded messages of the peer, capped so that
+    /// the peer gets at most `MAX_ROUTING_REWARD` per window.
+    fn routing_reward(&mut self, peer_id: &PeerId, successes: u64, now: time::Utc) -> f64 {
+        let r = self
+            .routing_rewards
+            .entry(peer_id.clone())
+            .or_insert(RoutingReward { window_start: now, reward: 0. });
+        if now - r.window_start >= ROUTING_REWARD_WINDOW {
+            *r = RoutingReward { window_start: now, reward: 0. };
+        }
+        let reward = (successes as f64 * ROUTING_SUCCESS_REWARD).min(MAX_ROUTING_REWARD - r.reward);
+        r.reward += reward;
+        reward
+    }
+
+    /// Returns the score of the peer, decayed up to `now`.
+    fn get_mut(&mut self, peer_id: &PeerId, now: time::Utc) -> &mut PeerScore {
+        let half_life = self.config.half_life;
+        let s =
+            self.scores.entry(peer_id.clone()).or_insert_with(|| new_score(peer_id.clone(), now));
+        s.score = decay(s.score, now - s.updated_at, half_life);
+        s.updated_at = now;
+        s
+    }
+
+    fn score(&self, peer_id: &PeerId, now: time::Utc) -> f64 {
+        self.scores
+            .get(peer_id)
+            .map_or(0., |s| decay(s.score, now - s.updated_at, self.config.half_life))
+    }
+
+    fn load(&self, now: time::Utc) -> Vec<PeerScore> {
+        (self.scores.values())
+            .map(|s| PeerScore {
+                score: decay(s.score, now - s.updated_at, self.config.half_life),
+                updated_at: now,
+                ..s.clone()
+            })
+            .collect()
+    }
+
+    fn record(&mut self, peer_id: &PeerId, event: Event, now: time::Utc) -> f64 {
+        let slow_response_threshold = self.config.slow_response_threshold;
+        let routing_reward = match event {
+            Event::RoutingSuccess => self.routing_reward(peer_id, 1, now),
+            _ => 0.,
+        };
+        let s = self.get_mut(peer_id, now);
+        let delta = match event {
+            Event::UsefulResponse => {
+                s.useful_responses += 1;
+                USEFUL_RESPONSE_REWARD
+            }
+            Event::UselessResponse => {
+                s.useless_responses += 1;
+                -USELESS_RESPONSE_PENALTY
+            }
+            Event::ResponseLatency(latency) => {
+                s.avg_latency = Some(match s.avg_latency {
+                    None => latency,
+                    Some(avg) => {
+                        avg * (1. - LATENCY_AVERAGE_WEIGHT) + latency * LATENCY_AVERAGE_WEIGHT
+                    }
+                });
+                if latency > slow_response_threshold {
+                    -SLOW_RESPONSE_PENALTY
+                } else {
+                    0.
+                }
+            }
+            Event::RoutingSuccess => {
+                s.routing_successes += 1;
+                routing_reward
+            }
+            Event::RoutingFailure => {
+                s.routing_failures += 1;
+                -ROUTING_FAILURE_PENALTY
+            }
+            Event::ProtocolViolation(reason) => {
+                s.violations += 1;
+                -violation_penalty(reason)
+            }
+        };
+        s.score = (s.score + delta).clamp(MIN_SCORE, MAX_SCORE);
+        s.score
+    }
+
+    fn record_routing(&mut self, peer_id: &PeerId, pending: PendingRouting, now: time::Utc) {
+        let reward = self.routing_reward(peer_id, pending.successes, now);
+        let s = self.get_mut(peer_id, now);
+        s.routing_successes += pending.successes;
+        s.routing_failures += pending.failures;
+        let delta = reward - pending.failures as f64 * ROUTING_FAILURE_PENALTY;
+        s.score = (s.score + delta).clamp(MIN_SCORE, MAX_SCORE);
+    }
+}
+
+/// Reputation scores of peers. See the module documentation.
+pub(crate) struct PeerScores {
+    enabled: bool,
+    inner: Mutex<Inner>,
+    routing: [Mutex<HashMap<PeerId, PendingRouting>>; ROUTING_SHARDS],
+}
+
+impl PeerScores {
+    pub fn new(config: PeerScoreConfig, store: store::Store) -> Self {
+        let scores = store.get_peer_scores().into_iter().map(|s| (s.peer_id.clone(), s)).collect();
+        Self {
+            enabled: config.enabled,
+            inner: Mutex::new(Inner { config, store, scores, routing_rewards: HashMap::new() }),
+            routing: std::array::from_fn(|_| Mutex::default()),
+        }
+    }
+
+    /// Locks the scores, applying the pending routing events first.
+    fn lock(&self, clock: &time::Clock) -> MutexGuard<Inner> {
+        let mut inner = self.inner.lock();
+        let now = clock.now_utc();
+        for shard in &self.routing {
+            for (peer_id, pending) in std::mem::take(&mut *shard.lock()) {
+                inner.record_routing(&peer_id, pending, now);
+            }
+        }
+        inner
+    }
+
+    /// Whether the scores drive bans and the choice of peers.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Current score of the peer. Peers without a score have score 0.
+    pub fn score(&self, clock: &time::Clock, peer_id: &PeerId) -> f64 {
+        self.lock(clock).score(peer_id, clock.now_utc())
+    }
+
+    /// Updates the score of the peer with an observed event. Does nothing if scoring is disabled.
+    /// Routing events are only buffered, they are applied when the scores are read.
+    pub fn record(&self, clock: &time::Clock, peer_id: &PeerId, event: Event) {
+        if !self.enabled {
+            return;
+        }
+        match event {
+            Event::RoutingSuccess | Event::RoutingFailure => {
+                let shard = BuildHasherDefault::<DefaultHasher>::default().hash_one(peer_id)
+                    as usize
+                    % ROUTING_SHARDS;
+                let mut routing = self.routing[shard].lock();
+                let pending = routing.entry(peer_id.clone()).or_default();
+                if event == Event::RoutingSuccess {
+                    pending.successes += 1;
+                } else {
+                    pending.failures += 1;
+                }
+            }
+            _ => {
+                self.lock(clock).record(peer_id, event, clock.now_utc());
+            }
+        }
+    }
+
+    /// Penalizes the peer for a protocol violation.
+    /// Returns whether the peer should be banned for it: always if scoring is disabled,
+    /// otherwise only if the score dropped below the ban threshold.
+    pub fn should_ban(&self, clock: &time::Clock, peer_id: &PeerId, reason: ReasonForBan) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let mut inner = self.lock(clock);
+        let score = inner.record(peer_id, Event::ProtocolViolation(reason), clock.now_utc());
+        score < inner.config.ban_threshold
+    }
+
+    /// Whether the peer should be avoided when sending sync requests.
+    pub fn is_deprioritized(&self, clock: &time::Clock, peer_id: &PeerId) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let inner = self.lock(clock);
+        inner.score(peer_id, clock.now_utc()) < inner.config.deprioritize_threshold
+    }
+
+    /// Returns all the scores, decayed up to now.
+    pub fn load(&self, clock: &time::Clock) -> Vec<PeerScore> {
+        self.lock(clock).load(clock.now_utc())
+    }
+
+    /// Saves the scores to the DB, dropping the ones closest to 0 if there are too many.
+    /// Does nothing if scoring is disabled.
+    pub fn save(&self, clock: &time::Clock) {
+        if !self.enabled {
+            return;
+        }
+        let mut inner = self.lock(clock);
+        let now = clock.now_utc();
+        inner.routing_rewards.retain(|_, r| now - r.window_start < ROUTING_REWARD_WINDOW);
+        let mut scores = inner.load(now);
+        scores.sort_by(|a, b| b.score.abs().total_cmp(&a.score.abs()));
+        scores.truncate(MAX_STORED_SCORES);
+        inner.scores = scores.iter().map(|s| (s.peer_id.clone(), s.clone())).collect();
+        if let Err(err) = inner.store.set_peer_scores(&scores) {
+            tracing::error!(target: "network", ?err, "Failed to save peer scores");
+        }
+    }
+}
+
+impl From<&PeerScore> for PeerScoreView {
+    fn from(s: &PeerScore) -> Self {
+        Self {
+            score: s.score.round() as i64,
+            useful_responses: s.useful_responses,
+            useless_responses: s.useless_responses,
+            routing_successes: s.routing_successes,
+            routing_failures: s.routing_failures,
+            violations: s.violations,
+            avg_latency_millis: s.avg_latency.map(|d| d.whole_milliseconds() as u64),
+        }
+    }
+}
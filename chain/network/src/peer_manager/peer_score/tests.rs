@@ -0,0 +1,198 @@
+use crate::config::PeerScoreConfig;
+use crate::network_protocol::testonly::make_peer_id;
+use crate::peer_manager::peer_score::{Event, PeerScores, MAX_SCORE, MIN_SCORE};
+use crate::store;
+use crate::testonly::make_rng;
+use crate::types::ReasonForBan;
+use near_async::time;
+
+fn make_peer_scores(enabled: bool) -> PeerScores {
+    let store = store::Store::from(near_store::db::TestDB::new());
+    PeerScores::new(PeerScoreConfig { enabled, ..PeerScoreConfig::default() }, store)
+}
+
+fn assert_close(got: f64, want: f64) {
+    assert!((got - want).abs() < 1e-6, "got {got}, want {want}");
+}
+
+#[test]
+fn score_decays() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(true);
+    let half_life = PeerScoreConfig::default().half_life;
+    let peer_id = make_peer_id(&mut rng);
+
+    assert_eq!(scores.score(&clock.clock(), &peer_id), 0.);
+    for _ in 0..8 {
+        scores.record(&clock.clock(), &peer_id, Event::UsefulResponse);
+    }
+    assert_close(scores.score(&clock.clock(), &peer_id), 8.);
+    clock.advance(half_life);
+    assert_close(scores.score(&clock.clock(), &peer_id), 4.);
+    clock.advance(half_life);
+    // Events are applied to the decayed score.
+    scores.record(&clock.clock(), &peer_id, Event::UselessResponse);
+    assert_close(scores.score(&clock.clock(), &peer_id), 0.);
+}
+
+#[test]
+fn score_is_bounded() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(true);
+    let good = make_peer_id(&mut rng);
+    let bad = make_peer_id(&mut rng);
+
+    for _ in 0..2 * MAX_SCORE as usize {
+        scores.record(&clock.clock(), &good, Event::UsefulResponse);
+    }
+    assert_eq!(scores.score(&clock.clock(), &good), MAX_SCORE);
+    scores.record(&clock.clock(), &bad, Event::ProtocolViolation(ReasonForBan::InvalidSignature));
+    scores.record(&clock.clock(), &bad, Event::ProtocolViolation(ReasonForBan::InvalidSignature));
+    assert_eq!(scores.score(&clock.clock(), &bad), MIN_SCORE);
+}
+
+#[test]
+fn ban_disabled() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(false);
+    let peer_id = make_peer_id(&mut rng);
+
+    // Every violation bans the peer, and the scores are not tracked.
+    assert!(scores.should_ban(&clock.clock(), &peer_id, ReasonForBan::ProvidedNotEnoughHeaders));
+    scores.record(&clock.clock(), &peer_id, Event::UselessResponse);
+    assert_eq!(scores.score(&clock.clock(), &peer_id), 0.);
+    assert!(!scores.is_deprioritized(&clock.clock(), &peer_id));
+    assert_eq!(scores.load(&clock.clock()), vec![]);
+}
+
+#[test]
+fn ban_enabled() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(true);
+    let half_life = PeerScoreConfig::default().half_life;
+
+    tracing::info!(target:"test", "mild violations get the peer banned only if repeated");
+    let peer_id = make_peer_id(&mut rng);
+    assert!(!scores.should_ban(&clock.clock(), &peer_id, ReasonForBan::ProvidedNotEnoughHeaders));
+    assert!(scores.is_deprioritized(&clock.clock(), &peer_id));
+    assert!(!scores.should_ban(&clock.clock(), &peer_id, ReasonForBan::ProvidedNotEnoughHeaders));
+    assert!(scores.should_ban(&clock.clock(), &peer_id, ReasonForBan::ProvidedNotEnoughHeaders));
+
+    tracing::info!(target:"test", "mild violations are forgotten over time");
+    let peer_id = make_peer_id(&mut rng);
+    for _ in 0..5 {
+        assert!(!scores.should_ban(
+            &clock.clock(),
+            &peer_id,
+            ReasonForBan::ProvidedNotEnoughHeaders
+        ));
+        clock.advance(half_life * 4);
+    }
+    assert!(!scores.is_deprioritized(&clock.clock(), &peer_id));
+
+    tracing::info!(target:"test", "severe violations get even the best peers banned");
+    let peer_id = make_peer_id(&mut rng);
+    for _ in 0..MAX_SCORE as usize {
+        scores.record(&clock.clock(), &peer_id, Event::UsefulResponse);
+    }
+    assert!(scores.should_ban(&clock.clock(), &peer_id, ReasonForBan::InvalidSignature));
+    let peer_id = make_peer_id(&mut rng);
+    for _ in 0..MAX_SCORE as usize {
+        scores.record(&clock.clock(), &peer_id, Event::UsefulResponse);
+    }
+    assert!(scores.should_ban(&clock.clock(), &peer_id, ReasonForBan::BadBlock));
+}
+
+#[test]
+fn slow_responses() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(true);
+    let threshold = PeerScoreConfig::default().slow_response_threshold;
+    let peer_id = make_peer_id(&mut rng);
+
+    scores.record(&clock.clock(), &peer_id, Event::ResponseLatency(threshold / 2));
+    assert_eq!(scores.score(&clock.clock(), &peer_id), 0.);
+    scores.record(&clock.clock(), &peer_id, Event::ResponseLatency(threshold * 2));
+    assert!(scores.score(&clock.clock(), &peer_id) < 0.);
+    let got = scores.load(&clock.clock());
+    assert_eq!(got.len(), 1);
+    // 0.8 * 1s + 0.2 * 4s
+    assert_eq!(got[0].avg_latency, Some(time::Duration::milliseconds(1600)));
+}
+
+#[test]
+fn routing_events_are_batched() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(true);
+    let half_life = PeerScoreConfig::default().half_life;
+    let peers: Vec<_> = (0..20).map(|_| make_peer_id(&mut rng)).collect();
+
+    for peer_id in &peers {
+        for _ in 0..10 {
+            scores.record(&clock.clock(), peer_id, Event::RoutingSuccess);
+        }
+        scores.record(&clock.clock(), peer_id, Event::RoutingFailure);
+    }
+    // Pending events are applied when the scores are read, at the time of the read.
+    clock.advance(half_life);
+    for peer_id in &peers {
+        assert_close(scores.score(&clock.clock(), peer_id), 0.5);
+    }
+    let got = scores.load(&clock.clock());
+    assert_eq!(got.len(), peers.len());
+    for s in got {
+        assert_eq!((s.routing_successes, s.routing_failures), (10, 1));
+    }
+}
+
+#[test]
+fn routing_reward_is_capped() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let scores = make_peer_scores(true);
+    let peer_id = make_peer_id(&mut rng);
+
+    for _ in 0..100 {
+        scores.record(&clock.clock(), &peer_id, Event::RoutingSuccess);
+    }
+    assert_close(scores.score(&clock.clock(), &peer_id), 1.);
+    for _ in 0..100 {
+        scores.record(&clock.clock(), &peer_id, Event::RoutingSuccess);
+    }
+    assert_close(scores.score(&clock.clock(), &peer_id), 1.);
+    // Every message is counted even if it isn't rewarded.
+    assert_eq!(scores.load(&clock.clock())[0].routing_successes, 200);
+
+    // The reward is granted again in the next window.
+    let half_life = PeerScoreConfig::default().half_life;
+    clock.advance(half_life);
+    for _ in 0..100 {
+        scores.record(&clock.clock(), &peer_id, Event::RoutingSuccess);
+    }
+    assert_close(scores.score(&clock.clock(), &peer_id), 1.5);
+}
+
+#[test]
+fn reload_from_storage() {
+    let mut rng = make_rng(921853233);
+    let clock = time::FakeClock::default();
+    let store = store::Store::from(near_store::db::TestDB::new());
+    let config = PeerScoreConfig { enabled: true, ..PeerScoreConfig::default() };
+    let peer_id = make_peer_id(&mut rng);
+    let want = {
+        let scores = PeerScores::new(config.clone(), store.clone());
+        scores.record(&clock.clock(), &peer_id, Event::UsefulResponse);
+        scores.record(&clock.clock(), &peer_id, Event::RoutingFailure);
+        scores.record(&clock.clock(), &peer_id, Event::ResponseLatency(time::Duration::SECOND));
+        scores.save(&clock.clock());
+        scores.load(&clock.clock())
+    };
+    let scores = PeerScores::new(config, store);
+    assert_eq!(scores.load(&clock.clock()), want);
+}
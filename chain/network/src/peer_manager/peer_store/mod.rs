@@ -2,7 +2,7 @@
 //!     - Store information about known peers in the network. Peers may be discovered
 //!       by connecting to them directly or by learning about them from other peers.
 //!     - Respond to requests from other peers for known peers (see PeerStore::healthy_peers).
-//!     - Select peers to which we may try to connect directly (see PeerStore::unconnected_peer).
+//!     - Select peers to which we may try to connect directly (see PeerStore::unconnected_peers).
 //!
 //! Contents of the PeerStore are not persisted to the database. Upon starting a node,
 //! the PeerStore is initialized from the boot nodes in its config.
@@ -428,16 +428,27 @@ impl PeerStore {
         Ok(())
     }
 
-    /// Return unconnected or peers with unknown status that we can try to connect to.
-    /// Peers with unknown addresses are filtered out.
+    /// Like `unconnected_peers`, but returns a single peer.
+    #[cfg(test)]
     pub fn unconnected_peer(
         &self,
         ignore_fn: impl Fn(&KnownPeerState) -> bool,
         prefer_previously_connected_peer: bool,
     ) -> Option<PeerInfo> {
+        self.unconnected_peers(ignore_fn, prefer_previously_connected_peer, 1).pop()
+    }
+
+    /// Return up to `count` random unconnected or peers with unknown status that we can try
+    /// to connect to. Peers with unknown addresses are filtered out.
+    pub fn unconnected_peers(
+        &self,
+        ignore_fn: impl Fn(&KnownPeerState) -> bool,
+        prefer_previously_connected_peer: bool,
+        count: usize,
+    ) -> Vec<PeerInfo> {
         let inner = self.0.lock();
         if prefer_previously_connected_peer {
-            let preferred_peer = inner.find_peers(
+            let preferred_peers = inner.find_peers(
                 |p| {
                     (p.status == KnownPeerStatus::NotConnected)
                         && !ignore_fn(p)
@@ -445,15 +456,13 @@ impl PeerStore {
                         // if we're connecting only to the boot nodes - filter out the nodes that are not bootnodes.
                         && (!inner.config.connect_only_to_boot_nodes || inner.boot_nodes.contains(&p.peer_info.id))
                 },
-                1,
-            )
-            .get(0)
-            .cloned();
-            // If we found a preferred peer - return it.
-            if preferred_peer.is_some() {
-                return preferred_peer;
+                count,
+            );
+            // If we found preferred peers - return them.
+            if !preferred_peers.is_empty() {
+                return preferred_peers;
             };
-            // otherwise, pick a peer from the wider pool below.
+            // otherwise, pick peers from the wider pool below.
         }
         inner.find_peers(
             |p| {
@@ -463,10 +472,8 @@ impl PeerStore {
                     // If we're connecting only to the boot nodes - filter out the nodes that are not boot nodes.
                     && (!inner.config.connect_only_to_boot_nodes || inner.boot_nodes.contains(&p.peer_info.id))
             },
-            1,
+            count,
         )
-        .get(0)
-        .cloned()
     }
 
     /// Return healthy known peers up to given amount.
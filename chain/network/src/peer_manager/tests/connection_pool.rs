@@ -107,6 +107,9 @@ async fn loop_connection() {
                 &pm.cfg.node_key,
             ),
             owned_account: None,
+            encrypted_transport: false,
+            message_compression: false,
+            encrypted_transport_signature: None,
         }))
         .await;
     let reason = events
@@ -177,6 +180,9 @@ async fn owned_account_mismatch() {
                 }
                 .sign(&signer),
             ),
+            encrypted_transport: false,
+            message_compression: false,
+            encrypted_transport_signature: None,
         }))
         .await;
     let reason = events
@@ -299,6 +305,9 @@ async fn invalid_edge() {
                     }
                     .sign(&signer),
                 ),
+                encrypted_transport: false,
+                message_compression: false,
+                encrypted_transport_signature: None,
             };
             let handshake = match tier {
                 tcp::Tier::T1 => PeerMessage::Tier1Handshake(handshake),
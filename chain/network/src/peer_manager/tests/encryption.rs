@@ -0,0 +1,157 @@
+use crate::broadcast;
+use crate::config::{EncryptedTransport, SocketOptions};
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::{Encoding, Handshake, PartialEdgeInfo, PeerMessage};
+use crate::peer::peer_actor::ClosingReason;
+use crate::peer_manager;
+use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::peer_manager::testonly::Event;
+use crate::tcp;
+use crate::testonly::stream::Stream;
+use crate::testonly::{make_rng, Rng};
+use crate::types::Edge;
+use near_async::time;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::network::PeerId;
+use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
+use near_store::db::TestDB;
+use std::sync::Arc;
+
+async fn start_with_encryption(
+    clock: &time::FakeClock,
+    rng: &mut Rng,
+    chain: &Arc<data::Chain>,
+    encrypted_transport: EncryptedTransport,
+) -> peer_manager::testonly::ActorHandler {
+    let mut cfg = chain.make_config(rng);
+    cfg.encrypted_transport = encrypted_transport;
+    start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await
+}
+
+/// Connects `pm1` to `pm2` and returns whether the connection is encrypted,
+/// as seen by both sides.
+async fn connect(
+    pm1: &peer_manager::testonly::ActorHandler,
+    pm2: &peer_manager::testonly::ActorHandler,
+) -> (bool, bool) {
+    pm1.connect_to(&pm2.peer_info(), tcp::Tier::T2).await;
+    // Routing table sync requires messages to be exchanged in both directions
+    // after the handshake, so waiting for it checks that the session works.
+    let id1 = pm1.cfg.node_id();
+    let id2 = pm2.cfg.node_id();
+    pm1.wait_for_routing_table(&[(id2.clone(), vec![id2.clone()])]).await;
+    pm2.wait_for_routing_table(&[(id1.clone(), vec![id1.clone()])]).await;
+    let encrypted1 =
+        pm1.with_state(|s| async move { s.tier2.load().ready.get(&id2).unwrap().encrypted }).await;
+    let encrypted2 =
+        pm2.with_state(|s| async move { s.tier2.load().ready.get(&id1).unwrap().encrypted }).await;
+    (encrypted1, encrypted2)
+}
+
+/// Waits for a connection to be closed and returns the reason.
+async fn wait_for_closed_connection(mut events: broadcast::Receiver<Event>) -> ClosingReason {
+    events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev)) => Some(ev.reason),
+            Event::PeerManager(PME::HandshakeCompleted(_)) => {
+                panic!("unencrypted connection has been established")
+            }
+            _ => None,
+        })
+        .await
+}
+
+#[tokio::test]
+async fn preferred() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm1 = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Preferred).await;
+    let pm2 = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Preferred).await;
+    let want = ProtocolFeature::EncryptedPeerConnections.enabled(PROTOCOL_VERSION);
+    assert_eq!((want, want), connect(&pm1, &pm2).await);
+}
+
+#[tokio::test]
+async fn preferred_with_disabled() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm1 = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Preferred).await;
+    let pm2 = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Disabled).await;
+    assert_eq!((false, false), connect(&pm1, &pm2).await);
+}
+
+#[tokio::test]
+async fn required_with_disabled() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let required = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Required).await;
+    let disabled = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Disabled).await;
+
+    tracing::info!(target:"test", "outbound connection from the node requiring encryption");
+    let events = required.events.from_now();
+    required.send_outbound_connect(&disabled.peer_info(), tcp::Tier::T2).await;
+    assert_eq!(ClosingReason::HandshakeFailed, wait_for_closed_connection(events).await);
+
+    tracing::info!(target:"test", "inbound connection to the node requiring encryption");
+    let events = required.events.from_now();
+    disabled.send_outbound_connect(&required.peer_info(), tcp::Tier::T2).await;
+    assert_eq!(ClosingReason::HandshakeFailed, wait_for_closed_connection(events).await);
+}
+
+#[tokio::test]
+async fn stripped_flag() {
+    init_test_logger();
+    if !ProtocolFeature::EncryptedPeerConnections.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm = start_with_encryption(&clock, rng, &chain, EncryptedTransport::Preferred).await;
+    let peer_key = data::make_secret_key(rng);
+    let peer_id = PeerId::new(peer_key.public_key());
+    let stream = tcp::Stream::connect(&pm.peer_info(), tcp::Tier::T2, &SocketOptions::default())
+        .await
+        .unwrap();
+    let port = stream.local_addr.port();
+    let mut stream = Stream::new(Some(Encoding::Proto), stream);
+    // The peer signs that it wants encryption, but the flag is stripped on the way.
+    let mut handshake = Handshake {
+        protocol_version: PROTOCOL_VERSION,
+        oldest_supported_version: PROTOCOL_VERSION,
+        sender_peer_id: peer_id.clone(),
+        target_peer_id: pm.cfg.node_id(),
+        sender_listen_port: Some(port),
+        sender_chain_info: chain.get_peer_chain_info(),
+        partial_edge_info: PartialEdgeInfo::new(
+            &peer_id,
+            &pm.cfg.node_id(),
+            Edge::create_fresh_nonce(&clock.clock()),
+            &peer_key,
+        ),
+        owned_account: None,
+        encrypted_transport: true,
+        message_compression: false,
+        encrypted_transport_signature: None,
+    }
+    .sign_encrypted_transport(&peer_key);
+    handshake.encrypted_transport = false;
+    let events = pm.events.from_now();
+    stream.write(&PeerMessage::Tier2Handshake(handshake)).await;
+    assert_eq!(ClosingReason::HandshakeFailed, wait_for_closed_connection(events).await);
+}
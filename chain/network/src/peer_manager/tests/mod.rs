@@ -1,7 +1,9 @@
 mod accounts_data;
 mod connection_pool;
+mod encryption;
 mod fuzzers;
 mod nonce;
+mod peer_score;
 mod routing;
 mod snapshot_hosts;
 mod tier1;
@@ -0,0 +1,217 @@
+use crate::broadcast;
+use crate::config::NetworkConfig;
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::PeerMessage;
+use crate::peer::peer_actor::ClosingReason;
+use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::peer_manager::peer_score::{self, EVICTION_SCORE_MARGIN};
+use crate::peer_manager::testonly::start as start_pm;
+use crate::peer_manager::testonly::{ActorHandler, Event};
+use crate::private_actix::RegisterPeerError;
+use crate::tcp;
+use crate::test_utils::GetInfo;
+use crate::testonly::{make_rng, Rng};
+use crate::types::ReasonForBan;
+use near_async::time;
+use near_o11y::testonly::init_test_logger;
+use near_o11y::WithSpanContextExt;
+use near_primitives::network::PeerId;
+use near_store::db::TestDB;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+fn make_config(chain: &data::Chain, rng: &mut Rng) -> NetworkConfig {
+    let mut cfg = chain.make_config(rng);
+    cfg.peer_score.enabled = true;
+    cfg
+}
+
+/// Records `event` for the peer `count` times in the scores kept by `pm`.
+async fn record(
+    pm: &ActorHandler,
+    clock: &time::Clock,
+    peer_id: &PeerId,
+    event: peer_score::Event,
+    count: usize,
+) {
+    let clock = clock.clone();
+    let peer_id = peer_id.clone();
+    pm.with_state(move |s| async move {
+        for _ in 0..count {
+            s.peer_scores.record(&clock, &peer_id, event);
+        }
+    })
+    .await
+}
+
+async fn connected_peers(pm: &ActorHandler) -> HashSet<PeerId> {
+    pm.with_state(|s| async move { s.tier2.load().ready.keys().cloned().collect() }).await
+}
+
+async fn highest_height_peers(pm: &ActorHandler) -> HashSet<PeerId> {
+    let info = pm.actix.addr.send(GetInfo {}.with_span_context()).await.unwrap();
+    info.highest_height_peers.into_iter().map(|p| p.peer_info.id).collect()
+}
+
+async fn wait_for_stream_closed(
+    events: &mut broadcast::Receiver<Event>,
+    stream_id: tcp::StreamId,
+) -> ClosingReason {
+    events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev)) if ev.stream_id == stream_id => {
+                Some(ev.reason)
+            }
+            _ => None,
+        })
+        .await
+}
+
+/// Once the connection limit is reached, an inbound peer replaces the lowest scored
+/// connected peer, but only if its own score is sufficiently higher.
+#[tokio::test]
+async fn evict_lowest_scored_peer() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = make_config(&chain, rng);
+    cfg.max_num_peers = 1;
+    cfg.ideal_connections_lo = 1;
+    cfg.ideal_connections_hi = 1;
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    tracing::info!(target:"test", "connect a peer, reaching the connection limit");
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    let stream_id = conn.stream.id();
+    let peer1 = conn.handshake(&clock.clock()).await;
+    let id1 = peer1.cfg.id();
+
+    tracing::info!(target:"test", "a peer with a slightly higher score is rejected");
+    let cfg2 = chain.make_config(rng);
+    let id2 = cfg2.node_id();
+    record(&pm, &clock.clock(), &id2, peer_score::Event::UsefulResponse, 1).await;
+    let reason = pm
+        .start_inbound(chain.clone(), cfg2.clone())
+        .await
+        .manager_fail_handshake(&clock.clock())
+        .await;
+    assert_eq!(
+        ClosingReason::RejectedByPeerManager(RegisterPeerError::ConnectionLimitExceeded),
+        reason
+    );
+    assert_eq!(connected_peers(&pm).await, HashSet::from([id1]));
+
+    tracing::info!(target:"test", "a peer with a sufficiently higher score evicts the connected peer");
+    let margin = EVICTION_SCORE_MARGIN as usize;
+    record(&pm, &clock.clock(), &id2, peer_score::Event::UsefulResponse, margin).await;
+    let mut events = pm.events.from_now();
+    let _peer2 = pm.start_inbound(chain.clone(), cfg2).await.handshake(&clock.clock()).await;
+    wait_for_stream_closed(&mut events, stream_id).await;
+    assert_eq!(connected_peers(&pm).await, HashSet::from([id2]));
+}
+
+/// Outbound connections are established to the highest scored of the candidate peers.
+#[tokio::test]
+async fn connect_to_highest_scored_peer() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = make_config(&chain, rng);
+    let peers = [data::make_peer_info(rng), data::make_peer_info(rng)];
+    cfg.peer_store.boot_nodes = peers.to_vec();
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+    let peer_to_connect = || {
+        let clock = clock.clock();
+        pm.with_state(move |s| async move { s.peer_to_connect(&clock, false).unwrap().id })
+    };
+
+    record(&pm, &clock.clock(), &peers[0].id, peer_score::Event::UsefulResponse, 1).await;
+    assert_eq!(peer_to_connect().await, peers[0].id);
+    record(&pm, &clock.clock(), &peers[1].id, peer_score::Event::UsefulResponse, 2).await;
+    assert_eq!(peer_to_connect().await, peers[1].id);
+}
+
+/// Sync requests are not sent to peers with low scores, unless all the peers have low scores.
+#[tokio::test]
+async fn deprioritize_low_scored_peers() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm = start_pm(clock.clock(), TestDB::new(), make_config(&chain, rng), chain.clone()).await;
+    let deprioritize_threshold = pm.cfg.peer_score.deprioritize_threshold;
+
+    tracing::info!(target:"test", "connect two peers and let them announce their heads");
+    let mut ids = vec![];
+    let mut peers = vec![];
+    for _ in 0..2 {
+        let peer = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+        let peer = peer.handshake(&clock.clock()).await;
+        let mut events = pm.events.from_now();
+        peer.send(PeerMessage::Block(chain.blocks[5].clone())).await;
+        events
+            .recv_until(|ev| match ev {
+                Event::PeerManager(PME::MessageProcessed(tcp::Tier::T2, PeerMessage::Block(_))) => {
+                    Some(())
+                }
+                _ => None,
+            })
+            .await;
+        ids.push(peer.cfg.id());
+        peers.push(peer);
+    }
+    assert_eq!(highest_height_peers(&pm).await, ids.iter().cloned().collect());
+
+    tracing::info!(target:"test", "a low scored peer is left out");
+    // Each useless response costs more than a single point of the score.
+    let count = -deprioritize_threshold as usize;
+    record(&pm, &clock.clock(), &ids[0], peer_score::Event::UselessResponse, count).await;
+    assert_eq!(highest_height_peers(&pm).await, HashSet::from([ids[1].clone()]));
+
+    tracing::info!(target:"test", "all the peers are returned if all of them have low scores");
+    record(&pm, &clock.clock(), &ids[1], peer_score::Event::UselessResponse, count).await;
+    assert_eq!(highest_height_peers(&pm).await, ids.iter().cloned().collect());
+}
+
+/// With scoring enabled, a peer is disconnected and banned only once its score drops below
+/// the ban threshold.
+#[tokio::test]
+async fn penalize_instead_of_ban() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm = start_pm(clock.clock(), TestDB::new(), make_config(&chain, rng), chain.clone()).await;
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    let stream_id = conn.stream.id();
+    let peer = conn.handshake(&clock.clock()).await;
+    let id = peer.cfg.id();
+    let mut events = pm.events.from_now();
+
+    tracing::info!(target:"test", "a mild violation only penalizes the peer");
+    pm.disconnect_and_ban(&clock.clock(), &id, ReasonForBan::ProvidedNotEnoughHeaders).await;
+    tracing::info!(target:"test", "a severe violation gets the peer banned");
+    pm.disconnect_and_ban(&clock.clock(), &id, ReasonForBan::InvalidSignature).await;
+    // The PeerActor handles the violations in order, so if the first one had stopped
+    // the connection, it would be the closing reason.
+    assert_eq!(
+        ClosingReason::Ban(ReasonForBan::InvalidSignature),
+        wait_for_stream_closed(&mut events, stream_id).await
+    );
+    let got_reason = pm
+        .start_inbound(chain.clone(), peer.cfg.network.clone())
+        .await
+        .manager_fail_handshake(&clock.clock())
+        .await;
+    assert_eq!(ClosingReason::RejectedByPeerManager(RegisterPeerError::Banned), got_reason);
+}
@@ -51,9 +51,22 @@ impl RateLimits {
     /// if it should be rate limited, returns `false`.
     pub fn is_allowed(&mut self, message: &PeerMessage, now: Instant) -> bool {
         if let Some((key, cost)) = get_key_and_token_cost(message) {
-            if let Some(bucket) = &mut self.buckets[key] {
-                return bucket.acquire(cost, now);
-            }
+            return self.is_allowed_key(key, cost, now);
+        }
+        true
+    }
+
+    /// Checks if a message of type `key` and token cost `cost` is under the rate limits.
+    /// Useful when the message itself isn't available yet, e.g. before a compressed
+    /// message is decompressed.
+    pub fn is_allowed_key(
+        &mut self,
+        key: RateLimitedPeerMessageKey,
+        cost: u32,
+        now: Instant,
+    ) -> bool {
+        if let Some(bucket) = &mut self.buckets[key] {
+            return bucket.acquire(cost, now);
         }
         true
     }
@@ -124,6 +137,37 @@ impl Config {
         config
     }
 
+    /// Returns the preset of rate limits of the messages received compressed. They are applied
+    /// on top of the other rate limits to compressed messages only, before decompression, and
+    /// bound how much a peer can make the node decompress. They are well above the rates of an
+    /// honest peer: a block per block production plus up to `max_block_requests` during block
+    /// sync, a state response per requested header or part, and a partial state witness per
+    /// shard and block from each chunk producer.
+    pub fn compressed_preset() -> Self {
+        let mut config = Self::default();
+        config
+            .rate_limits
+            .insert(RateLimitedPeerMessageKey::Block, SingleMessageConfig::new(200, 50.0, None));
+        config.rate_limits.insert(
+            RateLimitedPeerMessageKey::VersionedStateResponse,
+            SingleMessageConfig::new(50, 10.0, None),
+        );
+        config.rate_limits.insert(
+            RateLimitedPeerMessageKey::PartialEncodedStateWitness,
+            SingleMessageConfig::new(300, 100.0, None),
+        );
+        config.rate_limits.insert(
+            RateLimitedPeerMessageKey::PartialEncodedStateWitnessForward,
+            SingleMessageConfig::new(300, 100.0, None),
+        );
+        // Snapshot hosts are gossiped once per epoch.
+        config.rate_limits.insert(
+            RateLimitedPeerMessageKey::SyncSnapshotHosts,
+            SingleMessageConfig::new(50, 1.0, None),
+        );
+        config
+    }
+
     /// Applies rate limits configuration overrides to `self`. In practice, merges the two configurations
     /// giving preference to the values defined by the `overrides` parameter.
     pub fn apply_overrides(&mut self, overrides: OverrideConfig) {
@@ -143,6 +187,7 @@ impl Config {
     Copy,
     enum_map::Enum,
     strum::Display,
+    strum::EnumString,
     Debug,
     PartialEq,
     Eq,
@@ -482,4 +527,42 @@ mod tests {
         clock.advance(Duration::seconds(30));
         assert!(rate_limits.is_allowed(&PeerMessage::EpochSyncRequest, clock.now()));
     }
+
+    #[test]
+    fn test_compressible_messages_rate_limits() {
+        use RateLimitedPeerMessageKey::*;
+        let config = Config::compressed_preset();
+        assert!(config.validate().is_ok());
+        let clock = FakeClock::default();
+        let mut rate_limits = RateLimits::from_config(&config, clock.now());
+        for key in [
+            Block,
+            VersionedStateResponse,
+            PartialEncodedStateWitness,
+            PartialEncodedStateWitnessForward,
+            SyncSnapshotHosts,
+        ] {
+            let maximum_size = config.rate_limits[&key].maximum_size;
+            for _ in 0..maximum_size {
+                assert!(rate_limits.is_allowed_key(key, 1, clock.now()));
+            }
+            assert!(!rate_limits.is_allowed_key(key, 1, clock.now()), "{key}");
+        }
+        clock.advance(Duration::seconds(1));
+        assert!(rate_limits.is_allowed_key(Block, 1, clock.now()));
+    }
+    #[test]
+    fn test_standard_preset_does_not_limit_compressible_messages() {
+        use RateLimitedPeerMessageKey::*;
+        let config = Config::standard_preset();
+        for key in [
+            Block,
+            VersionedStateResponse,
+            PartialEncodedStateWitness,
+            PartialEncodedStateWitnessForward,
+            SyncSnapshotHosts,
+        ] {
+            assert!(!config.rate_limits.contains_key(&key), "{key}");
+        }
+    }
 }
@@ -80,6 +80,12 @@ impl TokenBucket {
         }
     }
 
+    /// Checks whether `tokens` tokens could be acquired at `now`, without acquiring them.
+    pub fn can_acquire(&mut self, tokens: u32, now: Instant) -> bool {
+        self.refill(now);
+        self.size >= to_tokens_with_parts(tokens)
+    }
+
     /// Refills the bucket with the right number of tokens according to
     /// the `refill_rate` and the new current time `now`.
     ///
@@ -178,6 +184,20 @@ mod tests {
         assert_eq!(bucket.size, to_tokens_with_parts(0));
     }
 
+    #[test]
+    fn can_acquire() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(5, 10, 1.0, now).expect("bucket should be well formed");
+
+        assert!(bucket.can_acquire(5, now));
+        assert!(!bucket.can_acquire(6, now));
+        assert_eq!(bucket.size, to_tokens_with_parts(5));
+
+        let now = now + Duration::seconds(1);
+        assert!(bucket.can_acquire(6, now));
+        assert_eq!(bucket.size, to_tokens_with_parts(6));
+    }
+
     #[test]
     fn max_is_zero() {
         let now = Instant::now();
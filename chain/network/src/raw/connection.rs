@@ -202,7 +202,7 @@ fn new_handshake(
     tracked_shards: Vec<ShardId>,
     archival: bool,
 ) -> PeerMessage {
-    PeerMessage::Tier2Handshake(Handshake {
+    let handshake = Handshake {
         protocol_version,
         oldest_supported_version: protocol_version - 2,
         sender_peer_id: my_peer_id.clone(),
@@ -218,7 +218,11 @@ fn new_handshake(
         },
         partial_edge_info: PartialEdgeInfo::new(my_peer_id, target_peer_id, nonce, secret_key),
         owned_account: None,
-    })
+        encrypted_transport: false,
+        message_compression: false,
+        encrypted_transport_signature: None,
+    };
+    PeerMessage::Tier2Handshake(handshake.sign_encrypted_transport(secret_key))
 }
 
 impl Connection {
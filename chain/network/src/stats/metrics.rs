@@ -204,6 +204,42 @@ pub(crate) static PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL: LazyLock<IntCounterVe
         )
         .unwrap()
     });
+pub(crate) static PEER_MESSAGE_COMPRESSED_BY_TYPE_TOTAL: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_peer_message_compressed_by_type_total",
+            "Number of compressed messages sent to or received from peers by message types",
+            &["direction", "type"],
+        )
+        .unwrap()
+    });
+pub(crate) static PEER_MESSAGE_COMPRESSED_BY_TYPE_BYTES: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_peer_message_compressed_by_type_bytes",
+            "Total size of compressed messages by message types, after compression",
+            &["direction", "type"],
+        )
+        .unwrap()
+    });
+pub(crate) static PEER_MESSAGE_COMPRESSED_BY_TYPE_UNCOMPRESSED_BYTES: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_peer_message_compressed_by_type_uncompressed_bytes",
+            "Total size of compressed messages by message types, before compression",
+            &["direction", "type"],
+        )
+        .unwrap()
+    });
+pub(crate) static PEER_MESSAGE_DECOMPRESSION_FAILED_BY_TYPE_TOTAL: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_peer_message_decompression_failed_by_type_total",
+            "Number of received compressed messages which were rejected by declared message types",
+            &["type"],
+        )
+        .unwrap()
+    });
 pub(crate) static SYNC_ACCOUNTS_DATA: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_sync_accounts_data",
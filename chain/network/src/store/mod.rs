@@ -1,7 +1,7 @@
 /// Store module defines atomic DB operations on top of schema module.
 /// All transactions should be implemented within this module,
 /// in particular schema::StoreUpdate is not exported.
-use crate::types::ConnectionInfo;
+use crate::types::{ConnectionInfo, PeerScore};
 use near_primitives::network::AnnounceAccount;
 use near_primitives::types::AccountId;
 use std::sync::Arc;
@@ -78,6 +78,25 @@ impl Store {
     }
 }
 
+// PeerScores storage.
+impl Store {
+    #[tracing::instrument(
+        target = "network::store",
+        level = "trace",
+        "Store::set_peer_scores",
+        skip_all
+    )]
+    pub fn set_peer_scores(&mut self, peer_scores: &Vec<PeerScore>) -> Result<(), Error> {
+        let mut update = self.0.new_update();
+        update.set::<schema::PeerScores>(&(), peer_scores);
+        self.0.commit(update).map_err(Error)
+    }
+
+    pub fn get_peer_scores(&self) -> Vec<PeerScore> {
+        self.0.get::<schema::PeerScores>(&()).unwrap_or(Some(vec![])).unwrap_or(vec![])
+    }
+}
+
 impl From<Arc<dyn near_store::db::Database>> for Store {
     fn from(store: Arc<dyn near_store::db::Database>) -> Self {
         Self(schema::Store::from(store))
@@ -58,6 +58,54 @@ impl BorshRepr for ConnectionInfoRepr {
     }
 }
 
+/// A Borsh representation of the primitives::PeerScore.
+#[derive(BorshSerialize, BorshDeserialize, ProtocolSchema)]
+pub(super) struct PeerScoreRepr {
+    peer_id: PeerId,
+    /// Score multiplied by 1000.
+    score_millis: i64,
+    /// UNIX timestamp in nanos.
+    updated_at: u64,
+    useful_responses: u64,
+    useless_responses: u64,
+    routing_successes: u64,
+    routing_failures: u64,
+    violations: u64,
+    avg_latency_micros: Option<u64>,
+}
+
+impl BorshRepr for PeerScoreRepr {
+    type T = primitives::PeerScore;
+    fn to_repr(s: &primitives::PeerScore) -> Self {
+        Self {
+            peer_id: s.peer_id.clone(),
+            score_millis: (s.score * 1000.).round() as i64,
+            updated_at: s.updated_at.unix_timestamp_nanos() as u64,
+            useful_responses: s.useful_responses,
+            useless_responses: s.useless_responses,
+            routing_successes: s.routing_successes,
+            routing_failures: s.routing_failures,
+            violations: s.violations,
+            avg_latency_micros: s.avg_latency.map(|d| d.whole_microseconds() as u64),
+        }
+    }
+
+    fn from_repr(s: Self) -> Result<primitives::PeerScore, Error> {
+        Ok(primitives::PeerScore {
+            peer_id: s.peer_id,
+            score: s.score_millis as f64 / 1000.,
+            updated_at: time::Utc::from_unix_timestamp_nanos(s.updated_at as i128)
+                .map_err(invalid_data)?,
+            useful_responses: s.useful_responses,
+            useless_responses: s.useless_responses,
+            routing_successes: s.routing_successes,
+            routing_failures: s.routing_failures,
+            violations: s.violations,
+            avg_latency: s.avg_latency_micros.map(|us| time::Duration::microseconds(us as i64)),
+        })
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, ProtocolSchema)]
 pub(super) struct EdgeRepr {
     key: (PeerId, PeerId),
@@ -102,6 +150,13 @@ impl Column for RecentOutboundConnections {
     type Value = Vec<ConnectionInfoRepr>;
 }
 
+pub(super) struct PeerScores;
+impl Column for PeerScores {
+    const COL: DBCol = DBCol::PeerScores;
+    type Key = Borsh<()>;
+    type Value = Vec<PeerScoreRepr>;
+}
+
 ////////////////////////////////////////////////////
 // Storage
 
@@ -141,6 +141,22 @@ pub struct ConnectionInfo {
     pub time_connected_until: time::Utc,
 }
 
+/// Reputation score of a peer, see `peer_manager::peer_score`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerScore {
+    pub peer_id: PeerId,
+    /// Score as of `updated_at`. It decays towards 0 over time.
+    pub score: f64,
+    pub updated_at: time::Utc,
+    pub useful_responses: u64,
+    pub useless_responses: u64,
+    pub routing_successes: u64,
+    pub routing_failures: u64,
+    pub violations: u64,
+    /// Moving average of the latency of block responses.
+    pub avg_latency: Option<time::Duration>,
+}
+
 impl KnownPeerStatus {
     pub fn is_banned(&self) -> bool {
         matches!(self, KnownPeerStatus::Banned(_, _))
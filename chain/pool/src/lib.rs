@@ -1,14 +1,16 @@
+use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
 
 use crate::types::{PoolKey, TransactionGroup, TransactionGroupIterator};
 
+use near_chain_configs::TransactionPoolOrdering;
 use near_crypto::PublicKey;
-use near_o11y::metrics::prometheus::core::{AtomicI64, GenericGauge};
+use near_o11y::metrics::prometheus::core::{AtomicI64, AtomicU64, GenericCounter, GenericGauge};
 use near_primitives::epoch_info::RngSeed;
 use near_primitives::hash::{hash, CryptoHash};
-use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::AccountId;
+use near_primitives::transaction::{Action, SignedTransaction};
+use near_primitives::types::{AccountId, Gas};
 use std::ops::Bound;
 
 mod metrics;
@@ -22,6 +24,33 @@ pub enum InsertTransactionResult {
     Duplicate,
     /// Not enough space to fit the transaction.
     NoSpaceLeft,
+    /// A transaction with the same signer, access key and nonce with at least the same priority
+    /// is already in the pool.
+    Underpriced,
+}
+
+/// Priority of a transaction in the pool: the priority fee it pays, and then the gas it prepays.
+/// Only `Transaction::V1` carries a priority fee, so until the runtime accepts such transactions
+/// they are told apart by the prepaid gas, which the signer buys at the same gas price.
+type TransactionPriority = (u64, Gas);
+
+/// Returns the priority of the transaction in the pool.
+fn transaction_priority(transaction: &SignedTransaction) -> TransactionPriority {
+    let transaction = &transaction.transaction;
+    (transaction.priority_fee().unwrap_or(0), prepaid_gas(transaction.actions()))
+}
+
+/// Returns the total gas prepaid by the actions, including the ones of delegate actions.
+fn prepaid_gas(actions: &[Action]) -> Gas {
+    actions
+        .iter()
+        .map(|action| match action {
+            Action::Delegate(signed_delegate_action) => {
+                prepaid_gas(&signed_delegate_action.delegate_action.get_actions())
+            }
+            action => action.get_prepaid_gas(),
+        })
+        .fold(0, Gas::saturating_add)
 }
 
 /// Transaction pool: keeps track of transactions that were not yet accepted into the block chain.
@@ -36,25 +65,32 @@ pub struct TransactionPool {
     key_seed: RngSeed,
     /// The key after which the pool iterator starts. Doesn't have to be present in the pool.
     last_used_key: PoolKey,
-    /// If set, new transactions that bring the size of the pool over this limit will be rejected.
+    /// If set, new transactions that bring the size of the pool over this limit will be rejected,
+    /// or with the `PriorityFee` ordering will evict transactions with lower priority.
     total_transaction_size_limit: Option<u64>,
     /// Total size of transactions in the pool measured in bytes.
     total_transaction_size: u64,
+    /// Order in which the pool iterator returns transaction groups.
+    ordering: TransactionPoolOrdering,
     /// Metrics tracked for transaction pool.
     transaction_pool_count_metric: GenericGauge<AtomicI64>,
     transaction_pool_size_metric: GenericGauge<AtomicI64>,
+    transaction_pool_evicted_metric: GenericCounter<AtomicU64>,
 }
 
 impl TransactionPool {
     pub fn new(
         key_seed: RngSeed,
         total_transaction_size_limit: Option<u64>,
+        ordering: TransactionPoolOrdering,
         metrics_label: &str,
     ) -> Self {
         let transaction_pool_count_metric =
             metrics::TRANSACTION_POOL_COUNT.with_label_values(&[metrics_label]);
         let transaction_pool_size_metric =
             metrics::TRANSACTION_POOL_SIZE.with_label_values(&[metrics_label]);
+        let transaction_pool_evicted_metric =
+            metrics::TRANSACTION_POOL_EVICTED_TOTAL.with_label_values(&[metrics_label]);
         // A `get()` call initializes a metric even if its value is zero.
         transaction_pool_count_metric.get();
         transaction_pool_size_metric.get();
@@ -66,8 +102,10 @@ impl TransactionPool {
             last_used_key: CryptoHash::default(),
             total_transaction_size_limit,
             total_transaction_size: 0,
+            ordering,
             transaction_pool_count_metric,
             transaction_pool_size_metric,
+            transaction_pool_evicted_metric,
         }
     }
 
@@ -79,42 +117,125 @@ impl TransactionPool {
     }
 
     /// Inserts a signed transaction that passed validation into the pool.
+    ///
+    /// With the `PriorityFee` ordering, a transaction replaces the pending one with the same
+    /// signer, access key and nonce if it has a higher priority, and transactions with lower
+    /// priorities are evicted if the pool is full.
     #[must_use]
     pub fn insert_transaction(
         &mut self,
         signed_transaction: SignedTransaction,
     ) -> InsertTransactionResult {
-        if !self.unique_transactions.insert(signed_transaction.get_hash()) {
+        if self.unique_transactions.contains(&signed_transaction.get_hash()) {
             // The hash of this transaction was already seen, skip it.
             return InsertTransactionResult::Duplicate;
         }
+        let signer_id = signed_transaction.transaction.signer_id();
+        let signer_public_key = signed_transaction.transaction.public_key();
+        let key = self.key(signer_id, signer_public_key);
+        let priority = match self.ordering {
+            TransactionPoolOrdering::RoundRobin => None,
+            TransactionPoolOrdering::PriorityFee => Some(transaction_priority(&signed_transaction)),
+        };
+
+        let replaced_transaction = match priority {
+            None => None,
+            Some(priority) => {
+                let nonce = signed_transaction.transaction.nonce();
+                let pending = self.transactions.get(&key).and_then(|transactions| {
+                    transactions.iter().find(|tx| tx.transaction.nonce() == nonce)
+                });
+                match pending {
+                    Some(pending) if transaction_priority(pending) >= priority => {
+                        return InsertTransactionResult::Underpriced;
+                    }
+                    pending => pending.cloned(),
+                }
+            }
+        };
+        let replaced_size = replaced_transaction.as_ref().map_or(0, |tx| tx.get_size());
+
         // We never expect the total size to go over `u64` during real operation as that would
         // be more than 10^9 GiB of RAM consumed for transaction pool, so panicing here is intended
         // to catch a logic error in estimation of transaction size.
         let new_total_transaction_size = self
             .total_transaction_size
             .checked_add(signed_transaction.get_size())
-            .expect("Total transaction size is too large");
+            .expect("Total transaction size is too large")
+            - replaced_size;
         if let Some(limit) = self.total_transaction_size_limit {
             if new_total_transaction_size > limit {
-                return InsertTransactionResult::NoSpaceLeft;
+                let evicted = match priority {
+                    None => false,
+                    Some(priority) => self.evict_transactions(
+                        new_total_transaction_size - limit,
+                        priority,
+                        replaced_transaction.as_ref(),
+                    ),
+                };
+                if !evicted {
+                    return InsertTransactionResult::NoSpaceLeft;
+                }
             }
         }
 
         // At this point transaction is accepted to the pool.
-        self.total_transaction_size = new_total_transaction_size;
-        let signer_id = signed_transaction.transaction.signer_id();
-        let signer_public_key = signed_transaction.transaction.public_key();
-        self.transactions
-            .entry(self.key(signer_id, signer_public_key))
-            .or_insert_with(Vec::new)
-            .push(signed_transaction);
+        if let Some(replaced_transaction) = replaced_transaction {
+            self.remove_transactions(&[replaced_transaction]);
+        }
+        self.unique_transactions.insert(signed_transaction.get_hash());
+        self.total_transaction_size = self
+            .total_transaction_size
+            .checked_add(signed_transaction.get_size())
+            .expect("Total transaction size is too large");
+        self.transactions.entry(key).or_insert_with(Vec::new).push(signed_transaction);
 
         self.transaction_pool_count_metric.inc();
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
         InsertTransactionResult::Success
     }
 
+    /// Evicts transactions with priority lower than `priority` to free up at least
+    /// `required_size` bytes, excluding the `replaced` transaction which is going to be removed
+    /// anyway.  Transactions with the lowest priority, and among them the ones with the highest
+    /// nonces, are evicted first.
+    ///
+    /// Returns false and doesn't evict anything if not enough space can be freed.
+    fn evict_transactions(
+        &mut self,
+        required_size: u64,
+        priority: TransactionPriority,
+        replaced: Option<&SignedTransaction>,
+    ) -> bool {
+        let replaced_hash = replaced.map(|tx| tx.get_hash());
+        // Eviction only happens when the pool is full, so it's fine to go through the whole pool
+        // rather than maintain an index of transactions by priority.
+        let mut candidates: Vec<&SignedTransaction> = self
+            .transactions
+            .values()
+            .flatten()
+            .filter(|tx| transaction_priority(tx) < priority)
+            .filter(|tx| Some(tx.get_hash()) != replaced_hash)
+            .collect();
+        candidates.sort_by_key(|tx| (transaction_priority(tx), Reverse(tx.transaction.nonce())));
+
+        let mut evicted = vec![];
+        let mut evicted_size = 0;
+        for tx in candidates {
+            if evicted_size >= required_size {
+                break;
+            }
+            evicted_size += tx.get_size();
+            evicted.push(tx.clone());
+        }
+        if evicted_size < required_size {
+            return false;
+        }
+        self.transaction_pool_evicted_metric.inc_by(evicted.len() as u64);
+        self.remove_transactions(&evicted);
+        true
+    }
+
     /// Returns a pool iterator wrapper that implements an iterator-like trait to iterate over
     /// transaction groups in the proper order defined by the protocol.
     /// When the iterator is dropped, all remaining groups are inserted back into the pool.
@@ -171,10 +292,20 @@ impl TransactionPool {
         self.unique_transactions.len()
     }
 
+    /// Returns whether the transaction with the given hash is in the pool.
+    pub fn contains(&self, tx_hash: &CryptoHash) -> bool {
+        self.unique_transactions.contains(tx_hash)
+    }
+
     /// Returns the total size of transactions in the pool in bytes.
     pub fn transaction_size(&self) -> u64 {
         self.total_transaction_size
     }
+
+    /// Returns all transactions in the pool in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.transactions.values().flatten()
+    }
 }
 
 /// PoolIterator is a structure to pull transactions from the pool.
@@ -186,11 +317,65 @@ pub struct PoolIteratorWrapper<'a> {
 
     /// Queue of transaction groups. Each group there is sorted by nonce.
     sorted_groups: VecDeque<TransactionGroup>,
+
+    /// With the `PriorityFee` ordering, indices of non-empty groups in `sorted_groups` by
+    /// priority of their next transaction.  Groups with the same priority are ordered by the
+    /// sequence number of their insertion into the queue, so that they are taken in round robin.
+    priority_queue: BinaryHeap<(TransactionPriority, Reverse<u64>, usize)>,
+    /// Sequence number of the next group pushed into `priority_queue`.
+    next_sequence_number: u64,
+    /// Index of the group returned by the last call to `next()`, which isn't in the
+    /// `priority_queue` as its next transaction may have been pulled since.
+    returned_group: Option<usize>,
 }
 
 impl<'a> PoolIteratorWrapper<'a> {
     pub fn new(pool: &'a mut TransactionPool) -> Self {
-        Self { pool, sorted_groups: Default::default() }
+        Self {
+            pool,
+            sorted_groups: Default::default(),
+            priority_queue: Default::default(),
+            next_sequence_number: 0,
+            returned_group: None,
+        }
+    }
+
+    /// Returns the group whose next transaction has the highest priority.
+    ///
+    /// All groups are taken from the pool on the first call, starting after the last used key as
+    /// with the `RoundRobin` ordering, so that groups with the same priority are rotated between
+    /// calls to `pool_iterator`.  Emptied groups stay in the sorted groups queue until the
+    /// iterator is dropped.
+    fn next_by_priority(&mut self) -> Option<&mut TransactionGroup> {
+        if let Some(index) = self.returned_group.take() {
+            self.push_to_priority_queue(index);
+        }
+        let last_used_key = self.pool.last_used_key;
+        let (before, after): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pool.transactions)
+            .into_iter()
+            .partition(|(key, _)| *key <= last_used_key);
+        for (key, mut transactions) in after.into_iter().chain(before) {
+            transactions.sort_by_key(|st| Reverse(st.transaction.nonce()));
+            self.sorted_groups.push_back(TransactionGroup {
+                key,
+                transactions,
+                removed_transaction_hashes: vec![],
+                removed_transaction_size: 0,
+            });
+            self.push_to_priority_queue(self.sorted_groups.len() - 1);
+        }
+        let (_, _, index) = self.priority_queue.pop()?;
+        self.returned_group = Some(index);
+        self.pool.last_used_key = self.sorted_groups[index].key;
+        Some(&mut self.sorted_groups[index])
+    }
+
+    fn push_to_priority_queue(&mut self, index: usize) {
+        if let Some(transaction) = self.sorted_groups[index].peek_next() {
+            let priority = transaction_priority(transaction);
+            self.priority_queue.push((priority, Reverse(self.next_sequence_number), index));
+            self.next_sequence_number += 1;
+        }
     }
 }
 
@@ -211,8 +396,14 @@ impl<'a> PoolIteratorWrapper<'a> {
 ///
 /// When the iterator is dropped, `unique_transactions` in the pool is updated for every group.
 /// And all non-empty group from the sorted groups queue are inserted back into the pool.
+///
+/// With the `PriorityFee` ordering groups are returned by priority instead, see
+/// `next_by_priority`.
 impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
     fn next(&mut self) -> Option<&mut TransactionGroup> {
+        if self.pool.ordering == TransactionPoolOrdering::PriorityFee {
+            return self.next_by_priority();
+        }
         if !self.pool.transactions.is_empty() {
             let key = *self
                 .pool
@@ -230,7 +421,7 @@ impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
             self.pool.last_used_key = key;
             let mut transactions =
                 self.pool.transactions.remove(&key).expect("just checked existence");
-            transactions.sort_by_key(|st| std::cmp::Reverse(st.transaction.nonce()));
+            transactions.sort_by_key(|st| Reverse(st.transaction.nonce()));
             self.sorted_groups.push_back(TransactionGroup {
                 key,
                 transactions,
@@ -340,6 +531,7 @@ mod tests {
     use near_crypto::{InMemorySigner, KeyType};
 
     use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::{Action, TransferAction};
     use near_primitives::types::Balance;
 
     const TEST_SEED: RngSeed = [3; 32];
@@ -367,11 +559,32 @@ mod tests {
             .collect()
     }
 
+    /// Creates a transaction paying the given priority fee.
+    fn transaction_with_priority_fee(
+        signer_id: &str,
+        nonce: u64,
+        deposit: Balance,
+        priority_fee: u64,
+    ) -> SignedTransaction {
+        let signer_id: AccountId = signer_id.parse().unwrap();
+        let signer = InMemorySigner::test_signer(&signer_id);
+        SignedTransaction::from_actions_v1(
+            nonce,
+            signer_id,
+            "bob.near".parse().unwrap(),
+            &signer,
+            vec![Action::Transfer(TransferAction { deposit })],
+            CryptoHash::default(),
+            priority_fee,
+        )
+    }
+
     fn process_txs_to_nonces(
         mut transactions: Vec<SignedTransaction>,
         expected_weight: u32,
     ) -> (Vec<u64>, TransactionPool) {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::RoundRobin, "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions {
@@ -482,7 +695,8 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::RoundRobin, "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions.clone() {
@@ -592,7 +806,8 @@ mod tests {
 
     #[test]
     fn test_transaction_pool_size() {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::RoundRobin, "");
         let transactions = generate_transactions("alice.near", "alice.near", 1, 100);
         let mut total_transaction_size = 0;
         // Adding transactions increases the size.
@@ -616,7 +831,12 @@ mod tests {
         // Each transaction is at least 1 byte in size, so the last transaction will not fit.
         let pool_size_limit =
             transactions.iter().map(|tx| tx.get_size()).sum::<u64>().checked_sub(1).unwrap();
-        let mut pool = TransactionPool::new(TEST_SEED, Some(pool_size_limit), "");
+        let mut pool = TransactionPool::new(
+            TEST_SEED,
+            Some(pool_size_limit),
+            TransactionPoolOrdering::RoundRobin,
+            "",
+        );
         for (i, tx) in transactions.iter().cloned().enumerate() {
             if i + 1 < transactions.len() {
                 assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
@@ -625,4 +845,206 @@ mod tests {
             }
         }
     }
+
+    /// Groups are taken by the priority of their next transaction, and transactions within
+    /// a group are still taken in the order of nonces.
+    #[test]
+    fn test_priority_fee_ordering() {
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::PriorityFee, "");
+        let transactions = vec![
+            transaction_with_priority_fee("alice.near", 1, 1, 1),
+            transaction_with_priority_fee("alice.near", 2, 1, 10),
+            transaction_with_priority_fee("bob.near", 1, 1, 5),
+            transaction_with_priority_fee("bob.near", 2, 1, 5),
+            transaction_with_priority_fee("carol.near", 1, 1, 3),
+        ];
+        for tx in transactions {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+
+        let order = prepare_transactions(&mut pool, 5)
+            .into_iter()
+            .map(|tx| (tx.transaction.signer_id().to_string(), tx.transaction.nonce()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                ("bob.near".to_string(), 1),
+                ("bob.near".to_string(), 2),
+                ("carol.near".to_string(), 1),
+                ("alice.near".to_string(), 1),
+                ("alice.near".to_string(), 2),
+            ]
+        );
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.transaction_size(), 0);
+    }
+
+    /// Groups with the same priority are rotated between iterators as with the round robin
+    /// ordering, while a group paying more is taken first however it's placed in the rotation.
+    #[test]
+    fn test_priority_fee_ordering_remembers_the_last_key() {
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::PriorityFee, "");
+        let signers: Vec<String> = (1..=6).map(|i| format!("user_{i}.near")).collect();
+        for signer_id in &signers {
+            let tx = transaction_with_priority_fee(signer_id, 1, 1, 1);
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        let signers_of = |txs: Vec<SignedTransaction>| -> Vec<String> {
+            txs.iter().map(|tx| tx.transaction.signer_id().to_string()).collect()
+        };
+
+        let first = signers_of(prepare_transactions(&mut pool, 3));
+        assert_eq!(pool.len(), 3);
+        for signer_id in &first {
+            let tx = transaction_with_priority_fee(signer_id, 2, 1, 1);
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        // The groups which were skipped by the first iterator are taken next.
+        let second = signers_of(prepare_transactions(&mut pool, 3));
+        assert!(second.iter().all(|signer_id| !first.contains(signer_id)), "{second:?}");
+
+        // A higher priority is taken before the rotation, whose position is kept.
+        let expensive = transaction_with_priority_fee("user_7.near", 1, 1, 10);
+        assert_eq!(pool.insert_transaction(expensive), InsertTransactionResult::Success);
+        let third = signers_of(prepare_transactions(&mut pool, 4));
+        assert_eq!(third[0], "user_7.near");
+        let mut rest = third[1..].to_vec();
+        rest.sort();
+        let mut expected = first.clone();
+        expected.sort();
+        assert_eq!(rest, expected);
+    }
+
+    /// Transactions which are not taken by the iterator return to the pool.
+    #[test]
+    fn test_priority_fee_ordering_retain() {
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::PriorityFee, "");
+        for tx in generate_transactions("alice.near", "alice.near", 1, 10) {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        let nonces: Vec<u64> =
+            prepare_transactions(&mut pool, 4).iter().map(|tx| tx.transaction.nonce()).collect();
+        assert_eq!(nonces, vec![1, 2, 3, 4]);
+        assert_eq!(pool.len(), 6);
+        let nonces: Vec<u64> =
+            prepare_transactions(&mut pool, 10).iter().map(|tx| tx.transaction.nonce()).collect();
+        assert_eq!(nonces, vec![5, 6, 7, 8, 9, 10]);
+        assert_eq!(pool.transaction_size(), 0);
+    }
+
+    #[test]
+    fn test_replace_by_nonce() {
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::PriorityFee, "");
+        let pending = transaction_with_priority_fee("alice.near", 1, 1, 5);
+        assert_eq!(pool.insert_transaction(pending.clone()), InsertTransactionResult::Success);
+
+        let same_fee = transaction_with_priority_fee("alice.near", 1, 2, 5);
+        assert_eq!(pool.insert_transaction(same_fee), InsertTransactionResult::Underpriced);
+        let lower_fee = transaction_with_priority_fee("alice.near", 1, 3, 4);
+        assert_eq!(pool.insert_transaction(lower_fee), InsertTransactionResult::Underpriced);
+
+        let replacement = transaction_with_priority_fee("alice.near", 1, 4, 6);
+        assert_eq!(pool.insert_transaction(replacement.clone()), InsertTransactionResult::Success);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.transaction_size(), replacement.get_size());
+
+        // The replaced transaction is not a duplicate anymore.
+        assert_eq!(pool.insert_transaction(pending), InsertTransactionResult::Underpriced);
+        assert_eq!(prepare_transactions(&mut pool, 10), vec![replacement]);
+    }
+
+    /// With the round robin ordering transactions with the same nonce are kept side by side.
+    #[test]
+    fn test_no_replace_by_nonce_in_round_robin() {
+        let mut pool =
+            TransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::RoundRobin, "");
+        let pending = transaction_with_priority_fee("alice.near", 1, 1, 5);
+        assert_eq!(pool.insert_transaction(pending), InsertTransactionResult::Success);
+        let same_nonce = transaction_with_priority_fee("alice.near", 1, 2, 1);
+        assert_eq!(pool.insert_transaction(same_nonce), InsertTransactionResult::Success);
+        assert_eq!(pool.len(), 2);
+    }
+
+    /// Transactions without a priority fee are prioritized by the gas they prepay, so they replace
+    /// and evict transactions prepaying less gas.
+    #[test]
+    fn test_priority_fee_ordering_by_prepaid_gas() {
+        let signer_id: AccountId = "alice.near".parse().unwrap();
+        let signer = InMemorySigner::test_signer(&signer_id);
+        let call = |nonce, gas| {
+            SignedTransaction::call(
+                nonce,
+                signer_id.clone(),
+                "bob.near".parse().unwrap(),
+                &signer,
+                0,
+                "method".to_string(),
+                vec![],
+                gas,
+                CryptoHash::default(),
+            )
+        };
+        let pool_size_limit = 2 * call(1, 1).get_size();
+        let mut pool = TransactionPool::new(
+            TEST_SEED,
+            Some(pool_size_limit),
+            TransactionPoolOrdering::PriorityFee,
+            "",
+        );
+        assert_eq!(pool.insert_transaction(call(1, 1)), InsertTransactionResult::Success);
+        assert_eq!(pool.insert_transaction(call(2, 2)), InsertTransactionResult::Success);
+        assert_eq!(pool.insert_transaction(call(1, 1)), InsertTransactionResult::Duplicate);
+        assert_eq!(pool.insert_transaction(call(2, 1)), InsertTransactionResult::Underpriced);
+        assert_eq!(pool.insert_transaction(call(3, 1)), InsertTransactionResult::NoSpaceLeft);
+
+        assert_eq!(pool.insert_transaction(call(1, 3)), InsertTransactionResult::Success);
+        assert_eq!(pool.insert_transaction(call(3, 3)), InsertTransactionResult::Success);
+        assert_eq!(pool.len(), 2);
+        let gas: Vec<Gas> = prepare_transactions(&mut pool, 10)
+            .iter()
+            .map(|tx| prepaid_gas(tx.transaction.actions()))
+            .collect();
+        assert_eq!(gas, vec![3, 3]);
+    }
+
+    #[test]
+    fn test_evict_lowest_priority() {
+        // Names of all signers have the same length so that all transactions have the same size.
+        let transactions = vec![
+            transaction_with_priority_fee("alice.near", 1, 1, 2),
+            transaction_with_priority_fee("bob.near", 1, 1, 1),
+            transaction_with_priority_fee("bob.near", 2, 1, 1),
+        ];
+        let pool_size_limit = transactions.iter().map(|tx| tx.get_size()).sum::<u64>();
+        let mut pool = TransactionPool::new(
+            TEST_SEED,
+            Some(pool_size_limit),
+            TransactionPoolOrdering::PriorityFee,
+            "",
+        );
+        for tx in transactions.iter().cloned() {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+
+        // Transactions paying less than everything in the pool are rejected.
+        let cheap = transaction_with_priority_fee("dan.near", 1, 1, 0);
+        assert_eq!(pool.insert_transaction(cheap), InsertTransactionResult::NoSpaceLeft);
+        assert_eq!(pool.len(), 3);
+
+        // The highest nonce of the lowest priority is evicted first.
+        let expensive = transaction_with_priority_fee("dan.near", 1, 1, 3);
+        assert_eq!(pool.insert_transaction(expensive.clone()), InsertTransactionResult::Success);
+        assert_eq!(pool.len(), 3);
+        assert!(pool.transaction_size() <= pool_size_limit);
+        let mut remaining = prepare_transactions(&mut pool, 10);
+        remaining.sort_by_key(|tx| tx.get_hash());
+        let mut expected = vec![transactions[0].clone(), transactions[1].clone(), expensive];
+        expected.sort_by_key(|tx| tx.get_hash());
+        assert_eq!(remaining, expected);
+    }
 }
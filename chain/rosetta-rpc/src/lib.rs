@@ -822,6 +822,10 @@ async fn construction_submit(
         near_client::ProcessTxResponse::InvalidTx(error) => {
             Err(errors::ErrorKind::InvalidInput(error.to_string()).into())
         }
+        near_client::ProcessTxResponse::Underpriced => Err(errors::ErrorKind::InvalidInput(
+            "Transaction with the same nonce and at least the same priority is pending".to_string(),
+        )
+        .into()),
         _ => Err(errors::ErrorKind::InternalInvariantError(format!(
             "Transaction submition return unexpected result: {:?}",
             transaction_submittion
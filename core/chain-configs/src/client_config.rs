@@ -73,6 +73,42 @@ impl GCConfig {
     }
 }
 
+/// Configuration of the data scrubber, which checks the integrity of the
+/// database in the background.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ScrubberConfig {
+    /// Whether to run the scrubber.
+    pub enabled: bool,
+    /// How often the scrubber makes a step.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub step_period: Duration,
+    /// Maximum number of database entries (trie nodes, flat state values or
+    /// blocks) checked at every step.  Together with `step_period` limits
+    /// the load the scrubber puts on the database.
+    pub max_entries_per_step: u64,
+    /// Whether to overwrite `FlatState` values which don't match the trie
+    /// with values from the trie.  Values are written while the flat storage
+    /// is locked, and only if its head hasn't moved since the check.
+    pub repair_flat_state: bool,
+    /// Whether to restore corrupted trie nodes from the state parts in the
+    /// external storage configured for state sync.  Only nodes whose
+    /// reference count is intact are restored; missing nodes are reported.
+    pub repair_state_from_external_storage: bool,
+}
+
+impl Default for ScrubberConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            step_period: Duration::seconds(1),
+            max_entries_per_step: 1000,
+            repair_flat_state: false,
+            repair_state_from_external_storage: false,
+        }
+    }
+}
+
 fn default_num_concurrent_requests() -> u32 {
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL
 }
@@ -117,6 +153,11 @@ pub enum ExternalStorageLocation {
     GCS {
         bucket: String,
     },
+    /// Read-only mirror of a state dump served over HTTP(S). Objects are fetched from
+    /// `<base_url>/<location>`, where the location has the same layout as in the buckets.
+    Http {
+        base_url: String,
+    },
 }
 
 /// Configures how to dump state to external storage.
@@ -247,6 +288,24 @@ impl ReshardingHandle {
     }
 }
 
+/// Order in which chunk producers take transactions from the transaction pool.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionPoolOrdering {
+    /// Groups of transactions with the same signer and access key are taken
+    /// in round robin, starting from a random group.  A full pool rejects new
+    /// transactions.
+    #[default]
+    RoundRobin,
+    /// Groups whose next transaction has the highest priority are taken
+    /// first.  Transactions are prioritized by the priority fee they pay and
+    /// then by the gas they prepay.  A transaction with a higher priority
+    /// replaces a pending one with the same signer, access key and nonce, and
+    /// a full pool evicts transactions with lower priorities to make space for
+    /// new ones.  Groups with the same priority are taken in round robin.
+    PriorityFee,
+}
+
 /// Configuration for resharding.
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
 #[serde(default)]
@@ -381,6 +440,10 @@ pub fn default_transaction_pool_size_limit() -> Option<u64> {
     Some(100_000_000) // 100 MB.
 }
 
+pub fn default_transaction_pool_save_period() -> Duration {
+    Duration::minutes(1)
+}
+
 pub fn default_tx_routing_height_horizon() -> BlockHeightDelta {
     4
 }
@@ -496,6 +559,8 @@ pub struct ClientConfig {
     pub block_header_fetch_horizon: BlockHeightDelta,
     /// Garbage collection configuration.
     pub gc: GCConfig,
+    /// Data scrubber configuration.
+    pub scrubber: ScrubberConfig,
     /// Accounts that this client tracks.
     pub tracked_accounts: Vec<AccountId>,
     /// Track shards that should be tracked by given validator.
@@ -537,6 +602,13 @@ pub struct ClientConfig {
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
     /// will be unbounded.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Order in which transactions are taken from the transaction pool.
+    pub transaction_pool_ordering: TransactionPoolOrdering,
+    /// Whether to save the transaction pool to the database periodically and on shutdown, and
+    /// restore it on startup.
+    pub save_transaction_pool: bool,
+    /// How often the transaction pool is saved to the database.
+    pub transaction_pool_save_period: Duration,
     // Allows more detailed logging, for example a list of orphaned blocks.
     pub enable_multiline_logging: bool,
     // Configuration for resharding.
@@ -569,6 +641,10 @@ pub struct ClientConfig {
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// Whether to maintain the index of transactions and receipts which touched each account in
+    /// DBCol::AccountActivity.  The index is served by the `EXPERIMENTAL_account_history` and
+    /// `EXPERIMENTAL_access_key_history` JSON RPC methods.
+    pub save_account_activity: bool,
 }
 
 impl ClientConfig {
@@ -627,6 +703,7 @@ impl ClientConfig {
             doosmslug_step_period: Duration::milliseconds(100),
             block_header_fetch_horizon: 50,
             gc: GCConfig { gc_blocks_limit: 100, ..GCConfig::default() },
+            scrubber: ScrubberConfig::default(),
             tracked_accounts: vec![],
             tracked_shadow_validator: None,
             tracked_shards: vec![],
@@ -644,6 +721,9 @@ impl ClientConfig {
             state_sync: StateSyncConfig::default(),
             epoch_sync: EpochSyncConfig::default(),
             transaction_pool_size_limit: None,
+            transaction_pool_ordering: TransactionPoolOrdering::default(),
+            save_transaction_pool: false,
+            transaction_pool_save_period: default_transaction_pool_save_period(),
             enable_multiline_logging: false,
             resharding_config: MutableConfigValue::new(
                 ReshardingConfig::default(),
@@ -658,6 +738,7 @@ impl ClientConfig {
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
             save_latest_witnesses: false,
+            save_account_activity: false,
         }
     }
 }
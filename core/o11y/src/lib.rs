@@ -19,6 +19,7 @@ pub mod log_config;
 mod log_counter;
 pub mod macros;
 pub mod metrics;
+pub mod metrics_push;
 mod opentelemetry;
 mod reload;
 mod subscriber;
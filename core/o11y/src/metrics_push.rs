@@ -0,0 +1,462 @@
+//! Pushing metrics to an external collector.
+//!
+//! Metrics are normally exported by the `/metrics` endpoint of the JSON RPC
+//! server, which requires the node to be reachable by the scraper.  Nodes
+//! without a scrape path, e.g. behind NAT, can instead periodically push the
+//! whole Prometheus registry either as OTLP metrics over HTTP or to a
+//! Prometheus Pushgateway.  Pushed metrics carry the identity of the node:
+//! its chain id, node key and validator account id.
+
+use near_crypto::PublicKey;
+use near_primitives_core::types::AccountId;
+use near_time::Duration;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric, number_data_point, summary_data_point, AggregationTemporality, Gauge, Histogram,
+    HistogramDataPoint, Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum, Summary,
+    SummaryDataPoint,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use prometheus::proto::{MetricFamily, MetricType};
+use prometheus::{Encoder, TextEncoder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsPushProtocol {
+    /// OTLP over HTTP with protobuf payloads.  The endpoint is the URL of the
+    /// metrics endpoint of the collector, e.g. `http://localhost:4318/v1/metrics`.
+    #[default]
+    Otlp,
+    /// Prometheus text format pushed to a Pushgateway.  The endpoint is the
+    /// base URL of the gateway, e.g. `http://localhost:9091`.
+    Pushgateway,
+}
+
+/// Configures pushing metrics to an external collector.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MetricsPushConfig {
+    /// URL metrics are pushed to, see `MetricsPushProtocol` for its meaning.
+    pub endpoint: String,
+    pub protocol: MetricsPushProtocol,
+    /// How often metrics are pushed.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub period: Duration,
+    /// Timeout of a single push.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub timeout: Duration,
+    /// Name of the job.  Used as the `job` grouping key of the Pushgateway and
+    /// as the `service.name` resource attribute of OTLP metrics.
+    pub job: String,
+    /// Labels added to all pushed metrics besides the identity of the node.
+    pub labels: BTreeMap<String, String>,
+}
+
+impl Default for MetricsPushConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:4318/v1/metrics".to_string(),
+            protocol: MetricsPushProtocol::Otlp,
+            period: Duration::seconds(15),
+            timeout: Duration::seconds(10),
+            job: "neard".to_string(),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MetricsPushError {
+    #[error("failed to encode metrics: {0}")]
+    Encode(#[from] prometheus::Error),
+    #[error("failed to send metrics: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("collector responded with {status}: {body}")]
+    Status { status: reqwest::StatusCode, body: String },
+}
+
+/// Pushes the metrics from the default Prometheus registry.
+pub struct MetricsPusher {
+    config: MetricsPushConfig,
+    client: reqwest::Client,
+    /// Identity of the node followed by the labels from the config.
+    labels: Vec<(String, String)>,
+    /// Start of the cumulative metrics reported over OTLP.
+    start_time: SystemTime,
+}
+
+impl MetricsPusher {
+    pub fn new(
+        config: MetricsPushConfig,
+        chain_id: String,
+        node_public_key: PublicKey,
+        account_id: Option<AccountId>,
+    ) -> Result<Self, MetricsPushError> {
+        let client = reqwest::Client::builder().timeout(config.timeout.unsigned_abs()).build()?;
+        let mut labels = vec![
+            ("chain_id".to_string(), chain_id),
+            ("node_id".to_string(), node_public_key.to_string()),
+        ];
+        if let Some(account_id) = account_id {
+            labels.push(("account_id".to_string(), account_id.to_string()));
+        }
+        labels.extend(config.labels.iter().map(|(name, value)| (name.clone(), value.clone())));
+        Ok(Self { config, client, labels, start_time: SystemTime::now() })
+    }
+
+    pub fn period(&self) -> Duration {
+        self.config.period
+    }
+
+    /// Pushes the current values of all registered metrics.
+    pub async fn push(&self) -> Result<(), MetricsPushError> {
+        let families = crate::metrics::gather();
+        let request = match self.config.protocol {
+            MetricsPushProtocol::Otlp => {
+                let request = to_otlp_request(
+                    &families,
+                    &self.config.job,
+                    &self.labels,
+                    unix_nanos(self.start_time),
+                    unix_nanos(SystemTime::now()),
+                );
+                self.client
+                    .post(&self.config.endpoint)
+                    .header(reqwest::header::CONTENT_TYPE, "application/x-protobuf")
+                    .body(prost::Message::encode_to_vec(&request))
+            }
+            MetricsPushProtocol::Pushgateway => {
+                let mut body = vec![];
+                TextEncoder::new().encode(&families, &mut body)?;
+                // PUT replaces all metrics of the group, so metrics which are
+                // no longer registered don't linger in the gateway.
+                self.client
+                    .put(pushgateway_url(&self.config.endpoint, &self.config.job, &self.labels))
+                    .header(reqwest::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)
+                    .body(body)
+            }
+        };
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(MetricsPushError::Status { status, body });
+        }
+        Ok(())
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+/// Returns the URL of the group identified by the job and the labels.
+/// Label values which can't be a part of the path are base64 encoded.
+fn pushgateway_url(endpoint: &str, job: &str, labels: &[(String, String)]) -> String {
+    use base64::Engine;
+
+    let mut url = format!("{}/metrics/job/{}", endpoint.trim_end_matches('/'), job);
+    for (name, value) in labels {
+        if value.is_empty() || value.contains('/') {
+            let value = base64::engine::general_purpose::URL_SAFE.encode(value);
+            url.push_str(&format!("/{}@base64/{}", name, value));
+        } else {
+            url.push_str(&format!("/{}/{}", name, value));
+        }
+    }
+    url
+}
+
+fn string_attribute(key: &str, value: &str) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue { value: Some(any_value::Value::StringValue(value.to_string())) }),
+    }
+}
+
+/// Converts Prometheus metric families to an OTLP export request.
+/// Counters, histograms and summaries are reported as cumulative since
+/// `start_time`.
+fn to_otlp_request(
+    families: &[MetricFamily],
+    job: &str,
+    labels: &[(String, String)],
+    start_time: u64,
+    time: u64,
+) -> ExportMetricsServiceRequest {
+    let mut attributes = vec![string_attribute("service.name", job)];
+    attributes.extend(labels.iter().map(|(name, value)| string_attribute(name, value)));
+
+    let metrics = families.iter().filter_map(|family| to_otlp_metric(family, start_time, time));
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: Some(Resource { attributes, ..Default::default() }),
+            scope_metrics: vec![ScopeMetrics {
+                scope: Some(InstrumentationScope {
+                    name: "near-o11y".to_string(),
+                    ..Default::default()
+                }),
+                metrics: metrics.collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+fn to_otlp_metric(family: &MetricFamily, start_time_unix_nano: u64, time: u64) -> Option<Metric> {
+    let metrics = family.get_metric();
+    if metrics.is_empty() {
+        return None;
+    }
+    let attributes = |metric: &prometheus::proto::Metric| {
+        metric.get_label().iter().map(|label| string_attribute(label.get_name(), label.get_value()))
+    };
+    let number_data_point = |metric: &prometheus::proto::Metric, value: f64| NumberDataPoint {
+        attributes: attributes(metric).collect(),
+        start_time_unix_nano,
+        time_unix_nano: time,
+        value: Some(number_data_point::Value::AsDouble(value)),
+        ..Default::default()
+    };
+    let cumulative = AggregationTemporality::Cumulative as i32;
+
+    let data = match family.get_field_type() {
+        MetricType::COUNTER => metric::Data::Sum(Sum {
+            data_points: metrics
+                .iter()
+                .map(|metric| number_data_point(metric, metric.get_counter().get_value()))
+                .collect(),
+            aggregation_temporality: cumulative,
+            is_monotonic: true,
+        }),
+        MetricType::GAUGE => metric::Data::Gauge(Gauge {
+            data_points: metrics
+                .iter()
+                .map(|metric| number_data_point(metric, metric.get_gauge().get_value()))
+                .collect(),
+        }),
+        MetricType::UNTYPED => metric::Data::Gauge(Gauge {
+            data_points: metrics
+                .iter()
+                .map(|metric| number_data_point(metric, metric.get_untyped().get_value()))
+                .collect(),
+        }),
+        MetricType::HISTOGRAM => metric::Data::Histogram(Histogram {
+            data_points: metrics
+                .iter()
+                .map(|metric| {
+                    let histogram = metric.get_histogram();
+                    // Prometheus buckets are cumulative while OTLP ones hold
+                    // the counts of samples falling into each bucket only.
+                    let mut explicit_bounds = vec![];
+                    let mut bucket_counts = vec![];
+                    let mut previous_count = 0;
+                    for bucket in histogram.get_bucket() {
+                        if bucket.get_upper_bound().is_infinite() {
+                            continue;
+                        }
+                        explicit_bounds.push(bucket.get_upper_bound());
+                        let count = bucket.get_cumulative_count();
+                        bucket_counts.push(count.saturating_sub(previous_count));
+                        previous_count = count;
+                    }
+                    bucket_counts.push(histogram.get_sample_count().saturating_sub(previous_count));
+                    HistogramDataPoint {
+                        attributes: attributes(metric).collect(),
+                        start_time_unix_nano,
+                        time_unix_nano: time,
+                        count: histogram.get_sample_count(),
+                        sum: Some(histogram.get_sample_sum()),
+                        bucket_counts,
+                        explicit_bounds,
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            aggregation_temporality: cumulative,
+        }),
+        MetricType::SUMMARY => metric::Data::Summary(Summary {
+            data_points: metrics
+                .iter()
+                .map(|metric| {
+                    let summary = metric.get_summary();
+                    SummaryDataPoint {
+                        attributes: attributes(metric).collect(),
+                        start_time_unix_nano,
+                        time_unix_nano: time,
+                        count: summary.get_sample_count(),
+                        sum: summary.get_sample_sum(),
+                        quantile_values: summary
+                            .get_quantile()
+                            .iter()
+                            .map(|quantile| summary_data_point::ValueAtQuantile {
+                                quantile: quantile.get_quantile(),
+                                value: quantile.get_value(),
+                            })
+                            .collect(),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+        }),
+    };
+    Some(Metric {
+        name: family.get_name().to_string(),
+        description: family.get_help().to_string(),
+        data: Some(data),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server};
+    use near_crypto::KeyType;
+    use prometheus::{HistogramOpts, HistogramVec, IntCounter, Opts, Registry};
+    use std::convert::Infallible;
+    use tokio::sync::mpsc;
+
+    /// Pushed request as seen by the collector.
+    struct PushedRequest {
+        method: hyper::Method,
+        path: String,
+        body: Vec<u8>,
+    }
+
+    /// Starts a stand-in for a collector which accepts any request and
+    /// forwards it to the returned channel.
+    fn start_collector() -> (String, mpsc::UnboundedReceiver<PushedRequest>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let make_svc = make_service_fn(move |_conn| {
+                let sender = sender.clone();
+                let handle_request = move |request: Request<Body>| {
+                    let sender = sender.clone();
+                    async move {
+                        let method = request.method().clone();
+                        let path = request.uri().path().to_string();
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        sender.send(PushedRequest { method, path, body: body.to_vec() }).unwrap();
+                        Ok::<_, Infallible>(Response::new(Body::empty()))
+                    }
+                };
+                async move { Ok::<_, Infallible>(service_fn(handle_request)) }
+            });
+            Server::from_tcp(listener).unwrap().serve(make_svc).await.unwrap();
+        });
+        (format!("http://127.0.0.1:{}", port), receiver)
+    }
+
+    fn pusher(endpoint: String, protocol: MetricsPushProtocol) -> MetricsPusher {
+        let config = MetricsPushConfig {
+            endpoint,
+            protocol,
+            labels: [("region".to_string(), "eu/west".to_string())].into(),
+            ..Default::default()
+        };
+        let public_key = PublicKey::empty(KeyType::ED25519);
+        MetricsPusher::new(config, "localnet".to_string(), public_key, None).unwrap()
+    }
+
+    fn test_families() -> Vec<MetricFamily> {
+        let registry = Registry::new();
+        let counter = IntCounter::new("near_test_counter", "Test counter").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc_by(3);
+        let opts =
+            HistogramOpts::new("near_test_histogram", "Test histogram").buckets(vec![1.0, 10.0]);
+        let histogram = HistogramVec::new(opts, &["shard_id"]).unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        for value in [0.5, 0.5, 5.0, 50.0] {
+            histogram.with_label_values(&["0"]).observe(value);
+        }
+        let gauge = prometheus::Gauge::with_opts(Opts::new("near_test_gauge", "Gauge")).unwrap();
+        registry.register(Box::new(gauge)).unwrap();
+        registry.gather()
+    }
+
+    #[test]
+    fn test_pushgateway_url() {
+        let labels = vec![
+            ("chain_id".to_string(), "mainnet".to_string()),
+            ("region".to_string(), "eu/west".to_string()),
+        ];
+        assert_eq!(
+            pushgateway_url("http://gateway:9091/", "neard", &labels),
+            "http://gateway:9091/metrics/job/neard/chain_id/mainnet/region@base64/ZXUvd2VzdA=="
+        );
+    }
+
+    #[test]
+    fn test_to_otlp_request() {
+        let labels = vec![("chain_id".to_string(), "mainnet".to_string())];
+        let request = to_otlp_request(&test_families(), "neard", &labels, 1, 2);
+        let resource_metrics = &request.resource_metrics[0];
+        let attributes = &resource_metrics.resource.as_ref().unwrap().attributes;
+        assert_eq!(
+            attributes,
+            &vec![
+                string_attribute("service.name", "neard"),
+                string_attribute("chain_id", "mainnet"),
+            ]
+        );
+
+        let metrics = &resource_metrics.scope_metrics[0].metrics;
+        assert_eq!(metrics.len(), 3);
+        let Some(metric::Data::Sum(sum)) = &metrics[0].data else { panic!("expected a sum") };
+        assert!(sum.is_monotonic);
+        assert_eq!(sum.data_points[0].value, Some(number_data_point::Value::AsDouble(3.0)));
+        let Some(metric::Data::Gauge(_)) = &metrics[1].data else { panic!("expected a gauge") };
+        let Some(metric::Data::Histogram(histogram)) = &metrics[2].data else {
+            panic!("expected a histogram")
+        };
+        let data_point = &histogram.data_points[0];
+        assert_eq!(data_point.attributes, vec![string_attribute("shard_id", "0")]);
+        assert_eq!(data_point.count, 4);
+        assert_eq!(data_point.explicit_bounds, vec![1.0, 10.0]);
+        assert_eq!(data_point.bucket_counts, vec![2, 1, 1]);
+        assert_eq!((data_point.start_time_unix_nano, data_point.time_unix_nano), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_push_otlp() {
+        let (endpoint, mut receiver) = start_collector();
+        let pusher = pusher(format!("{}/v1/metrics", endpoint), MetricsPushProtocol::Otlp);
+        pusher.push().await.unwrap();
+
+        let pushed = receiver.recv().await.unwrap();
+        assert_eq!(pushed.method, hyper::Method::POST);
+        assert_eq!(pushed.path, "/v1/metrics");
+        let request: ExportMetricsServiceRequest =
+            prost::Message::decode(pushed.body.as_slice()).unwrap();
+        let attributes = &request.resource_metrics[0].resource.as_ref().unwrap().attributes;
+        assert!(attributes.contains(&string_attribute("chain_id", "localnet")));
+        assert!(attributes.contains(&string_attribute("region", "eu/west")));
+    }
+
+    #[tokio::test]
+    async fn test_push_pushgateway() {
+        let (endpoint, mut receiver) = start_collector();
+        crate::metrics::try_create_int_counter("near_test_pushed_counter", "Test counter")
+            .unwrap()
+            .inc();
+        let pusher = pusher(endpoint, MetricsPushProtocol::Pushgateway);
+        pusher.push().await.unwrap();
+
+        let pushed = receiver.recv().await.unwrap();
+        assert_eq!(pushed.method, hyper::Method::PUT);
+        assert!(pushed.path.starts_with("/metrics/job/neard/chain_id/localnet/node_id/"));
+        assert!(pushed.path.ends_with("/region@base64/ZXUvd2VzdA=="));
+        let body = String::from_utf8(pushed.body).unwrap();
+        assert!(body.contains("near_test_pushed_counter 1"));
+    }
+}
@@ -52,6 +52,8 @@ static CONFIG_DIFFS: &[(ProtocolVersion, &str)] = &[
     // Fix wasm_yield_resume_byte and relax congestion control.
     (73, include_config!("73.yaml")),
     (129, include_config!("129.yaml")),
+    // Bulk memory support in contracts (ContractPrepareVersion::V3).
+    (150, include_config!("150.yaml")),
 ];
 
 /// Testnet parameters for versions <= 29, which (incorrectly) differed from mainnet parameters
@@ -204,6 +206,17 @@ impl RuntimeConfigStore {
         Self::with_one_config(RuntimeConfig::free())
     }
 
+    /// Enables `vm::Config::wasm_gas_profiling` for all the protocol versions.
+    ///
+    /// This must only be used off the consensus path, such as for view calls and in the state
+    /// viewer.
+    pub fn enable_wasm_gas_profiling(&mut self) {
+        for config in self.store.values_mut() {
+            let config = Arc::make_mut(config);
+            Arc::make_mut(&mut config.wasm_config).enable_wasm_gas_profiling();
+        }
+    }
+
     /// Returns a `RuntimeConfig` for the corresponding protocol version.
     pub fn get_config(&self, protocol_version: ProtocolVersion) -> &Arc<RuntimeConfig> {
         self.store
@@ -74,6 +74,7 @@ pub enum Parameter {
     // Smart contract dynamic gas costs
     WasmRegularOpCost,
     WasmGrowMemCost,
+    WasmBulkMemoryByteCost,
     /// Base cost for a host function
     WasmBase,
     WasmContractLoadingBase,
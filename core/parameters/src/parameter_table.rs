@@ -313,8 +313,10 @@ impl TryFrom<&ParameterTable> for RuntimeConfig {
                 vm_kind: params.get(Parameter::VmKind)?,
                 grow_mem_cost: params.get(Parameter::WasmGrowMemCost)?,
                 regular_op_cost: params.get(Parameter::WasmRegularOpCost)?,
+                bulk_memory_byte_cost: params.get(Parameter::WasmBulkMemoryByteCost)?,
                 disable_9393_fix: params.get(Parameter::Disable9393Fix)?,
                 discard_custom_sections: params.get(Parameter::DiscardCustomSections)?,
+                wasm_gas_profiling: false,
                 limit_config: serde_yaml::from_value(params.yaml_map(Parameter::vm_limits()))
                     .map_err(InvalidConfigError::InvalidYaml)?,
                 fix_contract_loading_cost: params.get(Parameter::FixContractLoadingCost)?,
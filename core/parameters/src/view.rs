@@ -209,6 +209,8 @@ pub struct VMConfigView {
     pub grow_mem_cost: u32,
     /// Gas cost of a regular operation.
     pub regular_op_cost: u32,
+    /// See [VMConfig::bulk_memory_byte_cost](crate::vm::Config::bulk_memory_byte_cost).
+    pub bulk_memory_byte_cost: u32,
 
     /// See [VMConfig::vm_kind](crate::vm::Config::vm_kind).
     pub vm_kind: crate::vm::VMKind,
@@ -249,6 +251,7 @@ impl From<crate::vm::Config> for VMConfigView {
             ext_costs: ExtCostsConfigView::from(config.ext_costs),
             grow_mem_cost: config.grow_mem_cost,
             regular_op_cost: config.regular_op_cost,
+            bulk_memory_byte_cost: config.bulk_memory_byte_cost,
             disable_9393_fix: config.disable_9393_fix,
             discard_custom_sections: config.discard_custom_sections,
             limit_config: config.limit_config,
@@ -272,8 +275,10 @@ impl From<VMConfigView> for crate::vm::Config {
             ext_costs: crate::ExtCostsConfig::from(view.ext_costs),
             grow_mem_cost: view.grow_mem_cost,
             regular_op_cost: view.regular_op_cost,
+            bulk_memory_byte_cost: view.bulk_memory_byte_cost,
             disable_9393_fix: view.disable_9393_fix,
             discard_custom_sections: view.discard_custom_sections,
+            wasm_gas_profiling: false,
             limit_config: view.limit_config,
             storage_get_mode: view.storage_get_mode,
             fix_contract_loading_cost: view.fix_contract_loading_cost,
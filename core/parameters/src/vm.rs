@@ -162,6 +162,12 @@ pub struct Config {
     /// Gas cost of a regular operation.
     pub regular_op_cost: u32,
 
+    /// Gas cost per byte (or table element) copied, filled or initialized by
+    /// the bulk memory instructions such as `memory.copy` and `memory.fill`.
+    ///
+    /// Charged on top of `regular_op_cost` for the instruction itself.
+    pub bulk_memory_byte_cost: u32,
+
     /// The kind of the VM implementation to use
     pub vm_kind: VMKind,
 
@@ -199,6 +205,12 @@ pub struct Config {
     /// Whether to discard custom sections.
     pub discard_custom_sections: bool,
 
+    /// Attribute the gas spent inside the Wasm VM to the Wasm functions which spent it.
+    ///
+    /// This is not a protocol parameter. It is only ever set for local debugging runs, such as
+    /// profiled view calls, and does not change the gas burnt by the contracts.
+    pub wasm_gas_profiling: bool,
+
     /// Describes limits for VM and Runtime.
     pub limit_config: LimitConfig,
 }
@@ -220,9 +232,19 @@ impl Config {
         };
         self.grow_mem_cost = 0;
         self.regular_op_cost = 0;
+        self.bulk_memory_byte_cost = 0;
         self.limit_config.max_gas_burnt = u64::MAX;
     }
 
+    /// Enables `wasm_gas_profiling`.
+    ///
+    /// The profiling instrumentation is only implemented for Wasmtime, which charges the same
+    /// gas as the other VMs, so the VM is switched to it.
+    pub fn enable_wasm_gas_profiling(&mut self) {
+        self.wasm_gas_profiling = true;
+        self.vm_kind = VMKind::Wasmtime;
+    }
+
     pub fn enable_all_features(&mut self) {
         self.yield_resume_host_functions = true;
         self.eth_implicit_accounts = true;
@@ -267,6 +289,13 @@ pub enum ContractPrepareVersion {
     V1,
     /// finite-wasm 0.3.0 based contract preparation code.
     V2,
+    /// Same as V2, but additionally accepts contracts using the bulk memory
+    /// proposal.
+    ///
+    /// Multi-value and reference types remain disabled: the near-vm singlepass
+    /// compiler does not support multi-value returns and the instrumentation
+    /// passes assume MVP function tables.
+    V3,
 }
 
 impl ContractPrepareVersion {
@@ -193,6 +193,12 @@ pub enum ProtocolFeature {
     ExcludeExistingCodeFromWitnessForCodeLen,
     /// Use the block height instead of the block hash to calculate the receipt ID.
     BlockHeightForReceiptId,
+    /// Allows peers to negotiate an encrypted and authenticated connection
+    /// (Noise_XX) during the handshake. It affects only the network protocol.
+    EncryptedPeerConnections,
+    /// Contract preparation accepting the Wasm bulk memory proposal, with
+    /// per-byte gas for `memory.copy`, `memory.fill` and friends.
+    PreparationV3,
 }
 
 impl ProtocolFeature {
@@ -275,6 +281,8 @@ impl ProtocolFeature {
             ProtocolFeature::ExcludeExistingCodeFromWitnessForCodeLen => 147,
             ProtocolFeature::BandwidthScheduler => 148,
             ProtocolFeature::BlockHeightForReceiptId => 149,
+            ProtocolFeature::EncryptedPeerConnections => 150,
+            ProtocolFeature::PreparationV3 => 150,
             // Place features that are not yet in Nightly below this line.
         }
     }
@@ -288,7 +296,7 @@ impl ProtocolFeature {
 const STABLE_PROTOCOL_VERSION: ProtocolVersion = 74;
 
 // On nightly, pick big enough version to support all features.
-const NIGHTLY_PROTOCOL_VERSION: ProtocolVersion = 149;
+const NIGHTLY_PROTOCOL_VERSION: ProtocolVersion = 150;
 
 /// Largest protocol version supported by the current binary.
 pub const PROTOCOL_VERSION: ProtocolVersion = if cfg!(feature = "nightly_protocol") {
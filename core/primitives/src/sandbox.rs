@@ -78,3 +78,70 @@ pub mod state_patch {
         }
     }
 }
+
+#[cfg(feature = "sandbox")]
+pub mod impersonation {
+    use crate::types::AccountId;
+    use std::collections::BTreeSet;
+    use std::sync::{Mutex, MutexGuard, RwLock};
+
+    /// Accounts whose transactions are accepted without checking their
+    /// signatures and access keys, see `sandbox_impersonate_account`.
+    ///
+    /// The set is shared by the whole process, so that the transaction pool,
+    /// chunk production and chunk application agree on which transactions are
+    /// valid.  It is only ever non-empty on sandbox builds.
+    static IMPERSONATED_ACCOUNTS: RwLock<BTreeSet<AccountId>> = RwLock::new(BTreeSet::new());
+
+    /// Replaces the set of impersonated accounts.  An empty list stops
+    /// impersonating any account.
+    pub fn set_impersonated_accounts(account_ids: Vec<AccountId>) {
+        *IMPERSONATED_ACCOUNTS.write().unwrap() = account_ids.into_iter().collect();
+    }
+
+    pub fn is_impersonated(account_id: &AccountId) -> bool {
+        IMPERSONATED_ACCOUNTS.read().unwrap().contains(account_id)
+    }
+
+    /// Held by the live `ImpersonationGuard`.
+    static IMPERSONATION_GUARD_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Impersonates accounts until dropped, then restores the previously
+    /// impersonated accounts, even if the owner panics.
+    ///
+    /// Only one guard is alive at a time, so that tests impersonating accounts
+    /// don't change the set under each other.
+    pub struct ImpersonationGuard {
+        previous: BTreeSet<AccountId>,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for ImpersonationGuard {
+        fn drop(&mut self) {
+            *IMPERSONATED_ACCOUNTS.write().unwrap_or_else(|err| err.into_inner()) =
+                std::mem::take(&mut self.previous);
+        }
+    }
+
+    /// Replaces the set of impersonated accounts for the lifetime of the
+    /// returned guard, waiting for any other guard to be dropped first.
+    pub fn impersonate_accounts(account_ids: Vec<AccountId>) -> ImpersonationGuard {
+        // A guard dropped while panicking has still restored the accounts.
+        let lock = IMPERSONATION_GUARD_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let previous = std::mem::replace(
+            &mut *IMPERSONATED_ACCOUNTS.write().unwrap(),
+            account_ids.into_iter().collect(),
+        );
+        ImpersonationGuard { previous, _lock: lock }
+    }
+}
+
+#[cfg(not(feature = "sandbox"))]
+pub mod impersonation {
+    use crate::types::AccountId;
+
+    #[inline(always)]
+    pub fn is_impersonated(_account_id: &AccountId) -> bool {
+        false
+    }
+}
@@ -220,6 +220,10 @@ pub struct ViewStateResult {
 pub struct CallResult {
     pub result: Vec<u8>,
     pub logs: Vec<String>,
+    /// Gas spent by the Wasm functions in the folded stacks format, if it was requested with
+    /// `profile_wasm_gas`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_gas_profile: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -254,6 +258,23 @@ pub struct KnownPeerStateView {
     pub first_seen: i64,
     pub last_seen: i64,
     pub last_attempt: Option<(i64, String)>,
+    /// Reputation score of the peer, if it has one.
+    #[serde(default)]
+    pub score: Option<PeerScoreView>,
+}
+
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PeerScoreView {
+    /// Current score, after the decay, rounded to an integer.
+    pub score: i64,
+    pub useful_responses: u64,
+    pub useless_responses: u64,
+    pub routing_successes: u64,
+    pub routing_failures: u64,
+    pub violations: u64,
+    /// Moving average of the latency of block responses.
+    pub avg_latency_millis: Option<u64>,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -313,6 +334,10 @@ pub enum QueryRequest {
         method_name: String,
         #[serde(rename = "args_base64")]
         args: FunctionArgs,
+        /// Attribute the gas spent inside the Wasm VM to the Wasm functions which spent it and
+        /// return that in `CallResult::wasm_gas_profile`. This does not change the gas burnt.
+        #[serde(default, skip_serializing_if = "is_false")]
+        profile_wasm_gas: bool,
     },
 }
 
@@ -2288,6 +2313,25 @@ impl From<StateChangeCause> for StateChangeCauseView {
     }
 }
 
+/// A transaction or receipt which touched an account, as recorded by the account activity index.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AccountActivityView {
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub cause: StateChangeCauseView,
+    /// Access key of the account which has been changed, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<PublicKey>,
+}
+
+/// Position in the account activity index at which a paginated query continues.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AccountActivityCursor {
+    pub block_height: BlockHeight,
+    /// Index of the entry among the activity of the account in the block.
+    pub index: u32,
+}
+
 #[serde_as]
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type", content = "change")]
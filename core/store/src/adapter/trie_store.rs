@@ -46,6 +46,31 @@ impl TrieStoreAdapter {
         Ok(val.into())
     }
 
+    /// Returns the node or value stored under the hash together with its
+    /// reference count.  Unlike `get`, doesn't treat cells with non-positive
+    /// reference count as missing, in which case the returned value is empty.
+    ///
+    /// Meant for checking the integrity of the column, see the data scrubber.
+    pub fn get_with_refcount(
+        &self,
+        shard_uid: ShardUId,
+        hash: &CryptoHash,
+    ) -> io::Result<Option<(Vec<u8>, i64)>> {
+        let key = get_key_from_shard_uid_and_hash(&self.store, shard_uid, hash);
+        let Some(bytes) = self.store.storage.get_raw_bytes(DBCol::State, key.as_ref())? else {
+            return Ok(None);
+        };
+        // An empty cell is what's left of a cell with zero reference count.
+        if !bytes.is_empty() && bytes.len() < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("State value {hash} is too short to hold a reference count"),
+            ));
+        }
+        let (value, refcount) = crate::db::refcount::decode_value_with_rc(&bytes);
+        Ok(Some((value.map_or_else(Vec::new, <[u8]>::to_vec), refcount)))
+    }
+
     pub fn get_ser<T: BorshDeserialize>(
         &self,
         shard_uid: ShardUId,
@@ -128,6 +153,17 @@ impl<'a> TrieStoreUpdateAdapter<'a> {
         self.store_update.increment_refcount_by(DBCol::State, key.as_ref(), data, increment);
     }
 
+    /// Replaces the node or value stored under the hash, keeping its
+    /// reference count, even if the count is updated concurrently.  Does
+    /// nothing if there is no node or value under the hash.
+    ///
+    /// This is only meant for repairing corrupted cells, see the data
+    /// scrubber.
+    pub fn replace_value(&mut self, shard_uid: ShardUId, hash: &CryptoHash, data: &[u8]) {
+        let key = self.get_key_from_shard_uid_and_hash(shard_uid, hash);
+        self.store_update.replace_refcounted_value(DBCol::State, key.as_ref(), data);
+    }
+
     pub fn set_state_snapshot_hash(&mut self, hash: Option<CryptoHash>) {
         let key = STATE_SNAPSHOT_KEY;
         match hash {
@@ -253,6 +289,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_with_refcount_and_replace_value() {
+        let (_tmp_dir, opener) = NodeStorage::test_opener();
+        let store = TrieStoreAdapter::new(opener.open().unwrap().get_hot_store());
+        let shard_uid = ShardUId { version: 0, shard_id: 0 };
+        let dummy_hash = CryptoHash::default();
+
+        assert_eq!(store.get_with_refcount(shard_uid, &dummy_hash).unwrap(), None);
+        {
+            let mut store_update = store.store_update();
+            store_update.increment_refcount_by(shard_uid, &dummy_hash, &[1], ONE);
+            store_update.increment_refcount_by(shard_uid, &dummy_hash, &[1], ONE);
+            store_update.commit().unwrap();
+        }
+        assert_eq!(store.get_with_refcount(shard_uid, &dummy_hash).unwrap(), Some((vec![1], 2)));
+        {
+            let mut store_update = store.store_update();
+            store_update.replace_value(shard_uid, &dummy_hash, &[2]);
+            store_update.commit().unwrap();
+        }
+        assert_eq!(store.get_with_refcount(shard_uid, &dummy_hash).unwrap(), Some((vec![2], 2)));
+        assert_eq!(*store.get(shard_uid, &dummy_hash).unwrap(), [2]);
+        {
+            let mut store_update = store.store_update();
+            store_update.decrement_refcount(shard_uid, &dummy_hash);
+            store_update.decrement_refcount(shard_uid, &dummy_hash);
+            store_update.decrement_refcount(shard_uid, &dummy_hash);
+            store_update.commit().unwrap();
+        }
+        // Unlike `get`, the cell with negative refcount is still visible.
+        assert_eq!(store.get_with_refcount(shard_uid, &dummy_hash).unwrap(), Some((vec![], -1)));
+        assert_matches!(
+            store.get(shard_uid, &dummy_hash),
+            Err(StorageError::MissingTrieValue(_, _))
+        );
+        // Replacing doesn't bring back removed cells.
+        {
+            let mut store_update = store.store_update();
+            store_update.replace_value(shard_uid, &dummy_hash, &[3]);
+            store_update.commit().unwrap();
+        }
+        assert_eq!(store.get_with_refcount(shard_uid, &dummy_hash).unwrap(), Some((vec![], -1)));
+    }
+
     #[test]
     fn test_shard_uid_mapping() {
         let (_tmp_dir, opener) = NodeStorage::test_opener();
@@ -0,0 +1,36 @@
+//! Layout of the keys of `DBCol::AccountActivity`, the index of the activity
+//! of accounts maintained by the chain when `save_account_activity` is
+//! enabled.
+//!
+//! Entries are stored per account, height and block hash, so all entries of a
+//! block can be found from the accounts active in it.  That's how they are
+//! copied to the cold database, see `copy_account_activity_from_store`.
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockHeight};
+
+/// Separates the account id from the height in the keys.  It sorts before all
+/// characters allowed in account ids, so entries of an account are never
+/// interleaved with entries of accounts whose ids start with the id of that
+/// account.
+pub const ACCOUNT_ID_SEPARATOR: u8 = b',';
+
+/// Returns the key without the block hash, i.e. the common prefix of the
+/// entries of the account at the given height.
+pub fn account_activity_key_prefix(account_id: &AccountId, height: BlockHeight) -> Vec<u8> {
+    let mut key = Vec::with_capacity(account_id.len() + 1 + 8 + CryptoHash::LENGTH);
+    key.extend_from_slice(account_id.as_bytes());
+    key.push(ACCOUNT_ID_SEPARATOR);
+    key.extend_from_slice(&height.to_be_bytes());
+    key
+}
+
+pub fn account_activity_key(
+    account_id: &AccountId,
+    height: BlockHeight,
+    block_hash: &CryptoHash,
+) -> Vec<u8> {
+    let mut key = account_activity_key_prefix(account_id, height);
+    key.extend_from_slice(block_hash.as_ref());
+    key
+}
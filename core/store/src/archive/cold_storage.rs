@@ -1,4 +1,5 @@
 use crate::adapter::trie_store::get_shard_uid_mapping;
+use crate::archive::account_activity::account_activity_key_prefix;
 use crate::columns::DBKeyType;
 use crate::db::{ColdDB, COLD_HEAD_KEY, HEAD_KEY};
 use crate::{metrics, DBCol, DBTransaction, Database, Store, TrieChanges};
@@ -8,9 +9,11 @@ use near_primitives::block::{Block, BlockHeader, Tip};
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::sharding::ShardChunk;
+use near_primitives::transaction::ExecutionOutcomeWithProof;
+use near_primitives::trie_key::trie_key_parsers;
 use near_primitives::types::BlockHeight;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::io;
 use std::sync::Arc;
 use strum::IntoEnumIterator;
@@ -116,6 +119,13 @@ pub fn update_cold_db(
                 .map(|col: DBCol| -> io::Result<()> {
                     if col == DBCol::State {
                         copy_state_from_store(shard_layout, block_hash_key, cold_db, &hot_store)
+                    } else if col == DBCol::AccountActivity {
+                        copy_account_activity_from_store(
+                            *height,
+                            &key_type_to_keys,
+                            cold_db,
+                            &hot_store,
+                        )
                     } else {
                         let keys = combine_keys(&key_type_to_keys, &col.key_type());
                         copy_from_store(cold_db, &hot_store, col, keys)
@@ -218,6 +228,70 @@ fn copy_state_from_store(
     Ok(())
 }
 
+// A specialized version of copy_from_store for the AccountActivity column. Its
+// keys start with account ids, so the entries of the block are found from the
+// accounts whose state changed in the block and the executors of its outcomes,
+// the same way the chain builds the index.
+//
+// The index is optional, and when it's disabled the hot column is empty and
+// nothing else is read.
+fn copy_account_activity_from_store(
+    height: BlockHeight,
+    key_type_to_keys: &HashMap<DBKeyType, Vec<StoreKey>>,
+    cold_db: &ColdDB,
+    hot_store: &Store,
+) -> io::Result<()> {
+    let col = DBCol::AccountActivity;
+    let _span =
+        tracing::debug_span!(target: "cold_store", "copy_account_activity_from_store", %col);
+    let instant = std::time::Instant::now();
+
+    if hot_store.iter(col).next().transpose()?.is_none() {
+        return Ok(());
+    }
+
+    debug_assert_eq!(DBCol::StateChanges.key_type(), &[DBKeyType::BlockHash, DBKeyType::TrieKey]);
+    let mut account_ids = BTreeSet::new();
+    for raw_trie_key in &key_type_to_keys[&DBKeyType::TrieKey] {
+        if let Some(account_id) = trie_key_parsers::parse_account_id_from_raw_key(raw_trie_key)? {
+            account_ids.insert(account_id);
+        }
+    }
+    for key in combine_keys(key_type_to_keys, DBCol::TransactionResultForBlock.key_type()) {
+        let outcome = hot_store.get_ser_for_cold::<ExecutionOutcomeWithProof>(
+            DBCol::TransactionResultForBlock,
+            &key,
+        )?;
+        if let Some(outcome) = outcome {
+            account_ids.insert(outcome.outcome.executor_id);
+        }
+    }
+
+    let mut transaction = DBTransaction::new();
+    let mut total_keys = 0;
+    let mut total_size = 0;
+    for account_id in &account_ids {
+        // Entries of all the blocks at the height are copied, entries of the
+        // blocks which are not on the canonical chain are skipped on read.
+        let prefix = account_activity_key_prefix(account_id, height);
+        for item in hot_store.iter_prefix(col, &prefix) {
+            let (key, value) = item?;
+            total_keys += 1;
+            total_size += rc_aware_set(&mut transaction, col, key.to_vec(), value.to_vec());
+        }
+    }
+
+    let read_duration = instant.elapsed();
+
+    let instant = std::time::Instant::now();
+    cold_db.write(transaction)?;
+    let write_duration = instant.elapsed();
+
+    tracing::trace!(target: "cold_store", accounts = account_ids.len(), ?total_keys, ?total_size, ?read_duration, ?write_duration, "copy_account_activity_from_store finished");
+
+    Ok(())
+}
+
 /// Gets values for given keys in a column from provided hot_store.
 /// Creates a transaction based on that values with set DBOp s.
 /// Writes that transaction to cold_db.
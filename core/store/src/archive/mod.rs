@@ -1 +1,3 @@
+pub mod account_activity;
 pub mod cold_storage;
+pub mod state_history;
@@ -0,0 +1,437 @@
+//! Index of the history of accounts, access keys and contract data used by
+//! archival nodes to answer queries about past blocks without traversing the
+//! trie.
+//!
+//! For every change of an indexed trie key the index stores the new value in
+//! `DBCol::StateHistory` under the trie key followed by the height of the block
+//! which made the change.  The value of a key at a given height is the value of
+//! the latest entry at or below that height, which is found with one range
+//! lookup.  The trie key is escaped so that the key of one trie key is never a
+//! prefix of the key of another one, while keys of trie keys sharing a prefix
+//! are still adjacent.  The height is inverted so that the latest entries come
+//! first.
+//!
+//! The index lives in the cold database.  It is initialised with a snapshot of
+//! the whole state at some height and then updated as blocks are copied to
+//! cold storage.  The range of heights it covers is stored under the empty key
+//! of the column.  Entries below the start of the range are ignored as they may
+//! be left over from a previous initialisation.
+
+use crate::adapter::StoreAdapter;
+use crate::db::{ColdDB, DBTransaction, Database};
+use crate::{DBCol, Store, Trie, TrieDBStorage};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::trie_key::col;
+use near_primitives::types::{BlockHeight, RawStateChangesWithTrieKey, StateRoot};
+use std::io;
+use std::sync::Arc;
+
+/// Columns of the trie keys which are indexed.
+const INDEXED_COLUMNS: [u8; 3] = [col::ACCOUNT, col::ACCESS_KEY, col::CONTRACT_DATA];
+
+/// Key under which `StateHistoryRange` is stored.
+const RANGE_KEY: &[u8] = &[];
+
+/// Zero bytes of trie keys are followed by this byte.
+const ESCAPE: u8 = 0xff;
+/// Sequence which ends an escaped trie key.  It sorts before any escaped byte.
+const TERMINATOR: [u8; 2] = [0, 0];
+
+/// Size of the transaction after which it is written while indexing a snapshot.
+const SNAPSHOT_BATCH_SIZE: usize = 100_000_000;
+
+/// Range of heights for which the index has complete information.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateHistoryRange {
+    /// Height of the snapshot the index has been initialised with.
+    pub tail: BlockHeight,
+    /// Height of the last block whose changes have been indexed.
+    pub head: BlockHeight,
+}
+
+impl StateHistoryRange {
+    pub fn contains(&self, height: BlockHeight) -> bool {
+        self.tail <= height && height <= self.head
+    }
+}
+
+/// Whether changes of the trie key are recorded in the index.
+pub fn is_indexed_trie_key(raw_key: &[u8]) -> bool {
+    raw_key.first().map_or(false, |col| INDEXED_COLUMNS.contains(col))
+}
+
+fn escape_trie_key(raw_key: &[u8], out: &mut Vec<u8>) {
+    for &byte in raw_key {
+        out.push(byte);
+        if byte == 0 {
+            out.push(ESCAPE);
+        }
+    }
+}
+
+fn history_key(raw_key: &[u8], height: BlockHeight) -> Vec<u8> {
+    let mut key = Vec::with_capacity(raw_key.len() + TERMINATOR.len() + 8);
+    escape_trie_key(raw_key, &mut key);
+    key.extend_from_slice(&TERMINATOR);
+    key.extend_from_slice(&(BlockHeight::MAX - height).to_be_bytes());
+    key
+}
+
+/// Inverse of `history_key`.  Returns `None` for the range key.
+fn parse_history_key(key: &[u8]) -> Option<(Vec<u8>, BlockHeight)> {
+    let mut raw_key = Vec::with_capacity(key.len());
+    let mut idx = 0;
+    loop {
+        let byte = *key.get(idx)?;
+        idx += 1;
+        if byte != 0 {
+            raw_key.push(byte);
+            continue;
+        }
+        match *key.get(idx)? {
+            ESCAPE => raw_key.push(0),
+            0 => break,
+            _ => return None,
+        }
+        idx += 1;
+    }
+    let height = key.get(idx + 1..)?.try_into().ok().map(BlockHeight::from_be_bytes)?;
+    Some((raw_key, BlockHeight::MAX - height))
+}
+
+/// Returns the smallest key greater than all keys starting with `prefix`, or
+/// `None` if there is no such key.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut upper_bound = prefix.to_vec();
+    while let Some(byte) = upper_bound.pop() {
+        if byte < u8::MAX {
+            upper_bound.push(byte + 1);
+            return Some(upper_bound);
+        }
+    }
+    None
+}
+
+fn get_range(db: &dyn Database) -> io::Result<Option<StateHistoryRange>> {
+    db.get_raw_bytes(DBCol::StateHistory, RANGE_KEY)?
+        .map(|value| StateHistoryRange::try_from_slice(&value))
+        .transpose()
+}
+
+/// Returns the range of heights covered by the index, or `None` if the index
+/// hasn't been initialised.
+pub fn get_state_history_range(store: &Store) -> io::Result<Option<StateHistoryRange>> {
+    store.get_ser(DBCol::StateHistory, RANGE_KEY)
+}
+
+/// Returns the value of the trie key at `height` which must be within `range`.
+pub fn get_historical_value(
+    store: &Store,
+    range: &StateHistoryRange,
+    raw_key: &[u8],
+    height: BlockHeight,
+) -> io::Result<Option<Vec<u8>>> {
+    debug_assert!(range.contains(height));
+    let lower_bound = history_key(raw_key, height);
+    let mut upper_bound = history_key(raw_key, range.tail);
+    upper_bound.push(0);
+    let Some(item) =
+        store.iter_range(DBCol::StateHistory, Some(&lower_bound), Some(&upper_bound)).next()
+    else {
+        return Ok(None);
+    };
+    let (_, value) = item?;
+    Ok(Option::<Vec<u8>>::try_from_slice(&value)?)
+}
+
+/// Iterates over trie keys starting with `raw_prefix` and their values at
+/// `height` which must be within `range`.  Keys are returned in order.
+pub fn iter_historical_values<'a>(
+    store: &'a Store,
+    range: &StateHistoryRange,
+    raw_prefix: &[u8],
+    height: BlockHeight,
+) -> impl Iterator<Item = io::Result<(Vec<u8>, Vec<u8>)>> + 'a {
+    debug_assert!(range.contains(height));
+    let mut lower_bound = Vec::with_capacity(raw_prefix.len());
+    escape_trie_key(raw_prefix, &mut lower_bound);
+    let upper_bound = prefix_upper_bound(&lower_bound);
+    let tail = range.tail;
+    // Entries of a trie key are ordered from the latest, so the first one at or
+    // below `height` holds its value and the remaining ones are skipped.
+    let mut last_key: Option<Vec<u8>> = None;
+    store.iter_range(DBCol::StateHistory, Some(&lower_bound), upper_bound.as_deref()).filter_map(
+        move |item| {
+            let (key, value) = match item {
+                Ok(item) => item,
+                Err(err) => return Some(Err(err)),
+            };
+            let Some((raw_key, entry_height)) = parse_history_key(&key) else {
+                return Some(Err(io::Error::other("invalid key in StateHistory")));
+            };
+            if entry_height > height || entry_height < tail || last_key.as_ref() == Some(&raw_key) {
+                return None;
+            }
+            last_key = Some(raw_key.clone());
+            match Option::<Vec<u8>>::try_from_slice(&value) {
+                Ok(Some(value)) => Some(Ok((raw_key, value))),
+                Ok(None) => None,
+                Err(err) => Some(Err(err)),
+            }
+        },
+    )
+}
+
+/// Initialises the index with the state of the given shards at `height`.
+///
+/// The state is read from `store` which needs to contain the trie nodes, for
+/// example the split or the cold store.  Entries written by a previous
+/// initialisation are kept but ignored from now on.
+pub fn init_state_history(
+    cold_db: &ColdDB,
+    store: &Store,
+    state_roots: &[(ShardUId, StateRoot)],
+    height: BlockHeight,
+) -> io::Result<()> {
+    let _span = tracing::info_span!(target: "cold_store", "init_state_history", height).entered();
+    for (shard_uid, state_root) in state_roots {
+        let storage = Arc::new(TrieDBStorage::new(store.trie_store(), *shard_uid));
+        let trie = Trie::new(storage, *state_root, None);
+        let mut num_keys = 0;
+        let mut transaction = DBTransaction::new();
+        let mut transaction_size = 0;
+        for col in INDEXED_COLUMNS {
+            let mut iter = trie.disk_iter().map_err(io::Error::other)?;
+            iter.seek_prefix(&[col]).map_err(io::Error::other)?;
+            for item in iter {
+                let (raw_key, value) = item.map_err(io::Error::other)?;
+                let key = history_key(&raw_key, height);
+                let value = borsh::to_vec(&Some(value))?;
+                transaction_size += key.len() + value.len();
+                transaction.set(DBCol::StateHistory, key, value);
+                num_keys += 1;
+                if transaction_size > SNAPSHOT_BATCH_SIZE {
+                    cold_db.write(std::mem::take(&mut transaction))?;
+                    transaction_size = 0;
+                }
+            }
+        }
+        cold_db.write(transaction)?;
+        tracing::info!(target: "cold_store", %shard_uid, num_keys, "Indexed state snapshot");
+    }
+
+    let range = StateHistoryRange { tail: height, head: height };
+    let mut transaction = DBTransaction::new();
+    transaction.set(DBCol::StateHistory, RANGE_KEY.to_vec(), borsh::to_vec(&range)?);
+    cold_db.write(transaction)
+}
+
+/// Records the state changes of the block at `height` in the index.
+///
+/// `prev_height` is the height of the previous block copied to cold storage.
+/// The index is only updated if it covers `prev_height` so that it never has
+/// gaps.  Otherwise it has to be caught up with the `cold-store
+/// backfill-state-history` command.  Does nothing if the index hasn't been
+/// initialised.
+pub fn update_state_history(
+    cold_db: &ColdDB,
+    store: &Store,
+    prev_height: BlockHeight,
+    height: BlockHeight,
+) -> io::Result<()> {
+    let _span =
+        tracing::debug_span!(target: "cold_store", "update_state_history", height).entered();
+    let Some(mut range) = get_range(cold_db)? else {
+        tracing::debug!(target: "cold_store", "State history index is not initialised");
+        return Ok(());
+    };
+    if range.head >= height {
+        return Ok(());
+    }
+    if range.head != prev_height {
+        tracing::warn!(
+            target: "cold_store",
+            head = range.head,
+            prev_height,
+            "State history index is behind, it needs to be backfilled"
+        );
+        return Ok(());
+    }
+
+    let mut transaction = DBTransaction::new();
+    let block_hash = store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())?;
+    if let Some(block_hash) = block_hash {
+        for item in store
+            .iter_prefix_ser::<RawStateChangesWithTrieKey>(DBCol::StateChanges, block_hash.as_ref())
+        {
+            let (_, changes) = item?;
+            let raw_key = changes.trie_key.to_vec();
+            if !is_indexed_trie_key(&raw_key) {
+                continue;
+            }
+            let Some(change) = changes.changes.last() else { continue };
+            let value = borsh::to_vec(&change.data)?;
+            transaction.set(DBCol::StateHistory, history_key(&raw_key, height), value);
+        }
+    }
+    range.head = height;
+    transaction.set(DBCol::StateHistory, RANGE_KEY.to_vec(), borsh::to_vec(&range)?);
+    cold_db.write(transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_key() {
+        for raw_key in [&b""[..], b"a", b"\x00", b"a\x00b", b"\x00\x00\xff"] {
+            let key = history_key(raw_key, 42);
+            assert_eq!(parse_history_key(&key), Some((raw_key.to_vec(), 42)));
+        }
+        assert_eq!(parse_history_key(RANGE_KEY), None);
+
+        // Keys are ordered by trie key and then from the latest height.
+        let keys = [
+            history_key(b"a", 10),
+            history_key(b"a", 5),
+            history_key(b"a\x00", 7),
+            history_key(b"a\x00\x00", 7),
+            history_key(b"a\x01", 7),
+            history_key(b"ab", 20),
+        ];
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_upper_bound(b"a\xff"), Some(b"b".to_vec()));
+        assert_eq!(prefix_upper_bound(b"\xff\xff"), None);
+    }
+
+    #[test]
+    fn test_historical_values() {
+        let store = crate::test_utils::create_test_store();
+        let mut store_update = store.store_update();
+        let mut set = |raw_key: &[u8], height: BlockHeight, value: Option<&[u8]>| {
+            let value = value.map(|value| value.to_vec());
+            store_update
+                .set_ser(DBCol::StateHistory, &history_key(raw_key, height), &value)
+                .unwrap();
+        };
+        // Left over from a previous initialisation.
+        set(b"\x09a,gone", 1, Some(b"old"));
+        set(b"\x09a,k", 10, Some(b"v10"));
+        set(b"\x09a,k", 20, Some(b"v20"));
+        set(b"\x09a,k", 30, None);
+        set(b"\x09a,k\x00", 10, Some(b"w10"));
+        set(b"\x09ab,k", 10, Some(b"other"));
+        let range = StateHistoryRange { tail: 10, head: 40 };
+        store_update.set_ser(DBCol::StateHistory, RANGE_KEY, &range).unwrap();
+        store_update.commit().unwrap();
+
+        assert_eq!(get_state_history_range(&store).unwrap(), Some(range));
+        let get =
+            |raw_key: &[u8], height| get_historical_value(&store, &range, raw_key, height).unwrap();
+        assert_eq!(get(b"\x09a,k", 10), Some(b"v10".to_vec()));
+        assert_eq!(get(b"\x09a,k", 25), Some(b"v20".to_vec()));
+        assert_eq!(get(b"\x09a,k", 35), None);
+        assert_eq!(get(b"\x09a,k\x00", 35), Some(b"w10".to_vec()));
+        assert_eq!(get(b"\x09a,gone", 20), None);
+
+        let values = |height| {
+            iter_historical_values(&store, &range, b"\x09a,", height)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap()
+        };
+        assert_eq!(
+            values(15),
+            vec![
+                (b"\x09a,k".to_vec(), b"v10".to_vec()),
+                (b"\x09a,k\x00".to_vec(), b"w10".to_vec())
+            ]
+        );
+        assert_eq!(values(30), vec![(b"\x09a,k\x00".to_vec(), b"w10".to_vec())]);
+    }
+
+    #[test]
+    fn test_init_and_update_state_history() {
+        use crate::metadata::DbKind;
+        use crate::test_utils::{
+            create_test_node_storage_with_cold, test_populate_trie, TestTriesBuilder,
+        };
+        use crate::KeyForStateChanges;
+        use near_primitives::trie_key::TrieKey;
+        use near_primitives::types::{RawStateChange, StateChangeCause};
+
+        let (storage, ..) =
+            create_test_node_storage_with_cold(crate::metadata::DB_VERSION, DbKind::Hot);
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        let cold_db = storage.cold_db().unwrap();
+
+        let account_key = TrieKey::Account { account_id: "alice".parse().unwrap() }.to_vec();
+        let data_key =
+            TrieKey::ContractData { account_id: "alice".parse().unwrap(), key: b"k".to_vec() }
+                .to_vec();
+        let code_key = TrieKey::ContractCode { account_id: "alice".parse().unwrap() }.to_vec();
+        let shard_uid = ShardUId::single_shard();
+        let tries = TestTriesBuilder::new().with_store(hot_store.clone()).build();
+        let state_root = test_populate_trie(
+            &tries,
+            &Trie::EMPTY_ROOT,
+            shard_uid,
+            vec![
+                (account_key.clone(), Some(b"account".to_vec())),
+                (data_key.clone(), Some(b"v10".to_vec())),
+                (code_key.clone(), Some(b"code".to_vec())),
+            ],
+        );
+        init_state_history(cold_db, &hot_store, &[(shard_uid, state_root)], 10).unwrap();
+        let range = get_state_history_range(&cold_store).unwrap().unwrap();
+        assert_eq!(range, StateHistoryRange { tail: 10, head: 10 });
+        let get = |range: &StateHistoryRange, raw_key: &[u8], height| {
+            get_historical_value(&cold_store, range, raw_key, height).unwrap()
+        };
+        assert_eq!(get(&range, &account_key, 10), Some(b"account".to_vec()));
+        assert_eq!(get(&range, &data_key, 10), Some(b"v10".to_vec()));
+        // Contract code is not indexed.
+        assert_eq!(get(&range, &code_key, 10), None);
+
+        // Block 11 changes the contract data and deletes the account.
+        let block_hash = CryptoHash::hash_bytes(b"block 11");
+        let mut store_update = hot_store.store_update();
+        store_update.set_ser(DBCol::BlockHeight, &11u64.to_le_bytes(), &block_hash).unwrap();
+        for (trie_key, data) in [
+            (TrieKey::Account { account_id: "alice".parse().unwrap() }, None),
+            (
+                TrieKey::ContractData { account_id: "alice".parse().unwrap(), key: b"k".to_vec() },
+                Some(b"v11".to_vec()),
+            ),
+        ] {
+            let key = KeyForStateChanges::from_trie_key(&block_hash, &trie_key);
+            let changes = RawStateChangesWithTrieKey {
+                trie_key,
+                changes: vec![RawStateChange { cause: StateChangeCause::InitialState, data }],
+            };
+            store_update.set_ser(DBCol::StateChanges, key.as_ref(), &changes).unwrap();
+        }
+        store_update.commit().unwrap();
+
+        update_state_history(cold_db, &hot_store, 10, 11).unwrap();
+        let range = get_state_history_range(&cold_store).unwrap().unwrap();
+        assert_eq!(range, StateHistoryRange { tail: 10, head: 11 });
+        assert_eq!(get(&range, &account_key, 10), Some(b"account".to_vec()));
+        assert_eq!(get(&range, &account_key, 11), None);
+        assert_eq!(get(&range, &data_key, 10), Some(b"v10".to_vec()));
+        assert_eq!(get(&range, &data_key, 11), Some(b"v11".to_vec()));
+
+        // Blocks are not indexed if that would leave a gap.
+        update_state_history(cold_db, &hot_store, 12, 13).unwrap();
+        assert_eq!(get_state_history_range(&cold_store).unwrap(), Some(range));
+    }
+}
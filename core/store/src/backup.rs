@@ -0,0 +1,268 @@
+//! Incremental backups of the node database.
+//!
+//! Backups are taken with RocksDB’s BackupEngine, so backups in the same
+//! directory share unchanged files and may be taken while the node is
+//! running.  Next to the RocksDB backups, the directory holds a JSON metadata
+//! file per backup recording the chain head and version and kind of the
+//! backed up database.  The version and kind are checked against the node
+//! before the backup is restored.
+//!
+//! Only databases of nodes with a single database (RPC and legacy archival
+//! nodes) may be backed up.  The hot database of split storage can’t be
+//! restored independently of the cold database.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use near_primitives::block::Tip;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+
+use crate::db::rocksdb::backup;
+use crate::metadata::{DbKind, DbVersion, DB_VERSION};
+use crate::{DBCol, Store, HEAD_KEY};
+
+/// Directory inside of the backup directory holding the metadata files.
+const METADATA_DIR: &str = "neard-metadata";
+
+/// Metadata of a single backup.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct BackupMetadata {
+    pub backup_id: u32,
+    /// Time the backup was taken at, in seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// Total size of the files of the backup, including files shared with
+    /// other backups.
+    pub size: u64,
+    pub db_version: DbVersion,
+    pub db_kind: DbKind,
+    /// Head of the chain just before the backup was taken.  The backup
+    /// contains at least the blocks up to and including the head.
+    pub head_height: Option<BlockHeight>,
+    pub head_hash: Option<CryptoHash>,
+}
+
+fn metadata_path(backup_dir: &Path, backup_id: u32) -> PathBuf {
+    backup_dir.join(METADATA_DIR).join(format!("{backup_id}.json"))
+}
+
+fn read_metadata(backup_dir: &Path, backup_id: u32) -> anyhow::Result<BackupMetadata> {
+    let path = metadata_path(backup_dir, backup_id);
+    let file = std::fs::File::open(&path)
+        .with_context(|| format!("failed to open backup metadata {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("failed to parse backup metadata {}", path.display()))
+}
+
+/// Creates a new backup of the store in `backup_dir` and deletes all but
+/// `max_backups` most recent backups.
+///
+/// The store must be backed by RocksDB and may be in use by the node.
+pub fn create_backup(
+    store: &Store,
+    backup_dir: &Path,
+    max_backups: usize,
+) -> anyhow::Result<BackupMetadata> {
+    let db_version = store.get_db_version()?.context("database version is missing")?;
+    let db_kind = store.get_db_kind()?.context("database kind is missing")?;
+    anyhow::ensure!(
+        matches!(db_kind, DbKind::RPC | DbKind::Archive),
+        "backups of {db_kind} databases are not supported"
+    );
+    // Read the head first so that the backup is guaranteed to contain it.
+    let head = store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?;
+
+    let backup_id = store.storage.create_backup(backup_dir, max_backups)?;
+    let infos = backup::get_backup_info(backup_dir)?;
+    let info = infos
+        .iter()
+        .find(|info| info.backup_id == backup_id)
+        .context("backup missing right after creating it")?;
+    let metadata = BackupMetadata {
+        backup_id,
+        timestamp: info.timestamp,
+        size: info.size,
+        db_version,
+        db_kind,
+        head_height: head.as_ref().map(|head| head.height),
+        head_hash: head.as_ref().map(|head| head.last_block_hash),
+    };
+
+    let path = metadata_path(backup_dir, backup_id);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec_pretty(&metadata)?)?;
+    std::fs::rename(&tmp_path, &path)?;
+
+    // Remove metadata of the backups purged by the engine.
+    for entry in std::fs::read_dir(path.parent().unwrap())? {
+        let entry_path = entry?.path();
+        let id = entry_path.file_stem().and_then(|stem| stem.to_str()?.parse::<u32>().ok());
+        if id.is_some_and(|id| infos.iter().all(|info| info.backup_id != id)) {
+            std::fs::remove_file(&entry_path)?;
+        }
+    }
+    tracing::info!(
+        target: "store",
+        backup_id,
+        head_height = metadata.head_height,
+        size = metadata.size,
+        "Created database backup"
+    );
+    Ok(metadata)
+}
+
+/// Returns metadata of all backups in `backup_dir`, oldest first.
+///
+/// Backups without metadata, e.g. ones interrupted before the metadata was
+/// written, are skipped.
+pub fn list_backups(backup_dir: &Path) -> anyhow::Result<Vec<BackupMetadata>> {
+    let mut backups = vec![];
+    for info in backup::get_backup_info(backup_dir)? {
+        match read_metadata(backup_dir, info.backup_id) {
+            Ok(metadata) => backups.push(metadata),
+            Err(err) => {
+                let backup_id = info.backup_id;
+                tracing::warn!(target: "store", backup_id, ?err, "Skipping backup");
+            }
+        }
+    }
+    backups.sort_by_key(|metadata| metadata.backup_id);
+    Ok(backups)
+}
+
+/// Checks that all files of the backup are present and not corrupted.
+///
+/// If `backup_id` is `None`, checks the most recent backup.
+pub fn verify_backup(backup_dir: &Path, backup_id: Option<u32>) -> anyhow::Result<BackupMetadata> {
+    let metadata = get_backup(backup_dir, backup_id)?;
+    backup::verify_backup(backup_dir, metadata.backup_id)
+        .with_context(|| format!("backup {} is corrupted", metadata.backup_id))?;
+    Ok(metadata)
+}
+
+/// Verifies the backup and restores it into `db_path`, which must not exist.
+///
+/// If `backup_id` is `None`, restores the most recent backup.  Fails without
+/// touching `db_path` if the metadata of the backup records a database
+/// version newer than this binary supports or a database kind which doesn’t
+/// match `archive`.  Besides that, the restored database isn’t checked in any
+/// way other than the checksums of its files.  The caller should open it,
+/// which migrates it if its version is older, and validate its contents
+/// before letting the node use it.
+pub fn restore_backup(
+    backup_dir: &Path,
+    backup_id: Option<u32>,
+    db_path: &Path,
+    archive: bool,
+) -> anyhow::Result<BackupMetadata> {
+    anyhow::ensure!(!db_path.exists(), "{} already exists", db_path.display());
+    let metadata = get_backup(backup_dir, backup_id)?;
+    check_metadata(&metadata, archive)?;
+    backup::verify_backup(backup_dir, metadata.backup_id)
+        .with_context(|| format!("backup {} is corrupted", metadata.backup_id))?;
+    tracing::info!(
+        target: "store",
+        backup_id = metadata.backup_id,
+        db_path = %db_path.display(),
+        "Restoring database backup"
+    );
+    backup::restore_backup(backup_dir, metadata.backup_id, db_path)
+        .with_context(|| format!("failed to restore backup {}", metadata.backup_id))?;
+    Ok(metadata)
+}
+
+/// Checks that a node with the given archival setting can open the database
+/// described by the metadata.
+fn check_metadata(metadata: &BackupMetadata, archive: bool) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        metadata.db_version <= DB_VERSION,
+        "backup {} has database version {} which is newer than the supported version {}",
+        metadata.backup_id,
+        metadata.db_version,
+        DB_VERSION
+    );
+    let want_kind = if archive { DbKind::Archive } else { DbKind::RPC };
+    anyhow::ensure!(
+        metadata.db_kind == want_kind,
+        "backup {} has database kind {} but the node needs {}",
+        metadata.backup_id,
+        metadata.db_kind,
+        want_kind
+    );
+    Ok(())
+}
+
+fn get_backup(backup_dir: &Path, backup_id: Option<u32>) -> anyhow::Result<BackupMetadata> {
+    let backups = list_backups(backup_dir)?;
+    let backup = match backup_id {
+        Some(backup_id) => backups.into_iter().find(|backup| backup.backup_id == backup_id),
+        None => backups.into_iter().last(),
+    };
+    backup.with_context(|| format!("no such backup in {}", backup_dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DBCol, NodeStorage};
+
+    #[test]
+    fn test_backup_and_restore() {
+        let (tmp_dir, opener) = NodeStorage::test_opener();
+        let store = opener.open().unwrap().get_hot_store();
+        let backup_dir = tmp_dir.path().join("backups");
+
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::BlockMisc, b"first", b"1");
+        store_update.commit().unwrap();
+        let first = super::create_backup(&store, &backup_dir, 2).unwrap();
+
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::BlockMisc, b"second", b"2");
+        store_update.commit().unwrap();
+        let second = super::create_backup(&store, &backup_dir, 2).unwrap();
+        assert!(second.backup_id > first.backup_id);
+        super::create_backup(&store, &backup_dir, 2).unwrap();
+
+        // Only the two most recent backups are kept.
+        let backups = super::list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].backup_id, second.backup_id);
+        assert_eq!(backups[0].db_version, crate::metadata::DB_VERSION);
+
+        let db_path = tmp_dir.path().join("restored");
+        super::restore_backup(&backup_dir, Some(second.backup_id), &db_path, false).unwrap();
+        let mut config = crate::StoreConfig::test_config();
+        config.path = Some(db_path);
+        let restored = NodeStorage::opener(tmp_dir.path(), &config, None)
+            .open_in_mode(crate::Mode::ReadOnly)
+            .unwrap()
+            .get_hot_store();
+        assert!(restored.exists(DBCol::BlockMisc, b"first").unwrap());
+        assert!(restored.exists(DBCol::BlockMisc, b"second").unwrap());
+
+        // Restoring over an existing directory is not allowed.
+        let db_path = tmp_dir.path().join("restored");
+        assert!(super::restore_backup(&backup_dir, None, &db_path, false).is_err());
+    }
+
+    #[test]
+    fn test_restore_checks_metadata() {
+        let (tmp_dir, opener) = NodeStorage::test_opener();
+        let store = opener.open().unwrap().get_hot_store();
+        let backup_dir = tmp_dir.path().join("backups");
+        let mut metadata = super::create_backup(&store, &backup_dir, 1).unwrap();
+        let db_path = tmp_dir.path().join("restored");
+
+        // The test database is not an archival one.
+        assert!(super::restore_backup(&backup_dir, None, &db_path, true).is_err());
+        assert!(!db_path.exists());
+
+        metadata.db_version = crate::metadata::DB_VERSION + 1;
+        let path = super::metadata_path(&backup_dir, metadata.backup_id);
+        std::fs::write(&path, serde_json::to_vec(&metadata).unwrap()).unwrap();
+        let err = super::restore_backup(&backup_dir, None, &db_path, false).unwrap_err();
+        assert!(err.to_string().contains("newer than the supported version"), "{err}");
+        assert!(!db_path.exists());
+    }
+}
@@ -16,7 +16,17 @@ use std::fmt;
 /// deprecation.  Make sure to add `#[strum(serialize = "OriginalName")]`
 /// attribute in front of the variant when you deprecate a column.
 #[derive(
-    PartialEq, Copy, Clone, Debug, Hash, Eq, enum_map::Enum, strum::EnumIter, strum::IntoStaticStr,
+    PartialEq,
+    Copy,
+    Clone,
+    Debug,
+    Hash,
+    Eq,
+    enum_map::Enum,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::EnumIter,
+    strum::IntoStaticStr,
 )]
 pub enum DBCol {
     /// Column to indicate which version of database this is.
@@ -317,6 +327,30 @@ pub enum DBCol {
     /// - *Rows*: `CryptoHash`
     /// - *Column type*: `Vec<u8>`
     StateSyncNewChunks,
+    /// Snapshot of the transaction pool, saved periodically and on shutdown so that pending
+    /// transactions survive node restarts.
+    /// - *Rows*: `TransactionHash`
+    /// - *Column type*: `SignedTransaction`
+    TransactionPool,
+    /// History of values of accounts, access keys and contract data, used by archival nodes to
+    /// answer queries about past blocks without traversing the trie. Exists only in the cold
+    /// database, see `crate::archive::state_history`.
+    /// - *Rows*: escaped TrieKey || inverted BlockHeight, or an empty key for the indexed range
+    /// - *Column type*: `Option<Vec<u8>>`, or `StateHistoryRange` for the empty key
+    StateHistory,
+    /// Index of transactions and receipts which touched each account, maintained when
+    /// `save_account_activity` is enabled in the client config.  Entries are garbage collected
+    /// together with their blocks, after archival nodes have copied them to the cold database,
+    /// and entries of blocks which ended up off the canonical chain are filtered out on read.
+    /// See `crate::archive::account_activity` for the layout of the keys.
+    /// - *Rows*: AccountId || ',' || BlockHeight || BlockHash
+    /// - *Column type*: `Vec<AccountActivity>`
+    AccountActivity,
+    /// Reputation scores of TIER2 peers, see `near_network::peer_manager::peer_score`.
+    /// Saved periodically so that the scores survive node restarts.
+    /// - *Rows*: single row (empty row name)
+    /// - *Column type*: Vec of `PeerScoreRepr`
+    PeerScores,
 }
 
 /// Defines different logical parts of a db key.
@@ -463,6 +497,10 @@ impl DBCol {
             | DBCol::TransactionResultForBlock
             | DBCol::Transactions
             | DBCol::StateShardUIdMapping => true,
+            // AccountActivity is an optional index garbage collected with the blocks, which
+            // archival nodes serve from the cold database. It has its own copying logic, see
+            // `copy_account_activity_from_store`.
+            DBCol::AccountActivity => true,
 
             // TODO
             DBCol::ChallengedBlocks => false,
@@ -499,6 +537,7 @@ impl DBCol {
             | DBCol::BlockHeight
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
+            | DBCol::PeerScores
             | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
@@ -522,13 +561,27 @@ impl DBCol {
             | DBCol::FlatStorageStatus
             | DBCol::EpochSyncProof
             | DBCol::StateSyncHashes
-            | DBCol::StateSyncNewChunks => false,
+            | DBCol::StateSyncNewChunks
+            // TransactionPool is specific to the node and is overwritten on every snapshot.
+            | DBCol::TransactionPool => false,
+            // StateHistory is written directly to the cold database rather than copied from hot.
+            DBCol::StateHistory => false,
         }
     }
 
     /// Whether this column exists in cold storage.
     pub(crate) const fn is_in_colddb(&self) -> bool {
-        matches!(*self, DBCol::DbVersion | DBCol::BlockMisc) || self.is_cold()
+        matches!(*self, DBCol::DbVersion | DBCol::BlockMisc)
+            || self.is_cold()
+            || self.is_cold_only()
+    }
+
+    /// Whether this column exists only in cold storage.
+    ///
+    /// Such columns are written directly to the cold database and reads from
+    /// the split storage always go to the cold database.
+    pub const fn is_cold_only(&self) -> bool {
+        matches!(*self, DBCol::StateHistory)
     }
 
     /// Vector of DBKeyType s concatenation of which results in key for the column.
@@ -547,6 +600,7 @@ impl DBCol {
             DBCol::IncomingReceipts => &[DBKeyType::BlockHash, DBKeyType::ShardId],
             DBCol::_Peers => &[DBKeyType::PeerId],
             DBCol::RecentOutboundConnections => &[DBKeyType::Empty],
+            DBCol::PeerScores => &[DBKeyType::Empty],
             DBCol::EpochInfo => &[DBKeyType::EpochId],
             DBCol::BlockInfo => &[DBKeyType::BlockHash],
             DBCol::Chunks => &[DBKeyType::ChunkHash],
@@ -598,6 +652,11 @@ impl DBCol {
             DBCol::StateShardUIdMapping => &[DBKeyType::ShardUId],
             DBCol::StateSyncHashes => &[DBKeyType::EpochId],
             DBCol::StateSyncNewChunks => &[DBKeyType::BlockHash],
+            DBCol::TransactionPool => &[DBKeyType::TransactionHash],
+            DBCol::StateHistory => &[DBKeyType::TrieKey, DBKeyType::BlockHeight],
+            DBCol::AccountActivity => {
+                &[DBKeyType::AccountId, DBKeyType::BlockHeight, DBKeyType::BlockHash]
+            }
         }
     }
 }
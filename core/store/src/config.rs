@@ -61,6 +61,13 @@ pub struct StoreConfig {
     /// the performance of the storage
     pub block_size: bytesize::ByteSize,
 
+    /// Overrides of RocksDB options of individual columns, e.g. heavier
+    /// compression of rarely read columns on archival nodes.  Options which
+    /// aren't overridden keep their defaults.  The effective options can be
+    /// printed with `neard database show-column-options`.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub column_overrides: HashMap<DBCol, ColumnOverrides>,
+
     /// Trie cache configuration per shard for normal (non-view) caches.
     pub trie_cache: TrieCacheConfig,
     /// Trie cache configuration per shard for view caches.
@@ -87,6 +94,14 @@ pub struct StoreConfig {
     /// If true, load mem trie for each shard being tracked; this has priority over `load_mem_tries_for_shards`.
     pub load_mem_tries_for_tracked_shards: bool,
 
+    /// Snapshots of in-memory tries, which make loading them on start faster.
+    pub mem_trie_snapshot: MemTrieSnapshotConfig,
+
+    /// Scheduled incremental backups of the database taken by the running
+    /// node.  Disabled if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<DbBackupConfig>,
+
     /// Path where to create RocksDB checkpoints during database migrations or
     /// `false` to disable that feature.
     ///
@@ -118,6 +133,96 @@ pub struct StoreConfig {
     pub state_snapshot_enabled: bool,
 }
 
+/// Number of levels of the LSM trees of the columns.
+const NUM_LEVELS: usize = 7;
+
+/// Default size of the dictionaries trained for Zstd compression.
+///
+/// RocksDB documentation says that 16KB is a typical dictionary size.  We've
+/// empirically tuned the dictionary size to twice of that ‘typical’ size.
+/// See: https://rocksdb.org/blog/2021/05/31/dictionary-compression.html
+const DEFAULT_ZSTD_DICTIONARY_SIZE: bytesize::ByteSize = bytesize::ByteSize::kib(32);
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, strum::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CompressionAlgorithm {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, strum::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CompactionStyle {
+    /// Leveled compaction, which keeps space and read amplification low.
+    Level,
+    /// Universal compaction, which reduces write amplification at the cost
+    /// of space and read amplification.
+    Universal,
+}
+
+/// Overrides of RocksDB options of a single column.
+///
+/// Options which are not set keep their defaults.  Changes take effect when
+/// the database is opened, for data written or compacted afterwards.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColumnOverrides {
+    /// Compression algorithm of all levels of the column.  By default the
+    /// first two levels aren’t compressed, the following ones use LZ4 and the
+    /// last level uses Zstd.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionAlgorithm>,
+    /// Compression level, meaningful for Zstd.  Defaults to the default
+    /// level of the algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression_level: Option<i32>,
+    /// Maximum size of the dictionaries trained for Zstd compression, for all
+    /// levels.  By default only the last level uses dictionaries of 32KiB.
+    /// Zero disables dictionaries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zstd_dictionary_size: Option<bytesize::ByteSize>,
+    /// Defaults to `block_size` of the store.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<bytesize::ByteSize>,
+    /// Bits per key of the bloom filters.  Defaults to 10.  Zero disables
+    /// bloom filters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bloom_filter_bits_per_key: Option<f64>,
+    /// Size of the block cache of the column.  Defaults to
+    /// `col_state_cache_size` for `State`, `col_flat_state_cache_size` for
+    /// `FlatState` and 32MiB for other columns.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_cache_size: Option<bytesize::ByteSize>,
+    /// Defaults to leveled compaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compaction_style: Option<CompactionStyle>,
+}
+
+/// Effective RocksDB options of a single column, as returned by
+/// [`StoreConfig::column_options`].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct ColumnOptions {
+    pub compression_per_level: Vec<CompressionAlgorithm>,
+    pub bottommost_compression: CompressionAlgorithm,
+    /// `None` means the default level of the algorithm.
+    pub compression_level: Option<i32>,
+    /// Dictionary size of levels other than the last one.
+    pub zstd_dictionary_size: bytesize::ByteSize,
+    pub bottommost_zstd_dictionary_size: bytesize::ByteSize,
+    pub block_size: bytesize::ByteSize,
+    pub bloom_filter_bits_per_key: f64,
+    pub block_cache_size: bytesize::ByteSize,
+    pub compaction_style: CompactionStyle,
+}
+
 /// Config used to control state snapshot creation. This is used for state sync and resharding.
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
@@ -136,6 +241,79 @@ pub enum StateSnapshotType {
     EveryEpoch,
 }
 
+/// Config of the on-disk snapshots of in-memory tries.
+///
+/// When enabled, the in-memory tries of each shard are saved to a snapshot on
+/// graceful shutdown.  On start the snapshot is mapped
+/// into memory and the flat state deltas since are applied on top of it, which
+/// is much faster than building the tries from flat storage.  Stale or invalid
+/// snapshots are ignored and the tries are loaded from flat storage.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MemTrieSnapshotConfig {
+    pub enabled: bool,
+    /// Directory of the snapshots.  If relative, resolved relative to the
+    /// database directory.  Snapshots take about as much space as the
+    /// in-memory tries of the tracked shards.
+    pub path: std::path::PathBuf,
+    /// Whether to verify the checksums of the snapshots on load.  Verifying
+    /// reads the whole snapshot, which makes loading slower as the snapshot
+    /// is otherwise only read as the tries are accessed.  Without it, only
+    /// the hashes stored in the root nodes are compared with the state roots,
+    /// so corrupted nodes below the roots go unnoticed.
+    pub verify_checksums: bool,
+}
+
+impl Default for MemTrieSnapshotConfig {
+    fn default() -> Self {
+        Self { enabled: false, path: "memtrie-snapshots".into(), verify_checksums: true }
+    }
+}
+
+impl MemTrieSnapshotConfig {
+    /// Returns path to the snapshots directory given path to the database.
+    ///
+    /// Returns `None` if snapshots are disabled.
+    pub fn get_path(&self, db_path: &std::path::Path) -> Option<std::path::PathBuf> {
+        self.enabled.then(|| db_path.join(&self.path))
+    }
+}
+
+/// Config of the scheduled backups of the database.
+///
+/// Backups are incremental: backups in the same directory share unchanged
+/// files, so each backup copies only the files written since the previous
+/// one.  Backups can be listed, verified and restored with the
+/// `neard database backup` and `neard database restore` commands.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DbBackupConfig {
+    /// Directory of the backups.  If relative, resolved relative to neard
+    /// home directory.  May be a mounted network or object storage.
+    pub path: std::path::PathBuf,
+    /// How often to take a backup.
+    #[serde(default = "default_db_backup_period")]
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub period: Duration,
+    /// Number of most recent backups to keep.
+    #[serde(default = "default_db_backup_max_backups")]
+    pub max_backups: usize,
+}
+
+impl DbBackupConfig {
+    /// Returns path to the backups directory given neard home directory.
+    pub fn get_path(&self, home_dir: &std::path::Path) -> std::path::PathBuf {
+        home_dir.join(&self.path)
+    }
+}
+
+fn default_db_backup_period() -> Duration {
+    Duration::hours(6)
+}
+
+fn default_db_backup_max_backups() -> usize {
+    4
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
 pub enum MigrationSnapshot {
@@ -191,6 +369,41 @@ impl StoreConfig {
         }
     }
 
+    /// Returns the effective RocksDB options of given column, i.e. the
+    /// defaults with `column_overrides` applied.
+    pub fn column_options(&self, col: DBCol) -> ColumnOptions {
+        let overrides = self.column_overrides.get(&col).cloned().unwrap_or_default();
+        // By default the first two levels aren’t compressed since their data is
+        // short-lived and the last level is compressed with Zstd with
+        // a dictionary.
+        let compression_per_level =
+            match overrides.compression {
+                Some(compression) => vec![compression; NUM_LEVELS],
+                None => (0..NUM_LEVELS)
+                    .map(|level| {
+                        if level < 2 {
+                            CompressionAlgorithm::None
+                        } else {
+                            CompressionAlgorithm::Lz4
+                        }
+                    })
+                    .collect(),
+            };
+        ColumnOptions {
+            compression_per_level,
+            bottommost_compression: overrides.compression.unwrap_or(CompressionAlgorithm::Zstd),
+            compression_level: overrides.compression_level,
+            zstd_dictionary_size: overrides.zstd_dictionary_size.unwrap_or_default(),
+            bottommost_zstd_dictionary_size: overrides
+                .zstd_dictionary_size
+                .unwrap_or(DEFAULT_ZSTD_DICTIONARY_SIZE),
+            block_size: overrides.block_size.unwrap_or(self.block_size),
+            bloom_filter_bits_per_key: overrides.bloom_filter_bits_per_key.unwrap_or(10.0),
+            block_cache_size: overrides.block_cache_size.unwrap_or(self.col_cache_size(col)),
+            compaction_style: overrides.compaction_style.unwrap_or(CompactionStyle::Level),
+        }
+    }
+
     fn default_per_shard_max_bytes() -> HashMap<ShardUId, bytesize::ByteSize> {
         let epoch_config_store = EpochConfigStore::for_chain_id(MAINNET, None).unwrap();
         let mut shard_layouts: Vec<ShardLayout> = Vec::new();
@@ -254,6 +467,8 @@ impl Default for StoreConfig {
             // we use it since then.
             block_size: bytesize::ByteSize::kib(16),
 
+            column_overrides: Default::default(),
+
             trie_cache: TrieCacheConfig {
                 default_max_bytes: bytesize::ByteSize::mb(500),
                 per_shard_max_bytes: Self::default_per_shard_max_bytes(),
@@ -291,6 +506,9 @@ impl Default for StoreConfig {
             // requires more RAM and takes several minutes on startup.
             load_mem_tries_for_shards: Default::default(),
             load_mem_tries_for_tracked_shards: false,
+            mem_trie_snapshot: Default::default(),
+
+            backup: None,
 
             migration_snapshot: Default::default(),
 
@@ -380,6 +598,14 @@ pub struct SplitStorageConfig {
 
     #[serde(default = "default_num_cold_store_read_threads")]
     pub num_cold_store_read_threads: usize,
+
+    /// Whether to keep the state history index in the cold storage up to date
+    /// while copying blocks, and to answer queries about accounts, access keys
+    /// and contract data at past blocks from it without traversing the trie.
+    /// It has to be initialised first with the `cold-store
+    /// backfill-state-history` command.
+    #[serde(default)]
+    pub enable_state_history_index: bool,
 }
 
 impl Default for SplitStorageConfig {
@@ -392,6 +618,7 @@ impl Default for SplitStorageConfig {
                 default_cold_store_initial_migration_loop_sleep_duration(),
             cold_store_loop_sleep_duration: default_cold_store_loop_sleep_duration(),
             num_cold_store_read_threads: default_num_cold_store_read_threads(),
+            enable_state_history_index: false,
         }
     }
 }
@@ -515,3 +742,43 @@ impl<'a> ArchivalConfig<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CompactionStyle, CompressionAlgorithm, StoreConfig};
+    use crate::DBCol;
+
+    #[test]
+    fn test_column_overrides() {
+        let config: StoreConfig = serde_json::from_value(serde_json::json!({
+            "column_overrides": {
+                "Transactions": {
+                    "compression": "zstd",
+                    "compression_level": 19,
+                    "zstd_dictionary_size": 65536,
+                    "compaction_style": "universal",
+                },
+                "FlatState": { "compression": "none" },
+            }
+        }))
+        .unwrap();
+
+        let options = config.column_options(DBCol::Transactions);
+        assert_eq!(options.compression_per_level, vec![CompressionAlgorithm::Zstd; 7]);
+        assert_eq!(options.compression_level, Some(19));
+        assert_eq!(options.zstd_dictionary_size, bytesize::ByteSize::kib(64));
+        assert_eq!(options.bottommost_zstd_dictionary_size, bytesize::ByteSize::kib(64));
+        assert_eq!(options.compaction_style, CompactionStyle::Universal);
+
+        let options = config.column_options(DBCol::FlatState);
+        assert_eq!(options.bottommost_compression, CompressionAlgorithm::None);
+        assert_eq!(options.block_cache_size, config.col_flat_state_cache_size);
+
+        // Columns without overrides keep the defaults.
+        let options = config.column_options(DBCol::Block);
+        assert_eq!(options, StoreConfig::default().column_options(DBCol::Block));
+        assert_eq!(options.compression_per_level[..2], [CompressionAlgorithm::None; 2]);
+        assert_eq!(options.bottommost_compression, CompressionAlgorithm::Zstd);
+        assert_eq!(options.bottommost_zstd_dictionary_size, bytesize::ByteSize::kib(32));
+    }
+}
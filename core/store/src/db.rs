@@ -7,6 +7,7 @@ pub(crate) mod rocksdb;
 
 mod colddb;
 mod mixeddb;
+mod overlaydb;
 mod recoverydb;
 mod splitdb;
 
@@ -18,6 +19,7 @@ mod database_tests;
 
 pub use self::colddb::ColdDB;
 pub use self::mixeddb::{MixedDB, ReadOrder};
+pub use self::overlaydb::OverlayDB;
 pub use self::recoverydb::RecoveryDB;
 pub use self::rocksdb::RocksDB;
 pub use self::splitdb::SplitDB;
@@ -41,6 +43,7 @@ pub const GENESIS_CONGESTION_INFO_KEY: &[u8] = b"GENESIS_CONGESTION_INFO_KEY";
 pub const COLD_HEAD_KEY: &[u8; 9] = b"COLD_HEAD";
 pub const STATE_SYNC_DUMP_KEY: &[u8; 15] = b"STATE_SYNC_DUMP";
 pub const STATE_SNAPSHOT_KEY: &[u8; 18] = b"STATE_SNAPSHOT_KEY";
+pub const SANDBOX_PINNED_HEIGHT_KEY: &[u8; 21] = b"SANDBOX_PINNED_HEIGHT";
 
 // `DBCol::Misc` keys
 pub const FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY: &[u8] =
@@ -260,6 +263,18 @@ pub trait Database: Sync + Send {
         columns_to_keep: Option<&[DBCol]>,
     ) -> anyhow::Result<()>;
 
+    /// Creates a new incremental backup of the database in provided directory
+    /// and deletes all but `max_backups` most recent backups.
+    ///
+    /// Returns id of the new backup.  Only supported by RocksDB.
+    fn create_backup(
+        &self,
+        _backup_dir: &std::path::Path,
+        _max_backups: usize,
+    ) -> anyhow::Result<u32> {
+        anyhow::bail!("backups are not supported by this database")
+    }
+
     /// If this is a test database, return a copy of the entire database.
     /// Otherwise return None.
     fn copy_if_test(&self) -> Option<Arc<dyn Database>> {
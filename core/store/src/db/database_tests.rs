@@ -2,15 +2,15 @@
 //! Set of tests over the 'Database' interface, that we can run over multiple implementations
 //! to make sure that they are working correctly.
 
-use crate::db::{DBTransaction, Database, TestDB};
+use crate::db::{DBTransaction, Database, OverlayDB, TestDB};
 use crate::{DBCol, NodeStorage};
 use std::sync::Arc;
 
-// Returns test & rocksDB databases.
+// Returns test & rocksDB databases, and an overlay over an empty test database.
 fn test_and_rocksdb() -> Vec<Arc<dyn Database>> {
     let (_tmp_dir, opener) = NodeStorage::test_opener();
     let store = opener.open().unwrap().get_hot_store();
-    vec![TestDB::new(), store.storage.clone()]
+    vec![TestDB::new(), store.storage.clone(), OverlayDB::in_memory(TestDB::new())]
 }
 
 /// Tests the behavior of the iterators. Iterators don't really work over cold storage, so we're not testing it here.
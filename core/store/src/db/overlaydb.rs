@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use itertools::EitherOrBoth;
+use strum::IntoEnumIterator;
+
+use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StoreStatistics};
+use crate::DBCol;
+
+/// Tag of values stored in the scratch database.
+const VALUE_TAG: u8 = 1;
+/// Value stored in the scratch database for keys deleted in the overlay.
+const TOMBSTONE: &[u8] = &[0];
+
+/// Number of operations written at once when committing the overlay.
+const COMMIT_BATCH_SIZE: usize = 10_000;
+
+type OverlayIteratorItem = io::Result<(Box<[u8]>, Option<Box<[u8]>>)>;
+
+/// OverlayDB is a copy-on-write layer over an immutable base database.
+///
+/// All reads see the base database with the changes made through the overlay
+/// applied on top, while all writes and deletes, including reference count
+/// updates, go to a separate scratch database.  The base database is never
+/// modified, so destructive experiments, e.g. applying a range of blocks or
+/// forking the network, can be run against a production database opened in
+/// read-only mode.  Once done, the overlay can be discarded or committed to
+/// a writable database.
+///
+/// The scratch database stores the raw value of each overridden key prefixed
+/// with a tag, or a tombstone for deleted keys.  Reference counts are merged
+/// with the base value when written, so the scratch database only ever sees
+/// plain `Set` operations and may be any `Database`, e.g. a `TestDB` or an
+/// empty RocksDB.
+///
+/// Like `MixedDB`, OverlayDB is meant for neard tools and not for production
+/// use.  Iterating over ranges and prefixes scans the whole column of the
+/// scratch database, so it gets slower as the overlay grows.
+pub struct OverlayDB {
+    base: Arc<dyn Database>,
+    scratch: Arc<dyn Database>,
+    /// Serializes writes, as reference count updates and range deletions need
+    /// to read the current state of the overlay.
+    write_lock: Mutex<()>,
+}
+
+impl OverlayDB {
+    pub fn new(base: Arc<dyn Database>, scratch: Arc<dyn Database>) -> Arc<Self> {
+        Arc::new(Self { base, scratch, write_lock: Mutex::new(()) })
+    }
+
+    /// Creates an overlay keeping the changes in memory.
+    pub fn in_memory(base: Arc<dyn Database>) -> Arc<Self> {
+        Self::new(base, crate::db::TestDB::new())
+    }
+
+    /// Returns the value of the key in the overlay: `None` if the key isn't
+    /// overridden, `Some(None)` if it's deleted and `Some(Some(value))` if it
+    /// is set to the given raw value.
+    fn get_overlay(&self, col: DBCol, key: &[u8]) -> io::Result<Option<Option<Vec<u8>>>> {
+        let Some(value) = self.scratch.get_raw_bytes(col, key)? else {
+            return Ok(None);
+        };
+        decode_overlay_value(value.as_slice()).map(|value| Some(value.map(<[u8]>::to_vec)))
+    }
+
+    /// Iterates over all entries of the overlay in the column, including
+    /// deleted keys.
+    fn iter_overlay<'a>(&'a self, col: DBCol) -> impl Iterator<Item = OverlayIteratorItem> + 'a {
+        self.scratch.iter_raw_bytes(col).map(|item| {
+            let (key, value) = item?;
+            let value = decode_overlay_value(&value)?.map(Box::from);
+            Ok((key, value))
+        })
+    }
+
+    /// Merges an iterator over the base database with an iterator over the
+    /// overlay.  Keys of the overlay replace keys of the base database and
+    /// deleted keys are skipped.
+    ///
+    /// If `strip_refcount` is set, values of the overlay are treated the same
+    /// way as the values of the base database returned by `Database::iter`.
+    fn merge_iter<'a>(
+        col: DBCol,
+        base: DBIterator<'a>,
+        overlay: impl Iterator<Item = OverlayIteratorItem> + 'a,
+        strip_refcount: bool,
+    ) -> DBIterator<'a> {
+        let strip_refcount = strip_refcount && col.is_rc();
+        let overlay = overlay.map(move |item| {
+            let (key, value) = item?;
+            let value = match value {
+                Some(value) if strip_refcount => {
+                    refcount::strip_refcount(value.into_vec()).map(Vec::into_boxed_slice)
+                }
+                value => value,
+            };
+            Ok((key, value))
+        });
+        let iter = itertools::merge_join_by(base, overlay, |a, b| match (a, b) {
+            (Err(_), _) => std::cmp::Ordering::Less,
+            (_, Err(_)) => std::cmp::Ordering::Greater,
+            (Ok((a_key, _)), Ok((b_key, _))) => Ord::cmp(a_key, b_key),
+        });
+        Box::new(iter.filter_map(|item| match item {
+            EitherOrBoth::Left(item) => Some(item),
+            EitherOrBoth::Right(item) | EitherOrBoth::Both(_, item) => match item {
+                Ok((key, Some(value))) => Some(Ok((key, value))),
+                Ok((_, None)) => None,
+                Err(err) => Some(Err(err)),
+            },
+        }))
+    }
+
+    /// Writes all changes made in the overlay to the given database, which
+    /// would usually be the base database opened for writing, and discards
+    /// them from the overlay.
+    pub fn commit(&self, db: &dyn Database) -> io::Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        for col in DBCol::iter() {
+            let mut transaction = DBTransaction::new();
+            for item in self.iter_overlay(col) {
+                let (key, value) = item?;
+                match value {
+                    Some(value) => transaction.set(col, key.into_vec(), value.into_vec()),
+                    None => transaction.delete(col, key.into_vec()),
+                }
+                if transaction.ops.len() >= COMMIT_BATCH_SIZE {
+                    db.write(std::mem::take(&mut transaction))?;
+                }
+            }
+            db.write(transaction)?;
+        }
+        tracing::info!(target: "store", "Committed overlay database");
+        self.discard_locked()
+    }
+
+    /// Discards all changes made in the overlay.
+    pub fn discard(&self) -> io::Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        self.discard_locked()
+    }
+
+    fn discard_locked(&self) -> io::Result<()> {
+        let mut transaction = DBTransaction::new();
+        for col in DBCol::iter() {
+            transaction.delete_all(col);
+        }
+        self.scratch.write(transaction)
+    }
+}
+
+fn decode_overlay_value(value: &[u8]) -> io::Result<Option<&[u8]>> {
+    match value.split_first() {
+        Some((&VALUE_TAG, value)) => Ok(Some(value)),
+        _ if value == TOMBSTONE => Ok(None),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid overlay value")),
+    }
+}
+
+/// Changes made by a single transaction, applied to the overlay at once.
+struct PendingChanges<'a> {
+    db: &'a OverlayDB,
+    changes: HashMap<(DBCol, Vec<u8>), Option<Vec<u8>>>,
+}
+
+impl PendingChanges<'_> {
+    /// Returns the current raw value of the key, taking into account the
+    /// earlier operations of the transaction.
+    fn get(&self, col: DBCol, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if let Some(value) = self.changes.get(&(col, key.to_vec())) {
+            return Ok(value.clone());
+        }
+        Ok(self.db.get_raw_bytes(col, key)?.map(|value| value.as_slice().to_vec()))
+    }
+
+    fn set(&mut self, col: DBCol, key: Vec<u8>, value: Option<Vec<u8>>) {
+        self.changes.insert((col, key), value);
+    }
+
+    /// Deletes all keys of the column within the range, which are either in
+    /// the database or set by the earlier operations of the transaction.
+    fn delete_range(
+        &mut self,
+        col: DBCol,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+    ) -> io::Result<()> {
+        let in_range =
+            |key: &[u8]| from.map_or(true, |from| key >= from) && to.map_or(true, |to| key < to);
+        for ((change_col, key), value) in self.changes.iter_mut() {
+            if *change_col == col && in_range(key.as_slice()) {
+                *value = None;
+            }
+        }
+        let keys = self
+            .db
+            .iter_range(col, from, to)
+            .map(|item| item.map(|(key, _)| key.into_vec()))
+            .collect::<io::Result<Vec<_>>>()?;
+        for key in keys {
+            self.set(col, key, None);
+        }
+        Ok(())
+    }
+}
+
+impl Database for OverlayDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        match self.get_overlay(col, key)? {
+            Some(value) => Ok(value.map(DBSlice::from_vec)),
+            None => self.base.get_raw_bytes(col, key),
+        }
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        Self::merge_iter(col, self.base.iter(col), self.iter_overlay(col), true)
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        let overlay = self.iter_overlay(col).filter(move |item| match item {
+            Ok((key, _)) => key.starts_with(key_prefix),
+            Err(_) => true,
+        });
+        Self::merge_iter(col, self.base.iter_prefix(col, key_prefix), overlay, true)
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        let lower = lower_bound.map(<[u8]>::to_vec);
+        let upper = upper_bound.map(<[u8]>::to_vec);
+        let overlay = self.iter_overlay(col).filter(move |item| match item {
+            Ok((key, _)) => {
+                lower.as_ref().map_or(true, |lower| **key >= **lower)
+                    && upper.as_ref().map_or(true, |upper| **key < **upper)
+            }
+            Err(_) => true,
+        });
+        Self::merge_iter(col, self.base.iter_range(col, lower_bound, upper_bound), overlay, true)
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        Self::merge_iter(col, self.base.iter_raw_bytes(col), self.iter_overlay(col), false)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut pending = PendingChanges { db: self, changes: HashMap::new() };
+        for op in transaction.ops {
+            match op {
+                DBOp::Set { col, key, value } => pending.set(col, key, Some(value)),
+                DBOp::Insert { col, key, value } => {
+                    if cfg!(debug_assertions) {
+                        if let Some(old_value) = pending.get(col, &key)? {
+                            super::assert_no_overwrite(col, &key, &value, &old_value)
+                        }
+                    }
+                    pending.set(col, key, Some(value));
+                }
+                DBOp::UpdateRefcount { col, key, value } => {
+                    let existing = pending.get(col, &key)?;
+                    let merged = refcount::refcount_merge(existing.as_deref(), [value.as_slice()]);
+                    let merged = if merged.is_empty() { None } else { Some(merged) };
+                    pending.set(col, key, merged);
+                }
+                DBOp::Delete { col, key } => pending.set(col, key, None),
+                DBOp::DeleteAll { col } => pending.delete_range(col, None, None)?,
+                DBOp::DeleteRange { col, from, to } => {
+                    pending.delete_range(col, Some(&from), Some(&to))?
+                }
+            }
+        }
+
+        let mut scratch_transaction = DBTransaction::new();
+        for ((col, key), value) in pending.changes {
+            let value = match value {
+                Some(value) => [&[VALUE_TAG], value.as_slice()].concat(),
+                None => TOMBSTONE.to_vec(),
+            };
+            scratch_transaction.set(col, key, value);
+        }
+        self.scratch.write(scratch_transaction)
+    }
+
+    /// There is no need to flush the immutable base DB.
+    fn flush(&self) -> io::Result<()> {
+        self.scratch.flush()
+    }
+
+    /// There is no need to compact the immutable base DB.
+    fn compact(&self) -> io::Result<()> {
+        self.scratch.compact()
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        self.scratch.get_store_statistics()
+    }
+
+    /// Checkpoints would need to merge the base and the overlay, which isn't
+    /// supported.  Commit the overlay to a writable copy of the base instead.
+    fn create_checkpoint(
+        &self,
+        _path: &std::path::Path,
+        _columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("checkpoints of an overlay database are not supported")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OverlayDB;
+    use crate::db::{DBTransaction, Database, TestDB};
+    use crate::DBCol;
+
+    fn keys(iter: crate::db::DBIterator) -> Vec<String> {
+        iter.map(|item| String::from_utf8(item.unwrap().0.into_vec()).unwrap()).collect()
+    }
+
+    fn rc_value(value: &[u8], rc: i64) -> Vec<u8> {
+        [value, &rc.to_le_bytes()].concat()
+    }
+
+    #[test]
+    fn test_overlay_doesnt_modify_base() {
+        let base = TestDB::new();
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::BlockMisc, b"a".to_vec(), b"base a".to_vec());
+        transaction.set(DBCol::BlockMisc, b"b".to_vec(), b"base b".to_vec());
+        transaction.set(DBCol::BlockMisc, b"c".to_vec(), b"base c".to_vec());
+        base.write(transaction).unwrap();
+
+        let overlay = OverlayDB::in_memory(base.clone());
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::BlockMisc, b"b".to_vec(), b"overlay b".to_vec());
+        transaction.delete(DBCol::BlockMisc, b"c".to_vec());
+        transaction.set(DBCol::BlockMisc, b"d".to_vec(), b"overlay d".to_vec());
+        overlay.write(transaction).unwrap();
+
+        let get = |db: &dyn Database, key: &[u8]| {
+            db.get_raw_bytes(DBCol::BlockMisc, key).unwrap().map(|value| value.to_vec())
+        };
+        assert_eq!(get(&*overlay, b"a"), Some(b"base a".to_vec()));
+        assert_eq!(get(&*overlay, b"b"), Some(b"overlay b".to_vec()));
+        assert_eq!(get(&*overlay, b"c"), None);
+        assert_eq!(get(&*overlay, b"d"), Some(b"overlay d".to_vec()));
+        assert_eq!(keys(overlay.iter(DBCol::BlockMisc)), vec!["a", "b", "d"]);
+        assert_eq!(keys(overlay.iter_range(DBCol::BlockMisc, Some(b"b"), Some(b"d"))), vec!["b"]);
+        assert_eq!(keys(base.iter(DBCol::BlockMisc)), vec!["a", "b", "c"]);
+        assert_eq!(get(&*base, b"b"), Some(b"base b".to_vec()));
+
+        let mut transaction = DBTransaction::new();
+        transaction.delete_range(DBCol::BlockMisc, b"a".to_vec(), b"c".to_vec());
+        overlay.write(transaction).unwrap();
+        assert_eq!(keys(overlay.iter(DBCol::BlockMisc)), vec!["d"]);
+
+        let mut transaction = DBTransaction::new();
+        transaction.delete_all(DBCol::BlockMisc);
+        overlay.write(transaction).unwrap();
+        assert!(keys(overlay.iter(DBCol::BlockMisc)).is_empty());
+        assert_eq!(keys(base.iter(DBCol::BlockMisc)), vec!["a", "b", "c"]);
+
+        overlay.discard().unwrap();
+        assert_eq!(keys(overlay.iter(DBCol::BlockMisc)), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_overlay_refcount() {
+        let base = TestDB::new();
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(DBCol::State, b"x".to_vec(), rc_value(b"value", 1));
+        base.write(transaction).unwrap();
+
+        let overlay = OverlayDB::in_memory(base.clone());
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(DBCol::State, b"x".to_vec(), rc_value(b"value", 2));
+        transaction.update_refcount(DBCol::State, b"x".to_vec(), rc_value(b"", -1));
+        transaction.update_refcount(DBCol::State, b"y".to_vec(), rc_value(b"other", 1));
+        overlay.write(transaction).unwrap();
+
+        let raw = overlay.get_raw_bytes(DBCol::State, b"x").unwrap().unwrap();
+        assert_eq!(raw.as_slice(), rc_value(b"value", 2).as_slice());
+        let value = overlay.get_with_rc_stripped(DBCol::State, b"y").unwrap().unwrap();
+        assert_eq!(value.as_slice(), b"other");
+
+        // Removing the last reference deletes the key from the overlay.
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(DBCol::State, b"x".to_vec(), rc_value(b"", -2));
+        overlay.write(transaction).unwrap();
+        assert!(overlay.get_raw_bytes(DBCol::State, b"x").unwrap().is_none());
+        assert_eq!(keys(overlay.iter(DBCol::State)), vec!["y"]);
+        assert_eq!(keys(base.iter(DBCol::State)), vec!["x"]);
+
+        // Committing writes the merged values to the target database.
+        overlay.commit(&*base).unwrap();
+        assert_eq!(keys(base.iter(DBCol::State)), vec!["y"]);
+        let raw = base.get_raw_bytes(DBCol::State, b"y").unwrap().unwrap();
+        assert_eq!(raw.as_slice(), rc_value(b"other", 1).as_slice());
+    }
+}
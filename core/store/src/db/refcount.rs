@@ -17,6 +17,10 @@
 //! a way to encode reference count.  During compaction, RocksDB merges the
 //! values by adding the reference counts.  When the reference count reaches
 //! zero RocksDB removes the key from the database.
+//!
+//! Corrupted values can be repaired without touching their reference counts
+//! with operands encoded by [`encode_payload_replacement`], which replace the
+//! value of an existing key when merged.
 
 use std::cmp::Ordering;
 use std::io;
@@ -101,6 +105,27 @@ pub(crate) fn encode_negative_refcount(rc: std::num::NonZeroU32) -> [u8; 8] {
     (-i64::from(rc.get())).to_le_bytes()
 }
 
+/// Returns an operand which replaces the value of an existing key when merged,
+/// leaving its reference count unchanged.  It is meant for repairing corrupted
+/// values only, as it breaks the rule that a key never changes its value.
+///
+/// The operand is the value with zero reference count, which is never written
+/// otherwise.  `data` must not be empty, as an empty value can't be told apart
+/// from a zero reference count.
+pub(crate) fn encode_payload_replacement(data: &[u8]) -> Vec<u8> {
+    debug_assert!(!data.is_empty());
+    [data, &0i64.to_le_bytes()].concat()
+}
+
+/// Returns the new value if the operand was encoded by
+/// [`encode_payload_replacement`].
+fn decode_payload_replacement(bytes: &[u8]) -> Option<&[u8]> {
+    match bytes.split_last_chunk::<8>() {
+        Some((head, tail)) if !head.is_empty() && i64::from_le_bytes(*tail) == 0 => Some(head),
+        _ => None,
+    }
+}
+
 /// Merge reference counted values together.
 ///
 /// Extracts reference count from all provided value and sums them together and
@@ -111,13 +136,19 @@ pub(crate) fn encode_negative_refcount(rc: std::num::NonZeroU32) -> [u8; 8] {
 ///
 /// Assumes that all provided values with positive reference count have the same
 /// value so that the function is free to pick any of the values.  In build with
-/// debug assertions panics if this is not true.
+/// debug assertions panics if this is not true.  Operands encoded by
+/// [`encode_payload_replacement`] replace the value merged so far instead.
 pub(crate) fn refcount_merge<'a>(
     existing: Option<&'a [u8]>,
     operands: impl IntoIterator<Item = &'a [u8]>,
 ) -> Vec<u8> {
     let (mut payload, mut rc) = existing.map_or((None, 0), decode_value_with_rc);
-    for (new_payload, delta) in operands.into_iter().map(decode_value_with_rc) {
+    for operand in operands {
+        if let Some(new_payload) = decode_payload_replacement(operand) {
+            payload = Some(new_payload);
+            continue;
+        }
+        let (new_payload, delta) = decode_value_with_rc(operand);
         if payload.is_none() {
             payload = new_payload;
         } else if new_payload.is_some() {
@@ -161,6 +192,20 @@ impl RocksDB {
         Some(self::refcount_merge(existing, operands))
     }
 
+    /// Partial merge of operands, without the existing value.  Operands
+    /// replacing the value are kept as they are, as merging them would lose
+    /// the replacement if the reference counts don't add up to a positive one.
+    pub(crate) fn refcount_partial_merge(
+        _new_key: &[u8],
+        _existing: Option<&[u8]>,
+        operands: &rocksdb::MergeOperands,
+    ) -> Option<Vec<u8>> {
+        if operands.into_iter().any(|operand| decode_payload_replacement(operand).is_some()) {
+            return None;
+        }
+        Some(self::refcount_merge(None, operands))
+    }
+
     /// Compaction filter for DBCol::State
     pub(crate) fn empty_value_compaction_filter(
         _level: u32,
@@ -281,6 +326,16 @@ mod test {
         test(b"foo\x02\0\0\0\0\0\0\0", &[b"foo\x01\0\0\0\0\0\0\0", b"foo\x01\0\0\0\0\0\0\0"]);
         test(b"foo\x01\0\0\0\0\0\0\0", &[b"foo\x01\0\0\0\0\0\0\0"]);
         test(b"foo\x01\0\0\0\0\0\0\0", &[b"foo\x02\0\0\0\0\0\0\0", MINUS_ONE]);
+
+        // Replacing the value keeps the reference count and doesn't bring back
+        // removed values.
+        test(b"bar\x02\0\0\0\0\0\0\0", &[b"foo\x02\0\0\0\0\0\0\0", b"bar\0\0\0\0\0\0\0\0"]);
+        test(
+            b"bar\x01\0\0\0\0\0\0\0",
+            &[b"foo\x02\0\0\0\0\0\0\0", b"bar\0\0\0\0\0\0\0\0", MINUS_ONE],
+        );
+        test(b"", &[b"bar\0\0\0\0\0\0\0\0"]);
+        test(b"", &[b"foo\x01\0\0\0\0\0\0\0", MINUS_ONE, b"bar\0\0\0\0\0\0\0\0"]);
     }
 
     #[test]
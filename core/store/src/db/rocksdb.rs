@@ -1,4 +1,5 @@
 use crate::config::Mode;
+use crate::config::{ColumnOptions, CompactionStyle, CompressionAlgorithm};
 use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StatsValue};
 use crate::{metadata, metrics, DBCol, StoreConfig, StoreStatistics, Temperature};
 use ::rocksdb::{
@@ -13,6 +14,7 @@ use std::sync::LazyLock;
 use strum::IntoEnumIterator;
 use tracing::warn;
 
+pub(crate) mod backup;
 mod instance_tracker;
 pub(crate) mod snapshot;
 
@@ -494,6 +496,11 @@ impl Database for RocksDB {
         }
         Ok(())
     }
+
+    fn create_backup(&self, backup_dir: &Path, max_backups: usize) -> anyhow::Result<u32> {
+        backup::create_backup(&self.db, backup_dir, max_backups)
+            .with_context(|| format!("failed to create backup in {}", backup_dir.display()))
+    }
 }
 
 fn cf_descriptors(
@@ -573,30 +580,31 @@ fn use_block_cache_for_index_and_filter_blocks(db_col: DBCol) -> bool {
     }
 }
 
-fn rocksdb_block_based_options(store_config: &StoreConfig, db_col: DBCol) -> BlockBasedOptions {
-    let cache_size = store_config.col_cache_size(db_col);
-
+fn rocksdb_block_based_options(col_options: &ColumnOptions, db_col: DBCol) -> BlockBasedOptions {
     let mut block_opts = BlockBasedOptions::default();
-    block_opts.set_block_size(store_config.block_size.as_u64().try_into().unwrap());
+    block_opts.set_block_size(col_options.block_size.as_u64().try_into().unwrap());
     // We create block_cache for each of the columns, so the total cache size is (num_of_columns - 2) * 32MiB
     // Plus the 128MiB from FlatState and 512MiB from State columns
-    block_opts.set_block_cache(&Cache::new_lru_cache(cache_size.as_u64().try_into().unwrap()));
+    let cache_size = col_options.block_cache_size.as_u64().try_into().unwrap();
+    block_opts.set_block_cache(&Cache::new_lru_cache(cache_size));
     if use_block_cache_for_index_and_filter_blocks(db_col) {
         block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
         block_opts.set_cache_index_and_filter_blocks(true);
     } else {
         block_opts.set_cache_index_and_filter_blocks(false);
     }
-    block_opts.set_bloom_filter(10.0, true);
+    if col_options.bloom_filter_bits_per_key > 0.0 {
+        block_opts.set_bloom_filter(col_options.bloom_filter_bits_per_key, true);
+    }
 
     block_opts
 }
 
 fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperature) -> Options {
+    let col_options = store_config.column_options(col);
     let mut opts = Options::default();
-    set_compression_options(&mut opts);
     opts.set_level_compaction_dynamic_level_bytes(true);
-    opts.set_block_based_table_factory(&rocksdb_block_based_options(store_config, col));
+    opts.set_block_based_table_factory(&rocksdb_block_based_options(&col_options, col));
 
     // Note that this function changes a lot of rustdb parameters including:
     //      write_buffer_size = memtable_memory_budget / 4
@@ -606,16 +614,27 @@ fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperat
     //      target_file_size_base = memtable_memory_budget / 8
     //      max_bytes_for_level_base = memtable_memory_budget
     //      compaction_style = kCompactionStyleLevel
-    // Also it sets compression_per_level in a way that the first 2 levels have no compression and
-    // the rest use LZ4 compression.
+    // Also it sets compression_per_level, which is overwritten below by
+    // set_column_compression_options.
     // See the implementation here:
     //      https://github.com/facebook/rocksdb/blob/c18c4a081c74251798ad2a1abf83bad417518481/options/options.cc#L588.
     let memtable_memory_budget = 128 * bytesize::MIB as usize;
     opts.optimize_level_style_compaction(memtable_memory_budget);
+    set_column_compression_options(&mut opts, &col_options);
+    match col_options.compaction_style {
+        CompactionStyle::Level => {}
+        CompactionStyle::Universal => {
+            opts.set_compaction_style(rocksdb::DBCompactionStyle::Universal)
+        }
+    }
 
     opts.set_target_file_size_base(64 * bytesize::MIB);
     if temp == Temperature::Hot && col.is_rc() {
-        opts.set_merge_operator("refcount merge", RocksDB::refcount_merge, RocksDB::refcount_merge);
+        opts.set_merge_operator(
+            "refcount merge",
+            RocksDB::refcount_merge,
+            RocksDB::refcount_partial_merge,
+        );
         opts.set_compaction_filter("empty value filter", RocksDB::empty_value_compaction_filter);
     }
     opts
@@ -642,6 +661,40 @@ fn set_compression_options(opts: &mut Options) {
     opts.set_bottommost_zstd_max_train_bytes(max_train_bytes, true);
 }
 
+fn compression_type(compression: CompressionAlgorithm) -> rocksdb::DBCompressionType {
+    match compression {
+        CompressionAlgorithm::None => rocksdb::DBCompressionType::None,
+        CompressionAlgorithm::Snappy => rocksdb::DBCompressionType::Snappy,
+        CompressionAlgorithm::Lz4 => rocksdb::DBCompressionType::Lz4,
+        CompressionAlgorithm::Zstd => rocksdb::DBCompressionType::Zstd,
+    }
+}
+
+/// Sets compression options of a column family.  With the default column
+/// options this is equivalent to [`set_compression_options`] followed by
+/// `optimize_level_style_compaction`.
+fn set_column_compression_options(opts: &mut Options, col_options: &ColumnOptions) {
+    let per_level = col_options.compression_per_level.iter().copied().map(compression_type);
+    let per_level = per_level.collect_vec();
+    opts.set_compression_per_level(&per_level);
+    if let Some(&last) = per_level.last() {
+        opts.set_compression_type(last);
+    }
+    opts.set_bottommost_compression_type(compression_type(col_options.bottommost_compression));
+
+    // 32767 means the default compression level of the algorithm.  Window
+    // bits and strategy are Zlib-specific and unused.  Having train data size
+    // x100 from dictionary size is a recommendation from RocksDB.
+    let level = col_options.compression_level.unwrap_or(32767);
+    let dict_size = col_options.zstd_dictionary_size.as_u64().try_into().unwrap_or(i32::MAX);
+    opts.set_compression_options(-14, level, 0, dict_size);
+    opts.set_zstd_max_train_bytes(dict_size.saturating_mul(100));
+    let dict_size =
+        col_options.bottommost_zstd_dictionary_size.as_u64().try_into().unwrap_or(i32::MAX);
+    opts.set_bottommost_compression_options(-14, level, 0, dict_size, true);
+    opts.set_bottommost_zstd_max_train_bytes(dict_size.saturating_mul(100), true);
+}
+
 impl RocksDB {
     /// Blocks until all RocksDB instances (usually 0 or 1) gracefully shutdown.
     pub fn block_until_all_instances_are_dropped() {
@@ -0,0 +1,56 @@
+//! Incremental backups of RocksDB databases built on RocksDB’s BackupEngine.
+//!
+//! Backups in the same directory share SST files so each new backup copies
+//! only the files created since the previous one.  Backups may be created
+//! while the database is open and being written to.
+
+use std::io;
+use std::path::Path;
+
+use ::rocksdb::backup::{BackupEngine, BackupEngineInfo, BackupEngineOptions, RestoreOptions};
+use ::rocksdb::{Env, DB};
+
+fn open_engine(backup_dir: &Path) -> io::Result<BackupEngine> {
+    let opts = BackupEngineOptions::new(backup_dir).map_err(io::Error::other)?;
+    let env = Env::new().map_err(io::Error::other)?;
+    BackupEngine::open(&opts, &env).map_err(io::Error::other)
+}
+
+/// Creates a new backup of the database and deletes all but `max_backups`
+/// most recent backups.  Returns id of the new backup.
+pub(crate) fn create_backup(db: &DB, backup_dir: &Path, max_backups: usize) -> io::Result<u32> {
+    std::fs::create_dir_all(backup_dir)?;
+    let mut engine = open_engine(backup_dir)?;
+    // Flushing memtables before the backup means WAL files don’t need to be
+    // copied, which keeps the backups smaller.
+    engine.create_new_backup_flush(db, true).map_err(io::Error::other)?;
+    engine.purge_old_backups(max_backups.max(1)).map_err(io::Error::other)?;
+    let info = engine.get_backup_info();
+    let backup_id = info.iter().map(|info| info.backup_id).max();
+    backup_id.ok_or_else(|| io::Error::other("backup missing right after creating it"))
+}
+
+/// Returns information about all backups in the directory.
+pub(crate) fn get_backup_info(backup_dir: &Path) -> io::Result<Vec<BackupEngineInfo>> {
+    if !backup_dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("backup directory {} does not exist", backup_dir.display()),
+        ));
+    }
+    Ok(open_engine(backup_dir)?.get_backup_info())
+}
+
+/// Checks that all files of the backup exist and have the expected sizes and
+/// checksums.
+pub(crate) fn verify_backup(backup_dir: &Path, backup_id: u32) -> io::Result<()> {
+    open_engine(backup_dir)?.verify_backup(backup_id).map_err(io::Error::other)
+}
+
+/// Restores the backup into the given directory, which must not contain
+/// a database.
+pub(crate) fn restore_backup(backup_dir: &Path, backup_id: u32, db_path: &Path) -> io::Result<()> {
+    let mut engine = open_engine(backup_dir)?;
+    let opts = RestoreOptions::default();
+    engine.restore_from_backup(db_path, db_path, &opts, backup_id).map_err(io::Error::other)
+}
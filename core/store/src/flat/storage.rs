@@ -481,6 +481,27 @@ impl FlatStorage {
         Ok(())
     }
 
+    /// Overwrites the value of the key in the flat state, provided that the
+    /// flat head is still `flat_head`.  The flat storage is locked while the
+    /// value is written, so the flat head can't move in the meantime.
+    ///
+    /// Meant for repairing values which don't match the trie at the flat head,
+    /// see the data scrubber.
+    pub fn repair_value(
+        &self,
+        flat_head: &CryptoHash,
+        key: Vec<u8>,
+        value: Option<FlatStateValue>,
+    ) -> Result<(), FlatStorageError> {
+        let guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        if guard.flat_head.hash != *flat_head {
+            return Err(FlatStorageError::BlockNotSupported((guard.flat_head.hash, *flat_head)));
+        }
+        let mut store_update = guard.store.store_update();
+        store_update.set(guard.shard_uid, key, value);
+        store_update.commit().map_err(|err| FlatStorageError::StorageInternalError(err.to_string()))
+    }
+
     pub(crate) fn get_head_hash(&self) -> CryptoHash {
         let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
         guard.flat_head.hash
@@ -1024,6 +1045,48 @@ mod tests {
         tracing::info!(?max_lag);
     }
 
+    #[test]
+    fn flat_storage_repair_value() {
+        let chain = MockChain::linear_chain(3);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store().flat_store();
+        let mut store_update = store.store_update();
+        store_update.set_flat_storage_status(
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_update.set(shard_uid, vec![1], Some(FlatStateValue::inlined(&[0])));
+        let delta = FlatStateDelta {
+            changes: FlatStateChanges::default(),
+            metadata: FlatStateDeltaMetadata {
+                block: chain.get_block(1),
+                prev_block_with_changes: None,
+            },
+        };
+        store_update.set_delta(shard_uid, &delta);
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+
+        let value = Some(FlatStateValue::inlined(&[1]));
+        let head_hash = chain.get_block_hash(0);
+        flat_storage.repair_value(&head_hash, vec![1], value.clone()).unwrap();
+        assert_eq!(store.get(shard_uid, &[1]).unwrap(), value);
+        flat_storage.repair_value(&head_hash, vec![1], None).unwrap();
+        assert_eq!(store.get(shard_uid, &[1]).unwrap(), None);
+
+        // The value can't be repaired once the flat head moved.
+        let new_head_hash = chain.get_block_hash(1);
+        flat_storage.update_flat_head_impl(&new_head_hash, true).unwrap();
+        assert_eq!(
+            flat_storage.repair_value(&head_hash, vec![1], value),
+            Err(FlatStorageError::BlockNotSupported((new_head_hash, head_hash)))
+        );
+        assert_eq!(store.get(shard_uid, &[1]).unwrap(), None);
+    }
+
     #[test]
     fn test_new_flat_head() {
         init_test_logger();
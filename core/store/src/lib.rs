@@ -20,7 +20,7 @@ use db::{SplitDB, GENESIS_CONGESTION_INFO_KEY};
 pub use db::{
     CHUNK_TAIL_KEY, COLD_HEAD_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, GENESIS_JSON_HASH_KEY,
     GENESIS_STATE_ROOTS_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
-    LATEST_KNOWN_KEY, STATE_SNAPSHOT_KEY, STATE_SYNC_DUMP_KEY, TAIL_KEY,
+    LATEST_KNOWN_KEY, SANDBOX_PINNED_HEIGHT_KEY, STATE_SNAPSHOT_KEY, STATE_SYNC_DUMP_KEY, TAIL_KEY,
 };
 use metadata::{DbKind, DbVersion, KIND_KEY, VERSION_KEY};
 use near_crypto::PublicKey;
@@ -48,6 +48,7 @@ use strum;
 
 pub mod adapter;
 pub mod archive;
+pub mod backup;
 mod columns;
 pub mod config;
 pub mod contract;
@@ -564,6 +565,22 @@ impl StoreUpdate {
         self.decrement_refcount_by(column, key, Self::ONE)
     }
 
+    /// Replaces the data of an existing reference-counted value, keeping its
+    /// reference count.  Does nothing if the value isn't there, including if
+    /// it is removed concurrently.
+    ///
+    /// This breaks the rule that the data under a key never changes and is
+    /// only meant for repairing corrupted values, see the data scrubber.
+    /// `data` must not be empty.
+    ///
+    /// Panics if this is used for columns which are not reference-counted
+    /// (see [`DBCol::is_rc`]).
+    pub fn replace_refcounted_value(&mut self, column: DBCol, key: &[u8], data: &[u8]) {
+        assert!(column.is_rc(), "can't update refcount: {column}");
+        let value = refcount::encode_payload_replacement(data);
+        self.transaction.update_refcount(column, key.to_vec(), value)
+    }
+
     /// Modifies a value in the database.
     ///
     /// Unlike `insert`, `increment_refcount` or `decrement_refcount`, arbitrary
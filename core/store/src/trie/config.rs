@@ -2,6 +2,7 @@ use crate::config::{PrefetchConfig, TrieCacheConfig};
 use crate::StoreConfig;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::AccountId;
+use std::path::PathBuf;
 use std::str::FromStr;
 use tracing::error;
 
@@ -37,6 +38,12 @@ pub struct TrieConfig {
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     /// Whether mem-trie should be loaded for each tracked shard.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// Directory of mem-trie snapshots, if they are enabled.  It isn't set by
+    /// `from_store_config` as the path depends on the database location.
+    pub mem_trie_snapshot_dir: Option<PathBuf>,
+    /// Whether the checksums of the chunks of memtrie snapshots are verified
+    /// on load.
+    pub mem_trie_snapshot_verify_checksums: bool,
 }
 
 impl TrieConfig {
@@ -48,6 +55,7 @@ impl TrieConfig {
         this.view_shard_cache_config = config.view_trie_cache.clone();
 
         this.enable_receipt_prefetching = config.enable_receipt_prefetching;
+        this.mem_trie_snapshot_verify_checksums = config.mem_trie_snapshot.verify_checksums;
         for account in &config.sweat_prefetch_receivers {
             match AccountId::from_str(account) {
                 Ok(account_id) => this.sweat_prefetch_receivers.push(account_id),
@@ -6,6 +6,7 @@ use crate::trie::mem::arena::metrics::{
 };
 use crate::trie::mem::arena::ArenaMemoryMut;
 use crate::trie::mem::flexible_data::encoding::BorshFixedSize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_o11y::metrics::IntGauge;
 
 /// Simple bump allocator with freelists.
@@ -44,6 +45,16 @@ pub struct Allocator {
     memory_usage_gauge: IntGauge,
 }
 
+/// State of the `Allocator` saved in memtrie snapshots, so that the memory
+/// freed before the snapshot was taken can be reused after it is loaded.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AllocatorState {
+    freelists: Vec<ArenaPos>,
+    next_alloc_pos: ArenaPos,
+    active_allocs_bytes: u64,
+    active_allocs_count: u64,
+}
+
 const MAX_ALLOC_SIZE: usize = 16 * 1024;
 const ROUND_UP_TO_8_BYTES_UNDER: usize = 256;
 const ROUND_UP_TO_64_BYTES_UNDER: usize = 1024;
@@ -103,6 +114,35 @@ impl Allocator {
         allocator
     }
 
+    /// Restores an allocator from the state saved in a snapshot of an arena
+    /// memory with the given number of chunks.
+    pub fn from_state(name: String, state: AllocatorState, num_chunks: usize) -> Option<Self> {
+        let is_valid_pos = |pos: &ArenaPos| pos.is_invalid() || pos.chunk() < num_chunks;
+        if state.freelists.len() != NUM_ALLOCATION_CLASSES
+            || !state.freelists.iter().all(is_valid_pos)
+            || !is_valid_pos(&state.next_alloc_pos)
+        {
+            return None;
+        }
+        let mut allocator = Self::new_with_initial_stats(
+            name,
+            state.active_allocs_bytes as usize,
+            state.active_allocs_count as usize,
+        );
+        allocator.freelists = state.freelists.try_into().unwrap();
+        allocator.next_alloc_pos = state.next_alloc_pos;
+        Some(allocator)
+    }
+
+    pub fn state(&self) -> AllocatorState {
+        AllocatorState {
+            freelists: self.freelists.to_vec(),
+            next_alloc_pos: self.next_alloc_pos,
+            active_allocs_bytes: self.active_allocs_bytes as u64,
+            active_allocs_count: self.active_allocs_count as u64,
+        }
+    }
+
     pub fn update_memory_usage_gauge(&self, memory: &STArenaMemory) {
         self.memory_usage_gauge.set(memory.chunks.len() as i64 * CHUNK_SIZE as i64);
     }
@@ -110,7 +150,7 @@ impl Allocator {
     /// Adds a new chunk to the arena, and updates the next_alloc_pos to the beginning of
     /// the new chunk.
     fn new_chunk(&mut self, memory: &mut STArenaMemory) {
-        memory.chunks.push(vec![0; CHUNK_SIZE]);
+        memory.chunks.push(vec![0; CHUNK_SIZE].into());
         self.next_alloc_pos =
             ArenaPos { chunk: u32::try_from(memory.chunks.len() - 1).unwrap(), pos: 0 };
         self.update_memory_usage_gauge(memory);
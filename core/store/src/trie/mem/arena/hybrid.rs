@@ -1,9 +1,9 @@
 use std::convert::From;
 use std::sync::Arc;
 
-use super::alloc::Allocator;
+use super::alloc::{Allocator, AllocatorState};
 use super::frozen::{FrozenArena, FrozenArenaMemory};
-use super::single_thread::{STArena, STArenaMemory};
+use super::single_thread::{ArenaChunk, STArena, STArenaMemory};
 use super::{
     Arena, ArenaMemory, ArenaMemoryMut, ArenaMut, ArenaPos, ArenaSliceMut, ArenaWithDealloc,
 };
@@ -122,6 +122,31 @@ impl HybridArena {
         }
     }
 
+    /// Restores an arena saved in a memtrie snapshot from its chunks and the
+    /// state of its allocator. Returns `None` if the state doesn't match the
+    /// chunks.
+    pub(crate) fn from_snapshot(
+        name: String,
+        chunks: Vec<ArenaChunk>,
+        allocator_state: AllocatorState,
+    ) -> Option<Self> {
+        let allocator = Allocator::from_state(name, allocator_state, chunks.len())?;
+        let memory = STArenaMemory { chunks };
+        allocator.update_memory_usage_gauge(&memory);
+        Some(STArena { memory, allocator }.into())
+    }
+
+    /// Returns the chunks and the state of the allocator to save in a memtrie
+    /// snapshot. Arenas with shared memory are not saved, as the shared memory
+    /// is owned by the arena of the parent shard.
+    pub(crate) fn snapshot_parts(&self) -> Option<(Vec<&[u8]>, AllocatorState)> {
+        if self.has_shared_memory() {
+            return None;
+        }
+        let chunks = self.memory.owned_memory.chunks.iter().map(|chunk| &chunk[..]).collect();
+        Some((chunks, self.allocator.state()))
+    }
+
     #[inline]
     pub fn has_shared_memory(&self) -> bool {
         self.memory.chunks_offset() > 0
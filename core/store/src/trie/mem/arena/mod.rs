@@ -14,6 +14,7 @@ mod frozen;
 pub mod hybrid;
 mod metrics;
 pub mod single_thread;
+pub use alloc::AllocatorState;
 pub use frozen::FrozenArena;
 
 /// An abstraction of a read-only arena.
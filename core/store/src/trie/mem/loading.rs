@@ -1,6 +1,7 @@
 use super::arena::single_thread::STArena;
 use super::mem_tries::MemTries;
 use super::node::MemTrieNodeId;
+use super::snapshot::load_mem_trie_snapshot;
 use crate::adapter::StoreAdapter;
 use crate::flat::FlatStorageStatus;
 use crate::trie::mem::arena::Arena;
@@ -15,8 +16,9 @@ use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, StateRoot};
 use std::collections::BTreeSet;
+use std::path::Path;
 use std::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Loads a trie from the FlatState column. The returned `MemTries` contains
 /// exactly one trie root.
@@ -141,6 +143,21 @@ pub fn load_trie_from_flat_state_and_delta(
         load_trie_from_flat_state(&store, shard_uid, state_root, flat_head.height, parallelize)
             .unwrap();
 
+    apply_flat_state_deltas(store, shard_uid, &mut mem_tries)?;
+    debug!(target: "memtrie", %shard_uid, "Done loading memtries for shard");
+    Ok(mem_tries)
+}
+
+/// Applies the flat state deltas of the shard to the memtries, so that they
+/// contain a root for each block that the flat storage has a delta for.
+/// Deltas of blocks whose state root is already present at their height, i.e.
+/// which were applied before the memtries were saved in a snapshot, are skipped.
+fn apply_flat_state_deltas(
+    store: &Store,
+    shard_uid: ShardUId,
+    mem_tries: &mut MemTries,
+) -> Result<(), StorageError> {
+    let flat_store = store.flat_store();
     debug!(target: "memtrie", %shard_uid, "Loading flat state deltas...");
     // We load the deltas in order of height, so that we always have the previous state root
     // already loaded.
@@ -155,6 +172,9 @@ pub fn load_trie_from_flat_state_and_delta(
         if let Some(changes) = delta {
             let old_state_root = get_state_root(store, prev_hash, shard_uid)?;
             let new_state_root = get_state_root(store, hash, shard_uid)?;
+            if mem_tries.has_root_at_height(&new_state_root, height) {
+                continue;
+            }
 
             let mut trie_update = mem_tries.update(old_state_root, TrackingMode::None)?;
             for (key, value) in changes.0 {
@@ -172,14 +192,87 @@ pub fn load_trie_from_flat_state_and_delta(
         }
         debug!(target: "memtrie", %shard_uid, "Applied memtrie changes for height {}", height);
     }
+    Ok(())
+}
+
+/// Loads in-memory tries for the given shard from the memtrie snapshot in
+/// `snapshot_dir` and applies the flat state deltas on top of it, so that the
+/// result is the same as of `load_trie_from_flat_state_and_delta`.
+///
+/// Checksums of the chunks of the snapshot are verified only if
+/// `verify_checksums` is set, see `load_mem_trie_snapshot`.
+///
+/// Returns `None` if there is no snapshot, or if it fails validation or is
+/// stale, i.e. doesn't contain the state root of the current flat head, in
+/// which case the tries need to be loaded from flat state.
+pub fn load_trie_from_snapshot_and_delta(
+    store: &Store,
+    shard_uid: ShardUId,
+    snapshot_dir: &Path,
+    verify_checksums: bool,
+) -> Result<Option<MemTries>, StorageError> {
+    let flat_head = match store.flat_store().get_flat_storage_status(shard_uid)? {
+        FlatStorageStatus::Ready(status) => status.flat_head,
+        _ => return Ok(None),
+    };
+    let load_start = Instant::now();
+    let snapshot = match load_mem_trie_snapshot(snapshot_dir, shard_uid, verify_checksums) {
+        Ok(snapshot) => snapshot,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!(target: "memtrie", %shard_uid, "No memtrie snapshot found");
+            return Ok(None);
+        }
+        Err(err) => {
+            warn!(target: "memtrie", %shard_uid, ?err, "Invalid memtrie snapshot");
+            return Ok(None);
+        }
+    };
+    if snapshot.flat_head.height > flat_head.height {
+        warn!(
+            target: "memtrie",
+            %shard_uid,
+            snapshot_flat_head = ?snapshot.flat_head,
+            ?flat_head,
+            "Memtrie snapshot is newer than flat storage"
+        );
+        return Ok(None);
+    }
+    let mut mem_tries = snapshot.mem_tries;
+    let state_root = get_state_root(store, flat_head.hash, shard_uid)?;
+    if state_root != CryptoHash::default() {
+        let Ok(root) = mem_tries.get_root(&state_root) else {
+            info!(
+                target: "memtrie",
+                %shard_uid,
+                snapshot_flat_head = ?snapshot.flat_head,
+                ?flat_head,
+                "Memtrie snapshot is stale"
+            );
+            return Ok(None);
+        };
+        // The root may have been saved at a lower height if the state didn't
+        // change since, so it's referenced at the flat head height before the
+        // roots below the flat head are deleted.
+        let root_id = root.id();
+        mem_tries.insert_root(state_root, root_id, flat_head.height);
+    }
+    mem_tries.delete_until_height(flat_head.height);
+    info!(
+        target: "memtrie",
+        %shard_uid,
+        snapshot_flat_head = ?snapshot.flat_head,
+        "Loaded memtrie snapshot, took {:?}",
+        load_start.elapsed()
+    );
 
+    apply_flat_state_deltas(store, shard_uid, &mut mem_tries)?;
     debug!(target: "memtrie", %shard_uid, "Done loading memtries for shard");
-    Ok(mem_tries)
+    Ok(Some(mem_tries))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::load_trie_from_flat_state_and_delta;
+    use super::{load_trie_from_flat_state_and_delta, load_trie_from_snapshot_and_delta};
     use crate::adapter::{StoreAdapter, StoreUpdateAdapter};
     use crate::flat::test_utils::MockChain;
     use crate::flat::{BlockInfo, FlatStorageReadyStatus, FlatStorageStatus};
@@ -189,7 +282,9 @@ mod tests {
     };
     use crate::trie::mem::loading::load_trie_from_flat_state;
     use crate::trie::mem::lookup::memtrie_lookup;
+    use crate::trie::mem::mem_tries::MemTries;
     use crate::trie::mem::nibbles_utils::{all_two_nibble_nibbles, multi_hex_to_nibbles};
+    use crate::trie::mem::snapshot::{snapshot_path, write_mem_trie_snapshot};
     use crate::trie::update::TrieUpdateResult;
     use crate::{DBCol, KeyLookupMode, NibbleSlice, ShardTries, Store, Trie, TrieUpdate};
     use near_primitives::bandwidth_scheduler::BandwidthRequests;
@@ -475,6 +570,116 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_memtrie_load_from_snapshot() {
+        let test_key = TrieKey::ContractData {
+            account_id: "test_account".parse().unwrap(),
+            key: b"test_key".to_vec(),
+        };
+        let test_val = |height: u64| format!("test_val{}", height).into_bytes();
+        let lookup = |mem_tries: &MemTries, state_root: &StateRoot| {
+            memtrie_lookup(mem_tries.get_root(state_root).unwrap(), &test_key.to_vec(), None)
+                .map(|v| v.to_flat_value())
+        };
+
+        let chain = MockChain::linear_chain(5);
+        let store = create_test_store();
+        let shard_tries = TestTriesBuilder::new().with_store(store.clone()).build();
+        let shard_uid = ShardUId { version: 1, shard_id: 1 };
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let save_snapshot = |mem_tries: &MemTries, flat_head: BlockInfo| {
+            write_mem_trie_snapshot(snapshot_dir.path(), shard_uid, mem_tries, flat_head)
+                .unwrap()
+                .commit()
+                .unwrap();
+        };
+
+        let mut store_update = shard_tries.store().flat_store().store_update();
+        store_update.set_flat_storage_status(
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_update.set(shard_uid, test_key.to_vec(), Some(FlatStateValue::inlined(&test_val(0))));
+        store_update.commit().unwrap();
+        let mut state_roots = vec![test_populate_trie(
+            &shard_tries,
+            &Trie::EMPTY_ROOT,
+            shard_uid,
+            vec![(test_key.to_vec(), Some(test_val(0)))],
+        )];
+        write_chunk_extra(&store, chain.get_block(0).hash, shard_uid, state_roots[0]);
+        let apply_block = |state_roots: &mut Vec<StateRoot>, height: u64| {
+            let state_root = apply_trie_changes(
+                &shard_tries,
+                shard_uid,
+                *state_roots.last().unwrap(),
+                chain.get_block(height),
+                vec![(test_key.clone(), test_val(height))],
+            );
+            write_chunk_extra(&store, chain.get_block(height).hash, shard_uid, state_root);
+            state_roots.push(state_root);
+        };
+
+        // Without a snapshot the tries have to be loaded from flat state.
+        assert!(load_trie_from_snapshot_and_delta(&store, shard_uid, snapshot_dir.path(), true)
+            .unwrap()
+            .is_none());
+
+        // The snapshot has the roots of blocks 0 to 2, and block 3 is applied
+        // after the snapshot was taken.  Loading skips the deltas of the blocks
+        // in the snapshot and replays the rest.
+        apply_block(&mut state_roots, 1);
+        apply_block(&mut state_roots, 2);
+        let mem_tries =
+            load_trie_from_flat_state_and_delta(&store, shard_uid, None, false).unwrap();
+        save_snapshot(&mem_tries, chain.get_block(0));
+        apply_block(&mut state_roots, 3);
+        let mem_tries =
+            load_trie_from_snapshot_and_delta(&store, shard_uid, snapshot_dir.path(), true)
+                .unwrap()
+                .unwrap();
+        for (height, state_root) in state_roots.iter().enumerate() {
+            let expected = FlatStateValue::inlined(&test_val(height as u64));
+            assert_eq!(lookup(&mem_tries, state_root), Some(expected));
+        }
+
+        // A corrupted snapshot is not used.
+        let path = snapshot_path(snapshot_dir.path(), shard_uid);
+        let data = std::fs::read(&path).unwrap();
+        let mut corrupted = data.clone();
+        // The first chunk of the arena starts at the first page after the header.
+        corrupted[4096 + 100] ^= 1;
+        let tmp_path = path.with_extension("corrupted");
+        std::fs::write(&tmp_path, &corrupted).unwrap();
+        std::fs::rename(&tmp_path, &path).unwrap();
+        assert!(load_trie_from_snapshot_and_delta(&store, shard_uid, snapshot_dir.path(), true)
+            .unwrap()
+            .is_none());
+        std::fs::write(&tmp_path, &data).unwrap();
+        std::fs::rename(&tmp_path, &path).unwrap();
+
+        // A snapshot taken at a flat head above the current one is not used.
+        save_snapshot(&mem_tries, chain.get_block(4));
+        assert!(load_trie_from_snapshot_and_delta(&store, shard_uid, snapshot_dir.path(), true)
+            .unwrap()
+            .is_none());
+
+        // A stale snapshot, which doesn't have the root of the flat head, is
+        // not used.
+        let stale_mem_tries =
+            load_trie_from_flat_state(&store, shard_uid, state_roots[0], 0, false).unwrap();
+        save_snapshot(&stale_mem_tries, chain.get_block(0));
+        let mut store_update = shard_tries.store().flat_store().store_update();
+        store_update.set_flat_storage_status(
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(2) }),
+        );
+        store_update.commit().unwrap();
+        assert!(load_trie_from_snapshot_and_delta(&store, shard_uid, snapshot_dir.path(), true)
+            .unwrap()
+            .is_none());
+    }
+
     /// Makes the given changes to both the trie and flat storage.
     fn apply_trie_changes(
         tries: &ShardTries,
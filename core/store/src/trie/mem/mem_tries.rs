@@ -90,6 +90,30 @@ impl MemTries {
         tries
     }
 
+    /// Creates a new `MemTries` from the arena, roots and heights loaded from a
+    /// memtrie snapshot.
+    pub(super) fn from_snapshot_parts(
+        shard_uid: ShardUId,
+        arena: HybridArena,
+        roots: HashMap<StateRoot, Vec<MemTrieNodeId>>,
+        heights: BTreeMap<BlockHeight, Vec<StateRoot>>,
+    ) -> Self {
+        MEM_TRIE_NUM_ROOTS.with_label_values(&[&shard_uid.to_string()]).set(roots.len() as i64);
+        Self { arena, roots, heights, shard_uid }
+    }
+
+    /// Returns whether the state root is present at the given height.
+    pub(super) fn has_root_at_height(&self, state_root: &StateRoot, height: BlockHeight) -> bool {
+        self.heights.get(&height).is_some_and(|roots| roots.contains(state_root))
+    }
+
+    /// Returns the roots and heights to save in a memtrie snapshot.
+    pub(super) fn roots_and_heights(
+        &self,
+    ) -> (&HashMap<StateRoot, Vec<MemTrieNodeId>>, &BTreeMap<BlockHeight, Vec<StateRoot>>) {
+        (&self.roots, &self.heights)
+    }
+
     /// This function should perform the entire construction of the new trie, possibly based on some existing
     /// trie nodes. This internally takes care of refcounting and inserts a new root into the memtrie.
     pub fn apply_memtrie_changes(
@@ -106,7 +130,7 @@ impl MemTries {
         }
     }
 
-    fn insert_root(
+    pub(super) fn insert_root(
         &mut self,
         state_root: StateRoot,
         mem_root: MemTrieNodeId,
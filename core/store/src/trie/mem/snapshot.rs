@@ -0,0 +1,399 @@
+//! Snapshots of in-memory tries saved on disk, so that a restarting node
+//! doesn't need to rebuild its memtries from flat storage.
+//!
+//! The snapshot of a shard is a single file with the chunks of the arena of
+//! the shard's `MemTries`, the state of its allocator and its roots, tagged
+//! with the flat storage head at the time the snapshot was taken.  Chunks are
+//! aligned to pages, so on load they are mapped from the file copy-on-write
+//! instead of being copied into the memory of the node.  The header holds a
+//! checksum of each chunk.  Verifying them on load reads the whole file, so
+//! it can be disabled.  Without it, the only check of the chunks is that the
+//! hashes stored in the root nodes match the state roots; nothing below the
+//! roots is rehashed, so a corrupted node would only be noticed when used.
+//!
+//! Snapshots are only saved on graceful shutdown, once the node has stopped
+//! applying chunks, see `ShardTries::save_mem_trie_snapshots`.  The chunks are
+//! then written straight from the arena while the memtries are locked, without
+//! copying them in memory.  The checksums are computed from the written file
+//! and the file is synced to disk after the lock is released, see
+//! `PendingMemTrieSnapshot::commit`.
+//!
+//! Snapshots are written to a temporary file which is renamed over the
+//! previous snapshot once complete, so a crash while saving leaves the
+//! previous snapshot intact.  Snapshot files must never be modified in place,
+//! as they may be mapped by a running node.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{BlockHeight, StateRoot};
+
+use super::arena::hybrid::HybridArena;
+use super::arena::single_thread::ArenaChunk;
+use super::arena::{AllocatorState, Arena, ArenaPos};
+use super::mem_tries::MemTries;
+use super::node::MemTrieNodeId;
+use crate::flat::BlockInfo;
+
+const MAGIC: &[u8; 8] = b"MEMTRIE\0";
+const FORMAT_VERSION: u32 = 1;
+/// Length of the magic, the format version, the length of the header and its
+/// hash, which precede the header.
+const PREFIX_LEN: usize = 8 + 4 + 8 + 32;
+/// Alignment of the chunks in the snapshot file.
+const PAGE_SIZE: u64 = 4096;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct SnapshotHeader {
+    shard_uid: ShardUId,
+    flat_head: BlockInfo,
+    roots: Vec<(StateRoot, Vec<ArenaPos>)>,
+    heights: Vec<(BlockHeight, Vec<StateRoot>)>,
+    allocator_state: AllocatorState,
+    chunk_lens: Vec<u64>,
+    chunk_hashes: Vec<CryptoHash>,
+}
+
+impl SnapshotHeader {
+    /// Offsets of the chunks in the snapshot file, given the length of the
+    /// serialized header.  The header has the same length whatever the values
+    /// of the checksums, so the chunks can be written before them.
+    fn chunk_offsets(&self, header_len: u64) -> impl Iterator<Item = u64> + '_ {
+        let mut offset = PREFIX_LEN as u64 + header_len;
+        self.chunk_lens.iter().map(move |&len| {
+            let chunk_offset = offset.next_multiple_of(PAGE_SIZE);
+            offset = chunk_offset + len;
+            chunk_offset
+        })
+    }
+
+    fn file_len(&self, header_len: u64) -> u64 {
+        let end = match (self.chunk_offsets(header_len).last(), self.chunk_lens.last()) {
+            (Some(offset), Some(len)) => offset + len,
+            _ => PREFIX_LEN as u64 + header_len,
+        };
+        end.next_multiple_of(PAGE_SIZE)
+    }
+}
+
+/// Snapshot of the memtries of a shard written to a temporary file by
+/// `write_mem_trie_snapshot`, which doesn't have checksums yet and doesn't
+/// replace the previous snapshot until committed.
+pub struct PendingMemTrieSnapshot {
+    dir: PathBuf,
+    file: File,
+    header: SnapshotHeader,
+    header_len: u64,
+}
+
+/// Memtries loaded from a snapshot.
+pub struct MemTrieSnapshot {
+    pub mem_tries: MemTries,
+    /// Flat storage head of the shard at the time the snapshot was taken.
+    pub flat_head: BlockInfo,
+}
+
+pub fn snapshot_path(dir: &Path, shard_uid: ShardUId) -> PathBuf {
+    dir.join(format!("{}.memtrie", shard_uid))
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Writes the chunks of the arena of the memtries of the shard whose flat
+/// storage has the given head to a temporary snapshot file.  The memtries
+/// must stay locked until this returns, but not until the snapshot is
+/// committed.
+///
+/// Returns an error of kind `Unsupported` if the memtries share memory with
+/// the memtries of another shard, after resharding, and can't be saved.
+pub fn write_mem_trie_snapshot(
+    dir: &Path,
+    shard_uid: ShardUId,
+    mem_tries: &MemTries,
+    flat_head: BlockInfo,
+) -> io::Result<PendingMemTrieSnapshot> {
+    let Some((chunks, allocator_state)) = mem_tries.arena.snapshot_parts() else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memtries share memory with another shard",
+        ));
+    };
+    let (roots, heights) = mem_tries.roots_and_heights();
+    let header = SnapshotHeader {
+        shard_uid,
+        flat_head,
+        roots: roots
+            .iter()
+            .map(|(state_root, ids)| (*state_root, ids.iter().map(|id| id.pos).collect()))
+            .collect(),
+        heights: heights.iter().map(|(height, roots)| (*height, roots.clone())).collect(),
+        allocator_state,
+        chunk_lens: chunks.iter().map(|chunk| chunk.len() as u64).collect(),
+        chunk_hashes: vec![CryptoHash::default(); chunks.len()],
+    };
+    let header_len = borsh::object_length(&header)? as u64;
+
+    std::fs::create_dir_all(dir)?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(snapshot_path(dir, shard_uid).with_extension("memtrie.tmp"))?;
+    for (chunk, offset) in chunks.iter().zip(header.chunk_offsets(header_len)) {
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(chunk)?;
+    }
+    file.set_len(header.file_len(header_len))?;
+    Ok(PendingMemTrieSnapshot { dir: dir.to_path_buf(), file, header, header_len })
+}
+
+impl PendingMemTrieSnapshot {
+    /// Computes the checksums of the written chunks, writes the header and
+    /// replaces the previous snapshot of the shard.
+    pub fn commit(self) -> io::Result<()> {
+        let Self { dir, mut file, mut header, header_len } = self;
+        let offsets: Vec<_> = header.chunk_offsets(header_len).collect();
+        for ((&offset, &len), chunk_hash) in
+            offsets.iter().zip(&header.chunk_lens).zip(&mut header.chunk_hashes)
+        {
+            // SAFETY: The temporary file is only written by this snapshot.
+            let chunk =
+                unsafe { memmap2::MmapOptions::new().offset(offset).len(len as usize).map(&file)? };
+            *chunk_hash = hash(&chunk);
+        }
+        let header_bytes = borsh::to_vec(&header)?;
+        assert_eq!(header_bytes.len() as u64, header_len, "checksums changed the header length");
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(MAGIC)?;
+        file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        file.write_all(&header_len.to_le_bytes())?;
+        file.write_all(hash(&header_bytes).as_ref())?;
+        file.write_all(&header_bytes)?;
+        file.sync_all()?;
+        drop(file);
+        let path = snapshot_path(&dir, header.shard_uid);
+        std::fs::rename(path.with_extension("memtrie.tmp"), &path)?;
+        File::open(&dir)?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Loads the snapshot of the memtries of the shard.  The chunks of the arena
+/// are mapped from the snapshot file, so they don't take up the memory of the
+/// node until they are modified.  Verifying their checksums reads the whole
+/// file once, so they are only verified if `verify_checksums` is set.
+///
+/// Returns an error of kind `NotFound` if there is no snapshot and
+/// `InvalidData` if the snapshot fails validation.
+pub fn load_mem_trie_snapshot(
+    dir: &Path,
+    shard_uid: ShardUId,
+    verify_checksums: bool,
+) -> io::Result<MemTrieSnapshot> {
+    let file = File::open(snapshot_path(dir, shard_uid))?;
+    let file_len = file.metadata()?.len();
+    let mut reader = &file;
+    let mut prefix = [0u8; PREFIX_LEN];
+    reader.read_exact(&mut prefix)?;
+    if &prefix[..8] != MAGIC {
+        return Err(invalid_data("not a memtrie snapshot"));
+    }
+    let version = u32::from_le_bytes(prefix[8..12].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(invalid_data(format!("unsupported snapshot format version {}", version)));
+    }
+    let header_len = u64::from_le_bytes(prefix[12..20].try_into().unwrap());
+    let header_hash = CryptoHash(prefix[20..].try_into().unwrap());
+    if header_len > file_len {
+        return Err(invalid_data("snapshot is truncated"));
+    }
+    let mut header = vec![0u8; header_len as usize];
+    reader.read_exact(&mut header)?;
+    if hash(&header) != header_hash {
+        return Err(invalid_data("snapshot header checksum mismatch"));
+    }
+    let header = SnapshotHeader::try_from_slice(&header)?;
+    if header.shard_uid != shard_uid {
+        return Err(invalid_data(format!("snapshot is of shard {}", header.shard_uid)));
+    }
+    if header.chunk_hashes.len() != header.chunk_lens.len() {
+        return Err(invalid_data("number of chunk checksums doesn't match number of chunks"));
+    }
+
+    let mut offset = (PREFIX_LEN as u64 + header_len).next_multiple_of(PAGE_SIZE);
+    let mut chunks = Vec::with_capacity(header.chunk_lens.len());
+    for (index, (&len, chunk_hash)) in
+        header.chunk_lens.iter().zip(&header.chunk_hashes).enumerate()
+    {
+        if offset + len > file_len {
+            return Err(invalid_data("snapshot is truncated"));
+        }
+        // SAFETY: The mapping is private, so writes to the chunk never reach
+        // the file.  Snapshot files are only ever replaced by renaming a new
+        // file over them, so the mapped contents can't change either.
+        let chunk = unsafe {
+            memmap2::MmapOptions::new().offset(offset).len(len as usize).map_copy(&file)?
+        };
+        if verify_checksums && hash(&chunk) != *chunk_hash {
+            return Err(invalid_data(format!("snapshot chunk {} checksum mismatch", index)));
+        }
+        chunks.push(ArenaChunk::Mapped(chunk));
+        offset = (offset + len).next_multiple_of(PAGE_SIZE);
+    }
+    if offset != file_len {
+        return Err(invalid_data("unexpected snapshot length"));
+    }
+
+    let chunk_lens = header.chunk_lens;
+    let arena = HybridArena::from_snapshot(shard_uid.to_string(), chunks, header.allocator_state)
+        .ok_or_else(|| invalid_data("invalid allocator state"))?;
+    let mut roots = HashMap::new();
+    for (state_root, positions) in header.roots {
+        let mut ids = Vec::with_capacity(positions.len());
+        for pos in positions {
+            if chunk_lens.get(pos.chunk()).map_or(true, |&len| pos.pos() as u64 >= len) {
+                return Err(invalid_data(format!("root {} is out of bounds", state_root)));
+            }
+            let id = MemTrieNodeId { pos };
+            if id.as_ptr(arena.memory()).view().node_hash() != state_root {
+                return Err(invalid_data(format!("root {} has a different hash", state_root)));
+            }
+            ids.push(id);
+        }
+        roots.insert(state_root, ids);
+    }
+    let heights: BTreeMap<_, _> = header.heights.into_iter().collect();
+    let mem_tries = MemTries::from_snapshot_parts(shard_uid, arena, roots, heights);
+    Ok(MemTrieSnapshot { mem_tries, flat_head: header.flat_head })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_mem_trie_snapshot, snapshot_path, write_mem_trie_snapshot, PAGE_SIZE};
+    use crate::flat::BlockInfo;
+    use crate::trie::mem::arena::Arena;
+    use crate::trie::mem::mem_tries::MemTries;
+    use crate::trie::mem::node::{InputMemTrieNode, MemTrieNodeId};
+    use crate::NibbleSlice;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::FlatStateValue;
+    use near_primitives::types::StateRoot;
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    fn save(dir: &Path, shard_uid: ShardUId, tries: &MemTries, flat_head: BlockInfo) {
+        write_mem_trie_snapshot(dir, shard_uid, tries, flat_head).unwrap().commit().unwrap();
+    }
+
+    /// Replaces the snapshot file by renaming a new file over it, as the old
+    /// one may still be mapped.
+    fn replace(path: &Path, data: &[u8]) {
+        let tmp_path = path.with_extension("replaced");
+        std::fs::write(&tmp_path, data).unwrap();
+        std::fs::rename(&tmp_path, path).unwrap();
+    }
+
+    fn insert_leaf(tries: &mut MemTries, value: &str, height: u64) -> StateRoot {
+        let root = MemTrieNodeId::new(
+            &mut tries.arena,
+            InputMemTrieNode::Leaf {
+                value: &FlatStateValue::Inlined(value.as_bytes().to_vec()),
+                extension: &NibbleSlice::new(&[]).encoded(true),
+            },
+        );
+        let state_root = root.as_ptr(tries.arena.memory()).view().node_hash();
+        tries.insert_root(state_root, root, height);
+        state_root
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let shard_uid = ShardUId::single_shard();
+        let flat_head = BlockInfo::genesis(CryptoHash::hash_bytes(b"head"), 100);
+        let mut tries = MemTries::new(shard_uid);
+        let state_roots: Vec<_> = (100..110)
+            .map(|height| insert_leaf(&mut tries, &format!("value {}", height), height))
+            .collect();
+        tries.delete_until_height(105);
+        save(dir.path(), shard_uid, &tries, flat_head);
+
+        let snapshot = load_mem_trie_snapshot(dir.path(), shard_uid, true).unwrap();
+        assert_eq!(snapshot.flat_head, flat_head);
+        let mut loaded = snapshot.mem_tries;
+        assert_eq!(loaded.num_roots(), 5);
+        assert!(loaded.get_root(&state_roots[4]).is_err());
+        for state_root in &state_roots[5..] {
+            let root = loaded.get_root(state_root).unwrap();
+            assert_eq!(root.view().node_hash(), *state_root);
+        }
+        assert_eq!(loaded.arena().num_active_allocs(), tries.arena().num_active_allocs());
+
+        // The mapped memory can be modified and freed without changing the
+        // snapshot.
+        let new_root = insert_leaf(&mut loaded, "new value", 110);
+        loaded.delete_until_height(111);
+        assert_eq!(loaded.arena().num_active_allocs(), 0);
+        assert!(loaded.get_root(&new_root).is_err());
+        let reloaded = load_mem_trie_snapshot(dir.path(), shard_uid, true).unwrap().mem_tries;
+        assert_eq!(reloaded.num_roots(), 5);
+        assert!(reloaded.get_root(&state_roots[9]).is_ok());
+    }
+
+    #[test]
+    fn test_snapshot_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let shard_uid = ShardUId::single_shard();
+        let err = load_mem_trie_snapshot(dir.path(), shard_uid, true).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        let mut tries = MemTries::new(shard_uid);
+        insert_leaf(&mut tries, "value", 1);
+        let flat_head = BlockInfo::genesis(CryptoHash::default(), 1);
+        save(dir.path(), shard_uid, &tries, flat_head);
+        let other_shard_uid = ShardUId { version: 1, shard_id: 1 };
+        std::fs::copy(
+            snapshot_path(dir.path(), shard_uid),
+            snapshot_path(dir.path(), other_shard_uid),
+        )
+        .unwrap();
+        let err = load_mem_trie_snapshot(dir.path(), other_shard_uid, true).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Corrupt the header.
+        let path = snapshot_path(dir.path(), shard_uid);
+        let data = std::fs::read(&path).unwrap();
+        let mut corrupted = data.clone();
+        corrupted[60] ^= 1;
+        replace(&path, &corrupted);
+        let err = load_mem_trie_snapshot(dir.path(), shard_uid, false).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Corrupt the chunk, which starts at the first page after the header.
+        // Only the checksum catches it.
+        let mut corrupted = data.clone();
+        corrupted[PAGE_SIZE as usize + 100] ^= 1;
+        replace(&path, &corrupted);
+        let err = load_mem_trie_snapshot(dir.path(), shard_uid, true).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("checksum"), "{}", err);
+
+        // Truncate the chunk.
+        replace(&path, &data[..data.len() - 1]);
+        let err = load_mem_trie_snapshot(dir.path(), shard_uid, false).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        replace(&path, &data);
+        assert!(load_mem_trie_snapshot(dir.path(), shard_uid, true).is_ok());
+    }
+}
@@ -5,7 +5,10 @@ use crate::adapter::trie_store::{TrieStoreAdapter, TrieStoreUpdateAdapter};
 use crate::adapter::StoreAdapter;
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::trie::config::TrieConfig;
-use crate::trie::mem::loading::load_trie_from_flat_state_and_delta;
+use crate::trie::mem::loading::{
+    load_trie_from_flat_state_and_delta, load_trie_from_snapshot_and_delta,
+};
+use crate::trie::mem::snapshot::write_mem_trie_snapshot;
 use crate::trie::prefetching_trie_storage::PrefetchingThreadsHandle;
 use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
 use crate::trie::{TrieRefcountAddition, POISONED_LOCK_ERR};
@@ -22,7 +25,7 @@ use near_primitives::types::{
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex, RwLock};
-use tracing::info;
+use tracing::{info, warn};
 
 struct ShardTriesInner {
     store: TrieStoreAdapter,
@@ -416,12 +419,33 @@ impl ShardTries {
         parallelize: bool,
     ) -> Result<(), StorageError> {
         info!(target: "memtrie", "Loading trie to memory for shard {:?}...", shard_uid);
-        let mem_tries = load_trie_from_flat_state_and_delta(
-            &self.0.store.store(),
-            *shard_uid,
-            state_root,
-            parallelize,
-        )?;
+        let store = self.0.store.store();
+        // On catchup the state root is given because `ChunkExtra` is not
+        // available yet, and any snapshot predates the state sync anyway.
+        let snapshot_mem_tries = match (&self.0.trie_config.mem_trie_snapshot_dir, state_root) {
+            (Some(snapshot_dir), None) => load_trie_from_snapshot_and_delta(
+                &store,
+                *shard_uid,
+                snapshot_dir,
+                self.0.trie_config.mem_trie_snapshot_verify_checksums,
+            )
+            .unwrap_or_else(|err| {
+                warn!(
+                    target: "memtrie",
+                    ?shard_uid,
+                    ?err,
+                    "Failed to load trie from snapshot, loading it from flat state"
+                );
+                None
+            }),
+            _ => None,
+        };
+        let mem_tries = match snapshot_mem_tries {
+            Some(mem_tries) => mem_tries,
+            None => {
+                load_trie_from_flat_state_and_delta(&store, *shard_uid, state_root, parallelize)?
+            }
+        };
         self.0.mem_tries.write().unwrap().insert(*shard_uid, Arc::new(RwLock::new(mem_tries)));
         info!(target: "memtrie", "Memtrie loading complete for shard {:?}", shard_uid);
         Ok(())
@@ -485,6 +509,57 @@ impl ShardTries {
         Ok(())
     }
 
+    /// Saves snapshots of all loaded in-memory tries, if memtrie snapshots are
+    /// enabled.  The tries are locked while their chunks are written to the
+    /// snapshot files, so this must only be called on shutdown, once the node
+    /// has stopped applying chunks.
+    pub fn save_mem_trie_snapshots(&self) {
+        let Some(snapshot_dir) = &self.0.trie_config.mem_trie_snapshot_dir else {
+            return;
+        };
+        let mem_tries = self.0.mem_tries.read().unwrap().clone();
+        for (shard_uid, mem_tries) in mem_tries {
+            let start = std::time::Instant::now();
+            let pending = {
+                // The flat head is read under the lock, so that the memtries
+                // have the root of the flat head.
+                let mem_tries = mem_tries.read().unwrap();
+                let flat_head =
+                    match self.0.store.store().flat_store().get_flat_storage_status(shard_uid) {
+                        Ok(FlatStorageStatus::Ready(status)) => status.flat_head,
+                        status => {
+                            warn!(
+                                target: "memtrie",
+                                ?shard_uid,
+                                ?status,
+                                "Flat storage is not ready, not saving memtrie snapshot"
+                            );
+                            continue;
+                        }
+                    };
+                write_mem_trie_snapshot(snapshot_dir, shard_uid, &mem_tries, flat_head)
+            };
+            let write_elapsed = start.elapsed();
+            match pending.and_then(|pending| pending.commit()) {
+                Ok(()) => info!(
+                    target: "memtrie",
+                    ?shard_uid,
+                    ?write_elapsed,
+                    "Saved memtrie snapshot, took {:?}",
+                    start.elapsed()
+                ),
+                Err(err) if err.kind() == std::io::ErrorKind::Unsupported => info!(
+                    target: "memtrie",
+                    ?shard_uid,
+                    "Memtrie shares memory with its parent shard, not saving snapshot"
+                ),
+                Err(err) => {
+                    warn!(target: "memtrie", ?shard_uid, ?err, "Failed to save memtrie snapshot")
+                }
+            }
+        }
+    }
+
     /// Retrieves the in-memory tries for the shard.
     pub fn get_mem_tries(&self, shard_uid: ShardUId) -> Option<Arc<RwLock<MemTries>>> {
         let guard = self.0.mem_tries.read().unwrap();
@@ -592,6 +667,10 @@ impl WrappedTrieChanges {
         &self.state_changes
     }
 
+    pub fn block_hash(&self) -> &CryptoHash {
+        &self.block_hash
+    }
+
     pub fn apply_mem_changes(&self) {
         self.tries.apply_memtrie_changes(&self.trie_changes, self.shard_uid, self.block_height);
     }
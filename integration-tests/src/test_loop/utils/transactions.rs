@@ -639,6 +639,9 @@ impl TransactionRunner {
                 }
                 _ => TxProcessingResult::Invalid(err),
             },
+            ProcessTxResponse::Underpriced => {
+                panic!("Transaction with the same nonce and at least the same priority is pending")
+            }
             ProcessTxResponse::DoesNotTrackShard => {
                 panic!("Transaction submitted to a node that doesn't track the shard")
             }
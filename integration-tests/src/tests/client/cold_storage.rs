@@ -147,6 +147,8 @@ fn test_storage_after_commit_of_cold_update() {
 
         let client = &env.clients[0];
         let client_store = client.runtime_adapter.store();
+        // Index the account activity as a node with `save_account_activity` enabled would.
+        client.chain.chain_store().save_account_activity(block.hash(), &client_store).unwrap();
         let epoch_id = client.epoch_manager.get_epoch_id(block.hash()).unwrap();
         let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
         let is_last_block_in_epoch =
@@ -321,10 +323,12 @@ fn test_cold_db_copy_with_height_skips() {
             let num_checks = check_iter(&client_store, &cold_store, col, &no_check_rules);
             // assert that this test actually checks something
             // apart from StateChangesForSplitStates, StateHeaders, and StateShardUIdMapping, that are empty
+            // AccountActivity is empty as the index is disabled.
             assert!(
                 col == DBCol::StateChangesForSplitStates
                     || col == DBCol::StateHeaders
                     || col == DBCol::StateShardUIdMapping
+                    || col == DBCol::AccountActivity
                     || num_checks > 0
             );
         }
@@ -374,10 +378,12 @@ fn test_initial_copy_to_cold(batch_size: usize) {
             continue;
         }
         let num_checks = check_iter(&client_store, &cold_store, col, &vec![]);
-        // StateChangesForSplitStates, StateHeaders, and StateShardUIdMapping are empty
+        // StateChangesForSplitStates, StateHeaders, and StateShardUIdMapping are empty, as is
+        // AccountActivity with the index disabled.
         if col == DBCol::StateChangesForSplitStates
             || col == DBCol::StateHeaders
             || col == DBCol::StateShardUIdMapping
+            || col == DBCol::AccountActivity
         {
             continue;
         }
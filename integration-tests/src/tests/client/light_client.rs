@@ -0,0 +1,157 @@
+//! Tests of the light client against the blocks and the execution proofs of a
+//! chain, built the same way as the `next_light_client_block` and
+//! `EXPERIMENTAL_light_client_proof` RPC methods build them.
+use near_chain::{
+    create_light_client_block_view, get_epoch_block_producers_view, Chain, ChainStoreAccess,
+};
+use near_chain_configs::Genesis;
+use near_client::test_utils::TestEnv;
+use near_client::ProcessTxResponse;
+use near_crypto::InMemorySigner;
+use near_epoch_manager::EpochManagerAdapter;
+use near_light_client::{ExecutionProof, LightClient};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::merklize;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::views::LightClientBlockView;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+const EPOCH_LENGTH: u64 = 5;
+
+/// Returns the block that `next_light_client_block` returns for a light client
+/// whose head is `last_block_hash`.
+fn next_light_client_block(
+    env: &TestEnv,
+    last_block_hash: &CryptoHash,
+) -> Option<LightClientBlockView> {
+    let client = &env.clients[0];
+    let last_block_header = client.chain.get_block_header(last_block_hash).unwrap();
+    let head = client.chain.head().unwrap();
+    if *last_block_header.epoch_id() == head.epoch_id
+        || *last_block_header.next_epoch_id() == head.epoch_id
+    {
+        let head_header = client.chain.get_block_header(&head.last_block_hash).unwrap();
+        let block = Chain::create_light_client_block(
+            &head_header,
+            client.epoch_manager.as_ref(),
+            client.chain.chain_store(),
+        )
+        .unwrap();
+        (block.inner_lite.height > last_block_header.height()).then_some(block)
+    } else {
+        let chain_store = client.chain.chain_store();
+        let block = chain_store.get_epoch_light_client_block(&last_block_header.next_epoch_id().0);
+        block.ok().map(|block| block.as_ref().clone())
+    }
+}
+
+/// Returns the proof that `EXPERIMENTAL_light_client_proof` returns for the
+/// outcome of the transaction when `light_client_head` is `head_hash`.
+fn execution_proof(
+    env: &TestEnv,
+    tx: &SignedTransaction,
+    head_hash: &CryptoHash,
+) -> ExecutionProof {
+    let client = &env.clients[0];
+    let chain = &client.chain;
+    let mut outcome = chain.get_execution_outcome(&tx.get_hash()).unwrap();
+    let epoch_id = *chain.get_block_header(&outcome.block_hash).unwrap().epoch_id();
+    let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
+    let shard_id =
+        client.epoch_manager.account_id_to_shard_id(tx.transaction.signer_id(), &epoch_id).unwrap();
+    let shard_index = shard_layout.get_shard_index(shard_id).unwrap();
+    let (block_hash, _) =
+        chain.get_next_block_hash_with_new_chunk(&outcome.block_hash, shard_id).unwrap().unwrap();
+    outcome.block_hash = block_hash;
+    let outcome_roots = chain
+        .get_block(&block_hash)
+        .unwrap()
+        .chunks()
+        .iter_deprecated()
+        .map(|chunk| chunk.prev_outcome_root())
+        .collect::<Vec<_>>();
+
+    let block_header = chain.get_block_header(&block_hash).unwrap();
+    let head_header = chain.get_block_header(head_hash).unwrap();
+    chain.check_blocks_final_and_canonical(&[block_header.clone(), head_header]).unwrap();
+    ExecutionProof {
+        outcome_proof: outcome.into(),
+        outcome_root_proof: merklize(&outcome_roots).1[shard_index].clone(),
+        block_header_lite: block_header.into(),
+        block_proof: chain
+            .compute_past_block_proof_in_merkle_tree_of_later_block(&block_hash, head_hash)
+            .unwrap(),
+    }
+}
+
+/// Follows the chain from its first block across several epochs with the
+/// light client and verifies the outcome of a transaction against its head.
+#[test]
+fn test_light_client_follows_chain() {
+    init_test_logger();
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = EPOCH_LENGTH;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer = InMemorySigner::test_signer(&"test0".parse().unwrap());
+    let tx = SignedTransaction::send_money(
+        1,
+        "test0".parse().unwrap(),
+        "test1".parse().unwrap(),
+        &signer,
+        100,
+        genesis_hash,
+    );
+    assert_eq!(env.clients[0].process_tx(tx.clone(), false, false), ProcessTxResponse::ValidTx);
+    for height in 1..=4 * EPOCH_LENGTH {
+        env.produce_block(0, height);
+    }
+
+    let client = &env.clients[0];
+    let first_header = client.chain.get_block_header_by_height(1).unwrap();
+    let block_producers = get_epoch_block_producers_view(
+        first_header.epoch_id(),
+        first_header.prev_hash(),
+        client.epoch_manager.as_ref(),
+    )
+    .unwrap();
+    let next_block_producers = get_epoch_block_producers_view(
+        first_header.next_epoch_id(),
+        first_header.prev_hash(),
+        client.epoch_manager.as_ref(),
+    )
+    .unwrap();
+    let first_block = create_light_client_block_view(
+        &first_header,
+        client.chain.chain_store(),
+        Some(next_block_producers),
+    )
+    .unwrap();
+    let mut light_client = LightClient::new(first_block, block_producers).unwrap();
+
+    let mut epoch_ids = vec![light_client.head().inner_lite.epoch_id];
+    while let Some(block) = next_light_client_block(&env, &light_client.head_hash()) {
+        light_client.validate_and_update_head(&block).unwrap();
+        let epoch_id = light_client.head().inner_lite.epoch_id;
+        if epoch_ids.last() != Some(&epoch_id) {
+            epoch_ids.push(epoch_id);
+        }
+    }
+    // The light client has moved across the epoch boundaries of the chain to
+    // a final block of it.
+    assert!(epoch_ids.len() >= 3, "{epoch_ids:?}");
+    let head = light_client.head().inner_lite.clone();
+    let final_head = env.clients[0].chain.final_head().unwrap();
+    assert!(head.height <= final_head.height);
+    let canonical_header = env.clients[0].chain.get_block_header_by_height(head.height).unwrap();
+    assert_eq!(*canonical_header.hash(), light_client.head_hash());
+
+    let proof = execution_proof(&env, &tx, &light_client.head_hash());
+    light_client.verify_execution_proof(&proof).unwrap();
+    // The proof only holds for the block merkle root of the head it was
+    // requested for.
+    let earlier_head = env.clients[0].chain.get_block_header(&proof.outcome_proof.block_hash);
+    let earlier_root = *earlier_head.unwrap().block_merkle_root();
+    assert!(near_light_client::verify_execution_proof(&proof, &earlier_root).is_err());
+}
@@ -6,12 +6,15 @@ mod cold_storage;
 mod features;
 mod flat_storage;
 mod invalid_txs;
+mod light_client;
 mod process_blocks;
 mod resharding_v2;
 mod runtimes;
 #[cfg(feature = "sandbox")]
 mod sandbox;
+mod scrubber;
 mod state_dump;
 mod state_snapshot;
 mod sync_state_nodes;
+mod transaction_pool;
 mod undo_block;
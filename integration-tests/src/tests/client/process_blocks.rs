@@ -2784,7 +2784,8 @@ fn test_execution_metadata() {
         near_vm_runner::logic::ContractPrepareVersion::V1 => 2,
         // We spend two wasm instructions (call & drop), plus 8 ops for initializing function
         // operand stack (8 bytes worth to hold the return value.)
-        near_vm_runner::logic::ContractPrepareVersion::V2 => 10,
+        near_vm_runner::logic::ContractPrepareVersion::V2
+        | near_vm_runner::logic::ContractPrepareVersion::V3 => 10,
     };
 
     // Profile for what's happening *inside* wasm vm during function call.
@@ -3804,6 +3805,7 @@ mod contract_precompilation_tests {
                 "log_something",
                 &[],
                 &mut logs,
+                None,
                 &MockEpochInfoProvider::default(),
             )
             .unwrap();
@@ -1,21 +1,25 @@
+use assert_matches::assert_matches;
+use near_async::time::Duration;
 use near_chain::Provenance;
 use near_chain_configs::Genesis;
 use near_client::test_utils::TestEnv;
 use near_client::ProcessTxResponse;
 use near_crypto::{InMemorySigner, Signer};
 use near_primitives::account::Account;
+use near_primitives::sandbox::impersonation::impersonate_accounts;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::state_record::StateRecord;
 use near_primitives::transaction::{
-    Action, DeployContractAction, FunctionCallAction, SignedTransaction,
+    Action, DeployContractAction, FunctionCallAction, SignedTransaction, TransferAction,
 };
 use near_primitives::types::{AccountId, BlockHeight, Nonce};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 
+const EPOCH_LENGTH: u64 = 5;
+
 fn test_setup() -> (TestEnv, Signer) {
-    let epoch_length = 5;
     let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
-    genesis.config.epoch_length = epoch_length;
+    genesis.config.epoch_length = EPOCH_LENGTH;
     let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
     let signer = InMemorySigner::test_signer(&"test0".parse().unwrap());
     assert_eq!(
@@ -105,3 +109,124 @@ fn test_patch_account() {
     let test1_after = env.query_account("test1".parse().unwrap());
     assert_eq!(test1_after.amount, 10);
 }
+
+#[test]
+fn test_snapshot_revert() {
+    let (mut env, _signer) = test_setup();
+    let head = env.clients[0].chain.head().unwrap();
+    let test1_before = env.query_account("test1".parse().unwrap());
+    let snapshot_id = env.clients[0].sandbox_snapshot().unwrap();
+
+    let mut test1: Account = test1_before.clone().into();
+    test1.set_amount(10);
+    env.clients[0].chain.patch_state(SandboxStatePatch::new(vec![StateRecord::Account {
+        account_id: "test1".parse().unwrap(),
+        account: test1,
+    }]));
+    do_blocks(&mut env, 9, 20);
+    assert_eq!(env.query_account("test1".parse().unwrap()).amount, 10);
+
+    env.clients[0].sandbox_revert(snapshot_id).unwrap();
+    assert_eq!(env.clients[0].chain.head().unwrap(), head);
+    assert_eq!(env.query_account("test1".parse().unwrap()), test1_before);
+    // The snapshot is dropped once reverted to.
+    assert!(env.clients[0].sandbox_revert(snapshot_id).is_err());
+
+    // Blocks are produced on top of the snapshotted block at heights which were not used yet.
+    do_blocks(&mut env, 21, 30);
+    let head_after = env.clients[0].chain.head().unwrap();
+    assert_eq!(head_after.height, 29);
+    assert_eq!(env.query_account("test1".parse().unwrap()).amount, test1_before.amount);
+}
+
+#[test]
+fn test_impersonate_account() {
+    let (mut env, signer) = test_setup();
+    let test1_before = env.query_account("test1".parse().unwrap());
+    // The transaction of test1 is signed with the key of test0.
+    let transfer = || vec![Action::Transfer(TransferAction { deposit: 100 })];
+    assert_matches!(
+        send_tx(
+            &mut env,
+            1,
+            "test1".parse().unwrap(),
+            "test0".parse().unwrap(),
+            &signer,
+            transfer()
+        ),
+        ProcessTxResponse::InvalidTx(_)
+    );
+
+    let guard = impersonate_accounts(vec!["test1".parse().unwrap()]);
+    let response = send_tx(
+        &mut env,
+        1,
+        "test1".parse().unwrap(),
+        "test0".parse().unwrap(),
+        &signer,
+        transfer(),
+    );
+    do_blocks(&mut env, 9, 20);
+    drop(guard);
+    assert_eq!(response, ProcessTxResponse::ValidTx);
+    assert!(env.query_account("test1".parse().unwrap()).amount < test1_before.amount - 100);
+}
+
+#[test]
+fn test_set_next_block_timestamp() {
+    let (mut env, _signer) = test_setup();
+    let head = env.clients[0].chain.head().unwrap();
+    let head_timestamp =
+        env.clients[0].chain.get_block_header(&head.last_block_hash).unwrap().timestamp();
+    let timestamp = head_timestamp + Duration::days(365);
+    env.clients[0].sandbox_set_next_block_timestamp(timestamp).unwrap();
+
+    let block = env.clients[0].produce_block(9).unwrap().unwrap();
+    assert_eq!(block.header().timestamp(), timestamp);
+    env.process_block(0, block, Provenance::PRODUCED);
+    // The following blocks continue from the timestamp.
+    let block = env.clients[0].produce_block(10).unwrap().unwrap();
+    assert!(block.header().timestamp() > timestamp);
+    assert!(block.header().timestamp() < timestamp + Duration::days(1));
+
+    env.clients[0].sandbox_time_warp(Duration::days(365)).unwrap();
+    env.process_block(0, block, Provenance::PRODUCED);
+    let block = env.clients[0].produce_block(11).unwrap().unwrap();
+    assert!(block.header().timestamp() > timestamp + Duration::days(365));
+    // Timestamps can't go back.
+    assert!(env.clients[0].sandbox_set_next_block_timestamp(head_timestamp).is_err());
+}
+
+#[test]
+fn test_snapshot_revert_after_gc_period() {
+    let (mut env, _signer) = test_setup();
+    let head = env.clients[0].chain.head().unwrap();
+    let test1_before = env.query_account("test1".parse().unwrap());
+    let snapshot_id = env.clients[0].sandbox_snapshot().unwrap();
+    let pinned_height =
+        env.clients[0].chain.chain_store().sandbox_pinned_height().unwrap().unwrap();
+    assert!(pinned_height <= head.height);
+
+    let mut test1: Account = test1_before.clone().into();
+    test1.set_amount(10);
+    env.clients[0].chain.patch_state(SandboxStatePatch::new(vec![StateRecord::Account {
+        account_id: "test1".parse().unwrap(),
+        account: test1,
+    }]));
+    // Garbage collection would remove the snapshotted block and its state after this many
+    // epochs, if it didn't keep them for the snapshot.
+    let num_blocks = EPOCH_LENGTH * (env.clients[0].config.gc.gc_num_epochs_to_keep + 2);
+    let end = head.height + 1 + num_blocks;
+    do_blocks(&mut env, head.height + 1, end);
+    assert_eq!(env.query_account("test1".parse().unwrap()).amount, 10);
+    assert!(env.clients[0].chain.tail().unwrap() <= pinned_height);
+
+    env.clients[0].sandbox_revert(snapshot_id).unwrap();
+    assert_eq!(env.clients[0].chain.head().unwrap(), head);
+    assert_eq!(env.query_account("test1".parse().unwrap()), test1_before);
+    assert_eq!(env.clients[0].chain.chain_store().sandbox_pinned_height().unwrap(), None);
+
+    do_blocks(&mut env, end + 1, end + 1 + EPOCH_LENGTH);
+    assert_eq!(env.clients[0].chain.head().unwrap().height, end + EPOCH_LENGTH);
+    assert_eq!(env.query_account("test1".parse().unwrap()).amount, test1_before.amount);
+}
@@ -0,0 +1,307 @@
+//! Tests of the data scrubber, which inject corruption into the database and
+//! check that the scrubber finds it, exports it in the metrics and repairs it.
+use near_async::futures::TokioRuntimeFutureSpawner;
+use near_async::time::Clock;
+use near_chain::{ChainStoreAccess, Provenance};
+use near_chain_configs::ExternalStorageLocation::Filesystem;
+use near_chain_configs::{
+    ExternalStorageConfig, Genesis, ScrubberConfig, StateSyncConfig, SyncConfig,
+};
+use near_client::scrubber_actor::ScrubberActor;
+use near_client::sync::external::{external_storage_location, StateFileType};
+use near_client::test_utils::TestEnv;
+use near_client_primitives::debug::{ScrubberFindingView, ScrubberStatusView};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::challenge::PartialState;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
+use near_primitives::state::FlatStateValue;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_store::adapter::StoreAdapter;
+use near_store::{DBCol, Store};
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Arc;
+
+const NUM_BLOCKS: u64 = 15;
+
+fn setup_env() -> TestEnv {
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = 100;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    for height in 1..=NUM_BLOCKS {
+        let block = env.clients[0].produce_block(height).unwrap().unwrap();
+        env.process_block(0, block, Provenance::PRODUCED);
+    }
+    env
+}
+
+fn new_scrubber(env: &TestEnv, state_parts_dir: Option<&Path>) -> ScrubberActor {
+    let client = &env.clients[0];
+    let config = ScrubberConfig {
+        enabled: true,
+        max_entries_per_step: 10,
+        repair_flat_state: true,
+        repair_state_from_external_storage: state_parts_dir.is_some(),
+        ..ScrubberConfig::default()
+    };
+    let state_sync_config = StateSyncConfig {
+        dump: None,
+        sync: match state_parts_dir {
+            Some(root_dir) => SyncConfig::ExternalStorage(ExternalStorageConfig {
+                location: Filesystem { root_dir: root_dir.to_path_buf() },
+                num_concurrent_requests: 1,
+                num_concurrent_requests_during_catchup: 1,
+                external_storage_fallback_threshold: 0,
+            }),
+            None => SyncConfig::Peers,
+        },
+    };
+    ScrubberActor::new(
+        Clock::real(),
+        store(env),
+        client.chain.genesis().height(),
+        client.epoch_manager.clone(),
+        client.runtime_adapter.get_flat_storage_manager(),
+        config,
+        client.config.chain_id.clone(),
+        &state_sync_config,
+        Arc::new(TokioRuntimeFutureSpawner(Arc::new(
+            tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap(),
+        ))),
+    )
+}
+
+/// Dumps the state header and parts of the shard for the epoch of the final
+/// head into `root_dir`, like a node dumping state for state sync would.
+/// Returns the state root of the dump.
+fn dump_state(env: &mut TestEnv, shard_uid: ShardUId, root_dir: &Path) -> CryptoHash {
+    let client = &mut env.clients[0];
+    let final_head = client.chain.chain_store().final_head().unwrap();
+    let epoch_info = client.epoch_manager.get_epoch_info(&final_head.epoch_id).unwrap();
+    let shard_id = shard_uid.shard_id();
+    let sync_hash = final_head.last_block_hash;
+    let write = |file_type: &StateFileType, data: &[u8]| {
+        let path = root_dir.join(external_storage_location(
+            &client.config.chain_id,
+            &final_head.epoch_id,
+            epoch_info.epoch_height(),
+            shard_id,
+            file_type,
+        ));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    };
+    let chain = &mut client.chain;
+    let header = chain.get_state_response_header(shard_id, sync_hash).unwrap();
+    write(&StateFileType::StateHeader, &borsh::to_vec(&header).unwrap());
+    let num_parts = header.num_state_parts();
+    for part_id in 0..num_parts {
+        let part = chain.get_state_response_part(shard_id, part_id, sync_hash).unwrap();
+        write(&StateFileType::StatePart { part_id, num_parts }, &part);
+    }
+    header.chunk_prev_state_root()
+}
+
+fn store(env: &TestEnv) -> Store {
+    env.clients[0].chain.chain_store().store().clone()
+}
+
+/// Returns the value of the scrubber counter with the given name and labels.
+fn metric(name: &str, labels: &[(&str, &str)]) -> f64 {
+    near_o11y::metrics::gather()
+        .iter()
+        .filter(|family| family.get_name() == name)
+        .flat_map(|family| family.get_metric())
+        .filter(|metric| {
+            labels.iter().all(|(name, value)| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == *name && label.get_value() == *value)
+            })
+        })
+        .map(|metric| metric.get_counter().get_value())
+        .sum()
+}
+
+fn corruptions_found(check: &str) -> f64 {
+    metric("near_scrubber_corruptions_found_total", &[("check", check)])
+}
+
+fn repairs(check: &str, result: &str) -> f64 {
+    metric("near_scrubber_repairs_total", &[("check", check), ("result", result)])
+}
+
+fn corruptions_found_in_status(status: &ScrubberStatusView, check: &str) -> u64 {
+    status.checks.iter().find(|view| view.check == check).unwrap().corruptions_found
+}
+
+fn find<'a>(status: &'a ScrubberStatusView, entry: &str) -> &'a ScrubberFindingView {
+    status
+        .findings
+        .iter()
+        .rev()
+        .find(|finding| finding.entry == entry)
+        .unwrap_or_else(|| panic!("no finding for {entry} in {:?}", status.findings))
+}
+
+/// Corrupts and removes a node of the trie and corrupts a `FlatState` value.
+/// The corrupted node is restored from the state part in the external storage
+/// and the value from the trie; the removed node can't be restored since its
+/// reference count is lost.
+#[test]
+fn test_scrubber_state_and_flat_state() {
+    init_test_logger();
+    let mut env = setup_env();
+    let store = store(&env);
+    let final_head = env.clients[0].chain.chain_store().final_head().unwrap();
+    let epoch_manager = env.clients[0].epoch_manager.clone();
+    let shard_layout = epoch_manager.get_shard_layout(&final_head.epoch_id).unwrap();
+    let shard_uid: ShardUId = shard_layout.shard_uids().next().unwrap();
+    let state_parts_dir = tempfile::Builder::new().prefix("scrubber").tempdir().unwrap();
+    let dump_state_root = dump_state(&mut env, shard_uid, state_parts_dir.path());
+    let chain_store = env.clients[0].chain.chain_store();
+    let state_root =
+        *chain_store.get_chunk_extra(&final_head.last_block_hash, &shard_uid).unwrap().state_root();
+    // No transactions are sent, so the state doesn't change between the
+    // dumped block and the final head.
+    assert_eq!(dump_state_root, state_root);
+
+    let mut scrubber = new_scrubber(&env, Some(state_parts_dir.path()));
+    let status = scrubber.run_full_pass();
+    assert!(status.findings.is_empty(), "{:?}", status.findings);
+    assert!(status.checks.iter().all(|view| view.passes_completed == 1));
+
+    // A node other than the root, which the state header holds, is corrupted
+    // in place.  The correct one is in the state part of the epoch of the
+    // final head.
+    let state_part = env.clients[0]
+        .chain
+        .get_state_response_part(shard_uid.shard_id(), 0, final_head.last_block_hash)
+        .unwrap();
+    let PartialState::TrieValues(values) = borsh::from_slice::<PartialState>(&state_part).unwrap();
+    let node =
+        values.into_iter().find(|value| CryptoHash::hash_bytes(value) != state_root).unwrap();
+    let node_hash = CryptoHash::hash_bytes(&node);
+    let trie_store = store.trie_store();
+    let (node, refcount) = trie_store.get_with_refcount(shard_uid, &node_hash).unwrap().unwrap();
+    let refcount = NonZeroU32::new(refcount.try_into().unwrap()).unwrap();
+    let mut corrupted = node.clone();
+    corrupted[0] ^= 0xff;
+    let mut store_update = trie_store.store_update();
+    store_update.replace_value(shard_uid, &node_hash, &corrupted);
+    store_update.commit().unwrap();
+
+    let state_corruptions_before = corruptions_found("state");
+    let state_repairs_before = repairs("state", "ok");
+    let status = scrubber.run_full_pass();
+    let finding = find(&status, &format!("{shard_uid} {node_hash}"));
+    assert_eq!(finding.check, "state");
+    assert!(finding.error.starts_with("hash mismatch"), "{}", finding.error);
+    assert_eq!(finding.repair.as_deref(), Some("repaired"));
+    assert_eq!(
+        trie_store.get_with_refcount(shard_uid, &node_hash).unwrap(),
+        Some((node.clone(), i64::from(refcount.get())))
+    );
+    assert_eq!(corruptions_found_in_status(&status, "state"), 1);
+    assert_eq!(corruptions_found("state") - state_corruptions_before, 1.0);
+    assert_eq!(repairs("state", "ok") - state_repairs_before, 1.0);
+
+    // A removed node is reported, but isn't restored.
+    let mut store_update = trie_store.store_update();
+    store_update.decrement_refcount_by(shard_uid, &node_hash, refcount);
+    store_update.commit().unwrap();
+    let state_corruptions_before = corruptions_found("state");
+    let state_repair_errors_before = repairs("state", "error");
+    let status = scrubber.run_full_pass();
+    let finding = find(&status, &format!("{shard_uid} {node_hash}"));
+    assert_eq!(finding.error, "missing");
+    assert!(finding.repair.as_deref().unwrap().starts_with("failed"), "{:?}", finding.repair);
+    assert_eq!(trie_store.get_with_refcount(shard_uid, &node_hash).unwrap(), None);
+    assert_eq!(corruptions_found("state") - state_corruptions_before, 1.0);
+    assert_eq!(repairs("state", "error") - state_repair_errors_before, 1.0);
+    let mut store_update = trie_store.store_update();
+    store_update.increment_refcount_by(shard_uid, &node_hash, &node, refcount);
+    store_update.commit().unwrap();
+
+    // A `FlatState` value is replaced with a wrong one.
+    let flat_store = store.flat_store();
+    let (key, value) = flat_store.iter(shard_uid).next().unwrap().unwrap();
+    let mut store_update = flat_store.store_update();
+    store_update.set(shard_uid, key.clone(), Some(FlatStateValue::inlined(b"corrupted")));
+    store_update.commit().unwrap();
+    let flat_state_corruptions_before = corruptions_found("flat_state");
+    let flat_state_repairs_before = repairs("flat_state", "ok");
+    let status = scrubber.run_full_pass();
+    let finding = find(&status, &format!("{shard_uid} {}", hex::encode(&key)));
+    assert_eq!(finding.check, "flat_state");
+    assert_eq!(finding.error, "value differs from the trie");
+    assert_eq!(finding.repair.as_deref(), Some("repaired"));
+    let repaired = flat_store.get(shard_uid, &key).unwrap().unwrap();
+    assert_eq!(repaired.to_value_ref(), value.to_value_ref());
+    assert_eq!(corruptions_found("flat_state") - flat_state_corruptions_before, 1.0);
+    assert_eq!(repairs("flat_state", "ok") - flat_state_repairs_before, 1.0);
+
+    // Everything is fixed, so the next pass finds nothing new.
+    let findings_before = status.findings.len();
+    let status = scrubber.run_full_pass();
+    assert_eq!(status.findings.len(), findings_before, "{:?}", status.findings);
+}
+
+/// Replaces a block header with another one and the chunk extra of a block
+/// with one with a different state root, which doesn't match the chunk of the
+/// next block.
+#[test]
+fn test_scrubber_chain() {
+    init_test_logger();
+    let env = setup_env();
+    let store = store(&env);
+    let chain_store = env.clients[0].chain.chain_store();
+    let epoch_manager = env.clients[0].epoch_manager.clone();
+    let final_head = chain_store.final_head().unwrap();
+    let shard_layout = epoch_manager.get_shard_layout(&final_head.epoch_id).unwrap();
+    let shard_uid: ShardUId = shard_layout.shard_uids().next().unwrap();
+
+    let header_hash = chain_store.get_block_hash_by_height(3).unwrap();
+    let other_header_hash = chain_store.get_block_hash_by_height(4).unwrap();
+    let other_header = chain_store.get_block_header(&other_header_hash).unwrap();
+    let chunk_extra_hash = chain_store.get_block_hash_by_height(7).unwrap();
+    let next_block_hash = chain_store.get_block_hash_by_height(8).unwrap();
+    let mut chunk_extra =
+        ChunkExtra::clone(&chain_store.get_chunk_extra(&chunk_extra_hash, &shard_uid).unwrap());
+    *chunk_extra.state_root_mut() = CryptoHash::hash_bytes(b"corrupted");
+
+    let mut store_update = store.store_update();
+    // Block headers are insert-only, so the header has to be overwritten with
+    // the raw bytes.
+    store_update.set_raw_bytes(
+        DBCol::BlockHeader,
+        header_hash.as_ref(),
+        &borsh::to_vec(&other_header).unwrap(),
+    );
+    store_update
+        .set_ser(
+            DBCol::ChunkExtra,
+            &get_block_shard_uid(&chunk_extra_hash, &shard_uid),
+            &chunk_extra,
+        )
+        .unwrap();
+    store_update.commit().unwrap();
+
+    // The scrubber is created after the corruption, so that its chain store
+    // doesn't have the chunk extra cached.
+    let mut scrubber = new_scrubber(&env, None);
+    let chain_corruptions_before = corruptions_found("chain");
+    let status = scrubber.run_full_pass();
+    let finding = find(&status, &format!("#3 {header_hash}"));
+    assert_eq!(finding.check, "chain");
+    assert!(finding.error.starts_with("header hash mismatch"), "{}", finding.error);
+    assert_eq!(finding.repair, None);
+    let finding = find(&status, &format!("#8 {next_block_hash}"));
+    assert!(finding.error.contains("doesn't match chunk extra"), "{}", finding.error);
+    let chain_corruptions = corruptions_found_in_status(&status, "chain");
+    assert!(chain_corruptions >= 2, "{:?}", status.findings);
+    assert_eq!(corruptions_found("chain") - chain_corruptions_before, chain_corruptions as f64);
+}
@@ -0,0 +1,206 @@
+use near_chain::ChainStoreAccess;
+use near_chain_configs::{Genesis, TransactionPoolOrdering};
+use near_chunks::client::ShardedTransactionPool;
+use near_client::test_utils::{TestEnv, TEST_SEED};
+use near_client::ProcessTxResponse;
+use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
+use near_primitives::action::{Action, TransferAction};
+use near_primitives::errors::InvalidTxError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, Nonce};
+use near_store::DBCol;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+fn transfer(
+    accounts: &[AccountId],
+    signer_index: usize,
+    nonce: Nonce,
+    block_hash: CryptoHash,
+) -> SignedTransaction {
+    let signer_id = &accounts[signer_index];
+    SignedTransaction::send_money(
+        nonce,
+        signer_id.clone(),
+        accounts[(signer_index + 1) % accounts.len()].clone(),
+        &create_user_test_signer(signer_id),
+        ONE_NEAR,
+        block_hash,
+    )
+}
+
+/// With the `PriorityFee` ordering a transaction replaces the pending one with the same nonce if it
+/// prepays more gas.  The runtime rejects transactions carrying a priority fee before they get into
+/// the pool.
+#[test]
+fn test_priority_fee_ordering_process_tx() {
+    let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+    let genesis = Genesis::test(accounts.clone(), 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    env.clients[0].sharded_tx_pool =
+        ShardedTransactionPool::new(TEST_SEED, None, TransactionPoolOrdering::PriorityFee);
+
+    let signer = create_user_test_signer(&accounts[0]);
+    let tip = env.clients[0].chain.head().unwrap();
+    let send_money = |deposit| {
+        SignedTransaction::send_money(
+            1,
+            accounts[0].clone(),
+            accounts[1].clone(),
+            &signer,
+            deposit,
+            tip.last_block_hash,
+        )
+    };
+    let call = SignedTransaction::call(
+        1,
+        accounts[0].clone(),
+        accounts[1].clone(),
+        &signer,
+        0,
+        "method".to_string(),
+        vec![],
+        10_000_000_000_000,
+        tip.last_block_hash,
+    );
+    assert_eq!(env.clients[0].process_tx(send_money(1), false, false), ProcessTxResponse::ValidTx);
+    assert_eq!(
+        env.clients[0].process_tx(send_money(2), false, false),
+        ProcessTxResponse::Underpriced
+    );
+    assert_eq!(env.clients[0].process_tx(call.clone(), false, false), ProcessTxResponse::ValidTx);
+
+    let with_priority_fee = SignedTransaction::from_actions_v1(
+        1,
+        accounts[0].clone(),
+        accounts[1].clone(),
+        &signer,
+        vec![Action::Transfer(TransferAction { deposit: 3 })],
+        tip.last_block_hash,
+        100,
+    );
+    assert_eq!(
+        env.clients[0].process_tx(with_priority_fee, false, false),
+        ProcessTxResponse::InvalidTx(InvalidTxError::InvalidTransactionVersion)
+    );
+
+    let pool_hashes: Vec<_> =
+        env.clients[0].sharded_tx_pool.transactions().map(|tx| tx.get_hash()).collect();
+    assert_eq!(pool_hashes, vec![call.get_hash()]);
+}
+
+/// Transactions saved from the pool are submitted again after a restart, except the ones which
+/// have been included, have expired or have become invalid in the meantime.
+#[test]
+fn test_restore_transaction_pool() {
+    let accounts: Vec<AccountId> = (0..4).map(|i| format!("test{i}").parse().unwrap()).collect();
+    let mut genesis = Genesis::test(accounts.clone(), 1);
+    genesis.config.transaction_validity_period = 8;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    for height in 1..=5 {
+        env.produce_block(0, height);
+    }
+    let recent_hash = env.clients[0].chain.head().unwrap().last_block_hash;
+
+    let included = transfer(&accounts, 0, 1, recent_hash);
+    let expired = transfer(&accounts, 1, 1, genesis_hash);
+    let invalid = transfer(&accounts, 2, 1, recent_hash);
+    let valid = transfer(&accounts, 3, 1, recent_hash);
+    for tx in [&included, &expired, &invalid, &valid] {
+        assert_eq!(env.clients[0].process_tx(tx.clone(), false, false), ProcessTxResponse::ValidTx);
+    }
+    env.clients[0].config.save_transaction_pool = true;
+    env.clients[0].save_transaction_pool().unwrap();
+
+    // The pool is lost on restart.  Before the saved transactions are restored, the first one is
+    // included, the nonce of the third one is used up, and the second one expires.
+    env.restart(0);
+    assert_eq!(env.clients[0].sharded_tx_pool.transactions().count(), 0);
+    for tx in [included.clone(), transfer(&accounts, 2, 10, recent_hash)] {
+        assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    }
+    for height in 6..=10 {
+        env.produce_block(0, height);
+    }
+    let chain_store = env.clients[0].chain.chain_store();
+    assert!(chain_store.get_transaction(&included.get_hash()).unwrap().is_some());
+
+    env.clients[0].load_saved_transaction_pool();
+    env.clients[0].restore_transaction_pool();
+    let pool_hashes: Vec<_> =
+        env.clients[0].sharded_tx_pool.transactions().map(|tx| tx.get_hash()).collect();
+    assert_eq!(pool_hashes, vec![valid.get_hash()]);
+}
+
+/// Saving the pool again writes the transactions which have entered the pool and deletes the ones
+/// which have left it.
+#[test]
+fn test_save_transaction_pool_changes() {
+    let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+    let genesis = Genesis::test(accounts.clone(), 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    env.clients[0].config.save_transaction_pool = true;
+    let saved_hashes = |env: &TestEnv| -> Vec<CryptoHash> {
+        let store = env.clients[0].chain.chain_store().store();
+        store
+            .iter(DBCol::TransactionPool)
+            .map(|item| CryptoHash::try_from(item.unwrap().0.as_ref()).unwrap())
+            .collect()
+    };
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+
+    let included = transfer(&accounts, 0, 1, genesis_hash);
+    assert_eq!(
+        env.clients[0].process_tx(included.clone(), false, false),
+        ProcessTxResponse::ValidTx
+    );
+    env.clients[0].save_transaction_pool().unwrap();
+    assert_eq!(saved_hashes(&env), vec![included.get_hash()]);
+
+    for height in 1..=3 {
+        env.produce_block(0, height);
+    }
+    let chain_store = env.clients[0].chain.chain_store();
+    assert!(chain_store.get_transaction(&included.get_hash()).unwrap().is_some());
+    let pending = transfer(&accounts, 1, 1, genesis_hash);
+    assert_eq!(
+        env.clients[0].process_tx(pending.clone(), false, false),
+        ProcessTxResponse::ValidTx
+    );
+    env.clients[0].save_transaction_pool().unwrap();
+    assert_eq!(saved_hashes(&env), vec![pending.get_hash()]);
+}
+
+/// A node without a validator key doesn't keep transactions in the pool, so it forwards the
+/// restored transactions to the validators instead of dropping them.
+#[test]
+fn test_restore_transaction_pool_without_validator_key() {
+    let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+    let genesis = Genesis::test(accounts.clone(), 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let tx = transfer(&accounts, 1, 1, genesis_hash);
+    assert_eq!(env.clients[0].process_tx(tx.clone(), false, false), ProcessTxResponse::ValidTx);
+    env.clients[0].config.save_transaction_pool = true;
+    env.clients[0].save_transaction_pool().unwrap();
+
+    env.restart(0);
+    env.clients[0].validator_signer.update(None);
+    env.network_adapters[0].requests.write().unwrap().clear();
+    env.clients[0].load_saved_transaction_pool();
+    env.clients[0].restore_transaction_pool();
+
+    assert_eq!(env.clients[0].sharded_tx_pool.transactions().count(), 0);
+    let forwarded = env.network_adapters[0].requests.read().unwrap().iter().any(|request| {
+        matches!(
+            request,
+            PeerManagerMessageRequest::NetworkRequests(NetworkRequests::ForwardTx(_, forwarded_tx))
+                if *forwarded_tx == tx
+        )
+    });
+    assert!(forwarded);
+}
@@ -123,12 +123,49 @@ fn test_view_call() {
         "run_test",
         &[],
         &mut logs,
+        None,
         &MockEpochInfoProvider::default(),
     );
 
     assert_eq!(result.unwrap(), (10i32).to_le_bytes());
 }
 
+#[test]
+fn test_view_call_with_wasm_gas_profile() {
+    let (viewer, root) = get_test_trie_viewer();
+
+    let mut logs = vec![];
+    let mut wasm_gas_profile = String::new();
+    let view_state = ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        shard_id: ShardUId::single_shard().shard_id(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    };
+    let result = viewer.call_function(
+        root,
+        view_state,
+        &"test.contract".parse().unwrap(),
+        "run_test",
+        &[],
+        &mut logs,
+        Some(&mut wasm_gas_profile),
+        &MockEpochInfoProvider::default(),
+    );
+
+    assert_eq!(result.unwrap(), (10i32).to_le_bytes());
+    assert!(!wasm_gas_profile.is_empty());
+    for line in wasm_gas_profile.lines() {
+        let (_stack, gas) = line.rsplit_once(' ').unwrap();
+        assert!(gas.parse::<u64>().unwrap() > 0, "{line}");
+    }
+}
+
 #[test]
 fn test_view_call_try_changing_storage() {
     let (viewer, root) = get_test_trie_viewer();
@@ -152,6 +189,7 @@ fn test_view_call_try_changing_storage() {
         "run_test_with_storage_change",
         &[],
         &mut logs,
+        None,
         &MockEpochInfoProvider::default(),
     );
     let err = result.unwrap_err();
@@ -185,6 +223,7 @@ fn test_view_call_with_args() {
         "sum_with_input",
         &args,
         &mut logs,
+        None,
         &MockEpochInfoProvider::default(),
     );
     assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
@@ -408,6 +447,7 @@ fn test_log_when_panic() {
             "panic_after_logging",
             &[],
             &mut logs,
+            None,
             &MockEpochInfoProvider::default(),
         )
         .unwrap_err();
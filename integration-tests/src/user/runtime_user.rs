@@ -346,6 +346,7 @@ impl User for RuntimeUser {
                 method_name,
                 args,
                 &mut result.logs,
+                None,
                 &self.epoch_info_provider,
             )
             .map_err(|err| err.to_string())?;
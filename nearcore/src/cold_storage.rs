@@ -10,6 +10,7 @@ use near_store::{
         copy_all_data_to_cold, get_cold_head, update_cold_db, update_cold_head,
         CopyAllDataToColdStatus,
     },
+    archive::state_history::update_state_history,
     db::ColdDB,
     DBCol, NodeStorage, Store, FINAL_HEAD_KEY, TAIL_KEY,
 };
@@ -90,6 +91,7 @@ fn cold_store_copy(
     genesis_height: BlockHeight,
     epoch_manager: &EpochManagerHandle,
     num_threads: usize,
+    enable_state_history_index: bool,
 ) -> anyhow::Result<ColdStoreCopyResult, ColdStoreError> {
     // If HEAD is not set for cold storage we default it to genesis_height.
     let cold_head = get_cold_head(cold_db)?;
@@ -144,6 +146,9 @@ fn cold_store_copy(
         is_last_block_in_epoch,
         num_threads,
     )?;
+    if enable_state_history_index {
+        update_state_history(cold_db, hot_store, cold_head_height, next_height)?;
+    }
     update_cold_head(cold_db, hot_store, &next_height)?;
 
     let result = if next_height >= hot_final_head_height {
@@ -382,6 +387,7 @@ fn cold_store_loop(
             genesis_height,
             epoch_manager,
             split_storage_config.num_cold_store_read_threads,
+            split_storage_config.enable_state_history_index,
         );
         let duration = instant.elapsed();
 
@@ -17,17 +17,19 @@ use near_chain_configs::{
     default_state_sync_enabled, default_state_sync_external_backoff,
     default_state_sync_external_timeout, default_state_sync_p2p_timeout,
     default_state_sync_retry_backoff, default_sync_check_period, default_sync_height_threshold,
-    default_sync_max_block_requests, default_sync_step_period, default_transaction_pool_size_limit,
+    default_sync_max_block_requests, default_sync_step_period,
+    default_transaction_pool_save_period, default_transaction_pool_size_limit,
     default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
     default_view_client_threads, default_view_client_throttle_period, get_initial_supply,
     ChunkDistributionNetworkConfig, ClientConfig, EpochSyncConfig, GCConfig, Genesis,
     GenesisConfig, GenesisValidationMode, LogSummaryStyle, MutableConfigValue,
-    MutableValidatorSigner, ReshardingConfig, StateSyncConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD,
-    CHUNK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD,
-    EXPECTED_EPOCH_LENGTH, FAST_EPOCH_LENGTH, FISHERMEN_THRESHOLD, GAS_PRICE_ADJUSTMENT_RATE,
-    GENESIS_CONFIG_FILENAME, INITIAL_GAS_LIMIT, MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY,
-    MIN_GAS_PRICE, NEAR_BASE, NUM_BLOCKS_PER_YEAR, NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE,
-    PROTOCOL_UPGRADE_STAKE_THRESHOLD, TRANSACTION_VALIDITY_PERIOD,
+    MutableValidatorSigner, ReshardingConfig, ScrubberConfig, StateSyncConfig,
+    TransactionPoolOrdering, BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
+    CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD, EXPECTED_EPOCH_LENGTH, FAST_EPOCH_LENGTH,
+    FISHERMEN_THRESHOLD, GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME, INITIAL_GAS_LIMIT,
+    MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE, NUM_BLOCKS_PER_YEAR,
+    NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE, PROTOCOL_UPGRADE_STAKE_THRESHOLD,
+    TRANSACTION_VALIDITY_PERIOD,
 };
 use near_config_utils::{DownloadConfigType, ValidationError, ValidationErrors};
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
@@ -37,6 +39,8 @@ use near_jsonrpc::RpcConfig;
 use near_network::config::NetworkConfig;
 use near_network::tcp;
 use near_o11y::log_config::LogConfig;
+use near_o11y::metrics_push::MetricsPushConfig;
+use near_parameters::RuntimeConfigStore;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::test_utils::create_test_signer;
@@ -237,6 +241,10 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rosetta_rpc: Option<RosettaRpcConfig>,
     pub telemetry: TelemetryConfig,
+    /// Pushes metrics to an external collector, for nodes which can't be scraped through the
+    /// `/metrics` endpoint of the JSON RPC server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_push: Option<MetricsPushConfig>,
     pub network: near_network::config_json::Config,
     pub consensus: Consensus,
     pub tracked_accounts: Vec<AccountId>,
@@ -262,6 +270,8 @@ pub struct Config {
     /// Garbage collection configuration.
     #[serde(flatten)]
     pub gc: GCConfig,
+    /// Configuration of the background data scrubber.
+    pub scrubber: ScrubberConfig,
     pub view_client_threads: usize,
     #[serde(with = "near_async::time::serde_duration_as_std")]
     pub view_client_throttle_period: Duration,
@@ -298,7 +308,20 @@ pub struct Config {
     /// guarantees that the node will use bounded resources to store incoming transactions.
     /// Setting this value too low (<1MB) on the validator might lead to production of smaller
     /// chunks and underutilizing the capacity of the network.
+    ///
+    /// With the `priority_fee` ordering transactions with the lowest priorities are evicted from
+    /// a full pool instead.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Order in which transactions are taken from the transaction pool when producing chunks,
+    /// either `round_robin` (default) or `priority_fee`.
+    pub transaction_pool_ordering: TransactionPoolOrdering,
+    /// Whether to save the transaction pool to the database periodically and on shutdown, and
+    /// restore it on startup.  Restored transactions which have been included into the chain,
+    /// have expired or are no longer valid are dropped.
+    pub save_transaction_pool: bool,
+    /// How often the transaction pool is saved to the database.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub transaction_pool_save_period: Duration,
     // Configuration for resharding.
     pub resharding_config: ReshardingConfig,
     /// If the node is not a chunk producer within that many blocks, then route
@@ -339,6 +362,12 @@ pub struct Config {
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// Whether to maintain the index of transactions and receipts which touched each account,
+    /// served by the `EXPERIMENTAL_account_history` and `EXPERIMENTAL_access_key_history` JSON
+    /// RPC methods.  Blocks processed before the index has been enabled can be indexed with
+    /// `neard database backfill-account-activity`.  Disabling the index deletes it from the hot
+    /// database on the next start.
+    pub save_account_activity: bool,
 }
 
 fn is_false(value: &bool) -> bool {
@@ -356,6 +385,7 @@ impl Default for Config {
             #[cfg(feature = "rosetta_rpc")]
             rosetta_rpc: None,
             telemetry: TelemetryConfig::default(),
+            metrics_push: None,
             network: Default::default(),
             consensus: Consensus::default(),
             tracked_accounts: vec![],
@@ -367,6 +397,7 @@ impl Default for Config {
             log_summary_style: LogSummaryStyle::Colored,
             log_summary_period: default_log_summary_period(),
             gc: GCConfig::default(),
+            scrubber: ScrubberConfig::default(),
             view_client_threads: default_view_client_threads(),
             view_client_throttle_period: default_view_client_throttle_period(),
             trie_viewer_state_size_limit: default_trie_viewer_state_size_limit(),
@@ -380,6 +411,9 @@ impl Default for Config {
             epoch_sync: default_epoch_sync(),
             state_sync_enabled: default_state_sync_enabled(),
             transaction_pool_size_limit: default_transaction_pool_size_limit(),
+            transaction_pool_ordering: TransactionPoolOrdering::default(),
+            save_transaction_pool: true,
+            transaction_pool_save_period: default_transaction_pool_save_period(),
             enable_multiline_logging: default_enable_multiline_logging(),
             resharding_config: ReshardingConfig::default(),
             tx_routing_height_horizon: default_tx_routing_height_horizon(),
@@ -390,6 +424,7 @@ impl Default for Config {
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
             max_loaded_contracts: 256,
             save_latest_witnesses: false,
+            save_account_activity: false,
         }
     }
 }
@@ -548,6 +583,7 @@ impl NearConfig {
                 save_trie_changes: config.save_trie_changes.unwrap_or(!config.archive),
                 log_summary_style: config.log_summary_style,
                 gc: config.gc,
+                scrubber: config.scrubber,
                 view_client_threads: config.view_client_threads,
                 view_client_throttle_period: config.view_client_throttle_period,
                 trie_viewer_state_size_limit: config.trie_viewer_state_size_limit,
@@ -558,6 +594,9 @@ impl NearConfig {
                 state_sync: config.state_sync.unwrap_or_default(),
                 epoch_sync: config.epoch_sync.unwrap_or_default(),
                 transaction_pool_size_limit: config.transaction_pool_size_limit,
+                transaction_pool_ordering: config.transaction_pool_ordering,
+                save_transaction_pool: config.save_transaction_pool,
+                transaction_pool_save_period: config.transaction_pool_save_period,
                 enable_multiline_logging: config.enable_multiline_logging.unwrap_or(true),
                 resharding_config: MutableConfigValue::new(
                     config.resharding_config,
@@ -572,6 +611,7 @@ impl NearConfig {
                 orphan_state_witness_pool_size: config.orphan_state_witness_pool_size,
                 orphan_state_witness_max_size: config.orphan_state_witness_max_size,
                 save_latest_witnesses: config.save_latest_witnesses,
+                save_account_activity: config.save_account_activity,
             },
             network_config: NetworkConfig::new(
                 config.network,
@@ -631,6 +671,18 @@ impl NightshadeRuntime {
         store: Store,
         config: &NearConfig,
         epoch_manager: Arc<EpochManagerHandle>,
+    ) -> std::io::Result<Arc<NightshadeRuntime>> {
+        Self::from_config_with_runtime_config_store(home_dir, store, config, epoch_manager, None)
+    }
+
+    /// Like `from_config`, but with the given runtime configs instead of the ones for the chain
+    /// of the genesis, if any.
+    pub fn from_config_with_runtime_config_store(
+        home_dir: &Path,
+        store: Store,
+        config: &NearConfig,
+        epoch_manager: Arc<EpochManagerHandle>,
+        runtime_config_store: Option<RuntimeConfigStore>,
     ) -> std::io::Result<Arc<NightshadeRuntime>> {
         // TODO (#9989): directly use the new state snapshot config once the migration is done.
         let mut state_snapshot_type =
@@ -638,15 +690,15 @@ impl NightshadeRuntime {
         if config.config.store.state_snapshot_enabled {
             state_snapshot_type = StateSnapshotType::EveryEpoch;
         }
+        let hot_store_path =
+            config.config.store.path.clone().unwrap_or_else(|| PathBuf::from("data"));
+        let mut trie_config = TrieConfig::from_store_config(&config.config.store);
+        trie_config.mem_trie_snapshot_dir =
+            config.config.store.mem_trie_snapshot.get_path(&home_dir.join(&hot_store_path));
         let state_snapshot_config = StateSnapshotConfig {
             state_snapshot_type,
             home_dir: home_dir.to_path_buf(),
-            hot_store_path: config
-                .config
-                .store
-                .path
-                .clone()
-                .unwrap_or_else(|| PathBuf::from("data")),
+            hot_store_path,
             state_snapshot_subdir: PathBuf::from("state_snapshot"),
         };
         // FIXME: this (and other contract runtime resources) should probably get constructed by
@@ -664,10 +716,15 @@ impl NightshadeRuntime {
             epoch_manager,
             config.client_config.trie_viewer_state_size_limit,
             config.client_config.max_gas_burnt_view,
-            None,
+            runtime_config_store,
             config.config.gc.gc_num_epochs_to_keep(),
-            TrieConfig::from_store_config(&config.config.store),
+            trie_config,
             state_snapshot_config,
+            config
+                .config
+                .split_storage
+                .as_ref()
+                .is_some_and(|split_storage| split_storage.enable_state_history_index),
         ))
     }
 }
@@ -1382,7 +1439,7 @@ pub fn load_config(
     let mut validation_errors = ValidationErrors::new();
 
     // if config.json has file issues, the program will directly panic
-    let config = Config::from_file_skip_validation(&dir.join(CONFIG_FILENAME))?;
+    let mut config = Config::from_file_skip_validation(&dir.join(CONFIG_FILENAME))?;
     // do config.json validation later so that genesis_file, validator_file and genesis_file can be validated before program panic
     if let Err(e) = config.validate() {
         validation_errors.push_errors(e)
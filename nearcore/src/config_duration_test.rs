@@ -5,6 +5,7 @@ use near_jsonrpc::RpcConfig;
 use near_network::config_json::{ExperimentalConfig, NetworkConfigOverrides};
 use near_o11y::testonly::init_test_logger;
 use near_primitives::types::AccountId;
+use near_store::config::DbBackupConfig;
 use near_store::StoreConfig;
 use serde::ser::{
     SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
@@ -28,7 +29,15 @@ fn test_config_duration_all_std() {
     // because there are Vec's. So it's best-effort.
     let config = Config {
         chunk_distribution_network: Some(Default::default()),
-        store: StoreConfig { path: Some(Default::default()), ..Default::default() },
+        store: StoreConfig {
+            path: Some(Default::default()),
+            backup: Some(DbBackupConfig {
+                path: Default::default(),
+                period: near_async::time::Duration::hours(1),
+                max_backups: 1,
+            }),
+            ..Default::default()
+        },
         cold_store: Some(StoreConfig { path: Some(Default::default()), ..Default::default() }),
         enable_multiline_logging: Some(Default::default()),
         expected_shutdown: Some(Default::default()),
@@ -65,6 +74,9 @@ fn test_config_duration_all_std() {
                     received_messages_rate_limits: Some(
                         near_network::MessagesLimitsOverrideConfig::default(),
                     ),
+                    received_compressed_messages_rate_limits: Some(
+                        near_network::MessagesLimitsOverrideConfig::default(),
+                    ),
                 },
                 ..Default::default()
             },
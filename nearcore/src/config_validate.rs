@@ -79,6 +79,64 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        let scrubber = &self.config.scrubber;
+        if scrubber.enabled {
+            if scrubber.step_period <= near_async::time::Duration::ZERO
+                || scrubber.max_entries_per_step == 0
+            {
+                let error_message = format!("scrubber.step_period and scrubber.max_entries_per_step should be greater than 0, but they are {} and {}.", scrubber.step_period, scrubber.max_entries_per_step);
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            let has_external_storage = self.config.state_sync.as_ref().is_some_and(|state_sync| {
+                matches!(state_sync.sync, SyncConfig::ExternalStorage(_))
+            });
+            if scrubber.repair_state_from_external_storage && !has_external_storage {
+                let error_message = "'config.scrubber.repair_state_from_external_storage' requires 'config.state_sync.sync' to be set to external storage.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        if let Some(rpc) = &self.config.rpc {
+            let relayer_config = &rpc.relayer_config;
+            if relayer_config.enabled && relayer_config.allowed_receivers.is_empty() {
+                let error_message = "'config.rpc.relayer_config.allowed_receivers' needs to be specified when the relayer is enabled.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        if let Some(metrics_push) = &self.config.metrics_push {
+            if metrics_push.period <= near_async::time::Duration::ZERO {
+                let error_message = format!(
+                    "'config.metrics_push.period' should be positive, but is {:?}.",
+                    metrics_push.period
+                );
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        for (col, overrides) in &self.config.store.column_overrides {
+            if overrides.block_size.is_some_and(|size| size.as_u64() == 0)
+                || overrides.bloom_filter_bits_per_key.is_some_and(|bits| !(bits >= 0.0))
+            {
+                let error_message = format!("'config.store.column_overrides.{col}' should have a positive block_size and a non-negative bloom_filter_bits_per_key, but are {:?} and {:?}.", overrides.block_size, overrides.bloom_filter_bits_per_key);
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        if let Some(backup) = &self.config.store.backup {
+            if backup.period <= near_async::time::Duration::ZERO || backup.max_backups == 0 {
+                let error_message = format!(
+                    "'config.store.backup.period' and 'config.store.backup.max_backups' should be positive, but are {:?} and {}.",
+                    backup.period, backup.max_backups
+                );
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if self.config.cold_store.is_some() {
+                let error_message = "'config.store.backup' is not supported with split storage ('config.cold_store').".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
         if let Some(state_sync) = &self.config.state_sync {
             if let Some(dump_config) = &state_sync.dump {
                 if let Some(restart_dump_for_shards) = &dump_config.restart_dump_for_shards {
@@ -108,6 +166,10 @@ impl<'a> ConfigValidator<'a> {
                             self.validation_errors.push_config_semantics_error(error_message);
                         }
                     }
+                    ExternalStorageLocation::Http { .. } => {
+                        let error_message = format!("'config.state_sync.dump.location.Http' is read-only and can't be used for dumping state.");
+                        self.validation_errors.push_config_semantics_error(error_message);
+                    }
                 }
 
                 if let Some(credentials_file) = &dump_config.credentials_file {
@@ -139,6 +201,12 @@ impl<'a> ConfigValidator<'a> {
                                 self.validation_errors.push_config_semantics_error(error_message);
                             }
                         }
+                        ExternalStorageLocation::Http { base_url } => {
+                            if base_url.is_empty() {
+                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.Http.base_url' needs to be specified when 'config.state_sync.sync.ExternalStorage.location.Http' is present.");
+                                self.validation_errors.push_config_semantics_error(error_message);
+                            }
+                        }
                     }
                     if config.num_concurrent_requests == 0 {
                         let error_message = format!("'config.state_sync.sync.ExternalStorage.num_concurrent_requests' needs to be greater than 0");
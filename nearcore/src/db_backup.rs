@@ -0,0 +1,96 @@
+use near_async::time::{Clock, Duration};
+use near_store::config::DbBackupConfig;
+use near_store::{NodeStorage, Store};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// How often the backup loop checks whether it should stop.
+const STOP_CHECK_INTERVAL: Duration = Duration::seconds(1);
+
+/// A handle that keeps the state of the database backup loop and can be used
+/// to stop it.
+pub struct DbBackupLoopHandle {
+    join_handle: std::thread::JoinHandle<()>,
+    keep_going: Arc<AtomicBool>,
+}
+
+impl DbBackupLoopHandle {
+    /// Stops the loop.  Waits for the backup in progress, if any, to finish.
+    pub fn stop(self) {
+        self.keep_going.store(false, Ordering::Relaxed);
+        match self.join_handle.join() {
+            Ok(_) => {
+                tracing::debug!(target: "db_backup", "Joined the database backup loop thread");
+            }
+            Err(_) => {
+                tracing::error!(target: "db_backup", "Failed to join the database backup loop");
+            }
+        }
+    }
+}
+
+fn db_backup_loop(
+    clock: Clock,
+    config: DbBackupConfig,
+    backup_dir: PathBuf,
+    keep_going: &AtomicBool,
+    store: Store,
+) {
+    // Take the first backup one period after the start rather than right
+    // away, so that restarting the node doesn't trigger a backup each time.
+    let mut next_backup = clock.now() + config.period;
+    while keep_going.load(Ordering::Relaxed) {
+        let now = clock.now();
+        if now < next_backup {
+            std::thread::sleep(STOP_CHECK_INTERVAL.min(next_backup - now).unsigned_abs());
+            continue;
+        }
+        next_backup = now + config.period;
+        match near_store::backup::create_backup(&store, &backup_dir, config.max_backups) {
+            Ok(metadata) => tracing::info!(
+                target: "db_backup",
+                backup_id = metadata.backup_id,
+                head_height = metadata.head_height,
+                took = %(clock.now() - now),
+                "Database backup finished"
+            ),
+            Err(err) => tracing::error!(target: "db_backup", ?err, "Database backup failed"),
+        }
+    }
+}
+
+/// Spawns the loop taking periodic backups of the hot database in
+/// a background thread.  Returns `None` if backups are not configured.
+///
+/// Like the cold store loop, the backup loop runs in a native thread because
+/// backups are long, blocking RocksDB operations.
+pub fn spawn_db_backup_loop(
+    home_dir: &Path,
+    config: &near_store::StoreConfig,
+    storage: &NodeStorage,
+) -> anyhow::Result<Option<DbBackupLoopHandle>> {
+    let Some(config) = config.backup.clone() else {
+        return Ok(None);
+    };
+    anyhow::ensure!(
+        storage.get_cold_store().is_none(),
+        "database backups are not supported with split storage"
+    );
+    let backup_dir = config.get_path(home_dir);
+    tracing::info!(
+        target: "db_backup",
+        backup_dir = %backup_dir.display(),
+        period = %config.period,
+        "Spawning the database backup loop"
+    );
+
+    let store = storage.get_hot_store();
+    let keep_going = Arc::new(AtomicBool::new(true));
+    let keep_going_clone = keep_going.clone();
+    let join_handle =
+        std::thread::Builder::new().name("db_backup".to_string()).spawn(move || {
+            db_backup_loop(Clock::real(), config, backup_dir, &keep_going_clone, store)
+        })?;
+    Ok(Some(DbBackupLoopHandle { join_handle, keep_going }))
+}
@@ -2,7 +2,7 @@ pub use crate::config::NightshadeRuntimeExt;
 pub use crate::config::{init_configs, load_config, load_test_config, NearConfig};
 #[cfg(feature = "json_rpc")]
 use crate::entity_debug::EntityDebugHandlerImpl;
-use crate::metrics::spawn_trie_metrics_loop;
+use crate::metrics::{spawn_metrics_push_loop, spawn_trie_metrics_loop};
 
 use crate::cold_storage::spawn_cold_store_loop;
 use crate::state_sync::StateSyncDumper;
@@ -10,6 +10,7 @@ use actix::{Actor, Addr};
 use actix_rt::ArbiterHandle;
 use anyhow::Context;
 use cold_storage::ColdStoreLoopHandle;
+use db_backup::{spawn_db_backup_loop, DbBackupLoopHandle};
 use near_async::actix::AddrWithAutoSpanContextExt;
 use near_async::actix_wrapper::{spawn_actix_actor, ActixWrapper};
 use near_async::futures::TokioRuntimeFutureSpawner;
@@ -27,6 +28,7 @@ use near_chain_configs::ReshardingHandle;
 use near_chunks::shards_manager_actor::start_shards_manager;
 use near_client::adapter::client_sender_for_network;
 use near_client::gc_actor::GCActor;
+use near_client::scrubber_actor::ScrubberActor;
 use near_client::{
     start_client, ClientActor, ConfigUpdater, PartialWitnessActor, StartClientResult,
     ViewClientActor, ViewClientActorInner,
@@ -40,7 +42,7 @@ use near_primitives::types::EpochId;
 use near_store::genesis::initialize_sharded_genesis_state;
 use near_store::metadata::DbKind;
 use near_store::metrics::spawn_db_metrics_loop;
-use near_store::{NodeStorage, Store, StoreOpenerError};
+use near_store::{NodeStorage, ShardTries, Store, StoreOpenerError};
 use near_telemetry::TelemetryActor;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -52,6 +54,7 @@ pub mod config;
 #[cfg(test)]
 mod config_duration_test;
 mod config_validate;
+pub mod db_backup;
 mod download_file;
 pub mod dyn_config;
 #[cfg(feature = "json_rpc")]
@@ -218,6 +221,9 @@ pub struct NearNode {
     /// The cold_store_loop_handle will only be set if the cold store is configured.
     /// It's a handle to a background thread that copies data from the hot store to the cold store.
     pub cold_store_loop_handle: Option<ColdStoreLoopHandle>,
+    /// Handle to a background thread taking periodic backups of the database,
+    /// set only if backups are configured.
+    pub db_backup_loop_handle: Option<DbBackupLoopHandle>,
     /// Contains handles to background threads that may be dumping state to S3.
     pub state_sync_dumper: StateSyncDumper,
     // A handle that allows the main process to interrupt resharding if needed.
@@ -227,6 +233,8 @@ pub struct NearNode {
     pub state_sync_runtime: Arc<tokio::runtime::Runtime>,
     /// Shard tracker, allows querying of which shards are tracked by this node.
     pub shard_tracker: ShardTracker,
+    /// Tries of the node, used to save memtrie snapshots on shutdown.
+    pub shard_tries: ShardTries,
 }
 
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> anyhow::Result<NearNode> {
@@ -255,6 +263,7 @@ pub fn start_with_config_and_synchronization(
         storage.get_hot_store(),
         config.client_config.log_summary_period,
     )?;
+    let metrics_push_arbiter = spawn_metrics_push_loop(&config)?;
 
     let epoch_manager = EpochManager::new_arc_handle(
         storage.get_hot_store(),
@@ -281,6 +290,7 @@ pub fn start_with_config_and_synchronization(
         epoch_manager.clone(),
     )
     .context("could not create the transaction runtime")?;
+    let shard_tries = runtime.get_tries();
 
     // Get the split store. If split store is some then create a new set of structures for
     // the view client. Otherwise just re-use the existing ones.
@@ -309,6 +319,7 @@ pub fn start_with_config_and_synchronization(
         };
 
     let cold_store_loop_handle = spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?;
+    let db_backup_loop_handle = spawn_db_backup_loop(home_dir, &config.config.store, &storage)?;
 
     let telemetry = ActixWrapper::new(TelemetryActor::new(config.telemetry_config.clone())).start();
     let chain_genesis = ChainGenesis::new(&config.genesis.config);
@@ -383,11 +394,24 @@ pub fn start_with_config_and_synchronization(
         config.client_config.archive,
     ));
 
+    let state_sync_runtime =
+        Arc::new(tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap());
+
+    let (_scrubber_actor, scrubber_arbiter) = spawn_actix_actor(ScrubberActor::new(
+        Clock::real(),
+        runtime.store().clone(),
+        chain_genesis.height,
+        epoch_manager.clone(),
+        runtime.get_flat_storage_manager(),
+        config.client_config.scrubber.clone(),
+        config.client_config.chain_id.clone(),
+        &config.client_config.state_sync,
+        Arc::new(TokioRuntimeFutureSpawner(state_sync_runtime.clone())),
+    ));
+
     let (resharding_sender_addr, _) =
         spawn_actix_actor(ReshardingActor::new(runtime.store().clone(), chain_genesis.height));
     let resharding_sender = resharding_sender_addr.with_auto_span_context();
-    let state_sync_runtime =
-        Arc::new(tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap());
 
     let StartClientResult { client_actor, client_arbiter_handle, resharding_handle } = start_client(
         Clock::real(),
@@ -464,10 +488,12 @@ pub fn start_with_config_and_synchronization(
         };
         rpc_servers.extend(near_jsonrpc::start_http(
             rpc_config,
+            home_dir,
             config.genesis.config.clone(),
             client_actor.clone().with_auto_span_context().into_multi_sender(),
             view_client_addr.clone().with_auto_span_context().into_multi_sender(),
             network_actor.into_multi_sender(),
+            _scrubber_actor.with_auto_span_context().into_multi_sender(),
             #[cfg(feature = "test_features")]
             _gc_actor.with_auto_span_context().into_multi_sender(),
             Arc::new(entity_debug_handler),
@@ -498,11 +524,15 @@ pub fn start_with_config_and_synchronization(
         trie_metrics_arbiter,
         state_snapshot_arbiter,
         gc_arbiter,
+        scrubber_arbiter,
         partial_witness_arbiter,
     ];
     if let Some(db_metrics_arbiter) = db_metrics_arbiter {
         arbiters.push(db_metrics_arbiter);
     }
+    if let Some(metrics_push_arbiter) = metrics_push_arbiter {
+        arbiters.push(metrics_push_arbiter);
+    }
 
     Ok(NearNode {
         client: client_actor,
@@ -510,9 +540,11 @@ pub fn start_with_config_and_synchronization(
         rpc_servers,
         arbiters,
         cold_store_loop_handle,
+        db_backup_loop_handle,
         state_sync_dumper,
         resharding_handle,
         state_sync_runtime,
         shard_tracker,
+        shard_tries,
     })
 }
@@ -8,6 +8,7 @@ use near_o11y::metrics::{
     try_create_int_gauge, try_create_int_gauge_vec, HistogramVec, IntCounterVec, IntGauge,
     IntGaugeVec,
 };
+use near_o11y::metrics_push::MetricsPusher;
 use near_primitives::types::ShardId;
 use near_primitives::{shard_layout::ShardLayout, state_record::StateRecord, trie_key};
 use near_store::adapter::StoreAdapter;
@@ -219,6 +220,39 @@ pub fn spawn_trie_metrics_loop(
     Ok(arbiter.handle())
 }
 
+/// Spawns the loop pushing metrics to an external collector if configured.
+pub fn spawn_metrics_push_loop(near_config: &NearConfig) -> anyhow::Result<Option<ArbiterHandle>> {
+    let Some(config) = near_config.config.metrics_push.clone() else {
+        return Ok(None);
+    };
+    tracing::info!(
+        target: "metrics",
+        endpoint = %config.endpoint,
+        protocol = ?config.protocol,
+        "Pushing metrics"
+    );
+    let pusher = MetricsPusher::new(
+        config,
+        near_config.client_config.chain_id.clone(),
+        near_config.network_config.node_key.public_key(),
+        near_config.network_config.validator.account_id(),
+    )?;
+    let arbiter = actix_rt::Arbiter::new();
+    let mut interval = actix_rt::time::interval(pusher.period().unsigned_abs());
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    arbiter.spawn(async move {
+        loop {
+            interval.tick().await;
+            if let Err(err) = pusher.push().await {
+                tracing::warn!(target: "metrics", %err, "Failed to push metrics");
+            }
+        }
+    });
+
+    Ok(Some(arbiter.handle()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,11 +93,11 @@ impl StateSyncDumper {
         tracing::info!(target: "state_sync_dump", "Spawning the state sync dump loop");
 
         let external = match dump_config.location {
-            ExternalStorageLocation::S3 { bucket, region } => ExternalConnection::S3 {
-                bucket: Arc::new(create_bucket_readwrite(&bucket, &region, std::time::Duration::from_secs(30), dump_config.credentials_file).expect(
-                    "Failed to authenticate connection to S3. Please either provide AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY in the environment, or create a credentials file and link it in config.json as 's3_credentials_file'."))
-            },
-            ExternalStorageLocation::Filesystem { root_dir } => ExternalConnection::Filesystem { root_dir },
+            ExternalStorageLocation::S3 { bucket, region } => ExternalConnection::s3(
+                create_bucket_readwrite(&bucket, &region, std::time::Duration::from_secs(30), dump_config.credentials_file).expect(
+                    "Failed to authenticate connection to S3. Please either provide AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY in the environment, or create a credentials file and link it in config.json as 's3_credentials_file'.")
+            ),
+            ExternalStorageLocation::Filesystem { root_dir } => ExternalConnection::filesystem(root_dir),
             ExternalStorageLocation::GCS { bucket } => {
                 if let Some(credentials_file) = dump_config.credentials_file {
                     if let Ok(var) = std::env::var("SERVICE_ACCOUNT") {
@@ -107,11 +107,10 @@ impl StateSyncDumper {
                     std::env::set_var("SERVICE_ACCOUNT", &credentials_file);
                     tracing::info!(target: "state_sync_dump", "Set the environment variable 'SERVICE_ACCOUNT' to '{credentials_file:?}'");
                 }
-                ExternalConnection::GCS {
-                    gcs_client: Arc::new(cloud_storage::Client::default()),
-                    reqwest_client: Arc::new(reqwest::Client::default()),
-                    bucket,
-                }
+                ExternalConnection::gcs(bucket)
+            },
+            ExternalStorageLocation::Http { .. } => {
+                anyhow::bail!("HTTP external storage is read-only and can't be used for dumping state");
             },
         };
 
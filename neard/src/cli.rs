@@ -2,7 +2,7 @@
 use anyhow::Context;
 use near_amend_genesis::AmendGenesisCommand;
 use near_chain_configs::GenesisValidationMode;
-use near_client::ConfigUpdater;
+use near_client::{ConfigUpdater, SaveTransactionPool};
 use near_cold_store_tool::ColdStoreCommand;
 use near_config_utils::DownloadConfigType;
 use near_database_tool::commands::DatabaseCommand;
@@ -15,7 +15,7 @@ use near_network::tcp;
 use near_o11y::tracing_subscriber::EnvFilter;
 use near_o11y::{
     default_subscriber, default_subscriber_with_opentelemetry, BuildEnvFilterError,
-    EnvFilterBuilder,
+    EnvFilterBuilder, WithSpanContextExt,
 };
 use near_ping::PingCommand;
 use near_primitives::hash::CryptoHash;
@@ -530,7 +530,7 @@ impl RunCmd {
             broadcast::channel::<Result<UpdateableConfigs, Arc<UpdateableConfigLoaderError>>>(16);
         let sys = actix::System::new();
 
-        sys.block_on(async move {
+        let shard_tries = sys.block_on(async move {
             // Initialize the subscriber that takes care of both logging and tracing.
             let _subscriber_guard = default_subscriber_with_opentelemetry(
                 make_env_filter(verbose_target).unwrap(),
@@ -549,10 +549,13 @@ impl RunCmd {
             let config_updater = ConfigUpdater::new(rx_config_update);
 
             let nearcore::NearNode {
+                client,
                 rpc_servers,
                 cold_store_loop_handle,
+                db_backup_loop_handle,
                 mut state_sync_dumper,
                 resharding_handle,
+                shard_tries,
                 ..
             } = nearcore::start_with_config_and_synchronization(
                 home_dir,
@@ -573,9 +576,15 @@ impl RunCmd {
                 }
             };
             warn!(target: "neard", "{}, stopping... this may take a few minutes.", sig);
+            if let Err(err) = client.send(SaveTransactionPool.with_span_context()).await {
+                warn!(target: "neard", ?err, "failed to save the transaction pool");
+            }
             if let Some(handle) = cold_store_loop_handle {
                 handle.stop()
             }
+            if let Some(handle) = db_backup_loop_handle {
+                handle.stop()
+            }
             state_sync_dumper.stop();
             resharding_handle.stop();
             futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
@@ -586,8 +595,12 @@ impl RunCmd {
             actix::System::current().stop();
             // Disable the subscriber to properly shutdown the tracer.
             near_o11y::reload(Some("error"), None, Some("off")).unwrap();
+            shard_tries
         });
         sys.run().unwrap();
+        // The memtries are locked while they are saved, so they are saved once
+        // the actors have stopped and no chunks are applied anymore.
+        shard_tries.save_mem_trie_snapshots();
         info!(target: "neard", "Waiting for RocksDB to gracefully shutdown");
         RocksDB::block_until_all_instances_are_dropped();
     }
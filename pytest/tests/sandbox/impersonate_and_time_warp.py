@@ -0,0 +1,71 @@
+#!/usr/bin/env python3
+# Send a transaction of an impersonated account which is not signed by any of
+# its keys, and control the timestamps of the blocks of a sandbox node.
+
+import sys, time
+import pathlib
+
+sys.path.append(str(pathlib.Path(__file__).resolve().parents[2] / 'lib'))
+
+import utils
+from cluster import start_cluster
+from key import Key
+from transaction import sign_payment_tx
+
+CONFIG = utils.figure_out_sandbox_binary()
+YEAR_NANOSEC = 365 * 24 * 60 * 60 * 10**9
+
+# start node
+nodes = start_cluster(1, 0, 1, CONFIG, [["epoch_length", 10]], {})
+time.sleep(3)
+
+# a key test0 doesn't have
+key = Key.from_random(nodes[0].signer_key.account_id)
+
+hash_ = nodes[0].get_latest_block().hash_bytes
+tx = sign_payment_tx(key, 'test0', 100, 1, hash_)
+res = nodes[0].send_tx_and_wait(tx, 20)
+assert ('error' in res), res
+
+res = nodes[0].json_rpc('sandbox_impersonate_account',
+                        {"account_ids": ["test0"]})
+assert ('error' not in res), res
+res = nodes[0].send_tx_and_wait(tx, 20)
+assert ('SuccessValue' in res['result']['status']), res
+
+res = nodes[0].json_rpc('sandbox_impersonate_account', {"account_ids": []})
+assert ('error' not in res), res
+tx = sign_payment_tx(key, 'test0', 100, 2, hash_)
+res = nodes[0].send_tx_and_wait(tx, 20)
+assert ('error' in res), res
+
+# set the timestamp of the next block
+start = nodes[0].get_latest_block()
+start_timestamp = int(
+    nodes[0].get_block(start.hash)['result']['header']['timestamp_nanosec'])
+timestamp = start_timestamp + YEAR_NANOSEC
+res = nodes[0].json_rpc('sandbox_set_next_block_timestamp',
+                        {"timestamp_nanosec": timestamp})
+assert ('error' not in res), res
+time.sleep(3)
+
+timestamps = []
+for height in range(start.height + 1, nodes[0].get_latest_block().height + 1):
+    block = nodes[0].get_block_by_height(height)
+    if 'result' in block:
+        timestamps.append(int(block['result']['header']['timestamp_nanosec']))
+assert (timestamp in timestamps), timestamps
+assert (timestamps[-1] > timestamp), timestamps
+
+# warp the time forward
+res = nodes[0].json_rpc('sandbox_time_warp', {"delta_nanosec": YEAR_NANOSEC})
+assert ('error' not in res), res
+time.sleep(3)
+block = nodes[0].get_block(nodes[0].get_latest_block().hash)
+assert (int(block['result']['header']['timestamp_nanosec']) >
+        timestamp + YEAR_NANOSEC)
+
+# timestamps can't go back
+res = nodes[0].json_rpc('sandbox_set_next_block_timestamp',
+                        {"timestamp_nanosec": start_timestamp})
+assert ('error' in res), res
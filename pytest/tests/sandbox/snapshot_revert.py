@@ -0,0 +1,77 @@
+#!/usr/bin/env python3
+# Take a snapshot of a sandbox node, change the state of a contract and revert
+# the node to the snapshot.
+
+import sys, time
+import base64
+import pathlib
+
+sys.path.append(str(pathlib.Path(__file__).resolve().parents[2] / 'lib'))
+
+import utils
+from cluster import start_cluster
+from transaction import sign_deploy_contract_tx, sign_function_call_tx
+
+CONFIG = utils.figure_out_sandbox_binary()
+
+# start node
+nodes = start_cluster(1, 0, 1, CONFIG, [["epoch_length", 10]], {})
+
+# deploy contract
+hash_ = nodes[0].get_latest_block().hash_bytes
+tx = sign_deploy_contract_tx(nodes[0].signer_key, utils.load_test_contract(),
+                             10, hash_)
+nodes[0].send_tx(tx)
+time.sleep(3)
+
+
+def write_key_value(k, v, nonce):
+    hash_ = nodes[0].get_latest_block().hash_bytes
+    tx = sign_function_call_tx(nodes[0].signer_key,
+                               nodes[0].signer_key.account_id,
+                               'write_key_value', k + v, 1000000000000, 0,
+                               nonce, hash_)
+    res = nodes[0].send_tx_and_wait(tx, 20)
+    assert ('SuccessValue' in res['result']['status'])
+
+
+def read_value(k):
+    res = nodes[0].call_function("test0", "read_value",
+                                 base64.b64encode(k).decode('ascii'))
+    return res['result']['result']
+
+
+# store a key value
+k = (10).to_bytes(8, byteorder="little")
+v = (20).to_bytes(8, byteorder="little")
+write_key_value(k, v, 20)
+assert (read_value(k) == list(v))
+
+# take a snapshot
+res = nodes[0].json_rpc('sandbox_snapshot', {})
+snapshot_id = res['result']['snapshot_id']
+snapshot_block = nodes[0].get_latest_block()
+
+# change the value after the snapshot
+new_v = (30).to_bytes(8, byteorder="little")
+write_key_value(k, new_v, 30)
+assert (read_value(k) == list(new_v))
+
+# revert to the snapshot
+res = nodes[0].json_rpc('sandbox_revert', {"snapshot_id": snapshot_id})
+assert ('error' not in res), res
+assert (read_value(k) == list(v))
+
+# blocks are produced on top of the snapshotted block
+time.sleep(3)
+block = nodes[0].get_latest_block()
+assert (block.height > snapshot_block.height)
+assert (read_value(k) == list(v))
+
+# the transaction with the same nonce can be sent again
+write_key_value(k, new_v, 30)
+assert (read_value(k) == list(new_v))
+
+# the snapshot is dropped once reverted to
+res = nodes[0].json_rpc('sandbox_revert', {"snapshot_id": snapshot_id})
+assert ('error' in res), res
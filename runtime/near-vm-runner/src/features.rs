@@ -23,16 +23,18 @@ use opts::*;
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub(crate) struct WasmFeatures {
     sign_extension: bool,
+    pub(crate) bulk_memory: bool,
 }
 
 impl From<crate::logic::ContractPrepareVersion> for WasmFeatures {
     fn from(version: crate::logic::ContractPrepareVersion) -> Self {
-        let sign_extension = match version {
-            crate::logic::ContractPrepareVersion::V0 => false,
-            crate::logic::ContractPrepareVersion::V1 => false,
-            crate::logic::ContractPrepareVersion::V2 => true,
+        let (sign_extension, bulk_memory) = match version {
+            crate::logic::ContractPrepareVersion::V0 => (false, false),
+            crate::logic::ContractPrepareVersion::V1 => (false, false),
+            crate::logic::ContractPrepareVersion::V2 => (true, false),
+            crate::logic::ContractPrepareVersion::V3 => (true, true),
         };
-        WasmFeatures { sign_extension }
+        WasmFeatures { sign_extension, bulk_memory }
     }
 }
 
@@ -47,7 +49,7 @@ impl From<WasmFeatures> for finite_wasm::wasmparser::WasmFeatures {
             reference_types: REFERENCE_TYPES,
             // wasmer singlepass compiler requires multi_value return values to be disabled.
             multi_value: MULTI_VALUE,
-            bulk_memory: BULK_MEMORY,
+            bulk_memory: f.bulk_memory,
             simd: SIMD,
             threads: THREADS,
             tail_call: TAIL_CALL,
@@ -108,7 +110,7 @@ impl From<WasmFeatures> for near_vm_types::Features {
             threads: THREADS,
             reference_types: REFERENCE_TYPES,
             simd: SIMD,
-            bulk_memory: BULK_MEMORY,
+            bulk_memory: f.bulk_memory,
             multi_value: MULTI_VALUE,
             tail_call: TAIL_CALL,
             multi_memory: MULTI_MEMORY,
@@ -91,6 +91,9 @@ imports! {
     @in internal: finite_wasm_gas<[gas: u64] -> []>,
     @in internal: finite_wasm_stack<[operand_size: u64, frame_size: u64] -> []>,
     @in internal: finite_wasm_unstack<[operand_size: u64, frame_size: u64] -> []>,
+    // Called by the WASM gas profiling instrumentation, see `instrument::profile`.
+    #[wasm_gas_profiling] @in internal: wasm_gas_profile_enter<[func_index: u64] -> []>,
+    #[wasm_gas_profiling] @in internal: wasm_gas_profile_exit<[func_index: u64] -> []>,
     // #############
     // # Registers #
     // #############
@@ -14,6 +14,8 @@
 //! something odd! See <https://github.com/near/nearcore/issues/6659> for the
 //! overall instrumentation story.
 
+pub(crate) mod bulk_memory;
 pub(crate) mod gas;
+pub(crate) mod profile;
 pub(crate) mod rules;
 pub(crate) mod stack_height;
@@ -0,0 +1,322 @@
+//! Instrumentation of wasm code for charging the bulk memory instructions by the number of bytes.
+//!
+//! finite-wasm charges every instruction a fixed `Config::regular_op_cost`, which does not
+//! account for the amount of work done by `memory.copy`, `memory.fill`, `memory.init`,
+//! `table.copy` and `table.init`. This is applied on top of the finite-wasm instrumentation and
+//! charges `len * Config::bulk_memory_byte_cost` right before each of these instructions, by
+//! calling the `internal.finite_wasm_gas` import:
+//!
+//! ```text
+//! local.tee $len
+//! i64.extend_i32_u
+//! i64.const <bulk_memory_byte_cost>
+//! i64.mul
+//! call $finite_wasm_gas
+//! local.get $len
+//! ```
+//!
+//! `$len` is a scratch `i32` local added to the functions using these instructions. The near-vm
+//! singlepass compiler charges exactly the same gas in its own codegen.
+
+use crate::logic::errors::PrepareError;
+use finite_wasm::wasmparser as wp;
+use wasm_encoder::{Encode, Instruction, Section, SectionId};
+
+const FINITE_WASM_GAS: &str = "finite_wasm_gas";
+
+struct BulkMemoryContext<'a> {
+    code: &'a [u8],
+    output_code: Vec<u8>,
+    byte_cost: u64,
+    /// Number of the parameters of the types in the type section.
+    type_params: Vec<u32>,
+    /// Type indices of the functions defined in the module.
+    function_types: Vec<u32>,
+    /// Index of the `internal.finite_wasm_gas` import.
+    gas_function: Option<u32>,
+    /// Code section being built, and the number of bodies still to be added to it.
+    code_section: Vec<u8>,
+    remaining_bodies: u32,
+    /// Number of function bodies seen so far.
+    bodies: u32,
+}
+
+impl<'a> BulkMemoryContext<'a> {
+    fn new(code: &'a [u8], byte_cost: u64) -> Self {
+        Self {
+            code,
+            output_code: Vec::with_capacity(code.len()),
+            byte_cost,
+            type_params: Vec::new(),
+            function_types: Vec::new(),
+            gas_function: None,
+            code_section: Vec::new(),
+            remaining_bodies: 0,
+            bodies: 0,
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<u8>, PrepareError> {
+        let parser = wp::Parser::new(0);
+        for payload in parser.parse_all(self.code) {
+            let payload = payload.map_err(|err| {
+                tracing::trace!(?err, "was not able to parse the instrumented module");
+                PrepareError::Deserialization
+            })?;
+            match payload {
+                wp::Payload::Version { range, .. } => self.copy(range)?,
+                wp::Payload::End(_) => {}
+                wp::Payload::TypeSection(reader) => {
+                    for ty in reader.clone() {
+                        let ty = ty.map_err(|_| PrepareError::Deserialization)?;
+                        #[allow(unreachable_patterns)]
+                        let params = match ty {
+                            wp::Type::Func(func_type) => func_type.params().len(),
+                            _ => return Err(PrepareError::GasInstrumentation),
+                        };
+                        let params =
+                            u32::try_from(params).map_err(|_| PrepareError::Deserialization)?;
+                        self.type_params.push(params);
+                    }
+                    self.copy_section(SectionId::Type, reader.range())?;
+                }
+                wp::Payload::ImportSection(reader) => {
+                    let mut functions = 0;
+                    for import in reader.clone() {
+                        let import = import.map_err(|_| PrepareError::Deserialization)?;
+                        if let wp::TypeRef::Func(_) = import.ty {
+                            if import.module == "internal" && import.name == FINITE_WASM_GAS {
+                                self.gas_function = Some(functions);
+                            }
+                            functions += 1;
+                        }
+                    }
+                    self.copy_section(SectionId::Import, reader.range())?;
+                }
+                wp::Payload::FunctionSection(reader) => {
+                    for type_index in reader.clone() {
+                        let type_index = type_index.map_err(|_| PrepareError::Deserialization)?;
+                        self.function_types.push(type_index);
+                    }
+                    self.copy_section(SectionId::Function, reader.range())?;
+                }
+                wp::Payload::TableSection(reader) => {
+                    self.copy_section(SectionId::Table, reader.range())?
+                }
+                wp::Payload::MemorySection(reader) => {
+                    self.copy_section(SectionId::Memory, reader.range())?
+                }
+                wp::Payload::GlobalSection(reader) => {
+                    self.copy_section(SectionId::Global, reader.range())?
+                }
+                wp::Payload::ExportSection(reader) => {
+                    self.copy_section(SectionId::Export, reader.range())?
+                }
+                wp::Payload::StartSection { range, .. } => {
+                    self.copy_section(SectionId::Start, range)?
+                }
+                wp::Payload::ElementSection(reader) => {
+                    self.copy_section(SectionId::Element, reader.range())?
+                }
+                wp::Payload::DataCountSection { range, .. } => {
+                    self.copy_section(SectionId::DataCount, range)?
+                }
+                wp::Payload::DataSection(reader) => {
+                    self.copy_section(SectionId::Data, reader.range())?
+                }
+                wp::Payload::CodeSectionStart { count, .. } => {
+                    count.encode(&mut self.code_section);
+                    self.remaining_bodies = count;
+                    self.maybe_finish_code_section();
+                }
+                wp::Payload::CodeSectionEntry(body) => {
+                    self.transform_function_body(body)?;
+                    self.remaining_bodies = self
+                        .remaining_bodies
+                        .checked_sub(1)
+                        .ok_or(PrepareError::Deserialization)?;
+                    self.maybe_finish_code_section();
+                }
+                wp::Payload::CustomSection(reader) => {
+                    self.copy_section(SectionId::Custom, reader.range())?
+                }
+
+                _ => {
+                    tracing::trace!("instrumented module contains unsupported section");
+                    return Err(PrepareError::Deserialization);
+                }
+            }
+        }
+        Ok(self.output_code)
+    }
+
+    /// Adds the charge before every bulk memory instruction of the function, along with the
+    /// scratch local holding the length operand. Functions without such instructions are copied
+    /// over as they are.
+    fn transform_function_body(&mut self, body: wp::FunctionBody) -> Result<(), PrepareError> {
+        let params = self
+            .function_types
+            .get(self.bodies as usize)
+            .and_then(|type_index| self.type_params.get(*type_index as usize))
+            .copied()
+            .ok_or(PrepareError::Deserialization)?;
+        self.bodies += 1;
+
+        let mut reader = body.get_operators_reader().map_err(|_| PrepareError::Deserialization)?;
+        let operators_start = reader.original_position();
+        let mut has_bulk_memory = false;
+        while !reader.eof() {
+            if is_bulk_memory(&reader.read().map_err(|_| PrepareError::Deserialization)?) {
+                has_bulk_memory = true;
+                break;
+            }
+        }
+        if !has_bulk_memory {
+            let range = body.range();
+            range.len().encode(&mut self.code_section);
+            return self.copy_into_code_section(range);
+        }
+
+        let mut locals = body.get_locals_reader().map_err(|_| PrepareError::Deserialization)?;
+        let entries = locals.get_count();
+        let entries_start = locals.original_position();
+        let mut declared = 0u32;
+        for _ in 0..entries {
+            let (count, _) = locals.read().map_err(|_| PrepareError::Deserialization)?;
+            declared = declared.checked_add(count).ok_or(PrepareError::Deserialization)?;
+        }
+        let scratch = params
+            .checked_add(declared)
+            .filter(|index| *index < u32::MAX)
+            .ok_or(PrepareError::Deserialization)?;
+        let gas_function = self.gas_function.ok_or(PrepareError::GasInstrumentation)?;
+        let byte_cost =
+            i64::try_from(self.byte_cost).map_err(|_| PrepareError::GasInstrumentation)?;
+
+        let mut output = Vec::new();
+        entries.checked_add(1).ok_or(PrepareError::Deserialization)?.encode(&mut output);
+        self.copy_into(&mut output, entries_start..operators_start)?;
+        1u32.encode(&mut output);
+        wasm_encoder::ValType::I32.encode(&mut output);
+
+        let mut reader = body.get_operators_reader().map_err(|_| PrepareError::Deserialization)?;
+        let mut copied = operators_start;
+        while !reader.eof() {
+            let offset = reader.original_position();
+            if is_bulk_memory(&reader.read().map_err(|_| PrepareError::Deserialization)?) {
+                self.copy_into(&mut output, copied..offset)?;
+                Instruction::LocalTee(scratch).encode(&mut output);
+                Instruction::I64ExtendI32U.encode(&mut output);
+                Instruction::I64Const(byte_cost).encode(&mut output);
+                Instruction::I64Mul.encode(&mut output);
+                Instruction::Call(gas_function).encode(&mut output);
+                Instruction::LocalGet(scratch).encode(&mut output);
+                copied = offset;
+            }
+        }
+        self.copy_into(&mut output, copied..body.range().end)?;
+
+        output.len().encode(&mut self.code_section);
+        self.code_section.extend(output);
+        Ok(())
+    }
+
+    fn maybe_finish_code_section(&mut self) {
+        if self.remaining_bodies == 0 {
+            let data = std::mem::take(&mut self.code_section);
+            wasm_encoder::RawSection { id: SectionId::Code as u8, data: &data }
+                .append_to(&mut self.output_code);
+        }
+    }
+
+    fn copy_section(
+        &mut self,
+        id: SectionId,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), PrepareError> {
+        id.encode(&mut self.output_code);
+        range.len().encode(&mut self.output_code);
+        self.copy(range)
+    }
+
+    fn copy(&mut self, range: std::ops::Range<usize>) -> Result<(), PrepareError> {
+        self.output_code.extend(self.code.get(range).ok_or(PrepareError::Deserialization)?);
+        Ok(())
+    }
+
+    fn copy_into_code_section(
+        &mut self,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), PrepareError> {
+        self.code_section.extend(self.code.get(range).ok_or(PrepareError::Deserialization)?);
+        Ok(())
+    }
+
+    fn copy_into(
+        &self,
+        output: &mut Vec<u8>,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), PrepareError> {
+        output.extend(self.code.get(range).ok_or(PrepareError::Deserialization)?);
+        Ok(())
+    }
+}
+
+/// Whether the instruction takes a length operand on the top of the stack and is charged for it.
+fn is_bulk_memory(op: &wp::Operator) -> bool {
+    matches!(
+        op,
+        wp::Operator::MemoryCopy { .. }
+            | wp::Operator::MemoryFill { .. }
+            | wp::Operator::MemoryInit { .. }
+            | wp::Operator::TableCopy { .. }
+            | wp::Operator::TableInit { .. }
+    )
+}
+
+/// Adds the per-byte charges of the bulk memory instructions to a module instrumented by
+/// finite-wasm.
+pub(crate) fn inject_bulk_memory_gas(code: &[u8], byte_cost: u64) -> Result<Vec<u8>, PrepareError> {
+    BulkMemoryContext::new(code, byte_cost).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::ContractPrepareVersion;
+    use crate::tests::test_vm_config;
+    use near_parameters::vm::VMKind;
+
+    #[test]
+    fn test_inject_bulk_memory_gas() {
+        let mut config = test_vm_config();
+        config.limit_config.contract_prepare_version = ContractPrepareVersion::V3;
+        config.bulk_memory_byte_cost = 4_242_424;
+        let features = crate::features::WasmFeatures::from(ContractPrepareVersion::V3);
+        let code = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data $data "hello")
+                (func $copy (param i32)
+                    (memory.copy (local.get 0) (i32.const 0) (i32.const 16)))
+                (func $main
+                    (local i64)
+                    (memory.fill (i32.const 0) (i32.const 1) (i32.const 16))
+                    (memory.init $data (i32.const 0) (i32.const 0) (i32.const 5))
+                    (data.drop $data)
+                    (call $copy (i32.const 32)))
+                (export "main" (func $main)))
+            "#,
+        )
+        .unwrap();
+        let prepared = crate::prepare::prepare_contract(&code, &config, VMKind::Wasmtime).unwrap();
+
+        finite_wasm::wasmparser::Validator::new_with_features(features.into())
+            .validate_all(&prepared)
+            .expect("instrumented code should be valid");
+
+        // One charge for each of `memory.fill`, `memory.init` and `memory.copy`.
+        let text = wasmprinter::print_bytes(&prepared).unwrap();
+        assert_eq!(text.matches("i64.const 4242424").count(), 3, "{text}");
+    }
+}
@@ -0,0 +1,500 @@
+//! Instrumentation of wasm code for attributing the gas to wasm functions.
+//!
+//! This is applied on top of the finite-wasm instrumentation if `Config::wasm_gas_profiling` is
+//! set. The body of every function is wrapped so that it reports entering and leaving the
+//! function to the host, by calling the `internal.wasm_gas_profile_enter` and
+//! `internal.wasm_gas_profile_exit` imports with the index of the function in the original
+//! module. The host keeps track of the stack of wasm functions and attributes the gas charged
+//! by the finite-wasm instrumentation to it.
+//!
+//! The instructions are inserted after finite-wasm has computed the gas costs of the code, so
+//! they are not charged for and a profiled contract burns exactly as much gas as an unprofiled
+//! one.
+
+use crate::logic::errors::PrepareError;
+use finite_wasm::wasmparser as wp;
+use std::collections::HashMap;
+use wasm_encoder::{BlockType, Encode, Instruction, Section, SectionId};
+
+/// The import the type of which, `(i64) -> ()`, is reused for the profiling imports.
+const FINITE_WASM_GAS: &str = "finite_wasm_gas";
+const PROFILE_ENTER: &str = "wasm_gas_profile_enter";
+const PROFILE_EXIT: &str = "wasm_gas_profile_exit";
+/// Number of the added profiling imports.
+const PROFILE_IMPORTS: u32 = 2;
+/// Binary encodings of the `funcref` reference type, of the `funcref` element kind and of the
+/// `ref.null` instruction.
+const FUNCREF: u8 = 0x70;
+const ELEMKIND_FUNCREF: u8 = 0x00;
+const REF_NULL: u8 = 0xD0;
+
+struct ProfileContext<'a> {
+    code: &'a [u8],
+    output_code: Vec<u8>,
+    /// Block types matching the results of the types in the type section.
+    block_types: Vec<BlockType>,
+    /// Type indices of the functions defined in the module.
+    function_types: Vec<u32>,
+    /// Number of the imported functions, not counting the profiling imports.
+    imported_functions: u32,
+    /// Number of the functions imported by the finite-wasm instrumentation.
+    internal_functions: u32,
+    /// Code section being built, and the number of bodies still to be added to it.
+    code_section: Vec<u8>,
+    remaining_bodies: u32,
+    /// Number of function bodies seen so far.
+    bodies: u32,
+}
+
+impl<'a> ProfileContext<'a> {
+    fn new(code: &'a [u8]) -> Self {
+        Self {
+            code,
+            output_code: Vec::with_capacity(code.len()),
+            block_types: Vec::new(),
+            function_types: Vec::new(),
+            imported_functions: 0,
+            internal_functions: 0,
+            code_section: Vec::new(),
+            remaining_bodies: 0,
+            bodies: 0,
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<u8>, PrepareError> {
+        let parser = wp::Parser::new(0);
+        for payload in parser.parse_all(self.code) {
+            let payload = payload.map_err(|err| {
+                tracing::trace!(?err, "was not able to parse the instrumented module");
+                PrepareError::Deserialization
+            })?;
+            match payload {
+                wp::Payload::Version { range, .. } => self.copy(range)?,
+                wp::Payload::End(_) => {}
+                wp::Payload::TypeSection(reader) => {
+                    for ty in reader.clone() {
+                        let ty = ty.map_err(|_| PrepareError::Deserialization)?;
+                        #[allow(unreachable_patterns)]
+                        let block_type = match ty {
+                            wp::Type::Func(func_type) => block_type(func_type.results())?,
+                            _ => return Err(PrepareError::GasInstrumentation),
+                        };
+                        self.block_types.push(block_type);
+                    }
+                    self.copy_section(SectionId::Type, reader.range())?;
+                }
+                wp::Payload::ImportSection(reader) => self.transform_import_section(&reader)?,
+                wp::Payload::FunctionSection(reader) => {
+                    for type_index in reader.clone() {
+                        let type_index = type_index.map_err(|_| PrepareError::Deserialization)?;
+                        self.function_types.push(type_index);
+                    }
+                    self.copy_section(SectionId::Function, reader.range())?;
+                }
+                wp::Payload::TableSection(reader) => {
+                    self.copy_section(SectionId::Table, reader.range())?
+                }
+                wp::Payload::MemorySection(reader) => {
+                    self.copy_section(SectionId::Memory, reader.range())?
+                }
+                wp::Payload::GlobalSection(reader) => {
+                    self.copy_section(SectionId::Global, reader.range())?
+                }
+                wp::Payload::ExportSection(reader) => {
+                    let mut new_section = wasm_encoder::ExportSection::new();
+                    for export in reader {
+                        let export = export.map_err(|_| PrepareError::Deserialization)?;
+                        let (kind, index) = match export.kind {
+                            wp::ExternalKind::Func => {
+                                (wasm_encoder::ExportKind::Func, self.function_index(export.index))
+                            }
+                            wp::ExternalKind::Table => {
+                                (wasm_encoder::ExportKind::Table, export.index)
+                            }
+                            wp::ExternalKind::Memory => {
+                                (wasm_encoder::ExportKind::Memory, export.index)
+                            }
+                            wp::ExternalKind::Global => {
+                                (wasm_encoder::ExportKind::Global, export.index)
+                            }
+                            wp::ExternalKind::Tag => (wasm_encoder::ExportKind::Tag, export.index),
+                        };
+                        new_section.export(export.name, kind, index);
+                    }
+                    new_section.append_to(&mut self.output_code);
+                }
+                wp::Payload::StartSection { func, .. } => {
+                    wasm_encoder::StartSection { function_index: self.function_index(func) }
+                        .append_to(&mut self.output_code);
+                }
+                wp::Payload::ElementSection(reader) => self.transform_element_section(reader)?,
+                wp::Payload::DataCountSection { range, .. } => {
+                    self.copy_section(SectionId::DataCount, range)?
+                }
+                wp::Payload::DataSection(reader) => {
+                    self.copy_section(SectionId::Data, reader.range())?
+                }
+                wp::Payload::CodeSectionStart { count, .. } => {
+                    count.encode(&mut self.code_section);
+                    self.remaining_bodies = count;
+                    self.maybe_finish_code_section();
+                }
+                wp::Payload::CodeSectionEntry(body) => {
+                    self.transform_function_body(body)?;
+                    self.remaining_bodies = self
+                        .remaining_bodies
+                        .checked_sub(1)
+                        .ok_or(PrepareError::Deserialization)?;
+                    self.maybe_finish_code_section();
+                }
+                // The function indices in the name section would no longer be right. The names
+                // are looked up in the original code instead, see `function_names`.
+                wp::Payload::CustomSection(_) => {}
+
+                _ => {
+                    tracing::trace!("instrumented module contains unsupported section");
+                    return Err(PrepareError::Deserialization);
+                }
+            }
+        }
+        Ok(self.output_code)
+    }
+
+    /// Copies the imports and adds the profiling imports after them.
+    fn transform_import_section(
+        &mut self,
+        reader: &wp::ImportSectionReader,
+    ) -> Result<(), PrepareError> {
+        let mut new_section = wasm_encoder::ImportSection::new();
+        let mut gas_type = None;
+        for import in reader.clone() {
+            let import = import.map_err(|_| PrepareError::Deserialization)?;
+            let new_type = match import.ty {
+                wp::TypeRef::Func(id) => {
+                    self.imported_functions += 1;
+                    if import.module == "internal" {
+                        self.internal_functions += 1;
+                        if import.name == FINITE_WASM_GAS {
+                            gas_type = Some(id);
+                        }
+                    }
+                    wasm_encoder::EntityType::Function(id)
+                }
+                wp::TypeRef::Memory(memory) => {
+                    wasm_encoder::EntityType::Memory(wasm_encoder::MemoryType {
+                        minimum: memory.initial,
+                        maximum: memory.maximum,
+                        memory64: memory.memory64,
+                        shared: memory.shared,
+                        page_size_log2: None,
+                    })
+                }
+                // Prepared contracts only import functions and the memory.
+                wp::TypeRef::Table(_) | wp::TypeRef::Global(_) | wp::TypeRef::Tag(_) => {
+                    return Err(PrepareError::Instantiate)
+                }
+            };
+            new_section.import(import.module, import.name, new_type);
+        }
+        let gas_type = gas_type.ok_or(PrepareError::GasInstrumentation)?;
+        new_section.import("internal", PROFILE_ENTER, wasm_encoder::EntityType::Function(gas_type));
+        new_section.import("internal", PROFILE_EXIT, wasm_encoder::EntityType::Function(gas_type));
+        new_section.append_to(&mut self.output_code);
+        Ok(())
+    }
+
+    /// Re-encodes the element segments with the shifted function indices.
+    ///
+    /// Without the reference types proposal all the segments are segments of `funcref`s and the
+    /// active ones are for the table 0.
+    fn transform_element_section(
+        &mut self,
+        reader: wp::ElementSectionReader,
+    ) -> Result<(), PrepareError> {
+        let mut data = Vec::new();
+        reader.count().encode(&mut data);
+        for element in reader {
+            let element = element.map_err(|_| PrepareError::Deserialization)?;
+            let expressions = matches!(element.items, wp::ElementItems::Expressions(..));
+            // See the element section encoding in the binary format specification.
+            match element.kind {
+                wp::ElementKind::Active { table_index, offset_expr } => {
+                    if table_index != 0 {
+                        return Err(PrepareError::Deserialization);
+                    }
+                    (if expressions { 4u32 } else { 0u32 }).encode(&mut data);
+                    self.encode_const_expr(offset_expr, &mut data)?;
+                }
+                wp::ElementKind::Passive => {
+                    (if expressions { 5u32 } else { 1u32 }).encode(&mut data);
+                    data.push(if expressions { FUNCREF } else { ELEMKIND_FUNCREF });
+                }
+                wp::ElementKind::Declared => {
+                    (if expressions { 7u32 } else { 3u32 }).encode(&mut data);
+                    data.push(if expressions { FUNCREF } else { ELEMKIND_FUNCREF });
+                }
+            }
+            match element.items {
+                wp::ElementItems::Functions(funcs) => {
+                    funcs.count().encode(&mut data);
+                    for func in funcs {
+                        let func = func.map_err(|_| PrepareError::Deserialization)?;
+                        self.function_index(func).encode(&mut data);
+                    }
+                }
+                wp::ElementItems::Expressions(exprs) => {
+                    exprs.count().encode(&mut data);
+                    for expr in exprs {
+                        let expr = expr.map_err(|_| PrepareError::Deserialization)?;
+                        self.encode_const_expr(expr, &mut data)?;
+                    }
+                }
+            }
+        }
+        wasm_encoder::RawSection { id: SectionId::Element as u8, data: &data }
+            .append_to(&mut self.output_code);
+        Ok(())
+    }
+
+    /// Re-encodes a constant expression of an element segment with the shifted function indices.
+    fn encode_const_expr(
+        &self,
+        expr: wp::ConstExpr,
+        data: &mut Vec<u8>,
+    ) -> Result<(), PrepareError> {
+        let mut reader = expr.get_operators_reader();
+        loop {
+            match reader.read().map_err(|_| PrepareError::Deserialization)? {
+                wp::Operator::I32Const { value } => Instruction::I32Const(value).encode(data),
+                wp::Operator::GlobalGet { global_index } => {
+                    Instruction::GlobalGet(global_index).encode(data)
+                }
+                wp::Operator::RefFunc { function_index } => {
+                    Instruction::RefFunc(self.function_index(function_index)).encode(data)
+                }
+                wp::Operator::RefNull { .. } => data.extend([REF_NULL, FUNCREF]),
+                wp::Operator::End => {
+                    Instruction::End.encode(data);
+                    return Ok(());
+                }
+                _ => return Err(PrepareError::Deserialization),
+            }
+        }
+    }
+
+    /// Wraps the function body into a block preceded by entering the function and followed by
+    /// leaving it. Every `return` leaves the function too. Branches to the function body now
+    /// target the block, which has the same result type.
+    fn transform_function_body(&mut self, body: wp::FunctionBody) -> Result<(), PrepareError> {
+        let type_index = self
+            .function_types
+            .get(self.bodies as usize)
+            .and_then(|type_index| self.block_types.get(*type_index as usize))
+            .copied()
+            .ok_or(PrepareError::Deserialization)?;
+        let func_index = i64::from(self.imported_functions - self.internal_functions + self.bodies);
+        self.bodies += 1;
+
+        let mut reader = body.get_operators_reader().map_err(|_| PrepareError::Deserialization)?;
+        let mut output = Vec::new();
+        // The locals are copied over as they are.
+        self.copy_into(&mut output, body.range().start..reader.original_position())?;
+        Instruction::I64Const(func_index).encode(&mut output);
+        Instruction::Call(self.imported_functions).encode(&mut output);
+        Instruction::Block(type_index).encode(&mut output);
+
+        let mut copied = reader.original_position();
+        let mut last_offset = copied;
+        while !reader.eof() {
+            let offset = reader.original_position();
+            last_offset = offset;
+            match reader.read().map_err(|_| PrepareError::Deserialization)? {
+                wp::Operator::Call { function_index } => {
+                    self.copy_into(&mut output, copied..offset)?;
+                    Instruction::Call(self.function_index(function_index)).encode(&mut output);
+                    copied = reader.original_position();
+                }
+                wp::Operator::Return => {
+                    self.copy_into(&mut output, copied..offset)?;
+                    self.encode_exit(&mut output, func_index);
+                    copied = offset;
+                }
+                _ => {}
+            }
+        }
+        // The last operator is the `end` of the function body.
+        self.copy_into(&mut output, copied..last_offset)?;
+        Instruction::End.encode(&mut output);
+        self.encode_exit(&mut output, func_index);
+        Instruction::End.encode(&mut output);
+
+        output.len().encode(&mut self.code_section);
+        self.code_section.extend(output);
+        Ok(())
+    }
+
+    fn encode_exit(&self, output: &mut Vec<u8>, func_index: i64) {
+        Instruction::I64Const(func_index).encode(output);
+        Instruction::Call(self.imported_functions + 1).encode(output);
+    }
+
+    fn maybe_finish_code_section(&mut self) {
+        if self.remaining_bodies == 0 {
+            let data = std::mem::take(&mut self.code_section);
+            wasm_encoder::RawSection { id: SectionId::Code as u8, data: &data }
+                .append_to(&mut self.output_code);
+        }
+    }
+
+    /// Index of the function in the instrumented module, with the profiling imports added.
+    fn function_index(&self, index: u32) -> u32 {
+        if index < self.imported_functions {
+            index
+        } else {
+            index + PROFILE_IMPORTS
+        }
+    }
+
+    fn copy_section(
+        &mut self,
+        id: SectionId,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), PrepareError> {
+        id.encode(&mut self.output_code);
+        range.len().encode(&mut self.output_code);
+        self.copy(range)
+    }
+
+    fn copy(&mut self, range: std::ops::Range<usize>) -> Result<(), PrepareError> {
+        self.output_code.extend(self.code.get(range).ok_or(PrepareError::Deserialization)?);
+        Ok(())
+    }
+
+    fn copy_into(
+        &self,
+        output: &mut Vec<u8>,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), PrepareError> {
+        output.extend(self.code.get(range).ok_or(PrepareError::Deserialization)?);
+        Ok(())
+    }
+}
+
+/// Type of a block with the given results. Multi-value blocks are not supported.
+fn block_type(results: &[wp::ValType]) -> Result<BlockType, PrepareError> {
+    let result = match results {
+        [] => return Ok(BlockType::Empty),
+        [result] => result,
+        _ => return Err(PrepareError::GasInstrumentation),
+    };
+    Ok(BlockType::Result(match result {
+        wp::ValType::I32 => wasm_encoder::ValType::I32,
+        wp::ValType::I64 => wasm_encoder::ValType::I64,
+        wp::ValType::F32 => wasm_encoder::ValType::F32,
+        wp::ValType::F64 => wasm_encoder::ValType::F64,
+        wp::ValType::V128 => wasm_encoder::ValType::V128,
+        wp::ValType::Ref(_) => return Err(PrepareError::GasInstrumentation),
+    }))
+}
+
+/// Adds the gas profiling instrumentation to a module instrumented by finite-wasm.
+pub(crate) fn inject_profiling(code: &[u8]) -> Result<Vec<u8>, PrepareError> {
+    ProfileContext::new(code).run()
+}
+
+/// Returns the names of the functions in the name section of the original contract code, by
+/// function index. The names are best effort, a malformed name section is ignored.
+pub(crate) fn function_names(code: &[u8]) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for payload in wp::Parser::new(0).parse_all(code) {
+        let Ok(payload) = payload else { break };
+        let wp::Payload::CustomSection(reader) = payload else { continue };
+        if reader.name() != "name" {
+            continue;
+        }
+        for subsection in wp::NameSectionReader::new(reader.data(), reader.data_offset()) {
+            let Ok(wp::Name::Function(map)) = subsection else { continue };
+            for naming in map.into_iter().flatten() {
+                names.insert(naming.index, naming.name.to_string());
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{function_names, inject_profiling};
+    use crate::logic::ContractPrepareVersion;
+    use crate::tests::test_vm_config;
+    use near_parameters::vm::VMKind;
+
+    const CONTRACT: &str = r#"
+        (module
+            (func $helper (param i32) (result i32)
+                (if (local.get 0) (then (return (i32.const 1))))
+                (block (br 1 (i32.const 2))))
+            (func $main
+                (drop (call $helper (i32.const 0)))
+                (call $tail))
+            (func $tail)
+            (table 1 funcref)
+            (elem (i32.const 0) $tail)
+            (export "main" (func $main)))
+    "#;
+
+    #[test]
+    fn test_inject_profiling() {
+        let mut config = test_vm_config();
+        config.limit_config.contract_prepare_version = ContractPrepareVersion::V2;
+        let features = crate::features::WasmFeatures::from(ContractPrepareVersion::V2);
+        let code = wat::parse_str(CONTRACT).unwrap();
+        let prepared = crate::prepare::prepare_contract(&code, &config, VMKind::Wasmtime).unwrap();
+        let profiled = inject_profiling(&prepared).unwrap();
+
+        let mut validator = wasmparser::Validator::new();
+        validator.wasm_features(features.into());
+        validator.validate_all(&profiled).expect("profiled code should be valid");
+
+        let text = wasmprinter::print_bytes(&profiled).unwrap();
+        for import in ["wasm_gas_profile_enter", "wasm_gas_profile_exit"] {
+            assert!(text.contains(&format!("(import \"internal\" \"{import}\"")), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_inject_profiling_bulk_memory() {
+        let mut config = test_vm_config();
+        config.limit_config.contract_prepare_version = ContractPrepareVersion::V3;
+        let features = crate::features::WasmFeatures::from(ContractPrepareVersion::V3);
+        let code = wat::parse_str(
+            r#"
+            (module
+                (func $tail)
+                (func $main
+                    (table.init $elem (i32.const 0) (i32.const 0) (i32.const 1))
+                    (elem.drop $elem)
+                    (call_indirect (i32.const 0)))
+                (table 1 funcref)
+                (elem $elem func $tail)
+                (export "main" (func $main)))
+            "#,
+        )
+        .unwrap();
+        let prepared = crate::prepare::prepare_contract(&code, &config, VMKind::Wasmtime).unwrap();
+        let profiled = inject_profiling(&prepared).unwrap();
+
+        finite_wasm::wasmparser::Validator::new_with_features(features.into())
+            .validate_all(&profiled)
+            .expect("profiled code should be valid");
+    }
+
+    #[test]
+    fn test_function_names() {
+        let code = wat::parse_str(CONTRACT).unwrap();
+        let names = function_names(&code);
+        assert_eq!(names.get(&0).map(String::as_str), Some("helper"));
+        assert_eq!(names.get(&1).map(String::as_str), Some("main"));
+        assert_eq!(names.get(&2).map(String::as_str), Some("tail"));
+    }
+}
@@ -8,7 +8,7 @@ use super::utils::split_method_names;
 use super::ValuePtr;
 use super::{HostError, VMLogicError};
 use crate::bls12381_impl;
-use crate::ProfileDataV3;
+use crate::{ProfileDataV3, WasmGasProfile};
 use near_crypto::Secp256K1Signature;
 use near_parameters::vm::{Config, StorageGetMode};
 use near_parameters::{
@@ -50,6 +50,8 @@ pub struct ExecutionResultState {
     current_account_balance: Balance,
     /// Storage usage of the current account at the moment
     current_storage_usage: StorageUsage,
+    /// Gas spent by the WASM functions, if `Config::wasm_gas_profiling` is set.
+    pub(crate) wasm_gas_profile: Option<WasmGasProfile>,
 }
 
 impl ExecutionResultState {
@@ -65,6 +67,7 @@ impl ExecutionResultState {
             .checked_add(context.attached_deposit)
             .expect("current_account_balance overflowed");
         let current_storage_usage = context.storage_usage;
+        let wasm_gas_profile = config.wasm_gas_profiling.then(WasmGasProfile::default);
         Self {
             config,
             gas_counter,
@@ -73,6 +76,7 @@ impl ExecutionResultState {
             return_data: ReturnData::None,
             current_account_balance,
             current_storage_usage,
+            wasm_gas_profile,
         }
     }
 
@@ -140,6 +144,7 @@ impl ExecutionResultState {
             compute_usage,
             logs: self.logs,
             profile,
+            wasm_gas_profile: self.wasm_gas_profile,
             aborted: None,
         }
     }
@@ -287,7 +292,7 @@ impl<'a> VMLogic<'a> {
     // # Finite-wasm internals #
     // #########################
     pub fn finite_wasm_gas(&mut self, gas: u64) -> Result<()> {
-        self.gas(gas)
+        self.wasm_gas(gas)
     }
 
     pub fn finite_wasm_stack(&mut self, operand_size: u64, frame_size: u64) -> Result<()> {
@@ -296,7 +301,7 @@ impl<'a> VMLogic<'a> {
                 Some(s) => s,
                 None => return Err(VMLogicError::HostError(HostError::MemoryAccessViolation)),
             };
-        self.gas(((frame_size + 7) / 8) * u64::from(self.config.regular_op_cost))?;
+        self.wasm_gas(((frame_size + 7) / 8) * u64::from(self.config.regular_op_cost))?;
         Ok(())
     }
 
@@ -308,6 +313,31 @@ impl<'a> VMLogic<'a> {
         Ok(())
     }
 
+    /// Called by the profiling instrumentation when entering a WASM function.
+    pub fn wasm_gas_profile_enter(&mut self, func_index: u64) -> Result<()> {
+        if let Some(profile) = &mut self.result_state.wasm_gas_profile {
+            profile.enter(func_index as u32);
+        }
+        Ok(())
+    }
+
+    /// Called by the profiling instrumentation when leaving a WASM function.
+    pub fn wasm_gas_profile_exit(&mut self, func_index: u64) -> Result<()> {
+        if let Some(profile) = &mut self.result_state.wasm_gas_profile {
+            profile.exit(func_index as u32);
+        }
+        Ok(())
+    }
+
+    /// Consumes gas for executing WASM code, attributing it to the current WASM function if
+    /// profiling is enabled.
+    fn wasm_gas(&mut self, gas: Gas) -> Result<()> {
+        if let Some(profile) = &mut self.result_state.wasm_gas_profile {
+            profile.add_gas(gas);
+        }
+        self.gas(gas)
+    }
+
     // #################
     // # Registers API #
     // #################
@@ -3519,6 +3549,8 @@ pub struct VMOutcome {
     pub logs: Vec<String>,
     /// Data collected from making a contract call
     pub profile: ProfileDataV3,
+    /// Gas spent by each WASM function, if `Config::wasm_gas_profiling` is set.
+    pub wasm_gas_profile: Option<WasmGasProfile>,
     pub aborted: Option<FunctionCallError>,
 }
 
@@ -3551,6 +3583,7 @@ impl VMOutcome {
             compute_usage: 0,
             logs: Vec::new(),
             profile: ProfileDataV3::default(),
+            wasm_gas_profile: None,
             aborted: Some(error),
         }
     }
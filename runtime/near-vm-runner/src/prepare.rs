@@ -25,10 +25,14 @@ pub fn prepare_contract(
     kind: VMKind,
 ) -> Result<Vec<u8>, PrepareError> {
     let prepare = config.limit_config.contract_prepare_version;
-    // NearVM => ContractPrepareVersion::V2
+    // NearVM => ContractPrepareVersion::V2 or later
     assert!(
-        (kind != VMKind::NearVm) || (prepare == crate::logic::ContractPrepareVersion::V2),
-        "NearVM only works with contract prepare version V2",
+        (kind != VMKind::NearVm)
+            || matches!(
+                prepare,
+                crate::logic::ContractPrepareVersion::V2 | crate::logic::ContractPrepareVersion::V3
+            ),
+        "NearVM only works with contract prepare version V2 or later",
     );
     let features = crate::features::WasmFeatures::from(prepare);
     match prepare {
@@ -41,7 +45,7 @@ pub fn prepare_contract(
             prepare_v1::validate_contract(original_code, features, config)?;
             prepare_v1::prepare_contract(original_code, config)
         }
-        crate::logic::ContractPrepareVersion::V2 => {
+        crate::logic::ContractPrepareVersion::V2 | crate::logic::ContractPrepareVersion::V3 => {
             prepare_v2::prepare_contract(original_code, features, config, kind)
         }
     }
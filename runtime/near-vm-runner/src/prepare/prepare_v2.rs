@@ -294,6 +294,17 @@ pub(crate) fn prepare_contract(
             tracing::error!(?err, ?kind, "Instrumentation failed");
             PrepareError::Serialization
         })?;
+    let res = if features.bulk_memory && config.bulk_memory_byte_cost != 0 {
+        crate::instrument::bulk_memory::inject_bulk_memory_gas(
+            &res,
+            u64::from(config.bulk_memory_byte_cost),
+        )?
+    } else {
+        res
+    };
+    if config.wasm_gas_profiling {
+        return crate::instrument::profile::inject_profiling(&res);
+    }
     Ok(res)
 }
 
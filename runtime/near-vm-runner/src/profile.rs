@@ -2,7 +2,9 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use enum_map::{enum_map, Enum, EnumMap};
 use near_parameters::{ActionCosts, ExtCosts, ExtCostsConfig};
 use near_primitives_core::types::{Compute, Gas};
-use std::fmt;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::sync::Arc;
 use strum::IntoEnumIterator;
 
 /// Profile of gas consumption.
@@ -231,6 +233,86 @@ impl fmt::Debug for ProfileDataV3 {
     }
 }
 
+/// Gas spent on execution inside the WASM VM, attributed to the stacks of WASM functions which
+/// spent it.
+///
+/// Only collected if `Config::wasm_gas_profiling` is set, see `crate::instrument::profile`.
+/// Unlike `ProfileDataV3`, it is never stored and is free to change between releases.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmGasProfile {
+    /// Names of the WASM functions from the name section, by function index.
+    names: Arc<HashMap<u32, String>>,
+    /// Indices of the WASM functions on the current call stack.
+    stack: Vec<u32>,
+    /// Gas spent by each call stack.
+    stacks: BTreeMap<Vec<u32>, Gas>,
+}
+
+impl WasmGasProfile {
+    pub(crate) fn set_function_names(&mut self, names: Arc<HashMap<u32, String>>) {
+        self.names = names;
+    }
+
+    pub(crate) fn enter(&mut self, func_index: u32) {
+        self.stack.push(func_index);
+    }
+
+    pub(crate) fn exit(&mut self, func_index: u32) {
+        let top = self.stack.pop();
+        debug_assert_eq!(top, Some(func_index), "unbalanced WASM function exit");
+    }
+
+    /// Attributes gas to the current call stack.
+    pub(crate) fn add_gas(&mut self, gas: Gas) {
+        if gas == 0 {
+            return;
+        }
+        match self.stacks.get_mut(self.stack.as_slice()) {
+            Some(total) => *total = total.saturating_add(gas),
+            None => {
+                self.stacks.insert(self.stack.clone(), gas);
+            }
+        }
+    }
+
+    /// Total gas attributed to the WASM functions.
+    pub fn total_gas(&self) -> Gas {
+        self.stacks.values().copied().fold(0, Gas::saturating_add)
+    }
+
+    /// Formats the profile as folded stacks, a `caller;callee gas` line per call stack, which
+    /// can be turned into a flamegraph by tools such as `inferno-flamegraph`.
+    ///
+    /// Functions missing from the name section are named `wasm-function[index]`. Gas spent
+    /// outside of any function, e.g. before the first call, is attributed to `[wasm]`.
+    pub fn to_folded(&self) -> String {
+        let mut out = String::new();
+        for (stack, gas) in &self.stacks {
+            if stack.is_empty() {
+                out.push_str("[wasm]");
+            }
+            for (i, func_index) in stack.iter().enumerate() {
+                if i > 0 {
+                    out.push(';');
+                }
+                match self.names.get(func_index) {
+                    // Semicolons and line breaks are separators in the folded format.
+                    Some(name) => out.extend(name.chars().map(|c| {
+                        if c == ';' || c.is_control() {
+                            '_'
+                        } else {
+                            c
+                        }
+                    })),
+                    None => write!(out, "wasm-function[{func_index}]").unwrap(),
+                }
+            }
+            writeln!(out, " {gas}").unwrap();
+        }
+        out
+    }
+}
+
 /// Tests for ProfileDataV3
 #[cfg(test)]
 mod test {
@@ -474,4 +556,33 @@ mod test {
         BorshSerialize::serialize(&wasm_cost, &mut input).unwrap();
         input
     }
+
+    #[test]
+    fn test_wasm_gas_profile_folded() {
+        let mut profile = WasmGasProfile::default();
+        profile.set_function_names(Arc::new(HashMap::from([
+            (3, "main".to_string()),
+            (5, "<Vec<T>;\n>::push".to_string()),
+        ])));
+        profile.add_gas(1);
+        profile.enter(3);
+        profile.add_gas(10);
+        profile.enter(5);
+        profile.add_gas(20);
+        profile.exit(5);
+        profile.enter(7);
+        profile.add_gas(30);
+        profile.exit(7);
+        profile.enter(5);
+        profile.add_gas(40);
+        profile.add_gas(0);
+        profile.exit(5);
+        profile.exit(3);
+
+        assert_eq!(profile.total_gas(), 101);
+        assert_eq!(
+            profile.to_folded(),
+            "[wasm] 1\nmain 10\nmain;<Vec<T>__>::push 60\nmain;wasm-function[7] 30\n"
+        );
+    }
 }
@@ -1,3 +1,5 @@
+#[cfg(feature = "prepare")]
+mod bulk_memory;
 mod cache;
 mod compile_errors;
 mod fuzzers;
@@ -6,6 +8,8 @@ mod rs_contract;
 mod runtime_errors;
 pub(crate) mod test_builder;
 mod ts_contract;
+#[cfg(feature = "wasmtime_vm")]
+mod wasm_gas_profile;
 mod wasm_validation;
 
 use crate::logic::VMContext;
@@ -46,8 +50,11 @@ pub(crate) fn with_vm_variants(
     run(VMKind::Wasmer2);
 
     #[cfg(all(feature = "near_vm", target_arch = "x86_64"))]
-    if cfg.limit_config.contract_prepare_version == near_parameters::vm::ContractPrepareVersion::V2
-    {
+    if matches!(
+        cfg.limit_config.contract_prepare_version,
+        near_parameters::vm::ContractPrepareVersion::V2
+            | near_parameters::vm::ContractPrepareVersion::V3
+    ) {
         run(VMKind::NearVm);
     }
 }
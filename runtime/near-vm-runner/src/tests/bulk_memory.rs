@@ -0,0 +1,77 @@
+use crate::logic::mocks::mock_external::MockedExternal;
+use crate::logic::{ContractPrepareVersion, VMOutcome};
+use crate::runner::VMKindExt;
+use crate::tests::{create_context, test_vm_config, with_vm_variants};
+use crate::ContractCode;
+use near_parameters::vm::{Config, VMKind};
+use near_parameters::RuntimeFeesConfig;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+const BYTE_COST: u32 = 1_000;
+
+const CONTRACT: &str = r#"
+    (module
+        (memory 1)
+        (data $data "0123456789abcdef")
+        (func (export "fill_small") (memory.fill (i32.const 0) (i32.const 1) (i32.const 16)))
+        (func (export "fill_large") (memory.fill (i32.const 0) (i32.const 1) (i32.const 4096)))
+        (func (export "copy_small") (memory.copy (i32.const 0) (i32.const 4096) (i32.const 16)))
+        (func (export "copy_large") (memory.copy (i32.const 0) (i32.const 4096) (i32.const 4096)))
+        (func (export "init")
+            (memory.init $data (i32.const 0) (i32.const 0) (i32.const 16))
+            (data.drop $data)))
+"#;
+
+fn config(version: ContractPrepareVersion) -> Config {
+    let mut config = test_vm_config();
+    config.limit_config.contract_prepare_version = version;
+    config.bulk_memory_byte_cost = BYTE_COST;
+    config
+}
+
+fn run(config: &Arc<Config>, vm_kind: VMKind, method: &str) -> VMOutcome {
+    let code = ContractCode::new(wat::parse_str(CONTRACT).unwrap(), None);
+    let mut fake_external = MockedExternal::with_code(code);
+    let context = create_context(vec![]);
+    let runtime = vm_kind.runtime(Arc::clone(config)).expect("runtime has not been compiled");
+    let gas_counter = context.make_gas_counter(config);
+    let outcome = runtime
+        .prepare(&fake_external, None, gas_counter, method)
+        .run(&mut fake_external, &context, Arc::new(RuntimeFeesConfig::test()))
+        .expect("execution failed");
+    assert!(outcome.aborted.is_none(), "{vm_kind:?} {method}: {:?}", outcome.aborted);
+    outcome
+}
+
+#[test]
+fn test_bulk_memory_gas() {
+    let config = Arc::new(config(ContractPrepareVersion::V3));
+    let used_gas = RefCell::new(Vec::new());
+    with_vm_variants(&config, |vm_kind: VMKind| {
+        // Only the runtimes using finite-wasm based preparation support bulk memory.
+        if !matches!(vm_kind, VMKind::Wasmtime | VMKind::NearVm) {
+            return;
+        }
+        let mut gas = Vec::new();
+        for op in ["fill", "copy"] {
+            let small = run(&config, vm_kind, &format!("{op}_small")).used_gas;
+            let large = run(&config, vm_kind, &format!("{op}_large")).used_gas;
+            assert_eq!(large - small, (4096 - 16) * u64::from(BYTE_COST), "{vm_kind:?} {op}");
+            gas.extend([small, large]);
+        }
+        gas.push(run(&config, vm_kind, "init").used_gas);
+        used_gas.borrow_mut().push(gas);
+    });
+    let used_gas = used_gas.into_inner();
+    assert!(used_gas.windows(2).all(|w| w[0] == w[1]), "{used_gas:?}");
+}
+
+#[test]
+fn test_bulk_memory_requires_v3() {
+    let code = wat::parse_str(CONTRACT).unwrap();
+    let v2 = config(ContractPrepareVersion::V2);
+    assert!(crate::prepare::prepare_contract(&code, &v2, VMKind::Wasmtime).is_err());
+    let v3 = config(ContractPrepareVersion::V3);
+    assert!(crate::prepare::prepare_contract(&code, &v3, VMKind::Wasmtime).is_ok());
+}
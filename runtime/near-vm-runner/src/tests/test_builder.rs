@@ -207,8 +207,10 @@ impl TestBuilder {
 
                 // NearVM includes a different contract preparation algorithm, that is not supported on old protocol versions
                 if vm_kind == VMKind::NearVm
-                    && runtime_config.wasm_config.limit_config.contract_prepare_version
-                        != ContractPrepareVersion::V2
+                    && !matches!(
+                        runtime_config.wasm_config.limit_config.contract_prepare_version,
+                        ContractPrepareVersion::V2 | ContractPrepareVersion::V3
+                    )
                 {
                     continue;
                 }
@@ -0,0 +1,62 @@
+use crate::logic::mocks::mock_external::MockedExternal;
+use crate::logic::VMOutcome;
+use crate::runner::VMKindExt;
+use crate::tests::{create_context, test_vm_config};
+use crate::ContractCode;
+use near_parameters::RuntimeFeesConfig;
+use std::sync::Arc;
+
+const CONTRACT: &str = r#"
+    (module
+        (func $fib (param i64) (result i64)
+            (if (i64.lt_u (local.get 0) (i64.const 2)) (then (return (local.get 0))))
+            (i64.add
+                (call $fib (i64.sub (local.get 0) (i64.const 1)))
+                (call $fib (i64.sub (local.get 0) (i64.const 2)))))
+        (func $main
+            (drop (call $fib (i64.const 10))))
+        (export "main" (func $main)))
+"#;
+
+fn run(profiling: bool) -> VMOutcome {
+    let mut config = test_vm_config();
+    if profiling {
+        config.enable_wasm_gas_profiling();
+    }
+    let config = Arc::new(config);
+    let code = ContractCode::new(wat::parse_str(CONTRACT).unwrap(), None);
+    let mut fake_external = MockedExternal::with_code(code);
+    let context = create_context(vec![]);
+    let runtime = near_parameters::vm::VMKind::Wasmtime
+        .runtime(Arc::clone(&config))
+        .expect("runtime has not been compiled");
+    let gas_counter = context.make_gas_counter(&config);
+    runtime
+        .prepare(&fake_external, None, gas_counter, "main")
+        .run(&mut fake_external, &context, Arc::new(RuntimeFeesConfig::test()))
+        .expect("execution failed")
+}
+
+#[test]
+fn test_wasm_gas_profile_does_not_change_gas() {
+    let plain = run(false);
+    let profiled = run(true);
+    assert!(plain.aborted.is_none(), "{:?}", plain.aborted);
+    assert!(profiled.aborted.is_none(), "{:?}", profiled.aborted);
+    assert!(plain.wasm_gas_profile.is_none());
+    assert_eq!(plain.burnt_gas, profiled.burnt_gas);
+    assert_eq!(plain.used_gas, profiled.used_gas);
+    assert_eq!(plain.profile, profiled.profile);
+}
+
+#[test]
+fn test_wasm_gas_profile_attributes_gas_to_functions() {
+    let outcome = run(true);
+    let profile = outcome.wasm_gas_profile.expect("profile should be recorded");
+    assert!(profile.total_gas() > 0);
+    assert!(profile.total_gas() <= outcome.profile.get_wasm_cost());
+    let folded = profile.to_folded();
+    assert!(folded.lines().any(|line| line.starts_with("main ")), "{folded}");
+    assert!(folded.lines().any(|line| line.starts_with("main;fib ")), "{folded}");
+    assert!(folded.lines().any(|line| line.starts_with("main;fib;fib;fib ")), "{folded}");
+}
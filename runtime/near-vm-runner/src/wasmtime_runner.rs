@@ -14,6 +14,7 @@ use near_parameters::vm::VMKind;
 use near_parameters::RuntimeFeesConfig;
 use std::borrow::Cow;
 use std::cell::{RefCell, UnsafeCell};
+use std::collections::HashMap;
 use std::ffi::c_void;
 use std::sync::Arc;
 use wasmtime::ExternType::Func;
@@ -334,11 +335,20 @@ impl crate::runner::VM for WasmtimeVM {
                     self.config.limit_config.max_memory_pages,
                 )
                 .unwrap();
+                // Names are looked up in the original code, as the prepared code may have its
+                // custom sections discarded.
+                let function_names = match code.get_code() {
+                    Some(code) if self.config.wasm_gas_profiling => {
+                        Arc::new(crate::instrument::profile::function_names(code.code()))
+                    }
+                    _ => Default::default(),
+                };
                 let result = PreparationResult::Ready(ReadyContract {
                     store,
                     memory,
                     module,
                     method: method.into(),
+                    function_names,
                 });
                 Ok(PreparedContract { config, gas_counter, result })
             },
@@ -352,6 +362,7 @@ struct ReadyContract {
     memory: WasmtimeMemory,
     module: Module,
     method: String,
+    function_names: Arc<HashMap<u32, String>>,
 }
 
 struct PreparedContract {
@@ -375,8 +386,8 @@ impl crate::PreparedContract for VMResult<PreparedContract> {
         fees_config: Arc<RuntimeFeesConfig>,
     ) -> VMResult {
         let PreparedContract { config, gas_counter, result } = (*self)?;
-        let result_state = ExecutionResultState::new(&context, gas_counter, config);
-        let ReadyContract { mut store, mut memory, module, method } = match result {
+        let mut result_state = ExecutionResultState::new(&context, gas_counter, config);
+        let ReadyContract { mut store, mut memory, module, method, function_names } = match result {
             PreparationResult::Ready(r) => r,
             PreparationResult::OutcomeAbortButNopInOldProtocol(e) => {
                 return Ok(VMOutcome::abort_but_nop_outcome_in_old_protocol(result_state, e));
@@ -386,6 +397,9 @@ impl crate::PreparedContract for VMResult<PreparedContract> {
             }
         };
 
+        if let Some(profile) = &mut result_state.wasm_gas_profile {
+            profile.set_function_names(function_names);
+        }
         let memory_copy = memory.0;
         let config = Arc::clone(&result_state.config);
         let mut logic = VMLogic::new(ext, context, fees_config, result_state, &mut memory);
@@ -93,6 +93,9 @@ pub(crate) struct FuncGen<'a> {
     /// Cost for initializing the stack of the function
     stack_init_gas_cost: u64,
 
+    /// Cost per byte (or table element) processed by the bulk memory instructions
+    bulk_memory_byte_gas_cost: u64,
+
     /// Iterator over the gas instrumentation points
     gas_iter: iter::Peekable<iter::Zip<slice::Iter<'a, usize>, slice::Iter<'a, u64>>>,
 
@@ -429,6 +432,28 @@ impl<'a> FuncGen<'a> {
         self.machine.release_temp_gpr(current_burnt_reg);
     }
 
+    /// Charge `bulk_memory_byte_gas_cost` for each of the `len` bytes (or table elements)
+    /// processed by a bulk memory instruction. Must be called before `len` is released.
+    fn emit_bulk_memory_gas(&mut self, len: Location) {
+        if self.bulk_memory_byte_gas_cost == 0 {
+            return;
+        }
+        let cost_reg = self.machine.acquire_temp_gpr().unwrap();
+        // The 32-bit move zero-extends `len` to 64 bits. The product of two u32 values can't
+        // overflow u64, and the sum with the burnt gas is checked for overflow by `emit_gas`.
+        self.assembler.emit_mov(Size::S32, len, Location::GPR(cost_reg));
+        let byte_cost_reg = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Imm64(self.bulk_memory_byte_gas_cost),
+            Location::GPR(byte_cost_reg),
+        );
+        self.assembler.emit_imul(Size::S64, Location::GPR(byte_cost_reg), Location::GPR(cost_reg));
+        self.machine.release_temp_gpr(byte_cost_reg);
+        self.emit_gas(Location::GPR(cost_reg));
+        self.machine.release_temp_gpr(cost_reg);
+    }
+
     fn emit_trap(&mut self, code: TrapCode) {
         let label = self.assembler.get_label();
         self.assembler.emit_label(label);
@@ -1690,6 +1715,7 @@ impl<'a> FuncGen<'a> {
         local_func_index: LocalFunctionIndex,
         calling_convention: CallingConvention,
         stack_init_gas_cost: u64,
+        bulk_memory_byte_gas_cost: u64,
         gas_offsets: &'a [usize],
         gas_costs: &'a [u64],
         _gas_kinds: &'a [InstrumentationKind],
@@ -1730,6 +1756,7 @@ impl<'a> FuncGen<'a> {
             calling_convention,
             signature,
             stack_init_gas_cost,
+            bulk_memory_byte_gas_cost,
             gas_iter: gas_offsets.iter().zip(gas_costs.iter()).peekable(),
             stack_size: u32::try_from(stack_size).map_err(|_| CodegenError {
                 message: "one function has a stack more than u32::MAX deep".to_string(),
@@ -5088,6 +5115,7 @@ impl<'a> FuncGen<'a> {
                 let len = self.value_stack.pop().unwrap();
                 let src = self.value_stack.pop().unwrap();
                 let dst = self.value_stack.pop().unwrap();
+                self.emit_bulk_memory_gas(len);
                 self.machine.release_locations_only_regs(&[len, src, dst]);
 
                 self.assembler.emit_mov(
@@ -5138,6 +5166,7 @@ impl<'a> FuncGen<'a> {
                 let len = self.value_stack.pop().unwrap();
                 let src_pos = self.value_stack.pop().unwrap();
                 let dst_pos = self.value_stack.pop().unwrap();
+                self.emit_bulk_memory_gas(len);
                 self.machine.release_locations_only_regs(&[len, src_pos, dst_pos]);
 
                 let memory_index = MemoryIndex::new(src_mem as usize);
@@ -5172,6 +5201,7 @@ impl<'a> FuncGen<'a> {
                 let len = self.value_stack.pop().unwrap();
                 let val = self.value_stack.pop().unwrap();
                 let dst = self.value_stack.pop().unwrap();
+                self.emit_bulk_memory_gas(len);
                 self.machine.release_locations_only_regs(&[len, val, dst]);
 
                 let memory_index = MemoryIndex::new(mem as usize);
@@ -7534,6 +7564,7 @@ impl<'a> FuncGen<'a> {
                 let len = self.value_stack.pop().unwrap();
                 let src = self.value_stack.pop().unwrap();
                 let dest = self.value_stack.pop().unwrap();
+                self.emit_bulk_memory_gas(len);
                 self.machine.release_locations_only_regs(&[len, src, dest]);
 
                 self.assembler.emit_mov(
@@ -7593,6 +7624,7 @@ impl<'a> FuncGen<'a> {
                 let len = self.value_stack.pop().unwrap();
                 let src = self.value_stack.pop().unwrap();
                 let dest = self.value_stack.pop().unwrap();
+                self.emit_bulk_memory_gas(len);
                 self.machine.release_locations_only_regs(&[len, src, dest]);
 
                 self.assembler.emit_mov(
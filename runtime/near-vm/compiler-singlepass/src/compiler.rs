@@ -133,6 +133,7 @@ impl Compiler for SinglepassCompiler {
                         i,
                         calling_convention,
                         stack_init_gas_cost,
+                        tunables.bulk_memory_byte_gas_cost(),
                         &instrumentation.gas_offsets[i.index()],
                         &instrumentation.gas_costs[i.index()],
                         &instrumentation.gas_kinds[i.index()],
@@ -59,6 +59,10 @@ pub trait Tunables: Sync {
 
     /// Cost for initializing a stack frame
     fn stack_init_gas_cost(&self, frame_size: u64) -> u64;
+
+    /// Cost per byte (or table element) processed by a bulk memory instruction, charged on top of
+    /// the cost of the instruction itself
+    fn bulk_memory_byte_gas_cost(&self) -> u64;
 }
 
 #[doc(hidden)]
@@ -118,4 +122,8 @@ impl Tunables for TestTunables {
     fn stack_init_gas_cost(&self, _frame_size: u64) -> u64 {
         unimplemented!()
     }
+
+    fn bulk_memory_byte_gas_cost(&self) -> u64 {
+        unimplemented!()
+    }
 }
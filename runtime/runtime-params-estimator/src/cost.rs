@@ -228,6 +228,15 @@ pub enum Cost {
     /// attempt to cause slow loads and stores. The total time spent in the
     /// runtime is divided by the number of executed instructions.
     WasmInstruction,
+    /// Estimates `wasm_config.bulk_memory_byte_cost` which is charged for every
+    /// byte (or table element) processed by the bulk memory instructions, such
+    /// as `memory.copy` and `memory.fill`, on top of `WasmInstruction`.
+    ///
+    /// Estimation: Run a contract that executes `memory.copy` and
+    /// `memory.fill` in a loop, once with a large length and once with a
+    /// length of a single byte. The difference of the times spent in the
+    /// runtime is divided by the difference of the numbers of processed bytes.
+    WasmBulkMemoryByte,
 
     // # Reading and writing memory
     // The hosting runtime sometimes copies data between in and out of WASM
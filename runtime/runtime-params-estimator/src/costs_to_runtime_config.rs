@@ -19,6 +19,9 @@ pub fn costs_to_runtime_config(cost_table: &CostTable) -> anyhow::Result<Runtime
     let regular_op_cost = cost_table
         .get(Cost::WasmInstruction)
         .with_context(|| format!("undefined cost: {}", Cost::WasmInstruction))?;
+    let bulk_memory_byte_cost = cost_table
+        .get(Cost::WasmBulkMemoryByte)
+        .with_context(|| format!("undefined cost: {}", Cost::WasmBulkMemoryByte))?;
 
     // Take latest VM limit config, because estimation doesn't affect it.
     // Note that if you run estimation against stable version, it doesn't catch updates of nightly
@@ -33,6 +36,7 @@ pub fn costs_to_runtime_config(cost_table: &CostTable) -> anyhow::Result<Runtime
             ext_costs: ext_costs_config(cost_table)?,
             grow_mem_cost: 1,
             regular_op_cost: u32::try_from(regular_op_cost).unwrap(),
+            bulk_memory_byte_cost: u32::try_from(bulk_memory_byte_cost).unwrap(),
             limit_config: vm_limit_config,
             ..*latest_runtime_config.wasm_config
         }),
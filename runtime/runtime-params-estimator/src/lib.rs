@@ -213,6 +213,7 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::ActionDelegateExec, action_costs::delegate_exec),
     (Cost::HostFunctionCall, host_function_call),
     (Cost::WasmInstruction, wasm_instruction),
+    (Cost::WasmBulkMemoryByte, wasm_bulk_memory_byte),
     (Cost::DataReceiptCreationBase, data_receipt_creation_base),
     (Cost::DataReceiptCreationPerByte, data_receipt_creation_per_byte),
     (Cost::ReadMemoryBase, read_memory_base),
@@ -941,6 +942,72 @@ fn wasm_instruction(ctx: &mut EstimatorContext) -> GasCost {
     per_instruction
 }
 
+fn wasm_bulk_memory_byte(ctx: &mut EstimatorContext) -> GasCost {
+    let vm_kind = ctx.config.vm_kind;
+
+    let n_iters = 10;
+    let n_ops = 1_000;
+    let large_len = 64 * 1024;
+
+    // Bytes are copied from the second page to the first one, so the memory has two pages.
+    let code = wat::parse_str(format!(
+        r#"
+        (module
+            (memory 2)
+            (func $bulk (param $len i32)
+                (local $i i32)
+                (loop $loop
+                    (memory.copy (i32.const 0) (i32.const {large_len}) (local.get $len))
+                    (memory.fill (i32.const 0) (i32.const 42) (local.get $len))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $loop (i32.lt_u (local.get $i) (i32.const {n_ops})))))
+            (func (export "bulk_memory_large") (call $bulk (i32.const {large_len})))
+            (func (export "bulk_memory_small") (call $bulk (i32.const 1))))
+        "#
+    ))
+    .unwrap();
+
+    let code = ContractCode::new(code, None);
+    let mut fake_external = MockedExternal::with_code(code.clone_for_tests());
+    let config_store = RuntimeConfigStore::new(None);
+    let mut config =
+        near_parameters::vm::Config::clone(&config_store.get_config(PROTOCOL_VERSION).wasm_config);
+    // Bulk memory is only accepted starting with this version of preparation, which might not be
+    // stable yet.
+    config.limit_config.contract_prepare_version =
+        near_vm_runner::logic::ContractPrepareVersion::V3;
+    let config = Arc::new(config);
+    let fees = Arc::new(RuntimeFeesConfig::test());
+    let cache = MockContractRuntimeCache::default();
+
+    let mut measure = |method: &str| {
+        let mut run = || {
+            let context = create_context(vec![]);
+            let gas_counter = context.make_gas_counter(&config);
+            let vm_result = vm_kind
+                .runtime(config.clone())
+                .unwrap()
+                .prepare(&fake_external, Some(&cache), gas_counter, method)
+                .run(&mut fake_external, &context, Arc::clone(&fees))
+                .expect("fatal_error");
+            assert!(vm_result.aborted.is_none(), "{:?}", vm_result.aborted);
+        };
+        // Warm up, including the compilation of the contract.
+        run();
+        let start = GasCost::measure(ctx.config.metric);
+        for _ in 0..n_iters {
+            run();
+        }
+        start.elapsed()
+    };
+
+    let large = measure("bulk_memory_large");
+    let small = measure("bulk_memory_small");
+    // Both `memory.copy` and `memory.fill` process the given number of bytes.
+    let bytes = 2 * n_ops * (large_len - 1) * n_iters;
+    large.saturating_sub(&small, &NonNegativeTolerance::PER_MILLE) / bytes
+}
+
 fn read_memory_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "read_memory_10b_10k", ExtCosts::read_memory_base, 10_000)
 }
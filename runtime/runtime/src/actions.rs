@@ -38,10 +38,18 @@ use near_vm_runner::logic::errors::{
 };
 use near_vm_runner::logic::{VMContext, VMOutcome};
 use near_vm_runner::{precompile_contract, PreparedContract};
-use near_vm_runner::{ContractCode, ContractRuntimeCache};
+use near_vm_runner::{ContractCode, ContractRuntimeCache, WasmGasProfile};
 use near_wallet_contract::{wallet_contract, wallet_contract_magic_bytes};
 use std::sync::Arc;
 
+/// Wasm gas profile of a function call applied with `vm::Config::wasm_gas_profiling` enabled.
+#[derive(Debug)]
+pub struct FunctionCallWasmGasProfile {
+    pub account_id: AccountId,
+    pub method_name: String,
+    pub profile: WasmGasProfile,
+}
+
 /// Runs given function call with given context / apply state.
 pub(crate) fn execute_function_call(
     contract: Box<dyn near_vm_runner::PreparedContract>,
@@ -274,6 +282,13 @@ pub(crate) fn action_function_call(
     result.compute_usage = safe_add_compute(result.compute_usage, outcome.compute_usage)?;
     result.logs.extend(outcome.logs);
     result.profile.merge(&outcome.profile);
+    if let Some(profile) = outcome.wasm_gas_profile {
+        result.wasm_gas_profiles.push(FunctionCallWasmGasProfile {
+            account_id: account_id.clone(),
+            method_name: function_call.method_name.clone(),
+            profile,
+        });
+    }
     if execution_succeeded {
         // Fetch metadata for PromiseYield timeout queue
         let mut promise_yield_indices = get_promise_yield_indices(state_update).unwrap_or_default();
@@ -39,6 +39,7 @@ pub trait ViewRuntimeAdapter {
         method_name: &str,
         args: &[u8],
         logs: &mut Vec<String>,
+        wasm_gas_profile: Option<&mut String>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, crate::state_viewer::errors::CallFunctionError>;
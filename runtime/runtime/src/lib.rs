@@ -1,3 +1,4 @@
+pub use crate::actions::FunctionCallWasmGasProfile;
 use crate::actions::*;
 use crate::balance_checker::check_balance;
 use crate::config::{
@@ -202,6 +203,9 @@ pub struct ApplyResult {
     pub bandwidth_scheduler_state_hash: CryptoHash,
     /// Contracts accessed and deployed while applying the chunk.
     pub contract_updates: ContractUpdates,
+    /// Wasm gas profiles of the function calls applied in the chunk, only recorded when
+    /// `vm::Config::wasm_gas_profiling` is enabled.
+    pub wasm_gas_profiles: Vec<FunctionCallWasmGasProfile>,
 }
 
 #[derive(Debug)]
@@ -215,6 +219,9 @@ pub struct ActionResult {
     pub new_receipts: Vec<Receipt>,
     pub validator_proposals: Vec<ValidatorStake>,
     pub profile: Box<ProfileDataV3>,
+    /// Wasm gas profiles of the function calls, only recorded when
+    /// `vm::Config::wasm_gas_profiling` is enabled.
+    pub wasm_gas_profiles: Vec<FunctionCallWasmGasProfile>,
 }
 
 impl ActionResult {
@@ -236,6 +243,7 @@ impl ActionResult {
         self.profile.merge(&next_result.profile);
         self.result = next_result.result;
         self.logs.append(&mut next_result.logs);
+        self.wasm_gas_profiles.append(&mut next_result.wasm_gas_profiles);
         if let Ok(ReturnData::ReceiptIndex(ref mut receipt_index)) = self.result {
             // Shifting local receipt index to be global receipt index.
             *receipt_index += self.new_receipts.len() as u64;
@@ -263,6 +271,7 @@ impl Default for ActionResult {
             new_receipts: vec![],
             validator_proposals: vec![],
             profile: Default::default(),
+            wasm_gas_profiles: vec![],
         }
     }
 }
@@ -586,6 +595,7 @@ impl Runtime {
         receipt_sink: &mut ReceiptSink,
         validator_proposals: &mut Vec<ValidatorStake>,
         stats: &mut ApplyStats,
+        wasm_gas_profiles: &mut Vec<FunctionCallWasmGasProfile>,
         epoch_info_provider: &dyn EpochInfoProvider,
     ) -> Result<ExecutionOutcomeWithId, RuntimeError> {
         let _span = tracing::debug_span!(
@@ -910,6 +920,7 @@ impl Runtime {
         };
 
         Self::print_log(&result.logs);
+        wasm_gas_profiles.append(&mut result.wasm_gas_profiles);
 
         Ok(ExecutionOutcomeWithId {
             id: *receipt.receipt_id(),
@@ -1014,6 +1025,7 @@ impl Runtime {
             epoch_info_provider,
             ref pipeline_manager,
             ref mut stats,
+            ref mut wasm_gas_profiles,
             ..
         } = *processing_state;
         let account_id = receipt.receiver_id();
@@ -1083,6 +1095,7 @@ impl Runtime {
                                 receipt_sink,
                                 validator_proposals,
                                 stats,
+                                wasm_gas_profiles,
                                 epoch_info_provider,
                             )
                             .map(Some);
@@ -1138,6 +1151,7 @@ impl Runtime {
                             receipt_sink,
                             validator_proposals,
                             stats,
+                            wasm_gas_profiles,
                             epoch_info_provider,
                         )
                         .map(Some);
@@ -1190,6 +1204,7 @@ impl Runtime {
                             receipt_sink,
                             validator_proposals,
                             stats,
+                            wasm_gas_profiles,
                             epoch_info_provider,
                         )
                         .map(Some);
@@ -2211,6 +2226,7 @@ impl Runtime {
             bandwidth_requests,
             bandwidth_scheduler_state_hash,
             contract_updates,
+            wasm_gas_profiles: processing_state.wasm_gas_profiles,
         })
     }
 }
@@ -2340,6 +2356,7 @@ fn missing_chunk_apply_result(
             .map(|o| o.scheduler_state_hash)
             .unwrap_or_default(),
         contract_updates,
+        wasm_gas_profiles: vec![],
     });
 }
 
@@ -2544,6 +2561,7 @@ impl<'a> ApplyProcessingState<'a> {
             total: self.total,
             stats: self.stats,
             outcomes: Vec::new(),
+            wasm_gas_profiles: Vec::new(),
             metrics: metrics::ApplyMetrics::default(),
             local_receipts: VecDeque::new(),
             incoming_receipts,
@@ -2564,6 +2582,7 @@ struct ApplyProcessingReceiptState<'a> {
     total: TotalResourceGuard,
     stats: ApplyStats,
     outcomes: Vec<ExecutionOutcomeWithId>,
+    wasm_gas_profiles: Vec<FunctionCallWasmGasProfile>,
     metrics: ApplyMetrics,
     local_receipts: VecDeque<Receipt>,
     incoming_receipts: &'a [Receipt],
@@ -2747,6 +2766,7 @@ pub mod estimator {
             &mut receipt_sink,
             validator_proposals,
             stats,
+            &mut Vec::new(),
             epoch_info_provider,
         );
         outgoing_receipts.extend(receipt_sink.into_outgoing_receipts().into_iter());
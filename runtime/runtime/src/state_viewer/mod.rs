@@ -74,6 +74,11 @@ impl TrieViewer {
         Self { state_size_limit, max_gas_burnt_view }
     }
 
+    /// Upper bound of the byte size of contract state that is still viewable.
+    pub fn state_size_limit(&self) -> Option<u64> {
+        self.state_size_limit
+    }
+
     pub fn view_account(
         &self,
         state_update: &TrieUpdate,
@@ -189,6 +194,7 @@ impl TrieViewer {
         method_name: &str,
         args: &[u8],
         logs: &mut Vec<String>,
+        wasm_gas_profile: Option<&mut String>,
         epoch_info_provider: &dyn EpochInfoProvider,
     ) -> Result<Vec<u8>, errors::CallFunctionError> {
         let now = Instant::now();
@@ -203,7 +209,10 @@ impl TrieViewer {
         let public_key = PublicKey::empty(KeyType::ED25519);
         let empty_hash = CryptoHash::default();
         let mut receipt_manager = ReceiptManager::default();
-        let config_store = RuntimeConfigStore::new(None);
+        let mut config_store = RuntimeConfigStore::new(None);
+        if wasm_gas_profile.is_some() {
+            config_store.enable_wasm_gas_profiling();
+        }
         let config = config_store.get_config(PROTOCOL_VERSION);
         let apply_state = ApplyState {
             apply_reason: ApplyChunkReason::ViewTrackedShard,
@@ -289,6 +298,9 @@ impl TrieViewer {
             (elapsed.as_secs() as f64 / 1_000.0) + f64::from(elapsed.subsec_nanos()) / 1_000_000.0;
         let time_str = format!("{:.*}ms", 2, time_ms);
 
+        if let (Some(out), Some(profile)) = (wasm_gas_profile, &outcome.wasm_gas_profile) {
+            *out = profile.to_folded();
+        }
         if let Some(err) = outcome.aborted {
             logs.extend(outcome.logs);
             let message = format!("wasm execution failed with error: {:?}", err);
@@ -3,13 +3,14 @@ use crate::near_primitives::account::Account;
 use crate::VerificationResult;
 use near_crypto::key_conversion::is_valid_staking_key;
 use near_parameters::RuntimeConfig;
-use near_primitives::account::AccessKeyPermission;
+use near_primitives::account::{AccessKey, AccessKeyPermission, FunctionCallPermission};
 use near_primitives::action::delegate::SignedDelegateAction;
 use near_primitives::checked_feature;
 use near_primitives::errors::{
     ActionsValidationError, InvalidAccessKeyError, InvalidTxError, ReceiptValidationError,
 };
 use near_primitives::receipt::{ActionReceipt, DataReceipt, Receipt, ReceiptEnum};
+use near_primitives::sandbox::impersonation::is_impersonated;
 use near_primitives::transaction::DeleteAccountAction;
 use near_primitives::transaction::{
     Action, AddKeyAction, DeployContractAction, FunctionCallAction, SignedTransaction, StakeAction,
@@ -105,6 +106,7 @@ pub fn validate_transaction(
     let signer_id = transaction.signer_id();
 
     if verify_signature
+        && !is_impersonated(signer_id)
         && !signed_transaction
             .signature
             .verify(signed_transaction.get_hash().as_ref(), transaction.public_key())
@@ -165,8 +167,28 @@ pub fn verify_and_charge_transaction(
             return Err(InvalidTxError::SignerDoesNotExist { signer_id: signer_id.clone() });
         }
     };
+    // Transactions of impersonated sandbox accounts are taken as signed with a full access key,
+    // whatever the permission of the access key they name. Their nonces are still tracked in the
+    // access key so that they can't be replayed. If the account has no such access key, a key
+    // without any allowance is added to record the nonce.
+    let impersonated = is_impersonated(signer_id);
     let mut access_key = match get_access_key(state_update, signer_id, transaction.public_key())? {
         Some(access_key) => access_key,
+        None if impersonated => {
+            let access_key = impersonation_nonce_key(signer_id);
+            let storage_usage = borsh::object_length(transaction.public_key()).unwrap() as u64
+                + borsh::object_length(&access_key).unwrap() as u64
+                + config.fees.storage_usage_config.num_extra_bytes_record;
+            signer.set_storage_usage(
+                signer.storage_usage().checked_add(storage_usage).ok_or_else(|| {
+                    StorageError::StorageInconsistentState(format!(
+                        "Storage usage integer overflow for account {}",
+                        signer_id
+                    ))
+                })?,
+            );
+            access_key
+        }
         None => {
             return Err(InvalidTxError::InvalidAccessKeyError(
                 InvalidAccessKeyError::AccessKeyNotFound {
@@ -209,8 +231,8 @@ pub fn verify_and_charge_transaction(
         }
     })?);
 
-    if let AccessKeyPermission::FunctionCall(ref mut function_call_permission) =
-        access_key.permission
+    if let (false, AccessKeyPermission::FunctionCall(ref mut function_call_permission)) =
+        (impersonated, &mut access_key.permission)
     {
         if let Some(ref mut allowance) = function_call_permission.allowance {
             *allowance = allowance.checked_sub(total_cost).ok_or_else(|| {
@@ -238,7 +260,9 @@ pub fn verify_and_charge_transaction(
         }
     };
 
-    if let AccessKeyPermission::FunctionCall(ref function_call_permission) = access_key.permission {
+    if let (false, AccessKeyPermission::FunctionCall(ref function_call_permission)) =
+        (impersonated, &access_key.permission)
+    {
         if transaction.actions().len() != 1 {
             return Err(InvalidTxError::InvalidAccessKeyError(
                 InvalidAccessKeyError::RequiresFullAccess,
@@ -288,6 +312,19 @@ pub fn verify_and_charge_transaction(
     Ok(VerificationResult { gas_burnt, gas_remaining, receipt_gas_price, burnt_amount })
 }
 
+/// Access key recording the nonces of the transactions of an impersonated account signed with a
+/// key the account doesn't have. It can't pay for any transaction on its own.
+fn impersonation_nonce_key(account_id: &AccountId) -> AccessKey {
+    AccessKey {
+        nonce: 0,
+        permission: AccessKeyPermission::FunctionCall(FunctionCallPermission {
+            allowance: Some(0),
+            receiver_id: account_id.to_string(),
+            method_names: vec![],
+        }),
+    }
+}
+
 /// Validates a given receipt. Checks validity of the Action or Data receipt.
 pub(crate) fn validate_receipt(
     limit_config: &LimitConfig,
@@ -1037,6 +1074,68 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "sandbox")]
+    #[test]
+    fn test_validate_transaction_impersonated() {
+        use near_primitives::errors::RuntimeError;
+        use near_primitives::sandbox::impersonation::impersonate_accounts;
+        use testlib::runtime_utils::carol_account;
+
+        let config = RuntimeConfig::test();
+        // Carol has no access keys and the transaction is not signed by her.
+        let (signer, mut state_update, gas_price) =
+            setup_accounts(vec![(carol_account(), TESTING_INIT_BALANCE, 0, vec![], false, false)]);
+        let mut tx = SignedTransaction::send_money(
+            1,
+            carol_account(),
+            bob_account(),
+            &*signer,
+            100,
+            CryptoHash::default(),
+        );
+        tx.signature = signer.sign(CryptoHash::default().as_ref());
+
+        assert_err_both_validations(
+            &config,
+            &mut state_update,
+            gas_price,
+            &tx,
+            InvalidTxError::InvalidSignature,
+        );
+
+        let _guard = impersonate_accounts(vec![carol_account()]);
+        verify_and_charge_transaction(
+            &config,
+            &mut state_update,
+            gas_price,
+            &tx,
+            true,
+            None,
+            PROTOCOL_VERSION,
+        )
+        .expect("valid transaction");
+        // The nonce of the transaction is recorded in a key without any allowance.
+        let access_key =
+            get_access_key(&state_update, &carol_account(), &signer.public_key()).unwrap().unwrap();
+        assert_eq!(access_key.nonce, 1);
+        assert_eq!(access_key.permission, impersonation_nonce_key(&carol_account()).permission);
+
+        // The transaction can't be replayed.
+        assert_eq!(
+            verify_and_charge_transaction(
+                &config,
+                &mut state_update,
+                gas_price,
+                &tx,
+                true,
+                None,
+                PROTOCOL_VERSION,
+            )
+            .expect_err("expected an error"),
+            RuntimeError::InvalidTxError(InvalidTxError::InvalidNonce { tx_nonce: 1, ak_nonce: 1 }),
+        );
+    }
+
     #[test]
     fn test_validate_transaction_invalid_balance_overflow() {
         let config = RuntimeConfig::test();
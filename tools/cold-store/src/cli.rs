@@ -5,10 +5,15 @@ use borsh::BorshDeserialize;
 use clap;
 use near_chain_configs::GenesisValidationMode;
 use near_epoch_manager::{EpochManager, EpochManagerAdapter, EpochManagerHandle};
-use near_primitives::block::Tip;
+use near_primitives::block::{BlockHeader, Tip};
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::hash::CryptoHash;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::BlockHeight;
 use near_store::archive::cold_storage::{copy_all_data_to_cold, update_cold_db, update_cold_head};
+use near_store::archive::state_history::{
+    get_state_history_range, init_state_history, update_state_history,
+};
 use near_store::metadata::DbKind;
 use near_store::{DBCol, NodeStorage, Store, StoreOpener};
 use near_store::{COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY, TAIL_KEY};
@@ -57,6 +62,11 @@ enum SubCommand {
     /// Modifies cold db from config to be considered not initialised.
     /// Doesn't actually delete any data, except for HEAD and COLD_HEAD in BlockMisc.
     ResetCold(ResetColdCmd),
+    /// Initialise the state history index and/or catch it up with the cold head.
+    /// The index is used to answer historical queries without traversing the trie
+    /// and is kept up to date by the node if `enable_state_history_index` is set.
+    /// Requires the `--readwrite` flag.
+    BackfillStateHistory(BackfillStateHistoryCmd),
 }
 
 impl ColdStoreCommand {
@@ -96,6 +106,7 @@ impl ColdStoreCommand {
             SubCommand::PrepareHot(cmd) => cmd.run(&storage, &home_dir, &near_config),
             SubCommand::CheckStateRoot(cmd) => cmd.run(&storage),
             SubCommand::ResetCold(cmd) => cmd.run(&storage),
+            SubCommand::BackfillStateHistory(cmd) => cmd.run(&storage, epoch_manager.as_ref()),
         }
     }
 
@@ -675,3 +686,69 @@ impl ResetColdCmd {
         Ok(())
     }
 }
+
+#[derive(clap::Parser)]
+struct BackfillStateHistoryCmd {
+    /// Initialise the index with the state at the given height, replacing the
+    /// existing index. Required if the index hasn't been initialised yet.
+    #[clap(long)]
+    start_height: Option<BlockHeight>,
+}
+
+impl BackfillStateHistoryCmd {
+    pub fn run(
+        self,
+        storage: &NodeStorage,
+        epoch_manager: &EpochManagerHandle,
+    ) -> anyhow::Result<()> {
+        let cold_db = storage.cold_db().context("Cold storage is not configured")?;
+        let cold_store = storage.get_cold_store().context("Cold storage is not configured")?;
+        // The split store is used to read data which may still be only in the hot store.
+        let split_store = storage.get_split_store().context("Cold storage is not configured")?;
+        let cold_head = cold_store
+            .get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?
+            .context("The cold head is missing")?
+            .height;
+
+        if let Some(start_height) = self.start_height {
+            anyhow::ensure!(
+                start_height <= cold_head,
+                "Start height {start_height} is ahead of the cold head {cold_head}"
+            );
+            let block_hash = split_store
+                .get_ser::<CryptoHash>(DBCol::BlockHeight, &start_height.to_le_bytes())?
+                .with_context(|| format!("No block at height {start_height}"))?;
+            // Block headers are used rather than the epoch manager because block
+            // infos of old blocks are only in the cold store.
+            let header = split_store
+                .get_ser::<BlockHeader>(DBCol::BlockHeader, block_hash.as_ref())?
+                .with_context(|| format!("Block header {block_hash} is missing"))?;
+            let shard_layout = epoch_manager.get_shard_layout(header.epoch_id())?;
+            let mut state_roots = vec![];
+            for shard_uid in shard_layout.shard_uids() {
+                let key = [block_hash.as_ref(), &shard_uid.to_bytes()].concat();
+                let chunk_extra =
+                    split_store.get_ser::<ChunkExtra>(DBCol::ChunkExtra, &key)?.with_context(
+                        || format!("Chunk extra of {block_hash} {shard_uid} is missing"),
+                    )?;
+                state_roots.push((shard_uid, *chunk_extra.state_root()));
+            }
+            println!("Indexing the state at height {start_height}");
+            init_state_history(cold_db, &split_store, &state_roots, start_height)?;
+        }
+
+        let mut range = get_state_history_range(&cold_store)?
+            .context("The state history index is not initialised, specify --start-height")?;
+        println!("Indexing state changes from height {} to {}", range.head + 1, cold_head);
+        while range.head < cold_head {
+            let height = range.head + 1;
+            update_state_history(cold_db, &split_store, range.head, height)?;
+            range.head = height;
+            if height % 10_000 == 0 {
+                println!("Indexed state changes up to height {height}");
+            }
+        }
+        println!("The state history index covers heights {} to {}", range.tail, range.head);
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use near_chain::ChainStore;
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::block::Tip;
+use near_primitives::types::BlockHeight;
+use near_store::{DBCol, COLD_HEAD_KEY};
+use nearcore::config::load_config;
+use nearcore::open_storage;
+
+use crate::block_iterators::{
+    make_block_iterator_from_command_args, CommandArgs, LastNBlocksIterator,
+};
+
+/// Indexes the activity of accounts in blocks processed before `save_account_activity` has been
+/// enabled.  The state changes and execution outcomes of the blocks must still be in the
+/// database, so the full history can only be indexed on archival nodes.  On split storage the
+/// entries of blocks up to the cold head are written to the cold database.
+#[derive(Parser)]
+pub(crate) struct BackfillAccountActivityCommand {
+    /// Index the last N blocks in the blockchain
+    #[arg(long)]
+    last_blocks: Option<u64>,
+
+    /// Index blocks from the given block height, inclusive
+    #[arg(long)]
+    from_block_height: Option<BlockHeight>,
+
+    /// Index blocks up to the given block height, inclusive
+    #[arg(long)]
+    to_block_height: Option<BlockHeight>,
+}
+
+impl BackfillAccountActivityCommand {
+    pub(crate) fn run(
+        &self,
+        home: &PathBuf,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let mut near_config = load_config(home, genesis_validation)?;
+        let node_storage = open_storage(&home, &mut near_config)?;
+        let hot_store = node_storage.get_hot_store();
+        // Reads of old blocks go to the cold database.  The entries of blocks which have already
+        // been copied to the cold database are written there as well, as they would never be
+        // copied nor garbage collected if written to the hot one.
+        let store = node_storage.get_split_store().unwrap_or_else(|| hot_store.clone());
+        let cold_store = node_storage.get_cold_store();
+        let cold_head_height = match &cold_store {
+            Some(_) => {
+                hot_store.get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY)?.map(|tip| tip.height)
+            }
+            None => None,
+        };
+        let chain_store =
+            Rc::new(ChainStore::new(store, near_config.genesis.config.genesis_height, false));
+
+        let blocks_iter = make_block_iterator_from_command_args(
+            CommandArgs {
+                last_blocks: self.last_blocks,
+                from_block_height: self.from_block_height,
+                to_block_height: self.to_block_height,
+            },
+            chain_store.clone(),
+        )
+        .unwrap_or_else(|| {
+            println!("No arguments, defaulting to last 100 blocks");
+            Box::new(LastNBlocksIterator::new(100, chain_store.clone()))
+        });
+
+        let mut blocks_count: u64 = 0;
+        for block in blocks_iter {
+            let target_store = match (&cold_store, cold_head_height) {
+                (Some(cold_store), Some(cold_head_height))
+                    if block.header().height() <= cold_head_height =>
+                {
+                    cold_store
+                }
+                _ => &hot_store,
+            };
+            chain_store.save_account_activity(block.hash(), target_store)?;
+            blocks_count += 1;
+            if blocks_count % 10000 == 0 {
+                println!(
+                    "Indexed {} blocks, last height {}",
+                    blocks_count,
+                    block.header().height()
+                );
+            }
+        }
+        println!("Indexed account activity in {} blocks", blocks_count);
+        Ok(())
+    }
+}
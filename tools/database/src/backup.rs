@@ -0,0 +1,375 @@
+use anyhow::Context;
+use near_chain::store_validator::StoreValidator;
+use near_chain_configs::GenesisValidationMode;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
+use near_store::backup::BackupMetadata;
+use near_store::NodeStorage;
+use nearcore::config::load_config;
+use nearcore::{open_storage, NightshadeRuntime, NightshadeRuntimeExt};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args)]
+pub(crate) struct BackupCommand {
+    #[clap(subcommand)]
+    subcmd: BackupSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum BackupSubCommand {
+    /// Take a new incremental backup of the database.  The node must be
+    /// stopped; running nodes take backups on their own if `store.backup` is
+    /// configured.
+    Create(CreateBackupCmd),
+    /// List the backups in the backup directory.
+    List(BackupDirArgs),
+    /// Check that the files of a backup are present and not corrupted.
+    Verify(VerifyBackupCmd),
+}
+
+#[derive(clap::Args)]
+struct BackupDirArgs {
+    /// Directory of the backups.  Defaults to `store.backup.path` from the
+    /// config.
+    #[clap(long)]
+    backup_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct CreateBackupCmd {
+    #[clap(flatten)]
+    backup_dir: BackupDirArgs,
+    /// Number of most recent backups to keep.  Defaults to
+    /// `store.backup.max_backups` from the config.
+    #[clap(long)]
+    max_backups: Option<usize>,
+}
+
+#[derive(clap::Args)]
+struct VerifyBackupCmd {
+    #[clap(flatten)]
+    backup_dir: BackupDirArgs,
+    /// Id of the backup.  Defaults to the most recent backup.
+    #[clap(long)]
+    backup_id: Option<u32>,
+}
+
+impl BackupDirArgs {
+    fn get_path(&self, home: &Path, near_config: &nearcore::NearConfig) -> anyhow::Result<PathBuf> {
+        match (&self.backup_dir, &near_config.config.store.backup) {
+            (Some(backup_dir), _) => Ok(home.join(backup_dir)),
+            (None, Some(backup)) => Ok(backup.get_path(home)),
+            (None, None) => anyhow::bail!("--backup-dir is required if store.backup isn't set"),
+        }
+    }
+}
+
+fn print_backup(metadata: &BackupMetadata) {
+    println!(
+        "backup {}: timestamp {}, size {}, db version {}, db kind {}, head {}",
+        metadata.backup_id,
+        metadata.timestamp,
+        bytesize::ByteSize::b(metadata.size),
+        metadata.db_version,
+        metadata.db_kind,
+        match (metadata.head_height, metadata.head_hash) {
+            (Some(height), Some(hash)) => format!("#{height} {hash}"),
+            _ => "none".to_string(),
+        },
+    );
+}
+
+impl BackupCommand {
+    pub(crate) fn run(
+        &self,
+        home: &PathBuf,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let mut near_config = load_config(home, genesis_validation)?;
+        match &self.subcmd {
+            BackupSubCommand::Create(cmd) => {
+                let backup_dir = cmd.backup_dir.get_path(home, &near_config)?;
+                let max_backups = cmd
+                    .max_backups
+                    .or(near_config.config.store.backup.as_ref().map(|backup| backup.max_backups))
+                    .context("--max-backups is required if store.backup isn't set")?;
+                let storage = open_storage(home, &mut near_config)?;
+                anyhow::ensure!(
+                    storage.get_cold_store().is_none(),
+                    "database backups are not supported with split storage"
+                );
+                let metadata = near_store::backup::create_backup(
+                    &storage.get_hot_store(),
+                    &backup_dir,
+                    max_backups,
+                )?;
+                print_backup(&metadata);
+            }
+            BackupSubCommand::List(args) => {
+                let backup_dir = args.get_path(home, &near_config)?;
+                for metadata in near_store::backup::list_backups(&backup_dir)? {
+                    print_backup(&metadata);
+                }
+            }
+            BackupSubCommand::Verify(cmd) => {
+                let backup_dir = cmd.backup_dir.get_path(home, &near_config)?;
+                let metadata = near_store::backup::verify_backup(&backup_dir, cmd.backup_id)?;
+                print_backup(&metadata);
+                println!("Backup {} is valid", metadata.backup_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Restores the database from a backup.
+///
+/// The version and kind of the database recorded in the metadata of the
+/// backup are checked first.  The backup is then restored next to the
+/// database directory and opened, which
+/// checks its version and kind against the node and runs migrations if it’s
+/// older.  Then the store validator checks the consistency of its contents.
+/// Only if all checks pass is the restored database moved to the database
+/// directory, so the node can never be started on a database that failed the
+/// checks.  The node must be stopped.
+#[derive(clap::Args)]
+pub(crate) struct RestoreCommand {
+    #[clap(flatten)]
+    backup_dir: BackupDirArgs,
+    /// Id of the backup to restore.  Defaults to the most recent backup.
+    #[clap(long)]
+    backup_id: Option<u32>,
+    /// Replace the existing database.  The existing database is kept next to
+    /// the restored one with a `.before-restore` suffix rather than deleted.
+    #[clap(long)]
+    replace_existing: bool,
+}
+
+impl RestoreCommand {
+    pub(crate) fn run(
+        &self,
+        home: &PathBuf,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let mut near_config = load_config(home, genesis_validation)?;
+        anyhow::ensure!(
+            near_config.config.cold_store.is_none(),
+            "database backups are not supported with split storage"
+        );
+        let backup_dir = self.backup_dir.get_path(home, &near_config)?;
+        let db_path = NodeStorage::opener(home, &near_config.config.store, None).path().to_owned();
+        let file_name = db_path.file_name().context("invalid database path")?.to_owned();
+        let with_suffix = |suffix: &str| {
+            let mut file_name = file_name.clone();
+            file_name.push(suffix);
+            db_path.with_file_name(file_name)
+        };
+        let old_db_path = with_suffix(".before-restore");
+        if db_path.exists() {
+            anyhow::ensure!(
+                self.replace_existing,
+                "database already exists at {}; pass --replace-existing to replace it",
+                db_path.display()
+            );
+            anyhow::ensure!(
+                !old_db_path.exists(),
+                "{} already exists; remove it before restoring again",
+                old_db_path.display()
+            );
+        }
+
+        // A leftover of an interrupted restore is never used by the node, so
+        // it's safe to remove.
+        let restore_path = with_suffix(".restore");
+        if restore_path.exists() {
+            std::fs::remove_dir_all(&restore_path)?;
+        }
+        let metadata = near_store::backup::restore_backup(
+            &backup_dir,
+            self.backup_id,
+            &restore_path,
+            near_config.client_config.archive,
+        )?;
+        print_backup(&metadata);
+
+        let result = validate_restored_db(home, &mut near_config, &restore_path);
+        if let Err(err) = result {
+            std::fs::remove_dir_all(&restore_path)?;
+            return Err(err.context("restored database failed validation"));
+        }
+
+        if db_path.exists() {
+            std::fs::rename(&db_path, &old_db_path)?;
+            println!("Moved the existing database to {}", old_db_path.display());
+        }
+        std::fs::rename(&restore_path, &db_path)?;
+        println!("Restored backup {} to {}", metadata.backup_id, db_path.display());
+        Ok(())
+    }
+}
+
+/// Opens the restored database, which checks and migrates its version and
+/// checks its kind, and runs the store validator on it.
+fn validate_restored_db(
+    home: &Path,
+    near_config: &mut nearcore::NearConfig,
+    db_path: &Path,
+) -> anyhow::Result<()> {
+    near_config.config.store.path = Some(db_path.to_owned());
+    let storage = open_storage(home, near_config)?;
+    let store = storage.get_hot_store();
+    let epoch_manager =
+        EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home));
+    let shard_tracker = ShardTracker::new(
+        TrackedConfig::from_config(&near_config.client_config),
+        epoch_manager.clone(),
+    );
+    let runtime =
+        NightshadeRuntime::from_config(home, store.clone(), near_config, epoch_manager.clone())
+            .context("could not create the transaction runtime")?;
+    let mut store_validator = StoreValidator::new(
+        near_config.validator_signer.get().map(|signer| signer.validator_id().clone()),
+        near_config.genesis.config.clone(),
+        epoch_manager,
+        shard_tracker,
+        runtime,
+        store,
+        near_config.client_config.archive,
+    );
+    println!("Validating the restored database, this may take a while");
+    store_validator.validate();
+    for error in &store_validator.errors {
+        println!("{}  {}  {}", error.col, error.key, error.err);
+    }
+    anyhow::ensure!(
+        !store_validator.is_failed(),
+        "store validator found {} errors",
+        store_validator.num_failed()
+    );
+    println!("Conditions validated: {}", store_validator.tests_done());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackupDirArgs, RestoreCommand};
+    use near_async::messaging::{noop, IntoMultiSender};
+    use near_async::time::Clock;
+    use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
+    use near_chain::types::ChainConfig;
+    use near_chain::{Chain, ChainGenesis, DoomslugThresholdMode};
+    use near_chain_configs::{GenesisValidationMode, MutableConfigValue};
+    use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+    use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+    use near_primitives::types::EpochId;
+    use near_store::genesis::initialize_sharded_genesis_state;
+    use near_store::{DBCol, Mode, NodeStorage};
+    use nearcore::config::load_config;
+    use nearcore::{open_storage, NightshadeRuntime, NightshadeRuntimeExt};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    /// Initializes the config and a database with the genesis block in `home`.
+    fn init_node(home: &Path) {
+        nearcore::init_configs(
+            home,
+            Some("localnet".to_string()),
+            None,
+            Some("test0"),
+            1,
+            false,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut near_config = load_config(home, GenesisValidationMode::UnsafeFast).unwrap();
+        let store = open_storage(home, &mut near_config).unwrap().get_hot_store();
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home));
+        let genesis_epoch_config = epoch_manager.get_epoch_config(&EpochId::default()).unwrap();
+        initialize_sharded_genesis_state(
+            store.clone(),
+            &near_config.genesis,
+            &genesis_epoch_config,
+            Some(home),
+        );
+        let shard_tracker = ShardTracker::new(
+            TrackedConfig::from_config(&near_config.client_config),
+            epoch_manager.clone(),
+        );
+        let runtime =
+            NightshadeRuntime::from_config(home, store, &near_config, epoch_manager.clone())
+                .unwrap();
+        Chain::new(
+            Clock::real(),
+            epoch_manager,
+            shard_tracker,
+            runtime,
+            &ChainGenesis::new(&near_config.genesis.config),
+            DoomslugThresholdMode::NoApprovals,
+            ChainConfig::test(),
+            None,
+            Arc::new(RayonAsyncComputationSpawner),
+            MutableConfigValue::new(None, "validator_signer"),
+            noop().into_multi_sender(),
+        )
+        .unwrap();
+    }
+
+    fn open_store(home: &Path) -> near_store::Store {
+        let near_config = load_config(home, GenesisValidationMode::UnsafeFast).unwrap();
+        NodeStorage::opener(home, &near_config.config.store, None)
+            .open_in_mode(Mode::ReadWrite)
+            .unwrap()
+            .get_hot_store()
+    }
+
+    #[test]
+    fn test_restore() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        init_node(home);
+        let backup_dir = home.join("backups");
+        let store = open_store(home);
+        let metadata = near_store::backup::create_backup(&store, &backup_dir, 1).unwrap();
+        // Written after the backup, so it must not be in the restored database.
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::BlockMisc, b"after-backup", b"1");
+        store_update.commit().unwrap();
+        drop(store);
+
+        let mut cmd = RestoreCommand {
+            backup_dir: BackupDirArgs { backup_dir: Some(backup_dir.clone()) },
+            backup_id: None,
+            replace_existing: false,
+        };
+        let home_buf = home.to_path_buf();
+        // The existing database is only replaced if requested.
+        assert!(cmd.run(&home_buf, GenesisValidationMode::UnsafeFast).is_err());
+        cmd.replace_existing = true;
+        cmd.run(&home_buf, GenesisValidationMode::UnsafeFast).unwrap();
+        assert!(!open_store(home).exists(DBCol::BlockMisc, b"after-backup").unwrap());
+        let old_db_path = home.join("data.before-restore");
+        assert!(old_db_path.exists());
+        std::fs::remove_dir_all(&old_db_path).unwrap();
+
+        // A backup of a newer database version is rejected before the
+        // existing database is touched.
+        let mut metadata = metadata;
+        metadata.db_version = near_store::metadata::DB_VERSION + 1;
+        let metadata_path =
+            backup_dir.join("neard-metadata").join(format!("{}.json", metadata.backup_id));
+        std::fs::write(&metadata_path, serde_json::to_vec(&metadata).unwrap()).unwrap();
+        let err = cmd.run(&home_buf, GenesisValidationMode::UnsafeFast).unwrap_err();
+        assert!(err.to_string().contains("newer than the supported version"), "{err}");
+        assert!(!old_db_path.exists());
+        assert!(!home.join("data.restore").exists());
+        open_store(home);
+    }
+}
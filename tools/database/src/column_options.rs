@@ -0,0 +1,65 @@
+use near_store::config::ColumnOptions;
+use near_store::{DBCol, StoreConfig};
+use strum::IntoEnumIterator;
+
+/// Prints the effective RocksDB options of the columns, i.e. the defaults with
+/// `store.column_overrides` from the config applied.
+#[derive(clap::Args)]
+pub(crate) struct ShowColumnOptionsCommand {
+    /// Show options of this column only, e.g. `State`.
+    #[clap(long)]
+    column: Option<String>,
+    /// Show options of the cold database instead of the hot one.
+    #[clap(long)]
+    cold: bool,
+    /// Show only the columns with overridden options.
+    #[clap(long)]
+    overridden_only: bool,
+}
+
+fn format_options(options: &ColumnOptions) -> String {
+    let per_level =
+        options.compression_per_level.iter().map(ToString::to_string).collect::<Vec<_>>();
+    format!(
+        "compression [{}], bottommost {}, level {}, zstd dictionary {} (bottommost {}), \
+         block size {}, bloom filter bits {}, block cache {}, compaction {}",
+        per_level.join(", "),
+        options.bottommost_compression,
+        options.compression_level.map_or("default".to_string(), |level| level.to_string()),
+        options.zstd_dictionary_size,
+        options.bottommost_zstd_dictionary_size,
+        options.block_size,
+        options.bloom_filter_bits_per_key,
+        options.block_cache_size,
+        options.compaction_style,
+    )
+}
+
+impl ShowColumnOptionsCommand {
+    pub(crate) fn run(
+        &self,
+        store_config: &StoreConfig,
+        cold_store_config: Option<&StoreConfig>,
+    ) -> anyhow::Result<()> {
+        let store_config = if self.cold {
+            cold_store_config.ok_or_else(|| anyhow::anyhow!("cold_store is not configured"))?
+        } else {
+            store_config
+        };
+        let columns = match &self.column {
+            Some(name) => vec![DBCol::iter()
+                .find(|col| col.to_string() == *name)
+                .ok_or_else(|| anyhow::anyhow!("unknown column {name}"))?],
+            None => DBCol::iter().collect(),
+        };
+        for col in columns {
+            let overridden = store_config.column_overrides.contains_key(&col);
+            if self.overridden_only && !overridden {
+                continue;
+            }
+            let marker = if overridden { " (overridden)" } else { "" };
+            println!("{col}{marker}: {}", format_options(&store_config.column_options(col)));
+        }
+        Ok(())
+    }
+}
@@ -4,6 +4,9 @@ use crate::analyse_gas_usage::AnalyseGasUsageCommand;
 use crate::analyse_high_load::HighLoadStatsCommand;
 use crate::analyze_contract_sizes::AnalyzeContractSizesCommand;
 use crate::analyze_delayed_receipt::AnalyzeDelayedReceiptCommand;
+use crate::backfill_account_activity::BackfillAccountActivityCommand;
+use crate::backup::{BackupCommand, RestoreCommand};
+use crate::column_options::ShowColumnOptionsCommand;
 use crate::compact::RunCompactionCommand;
 use crate::corrupt::CorruptStateSnapshotCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
@@ -64,6 +67,18 @@ enum SubCommand {
 
     /// Perform on demand resharding V2
     Resharding(ReshardingV2Command),
+
+    /// Index the activity of accounts in past blocks
+    BackfillAccountActivity(BackfillAccountActivityCommand),
+
+    /// Take, list and verify incremental backups of the database
+    Backup(BackupCommand),
+
+    /// Restore the database from a backup and validate it
+    Restore(RestoreCommand),
+
+    /// Show the effective RocksDB options of the columns
+    ShowColumnOptions(ShowColumnOptionsCommand),
 }
 
 impl DatabaseCommand {
@@ -93,6 +108,13 @@ impl DatabaseCommand {
                 let near_config = load_config(home, genesis_validation);
                 cmd.run(near_config, home)
             }
+            SubCommand::BackfillAccountActivity(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::Backup(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::Restore(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::ShowColumnOptions(cmd) => {
+                let near_config = load_config(home, genesis_validation);
+                cmd.run(&near_config.config.store, near_config.config.cold_store.as_ref())
+            }
         }
     }
 }
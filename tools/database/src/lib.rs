@@ -4,7 +4,10 @@ mod analyse_gas_usage;
 mod analyse_high_load;
 mod analyze_contract_sizes;
 mod analyze_delayed_receipt;
+mod backfill_account_activity;
+mod backup;
 mod block_iterators;
+mod column_options;
 pub mod commands;
 mod compact;
 mod corrupt;
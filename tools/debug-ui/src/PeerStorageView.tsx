@@ -1,8 +1,22 @@
 import { useQuery } from '@tanstack/react-query';
 import { toHumanTime } from './utils';
-import { fetchPeerStore } from './api';
+import { fetchPeerStore, PeerScoreView } from './api';
 import './PeerStorageView.scss';
 
+const PeerScore = ({ score }: { score: PeerScoreView | null }) => {
+    if (!score) {
+        return <td></td>;
+    }
+    const details =
+        `Useful responses: ${score.useful_responses}\n` +
+        `Useless responses: ${score.useless_responses}\n` +
+        `Routing successes: ${score.routing_successes}\n` +
+        `Routing failures: ${score.routing_failures}\n` +
+        `Violations: ${score.violations}\n` +
+        `Average latency: ${score.avg_latency_millis ?? '-'} ms`;
+    return <td title={details}>{score.score}</td>;
+};
+
 type PeerStorageViewProps = {
     addr: string;
 };
@@ -28,6 +42,7 @@ export const PeerStorageView = ({ addr }: PeerStorageViewProps) => {
                 <th>Last seen</th>
                 <th>Last connection attempt</th>
                 <th>Status</th>
+                <th>Score</th>
             </thead>
             <tbody>
                 {peerStore!.status_response.PeerStore.peer_states.map((peer) => {
@@ -56,6 +71,7 @@ export const PeerStorageView = ({ addr }: PeerStorageViewProps) => {
                                     <td>{peer.status}</td>
                                 </>
                             )}
+                            <PeerScore score={peer.score} />
                         </tr>
                     );
                 })}
@@ -251,6 +251,17 @@ export interface KnownPeerStateView {
     first_seen: number;
     last_seen: number;
     last_attempt: [number, string] | null;
+    score: PeerScoreView | null;
+}
+
+export interface PeerScoreView {
+    score: number;
+    useful_responses: number;
+    useless_responses: number;
+    routing_successes: number;
+    routing_failures: number;
+    violations: number;
+    avg_latency_millis: number | null;
 }
 
 export interface SyncStatusResponse {
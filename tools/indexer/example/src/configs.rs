@@ -17,11 +17,18 @@ pub(crate) struct Opts {
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum SubCommand {
     /// Run NEAR Indexer Example. Start observe the network
-    Run,
+    Run(RunArgs),
     /// Initialize necessary configs
     Init(InitConfigArgs),
 }
 
+#[derive(clap::Parser, Debug)]
+pub(crate) struct RunArgs {
+    /// Write the blocks to this directory in the NEAR Lake layout instead of printing them
+    #[clap(long)]
+    pub lake_dir: Option<std::path::PathBuf>,
+}
+
 #[derive(clap::Parser, Debug)]
 pub(crate) struct InitConfigArgs {
     /// chain/network id (localnet, testnet, devnet, betanet)
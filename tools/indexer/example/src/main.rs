@@ -270,18 +270,33 @@ fn main() -> Result<()> {
     let home_dir = opts.home_dir.unwrap_or_else(near_indexer::get_default_home);
 
     match opts.subcmd {
-        SubCommand::Run => {
-            let indexer_config = near_indexer::IndexerConfig {
-                home_dir,
-                sync_mode: near_indexer::SyncModeEnum::FromInterruption,
-                await_for_node_synced: near_indexer::AwaitForNodeSyncedEnum::WaitForFullSync,
-                validate_genesis: true,
-            };
+        SubCommand::Run(args) => {
             let system = actix::System::new();
             system.block_on(async move {
+                let lake_sink = args.lake_dir.map(|root_dir| {
+                    near_indexer::LakeSink::new(&near_indexer::LakeLocation::Filesystem {
+                        root_dir,
+                    })
+                    .expect("LakeSink::new()")
+                });
+                let mut sync_mode = near_indexer::SyncModeEnum::FromInterruption;
+                if let Some(lake_sink) = &lake_sink {
+                    sync_mode =
+                        lake_sink.sync_mode(sync_mode).await.expect("LakeSink::sync_mode()");
+                }
+                let indexer_config = near_indexer::IndexerConfig {
+                    home_dir,
+                    sync_mode,
+                    await_for_node_synced: near_indexer::AwaitForNodeSyncedEnum::WaitForFullSync,
+                    validate_genesis: true,
+                    filter: near_indexer::IndexerFilter::default(),
+                };
                 let indexer = near_indexer::Indexer::new(indexer_config).expect("Indexer::new()");
                 let stream = indexer.streamer();
-                actix::spawn(listen_blocks(stream));
+                match lake_sink {
+                    Some(lake_sink) => actix::spawn(lake_sink.run(stream)),
+                    None => actix::spawn(listen_blocks(stream)),
+                };
             });
             system.run()?;
         }
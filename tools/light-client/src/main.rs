@@ -0,0 +1,226 @@
+//! Light client which follows a node over JSON-RPC, verifying everything the node returns with
+//! `near-light-client`. The light client is stored in a JSON file between the runs.
+use anyhow::Context;
+use near_jsonrpc_client::JsonRpcClient;
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::types::light_client::{
+    RpcLightClientExecutionProofRequest, RpcLightClientNextBlockRequest,
+};
+use near_jsonrpc_primitives::types::query::{QueryResponseKind, RpcQueryRequest};
+use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
+use near_light_client::{ExecutionProof, LightClient};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockId, BlockReference, TransactionOrReceiptId};
+use near_primitives::views::{LightClientBlockView, QueryRequest};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(clap::Parser)]
+#[clap(subcommand_required = true, arg_required_else_help = true)]
+struct Cli {
+    /// Address of the JSON-RPC server of the node to follow.
+    #[clap(long, default_value = "http://localhost:3030")]
+    rpc_url: String,
+    /// File the light client is stored in.
+    #[clap(long, default_value = "light_client.json")]
+    state_file: PathBuf,
+    #[clap(subcommand)]
+    subcmd: CliSubcmd,
+}
+
+#[derive(clap::Subcommand)]
+enum CliSubcmd {
+    /// Creates the light client with the head at the block which the node returns as the next
+    /// light client block after the given block. The node is trusted to return the right block and
+    /// block producers.
+    Init(InitCmd),
+    /// Keeps moving the head of the light client to the blocks returned by the node.
+    Follow(FollowCmd),
+    /// Verifies that the outcome of a transaction is in the chain of the head.
+    VerifyTx(VerifyTxCmd),
+    /// Queries the contract data of an account at the block before the head and verifies it.
+    ViewState(ViewStateCmd),
+}
+
+#[derive(clap::Parser)]
+struct InitCmd {
+    /// Hash of a block the light client starts from.
+    #[clap(long)]
+    block_hash: CryptoHash,
+}
+
+#[derive(clap::Parser)]
+struct FollowCmd {
+    /// How often to ask the node for new blocks once the head caught up with it, in milliseconds.
+    #[clap(long, default_value = "1000")]
+    poll_interval_ms: u64,
+}
+
+#[derive(clap::Parser)]
+struct VerifyTxCmd {
+    #[clap(long)]
+    tx_hash: CryptoHash,
+    #[clap(long)]
+    sender_id: AccountId,
+}
+
+#[derive(clap::Parser)]
+struct ViewStateCmd {
+    #[clap(long)]
+    account_id: AccountId,
+    /// Prefix of the keys to view, as a UTF-8 string.
+    #[clap(long, default_value = "")]
+    prefix: String,
+}
+
+fn rpc_error(err: RpcError) -> anyhow::Error {
+    anyhow::anyhow!("RPC request failed: {}", err)
+}
+
+fn load(state_file: &Path) -> anyhow::Result<LightClient> {
+    let data = std::fs::read(state_file)
+        .with_context(|| format!("Failed to read {}, run `init` first", state_file.display()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+fn save(state_file: &Path, light_client: &LightClient) -> anyhow::Result<()> {
+    let tmp_file = state_file.with_extension("tmp");
+    std::fs::write(&tmp_file, serde_json::to_vec_pretty(light_client)?)?;
+    std::fs::rename(&tmp_file, state_file)?;
+    Ok(())
+}
+
+async fn next_light_client_block(
+    client: &JsonRpcClient,
+    last_block_hash: CryptoHash,
+) -> anyhow::Result<Option<LightClientBlockView>> {
+    let response = client
+        .next_light_client_block(RpcLightClientNextBlockRequest { last_block_hash })
+        .await
+        .map_err(rpc_error)?;
+    Ok(response.light_client_block.map(|block| (*block).clone()))
+}
+
+impl InitCmd {
+    async fn run(&self, client: &JsonRpcClient, state_file: &Path) -> anyhow::Result<()> {
+        let block = next_light_client_block(client, self.block_hash)
+            .await?
+            .with_context(|| format!("Node has no light client block after {}", self.block_hash))?;
+        let height = block.inner_lite.height;
+        let block_producers = client
+            .EXPERIMENTAL_validators_ordered(RpcValidatorsOrderedRequest {
+                block_id: Some(BlockId::Height(height)),
+            })
+            .await
+            .map_err(rpc_error)?;
+        let light_client = LightClient::new(block, block_producers)?;
+        save(state_file, &light_client)?;
+        println!("Light client head is {} at height {}", light_client.head_hash(), height);
+        Ok(())
+    }
+}
+
+impl FollowCmd {
+    async fn run(&self, client: &JsonRpcClient, state_file: &Path) -> anyhow::Result<()> {
+        let mut light_client = load(state_file)?;
+        loop {
+            let Some(block) = next_light_client_block(client, light_client.head_hash()).await?
+            else {
+                tokio::time::sleep(Duration::from_millis(self.poll_interval_ms)).await;
+                continue;
+            };
+            light_client
+                .validate_and_update_head(&block)
+                .context("Node returned an invalid light client block")?;
+            save(state_file, &light_client)?;
+            println!(
+                "Light client head is {} at height {}",
+                light_client.head_hash(),
+                block.inner_lite.height
+            );
+        }
+    }
+}
+
+impl VerifyTxCmd {
+    async fn run(&self, client: &JsonRpcClient, state_file: &Path) -> anyhow::Result<()> {
+        let light_client = load(state_file)?;
+        let response = client
+            .EXPERIMENTAL_light_client_proof(RpcLightClientExecutionProofRequest {
+                id: TransactionOrReceiptId::Transaction {
+                    transaction_hash: self.tx_hash,
+                    sender_id: self.sender_id.clone(),
+                },
+                light_client_head: light_client.head_hash(),
+            })
+            .await
+            .map_err(rpc_error)?;
+        let proof = ExecutionProof {
+            outcome_proof: response.outcome_proof,
+            outcome_root_proof: response.outcome_root_proof,
+            block_header_lite: response.block_header_lite,
+            block_proof: response.block_proof,
+        };
+        light_client.verify_execution_proof(&proof).context("Node returned an invalid proof")?;
+        println!(
+            "Outcome of {} in block {} is verified: {:?}",
+            self.tx_hash, proof.outcome_proof.block_hash, proof.outcome_proof.outcome.status
+        );
+        Ok(())
+    }
+}
+
+impl ViewStateCmd {
+    async fn run(&self, client: &JsonRpcClient, state_file: &Path) -> anyhow::Result<()> {
+        let light_client = load(state_file)?;
+        let head = light_client.head();
+        // The chunks of the head have the state roots of the shards after the previous block.
+        let block =
+            client.block_by_id(BlockId::Hash(light_client.head_hash())).await.map_err(rpc_error)?;
+        let state_roots: Vec<_> = block.chunks.iter().map(|chunk| chunk.prev_state_root).collect();
+        near_light_client::verify_state_roots(head, &state_roots)
+            .context("Node returned invalid chunks of the head")?;
+
+        let response = client
+            .query(RpcQueryRequest {
+                block_reference: BlockReference::BlockId(BlockId::Hash(head.prev_block_hash)),
+                request: QueryRequest::ViewState {
+                    account_id: self.account_id.clone(),
+                    prefix: self.prefix.as_bytes().to_vec().into(),
+                    include_proof: true,
+                },
+            })
+            .await
+            .map_err(rpc_error)?;
+        let QueryResponseKind::ViewState(result) = response.kind else {
+            anyhow::bail!("Node returned an unexpected response to view_state");
+        };
+        // The proof starts from the root of the shard of the account.
+        let state_root = state_roots
+            .iter()
+            .find(|state_root| {
+                result.proof.iter().any(|node| CryptoHash::hash_bytes(node) == **state_root)
+            })
+            .context("Proof doesn't match any state root, the chunk may be missing in the head")?;
+        near_light_client::verify_view_state(state_root, &self.account_id, &result)
+            .context("Node returned an invalid proof")?;
+        for item in &result.values {
+            println!("{}", serde_json::to_string(item)?);
+        }
+        println!("{} values of {} are verified", result.values.len(), self.account_id);
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Cli = clap::Parser::parse();
+    actix::System::new().block_on(async move {
+        let client = near_jsonrpc_client::new_client(&args.rpc_url);
+        match &args.subcmd {
+            CliSubcmd::Init(cmd) => cmd.run(&client, &args.state_file).await,
+            CliSubcmd::Follow(cmd) => cmd.run(&client, &args.state_file).await,
+            CliSubcmd::VerifyTx(cmd) => cmd.run(&client, &args.state_file).await,
+            CliSubcmd::ViewState(cmd) => cmd.run(&client, &args.state_file).await,
+        }
+    })
+}
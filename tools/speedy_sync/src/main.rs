@@ -261,6 +261,7 @@ fn load_snapshot(load_cmd: LoadCmd) {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            save_account_activity: false,
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
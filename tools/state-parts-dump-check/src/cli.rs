@@ -19,7 +19,6 @@ use near_store::Trie;
 use nearcore::state_sync::extract_part_id_from_part_file_name;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::Arc;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
@@ -220,17 +219,13 @@ fn create_external_connection(
     gcs_bucket: Option<String>,
 ) -> ExternalConnection {
     if let Some(root_dir) = root_dir {
-        ExternalConnection::Filesystem { root_dir }
+        ExternalConnection::filesystem(root_dir)
     } else if let (Some(bucket), Some(region)) = (bucket, region) {
         let bucket = create_bucket_readonly(&bucket, &region, Duration::from_secs(5))
             .expect("Failed to create an S3 bucket");
-        ExternalConnection::S3 { bucket: Arc::new(bucket) }
+        ExternalConnection::s3(bucket)
     } else if let Some(bucket) = gcs_bucket {
-        ExternalConnection::GCS {
-            gcs_client: Arc::new(cloud_storage::Client::default()),
-            reqwest_client: Arc::new(reqwest::Client::default()),
-            bucket,
-        }
+        ExternalConnection::gcs(bucket)
     } else {
         panic!(
             "Please provide --root-dir, or both of --s3-bucket and --s3-region, or --gcs-bucket"
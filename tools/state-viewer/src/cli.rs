@@ -356,12 +356,17 @@ pub struct ApplyReceiptCmd {
     hash: String,
     #[clap(long, default_value = "trie")]
     storage: StorageSource,
+    /// Attribute the gas spent inside the Wasm VM to the Wasm functions of the called contracts
+    /// and print it in the folded stacks format, e.g. for `inferno-flamegraph`.
+    #[clap(long)]
+    profile_wasm_gas: bool,
 }
 
 impl ApplyReceiptCmd {
     pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
         let hash = CryptoHash::from_str(&self.hash).unwrap();
-        apply_receipt(home_dir, near_config, store, hash, self.storage).unwrap();
+        apply_receipt(home_dir, near_config, store, hash, self.storage, self.profile_wasm_gas)
+            .unwrap();
     }
 }
 
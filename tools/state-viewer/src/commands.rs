@@ -26,6 +26,7 @@ use near_chain::{
 };
 use near_chain_configs::GenesisChangeConfig;
 use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_parameters::RuntimeConfigStore;
 use near_primitives::account::id::AccountId;
 use near_primitives::apply::ApplyChunkReason;
 use near_primitives::block::Block;
@@ -52,6 +53,7 @@ use near_store::{DBCol, Store, Trie, TrieCache, TrieCachingStorage, TrieConfig,
 use nearcore::NightshadeRuntimeExt;
 use nearcore::{NearConfig, NightshadeRuntime};
 use node_runtime::adapter::ViewRuntimeAdapter;
+use node_runtime::FunctionCallWasmGasProfile;
 use serde_json::json;
 use std::collections::HashMap;
 use std::collections::{BTreeMap, BinaryHeap};
@@ -304,25 +306,38 @@ pub(crate) fn apply_receipt(
     store: Store,
     hash: CryptoHash,
     storage: StorageSource,
+    profile_wasm_gas: bool,
 ) -> anyhow::Result<()> {
     let epoch_manager =
         EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home_dir));
-    let runtime = NightshadeRuntime::from_config(
+    let runtime_config_store = profile_wasm_gas.then(|| {
+        let mut config_store =
+            RuntimeConfigStore::for_chain_id(&near_config.genesis.config.chain_id);
+        config_store.enable_wasm_gas_profiling();
+        config_store
+    });
+    let runtime = NightshadeRuntime::from_config_with_runtime_config_store(
         home_dir,
         store.clone(),
         &near_config,
         epoch_manager.clone(),
+        runtime_config_store,
     )
     .context("could not create the transaction runtime")?;
-    apply_chunk::apply_receipt(
+    let results = apply_chunk::apply_receipt(
         near_config.genesis.config.genesis_height,
         epoch_manager.as_ref(),
         runtime.as_ref(),
         store,
         hash,
         storage,
-    )
-    .map(|_| ())
+    )?;
+    for FunctionCallWasmGasProfile { account_id, method_name, profile } in
+        results.iter().flat_map(|result| &result.wasm_gas_profiles)
+    {
+        println!("Wasm gas profile of {account_id}.{method_name}:\n{}", profile.to_folded());
+    }
+    Ok(())
 }
 
 pub(crate) fn apply_tx(
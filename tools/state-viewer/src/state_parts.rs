@@ -21,7 +21,6 @@ use nearcore::{NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 #[derive(clap::ValueEnum, Clone, Debug, Default)]
@@ -215,7 +214,7 @@ fn create_external_connection(
     mode: Mode,
 ) -> ExternalConnection {
     if let Some(root_dir) = root_dir {
-        ExternalConnection::Filesystem { root_dir }
+        ExternalConnection::filesystem(root_dir)
     } else if let (Some(bucket), Some(region)) = (bucket, region) {
         let bucket = match mode {
             Mode::Readonly => create_bucket_readonly(&bucket, &region, Duration::from_secs(5)),
@@ -224,16 +223,12 @@ fn create_external_connection(
             }
         }
         .expect("Failed to create an S3 bucket");
-        ExternalConnection::S3 { bucket: Arc::new(bucket) }
+        ExternalConnection::s3(bucket)
     } else if let Some(bucket) = gcs_bucket {
         if let Some(credentials_file) = credentials_file {
             std::env::set_var("SERVICE_ACCOUNT", &credentials_file);
         }
-        ExternalConnection::GCS {
-            gcs_client: Arc::new(cloud_storage::Client::default()),
-            reqwest_client: Arc::new(reqwest::Client::default()),
-            bucket,
-        }
+        ExternalConnection::gcs(bucket)
     } else {
         panic!(
             "Please provide --root-dir, or both of --s3-bucket and --s3-region, or --gcs-bucket"